@@ -0,0 +1,69 @@
+//! Export frames from an archived capture session into the fuzz corpus
+//!
+//! Usage: `cargo run --example export_corpus -- <session dir> [corpus dir]`
+//!
+//! Every frame (including quarantined ones) is written as a wire frame to the
+//! `schema` and `framing` corpora, and each flow's full frame stream to `framing`,
+//! so the fuzz targets and property suites start from real MTGO traffic.
+
+use mtgo_replay_lib::protocol::archive;
+use mtgo_replay_lib::protocol::frame::{encode_frame, Frame};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+fn main() -> Result<(), String> {
+    let mut args = std::env::args().skip(1);
+    let session_dir = PathBuf::from(
+        args.next()
+            .ok_or("usage: export_corpus <session dir> [corpus dir]")?,
+    );
+    let corpus_dir = args.next().map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fuzz")
+            .join("corpus")
+    });
+
+    let manifest = archive::read_manifest(&session_dir)?;
+    let frames = archive::read_all_frames(&session_dir)?;
+
+    let mut streams: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+    for frame in &frames {
+        let wire = encode_frame(frame.type_id, &frame.payload);
+        let name = format!("{}-{:06}.bin", manifest.id, frame.index);
+        write(&corpus_dir.join("schema"), &name, &wire)?;
+        write(&corpus_dir.join("framing"), &name, &wire)?;
+        streams
+            .entry(stream_name(&manifest.id, frame))
+            .or_default()
+            .extend(wire);
+    }
+    for (name, stream) in &streams {
+        write(&corpus_dir.join("framing"), name, stream)?;
+    }
+
+    println!(
+        "Exported {} frames and {} streams from session {} to {}",
+        frames.len(),
+        streams.len(),
+        manifest.id,
+        corpus_dir.display()
+    );
+    Ok(())
+}
+
+fn stream_name(session_id: &str, frame: &Frame) -> String {
+    format!(
+        "{}-{}-{}-{}-{}.stream.bin",
+        session_id,
+        frame.flow.src_addr,
+        frame.flow.src_port,
+        frame.flow.dst_addr,
+        frame.flow.dst_port
+    )
+    .replace(':', "_")
+}
+
+fn write(dir: &Path, name: &str, data: &[u8]) -> Result<(), String> {
+    std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    std::fs::write(dir.join(name), data).map_err(|e| format!("Failed to write {}: {}", name, e))
+}
@@ -0,0 +1,43 @@
+//! Frame splitting: input is a reassembled stream of length-prefixed frames
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mtgo_replay_lib::protocol::frame::{encode_frame, FrameReader};
+
+fn read_all(reader: &mut FrameReader, frames: &mut Vec<(u16, Vec<u8>)>) -> bool {
+    loop {
+        match reader.next_frame() {
+            Ok(Some(frame)) => frames.push(frame),
+            Ok(None) => return true,
+            Err(_) => return false,
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let mut whole = FrameReader::new();
+    let mut frames = Vec::new();
+    whole.push(data);
+    let whole_ok = read_all(&mut whole, &mut frames);
+
+    // Delivering the same bytes one at a time must produce the same frames
+    let mut bytewise = FrameReader::new();
+    let mut bytewise_frames = Vec::new();
+    let mut bytewise_ok = true;
+    for byte in data {
+        bytewise.push(std::slice::from_ref(byte));
+        if !read_all(&mut bytewise, &mut bytewise_frames) {
+            bytewise_ok = false;
+            break;
+        }
+    }
+    assert_eq!(frames, bytewise_frames);
+    assert_eq!(whole_ok, bytewise_ok);
+
+    // Re-encoding the frames reproduces the consumed prefix of the stream
+    let encoded: Vec<u8> = frames
+        .iter()
+        .flat_map(|(t, p)| encode_frame(*t, p))
+        .collect();
+    assert!(data.starts_with(&encoded));
+});
@@ -0,0 +1,11 @@
+//! Raw IP packet parsing: input is one packet starting at the IP header
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mtgo_replay_lib::protocol::packet::parse_packet;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(segment) = parse_packet(data) {
+        assert!(segment.payload.len() <= data.len());
+    }
+});
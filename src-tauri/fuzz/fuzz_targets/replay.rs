@@ -0,0 +1,17 @@
+//! Replay container: input is a whole replay file
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mtgo_replay_lib::replay::ReplayReader;
+use std::io::Cursor;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(mut reader) = ReplayReader::new(Cursor::new(data)) else {
+        return;
+    };
+    for entry in reader.index().to_vec() {
+        let _ = reader.read_chunk(&entry);
+    }
+    let _ = reader.events();
+    let _ = reader.seek(u64::MAX);
+});
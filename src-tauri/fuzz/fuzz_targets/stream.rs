@@ -0,0 +1,27 @@
+//! Full capture pipeline: input is a sequence of packets, each prefixed by a u16
+//! little-endian length
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use mtgo_replay_lib::protocol::stream::StreamAssembler;
+
+fuzz_target!(|data: &[u8]| {
+    let mut assembler = StreamAssembler::new();
+    let now = chrono::Utc::now();
+    let mut last_index = None;
+
+    let mut rest = data;
+    while rest.len() >= 2 {
+        let len = (u16::from_le_bytes([rest[0], rest[1]]) as usize).min(rest.len() - 2);
+        if let Ok(frames) = assembler.push_packet(&rest[2..2 + len], now) {
+            for frame in frames {
+                // Frame indices are assigned in order across all flows
+                if let Some(last) = last_index {
+                    assert_eq!(frame.index, last + 1);
+                }
+                last_index = Some(frame.index);
+            }
+        }
+        rest = &rest[2 + len..];
+    }
+});
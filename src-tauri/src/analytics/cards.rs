@@ -0,0 +1,123 @@
+use crate::analytics::WinRate;
+use crate::game::lifecycle::Outcome;
+use crate::library::LibraryCardGame;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Performance of one card across a set of games, in the style of 17lands
+///
+/// A card is "in hand" in a game when it was in the kept opening hand or drawn
+/// later, and "not seen" when it was in the registered maindeck but never drawn
+/// or cast. Games without a registered deck only count towards the seen rates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CardStats {
+    /// Name from the card database; None for cards missing from it
+    pub name: Option<String>,
+    /// Catalog ids of the printings counted together
+    pub card_ids: Vec<u32>,
+    /// Games with the card in the registered maindeck
+    pub games_in_deck: u32,
+    /// Games with the card in the kept opening hand (OH)
+    pub opening_hand: WinRate,
+    /// Games the card was drawn after the opening hand (GD)
+    pub drawn: WinRate,
+    /// Games the card was in hand at some point (GIH)
+    pub in_hand: WinRate,
+    /// Games the card was in the maindeck but never seen (GNS)
+    pub not_seen: WinRate,
+    /// GIH win rate minus GNS win rate (IWD)
+    pub improvement_when_drawn: Option<f64>,
+    /// Times cast or put onto the battlefield
+    pub casts: u32,
+    pub games_cast: u32,
+    /// Average turn of the first cast, over the games it was cast in
+    pub avg_first_cast_turn: Option<f64>,
+}
+
+/// One card (all its printings) in one game
+#[derive(Default)]
+struct CardInGame {
+    in_deck: bool,
+    opening_hand: bool,
+    drawn: bool,
+    casts: u32,
+    first_cast_turn: Option<u32>,
+    result: Option<Outcome>,
+}
+
+/// Printings of one card and the games it took part in, by (record id, game number)
+#[derive(Default)]
+struct CardGroup<'a> {
+    ids: BTreeSet<u32>,
+    games: BTreeMap<(&'a str, u8), CardInGame>,
+}
+
+/// Per-card statistics over the user's cards in a set of games
+///
+/// # Arguments
+/// * `cards` - Cards by game, from the library
+/// * `names` - Card names by catalog id; printings sharing a name are counted as one card
+///
+/// # Returns
+/// One entry per card, the most often in hand first
+pub fn card_stats(cards: &[LibraryCardGame], names: &HashMap<u32, String>) -> Vec<CardStats> {
+    let mut grouped: BTreeMap<(Option<&String>, u32), CardGroup> = BTreeMap::new();
+    for card in cards {
+        let key = match names.get(&card.card_id) {
+            Some(name) => (Some(name), 0),
+            None => (None, card.card_id),
+        };
+        let group = grouped.entry(key).or_default();
+        group.ids.insert(card.card_id);
+        let game = group
+            .games
+            .entry((card.record_id.as_str(), card.game_number))
+            .or_default();
+        game.in_deck |= card.in_deck > 0;
+        game.opening_hand |= card.opening_hand;
+        game.drawn |= card.drawn;
+        game.casts += card.casts;
+        game.first_cast_turn = match (game.first_cast_turn, card.first_cast_turn) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        game.result = card.result;
+    }
+
+    let mut stats: Vec<CardStats> = grouped
+        .into_iter()
+        .map(|((name, _), CardGroup { ids, games })| {
+            let results = |include: fn(&CardInGame) -> bool| {
+                WinRate::from_outcomes(games.values().filter(|g| include(g)).map(|g| g.result))
+            };
+            let in_hand = results(|g| g.opening_hand || g.drawn);
+            let not_seen = results(|g| g.in_deck && !g.opening_hand && !g.drawn && g.casts == 0);
+            let first_turns: Vec<u32> = games.values().filter_map(|g| g.first_cast_turn).collect();
+            CardStats {
+                name: name.cloned(),
+                card_ids: ids.into_iter().collect(),
+                games_in_deck: games.values().filter(|g| g.in_deck).count() as u32,
+                opening_hand: results(|g| g.opening_hand),
+                drawn: results(|g| g.drawn && !g.opening_hand),
+                improvement_when_drawn: in_hand
+                    .win_rate
+                    .zip(not_seen.win_rate)
+                    .map(|(gih, gns)| gih - gns),
+                in_hand,
+                not_seen,
+                casts: games.values().map(|g| g.casts).sum(),
+                games_cast: games.values().filter(|g| g.casts > 0).count() as u32,
+                avg_first_cast_turn: (!first_turns.is_empty())
+                    .then(|| f64::from(first_turns.iter().sum::<u32>()) / first_turns.len() as f64),
+            }
+        })
+        .collect();
+    stats.sort_by(|a, b| {
+        b.in_hand
+            .total()
+            .cmp(&a.in_hand.total())
+            .then_with(|| a.name.cmp(&b.name))
+            .then_with(|| a.card_ids.cmp(&b.card_ids))
+    });
+    stats
+}
@@ -0,0 +1,87 @@
+//! Statistics over the replay library (STAT-002)
+//!
+//! Analytics read the games the library selects for a `LibraryQuery` and compute
+//! in memory, so every statistic can be scoped with the same filters as a library
+//! search. Results are shaped for charts: labelled rows and dated series.
+
+pub mod cards;
+pub mod mulligan;
+pub mod winrate;
+
+use crate::game::lifecycle::Outcome;
+use serde::{Deserialize, Serialize};
+
+/// z-score of the two-sided 95% confidence intervals reported with win rates
+pub const CONFIDENCE_Z: f64 = 1.96;
+
+/// Wins, losses and draws with the win rate and its 95% Wilson score interval
+///
+/// Draws are reported but left out of the win rate, which is wins over decided
+/// games (or matches). Rate and interval are None without a decided sample.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WinRate {
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+    pub win_rate: Option<f64>,
+    pub ci_low: Option<f64>,
+    pub ci_high: Option<f64>,
+}
+
+impl WinRate {
+    /// Win rate of a tally of outcomes
+    pub fn from_counts(wins: u32, losses: u32, draws: u32) -> Self {
+        let decided = wins + losses;
+        let interval = wilson_interval(wins, decided, CONFIDENCE_Z);
+        Self {
+            wins,
+            losses,
+            draws,
+            win_rate: (decided > 0).then(|| f64::from(wins) / f64::from(decided)),
+            ci_low: interval.map(|(low, _)| low),
+            ci_high: interval.map(|(_, high)| high),
+        }
+    }
+
+    /// Win rate of a set of results; undecided results (None) are not counted
+    pub fn from_outcomes(outcomes: impl IntoIterator<Item = Option<Outcome>>) -> Self {
+        let (mut wins, mut losses, mut draws) = (0, 0, 0);
+        for outcome in outcomes.into_iter().flatten() {
+            match outcome {
+                Outcome::Win => wins += 1,
+                Outcome::Loss => losses += 1,
+                Outcome::Draw => draws += 1,
+            }
+        }
+        Self::from_counts(wins, losses, draws)
+    }
+
+    /// Games (or matches) counted, draws included
+    pub fn total(&self) -> u32 {
+        self.wins + self.losses + self.draws
+    }
+}
+
+/// Wilson score interval of a binomial proportion
+///
+/// Unlike the normal approximation it stays within [0, 1] and is meaningful for
+/// the small samples typical of a single deck or matchup.
+///
+/// # Arguments
+/// * `successes` - Wins
+/// * `trials` - Decided games or matches
+/// * `z` - z-score of the confidence level (`CONFIDENCE_Z` for 95%)
+///
+/// # Returns
+/// (low, high), or None without trials
+pub fn wilson_interval(successes: u32, trials: u32, z: f64) -> Option<(f64, f64)> {
+    if trials == 0 {
+        return None;
+    }
+    let n = f64::from(trials);
+    let p = f64::from(successes) / n;
+    let z2 = z * z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let margin = z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    Some(((center - margin).max(0.0), (center + margin).min(1.0)))
+}
@@ -0,0 +1,185 @@
+use crate::analytics::winrate::WinRateRow;
+use crate::analytics::WinRate;
+use crate::library::LibraryHand;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Cards in an opening hand before any mulligan
+pub const HAND_SIZE: u8 = 7;
+
+/// A card to report keep rates for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct KeyCard {
+    /// Label of the card in the report
+    pub name: String,
+    /// Catalog ids of its printings
+    pub ids: Vec<u32>,
+}
+
+/// An opening hand with its composition
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnalyzedHand {
+    #[serde(flatten)]
+    pub hand: LibraryHand,
+    /// Lands among the cards drawn
+    pub lands: u8,
+}
+
+impl AnalyzedHand {
+    /// Whether the hand holds any printing of a card
+    pub fn holds(&self, ids: &[u32]) -> bool {
+        self.hand.cards.iter().any(|card| ids.contains(card))
+    }
+}
+
+/// Opening hand search filters; every present filter must match
+///
+/// "Hands with 1 land kept on the draw" is `{ lands: 1, kept: true, on_play: false }`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HandFilter {
+    pub kept: Option<bool>,
+    pub on_play: Option<bool>,
+    /// Exact number of lands drawn
+    pub lands: Option<u8>,
+    /// Mulligans taken before the hand was drawn
+    pub mulligans: Option<u8>,
+    /// Catalog ids of a card's printings: hands holding any of them
+    #[serde(default)]
+    pub with_cards: Vec<u32>,
+}
+
+impl HandFilter {
+    pub fn matches(&self, hand: &AnalyzedHand) -> bool {
+        (self.kept.is_none() || self.kept == Some(hand.hand.kept))
+            && (self.on_play.is_none() || self.on_play == hand.hand.on_play)
+            && (self.lands.is_none() || self.lands == Some(hand.lands))
+            && (self.mulligans.is_none() || self.mulligans == Some(hand.hand.mulligans))
+            && (self.with_cards.is_empty() || hand.holds(&self.with_cards))
+    }
+}
+
+/// How often a group of hands was kept, and how the games went when it was
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeepRate {
+    pub label: String,
+    pub hands: u32,
+    pub kept: u32,
+    /// Kept over drawn; None without hands
+    pub keep_rate: Option<f64>,
+    /// Results of the games the hand was kept in
+    pub kept_win_rate: WinRate,
+}
+
+impl KeepRate {
+    fn of<'a>(label: String, hands: impl IntoIterator<Item = &'a AnalyzedHand>) -> Self {
+        let (mut drawn, mut kept_results) = (0, Vec::new());
+        for hand in hands {
+            drawn += 1;
+            if hand.hand.kept {
+                kept_results.push(hand.hand.result);
+            }
+        }
+        let kept = kept_results.len() as u32;
+        Self {
+            label,
+            hands: drawn,
+            kept,
+            keep_rate: (drawn > 0).then(|| f64::from(kept) / f64::from(drawn)),
+            kept_win_rate: WinRate::from_outcomes(kept_results),
+        }
+    }
+}
+
+/// Keep rates by hand composition and win rates by mulligans taken
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MulliganReport {
+    /// Every hand counted
+    pub overall: KeepRate,
+    /// By lands drawn, fewest first
+    pub by_lands: Vec<KeepRate>,
+    /// Hands holding each key card, in the order asked for
+    pub by_key_card: Vec<KeepRate>,
+    /// Game win rate after keeping 7 and after mulliganing to each smaller size
+    pub by_mulligans: Vec<WinRateRow>,
+}
+
+/// Count the lands of each hand
+///
+/// # Arguments
+/// * `hands` - Hands from the library
+/// * `lands` - Catalog ids of lands (cards missing from the card database count as spells)
+pub fn analyze(hands: Vec<LibraryHand>, lands: &HashSet<u32>) -> Vec<AnalyzedHand> {
+    hands
+        .into_iter()
+        .map(|hand| {
+            let count = hand
+                .cards
+                .iter()
+                .filter(|card| lands.contains(card))
+                .count();
+            AnalyzedHand {
+                lands: u8::try_from(count).unwrap_or(u8::MAX),
+                hand,
+            }
+        })
+        .collect()
+}
+
+/// Label of the hand size kept after some mulligans ("Keep 7", "Mull to 6")
+pub fn mulligan_label(mulligans: u8) -> String {
+    if mulligans == 0 {
+        format!("Keep {}", HAND_SIZE)
+    } else {
+        format!("Mull to {}", HAND_SIZE.saturating_sub(mulligans))
+    }
+}
+
+/// Mulligan report over a set of hands
+///
+/// # Arguments
+/// * `hands` - Hands to count (already narrowed by a `HandFilter` if wanted)
+/// * `key_cards` - Cards to report keep rates for
+pub fn report(hands: &[AnalyzedHand], key_cards: &[KeyCard]) -> MulliganReport {
+    let mut by_lands: BTreeMap<u8, Vec<&AnalyzedHand>> = BTreeMap::new();
+    let mut by_mulligans: BTreeMap<u8, Vec<_>> = BTreeMap::new();
+    for hand in hands {
+        by_lands.entry(hand.lands).or_default().push(hand);
+        if hand.hand.kept {
+            by_mulligans
+                .entry(hand.hand.mulligans)
+                .or_default()
+                .push(hand.hand.result);
+        }
+    }
+
+    MulliganReport {
+        overall: KeepRate::of("All".to_string(), hands),
+        by_lands: by_lands
+            .into_iter()
+            .map(|(lands, group)| {
+                let label = if lands == 1 {
+                    "1 land".to_string()
+                } else {
+                    format!("{} lands", lands)
+                };
+                KeepRate::of(label, group)
+            })
+            .collect(),
+        by_key_card: key_cards
+            .iter()
+            .map(|card| {
+                KeepRate::of(
+                    card.name.clone(),
+                    hands.iter().filter(|hand| hand.holds(&card.ids)),
+                )
+            })
+            .collect(),
+        by_mulligans: by_mulligans
+            .into_iter()
+            .map(|(mulligans, results)| WinRateRow {
+                label: mulligan_label(mulligans),
+                rate: WinRate::from_outcomes(results),
+            })
+            .collect(),
+    }
+}
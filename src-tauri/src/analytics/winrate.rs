@@ -0,0 +1,328 @@
+use crate::analytics::WinRate;
+use crate::game::lifecycle::Outcome;
+use crate::library::LibraryGame;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+/// Label of games whose match has no tagged deck
+pub const UNTAGGED: &str = "Untagged";
+
+/// Label of games whose archetype (or play/draw) is not known
+pub const UNKNOWN: &str = "Unknown";
+
+/// What win rates are grouped by
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Dimension {
+    /// Name of the deck list the match was tagged with
+    Deck,
+    /// Archetype of the user's deck
+    Archetype,
+    OpponentArchetype,
+    Opponent,
+    Format,
+    /// On the play or on the draw (per game)
+    PlayDraw,
+    /// Game 1, 2 or 3 of the match (per game)
+    GameNumber,
+    /// Game 1 against games after sideboarding (per game)
+    Sideboarding,
+}
+
+impl Dimension {
+    /// Whether the dimension describes a game rather than a match, so it is
+    /// always counted in games
+    pub fn is_per_game(self) -> bool {
+        matches!(
+            self,
+            Dimension::PlayDraw | Dimension::GameNumber | Dimension::Sideboarding
+        )
+    }
+
+    /// Label of the group a game belongs to
+    pub fn label(self, game: &LibraryGame) -> String {
+        let or = |value: &Option<String>, default: &str| {
+            value.clone().unwrap_or_else(|| default.to_string())
+        };
+        match self {
+            Dimension::Deck => or(&game.deck_name, UNTAGGED),
+            Dimension::Archetype => or(&game.archetype, UNKNOWN),
+            Dimension::OpponentArchetype => or(&game.opponent_archetype, UNKNOWN),
+            Dimension::Opponent => or(&game.opponent, UNKNOWN),
+            Dimension::Format => game.format.clone(),
+            Dimension::PlayDraw => match game.on_play {
+                Some(true) => "Play".to_string(),
+                Some(false) => "Draw".to_string(),
+                None => UNKNOWN.to_string(),
+            },
+            Dimension::GameNumber => format!("Game {}", game.game_number),
+            Dimension::Sideboarding => {
+                if game.game_number <= 1 {
+                    "Pre-board".to_string()
+                } else {
+                    "Post-board".to_string()
+                }
+            }
+        }
+    }
+}
+
+/// Whether win rates count games or matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatUnit {
+    Games,
+    /// Decided matches; per-game dimensions are still counted in games
+    Matches,
+}
+
+/// Width of the time buckets of a trend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeBucket {
+    Day,
+    /// Weeks starting on Monday
+    Week,
+    Month,
+}
+
+impl TimeBucket {
+    /// First day (UTC) of the bucket a time falls in
+    pub fn start(self, time: &DateTime<Utc>) -> NaiveDate {
+        let date = time.date_naive();
+        match self {
+            TimeBucket::Day => date,
+            TimeBucket::Week => {
+                date - Duration::days(i64::from(date.weekday().num_days_from_monday()))
+            }
+            TimeBucket::Month => date.with_day(1).unwrap_or(date),
+        }
+    }
+}
+
+/// One bar of a win rate chart
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WinRateRow {
+    pub label: String,
+    #[serde(flatten)]
+    pub rate: WinRate,
+}
+
+/// Win rates grouped by one dimension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WinRateBreakdown {
+    pub dimension: Dimension,
+    /// Unit actually counted (games for per-game dimensions)
+    pub unit: StatUnit,
+    /// Every selected game or match
+    pub overall: WinRate,
+    /// Largest samples first; per-game dimensions in label order
+    pub rows: Vec<WinRateRow>,
+}
+
+/// One point of a win rate trend
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WinRatePoint {
+    /// First day of the bucket
+    pub bucket: NaiveDate,
+    #[serde(flatten)]
+    pub rate: WinRate,
+}
+
+/// Win rate over time for one group; buckets without games are left out
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WinRateSeries {
+    pub label: String,
+    pub points: Vec<WinRatePoint>,
+}
+
+/// Win rates of each of the user's decks (rows) against each opponent archetype (columns)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchupMatrix {
+    pub rows: Dimension,
+    pub unit: StatUnit,
+    pub decks: Vec<String>,
+    pub opponents: Vec<String>,
+    /// `cells[deck][opponent]`; an empty tally where the matchup was never played
+    pub cells: Vec<Vec<WinRate>>,
+}
+
+/// A game or match to count: its games' attributes and its result
+struct Sample<'a> {
+    game: &'a LibraryGame,
+    result: Option<Outcome>,
+    at: &'a DateTime<Utc>,
+}
+
+/// Games, or one sample per match (its first game, carrying the match result)
+fn samples(games: &[LibraryGame], unit: StatUnit) -> Vec<Sample<'_>> {
+    match unit {
+        StatUnit::Games => games
+            .iter()
+            .map(|game| Sample {
+                game,
+                result: game.result,
+                at: &game.started_at,
+            })
+            .collect(),
+        StatUnit::Matches => {
+            let mut seen = HashSet::new();
+            games
+                .iter()
+                .filter(|game| seen.insert(game.record_id.as_str()))
+                .map(|game| Sample {
+                    game,
+                    result: game.match_result,
+                    at: &game.match_started_at,
+                })
+                .collect()
+        }
+    }
+}
+
+fn unit_for(dimension: Option<Dimension>, unit: StatUnit) -> StatUnit {
+    if dimension.is_some_and(Dimension::is_per_game) {
+        StatUnit::Games
+    } else {
+        unit
+    }
+}
+
+/// Win rates grouped by one dimension
+///
+/// # Arguments
+/// * `games` - Games selected from the library
+/// * `dimension` - What to group by
+/// * `unit` - Count games or matches
+pub fn breakdown(games: &[LibraryGame], dimension: Dimension, unit: StatUnit) -> WinRateBreakdown {
+    let unit = unit_for(Some(dimension), unit);
+    let samples = samples(games, unit);
+    let mut groups: BTreeMap<String, Vec<Option<Outcome>>> = BTreeMap::new();
+    for sample in &samples {
+        groups
+            .entry(dimension.label(sample.game))
+            .or_default()
+            .push(sample.result);
+    }
+
+    let mut rows: Vec<WinRateRow> = groups
+        .into_iter()
+        .map(|(label, results)| WinRateRow {
+            label,
+            rate: WinRate::from_outcomes(results),
+        })
+        .collect();
+    if !dimension.is_per_game() {
+        rows.sort_by(|a, b| {
+            b.rate
+                .total()
+                .cmp(&a.rate.total())
+                .then_with(|| a.label.cmp(&b.label))
+        });
+    }
+    WinRateBreakdown {
+        dimension,
+        unit,
+        overall: WinRate::from_outcomes(samples.iter().map(|s| s.result)),
+        rows,
+    }
+}
+
+/// Win rate per time bucket, overall or for each group of a dimension
+///
+/// # Arguments
+/// * `games` - Games selected from the library
+/// * `bucket` - Day, week or month (UTC)
+/// * `unit` - Count games or matches
+/// * `dimension` - One series per group; None for a single "All" series
+pub fn trend(
+    games: &[LibraryGame],
+    bucket: TimeBucket,
+    unit: StatUnit,
+    dimension: Option<Dimension>,
+) -> Vec<WinRateSeries> {
+    let unit = unit_for(dimension, unit);
+    let mut groups: BTreeMap<String, BTreeMap<NaiveDate, Vec<Option<Outcome>>>> = BTreeMap::new();
+    for sample in samples(games, unit) {
+        let label = dimension
+            .map(|d| d.label(sample.game))
+            .unwrap_or_else(|| "All".to_string());
+        groups
+            .entry(label)
+            .or_default()
+            .entry(bucket.start(sample.at))
+            .or_default()
+            .push(sample.result);
+    }
+
+    groups
+        .into_iter()
+        .map(|(label, buckets)| WinRateSeries {
+            label,
+            points: buckets
+                .into_iter()
+                .map(|(bucket, results)| WinRatePoint {
+                    bucket,
+                    rate: WinRate::from_outcomes(results),
+                })
+                .collect(),
+        })
+        .collect()
+}
+
+/// Matchup matrix of the user's decks against opponent archetypes
+///
+/// Rows and columns are ordered by sample size, largest first.
+///
+/// # Arguments
+/// * `games` - Games selected from the library
+/// * `rows` - Group the user's side by `Deck` or `Archetype`
+/// * `unit` - Count games or matches
+pub fn matchup_matrix(games: &[LibraryGame], rows: Dimension, unit: StatUnit) -> MatchupMatrix {
+    let unit = unit_for(Some(rows), unit);
+    let samples = samples(games, unit);
+    let mut cells: BTreeMap<(String, String), Vec<Option<Outcome>>> = BTreeMap::new();
+    for sample in &samples {
+        let deck = rows.label(sample.game);
+        let opponent = Dimension::OpponentArchetype.label(sample.game);
+        cells
+            .entry((deck, opponent))
+            .or_default()
+            .push(sample.result);
+    }
+
+    let ordered = |key: fn(&(String, String)) -> &String| -> Vec<String> {
+        let mut totals: BTreeMap<&String, usize> = BTreeMap::new();
+        for (cell, results) in &cells {
+            *totals.entry(key(cell)).or_default() += results.len();
+        }
+        let mut labels: Vec<(&String, usize)> = totals.into_iter().collect();
+        labels.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        labels.into_iter().map(|(label, _)| label.clone()).collect()
+    };
+    let decks = ordered(|(deck, _)| deck);
+    let opponents = ordered(|(_, opponent)| opponent);
+
+    let cells = decks
+        .iter()
+        .map(|deck| {
+            opponents
+                .iter()
+                .map(|opponent| {
+                    cells
+                        .get(&(deck.clone(), opponent.clone()))
+                        .map(|results| WinRate::from_outcomes(results.iter().copied()))
+                        .unwrap_or_default()
+                })
+                .collect()
+        })
+        .collect();
+    MatchupMatrix {
+        rows,
+        unit,
+        decks,
+        opponents,
+        cells,
+    }
+}
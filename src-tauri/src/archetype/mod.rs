@@ -0,0 +1,335 @@
+//! Rules-based deck archetype classification
+//!
+//! Archetypes are defined by the user as signature cards with weights, stored
+//! as a JSON file. A deck list, complete or only the opponent cards seen, scores
+//! the sum of the weights of the signature cards it contains; the best-scoring
+//! archetype wins if it clears its minimum score and is far enough ahead of the
+//! runner-up, otherwise the deck is unknown.
+//!
+//! The library stores the archetypes of every match with the version of the
+//! definitions (and card data) they were assigned with, so `reclassify` only
+//! revisits matches indexed or classified since the definitions last changed.
+
+use crate::cards::CardDatabase;
+use crate::common::error::ArchetypeError;
+use crate::common::hex;
+use crate::library::{ArchetypeAssignment, ReplayLibrary};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tracing::info;
+
+/// Default share of the evidence the best archetype needs (see `Classification::confidence`)
+pub const DEFAULT_MIN_CONFIDENCE: f64 = 0.6;
+
+/// Default score a deck needs before an archetype is considered at all
+pub const DEFAULT_MIN_SCORE: f64 = 1.0;
+
+fn default_min_confidence() -> f64 {
+    DEFAULT_MIN_CONFIDENCE
+}
+
+fn default_min_score() -> f64 {
+    DEFAULT_MIN_SCORE
+}
+
+/// A card that points towards (or, with a negative weight, away from) an archetype
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SignatureCard {
+    /// Card name; a split or double-faced card also matches by its front face
+    pub name: String,
+    pub weight: f64,
+}
+
+/// One archetype and the cards that identify it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchetypeDefinition {
+    pub name: String,
+    /// Formats the archetype exists in (case-insensitive); empty for every format
+    #[serde(default)]
+    pub formats: Vec<String>,
+    pub cards: Vec<SignatureCard>,
+    /// Score a deck needs before this archetype is considered
+    #[serde(default = "default_min_score")]
+    pub min_score: f64,
+}
+
+impl ArchetypeDefinition {
+    fn applies_to(&self, format: &str) -> bool {
+        self.formats.is_empty() || self.formats.iter().any(|f| f.eq_ignore_ascii_case(format))
+    }
+}
+
+/// User-editable archetype definitions, stored as a JSON file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchetypeDefinitions {
+    /// Share of the evidence the best archetype needs to be assigned
+    #[serde(default = "default_min_confidence")]
+    pub min_confidence: f64,
+    #[serde(default)]
+    pub archetypes: Vec<ArchetypeDefinition>,
+}
+
+impl Default for ArchetypeDefinitions {
+    fn default() -> Self {
+        Self {
+            min_confidence: DEFAULT_MIN_CONFIDENCE,
+            archetypes: Vec::new(),
+        }
+    }
+}
+
+/// Score of a deck for one archetype
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchetypeScore {
+    pub name: String,
+    pub score: f64,
+}
+
+/// Archetype assigned to a deck list
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Classification {
+    /// None when the deck is unknown: no archetype cleared its minimum score,
+    /// or the best one was not far enough ahead of the runner-up
+    pub archetype: Option<String>,
+    /// Best score over best plus runner-up score: 1 when no other archetype
+    /// scored, 0.5 for a tie, 0 when nothing qualified
+    pub confidence: f64,
+    /// Archetypes with a positive score, best first
+    pub scores: Vec<ArchetypeScore>,
+}
+
+/// Lowercase name and lowercase front face, for matching card names
+fn name_keys(name: &str) -> [String; 2] {
+    let full = name.trim().to_lowercase();
+    let front = full
+        .split("//")
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string();
+    [full, front]
+}
+
+impl ArchetypeDefinitions {
+    /// Read the definitions; a missing file defines no archetypes
+    pub fn load(path: &Path) -> Result<Self, ArchetypeError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)?;
+        let definitions: Self = serde_json::from_str(&json).map_err(|e| {
+            ArchetypeError::InvalidDefinitions(format!("{}: {}", path.display(), e))
+        })?;
+        definitions.validate()?;
+        Ok(definitions)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ArchetypeError> {
+        self.validate()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| ArchetypeError::InvalidDefinitions(e.to_string()))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Check names are present and unique and numbers are usable
+    pub fn validate(&self) -> Result<(), ArchetypeError> {
+        let invalid = |reason: String| Err(ArchetypeError::InvalidDefinitions(reason));
+        if !(0.0..=1.0).contains(&self.min_confidence) {
+            return invalid(format!(
+                "min_confidence {} is not between 0 and 1",
+                self.min_confidence
+            ));
+        }
+        let mut names = HashSet::new();
+        for archetype in &self.archetypes {
+            let name = archetype.name.trim();
+            if name.is_empty() {
+                return invalid("archetype without a name".to_string());
+            }
+            if !names.insert(name.to_lowercase()) {
+                return invalid(format!("archetype '{}' is defined twice", name));
+            }
+            if !(archetype.min_score.is_finite() && archetype.min_score > 0.0) {
+                return invalid(format!("archetype '{}' needs a positive min_score", name));
+            }
+            for card in &archetype.cards {
+                if card.name.trim().is_empty() || !card.weight.is_finite() {
+                    return invalid(format!(
+                        "archetype '{}' has a card without a name or weight",
+                        name
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Short hash identifying these definitions
+    pub fn version(&self) -> String {
+        let json = serde_json::to_vec(self).unwrap_or_default();
+        hex::encode(&Sha256::digest(json)[..8])
+    }
+
+    /// Classify a deck list
+    ///
+    /// # Arguments
+    /// * `format` - Format the deck was played in
+    /// * `cards` - Names of the cards in the deck (or seen of it); copies do not matter
+    pub fn classify<'a>(
+        &self,
+        format: &str,
+        cards: impl IntoIterator<Item = &'a str>,
+    ) -> Classification {
+        let held: HashSet<String> = cards.into_iter().flat_map(name_keys).collect();
+        let mut scores: Vec<(&ArchetypeDefinition, f64)> = self
+            .archetypes
+            .iter()
+            .filter(|archetype| archetype.applies_to(format))
+            .map(|archetype| {
+                let score = archetype
+                    .cards
+                    .iter()
+                    .filter(|card| name_keys(&card.name).iter().any(|key| held.contains(key)))
+                    .map(|card| card.weight)
+                    .sum();
+                (archetype, score)
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.name.cmp(&b.0.name)));
+
+        let (archetype, confidence) = match scores.first() {
+            Some((best, score)) if *score >= best.min_score => {
+                let runner_up = scores.get(1).map(|(_, score)| *score).unwrap_or(0.0);
+                let confidence = score / (score + runner_up);
+                let assigned = confidence >= self.min_confidence;
+                (assigned.then(|| best.name.clone()), confidence)
+            }
+            _ => (None, 0.0),
+        };
+        Classification {
+            archetype,
+            confidence,
+            scores: scores
+                .into_iter()
+                .map(|(archetype, score)| ArchetypeScore {
+                    name: archetype.name.clone(),
+                    score,
+                })
+                .collect(),
+        }
+    }
+
+    /// Classify a deck list given by catalog ids, naming the cards through the card database
+    pub fn classify_ids(
+        &self,
+        format: &str,
+        card_ids: &[u32],
+        cards: &CardDatabase,
+    ) -> Result<Classification, ArchetypeError> {
+        let names = cards.get_many(card_ids)?;
+        Ok(self.classify(format, names.values().map(|card| card.name.as_str())))
+    }
+}
+
+/// Outcome of classifying the library
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReclassifyReport {
+    /// Matches classified in this pass
+    pub classified: usize,
+    /// Of those, matches whose user deck got an archetype
+    pub decks_known: usize,
+    /// Of those, matches whose opponent deck got an archetype
+    pub opponents_known: usize,
+}
+
+/// Classify the user's and the opponent's deck of library matches
+///
+/// The classification version combines the definitions with the card data
+/// import times, so importing card data also triggers a new pass.
+///
+/// # Arguments
+/// * `library` - Library to update
+/// * `cards` - Card database naming the catalog ids of the decks
+/// * `definitions` - Archetype definitions
+/// * `all` - Reclassify every match, not only those classified with other definitions
+pub fn reclassify(
+    library: &mut ReplayLibrary,
+    cards: &CardDatabase,
+    definitions: &ArchetypeDefinitions,
+    all: bool,
+) -> Result<ReclassifyReport, ArchetypeError> {
+    let status = cards.status()?;
+    let version = format!(
+        "{}-{}-{}",
+        definitions.version(),
+        status
+            .scryfall_imported_at
+            .map(|t| t.timestamp())
+            .unwrap_or(0),
+        status
+            .catalog_imported_at
+            .map(|t| t.timestamp())
+            .unwrap_or(0)
+    );
+    let decks = library.decks_to_classify((!all).then_some(version.as_str()))?;
+    if decks.is_empty() {
+        return Ok(ReclassifyReport::default());
+    }
+
+    let ids: Vec<u32> = decks
+        .iter()
+        .flat_map(|deck| deck.deck.iter().chain(&deck.opponent_cards).copied())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let names: HashMap<u32, String> = cards
+        .get_many(&ids)?
+        .into_iter()
+        .map(|(id, card)| (id, card.name))
+        .collect();
+    let classify = |format: &str, ids: &[u32]| {
+        (!ids.is_empty()).then(|| {
+            definitions.classify(
+                format,
+                ids.iter()
+                    .filter_map(|id| names.get(id).map(String::as_str)),
+            )
+        })
+    };
+
+    let mut report = ReclassifyReport::default();
+    let assignments: Vec<ArchetypeAssignment> = decks
+        .into_iter()
+        .map(|deck| {
+            let ours = classify(&deck.format, &deck.deck);
+            let theirs = classify(&deck.format, &deck.opponent_cards);
+            let ours_known = ours.as_ref().and_then(|c| c.archetype.clone());
+            let theirs_known = theirs.as_ref().and_then(|c| c.archetype.clone());
+            report.classified += 1;
+            report.decks_known += usize::from(ours_known.is_some());
+            report.opponents_known += usize::from(theirs_known.is_some());
+            ArchetypeAssignment {
+                path: deck.path,
+                archetype: ours_known,
+                archetype_confidence: ours.map(|c| c.confidence),
+                opponent_archetype: theirs_known,
+                opponent_archetype_confidence: theirs.map(|c| c.confidence),
+            }
+        })
+        .collect();
+    library.set_archetypes(&version, &assignments)?;
+    info!(
+        "Classified {} matches ({} decks and {} opponents recognised)",
+        report.classified, report.decks_known, report.opponents_known
+    );
+    Ok(report)
+}
@@ -0,0 +1,397 @@
+//! Command-line tools for replay files
+//!
+//! Usage:
+//! `mtgo-replay-tool migrate [--out <dir>] [--force] [--no-backup]
+//!  [--redecode --sessions <dir> [--schemas <dir>]] <replay or directory>...`
+//!
+//! `migrate` rewrites replays written by older format versions with the current
+//! one, in place (keeping a `.bak` copy) or into `--out`. With `--redecode` the
+//! events are rebuilt with the current schemas from the frames embedded in the
+//! replay, or for replays recorded without them, from the archived capture
+//! session in `--sessions`.
+//!
+//! `mtgo-replay-tool anonymize [--key <file>] [--schemas <dir>] [--out <file>] <replay>`
+//!
+//! `anonymize` writes a copy safe to share (`<name>.anon.mtgoreplay` by default).
+//! Pseudonyms come from the key file, created if missing; pass the application's
+//! `anonymize.key` to get the same pseudonyms as replays shared from the app.
+//!
+//! `mtgo-replay-tool sign --key <file> <replay>...`
+//! `mtgo-replay-tool verify [--keyring <file>] <replay>...`
+//!
+//! `sign` signs replays with the Ed25519 key pair in `--key`, generating it if
+//! missing. `verify` reports each replay's signature status and signer, naming
+//! signers found in `--keyring` (the app's `trusted_keys.json`), and lists chunks
+//! changed since signing; it fails unless every replay is signed and unmodified.
+//!
+//! `mtgo-replay-tool export [--format json|csv] [--out <path>] <replay>`
+//! `mtgo-replay-tool export --schema`
+//!
+//! `export` writes a replay as one JSON document (`<name>.json` by default) or as
+//! CSV tables in a directory (`<name>_csv`). `--schema` prints the JSON Schema of
+//! the JSON export.
+
+use mtgo_replay_lib::export::{self, ExportFormat};
+use mtgo_replay_lib::protocol::version::SchemaRegistry;
+use mtgo_replay_lib::replay::anonymize::{self, Pseudonymizer};
+use mtgo_replay_lib::replay::format::REPLAY_EXTENSION;
+use mtgo_replay_lib::replay::migrate::{self, MigrateOptions, MigrationStatus, Redecode};
+use mtgo_replay_lib::replay::signing::{self, Keyring, SignatureStatus, SigningIdentity};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use tracing_subscriber::EnvFilter;
+
+const USAGE: &str = "usage: mtgo-replay-tool migrate [--out <dir>] [--force] [--no-backup] \
+[--redecode --sessions <dir> [--schemas <dir>]] <replay or directory>...
+       mtgo-replay-tool anonymize [--key <file>] [--schemas <dir>] [--out <file>] <replay>
+       mtgo-replay-tool sign --key <file> <replay or directory>...
+       mtgo-replay-tool verify [--keyring <file>] <replay or directory>...
+       mtgo-replay-tool export [--format json|csv] [--out <path>] <replay>
+       mtgo-replay-tool export --schema";
+
+fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")),
+        )
+        .init();
+
+    let mut args = std::env::args().skip(1);
+    let result = match args.next().as_deref() {
+        Some("migrate") => run_migrate(args),
+        Some("anonymize") => run_anonymize(args),
+        Some("sign") => run_sign(args),
+        Some("verify") => run_verify(args),
+        Some("export") => run_export(args),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::from(2)
+        }
+    }
+}
+
+/// Migrate every replay named on the command line
+///
+/// # Returns
+/// Ok(false) if any replay failed to migrate
+fn run_migrate(mut args: impl Iterator<Item = String>) -> Result<bool, String> {
+    let mut out_dir = None;
+    let mut force = false;
+    let mut backup = true;
+    let mut redecode = false;
+    let mut sessions_dir = None;
+    let mut schemas_dir = None;
+    let mut inputs = Vec::new();
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .map(PathBuf::from)
+                .ok_or(format!("{} needs a directory", name))
+        };
+        match arg.as_str() {
+            "--out" => out_dir = Some(value("--out")?),
+            "--sessions" => sessions_dir = Some(value("--sessions")?),
+            "--schemas" => schemas_dir = Some(value("--schemas")?),
+            "--force" => force = true,
+            "--no-backup" => backup = false,
+            "--redecode" => redecode = true,
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+    if inputs.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let redecode = if redecode {
+        let registry = match &schemas_dir {
+            Some(dir) => SchemaRegistry::with_user_schemas(dir)?,
+            None => SchemaRegistry::builtin()?,
+        };
+        Some(Redecode {
+            registry: Arc::new(registry),
+            sessions_dir,
+        })
+    } else {
+        None
+    };
+    let options = MigrateOptions {
+        redecode,
+        force,
+        backup,
+    };
+
+    let mut ok = true;
+    for path in replay_files(&inputs)? {
+        let output = out_dir
+            .as_ref()
+            .map(|dir| dir.join(path.file_name().unwrap_or_default()));
+        match migrate::migrate(&path, output.as_deref(), &options) {
+            Ok(report) if report.status == MigrationStatus::UpToDate => {
+                println!(
+                    "{}: already at format {}",
+                    path.display(),
+                    report.to_version
+                );
+            }
+            Ok(report) => {
+                let redecoded = if report.redecoded {
+                    format!(
+                        ", re-decoded {} events (was {})",
+                        report.events_after, report.events_before
+                    )
+                } else {
+                    String::new()
+                };
+                let unsigned = if report.signature_removed {
+                    ", signature removed"
+                } else {
+                    ""
+                };
+                println!(
+                    "{}: migrated {} -> {} into {}{}{}",
+                    path.display(),
+                    report.from_version,
+                    report.to_version,
+                    report.output.display(),
+                    redecoded,
+                    unsigned
+                );
+            }
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+/// Write an anonymized copy of one replay
+fn run_anonymize(mut args: impl Iterator<Item = String>) -> Result<bool, String> {
+    let mut key = None;
+    let mut schemas_dir = None;
+    let mut output = None;
+    let mut input = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .map(PathBuf::from)
+                .ok_or(format!("{} needs a path", name))
+        };
+        match arg.as_str() {
+            "--key" => key = Some(value("--key")?),
+            "--schemas" => schemas_dir = Some(value("--schemas")?),
+            "--out" => output = Some(value("--out")?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let input = input.ok_or(USAGE.to_string())?;
+
+    let pseudonyms = match &key {
+        Some(path) => Pseudonymizer::load_or_create(path)?,
+        None => {
+            eprintln!("note: no --key given, pseudonyms will not match other anonymized replays");
+            Pseudonymizer::random()?
+        }
+    };
+    let registry = match &schemas_dir {
+        Some(dir) => SchemaRegistry::with_user_schemas(dir)?,
+        None => SchemaRegistry::builtin()?,
+    };
+    let output = output.unwrap_or_else(|| anonymize::default_output(&input));
+
+    match anonymize::anonymize_file(&input, &output, &pseudonyms, Some(&Arc::new(registry))) {
+        Ok(report) => {
+            let r = &report.redactions;
+            println!(
+                "{}: anonymized into {} ({} players renamed, {} frames rewritten, {} removed, {} chat messages removed)",
+                input.display(),
+                report.output.display(),
+                r.players_renamed,
+                r.frames_rewritten,
+                r.frames_removed,
+                r.chat_removed
+            );
+            Ok(true)
+        }
+        Err(e) => {
+            eprintln!("{}: {}", input.display(), e);
+            Ok(false)
+        }
+    }
+}
+
+/// Export one replay as JSON or CSV, or print the JSON export's schema
+fn run_export(mut args: impl Iterator<Item = String>) -> Result<bool, String> {
+    let mut format = ExportFormat::Json;
+    let mut output = None;
+    let mut input = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--format" => {
+                format = match args.next().as_deref() {
+                    Some("json") => ExportFormat::Json,
+                    Some("csv") => ExportFormat::Csv,
+                    _ => return Err(format!("--format needs json or csv\n{}", USAGE)),
+                }
+            }
+            "--out" => output = Some(args.next().map(PathBuf::from).ok_or("--out needs a path")?),
+            "--schema" => {
+                print!("{}", export::JSON_SCHEMA);
+                return Ok(true);
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(USAGE.to_string()),
+        }
+    }
+    let input = input.ok_or(USAGE.to_string())?;
+    let output = output.unwrap_or_else(|| export::default_output(&input, format));
+
+    match export::export_file(&input, format, &output) {
+        Ok(report) => {
+            println!(
+                "{}: exported {} events into {}",
+                input.display(),
+                report.events,
+                output.display()
+            );
+            Ok(true)
+        }
+        Err(e) => {
+            eprintln!("{}: {}", input.display(), e);
+            Ok(false)
+        }
+    }
+}
+
+/// Sign every replay named on the command line
+fn run_sign(mut args: impl Iterator<Item = String>) -> Result<bool, String> {
+    let mut key = None;
+    let mut inputs = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--key" => key = Some(args.next().map(PathBuf::from).ok_or("--key needs a path")?),
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+    let (Some(key), false) = (key, inputs.is_empty()) else {
+        return Err(USAGE.to_string());
+    };
+
+    let identity = SigningIdentity::load_or_create(&key)?;
+    println!("signing with key {}", identity.fingerprint());
+    let mut ok = true;
+    for path in replay_files(&inputs)? {
+        match signing::sign_file(&path, &identity) {
+            Ok(info) => println!("{}: signed ({} chunks)", path.display(), info.chunks),
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                ok = false;
+            }
+        }
+    }
+    Ok(ok)
+}
+
+/// Check the signature of every replay named on the command line
+///
+/// # Returns
+/// Ok(false) unless every replay is signed and unmodified
+fn run_verify(mut args: impl Iterator<Item = String>) -> Result<bool, String> {
+    let mut keyring = Keyring::default();
+    let mut inputs = Vec::new();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--keyring" => {
+                let path = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or("--keyring needs a path")?;
+                keyring = Keyring::load(&path)?;
+            }
+            _ if arg.starts_with("--") => return Err(format!("unknown option {}\n{}", arg, USAGE)),
+            _ => inputs.push(PathBuf::from(arg)),
+        }
+    }
+    if inputs.is_empty() {
+        return Err(USAGE.to_string());
+    }
+
+    let mut ok = true;
+    for path in replay_files(&inputs)? {
+        let report = match signing::verify_file(&path, &keyring, None) {
+            Ok(report) => report,
+            Err(e) => {
+                eprintln!("{}: {}", path.display(), e);
+                ok = false;
+                continue;
+            }
+        };
+        let signer = match (&report.signer, &report.trusted_as) {
+            (Some(signer), Some(name)) => format!(" by {} ({})", name, signer.fingerprint),
+            (Some(signer), None) => format!(" by untrusted key {}", signer.fingerprint),
+            _ => String::new(),
+        };
+        let status = match report.status {
+            SignatureStatus::Unsigned => "not signed",
+            SignatureStatus::Valid => "signature valid",
+            SignatureStatus::Modified => "MODIFIED after signing",
+            SignatureStatus::Invalid => "signature INVALID",
+        };
+        println!("{}: {}{}", path.display(), status, signer);
+        if report.header_modified {
+            println!("  header modified");
+        }
+        for chunk in &report.modified_chunks {
+            let kind = chunk
+                .chunk_kind
+                .map(|k| format!("{:?}", k).to_lowercase())
+                .unwrap_or_else(|| format!("{:#06x}", chunk.kind));
+            let change = format!("{:?}", chunk.change).to_lowercase();
+            println!(
+                "  {} chunk {}..={} {}",
+                kind, chunk.first_seq, chunk.last_seq, change
+            );
+        }
+        ok &= report.status == SignatureStatus::Valid;
+    }
+    Ok(ok)
+}
+
+/// Expand directories to the replay files directly inside them
+fn replay_files(inputs: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut files = Vec::new();
+    for input in inputs {
+        if !input.is_dir() {
+            files.push(input.clone());
+            continue;
+        }
+        let entries =
+            std::fs::read_dir(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| is_replay(path))
+            .collect();
+        found.sort();
+        files.extend(found);
+    }
+    Ok(files)
+}
+
+fn is_replay(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some(REPLAY_EXTENSION)
+}
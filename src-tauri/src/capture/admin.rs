@@ -1,4 +1,5 @@
 use crate::common::error::CaptureError;
+#[cfg(target_os = "windows")]
 use std::path::PathBuf;
 
 /// Check if the application is running with administrator privileges
@@ -39,8 +40,7 @@ pub fn check_windivert_driver() -> Result<bool, CaptureError> {
     }
 
     // Check system32/drivers directory (if installed globally)
-    let system32 = std::env::var("SystemRoot")
-        .unwrap_or_else(|_| "C:\\Windows".to_string());
+    let system32 = std::env::var("SystemRoot").unwrap_or_else(|_| "C:\\Windows".to_string());
     let drivers_dir = PathBuf::from(&system32).join("System32").join("drivers");
 
     if drivers_dir.join("WinDivert64.sys").exists() || drivers_dir.join("WinDivert.sys").exists() {
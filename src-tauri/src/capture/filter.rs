@@ -1,6 +1,8 @@
 /// MTGO traffic filter string
 ///
-/// This filter captures all outbound TCP traffic. Starting with a broad filter
+/// This filter captures all TCP traffic in both directions. Game state is sent by
+/// the server, so inbound packets are required to reconstruct games; outbound
+/// packets carry the client's handshake and actions. Starting with a broad filter
 /// allows us to capture actual MTGO traffic and identify specific server IPs/ports
 /// during the proof-of-concept phase.
 ///
@@ -9,21 +11,21 @@
 /// actual MTGO traffic patterns.
 ///
 /// Filter components:
-/// - "outbound": Only capture outbound traffic from local machine
+/// - No direction restriction: both client and server messages are needed
 /// - "tcp": Only capture TCP packets (MTGO uses TCP, likely HTTP/HTTPS)
 /// - No port restrictions initially - broad capture for discovery
 ///
 /// Future refinement: After discovering MTGO servers, update filter to:
-/// "outbound and tcp and (ip.DstAddr == SERVER_IP_1 or ip.DstAddr == SERVER_IP_2)"
-pub const MTGO_FILTER: &str = "outbound and tcp";
+/// "tcp and (ip.DstAddr == SERVER_IP_1 or ip.SrcAddr == SERVER_IP_1 or ...)"
+pub const MTGO_FILTER: &str = "tcp";
 
 /// Analyze captured IP addresses and ports for filter refinement
 ///
 /// This function processes captured packet metadata to identify MTGO server
 /// characteristics (IP ranges, specific ports) for refining the BPF filter.
 ///
-/// The filter can then be updated from "outbound and tcp" to:
-/// "outbound and tcp and (ip.DstAddr == MTGO_IP_1 or ip.DstAddr == MTGO_IP_2)"
+/// The filter can then be updated from "tcp" to:
+/// "tcp and (ip.DstAddr == MTGO_IP_1 or ip.SrcAddr == MTGO_IP_1 or ...)"
 ///
 /// This addresses Success Criterion #5: "BPF filter successfully filters MTGO server traffic,
 /// reducing captured packets to < 10MB/hour"
@@ -59,16 +61,14 @@ pub fn analyze_and_suggest_refined_filter(captured_ips: Vec<(String, u16)>) -> S
         return MTGO_FILTER.to_string();
     }
 
-    // Build refined filter: "outbound and tcp and (ip.DstAddr == IP1 or ip.DstAddr == IP2 ...)"
+    // Build refined filter matching both directions:
+    // "tcp and (ip.DstAddr == IP1 or ip.SrcAddr == IP1 or ...)"
     let ip_conditions: Vec<String> = top_pairs
         .iter()
-        .map(|((ip, _port), _count)| format!("ip.DstAddr == {}", ip))
+        .map(|((ip, _port), _count)| format!("ip.DstAddr == {} or ip.SrcAddr == {}", ip, ip))
         .collect();
 
-    let refined_filter = format!(
-        "outbound and tcp and ({})",
-        ip_conditions.join(" or ")
-    );
+    let refined_filter = format!("tcp and ({})", ip_conditions.join(" or "));
 
     refined_filter
 }
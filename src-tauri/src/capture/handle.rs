@@ -1,17 +1,21 @@
 #[cfg(target_os = "windows")]
-use windivert::prelude::*;
 use crate::capture::filter::MTGO_FILTER;
 use crate::common::error::CaptureError;
+#[cfg(target_os = "windows")]
 use std::sync::Arc;
+#[cfg(target_os = "windows")]
+use windivert::prelude::*;
 
 /// Wrapper for WinDivert handle with automatic cleanup
 ///
 /// Uses Arc<WinDivert<NetworkLayer>> to allow sharing handle between capture loop
 /// and control commands without worrying about lifetime issues.
+#[cfg(target_os = "windows")]
 pub struct CaptureHandle {
     inner: Arc<WinDivert<NetworkLayer>>,
 }
 
+#[cfg(target_os = "windows")]
 impl CaptureHandle {
     /// Create a new WinDivert handle for capturing MTGO traffic
     ///
@@ -40,16 +44,17 @@ impl CaptureHandle {
             }
         }
         if paren_count != 0 {
-            return Err(CaptureError::CaptureLoopError(
-                format!("Unbalanced parentheses in filter: {}", MTGO_FILTER)
-            ));
+            return Err(CaptureError::CaptureLoopError(format!(
+                "Unbalanced parentheses in filter: {}",
+                MTGO_FILTER
+            )));
         }
 
         // Create WinDivert handle with network layer constructor
         let handle = WinDivert::network(
-            MTGO_FILTER,  // Apply MTGO traffic filter
-            0,            // Priority
-            Default::default(),  // Packet sniffing: copy, don't drop packets
+            MTGO_FILTER,        // Apply MTGO traffic filter
+            0,                  // Priority
+            Default::default(), // Packet sniffing: copy, don't drop packets
         )?;
 
         Ok(CaptureHandle {
@@ -78,6 +83,7 @@ impl CaptureHandle {
 ///
 /// The WinDivert<Network> type implements Drop, so closing the Arc
 /// when the last reference is dropped automatically closes the WinDivert handle.
+#[cfg(target_os = "windows")]
 impl Drop for CaptureHandle {
     fn drop(&mut self) {
         #[cfg(target_os = "windows")]
@@ -98,6 +104,10 @@ impl CaptureHandle {
     pub fn new() -> Result<Self, CaptureError> {
         Ok(CaptureHandle)
     }
+
+    pub fn clone_handle(&self) -> CaptureHandle {
+        CaptureHandle
+    }
 }
 
 #[cfg(not(target_os = "windows"))]
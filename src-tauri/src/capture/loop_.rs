@@ -1,20 +1,23 @@
 #[cfg(target_os = "windows")]
-use windivert::prelude::*;
 use std::sync::Arc;
+#[cfg(target_os = "windows")]
 use std::time::{Duration, Instant};
 use tokio::sync::{broadcast, mpsc};
+#[cfg(target_os = "windows")]
 use tracing::{debug, error, info, warn};
+#[cfg(target_os = "windows")]
+use windivert::prelude::*;
 
 /// Channel capacity for packet capture (PERF-004)
 ///
 /// Per RESEARCH.md Open Question 2, optimal capacity is unknown.
 /// Starting with 1000 packets based on typical burst patterns.
 /// Will adjust based on proof-of-concept metrics.
+#[cfg(target_os = "windows")]
 const CHANNEL_CAPACITY: usize = 1000;
 
 /// Packet data with metadata
 #[derive(Debug, Clone)]
-#[cfg(target_os = "windows")]
 pub struct CapturedPacket {
     pub data: Vec<u8>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
@@ -57,7 +60,10 @@ pub fn capture_loop(
 
     // Spawn capture task
     let task = tokio::spawn(async move {
-        info!("Packet capture loop started (channel capacity: {})", CHANNEL_CAPACITY);
+        info!(
+            "Packet capture loop started (channel capacity: {})",
+            CHANNEL_CAPACITY
+        );
 
         let mut stats = CaptureStats::default();
         let mut shutdown_rx = shutdown_tx.subscribe();
@@ -76,15 +82,18 @@ pub fn capture_loop(
             let handle_clone = Arc::clone(&handle);
             let recv_result = tokio::task::spawn_blocking(move || {
                 let mut buffer = Box::new([0u8; 1500]);
-                handle_clone.recv_wait(&mut *buffer, 100).map(|opt| opt.map(|p| {
-                    // Convert packet data to owned Vec
-                    WinDivertPacket {
-                        address: p.address,
-                        data: std::borrow::Cow::from(p.data.to_vec()),
-                    }
-                }))
-            }).await;
-            
+                handle_clone.recv_wait(&mut *buffer, 100).map(|opt| {
+                    opt.map(|p| {
+                        // Convert packet data to owned Vec
+                        WinDivertPacket {
+                            address: p.address,
+                            data: std::borrow::Cow::from(p.data.to_vec()),
+                        }
+                    })
+                })
+            })
+            .await;
+
             match recv_result {
                 Ok(Ok(Some(packet))) => {
                     // Capture successful
@@ -132,7 +141,8 @@ pub fn capture_loop(
                         // 10MB/hour = 10 * 1024 * 1024 bytes / 3600 seconds ≈ 2913 bytes/s
                         const MAX_BYTES_PER_SECOND: f64 = 10.0 * 1024.0 * 1024.0 / 3600.0;
 
-                        if stats.packet_count % 600 == 0 {  // Log every 600 packets (≈ every 6 seconds at typical rates)
+                        if stats.packet_count % 600 == 0 {
+                            // Log every 600 packets (≈ every 6 seconds at typical rates)
                             if throughput > MAX_BYTES_PER_SECOND {
                                 warn!(
                                     "Traffic volume {:.2} bytes/s exceeds 10MB/hour threshold ({:.2} bytes/s). Filter: '{}'. Consider refining filter to specific MTGO servers.",
@@ -165,8 +175,10 @@ pub fn capture_loop(
             }
         }
 
-        info!("Packet capture loop stopped. Total: {} packets, {} bytes",
-              stats.packet_count, stats.bytes_captured);
+        info!(
+            "Packet capture loop stopped. Total: {} packets, {} bytes",
+            stats.packet_count, stats.bytes_captured
+        );
     });
 
     // Return receiver and abort handle for control
@@ -180,18 +192,12 @@ pub fn capture_loop(
 }
 
 /// Stub implementation for non-Windows targets (development only)
-#[cfg(not(target_os = "windows"))]
-pub struct CapturedPacket;
-
-#[cfg(not(target_os = "windows"))]
-pub struct CaptureStats;
-
 #[cfg(not(target_os = "windows"))]
 pub fn capture_loop(
-    _handle: (),
-    _shutdown_tx: (),
-) -> (tokio::sync::mpsc::Receiver<CapturedPacket>, tokio::task::AbortHandle) {
-    let (tx, rx) = tokio::sync::mpsc::channel(1);
+    _handle: crate::capture::handle::CaptureHandle,
+    _shutdown_tx: broadcast::Sender<()>,
+) -> (mpsc::Receiver<CapturedPacket>, tokio::task::AbortHandle) {
+    let (tx, rx) = mpsc::channel(1);
     let handle = tokio::spawn(async move {
         tokio::time::sleep(tokio::time::Duration::from_secs(10)).await;
         drop(tx);
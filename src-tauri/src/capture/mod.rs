@@ -1,4 +1,6 @@
 pub mod admin;
-pub mod handle;
 pub mod filter;
+pub mod handle;
 pub mod loop_;
+pub mod pipeline;
+pub mod settings;
@@ -0,0 +1,207 @@
+use crate::capture::loop_::CapturedPacket;
+use crate::common::error::ProtocolError;
+use crate::game::engine::GameEngine;
+use crate::game::event::GameUpdate;
+use crate::game::model::GameState;
+use crate::protocol::archive::SessionManifest;
+use crate::protocol::frame::Frame;
+use crate::protocol::session::SessionRecorder;
+use crate::protocol::stream::StreamAssembler;
+use crate::protocol::version::ClientVersion;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+/// How often sinks get `GameSink::on_tick` while the capture runs, packets or not
+pub const TICK_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Consumer of the live game event stream (UI, replay writer, lifecycle tracking)
+pub trait GameSink: Send {
+    /// Called for every non-empty update, in event order
+    ///
+    /// # Arguments
+    /// * `update` - Events produced by one message, plus a snapshot when one was due
+    /// * `state` - Game state after the update
+    fn on_update(&mut self, update: &GameUpdate, state: &GameState);
+
+    /// Called for every frame before it is decoded, including frames that fail to decode
+    fn on_frame(&mut self, _frame: &Frame) {}
+
+    /// Called before the first update and whenever the detected client version changes
+    ///
+    /// # Arguments
+    /// * `client_version` - Version reported by the client handshake, if seen yet
+    /// * `schema_version` - Version of the schema set now decoding the session
+    fn on_versions(
+        &mut self,
+        _client_version: Option<&ClientVersion>,
+        _schema_version: &ClientVersion,
+    ) {
+    }
+
+    /// Called about every `TICK_INTERVAL`, also while no packets arrive, for
+    /// time-based work such as writing out buffered replay events
+    fn on_tick(&mut self) {}
+
+    /// Called once when the capture session ends
+    fn on_finish(&mut self) {}
+}
+
+/// Everything that happens to a captured packet after it leaves the capture loop
+///
+/// packet -> TCP reassembly and framing -> archive + decode -> game engine -> sinks
+pub struct CapturePipeline {
+    assembler: StreamAssembler,
+    recorder: SessionRecorder,
+    engine: GameEngine,
+    sinks: Vec<Box<dyn GameSink>>,
+    /// Client version last passed to `GameSink::on_versions` (outer None: not yet called)
+    announced_version: Option<Option<ClientVersion>>,
+    packets: u64,
+    frames: u64,
+}
+
+impl CapturePipeline {
+    pub fn new(recorder: SessionRecorder) -> Self {
+        Self {
+            assembler: StreamAssembler::new(),
+            recorder,
+            engine: GameEngine::new(),
+            sinks: Vec::new(),
+            announced_version: None,
+            packets: 0,
+            frames: 0,
+        }
+    }
+
+    /// Register a consumer of game updates
+    pub fn add_sink(&mut self, sink: Box<dyn GameSink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn session_id(&self) -> &str {
+        self.recorder.session_id()
+    }
+
+    pub fn engine(&self) -> &GameEngine {
+        &self.engine
+    }
+
+    /// Run one captured packet through the pipeline
+    ///
+    /// # Returns
+    /// Number of frames the packet completed
+    pub fn process_packet(&mut self, packet: &CapturedPacket) -> usize {
+        self.packets += 1;
+
+        let frames = match self.assembler.push_packet(&packet.data, packet.timestamp) {
+            Ok(frames) => frames,
+            Err(e) => {
+                debug!("Skipping packet {}: {}", self.packets, e);
+                return 0;
+            }
+        };
+
+        for frame in &frames {
+            for sink in &mut self.sinks {
+                sink.on_frame(frame);
+            }
+            let message = self.recorder.process(frame);
+            self.announce_versions();
+            let Some(message) = message else {
+                continue;
+            };
+
+            match self.engine.apply(frame, &message) {
+                Ok(update) if !update.is_empty() => {
+                    for sink in &mut self.sinks {
+                        sink.on_update(&update, self.engine.state());
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Ignoring game message in frame {}: {}", frame.index, e),
+            }
+        }
+
+        if !frames.is_empty() {
+            self.frames += frames.len() as u64;
+            if let Err(e) = self.recorder.flush() {
+                error!("Failed to flush session archive: {}", e);
+            }
+        }
+
+        frames.len()
+    }
+
+    /// Tell sinks about the session's versions if they changed since last time
+    fn announce_versions(&mut self) {
+        let decoder = self.recorder.decoder();
+        if self.announced_version.as_ref() == Some(&decoder.client_version().cloned()) {
+            return;
+        }
+        self.announced_version = Some(decoder.client_version().cloned());
+        for sink in &mut self.sinks {
+            sink.on_versions(decoder.client_version(), decoder.active_version());
+        }
+    }
+
+    /// Give sinks their periodic tick
+    pub fn tick(&mut self) {
+        for sink in &mut self.sinks {
+            sink.on_tick();
+        }
+    }
+
+    /// Close the session archive and notify sinks
+    pub fn finish(mut self) -> Result<SessionManifest, ProtocolError> {
+        for sink in &mut self.sinks {
+            sink.on_finish();
+        }
+        info!(
+            "Capture session {} finished: {} packets, {} frames, {} game events",
+            self.recorder.session_id(),
+            self.packets,
+            self.frames,
+            self.engine.event_count()
+        );
+        self.recorder.finish()
+    }
+}
+
+/// Consume captured packets until the capture loop closes the channel
+///
+/// Runs on a blocking thread since archiving performs synchronous file I/O. Waiting
+/// for packets times out every `TICK_INTERVAL` so sinks are ticked through idle
+/// stretches. The session archive is finalized when the channel closes (capture
+/// stopped).
+pub fn spawn_pipeline(
+    mut pipeline: CapturePipeline,
+    mut packet_rx: mpsc::Receiver<CapturedPacket>,
+) -> tokio::task::JoinHandle<()> {
+    let runtime = tokio::runtime::Handle::current();
+    tokio::task::spawn_blocking(move || {
+        info!(
+            "Capture pipeline started for session {}",
+            pipeline.session_id()
+        );
+
+        let mut last_tick = Instant::now();
+        loop {
+            match runtime.block_on(tokio::time::timeout(TICK_INTERVAL, packet_rx.recv())) {
+                Ok(Some(packet)) => {
+                    pipeline.process_packet(&packet);
+                }
+                Ok(None) => break,
+                Err(_) => {}
+            }
+            if last_tick.elapsed() >= TICK_INTERVAL {
+                pipeline.tick();
+                last_tick = Instant::now();
+            }
+        }
+
+        if let Err(e) = pipeline.finish() {
+            error!("Failed to finalize capture session: {}", e);
+        }
+    })
+}
@@ -0,0 +1,61 @@
+use crate::common::error::CaptureError;
+use crate::replay::StreamConfig;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+
+/// User-adjustable capture options, stored as JSON in the application data directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureSettings {
+    /// Longest time, in milliseconds, buffered replay events wait before being written to the file
+    pub replay_chunk_interval_ms: u64,
+    /// Longest time, in milliseconds, written replay chunks wait before being fsynced; 0 syncs every chunk
+    pub replay_sync_interval_ms: u64,
+    /// Also store the raw frames of each match in its replay
+    pub embed_frames: bool,
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        let config = StreamConfig::default();
+        Self {
+            replay_chunk_interval_ms: config.chunk_interval.as_millis() as u64,
+            replay_sync_interval_ms: config.sync_interval.as_millis() as u64,
+            embed_frames: config.embed_frames,
+        }
+    }
+}
+
+impl CaptureSettings {
+    /// Read the settings; a missing file holds the defaults
+    pub fn load(path: &Path) -> Result<Self, CaptureError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| CaptureError::InvalidSettings(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), CaptureError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| CaptureError::InvalidSettings(e.to_string()))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// How the replay of each captured match is written
+    pub fn stream_config(&self) -> StreamConfig {
+        StreamConfig {
+            chunk_interval: Duration::from_millis(self.replay_chunk_interval_ms),
+            sync_interval: Duration::from_millis(self.replay_sync_interval_ms),
+            embed_frames: self.embed_frames,
+        }
+    }
+}
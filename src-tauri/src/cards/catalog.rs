@@ -0,0 +1,289 @@
+use crate::common::error::CardError;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+/// Element describing one catalog object in the `client_<SET>.xml` files
+const OBJECT_ELEMENT: &[u8] = b"DigitalObject";
+const CATALOG_ID_ATTR: &[u8] = b"DigitalObjectCatalogID";
+
+/// Object properties the importer reads
+const NAME_PROPERTY: &str = "CARDNAME_STRING";
+const SET_NAME_PROPERTY: &str = "CARDSETNAME_STRING";
+const COLLECTOR_PROPERTY: &str = "COLLECTOR_INFO_STRING";
+const MANA_COST_PROPERTY: &str = "MANA_COST_STRING";
+const RARITY_PROPERTY: &str = "RARITY_STATUS";
+const PREMIUM_PROPERTY: &str = "IS_PREMIUM";
+
+/// Printing read from the MTGO card catalog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CatalogEntry {
+    pub mtgo_id: u32,
+    pub foil: bool,
+    pub name: String,
+    pub set_code: Option<String>,
+    pub set_name: Option<String>,
+    pub collector_number: Option<String>,
+    pub mana_cost: Option<String>,
+    pub rarity: Option<String>,
+}
+
+/// Catalog objects read from disk
+#[derive(Debug, Default)]
+pub struct Catalog {
+    pub entries: Vec<CatalogEntry>,
+    /// DigitalObjects without a card name (boosters, tickets, avatars)
+    pub skipped: usize,
+}
+
+/// String tables keyed by table name, then string id
+///
+/// The catalog stores most text once in `<PROPERTY>.xml` tables such as
+/// `CARDNAME_STRING.xml` (`<CARDNAME_STRING_ITEM id="ID123">Lightning Bolt</...>`)
+/// and objects refer to it by id.
+type StringTables = HashMap<String, HashMap<String, String>>;
+
+/// Read the MTGO card catalog
+///
+/// The MTGO client keeps its catalog under `CardDataSource` in its data
+/// directory: one `client_<SET>.xml` file per set listing `DigitalObject`
+/// elements, plus string tables the objects' properties refer to. Properties
+/// may also carry their text inline, which is what single exported files use.
+///
+/// # Arguments
+/// * `path` - The `CardDataSource` directory or a single catalog XML file
+///
+/// # Returns
+/// Printings with an MTGO catalog id and a card name
+/// Err(CardError) if a file cannot be read or is not well-formed XML
+pub fn read_catalog(path: &Path) -> Result<Catalog, CardError> {
+    let files = catalog_files(path)?;
+
+    let mut tables = StringTables::new();
+    let mut object_files = Vec::new();
+    for file in files {
+        if is_string_table(&file) {
+            read_string_table(&file, &mut tables)?;
+        } else {
+            object_files.push(file);
+        }
+    }
+
+    let mut catalog = Catalog::default();
+    for file in &object_files {
+        read_objects(file, &tables, &mut catalog)?;
+    }
+    if catalog.entries.is_empty() {
+        warn!(
+            "No card objects found in MTGO catalog at {}",
+            path.display()
+        );
+    }
+    Ok(catalog)
+}
+
+/// XML files to read, sorted for a deterministic import order
+fn catalog_files(path: &Path) -> Result<Vec<PathBuf>, CardError> {
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+    let mut files: Vec<PathBuf> = std::fs::read_dir(path)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| {
+            p.is_file()
+                && p.extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+/// String tables are named after the property they resolve (`*_STRING.xml`)
+fn is_string_table(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|stem| stem.ends_with("_STRING"))
+}
+
+/// Set code implied by a `client_<SET>.xml` file name
+fn set_code_from_file(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    let code = stem.strip_prefix("client_")?;
+    (!code.is_empty()).then(|| code.to_ascii_lowercase())
+}
+
+fn invalid(path: &Path, reason: impl ToString) -> CardError {
+    CardError::InvalidCatalog {
+        path: path.display().to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+fn open_reader(path: &Path) -> Result<Reader<BufReader<File>>, CardError> {
+    let mut reader = Reader::from_reader(BufReader::new(File::open(path)?));
+    reader.config_mut().trim_text(true);
+    Ok(reader)
+}
+
+fn attribute(path: &Path, element: &BytesStart, name: &[u8]) -> Result<Option<String>, CardError> {
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| invalid(path, e))?;
+        if attr.key.as_ref() == name {
+            let value = attr.unescape_value().map_err(|e| invalid(path, e))?;
+            return Ok(Some(value.into_owned()));
+        }
+    }
+    Ok(None)
+}
+
+fn read_string_table(path: &Path, tables: &mut StringTables) -> Result<(), CardError> {
+    let table_name = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default()
+        .to_string();
+    let table = tables.entry(table_name).or_default();
+
+    let mut reader = open_reader(path)?;
+    let mut buf = Vec::new();
+    let mut current_id: Option<String> = None;
+    loop {
+        match reader
+            .read_event_into(&mut buf)
+            .map_err(|e| invalid(path, e))?
+        {
+            Event::Start(e) if e.name().as_ref().ends_with(b"_ITEM") => {
+                current_id = attribute(path, &e, b"id")?;
+            }
+            Event::Text(text) => {
+                if let Some(id) = current_id.take() {
+                    let value = text.unescape().map_err(|e| invalid(path, e))?;
+                    table.insert(id, value.into_owned());
+                }
+            }
+            Event::End(_) => current_id = None,
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    debug!("Loaded {} strings from {}", table.len(), path.display());
+    Ok(())
+}
+
+/// Property values of the object being read
+#[derive(Default)]
+struct PendingObject {
+    catalog_id: Option<String>,
+    properties: HashMap<String, String>,
+}
+
+impl PendingObject {
+    /// Property value, resolved through its string table when it is a string id
+    fn get(&self, tables: &StringTables, property: &str) -> Option<String> {
+        let value = self.properties.get(property)?;
+        let resolved = tables
+            .get(property)
+            .and_then(|table| table.get(value))
+            .unwrap_or(value);
+        (!resolved.is_empty()).then(|| resolved.clone())
+    }
+
+    fn into_entry(self, tables: &StringTables, set_code: Option<&String>) -> Option<CatalogEntry> {
+        let mtgo_id = self
+            .catalog_id
+            .as_deref()
+            .map(|id| id.trim_start_matches("DOC_"))
+            .and_then(|id| id.parse::<u32>().ok())
+            .filter(|&id| id != 0)?;
+        let name = self.get(tables, NAME_PROPERTY)?;
+        // Present-but-empty (`<IS_PREMIUM/>`) counts as set
+        let foil = self
+            .properties
+            .get(PREMIUM_PROPERTY)
+            .is_some_and(|v| !matches!(v.as_str(), "0" | "false" | "False"));
+
+        Some(CatalogEntry {
+            mtgo_id,
+            foil,
+            name,
+            set_code: set_code.cloned(),
+            set_name: self.get(tables, SET_NAME_PROPERTY),
+            collector_number: self.get(tables, COLLECTOR_PROPERTY),
+            mana_cost: self.get(tables, MANA_COST_PROPERTY),
+            rarity: self.get(tables, RARITY_PROPERTY),
+        })
+    }
+}
+
+fn read_objects(
+    path: &Path,
+    tables: &StringTables,
+    catalog: &mut Catalog,
+) -> Result<(), CardError> {
+    let set_code = set_code_from_file(path);
+    let mut reader = open_reader(path)?;
+    let mut buf = Vec::new();
+    let mut object: Option<PendingObject> = None;
+    // Property whose text content is being read (`<CARDNAME_STRING>Name</...>`)
+    let mut open_property: Option<String> = None;
+
+    loop {
+        let event = reader
+            .read_event_into(&mut buf)
+            .map_err(|e| invalid(path, e))?;
+        match event {
+            Event::Start(e) if e.name().as_ref() == OBJECT_ELEMENT => {
+                object = Some(PendingObject {
+                    catalog_id: attribute(path, &e, CATALOG_ID_ATTR)?,
+                    properties: HashMap::new(),
+                });
+            }
+            // An object without properties has no card name
+            Event::Empty(e) if e.name().as_ref() == OBJECT_ELEMENT => catalog.skipped += 1,
+            Event::Start(e) => {
+                if let Some(pending) = object.as_mut() {
+                    let property = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                    let value = attribute(path, &e, b"value")?.unwrap_or_default();
+                    pending.properties.insert(property.clone(), value);
+                    open_property = Some(property);
+                }
+            }
+            Event::Empty(e) => {
+                if let Some(pending) = object.as_mut() {
+                    let property = String::from_utf8_lossy(e.name().as_ref()).into_owned();
+                    let value = attribute(path, &e, b"value")?.unwrap_or_default();
+                    pending.properties.insert(property, value);
+                }
+            }
+            Event::Text(text) => {
+                if let (Some(pending), Some(property)) = (object.as_mut(), open_property.as_ref()) {
+                    let value = text.unescape().map_err(|e| invalid(path, e))?;
+                    pending
+                        .properties
+                        .insert(property.clone(), value.into_owned());
+                }
+            }
+            Event::End(e) => {
+                open_property = None;
+                if e.name().as_ref() == OBJECT_ELEMENT {
+                    if let Some(pending) = object.take() {
+                        match pending.into_entry(tables, set_code.as_ref()) {
+                            Some(entry) => catalog.entries.push(entry),
+                            None => catalog.skipped += 1,
+                        }
+                    }
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(())
+}
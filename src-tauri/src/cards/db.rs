@@ -0,0 +1,424 @@
+use crate::cards::catalog::{self, CatalogEntry};
+use crate::cards::scryfall;
+use crate::cards::{Card, CardDbStatus, CardLookup, CardQuery, CardSource, ImportReport};
+use crate::common::error::CardError;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::collections::HashMap;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Schema version stored in `PRAGMA user_version`
+const SCHEMA_VERSION: i64 = 1;
+
+const DEFAULT_SEARCH_LIMIT: usize = 50;
+const MAX_SEARCH_LIMIT: usize = 500;
+
+const CREATE_SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS cards (
+        mtgo_id          INTEGER PRIMARY KEY,
+        foil             INTEGER NOT NULL,
+        name             TEXT NOT NULL,
+        mana_cost        TEXT,
+        cmc              REAL,
+        type_line        TEXT,
+        oracle_text      TEXT,
+        colors           TEXT NOT NULL DEFAULT '',
+        set_code         TEXT,
+        set_name         TEXT,
+        collector_number TEXT,
+        rarity           TEXT,
+        oracle_id        TEXT,
+        source           TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS cards_name ON cards (name COLLATE NOCASE);
+    CREATE INDEX IF NOT EXISTS cards_oracle_id ON cards (oracle_id);
+    CREATE TABLE IF NOT EXISTS imports (
+        source      TEXT PRIMARY KEY,
+        imported_at TEXT NOT NULL,
+        path        TEXT NOT NULL
+    );
+";
+
+const CARD_COLUMNS: &str = "mtgo_id, foil, name, mana_cost, cmc, type_line, oracle_text, colors, \
+                            set_code, set_name, collector_number, rarity, oracle_id, source";
+
+/// Local card database
+///
+/// An embedded SQLite store mapping MTGO catalog ids to oracle data, filled from
+/// files already on disk: Scryfall bulk data and the MTGO client's card
+/// catalog. Nothing is fetched over the network; if a card is missing the user
+/// re-imports newer files.
+///
+/// Scryfall records are authoritative. Catalog records only add printings that
+/// Scryfall has not mapped to an MTGO id yet, and borrow oracle data from a
+/// Scryfall printing with the same name.
+pub struct CardDatabase {
+    conn: Connection,
+}
+
+fn read_card(row: &Row) -> rusqlite::Result<Card> {
+    let colors: String = row.get(7)?;
+    let source: String = row.get(13)?;
+    Ok(Card {
+        mtgo_id: row.get(0)?,
+        foil: row.get(1)?,
+        name: row.get(2)?,
+        mana_cost: row.get(3)?,
+        cmc: row.get(4)?,
+        type_line: row.get(5)?,
+        oracle_text: row.get(6)?,
+        colors: colors.chars().map(String::from).collect(),
+        set_code: row.get(8)?,
+        set_name: row.get(9)?,
+        collector_number: row.get(10)?,
+        rarity: row.get(11)?,
+        oracle_id: row.get(12)?,
+        source: CardSource::parse(&source).unwrap_or(CardSource::MtgoCatalog),
+    })
+}
+
+/// Escape LIKE wildcards so user input matches literally
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+impl CardDatabase {
+    /// Open (creating if needed) the database at `path`
+    pub fn open(path: &Path) -> Result<Self, CardError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::init(conn)
+    }
+
+    /// Database held in memory (for tests and one-off lookups)
+    pub fn open_in_memory() -> Result<Self, CardError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, CardError> {
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SCHEMA_VERSION {
+            warn!(
+                "Card database schema version {} is newer than supported version {}",
+                version, SCHEMA_VERSION
+            );
+        }
+        conn.execute_batch(CREATE_SCHEMA)?;
+        conn.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self { conn })
+    }
+
+    /// Import a Scryfall bulk data file
+    ///
+    /// Every card with an `mtgo_id` or `mtgo_foil_id` replaces the stored record
+    /// for that id. Runs in a single transaction, so a failed import leaves the
+    /// previous data intact.
+    ///
+    /// # Arguments
+    /// * `path` - Scryfall bulk JSON ("Default Cards" covers every MTGO printing)
+    ///
+    /// # Returns
+    /// Counts of records read, printings written and catalog records linked
+    pub fn import_scryfall(&mut self, path: &Path) -> Result<ImportReport, CardError> {
+        let tx = self.conn.transaction()?;
+        let mut report = ImportReport::default();
+        {
+            let mut insert = tx.prepare(&format!(
+                "INSERT OR REPLACE INTO cards ({}) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+                CARD_COLUMNS
+            ))?;
+            let mut result: Result<(), rusqlite::Error> = Ok(());
+            scryfall::read_bulk_file(path, |cards| {
+                report.records += 1;
+                if cards.is_empty() {
+                    report.skipped += 1;
+                }
+                for card in cards {
+                    if result.is_err() {
+                        return;
+                    }
+                    result = insert
+                        .execute(params![
+                            card.mtgo_id,
+                            card.foil,
+                            card.name,
+                            card.mana_cost,
+                            card.cmc,
+                            card.type_line,
+                            card.oracle_text,
+                            card.colors.concat(),
+                            card.set_code,
+                            card.set_name,
+                            card.collector_number,
+                            card.rarity,
+                            card.oracle_id,
+                            CardSource::Scryfall.as_str(),
+                        ])
+                        .map(|_| report.imported += 1);
+                }
+            })?;
+            result?;
+        }
+        report.linked = link_catalog_cards(&tx)?;
+        record_import(&tx, CardSource::Scryfall, path)?;
+        tx.commit()?;
+
+        info!(
+            "Imported {} MTGO printings from {} Scryfall cards ({} not on MTGO)",
+            report.imported, report.records, report.skipped
+        );
+        Ok(report)
+    }
+
+    /// Import the MTGO client card catalog
+    ///
+    /// Adds printings Scryfall has not mapped yet (new sets, promos). Ids already
+    /// known from Scryfall are left alone.
+    ///
+    /// # Arguments
+    /// * `path` - The client's `CardDataSource` directory or one catalog XML file
+    ///
+    /// # Returns
+    /// Counts of objects read, printings written and printings linked to oracle data
+    pub fn import_mtgo_catalog(&mut self, path: &Path) -> Result<ImportReport, CardError> {
+        let catalog = catalog::read_catalog(path)?;
+        let mut report = ImportReport {
+            records: catalog.entries.len() + catalog.skipped,
+            skipped: catalog.skipped,
+            ..ImportReport::default()
+        };
+
+        let tx = self.conn.transaction()?;
+        {
+            let mut upsert = tx.prepare(&format!(
+                "INSERT INTO cards ({}) \
+                 VALUES (?1, ?2, ?3, ?4, NULL, NULL, NULL, '', ?5, ?6, ?7, ?8, NULL, ?9) \
+                 ON CONFLICT (mtgo_id) DO UPDATE SET \
+                     foil = excluded.foil, name = excluded.name, mana_cost = excluded.mana_cost, \
+                     set_code = excluded.set_code, set_name = excluded.set_name, \
+                     collector_number = excluded.collector_number, rarity = excluded.rarity, \
+                     oracle_id = NULL \
+                 WHERE cards.source = excluded.source",
+                CARD_COLUMNS
+            ))?;
+            for entry in &catalog.entries {
+                let CatalogEntry {
+                    mtgo_id,
+                    foil,
+                    name,
+                    set_code,
+                    set_name,
+                    collector_number,
+                    mana_cost,
+                    rarity,
+                } = entry;
+                report.imported += upsert.execute(params![
+                    mtgo_id,
+                    foil,
+                    name,
+                    mana_cost,
+                    set_code,
+                    set_name,
+                    collector_number,
+                    rarity,
+                    CardSource::MtgoCatalog.as_str(),
+                ])?;
+            }
+        }
+        report.linked = link_catalog_cards(&tx)?;
+        record_import(&tx, CardSource::MtgoCatalog, path)?;
+        tx.commit()?;
+
+        info!(
+            "Imported {} MTGO catalog printings ({} linked to oracle data, {} non-card objects)",
+            report.imported, report.linked, report.skipped
+        );
+        Ok(report)
+    }
+
+    /// Card with the given MTGO catalog id
+    pub fn get(&self, mtgo_id: u32) -> Result<Option<Card>, CardError> {
+        let card = self
+            .conn
+            .query_row(
+                &format!("SELECT {} FROM cards WHERE mtgo_id = ?1", CARD_COLUMNS),
+                [mtgo_id],
+                read_card,
+            )
+            .optional()?;
+        Ok(card)
+    }
+
+    /// Cards for a set of MTGO catalog ids; unknown ids are absent from the map
+    pub fn get_many(&self, mtgo_ids: &[u32]) -> Result<HashMap<u32, Card>, CardError> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {} FROM cards WHERE mtgo_id = ?1",
+            CARD_COLUMNS
+        ))?;
+        let mut cards = HashMap::with_capacity(mtgo_ids.len());
+        for &id in mtgo_ids {
+            if cards.contains_key(&id) {
+                continue;
+            }
+            if let Some(card) = stmt.query_row([id], read_card).optional()? {
+                cards.insert(id, card);
+            }
+        }
+        Ok(cards)
+    }
+
+    /// All MTGO printings of a card, by exact (case-insensitive) name
+    pub fn find_by_name(&self, name: &str) -> Result<Vec<Card>, CardError> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {} FROM cards WHERE name = ?1 COLLATE NOCASE ORDER BY foil, mtgo_id",
+            CARD_COLUMNS
+        ))?;
+        let cards = stmt
+            .query_map([name], read_card)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(cards)
+    }
+
+    /// Search cards by name, type line, oracle text and set
+    ///
+    /// # Returns
+    /// Matching printings ordered by name, at most `query.limit` of them
+    pub fn search(&self, query: &CardQuery) -> Result<Vec<Card>, CardError> {
+        let mut conditions = Vec::new();
+        let mut values: Vec<String> = Vec::new();
+
+        let like_filters = [
+            ("name", &query.name),
+            ("type_line", &query.type_line),
+            ("oracle_text", &query.text),
+        ];
+        for (column, filter) in like_filters {
+            if let Some(value) = filter.as_deref().filter(|v| !v.is_empty()) {
+                values.push(like_pattern(value));
+                conditions.push(format!("{} LIKE ?{} ESCAPE '\\'", column, values.len()));
+            }
+        }
+        if let Some(set_code) = query.set_code.as_deref().filter(|v| !v.is_empty()) {
+            values.push(set_code.to_string());
+            conditions.push(format!("set_code = ?{} COLLATE NOCASE", values.len()));
+        }
+        if !query.include_foil {
+            conditions.push("foil = 0".to_string());
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .min(MAX_SEARCH_LIMIT);
+        let sql = format!(
+            "SELECT {} FROM cards {} ORDER BY name COLLATE NOCASE, foil, mtgo_id LIMIT {}",
+            CARD_COLUMNS, where_clause, limit
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let cards = stmt
+            .query_map(params_from_iter(values.iter()), read_card)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(cards)
+    }
+
+    /// Record counts and last import times
+    pub fn status(&self) -> Result<CardDbStatus, CardError> {
+        let count = |sql: &str| -> Result<usize, CardError> {
+            let n: i64 = self.conn.query_row(sql, [], |row| row.get(0))?;
+            Ok(n as usize)
+        };
+        let imported_at = |source: CardSource| -> Result<_, CardError> {
+            let value: Option<String> = self
+                .conn
+                .query_row(
+                    "SELECT imported_at FROM imports WHERE source = ?1",
+                    [source.as_str()],
+                    |row| row.get(0),
+                )
+                .optional()?;
+            Ok(value
+                .and_then(|v| chrono::DateTime::parse_from_rfc3339(&v).ok())
+                .map(|t| t.with_timezone(&chrono::Utc)))
+        };
+
+        Ok(CardDbStatus {
+            cards: count("SELECT COUNT(*) FROM cards")?,
+            scryfall_cards: count("SELECT COUNT(*) FROM cards WHERE source = 'scryfall'")?,
+            catalog_cards: count("SELECT COUNT(*) FROM cards WHERE source = 'mtgo_catalog'")?,
+            unlinked_cards: count(
+                "SELECT COUNT(*) FROM cards WHERE source = 'mtgo_catalog' AND oracle_id IS NULL",
+            )?,
+            scryfall_imported_at: imported_at(CardSource::Scryfall)?,
+            catalog_imported_at: imported_at(CardSource::MtgoCatalog)?,
+        })
+    }
+}
+
+/// Copy oracle data onto catalog-only printings from a Scryfall printing of the same card
+///
+/// MTGO writes split and double-faced names with a single slash ("Fire/Ice") where
+/// Scryfall uses " // ", so both spellings are tried.
+///
+/// # Returns
+/// Number of catalog printings that gained oracle data
+fn link_catalog_cards(conn: &Connection) -> Result<usize, CardError> {
+    let linked = conn.execute(
+        "UPDATE cards SET
+             name = oracle.name,
+             mana_cost = COALESCE(cards.mana_cost, oracle.mana_cost),
+             cmc = oracle.cmc,
+             type_line = oracle.type_line,
+             oracle_text = oracle.oracle_text,
+             colors = oracle.colors,
+             oracle_id = oracle.oracle_id
+         FROM (
+             SELECT name, mana_cost, cmc, type_line, oracle_text, colors, oracle_id
+             FROM cards WHERE source = 'scryfall' AND oracle_id IS NOT NULL
+             GROUP BY name COLLATE NOCASE
+         ) AS oracle
+         WHERE cards.source = 'mtgo_catalog'
+           AND cards.oracle_id IS NULL
+           AND (oracle.name = cards.name COLLATE NOCASE
+                OR oracle.name = REPLACE(cards.name, '/', ' // ') COLLATE NOCASE)",
+        [],
+    )?;
+    Ok(linked)
+}
+
+fn record_import(conn: &Connection, source: CardSource, path: &Path) -> Result<(), CardError> {
+    conn.execute(
+        "INSERT OR REPLACE INTO imports (source, imported_at, path) VALUES (?1, ?2, ?3)",
+        params![
+            source.as_str(),
+            chrono::Utc::now().to_rfc3339(),
+            path.display().to_string()
+        ],
+    )?;
+    Ok(())
+}
+
+impl CardLookup for CardDatabase {
+    fn card(&self, mtgo_id: u32) -> Option<Card> {
+        match self.get(mtgo_id) {
+            Ok(card) => card,
+            Err(e) => {
+                warn!("Card lookup for {} failed: {}", mtgo_id, e);
+                None
+            }
+        }
+    }
+}
@@ -0,0 +1,139 @@
+pub mod catalog;
+pub mod db;
+pub mod scryfall;
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub use db::CardDatabase;
+
+/// Where a card record came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CardSource {
+    /// Scryfall bulk data export (authoritative oracle data)
+    Scryfall,
+    /// MTGO client card catalog (covers printings Scryfall has not mapped yet)
+    MtgoCatalog,
+}
+
+impl CardSource {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CardSource::Scryfall => "scryfall",
+            CardSource::MtgoCatalog => "mtgo_catalog",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<CardSource> {
+        match value {
+            "scryfall" => Some(CardSource::Scryfall),
+            "mtgo_catalog" => Some(CardSource::MtgoCatalog),
+            _ => None,
+        }
+    }
+}
+
+/// One MTGO printing of a card, keyed by its MTGO catalog id
+///
+/// Regular and premium (foil) versions of a printing have different catalog ids
+/// and are stored as separate records with the same oracle data.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Card {
+    pub mtgo_id: u32,
+    pub foil: bool,
+    pub name: String,
+    pub mana_cost: Option<String>,
+    /// Mana value
+    pub cmc: Option<f64>,
+    pub type_line: Option<String>,
+    pub oracle_text: Option<String>,
+    /// Color letters (W, U, B, R, G); empty for colorless cards
+    pub colors: Vec<String>,
+    pub set_code: Option<String>,
+    pub set_name: Option<String>,
+    pub collector_number: Option<String>,
+    pub rarity: Option<String>,
+    /// Scryfall oracle id shared by every printing of the card
+    pub oracle_id: Option<String>,
+    pub source: CardSource,
+}
+
+impl Card {
+    /// Whether the card is a land (analytics treat lands separately from spells)
+    pub fn is_land(&self) -> bool {
+        self.has_type("Land")
+    }
+
+    /// Whether the front face's type line contains a card type or subtype
+    pub fn has_type(&self, card_type: &str) -> bool {
+        self.type_line
+            .as_deref()
+            .and_then(|t| t.split("//").next())
+            .map(|front| front.split_whitespace().any(|word| word == card_type))
+            .unwrap_or(false)
+    }
+}
+
+/// Card search filters; every present filter must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CardQuery {
+    /// Case-insensitive substring of the card name
+    pub name: Option<String>,
+    /// Case-insensitive substring of the type line (e.g. "Creature", "Goblin")
+    pub type_line: Option<String>,
+    /// Case-insensitive substring of the oracle text
+    pub text: Option<String>,
+    /// Exact set code (e.g. "mh3")
+    pub set_code: Option<String>,
+    /// Include premium (foil) printings (default: regular printings only)
+    #[serde(default)]
+    pub include_foil: bool,
+    /// Maximum number of results (default 50, capped at 500)
+    pub limit: Option<usize>,
+}
+
+/// Outcome of importing one card data file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Card records read from the file
+    pub records: usize,
+    /// MTGO printings written to the database
+    pub imported: usize,
+    /// Records without an MTGO id (paper/Arena-only printings, non-card catalog objects)
+    pub skipped: usize,
+    /// Catalog-only printings given oracle data by matching their name
+    pub linked: usize,
+}
+
+/// Counts and import times of the card database
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CardDbStatus {
+    pub cards: usize,
+    pub scryfall_cards: usize,
+    pub catalog_cards: usize,
+    /// Catalog-only printings that have no oracle data yet
+    pub unlinked_cards: usize,
+    pub scryfall_imported_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub catalog_imported_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Card resolution used by the game and analytics layers
+///
+/// Game state and replays only store MTGO catalog ids; names and oracle data are
+/// resolved at load time so replays stay valid as the card data is updated.
+pub trait CardLookup {
+    /// Card with the given MTGO catalog id, if known
+    fn card(&self, mtgo_id: u32) -> Option<Card>;
+
+    /// Card name, if known
+    fn card_name(&self, mtgo_id: u32) -> Option<String> {
+        self.card(mtgo_id).map(|card| card.name)
+    }
+}
+
+impl CardLookup for HashMap<u32, Card> {
+    fn card(&self, mtgo_id: u32) -> Option<Card> {
+        self.get(&mtgo_id).cloned()
+    }
+}
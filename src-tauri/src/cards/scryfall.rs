@@ -0,0 +1,155 @@
+use crate::cards::{Card, CardSource};
+use crate::common::error::CardError;
+use serde::de::{Deserializer, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+/// Face of a multi-faced card (split, transform, modal double-faced, adventure)
+#[derive(Debug, Default, Deserialize)]
+struct BulkFace {
+    mana_cost: Option<String>,
+    type_line: Option<String>,
+    oracle_text: Option<String>,
+    colors: Option<Vec<String>>,
+}
+
+/// The subset of a Scryfall card object the database keeps
+///
+/// See https://scryfall.com/docs/api/cards. Unknown fields are ignored so newer
+/// bulk exports keep importing.
+#[derive(Debug, Deserialize)]
+struct BulkCard {
+    name: String,
+    oracle_id: Option<String>,
+    mtgo_id: Option<u32>,
+    mtgo_foil_id: Option<u32>,
+    mana_cost: Option<String>,
+    cmc: Option<f64>,
+    type_line: Option<String>,
+    oracle_text: Option<String>,
+    colors: Option<Vec<String>>,
+    set: Option<String>,
+    set_name: Option<String>,
+    collector_number: Option<String>,
+    rarity: Option<String>,
+    #[serde(default)]
+    card_faces: Vec<BulkFace>,
+}
+
+/// Top-level value or the faces' values joined the way Scryfall joins names
+fn face_value(
+    top: Option<String>,
+    faces: &[BulkFace],
+    get: impl Fn(&BulkFace) -> Option<&String>,
+) -> Option<String> {
+    if top.as_deref().is_some_and(|v| !v.is_empty()) {
+        return top;
+    }
+    let parts: Vec<&str> = faces
+        .iter()
+        .filter_map(|f| get(f).map(String::as_str))
+        .filter(|v| !v.is_empty())
+        .collect();
+    if parts.is_empty() {
+        top
+    } else {
+        Some(parts.join(" // "))
+    }
+}
+
+impl BulkCard {
+    /// MTGO printings of this card: the regular and the premium catalog id
+    fn into_cards(self) -> Vec<Card> {
+        let ids: Vec<(u32, bool)> = [(self.mtgo_id, false), (self.mtgo_foil_id, true)]
+            .into_iter()
+            .filter_map(|(id, foil)| id.filter(|&id| id != 0).map(|id| (id, foil)))
+            .collect();
+        if ids.is_empty() {
+            return Vec::new();
+        }
+
+        let mana_cost = face_value(self.mana_cost, &self.card_faces, |f| f.mana_cost.as_ref());
+        let type_line = face_value(self.type_line, &self.card_faces, |f| f.type_line.as_ref());
+        let oracle_text = face_value(self.oracle_text, &self.card_faces, |f| {
+            f.oracle_text.as_ref()
+        });
+        let colors = self.colors.unwrap_or_else(|| {
+            let mut colors: Vec<String> = self
+                .card_faces
+                .iter()
+                .flat_map(|f| f.colors.iter().flatten().cloned())
+                .collect();
+            colors.sort();
+            colors.dedup();
+            colors
+        });
+
+        ids.into_iter()
+            .map(|(mtgo_id, foil)| Card {
+                mtgo_id,
+                foil,
+                name: self.name.clone(),
+                mana_cost: mana_cost.clone(),
+                cmc: self.cmc,
+                type_line: type_line.clone(),
+                oracle_text: oracle_text.clone(),
+                colors: colors.clone(),
+                set_code: self.set.clone(),
+                set_name: self.set_name.clone(),
+                collector_number: self.collector_number.clone(),
+                rarity: self.rarity.clone(),
+                oracle_id: self.oracle_id.clone(),
+                source: CardSource::Scryfall,
+            })
+            .collect()
+    }
+}
+
+/// Visits the top-level array one card at a time
+struct CardSeqVisitor<'a, F> {
+    on_card: &'a mut F,
+}
+
+impl<'de, F> Visitor<'de> for CardSeqVisitor<'_, F>
+where
+    F: FnMut(Vec<Card>),
+{
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("an array of Scryfall card objects")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<(), A::Error> {
+        while let Some(card) = seq.next_element::<BulkCard>()? {
+            (self.on_card)(card.into_cards());
+        }
+        Ok(())
+    }
+}
+
+/// Stream a Scryfall bulk data file
+///
+/// Bulk exports ("Default Cards", "All Cards", "Oracle Cards") are a single JSON
+/// array that can exceed 500 MB, so cards are deserialized one at a time rather
+/// than loading the whole document.
+///
+/// # Arguments
+/// * `path` - Bulk data file downloaded from https://scryfall.com/docs/api/bulk-data
+/// * `on_card` - Called once per card object with its MTGO printings (empty if the
+///   card is not on MTGO)
+///
+/// # Returns
+/// Err(CardError) if the file cannot be read or is not a Scryfall card array
+pub fn read_bulk_file(path: &Path, mut on_card: impl FnMut(Vec<Card>)) -> Result<(), CardError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    deserializer.deserialize_seq(CardSeqVisitor {
+        on_card: &mut on_card,
+    })?;
+    deserializer.end()?;
+    Ok(())
+}
@@ -15,6 +15,7 @@ pub enum CaptureError {
     #[error("WinDivert driver installation blocked: WinDivert64.sys may be blocked by antivirus. Please add it to the antivirus allowlist.")]
     DriverBlocked,
 
+    #[cfg(target_os = "windows")]
     #[error("Failed to initialize WinDivert handle: {0}")]
     WinDivertInitFailed(#[from] windivert::error::WinDivertError),
 
@@ -23,6 +24,12 @@ pub enum CaptureError {
 
     #[error("Capture loop error: {0}")]
     CaptureLoopError(String),
+
+    #[error("Invalid capture settings: {0}")]
+    InvalidSettings(String),
+
+    #[error("Failed to read or write capture settings: {0}")]
+    SettingsIo(#[from] std::io::Error),
 }
 
 /// Implement Into<String> for Tauri command compatibility
@@ -31,3 +38,259 @@ impl From<CaptureError> for String {
         error.to_string()
     }
 }
+
+/// Protocol parsing and decoding errors
+#[derive(Error, Debug)]
+pub enum ProtocolError {
+    #[error("Malformed packet: {0}")]
+    MalformedPacket(String),
+
+    #[error("Frame length {0} exceeds maximum of {1} bytes")]
+    FrameTooLarge(usize, usize),
+
+    #[error("Unknown message type {0:#06x}")]
+    UnknownMessageType(u16),
+
+    #[error("Message truncated while decoding field '{field}' at offset {offset}")]
+    Truncated { field: String, offset: usize },
+
+    #[error("Invalid UTF-8 in field '{0}'")]
+    InvalidString(String),
+
+    #[error("Invalid MTGO client version string: {0}")]
+    InvalidVersion(String),
+
+    #[error("Invalid protocol schema: {0}")]
+    InvalidSchema(String),
+
+    #[error("No protocol schemas loaded")]
+    NoSchemas,
+
+    #[error("Capture session not found: {0}")]
+    SessionNotFound(String),
+
+    #[error("Corrupt session archive: {0}")]
+    CorruptArchive(String),
+
+    #[error("Protocol I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<ProtocolError> for String {
+    fn from(error: ProtocolError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Game state reconstruction errors
+#[derive(Error, Debug)]
+pub enum GameError {
+    #[error("Game message '{message}' is missing field '{field}'")]
+    MissingField { message: String, field: String },
+
+    #[error("Game message '{message}' has invalid {field} value {value}")]
+    InvalidValue {
+        message: String,
+        field: String,
+        value: String,
+    },
+
+    #[error("Match record not found: {0}")]
+    MatchNotFound(String),
+
+    #[error("Corrupt match record: {0}")]
+    CorruptMatch(String),
+
+    #[error("Invalid sideboard correction: {0}")]
+    InvalidCorrection(String),
+
+    #[error("Match store I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<GameError> for String {
+    fn from(error: GameError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Card database errors
+#[derive(Error, Debug)]
+pub enum CardError {
+    #[error("Card database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Failed to read card data file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid Scryfall bulk data: {0}")]
+    InvalidScryfall(#[from] serde_json::Error),
+
+    #[error("Invalid MTGO card catalog {path}: {reason}")]
+    InvalidCatalog { path: String, reason: String },
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<CardError> for String {
+    fn from(error: CardError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Deck list import and export errors
+#[derive(Error, Debug)]
+pub enum DeckError {
+    #[error("Failed to read or write deck file: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid .dek file {path}: {reason}")]
+    InvalidDek { path: String, reason: String },
+
+    #[error("No {0} deck list recorded for this match")]
+    NoDeck(String),
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<DeckError> for String {
+    fn from(error: DeckError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Replay file errors
+#[derive(Error, Debug)]
+pub enum ReplayError {
+    #[error("Not a replay file (bad magic bytes)")]
+    BadMagic,
+
+    #[error("Replay format version {major}.{minor} is newer than this application supports")]
+    UnsupportedVersion { major: u16, minor: u16 },
+
+    #[error("Replay uses a required chunk type {0:#06x} this application does not understand")]
+    UnknownRequiredChunk(u16),
+
+    #[error("Replay checksum mismatch at offset {0}")]
+    ChecksumMismatch(u64),
+
+    #[error("Corrupt replay file: {0}")]
+    Corrupt(String),
+
+    #[error("Failed to encode replay data: {0}")]
+    Encode(String),
+
+    #[error("Cannot re-decode replay: {0}")]
+    Redecode(String),
+
+    #[error("Invalid key: {0}")]
+    InvalidKey(String),
+
+    #[error("Cannot sign replay: {0}")]
+    Signing(String),
+
+    #[error("Replay has no {0}")]
+    SeekTarget(String),
+
+    #[error("Replay I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<ReplayError> for String {
+    fn from(error: ReplayError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Replay export errors (JSON and CSV)
+#[derive(Error, Debug)]
+pub enum ExportError {
+    #[error(transparent)]
+    Replay(#[from] ReplayError),
+
+    #[error("Failed to write export: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to serialize export: {0}")]
+    Encode(String),
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<ExportError> for String {
+    fn from(error: ExportError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Replay library errors
+#[derive(Error, Debug)]
+pub enum LibraryError {
+    #[error("Replay library database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
+    #[error("Replay library database version {found} is newer than supported version {supported}")]
+    NewerSchema { found: i64, supported: i64 },
+
+    #[error("Failed to index {path}: {reason}")]
+    Index { path: String, reason: String },
+
+    #[error("{0} is an anonymized copy and is not indexed")]
+    Anonymized(String),
+
+    #[error("Replay library I/O error: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<LibraryError> for String {
+    fn from(error: LibraryError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Archetype definition and classification errors
+#[derive(Error, Debug)]
+pub enum ArchetypeError {
+    #[error("Invalid archetype definitions: {0}")]
+    InvalidDefinitions(String),
+
+    #[error("Failed to read or write archetype definitions: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error(transparent)]
+    Library(#[from] LibraryError),
+
+    #[error(transparent)]
+    Cards(#[from] CardError),
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<ArchetypeError> for String {
+    fn from(error: ArchetypeError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Replay annotation errors
+#[derive(Error, Debug)]
+pub enum AnnotationError {
+    #[error("Invalid annotation: {0}")]
+    Invalid(String),
+
+    #[error("Annotation {0} not found")]
+    NotFound(String),
+
+    #[error("Corrupt annotations file {path}: {reason}")]
+    Corrupt { path: String, reason: String },
+
+    #[error("Failed to read or write annotations: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Implement Into<String> for Tauri command compatibility
+impl From<AnnotationError> for String {
+    fn from(error: AnnotationError) -> Self {
+        error.to_string()
+    }
+}
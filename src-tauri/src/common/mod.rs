@@ -1 +1,3 @@
 pub mod error;
+pub mod hex;
+pub mod paths;
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+use tauri::Manager;
+
+/// Root directory for application data (capture sessions, schemas, replays)
+pub fn app_data_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to resolve application data directory: {}", e))
+}
+
+/// Directory holding one archive per capture session
+pub fn sessions_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("sessions"))
+}
+
+/// Directory of user-supplied protocol schema files (override the built-in set)
+pub fn schemas_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("schemas"))
+}
+
+/// Directory of match records (one JSON file per match)
+pub fn matches_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("matches"))
+}
+
+/// Directory of replay files (one per match)
+pub fn replays_dir(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("replays"))
+}
+
+/// JSON Lines log of sessions whose client version had no exact schema match
+pub fn unknown_versions_log(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("unknown_versions.jsonl"))
+}
+
+/// Embedded card database imported from local Scryfall and MTGO catalog files
+pub fn card_db_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("cards.sqlite"))
+}
+
+/// Secret key replay pseudonyms are derived from, created on first use
+pub fn anonymize_key_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("anonymize.key"))
+}
+
+/// Ed25519 key pair replays are signed with, created on first use
+pub fn signing_key_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("signing.key"))
+}
+
+/// Public keys of teammates whose replay signatures are trusted
+pub fn keyring_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("trusted_keys.json"))
+}
+
+/// Searchable index of the replay directory (rebuilt from the replay files if deleted)
+pub fn library_db_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("library.sqlite"))
+}
+
+/// Replay writing and other capture options
+pub fn capture_settings_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("capture_settings.json"))
+}
+
+/// User-editable archetype definitions matches are classified with
+pub fn archetypes_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    Ok(app_data_dir(app)?.join("archetypes.json"))
+}
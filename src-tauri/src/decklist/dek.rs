@@ -0,0 +1,153 @@
+use crate::cards::CardLookup;
+use crate::common::error::DeckError;
+use crate::decklist::resolve;
+use crate::game::deck::{CardCounts, Deck};
+use quick_xml::escape::escape;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// Element holding one card line in a .dek file
+const CARDS_ELEMENT: &[u8] = b"Cards";
+
+/// A deck read from an MTGO .dek file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DekFile {
+    /// File name without extension
+    pub name: String,
+    pub deck: Deck,
+    /// Card names written in the file, by catalog id
+    pub card_names: BTreeMap<u32, String>,
+}
+
+/// Write an MTGO .dek file
+///
+/// MTGO identifies cards by `CatID`; the `Name` attribute is informational.
+pub fn write_dek(deck: &Deck, cards: &dyn CardLookup, unresolved: &mut Vec<u32>) -> String {
+    let mut out = String::from(
+        "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n\
+         <Deck xmlns:xsd=\"http://www.w3.org/2001/XMLSchema\" \
+         xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\">\n  \
+         <NetDeckID>0</NetDeckID>\n  \
+         <PreconstructedDeckID>0</PreconstructedDeckID>\n",
+    );
+    for (section, sideboard) in [(&deck.maindeck, false), (&deck.sideboard, true)] {
+        for entry in resolve(section, cards, unresolved) {
+            let name = entry
+                .card
+                .as_ref()
+                .map(|c| c.name.as_str())
+                .unwrap_or_default();
+            out.push_str(&format!(
+                "  <Cards CatID=\"{}\" Quantity=\"{}\" Sideboard=\"{}\" Name=\"{}\" Annotation=\"0\" />\n",
+                entry.card_id,
+                entry.count,
+                sideboard,
+                escape(name)
+            ));
+        }
+    }
+    out.push_str("</Deck>\n");
+    out
+}
+
+fn invalid(path: &Path, reason: impl ToString) -> DeckError {
+    DeckError::InvalidDek {
+        path: path.display().to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// Attributes of a `Cards` element: catalog id, quantity, sideboard flag, name
+fn read_card_line(
+    path: &Path,
+    element: &BytesStart,
+) -> Result<(u32, u32, bool, String), DeckError> {
+    let mut cat_id = None;
+    let mut quantity = None;
+    let mut sideboard = false;
+    let mut name = String::new();
+
+    for attr in element.attributes() {
+        let attr = attr.map_err(|e| invalid(path, e))?;
+        let value = attr.unescape_value().map_err(|e| invalid(path, e))?;
+        match attr.key.as_ref() {
+            b"CatID" => {
+                cat_id = Some(
+                    value
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|_| invalid(path, format!("invalid CatID '{}'", value)))?,
+                )
+            }
+            b"Quantity" => {
+                quantity = Some(
+                    value
+                        .trim()
+                        .parse::<u32>()
+                        .map_err(|_| invalid(path, format!("invalid Quantity '{}'", value)))?,
+                )
+            }
+            b"Sideboard" => sideboard = value.trim().eq_ignore_ascii_case("true"),
+            b"Name" => name = value.into_owned(),
+            _ => {}
+        }
+    }
+
+    let cat_id = cat_id.ok_or_else(|| invalid(path, "Cards element without CatID"))?;
+    Ok((cat_id, quantity.unwrap_or(1), sideboard, name))
+}
+
+/// Read an MTGO .dek file
+///
+/// # Returns
+/// The deck and the card names the file carries
+/// Err(DeckError) if the file is unreadable, malformed, or lists no cards
+pub fn read_dek(path: &Path) -> Result<DekFile, DeckError> {
+    let xml = std::fs::read_to_string(path)?;
+    let mut reader = Reader::from_str(&xml);
+    reader.config_mut().trim_text(true);
+
+    let mut maindeck = CardCounts::new();
+    let mut sideboard = CardCounts::new();
+    let mut card_names = BTreeMap::new();
+    loop {
+        match reader.read_event().map_err(|e| invalid(path, e))? {
+            Event::Start(e) | Event::Empty(e) if e.name().as_ref() == CARDS_ELEMENT => {
+                let (cat_id, quantity, in_sideboard, name) = read_card_line(path, &e)?;
+                let section = if in_sideboard {
+                    &mut sideboard
+                } else {
+                    &mut maindeck
+                };
+                let count = section.entry(cat_id).or_insert(0);
+                *count = count.saturating_add(quantity);
+                if !name.is_empty() {
+                    card_names.insert(cat_id, name);
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if maindeck.is_empty() && sideboard.is_empty() {
+        return Err(invalid(path, "no cards listed"));
+    }
+
+    Ok(DekFile {
+        name: path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string(),
+        deck: Deck {
+            maindeck,
+            sideboard,
+            companion: None,
+        },
+        card_names,
+    })
+}
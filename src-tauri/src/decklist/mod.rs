@@ -0,0 +1,130 @@
+pub mod dek;
+pub mod text;
+
+use crate::cards::{Card, CardLookup};
+use crate::common::error::DeckError;
+use crate::game::deck::{self, CardCounts, Deck};
+use crate::game::lifecycle::Match;
+use serde::{Deserialize, Serialize};
+
+/// Deck list file formats (STAT-005)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeckFormat {
+    /// MTGO `.dek` XML, keyed by catalog id (re-imports exactly on MTGO)
+    Dek,
+    /// "4 Card Name" lines, sideboard after a blank line
+    Text,
+    /// Arena import text with Companion/Deck/Sideboard sections
+    Arena,
+}
+
+/// Whose deck list to take from a match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeckSide {
+    /// The capturing user's registered deck
+    Own,
+    /// The opponent's cards seen during the match (partial)
+    Opponent,
+    /// The list the user tagged the match with
+    Intended,
+}
+
+/// Deck list of a match
+///
+/// # Arguments
+/// * `record` - Match to read
+/// * `side` - Whose deck
+/// * `game_number` - For `Own`, the game whose registration to use (None = game 1's)
+///
+/// # Returns
+/// Err(DeckError::NoDeck) if the match has no such list
+pub fn match_deck(
+    record: &Match,
+    side: DeckSide,
+    game_number: Option<u8>,
+) -> Result<Deck, DeckError> {
+    let found = match side {
+        DeckSide::Own => deck::own_deck(record, game_number).cloned(),
+        DeckSide::Opponent => Some(deck::opponent_deck(record)).filter(|d| !d.maindeck.is_empty()),
+        DeckSide::Intended => record.intended_deck.as_ref().map(|d| d.deck.clone()),
+    };
+    found.ok_or_else(|| {
+        let side = match side {
+            DeckSide::Own => "own",
+            DeckSide::Opponent => "opponent",
+            DeckSide::Intended => "intended",
+        };
+        DeckError::NoDeck(side.to_string())
+    })
+}
+
+/// A rendered deck list
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeckExport {
+    pub format: DeckFormat,
+    pub content: String,
+    /// Catalog ids missing from the card database; they are exported with a
+    /// placeholder name, so the list needs a card data import to be usable as text
+    pub unresolved: Vec<u32>,
+}
+
+/// One line of a deck list
+pub(crate) struct Entry {
+    pub card_id: u32,
+    pub count: u32,
+    pub card: Option<Card>,
+}
+
+impl Entry {
+    pub fn name(&self) -> String {
+        match &self.card {
+            Some(card) => card.name.clone(),
+            None => format!("Unknown card #{}", self.card_id),
+        }
+    }
+}
+
+/// Resolve the cards of one deck section, noting ids the database does not know
+pub(crate) fn resolve(
+    counts: &CardCounts,
+    cards: &dyn CardLookup,
+    unresolved: &mut Vec<u32>,
+) -> Vec<Entry> {
+    counts
+        .iter()
+        .filter(|(_, &count)| count > 0)
+        .map(|(&card_id, &count)| {
+            let card = cards.card(card_id);
+            if card.is_none() && !unresolved.contains(&card_id) {
+                unresolved.push(card_id);
+            }
+            Entry {
+                card_id,
+                count,
+                card,
+            }
+        })
+        .collect()
+}
+
+/// Render a deck in the requested format
+///
+/// # Arguments
+/// * `deck` - Deck to export
+/// * `format` - Output format
+/// * `cards` - Card database used to name catalog ids
+pub fn export(deck: &Deck, format: DeckFormat, cards: &dyn CardLookup) -> DeckExport {
+    let mut unresolved = Vec::new();
+    let content = match format {
+        DeckFormat::Dek => dek::write_dek(deck, cards, &mut unresolved),
+        DeckFormat::Text => text::write_text(deck, cards, &mut unresolved),
+        DeckFormat::Arena => text::write_arena(deck, cards, &mut unresolved),
+    };
+    DeckExport {
+        format,
+        content,
+        unresolved,
+    }
+}
@@ -0,0 +1,85 @@
+use crate::cards::{Card, CardLookup};
+use crate::decklist::{resolve, Entry};
+use crate::game::deck::Deck;
+use std::collections::BTreeMap;
+
+/// Merge entries by display name (regular and premium printings share a line), sorted by name
+fn by_name(entries: &[Entry], name: impl Fn(&Entry) -> String) -> BTreeMap<String, u32> {
+    let mut lines = BTreeMap::new();
+    for entry in entries {
+        *lines.entry(name(entry)).or_insert(0) += entry.count;
+    }
+    lines
+}
+
+fn push_lines(out: &mut String, lines: &BTreeMap<String, u32>) {
+    for (name, count) in lines {
+        out.push_str(&format!("{} {}\n", count, name));
+    }
+}
+
+/// Plain "4 Card Name" list: maindeck, a blank line, then the sideboard
+///
+/// This is the layout MTGO writes for .txt exports and most deck sites accept.
+pub fn write_text(deck: &Deck, cards: &dyn CardLookup, unresolved: &mut Vec<u32>) -> String {
+    let main = resolve(&deck.maindeck, cards, unresolved);
+    let side = resolve(&deck.sideboard, cards, unresolved);
+
+    let mut out = String::new();
+    push_lines(&mut out, &by_name(&main, Entry::name));
+    if !side.is_empty() {
+        out.push('\n');
+        push_lines(&mut out, &by_name(&side, Entry::name));
+    }
+    out
+}
+
+/// Name Arena expects: the front face for double-faced and adventure cards, the
+/// full "A // B" name for split cards
+fn arena_name(card: &Card) -> String {
+    let Some((front, _)) = card.name.split_once(" // ") else {
+        return card.name.clone();
+    };
+    let type_line = card.type_line.as_deref().unwrap_or_default();
+    let faces: Vec<&str> = type_line.split("//").map(str::trim).collect();
+    let is_split = faces.len() > 1
+        && !type_line.contains("Adventure")
+        && faces.iter().all(|face| {
+            face.split_whitespace()
+                .any(|word| matches!(word, "Instant" | "Sorcery" | "Room"))
+                && !face.contains("Creature")
+        });
+    if is_split {
+        card.name.clone()
+    } else {
+        front.to_string()
+    }
+}
+
+/// Arena import text: optional Companion section, then Deck and Sideboard
+///
+/// Lines carry names only; MTGO set codes do not all exist on Arena and Arena
+/// rejects lines whose set it does not know.
+pub fn write_arena(deck: &Deck, cards: &dyn CardLookup, unresolved: &mut Vec<u32>) -> String {
+    let main = resolve(&deck.maindeck, cards, unresolved);
+    let side = resolve(&deck.sideboard, cards, unresolved);
+    let name = |entry: &Entry| match &entry.card {
+        Some(card) => arena_name(card),
+        None => entry.name(),
+    };
+
+    let mut out = String::new();
+    if let Some(companion) = deck.companion {
+        let entry = resolve(&[(companion, 1)].into(), cards, unresolved);
+        out.push_str("Companion\n");
+        push_lines(&mut out, &by_name(&entry, name));
+        out.push('\n');
+    }
+    out.push_str("Deck\n");
+    push_lines(&mut out, &by_name(&main, name));
+    if !side.is_empty() {
+        out.push_str("\nSideboard\n");
+        push_lines(&mut out, &by_name(&side, name));
+    }
+    out
+}
@@ -0,0 +1,272 @@
+use crate::explorer::captures::{seconds_since_start, ScenarioLabel};
+use crate::explorer::LoadedSession;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+
+/// Integer widths probed at every payload offset
+const WIDTHS: [usize; 3] = [1, 2, 4];
+
+/// Distinct values tracked per candidate position before giving up counting
+const MAX_TRACKED_VALUES: usize = 64;
+
+/// Candidates reported per kind
+const MAX_CANDIDATES_PER_KIND: usize = 25;
+
+/// Plausible MTGO catalog id range
+const CARD_ID_RANGE: std::ops::RangeInclusive<i64> = 1..=1_000_000;
+
+/// Plausible life total range (life can go negative before state-based actions)
+const LIFE_RANGE: std::ops::RangeInclusive<i64> = -20..=99;
+
+/// Plausible zone enumeration range
+const ZONE_RANGE: std::ops::RangeInclusive<i64> = 0..=15;
+
+/// Kind of game-relevant field a candidate might hold
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CandidateKind {
+    CardId,
+    LifeTotal,
+    Zone,
+}
+
+/// A byte range that might encode a game-relevant value
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldCandidate {
+    pub kind: CandidateKind,
+    pub type_id: u16,
+    pub message_name: Option<String>,
+    pub offset: usize,
+    /// Little-endian integer width in bytes
+    pub width: usize,
+    /// Decoded field already covering this range, if any
+    pub field: Option<String>,
+    pub score: f64,
+    pub evidence: Vec<String>,
+}
+
+/// Observations accumulated for one (type, offset, width) position
+#[derive(Default)]
+struct PositionStats {
+    observations: usize,
+    card_range: usize,
+    life_range: usize,
+    zone_range: usize,
+    values: BTreeSet<i64>,
+    values_overflowed: bool,
+    /// Labels (by index) whose expected value appeared here near the labelled time
+    label_hits: HashMap<CandidateKind, HashSet<usize>>,
+    message_name: Option<String>,
+    field: Option<String>,
+}
+
+impl PositionStats {
+    fn distinct(&self) -> usize {
+        if self.values_overflowed {
+            usize::MAX
+        } else {
+            self.values.len()
+        }
+    }
+}
+
+/// Read a little-endian integer; 4-byte values are read as signed so negative
+/// life totals are recognized
+fn read_value(payload: &[u8], offset: usize, width: usize) -> i64 {
+    let bytes = &payload[offset..offset + width];
+    match width {
+        1 => bytes[0] as i64,
+        2 => u16::from_le_bytes([bytes[0], bytes[1]]) as i64,
+        _ => i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as i64,
+    }
+}
+
+/// Rank byte positions that may hold card ids, life totals or zones
+///
+/// Every offset of every message is probed as a 1-, 2- and 4-byte integer. A position
+/// scores highly when it holds a labelled expected value close to the labelled time,
+/// when its values fall in the plausible range for the kind, and when it takes a
+/// plausible number of distinct values across the captures.
+pub fn rank_candidates(
+    sessions: &[Arc<LoadedSession>],
+    labels: &[ScenarioLabel],
+    window_seconds: f64,
+) -> Vec<FieldCandidate> {
+    let mut stats: HashMap<(u16, usize, usize), PositionStats> = HashMap::new();
+
+    for (capture, session) in sessions.iter().enumerate() {
+        let capture_labels: Vec<(usize, &ScenarioLabel)> = labels
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| l.capture == capture)
+            .collect();
+
+        for (position, frame) in session.frames().iter().enumerate() {
+            let seconds = seconds_since_start(session, frame.timestamp);
+            let nearby: Vec<&(usize, &ScenarioLabel)> = capture_labels
+                .iter()
+                .filter(|(_, l)| (seconds - l.at_seconds).abs() <= window_seconds)
+                .collect();
+            let decoded = session.decoded(position);
+
+            for width in WIDTHS {
+                for offset in 0..frame.payload.len().saturating_sub(width - 1) {
+                    let value = read_value(&frame.payload, offset, width);
+                    let entry = stats.entry((frame.type_id, offset, width)).or_default();
+
+                    if entry.observations == 0 {
+                        entry.message_name = decoded.map(|m| m.name.clone());
+                        entry.field = decoded.and_then(|m| {
+                            m.fields
+                                .iter()
+                                .find(|f| f.offset == offset && f.len == width)
+                                .map(|f| f.name.clone())
+                        });
+                    }
+
+                    entry.observations += 1;
+                    if width > 1 && CARD_ID_RANGE.contains(&value) {
+                        entry.card_range += 1;
+                    }
+                    if LIFE_RANGE.contains(&value) {
+                        entry.life_range += 1;
+                    }
+                    if ZONE_RANGE.contains(&value) {
+                        entry.zone_range += 1;
+                    }
+                    if !entry.values_overflowed {
+                        entry.values.insert(value);
+                        if entry.values.len() > MAX_TRACKED_VALUES {
+                            entry.values_overflowed = true;
+                            entry.values.clear();
+                        }
+                    }
+
+                    for (label_index, label) in &nearby {
+                        let expected = [
+                            (CandidateKind::CardId, label.card_id.map(|v| v as i64)),
+                            (CandidateKind::LifeTotal, label.life_total),
+                            (CandidateKind::Zone, label.zone.map(|v| v as i64)),
+                        ];
+                        for (kind, expected) in expected {
+                            if expected == Some(value) {
+                                entry
+                                    .label_hits
+                                    .entry(kind)
+                                    .or_default()
+                                    .insert(*label_index);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let labelled = |kind: CandidateKind| {
+        labels
+            .iter()
+            .filter(|l| match kind {
+                CandidateKind::CardId => l.card_id.is_some(),
+                CandidateKind::LifeTotal => l.life_total.is_some(),
+                CandidateKind::Zone => l.zone.is_some(),
+            })
+            .count()
+    };
+
+    let mut candidates = Vec::new();
+    for kind in [
+        CandidateKind::CardId,
+        CandidateKind::LifeTotal,
+        CandidateKind::Zone,
+    ] {
+        let total_labels = labelled(kind);
+        let mut ranked: Vec<FieldCandidate> = stats
+            .iter()
+            .filter_map(|(&(type_id, offset, width), s)| {
+                score(kind, s, total_labels).map(|(score, evidence)| FieldCandidate {
+                    kind,
+                    type_id,
+                    message_name: s.message_name.clone(),
+                    offset,
+                    width,
+                    field: s.field.clone(),
+                    score,
+                    evidence,
+                })
+            })
+            .collect();
+
+        // On equal scores prefer the widest reading: a 4-byte field holding a small
+        // value also matches as 1- and 2-byte fields at the same offset
+        ranked.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.width.cmp(&a.width))
+                .then(a.type_id.cmp(&b.type_id))
+                .then(a.offset.cmp(&b.offset))
+        });
+        ranked.truncate(MAX_CANDIDATES_PER_KIND);
+        candidates.extend(ranked);
+    }
+
+    candidates
+}
+
+/// Score a position for one kind, or None if it is not a plausible candidate
+fn score(
+    kind: CandidateKind,
+    s: &PositionStats,
+    total_labels: usize,
+) -> Option<(f64, Vec<String>)> {
+    let hits = s.label_hits.get(&kind).map(|h| h.len()).unwrap_or(0);
+    let distinct = s.distinct();
+    let in_range = match kind {
+        CandidateKind::CardId => s.card_range,
+        CandidateKind::LifeTotal => s.life_range,
+        CandidateKind::Zone => s.zone_range,
+    };
+    let range_fraction = in_range as f64 / s.observations as f64;
+
+    // Constant positions carry no information unless a label pins them down
+    if hits == 0 && (distinct < 2 || range_fraction < 0.9) {
+        return None;
+    }
+
+    let distinct_plausible = match kind {
+        CandidateKind::CardId => distinct >= 2,
+        CandidateKind::LifeTotal => (2..=40).contains(&distinct) && s.values.contains(&20),
+        CandidateKind::Zone => (2..=8).contains(&distinct),
+    };
+
+    let mut score = 0.0;
+    let mut evidence = Vec::new();
+
+    if total_labels > 0 {
+        score += 10.0 * hits as f64 / total_labels as f64;
+        if hits > 0 {
+            evidence.push(format!(
+                "matched {}/{} labelled values near the labelled time",
+                hits, total_labels
+            ));
+        }
+    }
+
+    score += 2.0 * range_fraction;
+    evidence.push(format!(
+        "{:.0}% of values in plausible range",
+        range_fraction * 100.0
+    ));
+
+    if distinct_plausible {
+        score += 1.0;
+        if distinct == usize::MAX {
+            evidence.push("many distinct values".to_string());
+        } else {
+            evidence.push(format!("{} distinct values", distinct));
+        }
+    }
+
+    Some((score, evidence))
+}
@@ -0,0 +1,326 @@
+use crate::explorer::candidates::{rank_candidates, FieldCandidate};
+use crate::explorer::LoadedSession;
+use crate::protocol::frame::Direction;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+/// Default half-width of the window around a label in which messages are correlated
+pub const DEFAULT_LABEL_WINDOW_SECONDS: f64 = 2.0;
+
+/// Maximum byte positions reported in `byte_variance`
+const MAX_VARIANCE_ENTRIES: usize = 200;
+
+/// Maximum messages reported per label correlation
+const MAX_CORRELATED_MESSAGES: usize = 50;
+
+/// A user-supplied annotation of what happened in a controlled capture
+///
+/// e.g. `{ "capture": 1, "at_seconds": 12.0, "text": "cast Lightning Bolt",
+/// "card_id": 12345 }`. Expected values are optional; when given they let the
+/// candidate ranking look for the exact value near the labelled moment.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScenarioLabel {
+    /// Index into the list of captures being compared
+    pub capture: usize,
+    /// Seconds since the capture's first message
+    pub at_seconds: f64,
+    pub text: String,
+    #[serde(default)]
+    pub card_id: Option<u64>,
+    #[serde(default)]
+    pub life_total: Option<i64>,
+    #[serde(default)]
+    pub zone: Option<u64>,
+}
+
+/// Per-capture overview
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureSummary {
+    pub session_id: String,
+    pub message_count: usize,
+    pub duration_seconds: f64,
+}
+
+/// Message counts of one type across the compared captures
+#[derive(Debug, Clone, Serialize)]
+pub struct TypeStat {
+    pub type_id: u16,
+    pub name: Option<String>,
+    /// Count per capture, in capture order
+    pub counts: Vec<usize>,
+    pub differs: bool,
+}
+
+/// How often a byte position varied between aligned messages
+#[derive(Debug, Clone, Serialize)]
+pub struct ByteVariance {
+    pub type_id: u16,
+    pub offset: usize,
+    /// Decoded field covering this byte, if known
+    pub field: Option<String>,
+    /// Aligned message groups in which the byte differed between captures
+    pub varying_groups: usize,
+    /// Aligned message groups that were long enough to contain the byte
+    pub aligned_groups: usize,
+}
+
+/// A message near a label that differs from its counterparts in other captures
+#[derive(Debug, Clone, Serialize)]
+pub struct CorrelatedMessage {
+    pub index: u64,
+    pub type_id: u16,
+    pub name: Option<String>,
+    pub seconds: f64,
+    /// True if no other capture has an aligned counterpart
+    pub unmatched: bool,
+    /// Byte offsets that differ from at least one aligned counterpart
+    pub differing_offsets: Vec<usize>,
+}
+
+/// Messages correlated with one scenario label
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelCorrelation {
+    pub label: ScenarioLabel,
+    pub messages: Vec<CorrelatedMessage>,
+}
+
+/// Result of comparing two or more controlled captures
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureDiffReport {
+    pub captures: Vec<CaptureSummary>,
+    pub type_stats: Vec<TypeStat>,
+    pub byte_variance: Vec<ByteVariance>,
+    pub label_correlations: Vec<LabelCorrelation>,
+    pub candidates: Vec<FieldCandidate>,
+}
+
+/// Alignment key: the n-th message of a type in a direction
+///
+/// Controlled scenarios produce largely the same message sequence, so matching
+/// occurrences of each type is a robust, linear-time alignment that tolerates
+/// insertions of unrelated message types between captures.
+pub type AlignmentKey = (u16, Direction, usize);
+
+/// Position of each capture's message within an aligned group (None if absent)
+pub type AlignedGroup = Vec<Option<usize>>;
+
+/// Align the message sequences of several captures
+pub fn align(sessions: &[Arc<LoadedSession>]) -> BTreeMap<AlignmentKey, AlignedGroup> {
+    let mut groups: BTreeMap<AlignmentKey, AlignedGroup> = BTreeMap::new();
+
+    for (capture, session) in sessions.iter().enumerate() {
+        let mut occurrences: HashMap<(u16, Direction), usize> = HashMap::new();
+        for (position, frame) in session.frames().iter().enumerate() {
+            let n = occurrences
+                .entry((frame.type_id, frame.direction))
+                .or_insert(0);
+            let key = (frame.type_id, frame.direction, *n);
+            *n += 1;
+
+            groups
+                .entry(key)
+                .or_insert_with(|| vec![None; sessions.len()])[capture] = Some(position);
+        }
+    }
+
+    groups
+}
+
+/// Compare captures of controlled scenarios to locate game-relevant fields
+///
+/// # Arguments
+/// * `sessions` - Two or more decoded capture sessions
+/// * `labels` - What the analyst did and when, per capture
+/// * `window_seconds` - Half-width of the time window around each label
+pub fn compare_captures(
+    sessions: &[Arc<LoadedSession>],
+    labels: &[ScenarioLabel],
+    window_seconds: f64,
+) -> CaptureDiffReport {
+    let groups = align(sessions);
+
+    CaptureDiffReport {
+        captures: sessions.iter().map(|s| summarize(s)).collect(),
+        type_stats: type_stats(sessions),
+        byte_variance: byte_variance(sessions, &groups),
+        label_correlations: labels
+            .iter()
+            .filter(|l| l.capture < sessions.len())
+            .map(|l| correlate_label(sessions, &groups, l, window_seconds))
+            .collect(),
+        candidates: rank_candidates(sessions, labels, window_seconds),
+    }
+}
+
+/// Seconds between a capture's first message and a timestamp
+pub fn seconds_since_start(
+    session: &LoadedSession,
+    timestamp: chrono::DateTime<chrono::Utc>,
+) -> f64 {
+    session
+        .start_time()
+        .map(|start| (timestamp - start).num_milliseconds() as f64 / 1000.0)
+        .unwrap_or(0.0)
+}
+
+fn summarize(session: &LoadedSession) -> CaptureSummary {
+    CaptureSummary {
+        session_id: session.session_id.clone(),
+        message_count: session.len(),
+        duration_seconds: session
+            .frames()
+            .last()
+            .map(|f| seconds_since_start(session, f.timestamp))
+            .unwrap_or(0.0),
+    }
+}
+
+fn type_stats(sessions: &[Arc<LoadedSession>]) -> Vec<TypeStat> {
+    let mut stats: BTreeMap<u16, TypeStat> = BTreeMap::new();
+
+    for (capture, session) in sessions.iter().enumerate() {
+        for (position, frame) in session.frames().iter().enumerate() {
+            let stat = stats.entry(frame.type_id).or_insert_with(|| TypeStat {
+                type_id: frame.type_id,
+                name: None,
+                counts: vec![0; sessions.len()],
+                differs: false,
+            });
+            stat.counts[capture] += 1;
+            if stat.name.is_none() {
+                stat.name = session.decoded(position).map(|m| m.name.clone());
+            }
+        }
+    }
+
+    stats
+        .into_values()
+        .map(|mut stat| {
+            stat.differs = stat.counts.windows(2).any(|w| w[0] != w[1]);
+            stat
+        })
+        .collect()
+}
+
+/// Offsets at which the payloads of an aligned group differ
+fn differing_offsets(sessions: &[Arc<LoadedSession>], group: &AlignedGroup) -> (usize, Vec<usize>) {
+    let payloads: Vec<&[u8]> = group
+        .iter()
+        .enumerate()
+        .filter_map(|(capture, pos)| pos.map(|p| sessions[capture].frames()[p].payload.as_slice()))
+        .collect();
+
+    let max_len = payloads.iter().map(|p| p.len()).max().unwrap_or(0);
+    let differing = (0..max_len)
+        .filter(|&offset| {
+            let first = payloads[0].get(offset);
+            payloads.iter().any(|p| p.get(offset) != first)
+        })
+        .collect();
+
+    (max_len, differing)
+}
+
+fn byte_variance(
+    sessions: &[Arc<LoadedSession>],
+    groups: &BTreeMap<AlignmentKey, AlignedGroup>,
+) -> Vec<ByteVariance> {
+    let mut variance: BTreeMap<(u16, usize), ByteVariance> = BTreeMap::new();
+
+    for (&(type_id, _, _), group) in groups {
+        if group.iter().filter(|p| p.is_some()).count() < 2 {
+            continue;
+        }
+
+        let (len, differing) = differing_offsets(sessions, group);
+        let representative = group
+            .iter()
+            .enumerate()
+            .find_map(|(capture, pos)| pos.and_then(|p| sessions[capture].decoded(p)));
+
+        for offset in 0..len {
+            let entry = variance
+                .entry((type_id, offset))
+                .or_insert_with(|| ByteVariance {
+                    type_id,
+                    offset,
+                    field: representative.and_then(|m| {
+                        m.fields
+                            .iter()
+                            .find(|f| offset >= f.offset && offset < f.offset + f.len)
+                            .map(|f| f.name.clone())
+                    }),
+                    varying_groups: 0,
+                    aligned_groups: 0,
+                });
+            entry.aligned_groups += 1;
+        }
+        for offset in differing {
+            if let Some(entry) = variance.get_mut(&(type_id, offset)) {
+                entry.varying_groups += 1;
+            }
+        }
+    }
+
+    let mut entries: Vec<ByteVariance> = variance
+        .into_values()
+        .filter(|v| v.varying_groups > 0)
+        .collect();
+    entries.sort_by(|a, b| {
+        let ratio = |v: &ByteVariance| v.varying_groups as f64 / v.aligned_groups as f64;
+        ratio(b)
+            .total_cmp(&ratio(a))
+            .then(b.varying_groups.cmp(&a.varying_groups))
+    });
+    entries.truncate(MAX_VARIANCE_ENTRIES);
+    entries
+}
+
+fn correlate_label(
+    sessions: &[Arc<LoadedSession>],
+    groups: &BTreeMap<AlignmentKey, AlignedGroup>,
+    label: &ScenarioLabel,
+    window_seconds: f64,
+) -> LabelCorrelation {
+    let session = &sessions[label.capture];
+
+    // Reverse lookup from this capture's positions to their alignment groups
+    let by_position: HashMap<usize, &AlignedGroup> = groups
+        .values()
+        .filter_map(|group| group[label.capture].map(|p| (p, group)))
+        .collect();
+
+    let mut messages = Vec::new();
+    for (position, frame) in session.frames().iter().enumerate() {
+        let seconds = seconds_since_start(session, frame.timestamp);
+        if (seconds - label.at_seconds).abs() > window_seconds {
+            continue;
+        }
+
+        let Some(group) = by_position.get(&position) else {
+            continue;
+        };
+        let counterparts = group.iter().filter(|p| p.is_some()).count() - 1;
+        let (_, differing) = differing_offsets(sessions, group);
+
+        if counterparts == 0 || !differing.is_empty() {
+            messages.push(CorrelatedMessage {
+                index: frame.index,
+                type_id: frame.type_id,
+                name: session.decoded(position).map(|m| m.name.clone()),
+                seconds,
+                unmatched: counterparts == 0,
+                differing_offsets: differing,
+            });
+        }
+        if messages.len() == MAX_CORRELATED_MESSAGES {
+            break;
+        }
+    }
+
+    LabelCorrelation {
+        label: label.clone(),
+        messages,
+    }
+}
@@ -0,0 +1,183 @@
+use crate::common::hex;
+use crate::explorer::hexdump::display_value;
+use crate::protocol::schema::DecodedMessage;
+use serde::Serialize;
+
+/// How a byte region differs between two messages
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionKind {
+    /// Present in both messages with different values
+    Changed,
+    /// Only the left message is this long
+    LeftOnly,
+    /// Only the right message is this long
+    RightOnly,
+}
+
+/// A run of consecutive differing bytes
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffRegion {
+    pub offset: usize,
+    pub len: usize,
+    pub kind: RegionKind,
+    pub left_hex: String,
+    pub right_hex: String,
+    /// Names of decoded fields (from either side) overlapping this region
+    pub fields: Vec<String>,
+}
+
+/// A decoded field whose value differs between the two messages
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldChange {
+    pub name: String,
+    pub left: Option<String>,
+    pub right: Option<String>,
+}
+
+/// Byte-level and field-level comparison of two messages of the same type
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageDiff {
+    pub type_id: u16,
+    pub left_len: usize,
+    pub right_len: usize,
+    pub identical: bool,
+    pub changed_bytes: usize,
+    pub regions: Vec<DiffRegion>,
+    /// Empty unless both messages decoded
+    pub field_changes: Vec<FieldChange>,
+}
+
+/// Compare two payloads byte by byte at equal offsets
+///
+/// Messages of the same type mostly share a fixed layout, so positional comparison
+/// highlights exactly the bytes that carry changing values. Where variable-length
+/// fields shift the layout, the field-level comparison is the more useful view.
+pub fn diff_payloads(
+    type_id: u16,
+    left: &[u8],
+    right: &[u8],
+    left_decoded: Option<&DecodedMessage>,
+    right_decoded: Option<&DecodedMessage>,
+) -> MessageDiff {
+    let common = left.len().min(right.len());
+    let mut regions = Vec::new();
+    let mut changed_bytes = 0;
+
+    let mut i = 0;
+    while i < common {
+        if left[i] == right[i] {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        while i < common && left[i] != right[i] {
+            i += 1;
+        }
+        changed_bytes += i - start;
+        regions.push(region(
+            RegionKind::Changed,
+            start,
+            i,
+            left,
+            right,
+            left_decoded,
+            right_decoded,
+        ));
+    }
+
+    if left.len() != right.len() {
+        let kind = if left.len() > right.len() {
+            RegionKind::LeftOnly
+        } else {
+            RegionKind::RightOnly
+        };
+        let end = left.len().max(right.len());
+        changed_bytes += end - common;
+        regions.push(region(
+            kind,
+            common,
+            end,
+            left,
+            right,
+            left_decoded,
+            right_decoded,
+        ));
+    }
+
+    let field_changes = match (left_decoded, right_decoded) {
+        (Some(l), Some(r)) => field_changes(l, r),
+        _ => Vec::new(),
+    };
+
+    MessageDiff {
+        type_id,
+        left_len: left.len(),
+        right_len: right.len(),
+        identical: regions.is_empty(),
+        changed_bytes,
+        regions,
+        field_changes,
+    }
+}
+
+fn region(
+    kind: RegionKind,
+    start: usize,
+    end: usize,
+    left: &[u8],
+    right: &[u8],
+    left_decoded: Option<&DecodedMessage>,
+    right_decoded: Option<&DecodedMessage>,
+) -> DiffRegion {
+    let slice = |data: &[u8]| hex::encode(&data[start.min(data.len())..end.min(data.len())]);
+
+    // Each side may name the same field, and not necessarily next to each other
+    let mut fields: Vec<String> = Vec::new();
+    for field in left_decoded
+        .into_iter()
+        .chain(right_decoded)
+        .flat_map(|m| m.fields.iter())
+        .filter(|f| f.offset < end && f.offset + f.len > start)
+    {
+        if !fields.contains(&field.name) {
+            fields.push(field.name.clone());
+        }
+    }
+
+    DiffRegion {
+        offset: start,
+        len: end - start,
+        kind,
+        left_hex: slice(left),
+        right_hex: slice(right),
+        fields,
+    }
+}
+
+fn field_changes(left: &DecodedMessage, right: &DecodedMessage) -> Vec<FieldChange> {
+    let mut changes = Vec::new();
+
+    for field in &left.fields {
+        let other = right.field(&field.name);
+        if other != Some(&field.value) {
+            changes.push(FieldChange {
+                name: field.name.clone(),
+                left: Some(display_value(&field.value)),
+                right: other.map(display_value),
+            });
+        }
+    }
+
+    for field in &right.fields {
+        if left.field(&field.name).is_none() {
+            changes.push(FieldChange {
+                name: field.name.clone(),
+                left: None,
+                right: Some(display_value(&field.value)),
+            });
+        }
+    }
+
+    changes
+}
@@ -0,0 +1,105 @@
+use crate::common::hex;
+use crate::protocol::schema::{DecodedField, FieldValue};
+use serde::Serialize;
+
+/// Bytes shown per hex dump row
+pub const BYTES_PER_ROW: usize = 16;
+
+/// One row of a hex+ASCII dump
+#[derive(Debug, Clone, Serialize)]
+pub struct HexRow {
+    pub offset: usize,
+    /// Hex digits for each byte in the row
+    pub hex: Vec<String>,
+    /// Printable ASCII rendering ('.' for non-printable bytes)
+    pub ascii: String,
+    /// Index into the dump's `fields` for each byte, None where no field covers it
+    pub field_ids: Vec<Option<usize>>,
+}
+
+/// A decoded field's byte range, for overlaying on the dump
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldOverlay {
+    pub name: String,
+    pub offset: usize,
+    pub len: usize,
+    /// Human-readable value
+    pub display: String,
+}
+
+/// Hex+ASCII dump of a payload with decoded fields overlaid
+#[derive(Debug, Clone, Serialize)]
+pub struct HexDump {
+    pub len: usize,
+    pub rows: Vec<HexRow>,
+    pub fields: Vec<FieldOverlay>,
+}
+
+/// Build a dump of `payload`, marking the byte ranges of `fields`
+pub fn hex_dump(payload: &[u8], fields: &[DecodedField]) -> HexDump {
+    let mut owner: Vec<Option<usize>> = vec![None; payload.len()];
+    for (i, field) in fields.iter().enumerate() {
+        let end = (field.offset + field.len).min(payload.len());
+        for slot in owner.iter_mut().take(end).skip(field.offset) {
+            *slot = Some(i);
+        }
+    }
+
+    let rows = payload
+        .chunks(BYTES_PER_ROW)
+        .enumerate()
+        .map(|(row, chunk)| {
+            let offset = row * BYTES_PER_ROW;
+            HexRow {
+                offset,
+                hex: chunk.iter().map(|b| format!("{:02x}", b)).collect(),
+                ascii: chunk.iter().map(|&b| printable(b)).collect(),
+                field_ids: owner[offset..offset + chunk.len()].to_vec(),
+            }
+        })
+        .collect();
+
+    HexDump {
+        len: payload.len(),
+        rows,
+        fields: fields
+            .iter()
+            .map(|f| FieldOverlay {
+                name: f.name.clone(),
+                offset: f.offset,
+                len: f.len,
+                display: display_value(&f.value),
+            })
+            .collect(),
+    }
+}
+
+/// Render a byte as ASCII, substituting '.' for anything non-printable
+pub fn printable(b: u8) -> char {
+    if b.is_ascii_graphic() || b == b' ' {
+        b as char
+    } else {
+        '.'
+    }
+}
+
+/// Human-readable rendering of a decoded value
+pub fn display_value(value: &FieldValue) -> String {
+    match value {
+        FieldValue::UInt(v) => format!("{} ({:#x})", v, v),
+        FieldValue::Int(v) => v.to_string(),
+        FieldValue::Bool(v) => v.to_string(),
+        FieldValue::String(v) => format!("{:?}", v),
+        FieldValue::Bytes(v) => {
+            format!("[{} bytes] {}", v.len(), hex::encode(&v[..v.len().min(32)]))
+        }
+        FieldValue::Array(items) => {
+            let shown: Vec<String> = items.iter().take(8).map(display_value).collect();
+            if items.len() > shown.len() {
+                format!("[{}, ... ({} items)]", shown.join(", "), items.len())
+            } else {
+                format!("[{}]", shown.join(", "))
+            }
+        }
+    }
+}
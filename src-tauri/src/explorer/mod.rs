@@ -0,0 +1,221 @@
+pub mod candidates;
+pub mod captures;
+pub mod diff;
+pub mod hexdump;
+
+use crate::common::error::ProtocolError;
+use crate::explorer::diff::{diff_payloads, MessageDiff};
+use crate::explorer::hexdump::{hex_dump, HexDump};
+use crate::protocol::archive;
+use crate::protocol::decoder::Decoder;
+use crate::protocol::frame::{Direction, Frame};
+use crate::protocol::schema::DecodedMessage;
+use crate::protocol::version::SchemaRegistry;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Maximum messages returned in one page
+pub const MAX_PAGE_SIZE: usize = 500;
+
+/// An archived session decoded once and held in memory for browsing
+///
+/// Loading decodes every frame with the current schemas, so the explorer always
+/// reflects the latest protocol knowledge regardless of what was known at capture time.
+pub struct LoadedSession {
+    pub session_id: String,
+    frames: Vec<Frame>,
+    decoded: Vec<Result<DecodedMessage, String>>,
+}
+
+/// One line of the message list
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageSummary {
+    pub index: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub direction: Direction,
+    pub type_id: u16,
+    /// Message name if the frame decoded
+    pub name: Option<String>,
+    pub length: usize,
+    pub error: Option<String>,
+}
+
+/// A page of the message list
+#[derive(Debug, Clone, Serialize)]
+pub struct MessagePage {
+    /// Messages matching the filter, across all pages
+    pub total: usize,
+    pub offset: usize,
+    pub messages: Vec<MessageSummary>,
+}
+
+/// Filter applied when paging through messages
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct MessageFilter {
+    pub type_ids: Option<Vec<u16>>,
+    pub direction: Option<Direction>,
+    /// Only frames that failed to decode
+    #[serde(default)]
+    pub undecoded_only: bool,
+}
+
+/// Full view of one message: summary, hex dump and decoded fields
+#[derive(Debug, Clone, Serialize)]
+pub struct MessageDetail {
+    pub summary: MessageSummary,
+    pub dump: HexDump,
+    pub message: Option<DecodedMessage>,
+}
+
+impl LoadedSession {
+    /// Load and decode an archived session
+    pub fn load(dir: &Path, registry: Arc<SchemaRegistry>) -> Result<Self, ProtocolError> {
+        let manifest = archive::read_manifest(dir)?;
+
+        // Quarantined frames are also in the frame log; merge any that are not
+        let mut frames = archive::read_frames(dir)?;
+        let archived: HashSet<u64> = frames.iter().map(|f| f.index).collect();
+        frames.extend(
+            archive::read_quarantine(dir)?
+                .into_iter()
+                .filter(|q| !archived.contains(&q.frame.index))
+                .map(|q| q.frame),
+        );
+        frames.sort_by_key(|f| f.index);
+
+        let mut decoder = Decoder::new(registry)?;
+        let decoded = frames
+            .iter()
+            .map(|f| decoder.decode(f).map_err(|e| e.to_string()))
+            .collect();
+
+        Ok(Self {
+            session_id: manifest.id,
+            frames,
+            decoded,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Frames in capture order
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+
+    /// Decode result for the frame at a position in `frames()`
+    pub fn decoded(&self, position: usize) -> Option<&DecodedMessage> {
+        self.decoded.get(position).and_then(|d| d.as_ref().ok())
+    }
+
+    /// Timestamp of the first frame, used as time zero for scenario labels
+    pub fn start_time(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.frames.first().map(|f| f.timestamp)
+    }
+
+    /// Frames and decode results in capture order
+    pub fn iter(&self) -> impl Iterator<Item = (&Frame, Option<&DecodedMessage>)> {
+        self.frames
+            .iter()
+            .zip(self.decoded.iter().map(|d| d.as_ref().ok()))
+    }
+
+    /// Page through messages matching a filter
+    pub fn page(&self, filter: &MessageFilter, offset: usize, limit: usize) -> MessagePage {
+        let limit = limit.min(MAX_PAGE_SIZE);
+        let matching: Vec<usize> = (0..self.frames.len())
+            .filter(|&i| self.matches(i, filter))
+            .collect();
+
+        MessagePage {
+            total: matching.len(),
+            offset,
+            messages: matching
+                .iter()
+                .skip(offset)
+                .take(limit)
+                .map(|&i| self.summary(i))
+                .collect(),
+        }
+    }
+
+    /// Hex dump and decoded fields of one message
+    pub fn detail(&self, index: u64) -> Result<MessageDetail, ProtocolError> {
+        let i = self.position(index)?;
+        let message = self.decoded[i].as_ref().ok().cloned();
+        let fields = message.as_ref().map(|m| m.fields.as_slice()).unwrap_or(&[]);
+
+        Ok(MessageDetail {
+            summary: self.summary(i),
+            dump: hex_dump(&self.frames[i].payload, fields),
+            message,
+        })
+    }
+
+    /// Byte-by-byte diff of two messages of the same type
+    pub fn diff(&self, left: u64, right: u64) -> Result<MessageDiff, ProtocolError> {
+        let l = self.position(left)?;
+        let r = self.position(right)?;
+        let (left_frame, right_frame) = (&self.frames[l], &self.frames[r]);
+
+        if left_frame.type_id != right_frame.type_id {
+            return Err(ProtocolError::InvalidSchema(format!(
+                "cannot diff messages of different types ({:#06x} vs {:#06x})",
+                left_frame.type_id, right_frame.type_id
+            )));
+        }
+
+        Ok(diff_payloads(
+            left_frame.type_id,
+            &left_frame.payload,
+            &right_frame.payload,
+            self.decoded[l].as_ref().ok(),
+            self.decoded[r].as_ref().ok(),
+        ))
+    }
+
+    fn position(&self, index: u64) -> Result<usize, ProtocolError> {
+        self.frames
+            .binary_search_by_key(&index, |f| f.index)
+            .map_err(|_| ProtocolError::CorruptArchive(format!("no message with index {}", index)))
+    }
+
+    fn matches(&self, i: usize, filter: &MessageFilter) -> bool {
+        let frame = &self.frames[i];
+        let type_ok = match &filter.type_ids {
+            Some(ids) => ids.contains(&frame.type_id),
+            None => true,
+        };
+        let direction_ok = match filter.direction {
+            Some(direction) => direction == frame.direction,
+            None => true,
+        };
+        type_ok && direction_ok && (!filter.undecoded_only || self.decoded[i].is_err())
+    }
+
+    fn summary(&self, i: usize) -> MessageSummary {
+        let frame = &self.frames[i];
+        let (name, error) = match &self.decoded[i] {
+            Ok(message) => (Some(message.name.clone()), None),
+            Err(e) => (None, Some(e.clone())),
+        };
+
+        MessageSummary {
+            index: frame.index,
+            timestamp: frame.timestamp,
+            direction: frame.direction,
+            type_id: frame.type_id,
+            name,
+            length: frame.payload.len(),
+            error,
+        }
+    }
+}
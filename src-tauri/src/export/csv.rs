@@ -0,0 +1,341 @@
+use crate::common::error::ExportError;
+use crate::export::ExportReport;
+use crate::game::event::{GameEvent, GameEventKind};
+use crate::game::lifecycle::{Match, Outcome};
+use crate::game::model::{PlayerId, Zone};
+use crate::replay::format::ChunkKind;
+use crate::replay::{Chunk, ReplayReader};
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+pub const GAMES_COLUMNS: &[&str] = &[
+    "match_id",
+    "game_number",
+    "game_id",
+    "started_at",
+    "ended_at",
+    "on_play",
+    "mulligans",
+    "opponent_mulligans",
+    "turns",
+    "result",
+    "end_reason",
+];
+
+pub const TURNS_COLUMNS: &[&str] = &[
+    "game_id",
+    "turn",
+    "active_player",
+    "active_player_name",
+    "first_seq",
+    "last_seq",
+    "started_at",
+    "ended_at",
+    "events",
+];
+
+pub const PLAYS_COLUMNS: &[&str] = &[
+    "game_id",
+    "turn",
+    "seq",
+    "timestamp",
+    "player",
+    "player_name",
+    "object",
+    "card_id",
+    "from",
+    "to",
+];
+
+pub const MULLIGANS_COLUMNS: &[&str] = &[
+    "game_id",
+    "seq",
+    "timestamp",
+    "player",
+    "player_name",
+    "count",
+];
+
+/// Where each CSV table is written
+pub struct CsvOutputs<W: Write> {
+    /// One row per game of the match record (empty without one)
+    pub games: W,
+    /// One row per turn
+    pub turns: W,
+    /// One row per card cast or put onto the battlefield from another zone
+    pub plays: W,
+    /// One row per mulligan taken
+    pub mulligans: W,
+}
+
+/// Write the CSV tables of a replay
+///
+/// Events are read one chunk at a time and each row is written as soon as it is
+/// complete.
+///
+/// # Returns
+/// Number of events read
+pub fn write_csv<R: Read + Seek, W: Write>(
+    reader: &mut ReplayReader<R>,
+    outputs: &mut CsvOutputs<W>,
+) -> Result<u64, ExportError> {
+    let mut games = CsvWriter::new(&mut outputs.games, GAMES_COLUMNS)?;
+    if let Some(record) = reader.match_record()? {
+        write_games(&mut games, &record)?;
+    }
+    games.flush()?;
+
+    let mut tables = EventTables {
+        turns: CsvWriter::new(&mut outputs.turns, TURNS_COLUMNS)?,
+        plays: CsvWriter::new(&mut outputs.plays, PLAYS_COLUMNS)?,
+        mulligans: CsvWriter::new(&mut outputs.mulligans, MULLIGANS_COLUMNS)?,
+        names: HashMap::new(),
+        turn: None,
+    };
+    let mut events = 0u64;
+    let entries = reader.index().to_vec();
+    for entry in entries
+        .iter()
+        .filter(|e| e.chunk_kind() == Some(ChunkKind::Events))
+    {
+        if let Chunk::Events(chunk) = reader.read_chunk(entry)? {
+            for event in &chunk {
+                tables.add(event)?;
+                events += 1;
+            }
+        }
+    }
+    tables.finish()?;
+    Ok(events)
+}
+
+/// Write the CSV tables of a replay into a directory, one file per table
+pub fn write_csv_dir<R: Read + Seek>(
+    reader: &mut ReplayReader<R>,
+    dir: &Path,
+) -> Result<ExportReport, ExportError> {
+    std::fs::create_dir_all(dir)?;
+    let files = ["games.csv", "turns.csv", "plays.csv", "mulligans.csv"].map(|name| dir.join(name));
+    let create = |path: &Path| File::create(path).map(BufWriter::new);
+    let mut outputs = CsvOutputs {
+        games: create(&files[0])?,
+        turns: create(&files[1])?,
+        plays: create(&files[2])?,
+        mulligans: create(&files[3])?,
+    };
+    let events = write_csv(reader, &mut outputs)?;
+    Ok(ExportReport {
+        files: files.to_vec(),
+        events,
+    })
+}
+
+fn write_games<W: Write>(games: &mut CsvWriter<W>, record: &Match) -> Result<(), ExportError> {
+    for game in &record.games {
+        games.row(&[
+            record.id.to_string(),
+            game.number.to_string(),
+            game.game_id.to_string(),
+            game.started_at.to_rfc3339(),
+            optional(game.ended_at.map(|t| t.to_rfc3339())),
+            optional(game.on_play),
+            game.mulligans.to_string(),
+            game.opponent_mulligans.to_string(),
+            game.turns.to_string(),
+            optional(game.result.map(outcome_name)),
+            optional(game.end_reason.as_ref()),
+        ])?;
+    }
+    Ok(())
+}
+
+/// Turn being accumulated until the next one starts
+struct TurnRow {
+    game_id: Option<u32>,
+    turn: u32,
+    active_player: PlayerId,
+    first_seq: u64,
+    last_seq: u64,
+    started_at: chrono::DateTime<chrono::Utc>,
+    ended_at: chrono::DateTime<chrono::Utc>,
+    events: u64,
+}
+
+/// Turns, plays and mulligans tables, filled from the event stream
+struct EventTables<W: Write> {
+    turns: CsvWriter<W>,
+    plays: CsvWriter<W>,
+    mulligans: CsvWriter<W>,
+    /// Names of the current game's players
+    names: HashMap<PlayerId, String>,
+    turn: Option<TurnRow>,
+}
+
+impl<W: Write> EventTables<W> {
+    fn add(&mut self, event: &GameEvent) -> Result<(), ExportError> {
+        match &event.kind {
+            GameEventKind::GameStarted { .. } => {
+                self.end_turn()?;
+                self.names.clear();
+            }
+            GameEventKind::PlayerJoined { player, name, .. } => {
+                self.names.insert(*player, name.clone());
+            }
+            GameEventKind::TurnStarted {
+                turn,
+                active_player,
+            } => {
+                self.end_turn()?;
+                self.turn = Some(TurnRow {
+                    game_id: event.game_id,
+                    turn: *turn,
+                    active_player: *active_player,
+                    first_seq: event.seq,
+                    last_seq: event.seq,
+                    started_at: event.timestamp,
+                    ended_at: event.timestamp,
+                    events: 0,
+                });
+            }
+            GameEventKind::ZoneChanged {
+                object,
+                card_id,
+                controller,
+                from,
+                to,
+                ..
+            } if event.kind.is_play() => {
+                self.plays.row(&[
+                    optional(event.game_id),
+                    optional(self.turn.as_ref().map(|t| t.turn)),
+                    event.seq.to_string(),
+                    event.timestamp.to_rfc3339(),
+                    controller.to_string(),
+                    optional(self.names.get(controller)),
+                    object.to_string(),
+                    optional(*card_id),
+                    optional(from.map(zone_name)),
+                    zone_name(*to).to_string(),
+                ])?;
+            }
+            GameEventKind::Mulligan { player, count } => {
+                self.mulligans.row(&[
+                    optional(event.game_id),
+                    event.seq.to_string(),
+                    event.timestamp.to_rfc3339(),
+                    player.to_string(),
+                    optional(self.names.get(player)),
+                    count.to_string(),
+                ])?;
+            }
+            _ => {}
+        }
+
+        if let Some(turn) = self.turn.as_mut() {
+            turn.last_seq = event.seq;
+            turn.ended_at = event.timestamp;
+            turn.events += 1;
+        }
+        if matches!(event.kind, GameEventKind::GameEnded { .. }) {
+            self.end_turn()?;
+        }
+        Ok(())
+    }
+
+    fn end_turn(&mut self) -> Result<(), ExportError> {
+        let Some(turn) = self.turn.take() else {
+            return Ok(());
+        };
+        self.turns.row(&[
+            optional(turn.game_id),
+            turn.turn.to_string(),
+            turn.active_player.to_string(),
+            optional(self.names.get(&turn.active_player)),
+            turn.first_seq.to_string(),
+            turn.last_seq.to_string(),
+            turn.started_at.to_rfc3339(),
+            turn.ended_at.to_rfc3339(),
+            turn.events.to_string(),
+        ])
+    }
+
+    fn finish(mut self) -> Result<(), ExportError> {
+        self.end_turn()?;
+        self.turns.flush()?;
+        self.plays.flush()?;
+        self.mulligans.flush()
+    }
+}
+
+fn zone_name(zone: Zone) -> &'static str {
+    match zone {
+        Zone::Library => "library",
+        Zone::Hand => "hand",
+        Zone::Battlefield => "battlefield",
+        Zone::Graveyard => "graveyard",
+        Zone::Exile => "exile",
+        Zone::Stack => "stack",
+        Zone::Command => "command",
+    }
+}
+
+fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Win => "win",
+        Outcome::Loss => "loss",
+        Outcome::Draw => "draw",
+    }
+}
+
+/// Empty cell for None
+fn optional<T: Display>(value: Option<T>) -> String {
+    value.map(|v| v.to_string()).unwrap_or_default()
+}
+
+/// Minimal RFC 4180 writer
+///
+/// Spreadsheet applications run a cell starting with `=`, `+`, `-` or `@` as a
+/// formula, and player names come from other users, so such cells are written
+/// with a leading `'` that makes them plain text.
+struct CsvWriter<W: Write> {
+    out: W,
+}
+
+impl<W: Write> CsvWriter<W> {
+    /// Start a table with its header row
+    fn new(out: W, columns: &[&str]) -> Result<Self, ExportError> {
+        let mut writer = Self { out };
+        let header: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+        writer.row(&header)?;
+        Ok(writer)
+    }
+
+    fn row(&mut self, fields: &[String]) -> Result<(), ExportError> {
+        for (i, field) in fields.iter().enumerate() {
+            if i > 0 {
+                self.out.write_all(b",")?;
+            }
+            let escaped;
+            let field = if field.starts_with(['=', '+', '-', '@']) {
+                escaped = format!("'{}", field);
+                &escaped
+            } else {
+                field
+            };
+            if field.contains([',', '"', '\n', '\r']) {
+                write!(self.out, "\"{}\"", field.replace('"', "\"\""))?;
+            } else {
+                self.out.write_all(field.as_bytes())?;
+            }
+        }
+        self.out.write_all(b"\r\n")?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), ExportError> {
+        Ok(self.out.flush()?)
+    }
+}
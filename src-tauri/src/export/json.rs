@@ -0,0 +1,146 @@
+use crate::common::error::ExportError;
+use crate::export::EXPORT_VERSION;
+use crate::game::deck::{self, Deck, IntendedDeck};
+use crate::game::lifecycle::Match;
+use crate::game::sideboard::{SideboardCorrection, SideboardFlag, SideboardPlan};
+use crate::replay::format::ChunkKind;
+use crate::replay::{Chunk, ReplayReader};
+use serde::Serialize;
+use std::io::{Read, Seek, Write};
+
+/// Deck lists section of the export
+#[derive(Serialize)]
+struct Decks<'a> {
+    games: Vec<GameDeck<'a>>,
+    opponent_seen: Deck,
+    intended: Option<&'a IntendedDeck>,
+}
+
+#[derive(Serialize)]
+struct GameDeck<'a> {
+    game_number: u8,
+    game_id: u32,
+    deck: &'a Deck,
+}
+
+#[derive(Serialize)]
+struct SideboardPlanExport<'a> {
+    game_number: u8,
+    effective: &'a SideboardPlan,
+    detected: &'a SideboardPlan,
+    corrected: Option<&'a SideboardCorrection>,
+    flags: &'a [SideboardFlag],
+}
+
+/// Write a replay as one JSON document (see `export::JSON_SCHEMA`)
+///
+/// The match record, header and deck lists are written first; events and turn
+/// snapshots are then copied one chunk at a time.
+///
+/// # Returns
+/// Number of events written
+pub fn write_json<R: Read + Seek, W: Write>(
+    reader: &mut ReplayReader<R>,
+    mut out: W,
+) -> Result<u64, ExportError> {
+    let (major, minor) = reader.format_version();
+    let record = reader.match_record()?;
+
+    write!(
+        out,
+        "{{\"export_version\":{},\"replay_format\":",
+        EXPORT_VERSION
+    )?;
+    value(&mut out, &format!("{}.{}", major, minor))?;
+    out.write_all(b",\n\"metadata\":")?;
+    value(&mut out, reader.header())?;
+    out.write_all(b",\n\"match\":")?;
+    value(&mut out, &record)?;
+    out.write_all(b",\n\"decks\":")?;
+    value(&mut out, &decks(record.as_ref()))?;
+    out.write_all(b",\n\"sideboard_plans\":")?;
+    value(&mut out, &sideboard_plans(record.as_ref()))?;
+
+    let entries = reader.index().to_vec();
+    let mut events = 0u64;
+    out.write_all(b",\n\"events\":[")?;
+    for entry in entries
+        .iter()
+        .filter(|e| e.chunk_kind() == Some(ChunkKind::Events))
+    {
+        if let Chunk::Events(chunk) = reader.read_chunk(entry)? {
+            for event in &chunk {
+                out.write_all(if events == 0 { b"\n" } else { b",\n" })?;
+                value(&mut out, event)?;
+                events += 1;
+            }
+        }
+    }
+
+    // The engine snapshots at the start of every turn and periodically in between;
+    // only the first snapshot of each turn is exported
+    out.write_all(b"],\n\"turn_snapshots\":[")?;
+    let mut last_turn = None;
+    for entry in entries
+        .iter()
+        .filter(|e| e.chunk_kind() == Some(ChunkKind::Snapshot))
+    {
+        if let Chunk::Snapshot(snapshot) = reader.read_chunk(entry)? {
+            let turn = Some((snapshot.state.game_id, snapshot.state.turn.number));
+            if turn == last_turn {
+                continue;
+            }
+            out.write_all(if last_turn.is_none() { b"\n" } else { b",\n" })?;
+            value(&mut out, &snapshot)?;
+            last_turn = turn;
+        }
+    }
+    out.write_all(b"]}\n")?;
+    out.flush()?;
+    Ok(events)
+}
+
+fn decks(record: Option<&Match>) -> Decks<'_> {
+    let Some(record) = record else {
+        return Decks {
+            games: Vec::new(),
+            opponent_seen: Deck::default(),
+            intended: None,
+        };
+    };
+    Decks {
+        games: record
+            .games
+            .iter()
+            .filter_map(|game| {
+                game.deck.as_ref().map(|deck| GameDeck {
+                    game_number: game.number,
+                    game_id: game.game_id,
+                    deck,
+                })
+            })
+            .collect(),
+        opponent_seen: deck::opponent_deck(record),
+        intended: record.intended_deck.as_ref(),
+    }
+}
+
+fn sideboard_plans(record: Option<&Match>) -> Vec<SideboardPlanExport<'_>> {
+    record
+        .into_iter()
+        .flat_map(|record| &record.games)
+        .filter_map(|game| {
+            game.sideboard.as_ref().map(|change| SideboardPlanExport {
+                game_number: game.number,
+                effective: change.effective(),
+                detected: &change.detected,
+                corrected: change.corrected.as_ref(),
+                flags: &change.flags,
+            })
+        })
+        .collect()
+}
+
+fn value<W: Write, T: Serialize + ?Sized>(out: &mut W, value: &T) -> Result<(), ExportError> {
+    serde_json::to_writer(out, value).map_err(|e| ExportError::Encode(e.to_string()))
+}
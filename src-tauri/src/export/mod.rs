@@ -0,0 +1,90 @@
+//! Replay export to JSON and CSV (XPORT-001)
+//!
+//! `json` writes a replay as one JSON document described by `JSON_SCHEMA`:
+//! metadata, deck lists, sideboard plans, the event log and the game state at the
+//! start of every turn. `csv` writes the games, turns, plays and mulligans of a
+//! replay as CSV tables for spreadsheets and notebooks.
+//!
+//! Both read the replay one chunk at a time and write as they go, so exporting a
+//! long session does not hold its events in memory.
+
+pub mod csv;
+pub mod json;
+
+use crate::common::error::ExportError;
+use crate::replay::format::REPLAY_EXTENSION;
+use crate::replay::ReplayReader;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// JSON Schema (draft 2020-12) of the JSON export
+pub const JSON_SCHEMA: &str = include_str!("replay_export.schema.json");
+
+/// Version of the JSON export layout, bumped when `JSON_SCHEMA` changes incompatibly
+pub const EXPORT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// One JSON document
+    Json,
+    /// A directory of CSV tables
+    Csv,
+}
+
+/// Outcome of exporting one replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportReport {
+    /// Files written
+    pub files: Vec<PathBuf>,
+    /// Events exported
+    pub events: u64,
+}
+
+/// Default export location next to the replay: `<name>.json` or a `<name>_csv` directory
+pub fn default_output(path: &Path, format: ExportFormat) -> PathBuf {
+    let stem = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| {
+            n.strip_suffix(&format!(".{}", REPLAY_EXTENSION))
+                .unwrap_or(n)
+        })
+        .unwrap_or("replay");
+    match format {
+        ExportFormat::Json => path.with_file_name(format!("{}.json", stem)),
+        ExportFormat::Csv => path.with_file_name(format!("{}_csv", stem)),
+    }
+}
+
+/// Export a replay file
+///
+/// # Arguments
+/// * `path` - Replay to export
+/// * `format` - JSON document or CSV tables
+/// * `output` - JSON file, or directory for the CSV tables (created if missing)
+///
+/// # Returns
+/// The files written and the number of events exported
+pub fn export_file(
+    path: &Path,
+    format: ExportFormat,
+    output: &Path,
+) -> Result<ExportReport, ExportError> {
+    let mut reader = ReplayReader::open(path)?;
+    match format {
+        ExportFormat::Json => {
+            let mut out = BufWriter::new(File::create(output)?);
+            let events = json::write_json(&mut reader, &mut out)?;
+            out.into_inner()
+                .map_err(|e| ExportError::Io(e.into_error()))?;
+            Ok(ExportReport {
+                files: vec![output.to_path_buf()],
+                events,
+            })
+        }
+        ExportFormat::Csv => csv::write_csv_dir(&mut reader, output),
+    }
+}
@@ -0,0 +1,107 @@
+use crate::game::lifecycle::Match;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// MTGO catalog id -> number of copies
+pub type CardCounts = BTreeMap<u32, u32>;
+
+/// A deck registered for one game: maindeck, sideboard and companion
+///
+/// Cards are MTGO catalog ids; names are resolved from the card database when
+/// displayed.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Deck {
+    pub maindeck: CardCounts,
+    /// Sideboard, including the companion if there is one
+    pub sideboard: CardCounts,
+    pub companion: Option<u32>,
+}
+
+impl Deck {
+    /// Build a deck from the one-entry-per-copy id lists used on the wire
+    pub fn from_card_lists(maindeck: &[u32], sideboard: &[u32], companion: Option<u32>) -> Self {
+        Self {
+            maindeck: count_cards(maindeck),
+            sideboard: count_cards(sideboard),
+            companion,
+        }
+    }
+
+    pub fn maindeck_size(&self) -> u32 {
+        self.maindeck.values().sum()
+    }
+
+    pub fn sideboard_size(&self) -> u32 {
+        self.sideboard.values().sum()
+    }
+
+    /// Every card registered, maindeck and sideboard together
+    pub fn pool(&self) -> CardCounts {
+        let mut pool = self.maindeck.clone();
+        for (&card, &count) in &self.sideboard {
+            *pool.entry(card).or_insert(0) += count;
+        }
+        pool
+    }
+
+    pub fn contains(&self, card_id: u32) -> bool {
+        self.maindeck.contains_key(&card_id) || self.sideboard.contains_key(&card_id)
+    }
+}
+
+/// Count the copies in a one-entry-per-copy id list
+pub fn count_cards(cards: &[u32]) -> CardCounts {
+    let mut counts = CardCounts::new();
+    for &card in cards {
+        *counts.entry(card).or_insert(0) += 1;
+    }
+    counts
+}
+
+/// A deck list the user attached to a match as the list they meant to play
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IntendedDeck {
+    /// Deck name (the imported file's name)
+    pub name: String,
+    pub deck: Deck,
+    pub imported_at: DateTime<Utc>,
+}
+
+/// The capturing user's deck in a match (STAT-005)
+///
+/// # Arguments
+/// * `record` - Match to read
+/// * `game_number` - Game whose registration to use; None for the first captured
+///   registration (the pre-sideboard list)
+pub fn own_deck(record: &Match, game_number: Option<u8>) -> Option<&Deck> {
+    match game_number {
+        Some(number) => record
+            .games
+            .iter()
+            .find(|g| g.number == number)
+            .and_then(|g| g.deck.as_ref()),
+        None => record.games.iter().find_map(|g| g.deck.as_ref()),
+    }
+}
+
+/// Partial opponent deck from the cards they revealed
+///
+/// Each card counts with the most copies seen in any single game, since the same
+/// physical card is a different object in every game. Sideboard cards brought in
+/// for later games cannot be told apart from maindeck cards, so everything is
+/// listed as maindeck.
+pub fn opponent_deck(record: &Match) -> Deck {
+    let mut seen = CardCounts::new();
+    for game in &record.games {
+        for (&card, &count) in &game.opponent_cards {
+            let entry = seen.entry(card).or_insert(0);
+            *entry = (*entry).max(count);
+        }
+    }
+    Deck {
+        maindeck: seen,
+        sideboard: CardCounts::new(),
+        companion: None,
+    }
+}
@@ -0,0 +1,465 @@
+use crate::common::error::GameError;
+use crate::game::deck::Deck;
+use crate::game::event::{GameEvent, GameEventKind, GameSnapshot, GameUpdate};
+use crate::game::messages::{self, GameMessage};
+use crate::game::model::{GameObject, GameState, ObjectId, Player, PlayerId, Zone};
+use crate::protocol::frame::Frame;
+use crate::protocol::schema::DecodedMessage;
+use tracing::{debug, info};
+
+/// Events between periodic snapshots (in addition to one per turn)
+///
+/// Bounds how many events a replay has to re-apply when seeking to an arbitrary point.
+pub const SNAPSHOT_INTERVAL: u64 = 250;
+
+/// Reconstructs game state from decoded MTGO messages
+///
+/// Each game message is applied to the current `GameState` and turned into zero or
+/// more `GameEvent`s describing the changes that actually happened; repeated state
+/// messages that change nothing produce no events. Snapshots of the full state are
+/// emitted at game start, at every new turn, at game end and every
+/// `SNAPSHOT_INTERVAL` events.
+pub struct GameEngine {
+    state: GameState,
+    next_seq: u64,
+    events_since_snapshot: u64,
+}
+
+impl Default for GameEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GameEngine {
+    pub fn new() -> Self {
+        Self {
+            state: GameState::default(),
+            next_seq: 0,
+            events_since_snapshot: 0,
+        }
+    }
+
+    /// Current reconstructed state
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    /// Number of events emitted so far
+    pub fn event_count(&self) -> u64 {
+        self.next_seq
+    }
+
+    /// Apply a decoded message
+    ///
+    /// # Arguments
+    /// * `frame` - Frame the message was decoded from (for ordering and timestamps)
+    /// * `message` - Decoded message; messages that are not game messages are ignored
+    ///
+    /// # Returns
+    /// Events (and possibly a snapshot) produced by the message
+    /// Err(GameError) if a game message is missing required fields
+    pub fn apply(
+        &mut self,
+        frame: &Frame,
+        message: &DecodedMessage,
+    ) -> Result<GameUpdate, GameError> {
+        let Some(game_message) = messages::parse(message)? else {
+            return Ok(GameUpdate::default());
+        };
+
+        let mut changes = Vec::new();
+        let snapshot_due = self.apply_message(game_message, &mut changes);
+
+        let events: Vec<GameEvent> = changes
+            .into_iter()
+            .map(|kind| {
+                let event = GameEvent {
+                    seq: self.next_seq,
+                    game_id: self.state.game_id,
+                    frame_index: frame.index,
+                    timestamp: frame.timestamp,
+                    kind,
+                };
+                self.next_seq += 1;
+                event
+            })
+            .collect();
+
+        self.events_since_snapshot += events.len() as u64;
+        let snapshot = if !events.is_empty()
+            && (snapshot_due || self.events_since_snapshot >= SNAPSHOT_INTERVAL)
+        {
+            self.events_since_snapshot = 0;
+            Some(self.snapshot(frame))
+        } else {
+            None
+        };
+
+        Ok(GameUpdate { events, snapshot })
+    }
+
+    /// Snapshot of the current state as of the last emitted event
+    pub fn snapshot(&self, frame: &Frame) -> GameSnapshot {
+        GameSnapshot {
+            seq: self.next_seq.saturating_sub(1),
+            frame_index: frame.index,
+            timestamp: frame.timestamp,
+            state: self.state.clone(),
+        }
+    }
+
+    /// Apply one game message, pushing the resulting changes
+    ///
+    /// # Returns
+    /// true if the message starts a new game, a new turn or ends the game (snapshot points)
+    fn apply_message(&mut self, message: GameMessage, changes: &mut Vec<GameEventKind>) -> bool {
+        match message {
+            GameMessage::GameStarted {
+                game_id,
+                starting_player,
+            } => {
+                if self.state.game_id.is_some() && !self.state.ended {
+                    info!(
+                        "Game {:?} superseded by game {} before it ended",
+                        self.state.game_id, game_id
+                    );
+                }
+                self.state = GameState::new(game_id);
+                self.state.starting_player = starting_player;
+                changes.push(GameEventKind::GameStarted {
+                    game_id,
+                    starting_player,
+                });
+                true
+            }
+
+            GameMessage::PlayerJoined {
+                player,
+                seat,
+                name,
+                life,
+            } => {
+                let (zone_sizes, mulligans) = self
+                    .state
+                    .players
+                    .remove(&player)
+                    .map(|p| (p.zone_sizes, p.mulligans))
+                    .unwrap_or_default();
+                self.state.players.insert(
+                    player,
+                    Player {
+                        id: player,
+                        seat,
+                        name: name.clone(),
+                        life,
+                        mulligans,
+                        zone_sizes,
+                    },
+                );
+                changes.push(GameEventKind::PlayerJoined {
+                    player,
+                    seat,
+                    name,
+                    life,
+                });
+                false
+            }
+
+            GameMessage::LifeTotal { player, life } => {
+                let entry = self.player_mut(player);
+                if entry.life != life {
+                    let from = std::mem::replace(&mut entry.life, life);
+                    changes.push(GameEventKind::LifeChanged {
+                        player,
+                        from,
+                        to: life,
+                    });
+                }
+                false
+            }
+
+            GameMessage::ZoneChange {
+                object,
+                card_id,
+                owner,
+                controller,
+                from,
+                to,
+            } => {
+                self.move_object(object, card_id, owner, controller, from, to, changes);
+                false
+            }
+
+            GameMessage::ZoneSize { player, zone, size } => {
+                let entry = self.player_mut(player);
+                if entry.zone_sizes.insert(zone, size) != Some(size) {
+                    changes.push(GameEventKind::ZoneSizeChanged { player, zone, size });
+                }
+                false
+            }
+
+            GameMessage::ObjectState {
+                object,
+                tapped,
+                face_down,
+                controller,
+            } => {
+                let Some(entry) = self.state.objects.get_mut(&object) else {
+                    debug!("State update for unknown object {}", object);
+                    return false;
+                };
+                if entry.tapped != tapped {
+                    entry.tapped = tapped;
+                    changes.push(GameEventKind::Tapped { object, tapped });
+                }
+                if entry.face_down != face_down {
+                    entry.face_down = face_down;
+                    changes.push(GameEventKind::FaceDownChanged { object, face_down });
+                }
+                if entry.controller != controller {
+                    let from = std::mem::replace(&mut entry.controller, controller);
+                    changes.push(GameEventKind::ControlChanged {
+                        object,
+                        from,
+                        to: controller,
+                    });
+                }
+                false
+            }
+
+            GameMessage::Counters {
+                object,
+                counter,
+                count,
+            } => {
+                let Some(entry) = self.state.objects.get_mut(&object) else {
+                    debug!("Counter update for unknown object {}", object);
+                    return false;
+                };
+                let from = entry.counters.get(&counter).copied().unwrap_or(0);
+                if from != count {
+                    if count == 0 {
+                        entry.counters.remove(&counter);
+                    } else {
+                        entry.counters.insert(counter.clone(), count);
+                    }
+                    changes.push(GameEventKind::CountersChanged {
+                        object,
+                        counter,
+                        from,
+                        to: count,
+                    });
+                }
+                false
+            }
+
+            GameMessage::Attachment {
+                object,
+                attached_to,
+            } => {
+                let Some(entry) = self.state.objects.get_mut(&object) else {
+                    debug!("Attachment update for unknown object {}", object);
+                    return false;
+                };
+                if entry.attached_to != attached_to {
+                    entry.attached_to = attached_to;
+                    changes.push(GameEventKind::Attached {
+                        object,
+                        to: attached_to,
+                    });
+                }
+                false
+            }
+
+            GameMessage::TurnStep {
+                turn,
+                active_player,
+                step,
+            } => {
+                let new_turn = turn != self.state.turn.number;
+                if new_turn {
+                    self.state.turn.number = turn;
+                    self.state.turn.active_player = Some(active_player);
+                    changes.push(GameEventKind::TurnStarted {
+                        turn,
+                        active_player,
+                    });
+                }
+                if new_turn || self.state.turn.step != Some(step) {
+                    self.state.turn.step = Some(step);
+                    self.state.turn.phase = Some(step.phase());
+                    changes.push(GameEventKind::StepChanged {
+                        turn,
+                        phase: step.phase(),
+                        step,
+                    });
+                }
+                new_turn
+            }
+
+            GameMessage::Priority { player } => {
+                if self.state.priority != Some(player) {
+                    self.state.priority = Some(player);
+                    changes.push(GameEventKind::PriorityChanged { player });
+                }
+                false
+            }
+
+            GameMessage::GameEnded {
+                game_id,
+                winner,
+                reason,
+            } => {
+                if self.state.ended && self.state.game_id == Some(game_id) {
+                    return false;
+                }
+                self.state.ended = true;
+                self.state.winner = winner;
+                self.state.priority = None;
+                changes.push(GameEventKind::GameEnded {
+                    game_id,
+                    winner,
+                    reason,
+                });
+                true
+            }
+
+            // Match boundaries do not change the game state; they are passed through
+            // for lifecycle tracking
+            GameMessage::MatchStarted {
+                match_id,
+                format,
+                event_type,
+                best_of,
+                local_player,
+                opponent,
+            } => {
+                changes.push(GameEventKind::MatchStarted {
+                    match_id,
+                    format,
+                    event_type,
+                    best_of,
+                    local_player,
+                    opponent,
+                });
+                false
+            }
+
+            GameMessage::Mulligan { player, count } => {
+                let entry = self.player_mut(player);
+                if entry.mulligans != count {
+                    entry.mulligans = count;
+                    changes.push(GameEventKind::Mulligan { player, count });
+                }
+                false
+            }
+
+            GameMessage::MatchEnded { match_id, reason } => {
+                changes.push(GameEventKind::MatchEnded { match_id, reason });
+                false
+            }
+
+            GameMessage::DeckSubmitted {
+                player,
+                maindeck,
+                sideboard,
+                companion,
+            } => {
+                changes.push(GameEventKind::DeckSubmitted {
+                    player,
+                    deck: Deck::from_card_lists(&maindeck, &sideboard, companion),
+                });
+                false
+            }
+        }
+    }
+
+    /// Player entry, created as a placeholder if the server has not introduced it yet
+    fn player_mut(&mut self, id: PlayerId) -> &mut Player {
+        self.state.player_entry(id)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn move_object(
+        &mut self,
+        object: ObjectId,
+        card_id: Option<u32>,
+        owner: PlayerId,
+        controller: PlayerId,
+        from: Option<Zone>,
+        to: Zone,
+        changes: &mut Vec<GameEventKind>,
+    ) {
+        let zone_order = self.state.next_zone_order;
+        self.state.next_zone_order += 1;
+
+        let previous = self.state.objects.get(&object).map(|o| o.zone).or(from);
+        let entry = self
+            .state
+            .objects
+            .entry(object)
+            .or_insert_with(|| GameObject {
+                id: object,
+                card_id,
+                owner,
+                controller,
+                zone: to,
+                tapped: false,
+                face_down: false,
+                counters: Default::default(),
+                attached_to: None,
+                zone_order,
+            });
+
+        // A card seen earlier may be revealed (or hidden again) by this move
+        entry.card_id = card_id.or(if to.is_hidden() { None } else { entry.card_id });
+        entry.owner = owner;
+        entry.controller = controller;
+        entry.zone = to;
+        entry.zone_order = zone_order;
+
+        // A permanent leaving the battlefield becomes a new object: untapped, no
+        // counters, no longer attached, and anything attached to it falls off
+        let left_battlefield = previous == Some(Zone::Battlefield) && to != Zone::Battlefield;
+        if left_battlefield {
+            entry.tapped = false;
+            entry.counters.clear();
+            entry.attached_to = None;
+        }
+        let card_id = entry.card_id;
+
+        // Keep reported sizes of owned zones in step with the move
+        if let Some(from) = previous.filter(|z| !z.is_shared() && *z != to) {
+            if let Some(size) = self.player_mut(owner).zone_sizes.get_mut(&from) {
+                *size = size.saturating_sub(1);
+            }
+        }
+        if previous != Some(to) && !to.is_shared() {
+            if let Some(size) = self.player_mut(owner).zone_sizes.get_mut(&to) {
+                *size += 1;
+            }
+        }
+
+        changes.push(GameEventKind::ZoneChanged {
+            object,
+            card_id,
+            owner,
+            controller,
+            from: previous,
+            to,
+        });
+
+        if left_battlefield {
+            for other in self.state.objects.values_mut() {
+                if other.attached_to == Some(object) {
+                    other.attached_to = None;
+                    changes.push(GameEventKind::Attached {
+                        object: other.id,
+                        to: None,
+                    });
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use crate::game::deck::Deck;
+use crate::game::model::{GameState, ObjectId, Phase, PlayerId, Step, Zone};
+use serde::{Deserialize, Serialize};
+
+/// What changed in the game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum GameEventKind {
+    GameStarted {
+        game_id: u32,
+        starting_player: Option<PlayerId>,
+    },
+    PlayerJoined {
+        player: PlayerId,
+        seat: u8,
+        name: String,
+        life: i64,
+    },
+    LifeChanged {
+        player: PlayerId,
+        from: i64,
+        to: i64,
+    },
+    ZoneChanged {
+        object: ObjectId,
+        card_id: Option<u32>,
+        owner: PlayerId,
+        controller: PlayerId,
+        /// None when the object was first seen entering `to`
+        from: Option<Zone>,
+        to: Zone,
+    },
+    ZoneSizeChanged {
+        player: PlayerId,
+        zone: Zone,
+        size: u32,
+    },
+    Tapped {
+        object: ObjectId,
+        tapped: bool,
+    },
+    FaceDownChanged {
+        object: ObjectId,
+        face_down: bool,
+    },
+    ControlChanged {
+        object: ObjectId,
+        from: PlayerId,
+        to: PlayerId,
+    },
+    CountersChanged {
+        object: ObjectId,
+        counter: String,
+        from: i64,
+        to: i64,
+    },
+    Attached {
+        object: ObjectId,
+        /// None when the object became unattached
+        to: Option<ObjectId>,
+    },
+    TurnStarted {
+        turn: u32,
+        active_player: PlayerId,
+    },
+    StepChanged {
+        turn: u32,
+        phase: Phase,
+        step: Step,
+    },
+    PriorityChanged {
+        player: PlayerId,
+    },
+    GameEnded {
+        game_id: u32,
+        winner: Option<PlayerId>,
+        reason: String,
+    },
+    /// A match (one or more games against the same opponent) began
+    MatchStarted {
+        match_id: u32,
+        format: String,
+        /// Event kind as reported by the server (e.g. "league", "challenge", "2-man")
+        event_type: String,
+        best_of: u8,
+        /// Screen name of the capturing user
+        local_player: String,
+        opponent: String,
+    },
+    Mulligan {
+        player: PlayerId,
+        /// Mulligans taken so far this game
+        count: u8,
+    },
+    MatchEnded {
+        match_id: u32,
+        reason: String,
+    },
+    /// Deck registered for the upcoming game (only sent for the local player)
+    DeckSubmitted {
+        player: PlayerId,
+        deck: Deck,
+    },
+}
+
+impl GameEventKind {
+    /// Whether this is a card being cast or put onto the battlefield from another
+    /// zone, rather than a spell resolving or a permanent moving within play
+    pub fn is_play(&self) -> bool {
+        match self {
+            GameEventKind::ZoneChanged { from, to, .. } => {
+                to.is_shared() && !from.is_some_and(|zone| zone.is_shared())
+            }
+            _ => false,
+        }
+    }
+}
+
+/// One entry of the ordered game event stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameEvent {
+    /// Position in the event stream of the capture session, starting at 0
+    pub seq: u64,
+    pub game_id: Option<u32>,
+    /// Frame the event was derived from
+    pub frame_index: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    #[serde(flatten)]
+    pub kind: GameEventKind,
+}
+
+/// Full game state after a given event, for seeking without replaying from the start
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    /// Sequence number of the last event applied to `state`
+    pub seq: u64,
+    pub frame_index: u64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub state: GameState,
+}
+
+/// Output of applying one decoded message
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameUpdate {
+    pub events: Vec<GameEvent>,
+    /// Present when a snapshot became due after these events
+    pub snapshot: Option<GameSnapshot>,
+}
+
+impl GameUpdate {
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty() && self.snapshot.is_none()
+    }
+}
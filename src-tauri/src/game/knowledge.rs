@@ -0,0 +1,192 @@
+use crate::game::event::{GameEvent, GameEventKind};
+use crate::game::model::{GameState, ObjectId, PlayerId, Zone};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Which player's knowledge a replay is shown with
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ViewMode {
+    /// What the capturing player knew at the time
+    #[default]
+    AsPlayed,
+    /// Every card the replay identifies at any point, e.g. the opponent's hand
+    /// revealed at game end
+    FullInformation,
+    /// What the opponent knew at the time, as far as the replay shows it
+    OpponentPerspective,
+}
+
+/// Why a card is shown face up (or not) to the viewer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum Provenance {
+    /// In a zone every player sees
+    Public,
+    /// In the viewer's own hand
+    Owner,
+    /// In a hidden zone but known to the viewer since event `seq`: the card went
+    /// there from a public zone, or was revealed
+    Revealed { seq: u64 },
+    /// Unknown to the viewer at the time; identified by event `seq`, earlier or
+    /// later in the replay (full information only)
+    Identified { seq: u64 },
+    /// Unknown to the viewer
+    Hidden,
+}
+
+/// One zone change of an object
+struct Move {
+    /// Cursor position after the change
+    position: usize,
+    seq: u64,
+    from: Option<Zone>,
+    card_id: Option<u32>,
+}
+
+/// Where each card's identity was seen in a replay, for masking what a viewer
+/// could not have known
+///
+/// Identities follow objects: an object keeps its id across zones for the whole
+/// game, so a card seen once (cast, revealed, shown at game end) is known to be
+/// that card everywhere else it went.
+pub struct KnowledgeIndex {
+    /// Zone changes by (game, object), in order
+    moves: HashMap<(Option<u32>, ObjectId), Vec<Move>>,
+    /// Players who registered a deck, by the cursor position they did so at
+    decks: Vec<(usize, PlayerId)>,
+}
+
+impl KnowledgeIndex {
+    /// # Arguments
+    /// * `events` - Events of the replay in order; position `i + 1` is just after event `i`
+    pub fn new(events: &[GameEvent]) -> Self {
+        let mut moves: HashMap<(Option<u32>, ObjectId), Vec<Move>> = HashMap::new();
+        let mut decks = Vec::new();
+        for (i, event) in events.iter().enumerate() {
+            match &event.kind {
+                GameEventKind::ZoneChanged {
+                    object,
+                    card_id,
+                    from,
+                    ..
+                } => moves
+                    .entry((event.game_id, *object))
+                    .or_default()
+                    .push(Move {
+                        position: i + 1,
+                        seq: event.seq,
+                        from: *from,
+                        card_id: *card_id,
+                    }),
+                GameEventKind::DeckSubmitted { player, .. } => decks.push((i + 1, *player)),
+                _ => {}
+            }
+        }
+        Self { moves, decks }
+    }
+
+    /// The capturing player's id at a position
+    ///
+    /// # Arguments
+    /// * `state` - State at `position`
+    /// * `local_player` - Screen name of the capturing player, if known
+    pub fn local_player(
+        &self,
+        state: &GameState,
+        position: usize,
+        local_player: Option<&str>,
+    ) -> Option<PlayerId> {
+        local_player
+            .and_then(|name| {
+                state
+                    .players
+                    .values()
+                    .find(|player| player.name.eq_ignore_ascii_case(name))
+                    .map(|player| player.id)
+            })
+            .or_else(|| {
+                // Only the capturing player's deck is sent to their client
+                self.decks
+                    .iter()
+                    .rev()
+                    .find(|(at, _)| *at <= position)
+                    .map(|(_, player)| *player)
+            })
+    }
+
+    /// A state as one player saw it
+    ///
+    /// # Arguments
+    /// * `state` - State after the first `position` events
+    /// * `viewer` - Player whose knowledge to show, if known
+    /// * `captured` - Whether the viewer is the capturing player, whose client
+    ///   saw the identities in `state`
+    /// * `full` - Also identify cards from anywhere in the replay
+    ///
+    /// # Returns
+    /// The state with unknown cards' identities removed, and why each object is
+    /// shown the way it is
+    pub fn mask(
+        &self,
+        state: &GameState,
+        position: usize,
+        viewer: Option<PlayerId>,
+        captured: bool,
+        full: bool,
+    ) -> (GameState, BTreeMap<ObjectId, Provenance>) {
+        let mut masked = state.clone();
+        let mut provenance = BTreeMap::new();
+        for object in masked.objects.values_mut() {
+            let moves = self
+                .moves
+                .get(&(state.game_id, object.id))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let past = &moves[..moves.partition_point(|m| m.position <= position)];
+            // Last identity seen so far, else the first seen later
+            let seen_before = identity(past.iter().rev());
+            let seen_ever = seen_before.or_else(|| identity(moves[past.len()..].iter()));
+
+            let known = if !object.zone.is_hidden() {
+                object.card_id.map(|id| (id, Provenance::Public))
+            } else if object.zone == Zone::Hand && viewer == Some(object.owner) {
+                // Owners know their hand, whenever the replay learned it
+                object
+                    .card_id
+                    .filter(|_| captured)
+                    .or(seen_ever.map(|(id, _)| id))
+                    .map(|id| (id, Provenance::Owner))
+            } else {
+                let entered_from_public = past
+                    .last()
+                    .filter(|m| m.from.is_some_and(|zone| !zone.is_hidden()))
+                    .and_then(|m| Some((seen_before?.0, Provenance::Revealed { seq: m.seq })));
+                let shown_to_client = object.card_id.filter(|_| captured).map(|id| {
+                    let seq = seen_before
+                        .or(past.last().map(|m| (id, m.seq)))
+                        .map_or(0, |(_, seq)| seq);
+                    (id, Provenance::Revealed { seq })
+                });
+                entered_from_public.or(shown_to_client)
+            };
+            let identified = || {
+                seen_ever
+                    .filter(|_| full)
+                    .map(|(id, seq)| (id, Provenance::Identified { seq }))
+            };
+            let (card_id, source) = match known.or_else(identified) {
+                Some((id, source)) => (Some(id), source),
+                None => (None, Provenance::Hidden),
+            };
+            object.card_id = card_id;
+            provenance.insert(object.id, source);
+        }
+        (masked, provenance)
+    }
+}
+
+/// First card id among moves, with the event that carried it
+fn identity<'a>(mut moves: impl Iterator<Item = &'a Move>) -> Option<(u32, u64)> {
+    moves.find_map(|m| m.card_id.map(|id| (id, m.seq)))
+}
@@ -0,0 +1,570 @@
+use crate::game::deck::{CardCounts, Deck, IntendedDeck};
+use crate::game::event::{GameEvent, GameEventKind};
+use crate::game::model::{GameState, ObjectId, PlayerId};
+use crate::game::sideboard::{self, SideboardChange};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use tracing::{debug, info};
+
+/// Kind of event a match was played in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventType {
+    League,
+    Challenge,
+    /// Two-player single-elimination queue ("2-man")
+    TwoMan,
+    /// Anything else (preliminaries, casual rooms, unknown)
+    Other,
+}
+
+impl EventType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EventType::League => "league",
+            EventType::Challenge => "challenge",
+            EventType::TwoMan => "two_man",
+            EventType::Other => "other",
+        }
+    }
+
+    /// Classify the server's event type string
+    pub fn parse(value: &str) -> EventType {
+        let normalized: String = value
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if normalized.contains("league") {
+            EventType::League
+        } else if normalized.contains("challenge") {
+            EventType::Challenge
+        } else if matches!(
+            normalized.as_str(),
+            "2man" | "twoman" | "2player" | "twoplayer"
+        ) {
+            EventType::TwoMan
+        } else {
+            EventType::Other
+        }
+    }
+}
+
+/// Game or match result from the capturing user's point of view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Win,
+    Loss,
+    Draw,
+}
+
+/// One game of a match
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Game {
+    pub game_id: u32,
+    /// Position in the match, starting at 1
+    pub number: u8,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    /// Whether the capturing user took the first turn; None if not observed
+    pub on_play: Option<bool>,
+    pub mulligans: u8,
+    pub opponent_mulligans: u8,
+    /// Last turn number reached
+    pub turns: u32,
+    /// None while the game is in progress or if the winner could not be attributed
+    pub result: Option<Outcome>,
+    pub end_reason: Option<String>,
+    /// Deck the capturing user registered for this game
+    #[serde(default)]
+    pub deck: Option<Deck>,
+    /// Sideboarding since the previous game (games 2 and later)
+    #[serde(default)]
+    pub sideboard: Option<SideboardChange>,
+    /// Sideboard cards brought into this game (wishes, companion)
+    #[serde(default)]
+    pub outside_cards: Vec<u32>,
+    /// Opponent's cards revealed this game: catalog id -> distinct objects seen
+    #[serde(default)]
+    pub opponent_cards: CardCounts,
+}
+
+/// A match against one opponent, built from the game event stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Match {
+    /// Server match id; 0 for games observed without their match start
+    pub id: u32,
+    pub format: String,
+    pub event_type: EventType,
+    /// Event type exactly as the server reported it
+    pub event_name: String,
+    /// Games needed to win is `best_of / 2 + 1`; 0 if unknown
+    pub best_of: u8,
+    pub local_player: Option<String>,
+    pub opponent: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub games: Vec<Game>,
+    pub result: Option<Outcome>,
+    pub end_reason: Option<String>,
+    /// Capture session the match was recorded in
+    pub session_id: Option<String>,
+    /// Deck list the user tagged the match with (imported from a .dek file)
+    #[serde(default)]
+    pub intended_deck: Option<IntendedDeck>,
+}
+
+impl Match {
+    /// Stable identifier for storage, unique even for matches without a server id
+    pub fn record_id(&self) -> String {
+        format!("{}-{}", self.started_at.format("%Y%m%dT%H%M%S%3f"), self.id)
+    }
+
+    pub fn wins(&self) -> usize {
+        self.count(Outcome::Win)
+    }
+
+    pub fn losses(&self) -> usize {
+        self.count(Outcome::Loss)
+    }
+
+    fn count(&self, outcome: Outcome) -> usize {
+        self.games
+            .iter()
+            .filter(|g| g.result == Some(outcome))
+            .count()
+    }
+
+    /// Whether either player has won enough games to take the match
+    pub fn is_decided(&self) -> bool {
+        if self.best_of == 0 {
+            return false;
+        }
+        let needed = usize::from(self.best_of / 2 + 1);
+        self.wins() >= needed || self.losses() >= needed
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.ended_at.is_some()
+    }
+
+    /// Match result from the game results, if any game was attributed
+    fn tally(&self) -> Option<Outcome> {
+        let (wins, losses) = (self.wins(), self.losses());
+        if wins + losses == 0 && self.count(Outcome::Draw) == 0 {
+            return None;
+        }
+        Some(match wins.cmp(&losses) {
+            std::cmp::Ordering::Greater => Outcome::Win,
+            std::cmp::Ordering::Less => Outcome::Loss,
+            std::cmp::Ordering::Equal => Outcome::Draw,
+        })
+    }
+}
+
+/// Match and game boundaries, as emitted to the frontend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LifecycleEvent {
+    MatchStarted {
+        #[serde(rename = "match")]
+        record: Match,
+    },
+    GameStarted {
+        match_id: u32,
+        game: Game,
+    },
+    GameEnded {
+        match_id: u32,
+        game: Game,
+    },
+    /// A game ended and the match continues: players are sideboarding
+    SideboardingStarted {
+        match_id: u32,
+        next_game: u8,
+    },
+    /// The deck for a game was registered and compared with the previous game's
+    SideboardDetected {
+        match_id: u32,
+        game_number: u8,
+        change: SideboardChange,
+    },
+    MatchEnded {
+        #[serde(rename = "match")]
+        record: Match,
+    },
+}
+
+impl LifecycleEvent {
+    /// Whether the event closes a game or match (the record should be persisted)
+    pub fn closes_record(&self) -> bool {
+        matches!(
+            self,
+            LifecycleEvent::GameEnded { .. } | LifecycleEvent::MatchEnded { .. }
+        )
+    }
+}
+
+/// Detects match, game and sideboarding boundaries in the game event stream
+///
+/// Results are attributed by matching the capturing user's screen name (from the
+/// match start) to the seated players. Games seen without a match start (capture
+/// started mid-match) are collected into a match with id 0 so they are not lost.
+pub struct MatchTracker {
+    session_id: Option<String>,
+    current: Option<Match>,
+    /// The capturing user's player id in the current game
+    local_id: Option<PlayerId>,
+    /// Deck registered before its game started
+    pending_deck: Option<Deck>,
+    /// Opponent objects already counted in the current game
+    opponent_objects: HashSet<ObjectId>,
+}
+
+impl MatchTracker {
+    pub fn new(session_id: Option<String>) -> Self {
+        Self {
+            session_id,
+            current: None,
+            local_id: None,
+            pending_deck: None,
+            opponent_objects: HashSet::new(),
+        }
+    }
+
+    /// Match in progress (or the last match, once closed)
+    pub fn current(&self) -> Option<&Match> {
+        self.current.as_ref()
+    }
+
+    /// Apply one game event
+    ///
+    /// # Arguments
+    /// * `event` - Next event of the stream
+    /// * `state` - Game state after the event
+    ///
+    /// # Returns
+    /// Lifecycle boundaries the event crossed, in order
+    pub fn apply(&mut self, event: &GameEvent, state: &GameState) -> Vec<LifecycleEvent> {
+        let mut out = Vec::new();
+        let at = event.timestamp;
+
+        match &event.kind {
+            GameEventKind::MatchStarted {
+                match_id,
+                format,
+                event_type,
+                best_of,
+                local_player,
+                opponent,
+            } => {
+                if let Some(open) = self.current.as_ref().filter(|m| !m.is_closed()) {
+                    if open.id == *match_id {
+                        return out;
+                    }
+                    info!(
+                        "Match {} superseded by match {} before it ended",
+                        open.id, match_id
+                    );
+                    self.close_match(at, "superseded".to_string(), &mut out);
+                }
+                let record = Match {
+                    id: *match_id,
+                    format: format.clone(),
+                    event_type: EventType::parse(event_type),
+                    event_name: event_type.clone(),
+                    best_of: *best_of,
+                    local_player: non_empty(local_player),
+                    opponent: non_empty(opponent),
+                    started_at: at,
+                    ended_at: None,
+                    games: Vec::new(),
+                    result: None,
+                    end_reason: None,
+                    session_id: self.session_id.clone(),
+                    intended_deck: None,
+                };
+                self.current = Some(record.clone());
+                self.local_id = None;
+                self.pending_deck = None;
+                out.push(LifecycleEvent::MatchStarted { record });
+            }
+
+            GameEventKind::GameStarted {
+                game_id,
+                starting_player,
+            } => {
+                self.close_open_game(at, None, "superseded".to_string(), state);
+                let record = self.open_match(at);
+                let number = u8::try_from(record.games.len() + 1).unwrap_or(u8::MAX);
+                let game = Game {
+                    game_id: *game_id,
+                    number,
+                    started_at: at,
+                    ended_at: None,
+                    on_play: None,
+                    mulligans: 0,
+                    opponent_mulligans: 0,
+                    turns: 0,
+                    result: None,
+                    end_reason: None,
+                    deck: None,
+                    sideboard: None,
+                    outside_cards: Vec::new(),
+                    opponent_cards: CardCounts::new(),
+                };
+                record.games.push(game.clone());
+                let match_id = record.id;
+                self.local_id = None;
+                self.opponent_objects.clear();
+                self.set_starting_player(*starting_player);
+                out.push(LifecycleEvent::GameStarted { match_id, game });
+                if let Some(deck) = self.pending_deck.take() {
+                    self.attach_deck(deck, &mut out);
+                }
+            }
+
+            GameEventKind::PlayerJoined { player, name, .. } => {
+                let is_local = self
+                    .current
+                    .as_ref()
+                    .and_then(|m| m.local_player.as_deref())
+                    .is_some_and(|local| local.eq_ignore_ascii_case(name));
+                if is_local {
+                    self.local_id = Some(*player);
+                    self.set_starting_player(state.starting_player);
+                } else if let Some(record) = self.current.as_mut().filter(|m| m.opponent.is_none())
+                {
+                    // Matches observed without their start learn the opponent here
+                    if record.local_player.is_some() {
+                        record.opponent = non_empty(name);
+                    }
+                }
+            }
+
+            GameEventKind::DeckSubmitted { player, deck } => {
+                if self.local_id.is_some_and(|local| local != *player) {
+                    debug!("Ignoring deck registered by player {}", player);
+                    return out;
+                }
+                let game_has_deck = self.open_game_mut().map(|g| g.deck.is_some());
+                match game_has_deck {
+                    Some(false) => self.attach_deck(deck.clone(), &mut out),
+                    // Sent before the next game starts (after sideboarding)
+                    _ => self.pending_deck = Some(deck.clone()),
+                }
+            }
+
+            GameEventKind::ZoneChanged {
+                object,
+                card_id: Some(card_id),
+                owner,
+                ..
+            } => {
+                let Some(local) = self.local_id else {
+                    return out;
+                };
+                let newly_seen = local != *owner && self.opponent_objects.insert(*object);
+                let Some(game) = self.open_game_mut() else {
+                    return out;
+                };
+                if local == *owner {
+                    let from_sideboard = game.deck.as_ref().is_some_and(|deck| {
+                        deck.sideboard.contains_key(card_id) && !deck.maindeck.contains_key(card_id)
+                    });
+                    if from_sideboard && !game.outside_cards.contains(card_id) {
+                        game.outside_cards.push(*card_id);
+                    }
+                } else if newly_seen {
+                    *game.opponent_cards.entry(*card_id).or_insert(0) += 1;
+                }
+            }
+
+            GameEventKind::Mulligan { player, count } => {
+                let is_local = self.local_id == Some(*player);
+                if let Some(game) = self.open_game_mut() {
+                    if is_local {
+                        game.mulligans = *count;
+                    } else {
+                        game.opponent_mulligans = *count;
+                    }
+                }
+            }
+
+            GameEventKind::TurnStarted {
+                turn,
+                active_player,
+            } => {
+                if *turn == 1 {
+                    self.set_starting_player(Some(*active_player));
+                }
+                if let Some(game) = self.open_game_mut() {
+                    game.turns = game.turns.max(*turn);
+                }
+            }
+
+            GameEventKind::GameEnded { winner, reason, .. } => {
+                // The server reports no winner for drawn games
+                let result = match (winner, self.local_id) {
+                    (None, _) => Some(Outcome::Draw),
+                    (Some(winner), Some(local)) if *winner == local => Some(Outcome::Win),
+                    (Some(_), Some(_)) => Some(Outcome::Loss),
+                    (Some(_), None) => None,
+                };
+                let closed = self.close_open_game(at, result, reason.clone(), state);
+                if let (Some(game), Some(record)) = (closed, self.current.as_ref()) {
+                    let match_id = record.id;
+                    let decided = record.is_decided();
+                    let more_games =
+                        record.best_of == 0 || usize::from(record.best_of) > record.games.len();
+                    let next_game = game.number.saturating_add(1);
+                    out.push(LifecycleEvent::GameEnded { match_id, game });
+
+                    if decided {
+                        self.close_match(at, "decided".to_string(), &mut out);
+                    } else if more_games {
+                        out.push(LifecycleEvent::SideboardingStarted {
+                            match_id,
+                            next_game,
+                        });
+                    }
+                }
+            }
+
+            GameEventKind::MatchEnded { match_id, reason } => {
+                let open = self
+                    .current
+                    .as_ref()
+                    .is_some_and(|m| !m.is_closed() && (m.id == *match_id || m.id == 0));
+                if open {
+                    self.close_open_game(at, None, reason.clone(), state);
+                    self.close_match(at, reason.clone(), &mut out);
+                } else {
+                    debug!("Match end for unknown or closed match {}", match_id);
+                }
+            }
+
+            _ => {}
+        }
+
+        out
+    }
+
+    /// Open match, starting an anonymous one if the match start was not observed
+    fn open_match(&mut self, at: DateTime<Utc>) -> &mut Match {
+        if !matches!(&self.current, Some(record) if !record.is_closed()) {
+            debug!("Game started without an open match; tracking it in an unnamed match");
+            self.current = None;
+        }
+        self.current.get_or_insert_with(|| Match {
+            id: 0,
+            format: String::new(),
+            event_type: EventType::Other,
+            event_name: String::new(),
+            best_of: 0,
+            local_player: None,
+            opponent: None,
+            started_at: at,
+            ended_at: None,
+            games: Vec::new(),
+            result: None,
+            end_reason: None,
+            session_id: self.session_id.clone(),
+            intended_deck: None,
+        })
+    }
+
+    /// Attach the registered deck to the open game and detect its sideboarding
+    fn attach_deck(&mut self, deck: Deck, out: &mut Vec<LifecycleEvent>) {
+        let Some(record) = self.current.as_mut().filter(|m| !m.is_closed()) else {
+            return;
+        };
+        let match_id = record.id;
+        let Some((game, earlier)) = record.games.split_last_mut() else {
+            return;
+        };
+
+        if game.number > 1 {
+            let previous = earlier.last();
+            let change = sideboard::detect(
+                previous.and_then(|g| g.deck.as_ref()),
+                &deck,
+                previous
+                    .map(|g| g.outside_cards.as_slice())
+                    .unwrap_or_default(),
+            );
+            game.sideboard = Some(change.clone());
+            out.push(LifecycleEvent::SideboardDetected {
+                match_id,
+                game_number: game.number,
+                change,
+            });
+        }
+        game.deck = Some(deck);
+    }
+
+    fn open_game_mut(&mut self) -> Option<&mut Game> {
+        self.current
+            .as_mut()
+            .filter(|m| !m.is_closed())?
+            .games
+            .last_mut()
+            .filter(|g| g.ended_at.is_none())
+    }
+
+    fn set_starting_player(&mut self, starting_player: Option<PlayerId>) {
+        let local_id = self.local_id;
+        if let (Some(local), Some(starting)) = (local_id, starting_player) {
+            if let Some(game) = self.open_game_mut() {
+                game.on_play.get_or_insert(local == starting);
+            }
+        }
+    }
+
+    /// Close the game in progress, if any
+    ///
+    /// # Arguments
+    /// * `result` - None for games closed without a reported end (superseded, match ended)
+    ///
+    /// # Returns
+    /// The closed game
+    fn close_open_game(
+        &mut self,
+        at: DateTime<Utc>,
+        result: Option<Outcome>,
+        reason: String,
+        state: &GameState,
+    ) -> Option<Game> {
+        self.set_starting_player(state.starting_player);
+        let game = self.open_game_mut()?;
+        game.ended_at = Some(at);
+        game.end_reason = non_empty(&reason);
+        game.result = result;
+        Some(game.clone())
+    }
+
+    fn close_match(&mut self, at: DateTime<Utc>, reason: String, out: &mut Vec<LifecycleEvent>) {
+        let Some(record) = self.current.as_mut().filter(|m| !m.is_closed()) else {
+            return;
+        };
+        record.ended_at = Some(at);
+        record.end_reason = non_empty(&reason);
+        record.result = record.tally();
+        info!(
+            "Match {} ended {}-{} ({:?})",
+            record.id,
+            record.wins(),
+            record.losses(),
+            record.result
+        );
+        out.push(LifecycleEvent::MatchEnded {
+            record: record.clone(),
+        });
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    (!value.is_empty()).then(|| value.to_string())
+}
@@ -0,0 +1,69 @@
+use crate::common::error::GameError;
+use crate::game::lifecycle::Match;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Path of a match record, rejecting ids that are not plain file names
+///
+/// Record ids arrive from the UI, so they are validated the same way session ids are.
+pub fn record_path(matches_dir: &Path, record_id: &str) -> Result<PathBuf, GameError> {
+    let is_plain = !record_id.is_empty()
+        && record_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if !is_plain {
+        return Err(GameError::MatchNotFound(record_id.to_string()));
+    }
+    Ok(matches_dir.join(format!("{}.json", record_id)))
+}
+
+/// Persist a match record atomically (write to temp file, then rename)
+///
+/// Called whenever a game or the match closes, so a crash loses at most the
+/// game in progress.
+pub fn save_match(matches_dir: &Path, record: &Match) -> Result<(), GameError> {
+    std::fs::create_dir_all(matches_dir)?;
+    let path = record_path(matches_dir, &record.record_id())?;
+    let json =
+        serde_json::to_string_pretty(record).map_err(|e| GameError::CorruptMatch(e.to_string()))?;
+    let tmp = path.with_extension("json.tmp");
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+/// Read one match record
+pub fn load_match(matches_dir: &Path, record_id: &str) -> Result<Match, GameError> {
+    let path = record_path(matches_dir, record_id)?;
+    if !path.exists() {
+        return Err(GameError::MatchNotFound(record_id.to_string()));
+    }
+    let json = std::fs::read_to_string(&path)?;
+    serde_json::from_str(&json)
+        .map_err(|e| GameError::CorruptMatch(format!("{}: {}", record_id, e)))
+}
+
+/// Every stored match record, newest first
+pub fn list_matches(matches_dir: &Path) -> Result<Vec<Match>, GameError> {
+    if !matches_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut matches = Vec::new();
+    for entry in std::fs::read_dir(matches_dir)? {
+        let path = entry?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+        let record = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str::<Match>(&json).map_err(|e| e.to_string()));
+        match record {
+            Ok(record) => matches.push(record),
+            Err(e) => warn!("Skipping match record {}: {}", path.display(), e),
+        }
+    }
+
+    matches.sort_by_key(|m| std::cmp::Reverse(m.started_at));
+    Ok(matches)
+}
@@ -0,0 +1,292 @@
+use crate::common::error::GameError;
+use crate::game::model::{ObjectId, PlayerId, Step, Zone};
+use crate::protocol::schema::{DecodedMessage, FieldValue};
+
+/// Schema message names the game engine understands
+///
+/// Schema files map MTGO wire layouts onto these names and the field names used
+/// below, so the engine is independent of type ids and field order. Messages with
+/// other names are ignored by the engine (they are still archived and decoded).
+pub const GAME_STARTED: &str = "GameStarted";
+pub const PLAYER_JOINED: &str = "PlayerJoined";
+pub const LIFE_TOTAL: &str = "LifeTotal";
+pub const ZONE_CHANGE: &str = "ZoneChange";
+pub const ZONE_SIZE: &str = "ZoneSize";
+pub const OBJECT_STATE: &str = "ObjectState";
+pub const COUNTERS: &str = "Counters";
+pub const ATTACHMENT: &str = "Attachment";
+pub const TURN_STEP: &str = "TurnStep";
+pub const PRIORITY: &str = "Priority";
+pub const GAME_ENDED: &str = "GameEnded";
+pub const MATCH_STARTED: &str = "MatchStarted";
+pub const MULLIGAN: &str = "Mulligan";
+pub const MATCH_ENDED: &str = "MatchEnded";
+pub const DECK_SUBMITTED: &str = "DeckSubmitted";
+
+/// A decoded message translated into a typed game action
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameMessage {
+    GameStarted {
+        game_id: u32,
+        starting_player: Option<PlayerId>,
+    },
+    PlayerJoined {
+        player: PlayerId,
+        seat: u8,
+        name: String,
+        life: i64,
+    },
+    LifeTotal {
+        player: PlayerId,
+        life: i64,
+    },
+    ZoneChange {
+        object: ObjectId,
+        card_id: Option<u32>,
+        owner: PlayerId,
+        controller: PlayerId,
+        from: Option<Zone>,
+        to: Zone,
+    },
+    ZoneSize {
+        player: PlayerId,
+        zone: Zone,
+        size: u32,
+    },
+    ObjectState {
+        object: ObjectId,
+        tapped: bool,
+        face_down: bool,
+        controller: PlayerId,
+    },
+    Counters {
+        object: ObjectId,
+        counter: String,
+        count: i64,
+    },
+    Attachment {
+        object: ObjectId,
+        attached_to: Option<ObjectId>,
+    },
+    TurnStep {
+        turn: u32,
+        active_player: PlayerId,
+        step: Step,
+    },
+    Priority {
+        player: PlayerId,
+    },
+    GameEnded {
+        game_id: u32,
+        winner: Option<PlayerId>,
+        reason: String,
+    },
+    MatchStarted {
+        match_id: u32,
+        format: String,
+        event_type: String,
+        best_of: u8,
+        local_player: String,
+        opponent: String,
+    },
+    Mulligan {
+        player: PlayerId,
+        count: u8,
+    },
+    MatchEnded {
+        match_id: u32,
+        reason: String,
+    },
+    DeckSubmitted {
+        player: PlayerId,
+        /// One catalog id per copy
+        maindeck: Vec<u32>,
+        sideboard: Vec<u32>,
+        companion: Option<u32>,
+    },
+}
+
+/// Zero in an id field means "none" (no starting player yet, no winner, hidden card)
+fn nonzero(value: u32) -> Option<u32> {
+    (value != 0).then_some(value)
+}
+
+/// Typed field access with errors naming the message and field
+struct Fields<'a> {
+    message: &'a DecodedMessage,
+}
+
+impl Fields<'_> {
+    fn missing(&self, field: &str) -> GameError {
+        GameError::MissingField {
+            message: self.message.name.clone(),
+            field: field.to_string(),
+        }
+    }
+
+    fn invalid(&self, field: &str, value: impl ToString) -> GameError {
+        GameError::InvalidValue {
+            message: self.message.name.clone(),
+            field: field.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn u64(&self, field: &str) -> Result<u64, GameError> {
+        self.message.u64(field).ok_or_else(|| self.missing(field))
+    }
+
+    fn u32(&self, field: &str) -> Result<u32, GameError> {
+        let value = self.u64(field)?;
+        u32::try_from(value).map_err(|_| self.invalid(field, value))
+    }
+
+    fn u8(&self, field: &str) -> Result<u8, GameError> {
+        let value = self.u64(field)?;
+        u8::try_from(value).map_err(|_| self.invalid(field, value))
+    }
+
+    fn i64(&self, field: &str) -> Result<i64, GameError> {
+        self.message.i64(field).ok_or_else(|| self.missing(field))
+    }
+
+    fn bool(&self, field: &str) -> Result<bool, GameError> {
+        self.message.bool(field).ok_or_else(|| self.missing(field))
+    }
+
+    fn string(&self, field: &str) -> Result<String, GameError> {
+        self.message
+            .str(field)
+            .map(str::to_string)
+            .ok_or_else(|| self.missing(field))
+    }
+
+    fn u32_array(&self, field: &str) -> Result<Vec<u32>, GameError> {
+        let values = self
+            .message
+            .field(field)
+            .and_then(FieldValue::as_array)
+            .ok_or_else(|| self.missing(field))?;
+        values
+            .iter()
+            .map(|v| {
+                v.as_u64()
+                    .and_then(|n| u32::try_from(n).ok())
+                    .ok_or_else(|| self.invalid(field, format!("{:?}", v)))
+            })
+            .collect()
+    }
+
+    fn optional_u32(&self, field: &str) -> Result<Option<u32>, GameError> {
+        match self.message.field(field) {
+            Some(_) => self.u32(field).map(nonzero),
+            None => Ok(None),
+        }
+    }
+
+    fn zone(&self, field: &str) -> Result<Zone, GameError> {
+        let code = self.u64(field)?;
+        Zone::from_code(code).ok_or_else(|| self.invalid(field, code))
+    }
+
+    fn step(&self, field: &str) -> Result<Step, GameError> {
+        let code = self.u64(field)?;
+        Step::from_code(code).ok_or_else(|| self.invalid(field, code))
+    }
+}
+
+/// Translate a decoded message into a game action
+///
+/// # Returns
+/// Ok(None) for messages that do not describe game state
+/// Err(GameError) if a game message lacks a required field or has an invalid value
+pub fn parse(message: &DecodedMessage) -> Result<Option<GameMessage>, GameError> {
+    let f = Fields { message };
+
+    let parsed = match message.name.as_str() {
+        GAME_STARTED => GameMessage::GameStarted {
+            game_id: f.u32("game_id")?,
+            starting_player: f.optional_u32("starting_player")?,
+        },
+        PLAYER_JOINED => GameMessage::PlayerJoined {
+            player: f.u32("player_id")?,
+            seat: f.u8("seat")?,
+            name: f.string("name")?,
+            life: f.i64("life")?,
+        },
+        LIFE_TOTAL => GameMessage::LifeTotal {
+            player: f.u32("player_id")?,
+            life: f.i64("life")?,
+        },
+        ZONE_CHANGE => {
+            // Objects first seen in a zone (e.g. tokens) carry an out-of-range from_zone
+            let from = f.u64("from_zone").ok().and_then(Zone::from_code);
+            GameMessage::ZoneChange {
+                object: f.u32("object_id")?,
+                card_id: f.optional_u32("card_id")?,
+                owner: f.u32("owner")?,
+                controller: f.u32("controller")?,
+                from,
+                to: f.zone("to_zone")?,
+            }
+        }
+        ZONE_SIZE => GameMessage::ZoneSize {
+            player: f.u32("player_id")?,
+            zone: f.zone("zone")?,
+            size: f.u32("size")?,
+        },
+        OBJECT_STATE => GameMessage::ObjectState {
+            object: f.u32("object_id")?,
+            tapped: f.bool("tapped")?,
+            face_down: f.bool("face_down")?,
+            controller: f.u32("controller")?,
+        },
+        COUNTERS => GameMessage::Counters {
+            object: f.u32("object_id")?,
+            counter: f.string("counter")?,
+            count: f.i64("count")?,
+        },
+        ATTACHMENT => GameMessage::Attachment {
+            object: f.u32("object_id")?,
+            attached_to: f.optional_u32("attached_to")?,
+        },
+        TURN_STEP => GameMessage::TurnStep {
+            turn: f.u32("turn")?,
+            active_player: f.u32("active_player")?,
+            step: f.step("step")?,
+        },
+        PRIORITY => GameMessage::Priority {
+            player: f.u32("player_id")?,
+        },
+        GAME_ENDED => GameMessage::GameEnded {
+            game_id: f.u32("game_id")?,
+            winner: f.optional_u32("winner")?,
+            reason: message.str("reason").unwrap_or_default().to_string(),
+        },
+        MATCH_STARTED => GameMessage::MatchStarted {
+            match_id: f.u32("match_id")?,
+            format: f.string("format")?,
+            event_type: f.string("event_type")?,
+            best_of: f.u8("best_of")?,
+            local_player: f.string("local_player")?,
+            opponent: f.string("opponent")?,
+        },
+        MULLIGAN => GameMessage::Mulligan {
+            player: f.u32("player_id")?,
+            count: f.u8("count")?,
+        },
+        MATCH_ENDED => GameMessage::MatchEnded {
+            match_id: f.u32("match_id")?,
+            reason: message.str("reason").unwrap_or_default().to_string(),
+        },
+        DECK_SUBMITTED => GameMessage::DeckSubmitted {
+            player: f.u32("player_id")?,
+            maindeck: f.u32_array("maindeck")?,
+            sideboard: f.u32_array("sideboard")?,
+            companion: f.optional_u32("companion")?,
+        },
+        _ => return Ok(None),
+    };
+
+    Ok(Some(parsed))
+}
@@ -0,0 +1,10 @@
+pub mod deck;
+pub mod engine;
+pub mod event;
+pub mod knowledge;
+pub mod lifecycle;
+pub mod match_store;
+pub mod messages;
+pub mod model;
+pub mod opening_hand;
+pub mod sideboard;
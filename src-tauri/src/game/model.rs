@@ -0,0 +1,403 @@
+use crate::game::event::GameEventKind;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Server-assigned player identifier
+pub type PlayerId = u32;
+
+/// Server-assigned identifier of a card or token for the duration of a game
+pub type ObjectId = u32;
+
+/// Game zones
+///
+/// Library, hand, graveyard, exile and command zones belong to a player; the
+/// battlefield and the stack are shared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Zone {
+    Library,
+    Hand,
+    Battlefield,
+    Graveyard,
+    Exile,
+    Stack,
+    Command,
+}
+
+impl Zone {
+    /// All zones, in wire code order
+    pub const ALL: [Zone; 7] = [
+        Zone::Library,
+        Zone::Hand,
+        Zone::Battlefield,
+        Zone::Graveyard,
+        Zone::Exile,
+        Zone::Stack,
+        Zone::Command,
+    ];
+
+    /// Map a wire zone code (0 = library ... 6 = command)
+    pub fn from_code(code: u64) -> Option<Zone> {
+        Zone::ALL.get(usize::try_from(code).ok()?).copied()
+    }
+
+    /// Zones whose contents are not visible to every player
+    pub fn is_hidden(self) -> bool {
+        matches!(self, Zone::Library | Zone::Hand)
+    }
+
+    /// Zones shared by all players rather than owned by one
+    pub fn is_shared(self) -> bool {
+        matches!(self, Zone::Battlefield | Zone::Stack)
+    }
+}
+
+/// Turn phases
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Beginning,
+    PrecombatMain,
+    Combat,
+    PostcombatMain,
+    Ending,
+}
+
+/// Turn steps (main phases have a single step of the same name)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    Untap,
+    Upkeep,
+    Draw,
+    PrecombatMain,
+    BeginCombat,
+    DeclareAttackers,
+    DeclareBlockers,
+    CombatDamage,
+    EndCombat,
+    PostcombatMain,
+    End,
+    Cleanup,
+}
+
+impl Step {
+    /// All steps, in turn order (which is also wire code order)
+    pub const ALL: [Step; 12] = [
+        Step::Untap,
+        Step::Upkeep,
+        Step::Draw,
+        Step::PrecombatMain,
+        Step::BeginCombat,
+        Step::DeclareAttackers,
+        Step::DeclareBlockers,
+        Step::CombatDamage,
+        Step::EndCombat,
+        Step::PostcombatMain,
+        Step::End,
+        Step::Cleanup,
+    ];
+
+    /// Map a wire step code (0 = untap ... 11 = cleanup)
+    pub fn from_code(code: u64) -> Option<Step> {
+        Step::ALL.get(usize::try_from(code).ok()?).copied()
+    }
+
+    /// Phase this step belongs to
+    pub fn phase(self) -> Phase {
+        match self {
+            Step::Untap | Step::Upkeep | Step::Draw => Phase::Beginning,
+            Step::PrecombatMain => Phase::PrecombatMain,
+            Step::BeginCombat
+            | Step::DeclareAttackers
+            | Step::DeclareBlockers
+            | Step::CombatDamage
+            | Step::EndCombat => Phase::Combat,
+            Step::PostcombatMain => Phase::PostcombatMain,
+            Step::End | Step::Cleanup => Phase::Ending,
+        }
+    }
+}
+
+/// A player seated in the game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Player {
+    pub id: PlayerId,
+    pub seat: u8,
+    pub name: String,
+    pub life: i64,
+    /// Mulligans taken this game
+    #[serde(default)]
+    pub mulligans: u8,
+    /// Zone sizes reported by the server, including cards whose identity is hidden
+    pub zone_sizes: BTreeMap<Zone, u32>,
+}
+
+/// A card, token or copy tracked by the game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameObject {
+    pub id: ObjectId,
+    /// MTGO catalog id, None while the card is hidden from the local player
+    pub card_id: Option<u32>,
+    pub owner: PlayerId,
+    pub controller: PlayerId,
+    pub zone: Zone,
+    pub tapped: bool,
+    pub face_down: bool,
+    /// Counter name -> count (only non-zero counts are kept)
+    pub counters: BTreeMap<String, i64>,
+    /// Permanent this object is attached to (auras, equipment, fortifications)
+    pub attached_to: Option<ObjectId>,
+    /// Ordering within the zone: higher values entered later (top of library/stack)
+    pub zone_order: u64,
+}
+
+/// Turn, phase and step
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnInfo {
+    /// Turn number, 0 before the first turn begins
+    pub number: u32,
+    pub active_player: Option<PlayerId>,
+    pub phase: Option<Phase>,
+    pub step: Option<Step>,
+}
+
+/// Reconstructed state of one game
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameState {
+    pub game_id: Option<u32>,
+    pub players: BTreeMap<PlayerId, Player>,
+    pub objects: BTreeMap<ObjectId, GameObject>,
+    pub turn: TurnInfo,
+    pub priority: Option<PlayerId>,
+    pub starting_player: Option<PlayerId>,
+    pub ended: bool,
+    pub winner: Option<PlayerId>,
+    /// Next `zone_order` value to hand out
+    pub next_zone_order: u64,
+}
+
+impl GameState {
+    /// Empty state for a newly started game
+    pub fn new(game_id: u32) -> Self {
+        Self {
+            game_id: Some(game_id),
+            ..Self::default()
+        }
+    }
+
+    pub fn player(&self, id: PlayerId) -> Option<&Player> {
+        self.players.get(&id)
+    }
+
+    /// Player entry, created as a placeholder if the server has not introduced it yet
+    pub fn player_entry(&mut self, id: PlayerId) -> &mut Player {
+        self.players.entry(id).or_insert_with(|| Player {
+            id,
+            seat: 0,
+            name: String::new(),
+            life: 0,
+            mulligans: 0,
+            zone_sizes: Default::default(),
+        })
+    }
+
+    pub fn object(&self, id: ObjectId) -> Option<&GameObject> {
+        self.objects.get(&id)
+    }
+
+    /// Known objects in a zone, bottom to top
+    ///
+    /// # Arguments
+    /// * `owner` - Owning player for player zones; ignored for shared zones
+    /// * `zone` - Zone to list
+    pub fn zone(&self, owner: PlayerId, zone: Zone) -> Vec<&GameObject> {
+        let mut objects: Vec<&GameObject> = self
+            .objects
+            .values()
+            .filter(|o| o.zone == zone && (zone.is_shared() || o.owner == owner))
+            .collect();
+        objects.sort_by_key(|o| o.zone_order);
+        objects
+    }
+
+    /// Permanents controlled by a player
+    pub fn battlefield(&self, controller: PlayerId) -> Vec<&GameObject> {
+        self.zone(controller, Zone::Battlefield)
+            .into_iter()
+            .filter(|o| o.controller == controller)
+            .collect()
+    }
+
+    /// The stack, bottom to top
+    pub fn stack(&self) -> Vec<&GameObject> {
+        self.zone(0, Zone::Stack)
+    }
+
+    /// Objects attached to a permanent
+    pub fn attachments(&self, id: ObjectId) -> Vec<&GameObject> {
+        self.objects
+            .values()
+            .filter(|o| o.attached_to == Some(id))
+            .collect()
+    }
+
+    /// Number of cards in a player's zone, counting hidden cards the server reported
+    pub fn zone_size(&self, owner: PlayerId, zone: Zone) -> usize {
+        let known = self.zone(owner, zone).len();
+        let reported = self
+            .player(owner)
+            .and_then(|p| p.zone_sizes.get(&zone))
+            .map(|&n| n as usize)
+            .unwrap_or(0);
+        known.max(reported)
+    }
+}
+
+impl GameState {
+    /// Apply one event of the stream the `GameEngine` produced
+    ///
+    /// Replaying a game's events from the snapshot before them reproduces the
+    /// engine's state after each event, which is how replays seek (VIEW-001).
+    /// Events that do not change the game (match boundaries, deck registrations)
+    /// are ignored.
+    pub fn apply(&mut self, event: &GameEventKind) {
+        match event {
+            GameEventKind::GameStarted {
+                game_id,
+                starting_player,
+            } => {
+                *self = GameState::new(*game_id);
+                self.starting_player = *starting_player;
+            }
+            GameEventKind::PlayerJoined {
+                player,
+                seat,
+                name,
+                life,
+            } => {
+                let entry = self.player_entry(*player);
+                entry.seat = *seat;
+                entry.name = name.clone();
+                entry.life = *life;
+            }
+            GameEventKind::LifeChanged { player, to, .. } => self.player_entry(*player).life = *to,
+            GameEventKind::ZoneChanged {
+                object,
+                card_id,
+                owner,
+                controller,
+                from,
+                to,
+            } => self.move_object(*object, *card_id, *owner, *controller, *from, *to),
+            GameEventKind::ZoneSizeChanged { player, zone, size } => {
+                self.player_entry(*player).zone_sizes.insert(*zone, *size);
+            }
+            GameEventKind::Tapped { object, tapped } => {
+                if let Some(entry) = self.objects.get_mut(object) {
+                    entry.tapped = *tapped;
+                }
+            }
+            GameEventKind::FaceDownChanged { object, face_down } => {
+                if let Some(entry) = self.objects.get_mut(object) {
+                    entry.face_down = *face_down;
+                }
+            }
+            GameEventKind::ControlChanged { object, to, .. } => {
+                if let Some(entry) = self.objects.get_mut(object) {
+                    entry.controller = *to;
+                }
+            }
+            GameEventKind::CountersChanged {
+                object,
+                counter,
+                to,
+                ..
+            } => {
+                if let Some(entry) = self.objects.get_mut(object) {
+                    if *to == 0 {
+                        entry.counters.remove(counter);
+                    } else {
+                        entry.counters.insert(counter.clone(), *to);
+                    }
+                }
+            }
+            GameEventKind::Attached { object, to } => {
+                if let Some(entry) = self.objects.get_mut(object) {
+                    entry.attached_to = *to;
+                }
+            }
+            GameEventKind::TurnStarted {
+                turn,
+                active_player,
+            } => {
+                self.turn.number = *turn;
+                self.turn.active_player = Some(*active_player);
+            }
+            GameEventKind::StepChanged { phase, step, .. } => {
+                self.turn.phase = Some(*phase);
+                self.turn.step = Some(*step);
+            }
+            GameEventKind::PriorityChanged { player } => self.priority = Some(*player),
+            GameEventKind::GameEnded { winner, .. } => {
+                self.ended = true;
+                self.winner = *winner;
+                self.priority = None;
+            }
+            GameEventKind::Mulligan { player, count } => {
+                self.player_entry(*player).mulligans = *count
+            }
+            GameEventKind::MatchStarted { .. }
+            | GameEventKind::MatchEnded { .. }
+            | GameEventKind::DeckSubmitted { .. } => {}
+        }
+    }
+
+    /// Move an object as the engine did; `card_id` is the identity after the move
+    fn move_object(
+        &mut self,
+        object: ObjectId,
+        card_id: Option<u32>,
+        owner: PlayerId,
+        controller: PlayerId,
+        from: Option<Zone>,
+        to: Zone,
+    ) {
+        let zone_order = self.next_zone_order;
+        self.next_zone_order += 1;
+        let entry = self.objects.entry(object).or_insert_with(|| GameObject {
+            id: object,
+            card_id,
+            owner,
+            controller,
+            zone: to,
+            tapped: false,
+            face_down: false,
+            counters: Default::default(),
+            attached_to: None,
+            zone_order,
+        });
+        entry.card_id = card_id;
+        entry.owner = owner;
+        entry.controller = controller;
+        entry.zone = to;
+        entry.zone_order = zone_order;
+        // Attachments falling off are events of their own
+        if from == Some(Zone::Battlefield) && to != Zone::Battlefield {
+            entry.tapped = false;
+            entry.counters.clear();
+            entry.attached_to = None;
+        }
+
+        if let Some(from) = from.filter(|z| !z.is_shared() && *z != to) {
+            if let Some(size) = self.player_entry(owner).zone_sizes.get_mut(&from) {
+                *size = size.saturating_sub(1);
+            }
+        }
+        if from != Some(to) && !to.is_shared() {
+            if let Some(size) = self.player_entry(owner).zone_sizes.get_mut(&to) {
+                *size += 1;
+            }
+        }
+    }
+}
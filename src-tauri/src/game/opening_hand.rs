@@ -0,0 +1,168 @@
+use crate::game::event::{GameEvent, GameEventKind};
+use crate::game::model::{ObjectId, PlayerId, Zone};
+use serde::{Deserialize, Serialize};
+
+/// One hand the capturing user drew before the first turn
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OpeningHand {
+    pub game_id: u32,
+    /// Mulligans taken before this hand was drawn (0 for the first seven)
+    pub mulligans: u8,
+    /// Catalog ids of the cards drawn, sorted
+    pub cards: Vec<u32>,
+    /// Whether the hand was kept; every earlier hand of the game was mulliganed
+    pub kept: bool,
+    /// Cards of a kept hand put on the bottom of the library (London mulligan), sorted
+    pub bottomed: Vec<u32>,
+}
+
+/// Reads the capturing user's opening hands from the game event stream
+///
+/// A hand is every card that entered the user's hand before turn 1. When the
+/// hand empties before turn 1 it was mulliganed (shuffled back, or exiled by
+/// Serum Powder) and the next cards to arrive form a new hand. The hand still
+/// held when turn 1 starts was kept, and cards it put back into the library were
+/// bottomed for the mulligans taken.
+pub struct OpeningHandTracker {
+    /// Screen name of the capturing user, to recognise their seat
+    local_player: Option<String>,
+    local_id: Option<PlayerId>,
+    game_id: Option<u32>,
+    /// Cards in hand now, by object
+    held: Vec<(ObjectId, u32)>,
+    /// Every card drawn into the current hand
+    drawn: Vec<u32>,
+    /// Cards of the current hand put back into the library
+    returned: Vec<u32>,
+    mulligans: u8,
+    /// Turn 1 started: the game's opening hand is settled
+    settled: bool,
+}
+
+impl OpeningHandTracker {
+    /// # Arguments
+    /// * `local_player` - Screen name of the capturing user if already known; it
+    ///   is also learned from match starts and deck registrations in the stream
+    pub fn new(local_player: Option<String>) -> Self {
+        Self {
+            local_player,
+            local_id: None,
+            game_id: None,
+            held: Vec::new(),
+            drawn: Vec::new(),
+            returned: Vec::new(),
+            mulligans: 0,
+            settled: true,
+        }
+    }
+
+    /// Apply one game event
+    ///
+    /// # Returns
+    /// The hand the event settled: mulliganed, or kept as turn 1 started
+    pub fn apply(&mut self, event: &GameEvent) -> Option<OpeningHand> {
+        match &event.kind {
+            GameEventKind::MatchStarted { local_player, .. } if !local_player.is_empty() => {
+                self.local_player = Some(local_player.clone());
+            }
+            GameEventKind::GameStarted { game_id, .. } => {
+                // The deck is registered just before its game starts, so the
+                // local id it taught is kept
+                *self = Self {
+                    local_id: self.local_id,
+                    game_id: Some(*game_id),
+                    settled: false,
+                    ..Self::new(self.local_player.take())
+                };
+            }
+            GameEventKind::PlayerJoined { player, name, .. } => {
+                let is_local = self
+                    .local_player
+                    .as_deref()
+                    .is_some_and(|local| local.eq_ignore_ascii_case(name));
+                if is_local {
+                    self.local_id = Some(*player);
+                }
+            }
+            GameEventKind::DeckSubmitted { player, .. } => self.local_id = Some(*player),
+            GameEventKind::ZoneChanged {
+                object,
+                card_id,
+                owner,
+                to,
+                ..
+            } if !self.settled && self.local_id == Some(*owner) => {
+                return self.moved(*object, *card_id, *to);
+            }
+            GameEventKind::TurnStarted { .. } if !self.settled => {
+                self.settled = true;
+                return self.take_hand(true);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn moved(&mut self, object: ObjectId, card_id: Option<u32>, to: Zone) -> Option<OpeningHand> {
+        let position = self.held.iter().position(|(held, _)| *held == object);
+        match (position, to) {
+            (None, Zone::Hand) => {
+                // Cards are revealed to their owner as they are drawn
+                let card_id = card_id?;
+                self.held.push((object, card_id));
+                self.drawn.push(card_id);
+                None
+            }
+            (Some(i), Zone::Hand) => {
+                self.held[i].1 = card_id.unwrap_or(self.held[i].1);
+                None
+            }
+            (Some(i), to) => {
+                let (_, card_id) = self.held.remove(i);
+                if to == Zone::Library {
+                    self.returned.push(card_id);
+                }
+                if self.held.is_empty() {
+                    let hand = self.take_hand(false);
+                    self.mulligans = self.mulligans.saturating_add(1);
+                    return hand;
+                }
+                None
+            }
+            (None, _) => None,
+        }
+    }
+
+    fn take_hand(&mut self, kept: bool) -> Option<OpeningHand> {
+        let mut cards = std::mem::take(&mut self.drawn);
+        let mut bottomed = std::mem::take(&mut self.returned);
+        if cards.is_empty() {
+            return None;
+        }
+        cards.sort_unstable();
+        bottomed.sort_unstable();
+        Some(OpeningHand {
+            game_id: self.game_id?,
+            mulligans: self.mulligans,
+            cards,
+            kept,
+            bottomed: if kept { bottomed } else { Vec::new() },
+        })
+    }
+}
+
+/// Opening hands of every game in an event stream, in order
+///
+/// # Arguments
+/// * `events` - Game event stream
+/// * `local_player` - Screen name of the capturing user, if known
+pub fn opening_hands<'a>(
+    events: impl IntoIterator<Item = &'a GameEvent>,
+    local_player: Option<String>,
+) -> Vec<OpeningHand> {
+    let mut tracker = OpeningHandTracker::new(local_player);
+    events
+        .into_iter()
+        .filter_map(|event| tracker.apply(event))
+        .collect()
+}
@@ -0,0 +1,254 @@
+use crate::common::error::GameError;
+use crate::game::deck::{CardCounts, Deck};
+use crate::game::lifecycle::Match;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Copies of one card moved in or out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CardCount {
+    pub card_id: u32,
+    pub count: u32,
+}
+
+/// Cards swapped between maindeck and sideboard before a game (SIDE-001)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SideboardPlan {
+    /// Cards added to the maindeck
+    pub cards_in: Vec<CardCount>,
+    /// Cards removed from the maindeck
+    pub cards_out: Vec<CardCount>,
+}
+
+impl SideboardPlan {
+    pub fn is_empty(&self) -> bool {
+        self.cards_in.is_empty() && self.cards_out.is_empty()
+    }
+
+    /// Sort and merge duplicate entries, dropping zero counts
+    pub fn normalized(&self) -> SideboardPlan {
+        let merge = |cards: &[CardCount]| {
+            let mut counts = CardCounts::new();
+            for c in cards {
+                *counts.entry(c.card_id).or_insert(0) += c.count;
+            }
+            to_list(&counts)
+        };
+        SideboardPlan {
+            cards_in: merge(&self.cards_in),
+            cards_out: merge(&self.cards_out),
+        }
+    }
+}
+
+/// Reasons a detected plan may not reflect what the player actually did
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SideboardFlag {
+    /// The previous game's deck was not captured, so nothing could be compared
+    NoPreviousDeck,
+    /// The 75-card pool itself changed, which registration should not allow
+    PoolChanged {
+        added: Vec<CardCount>,
+        removed: Vec<CardCount>,
+    },
+    /// Maindeck size changed (more cards in than out or vice versa)
+    MaindeckSizeChanged {
+        from: u32,
+        to: u32,
+    },
+    CompanionChanged {
+        from: Option<u32>,
+        to: Option<u32>,
+    },
+    /// Sideboard cards brought into the previous game by wishes or as the companion;
+    /// they stay in the sideboard and are not part of the plan
+    OutsideCardsUsed {
+        cards: Vec<u32>,
+    },
+}
+
+/// A user's fix of a detected plan
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SideboardCorrection {
+    pub plan: SideboardPlan,
+    pub note: Option<String>,
+    pub corrected_at: DateTime<Utc>,
+}
+
+/// Sideboarding before one game, as detected and as corrected by the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SideboardChange {
+    pub detected: SideboardPlan,
+    /// Empty when the detection is unambiguous
+    pub flags: Vec<SideboardFlag>,
+    pub corrected: Option<SideboardCorrection>,
+}
+
+impl SideboardChange {
+    /// The corrected plan if the user fixed it, otherwise the detected one
+    pub fn effective(&self) -> &SideboardPlan {
+        self.corrected
+            .as_ref()
+            .map(|c| &c.plan)
+            .unwrap_or(&self.detected)
+    }
+
+    pub fn is_ambiguous(&self) -> bool {
+        !self.flags.is_empty()
+    }
+}
+
+fn to_list(counts: &CardCounts) -> Vec<CardCount> {
+    counts
+        .iter()
+        .filter(|(_, &count)| count > 0)
+        .map(|(&card_id, &count)| CardCount { card_id, count })
+        .collect()
+}
+
+/// Per-card difference `to - from`, split into gains and losses
+fn count_diff(from: &CardCounts, to: &CardCounts) -> (CardCounts, CardCounts) {
+    let mut gained = CardCounts::new();
+    let mut lost = CardCounts::new();
+    for (&card, &count) in to {
+        let before = from.get(&card).copied().unwrap_or(0);
+        if count > before {
+            gained.insert(card, count - before);
+        }
+    }
+    for (&card, &count) in from {
+        let after = to.get(&card).copied().unwrap_or(0);
+        if count > after {
+            lost.insert(card, count - after);
+        }
+    }
+    (gained, lost)
+}
+
+/// Detect the sideboarding between two consecutive games
+///
+/// The plan is the maindeck difference: cards gained are "in", cards lost are
+/// "out". Anything that makes the difference suspect is flagged rather than
+/// guessed at, so the user can correct it.
+///
+/// # Arguments
+/// * `previous` - Deck of the previous game, None if it was not captured
+/// * `next` - Deck registered for the game about to start
+/// * `outside_cards` - Sideboard cards brought into the previous game (wishes, companion)
+pub fn detect(previous: Option<&Deck>, next: &Deck, outside_cards: &[u32]) -> SideboardChange {
+    let Some(previous) = previous else {
+        return SideboardChange {
+            detected: SideboardPlan::default(),
+            flags: vec![SideboardFlag::NoPreviousDeck],
+            corrected: None,
+        };
+    };
+
+    let (cards_in, cards_out) = count_diff(&previous.maindeck, &next.maindeck);
+    let detected = SideboardPlan {
+        cards_in: to_list(&cards_in),
+        cards_out: to_list(&cards_out),
+    };
+
+    let mut flags = Vec::new();
+    let (added, removed) = count_diff(&previous.pool(), &next.pool());
+    if !added.is_empty() || !removed.is_empty() {
+        flags.push(SideboardFlag::PoolChanged {
+            added: to_list(&added),
+            removed: to_list(&removed),
+        });
+    }
+    let (from, to) = (previous.maindeck_size(), next.maindeck_size());
+    if from != to {
+        flags.push(SideboardFlag::MaindeckSizeChanged { from, to });
+    }
+    if previous.companion != next.companion {
+        flags.push(SideboardFlag::CompanionChanged {
+            from: previous.companion,
+            to: next.companion,
+        });
+    }
+    if !outside_cards.is_empty() {
+        flags.push(SideboardFlag::OutsideCardsUsed {
+            cards: outside_cards.to_vec(),
+        });
+    }
+
+    SideboardChange {
+        detected,
+        flags,
+        corrected: None,
+    }
+}
+
+/// Record a user's correction of the sideboarding before a game
+///
+/// # Arguments
+/// * `record` - Match to update
+/// * `game_number` - Game the sideboarding led into (2 or later)
+/// * `plan` - Corrected plan; None reverts to the detected plan
+/// * `note` - Optional explanation shown alongside the plan
+///
+/// # Returns
+/// Err(GameError::InvalidCorrection) for game 1 or a game the match does not have
+pub fn correct(
+    record: &mut Match,
+    game_number: u8,
+    plan: Option<SideboardPlan>,
+    note: Option<String>,
+) -> Result<(), GameError> {
+    if game_number < 2 {
+        return Err(GameError::InvalidCorrection(
+            "sideboarding only happens before game 2 and later".to_string(),
+        ));
+    }
+    let game = record
+        .games
+        .iter_mut()
+        .find(|g| g.number == game_number)
+        .ok_or_else(|| {
+            GameError::InvalidCorrection(format!("match has no game {}", game_number))
+        })?;
+
+    // Games whose decks were not captured can still be given a plan by hand
+    let change = game.sideboard.get_or_insert_with(|| SideboardChange {
+        detected: SideboardPlan::default(),
+        flags: vec![SideboardFlag::NoPreviousDeck],
+        corrected: None,
+    });
+    change.corrected = plan.map(|plan| SideboardCorrection {
+        plan: plan.normalized(),
+        note: note.filter(|n| !n.trim().is_empty()),
+        corrected_at: Utc::now(),
+    });
+    Ok(())
+}
+
+/// Carry corrections from the stored copy of a match into a newer copy
+///
+/// The live tracker rewrites match records as games close; corrections the user
+/// made in the meantime must survive that.
+pub fn keep_corrections(record: &mut Match, stored: &Match) {
+    for stored_game in &stored.games {
+        let Some(stored_change) = stored_game.sideboard.as_ref() else {
+            continue;
+        };
+        if stored_change.corrected.is_none() {
+            continue;
+        }
+        if let Some(game) = record
+            .games
+            .iter_mut()
+            .find(|g| g.number == stored_game.number)
+        {
+            match game.sideboard.as_mut() {
+                Some(change) if change.corrected.is_none() => {
+                    change.corrected = stored_change.corrected.clone();
+                }
+                Some(_) => {}
+                None => game.sideboard = Some(stored_change.clone()),
+            }
+        }
+    }
+}
@@ -1,2 +1,180 @@
-// Fst
-// Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+use crate::common::paths::{
+    archetypes_path, card_db_path, library_db_path, matches_dir, replays_dir,
+};
+use crate::ui::analytics_commands::{
+    get_card_stats, get_matchup_matrix, get_mulligan_report, get_win_rate_trend, get_win_rates,
+    search_opening_hands,
+};
+use crate::ui::annotation_commands::{
+    add_annotation, delete_annotation, get_annotations, merge_annotations, search_annotations,
+    update_annotation,
+};
+use crate::ui::archetype_commands::{
+    classify_deck, get_archetype_definitions, reclassify_library, save_archetype_definitions,
+};
+use crate::ui::card_commands::{
+    get_card, get_card_db_status, import_card_data, resolve_cards, search_cards,
+};
+use crate::ui::commands::{
+    check_admin_privileges, get_capture_settings, get_capture_status, set_capture_settings,
+    start_capture, stop_capture, CaptureState,
+};
+use crate::ui::deck_commands::{export_match_deck, import_dek_file, tag_match_deck};
+use crate::ui::explorer_commands::{
+    close_explorer_session, compare_capture_sessions, diff_messages, get_message_detail,
+    list_session_messages, ExplorerState,
+};
+use crate::ui::export_commands::{export_replay, get_export_schema};
+use crate::ui::game_commands::{get_live_game_state, LiveGameState};
+use crate::ui::library_commands::{get_library_status, query_library, rescan_library};
+use crate::ui::match_commands::{correct_sideboard, get_match, list_matches};
+use crate::ui::playback_commands::{
+    close_playback, get_playback_view, open_playback, pause_playback, play_playback, seek_playback,
+    set_playback_speed, set_playback_view_mode, step_playback, PlaybackState,
+};
+use crate::ui::replay_commands::{
+    anonymize_replay, get_signing_key, list_trusted_keys, sign_replay, trust_key, untrust_key,
+    verify_replay,
+};
+use crate::ui::session_commands::{
+    list_capture_sessions, list_unknown_version_sessions, redecode_session,
+    redecode_unknown_version_sessions,
+};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+pub mod analytics;
+pub mod archetype;
+pub mod capture;
+pub mod cards;
+pub mod common;
+pub mod decklist;
+pub mod explorer;
+pub mod export;
+pub mod game;
+pub mod library;
+pub mod protocol;
+pub mod replay;
+pub mod ui;
+
+/// Build and run the Tauri application
+///
+/// The application lives in the library crate so the protocol parsers can be
+/// exercised by integration tests and fuzz targets without the capture driver.
+pub fn run() {
+    // Initialize shared capture state
+    let capture_state = Arc::new(Mutex::new(CaptureState::default()));
+    let explorer_state = Arc::new(Mutex::new(ExplorerState::default()));
+    let live_game_state = Arc::new(Mutex::new(LiveGameState::default()));
+    let playback_state = Arc::new(Mutex::new(PlaybackState::default()));
+
+    tauri::Builder::default()
+        .setup(|app| {
+            // Finalize replays a crash left unfinished, before any capture can start
+            match replays_dir(app.handle()) {
+                Ok(dir) => {
+                    if let Err(e) = replay::recovery::recover_all(&dir) {
+                        tracing::warn!("Replay recovery failed: {}", e);
+                    }
+                }
+                Err(e) => tracing::warn!("Replay recovery skipped: {}", e),
+            }
+
+            // Index replays into the library as they are finished, and classify
+            // their archetypes (STAT-004)
+            let handle = app.handle();
+            let paths = (|| -> Result<_, String> {
+                Ok(library::watcher::WatchPaths {
+                    db_path: library_db_path(handle)?,
+                    replays_dir: replays_dir(handle)?,
+                    matches_dir: matches_dir(handle)?,
+                    card_db_path: card_db_path(handle)?,
+                    archetypes_path: archetypes_path(handle)?,
+                })
+            })();
+            match paths {
+                Ok(paths) => {
+                    tauri::async_runtime::spawn(library::watcher::watch(
+                        paths,
+                        library::watcher::SCAN_INTERVAL,
+                    ));
+                }
+                Err(_) => tracing::warn!(
+                    "Replay library watcher not started: application data directory unavailable"
+                ),
+            }
+            Ok(())
+        })
+        .manage(capture_state)
+        .manage(explorer_state)
+        .manage(live_game_state)
+        .manage(playback_state)
+        .invoke_handler(tauri::generate_handler![
+            check_admin_privileges,
+            get_capture_status,
+            get_capture_settings,
+            set_capture_settings,
+            start_capture,
+            stop_capture,
+            list_capture_sessions,
+            redecode_session,
+            list_unknown_version_sessions,
+            redecode_unknown_version_sessions,
+            list_session_messages,
+            get_message_detail,
+            diff_messages,
+            close_explorer_session,
+            compare_capture_sessions,
+            get_live_game_state,
+            import_card_data,
+            get_card,
+            resolve_cards,
+            search_cards,
+            get_card_db_status,
+            list_matches,
+            get_match,
+            correct_sideboard,
+            export_match_deck,
+            import_dek_file,
+            tag_match_deck,
+            anonymize_replay,
+            sign_replay,
+            verify_replay,
+            get_signing_key,
+            list_trusted_keys,
+            trust_key,
+            untrust_key,
+            export_replay,
+            get_export_schema,
+            query_library,
+            rescan_library,
+            get_library_status,
+            get_win_rates,
+            get_win_rate_trend,
+            get_matchup_matrix,
+            get_mulligan_report,
+            search_opening_hands,
+            get_card_stats,
+            get_archetype_definitions,
+            save_archetype_definitions,
+            classify_deck,
+            reclassify_library,
+            open_playback,
+            close_playback,
+            get_playback_view,
+            seek_playback,
+            step_playback,
+            play_playback,
+            pause_playback,
+            set_playback_speed,
+            set_playback_view_mode,
+            get_annotations,
+            add_annotation,
+            update_annotation,
+            delete_annotation,
+            merge_annotations,
+            search_annotations
+        ])
+        .run(tauri::generate_context!())
+        .expect("error while running tauri application");
+}
@@ -0,0 +1,1346 @@
+use crate::common::error::{GameError, LibraryError, ReplayError};
+use crate::game::event::GameEventKind;
+use crate::game::lifecycle::{EventType, Match, Outcome};
+use crate::game::match_store;
+use crate::game::model::{PlayerId, Zone};
+use crate::game::opening_hand::{OpeningHand, OpeningHandTracker};
+use crate::game::sideboard::CardCount;
+use crate::library::{
+    AnnotationQuery, ArchetypeAssignment, LibraryAnnotation, LibraryCardGame, LibraryGame,
+    LibraryHand, LibraryMatch, LibraryQuery, LibraryStatus, MatchDecks, PlayerSide,
+};
+use crate::replay::annotations::{self, normalize_tag};
+use crate::replay::format::{ChunkKind, REPLAY_EXTENSION};
+use crate::replay::{Annotation, AnnotationAnchor, Chunk, ReplayAnnotations, ReplayReader};
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::types::Value;
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row, Transaction};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+
+/// Schema migrations, applied in order; the schema version stored in
+/// `PRAGMA user_version` is the number applied so far
+///
+/// Never edit a released migration: append a new one, so existing libraries are
+/// upgraded in place.
+const MIGRATIONS: &[&str] = &[
+    "
+    CREATE TABLE replays (
+        id                 INTEGER PRIMARY KEY,
+        path               TEXT NOT NULL UNIQUE,
+        file_size          INTEGER NOT NULL,
+        modified_ms        INTEGER NOT NULL,
+        record_modified_ms INTEGER,
+        format_version     TEXT NOT NULL,
+        indexed_at         TEXT NOT NULL
+    );
+    CREATE TABLE matches (
+        replay_id          INTEGER PRIMARY KEY REFERENCES replays (id) ON DELETE CASCADE,
+        record_id          TEXT NOT NULL,
+        match_id           INTEGER NOT NULL,
+        format             TEXT NOT NULL,
+        event_type         TEXT NOT NULL,
+        event_name         TEXT NOT NULL,
+        best_of            INTEGER NOT NULL,
+        local_player       TEXT,
+        opponent           TEXT,
+        started_at         TEXT NOT NULL,
+        ended_at           TEXT,
+        result             TEXT,
+        end_reason         TEXT,
+        games_won          INTEGER NOT NULL,
+        games_lost         INTEGER NOT NULL,
+        deck_name          TEXT,
+        archetype          TEXT,
+        opponent_archetype TEXT
+    );
+    CREATE INDEX matches_started_at ON matches (started_at);
+    CREATE INDEX matches_format ON matches (format COLLATE NOCASE);
+    CREATE TABLE games (
+        replay_id          INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        game_number        INTEGER NOT NULL,
+        game_id            INTEGER NOT NULL,
+        started_at         TEXT NOT NULL,
+        ended_at           TEXT,
+        on_play            INTEGER,
+        mulligans          INTEGER NOT NULL,
+        opponent_mulligans INTEGER NOT NULL,
+        turns              INTEGER NOT NULL,
+        result             TEXT,
+        end_reason         TEXT,
+        PRIMARY KEY (replay_id, game_number)
+    );
+    CREATE TABLE deck_cards (
+        replay_id   INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        game_number INTEGER NOT NULL,
+        board       TEXT NOT NULL,
+        card_id     INTEGER NOT NULL,
+        quantity    INTEGER NOT NULL,
+        PRIMARY KEY (replay_id, game_number, board, card_id)
+    );
+    CREATE TABLE sideboard_plans (
+        replay_id   INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        game_number INTEGER NOT NULL,
+        direction   TEXT NOT NULL,
+        card_id     INTEGER NOT NULL,
+        quantity    INTEGER NOT NULL,
+        PRIMARY KEY (replay_id, game_number, direction, card_id)
+    );
+    CREATE TABLE card_plays (
+        replay_id INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        game_id   INTEGER NOT NULL,
+        side      TEXT NOT NULL,
+        card_id   INTEGER NOT NULL,
+        plays     INTEGER NOT NULL,
+        PRIMARY KEY (replay_id, game_id, side, card_id)
+    );
+    CREATE INDEX card_plays_card ON card_plays (card_id);
+",
+    "
+    CREATE TABLE opening_hands (
+        replay_id INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        game_id   INTEGER NOT NULL,
+        mulligans INTEGER NOT NULL,
+        kept      INTEGER NOT NULL,
+        PRIMARY KEY (replay_id, game_id, mulligans)
+    );
+    CREATE TABLE opening_hand_cards (
+        replay_id INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        game_id   INTEGER NOT NULL,
+        mulligans INTEGER NOT NULL,
+        card_id   INTEGER NOT NULL,
+        quantity  INTEGER NOT NULL,
+        bottomed  INTEGER NOT NULL,
+        PRIMARY KEY (replay_id, game_id, mulligans, card_id)
+    );
+    -- Replays indexed before opening hands were recorded are re-indexed on the next scan
+    UPDATE replays SET modified_ms = -1;
+",
+    "
+    CREATE TABLE card_games (
+        replay_id       INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        game_id         INTEGER NOT NULL,
+        card_id         INTEGER NOT NULL,
+        opening_hand    INTEGER NOT NULL,
+        drawn           INTEGER NOT NULL,
+        casts           INTEGER NOT NULL,
+        first_cast_turn INTEGER,
+        PRIMARY KEY (replay_id, game_id, card_id)
+    );
+    UPDATE replays SET modified_ms = -1;
+",
+    "
+    ALTER TABLE matches ADD COLUMN archetype_confidence REAL;
+    ALTER TABLE matches ADD COLUMN opponent_archetype_confidence REAL;
+    -- Version of the archetype definitions and card data the match was classified with
+    ALTER TABLE matches ADD COLUMN classified_with TEXT;
+",
+    "
+    -- Modification time of the annotations file the annotations were indexed from
+    ALTER TABLE replays ADD COLUMN annotations_modified_ms INTEGER;
+    CREATE TABLE annotations (
+        replay_id     INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        annotation_id TEXT NOT NULL,
+        game_id       INTEGER,
+        turn          INTEGER NOT NULL,
+        seq           INTEGER,
+        text          TEXT NOT NULL,
+        bookmark      INTEGER NOT NULL,
+        author        TEXT NOT NULL,
+        created_at    TEXT NOT NULL,
+        updated_at    TEXT NOT NULL,
+        PRIMARY KEY (replay_id, annotation_id)
+    );
+    CREATE TABLE annotation_tags (
+        replay_id     INTEGER NOT NULL REFERENCES replays (id) ON DELETE CASCADE,
+        annotation_id TEXT NOT NULL,
+        tag           TEXT NOT NULL,
+        PRIMARY KEY (replay_id, annotation_id, tag)
+    );
+    CREATE INDEX annotation_tags_tag ON annotation_tags (tag);
+",
+];
+
+const MATCH_COLUMNS: &str = "r.path, m.record_id, m.match_id, m.format, m.event_name, m.best_of, \
+                             m.local_player, m.opponent, m.started_at, m.ended_at, m.result, \
+                             m.games_won, m.games_lost, \
+                             (SELECT COUNT(*) FROM games g WHERE g.replay_id = m.replay_id), \
+                             m.deck_name, m.archetype, m.opponent_archetype, \
+                             m.archetype_confidence, m.opponent_archetype_confidence";
+
+/// Size and modification times a replay was indexed at, to tell when it changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileStamp {
+    pub size: u64,
+    /// Modification time of the replay, in milliseconds since the Unix epoch
+    pub modified_ms: i64,
+    /// Modification time of the stored match record, if there is one
+    pub record_modified_ms: Option<i64>,
+    /// Modification time of the replay's annotations file, if there is one
+    pub annotations_modified_ms: Option<i64>,
+}
+
+impl FileStamp {
+    /// Current stamp of a replay, its stored match record and its annotations
+    pub fn of(path: &Path, matches_dir: Option<&Path>) -> std::io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        let record_modified_ms = record_path(path, matches_dir)
+            .and_then(|record| std::fs::metadata(record).ok())
+            .map(|m| modified_ms(&m));
+        Ok(Self {
+            size: metadata.len(),
+            modified_ms: modified_ms(&metadata),
+            record_modified_ms,
+            annotations_modified_ms: annotations_modified_ms(path),
+        })
+    }
+}
+
+/// Modification time of a replay's annotations file, if there is one
+fn annotations_modified_ms(path: &Path) -> Option<i64> {
+    std::fs::metadata(annotations::sidecar_path(path))
+        .ok()
+        .map(|m| modified_ms(&m))
+}
+
+/// Annotations of a replay to index; unreadable files are indexed as none
+fn load_annotations(path: &Path) -> ReplayAnnotations {
+    ReplayAnnotations::load(&annotations::sidecar_path(path)).unwrap_or_else(|e| {
+        warn!("Ignoring annotations of {}: {}", path.display(), e);
+        ReplayAnnotations::default()
+    })
+}
+
+fn modified_ms(metadata: &std::fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Match record id of a replay, from its file name (`<record id>.mtgoreplay`)
+fn record_id(path: &Path) -> Option<&str> {
+    path.file_name()?
+        .to_str()?
+        .strip_suffix(REPLAY_EXTENSION)?
+        .strip_suffix('.')
+}
+
+/// Stored match record of a replay, if the record exists
+fn record_path(path: &Path, matches_dir: Option<&Path>) -> Option<PathBuf> {
+    let path = match_store::record_path(matches_dir?, record_id(path)?).ok()?;
+    path.exists().then_some(path)
+}
+
+/// Local replay library
+///
+/// An embedded SQLite index of replay files (STAT-004). Each replay contributes
+/// one match row, its games, the user's deck and sideboard plan for each game,
+/// the opponent cards seen, the user's opening hands, how each of the user's
+/// cards was drawn and cast, per-card play counts for both players, and the
+/// annotations written on it. Rows of a replay are replaced as a whole when it
+/// is re-indexed.
+pub struct ReplayLibrary {
+    conn: Connection,
+}
+
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .ok()
+        .map(|t| t.with_timezone(&Utc))
+}
+
+fn outcome_str(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Win => "win",
+        Outcome::Loss => "loss",
+        Outcome::Draw => "draw",
+    }
+}
+
+fn parse_outcome(value: &str) -> Option<Outcome> {
+    match value {
+        "win" => Some(Outcome::Win),
+        "loss" => Some(Outcome::Loss),
+        "draw" => Some(Outcome::Draw),
+        _ => None,
+    }
+}
+
+fn read_match(row: &Row) -> rusqlite::Result<LibraryMatch> {
+    let path: String = row.get(0)?;
+    let started_at: String = row.get(8)?;
+    let ended_at: Option<String> = row.get(9)?;
+    let result: Option<String> = row.get(10)?;
+    Ok(LibraryMatch {
+        path: PathBuf::from(path),
+        record_id: row.get(1)?,
+        match_id: row.get(2)?,
+        format: row.get(3)?,
+        event_name: row.get(4)?,
+        best_of: row.get(5)?,
+        local_player: row.get(6)?,
+        opponent: row.get(7)?,
+        started_at: parse_timestamp(&started_at).unwrap_or_default(),
+        ended_at: ended_at.as_deref().and_then(parse_timestamp),
+        result: result.as_deref().and_then(parse_outcome),
+        games_won: row.get(11)?,
+        games_lost: row.get(12)?,
+        games: row.get(13)?,
+        deck_name: row.get(14)?,
+        archetype: row.get(15)?,
+        opponent_archetype: row.get(16)?,
+        archetype_confidence: row.get(17)?,
+        opponent_archetype_confidence: row.get(18)?,
+    })
+}
+
+impl ReplayLibrary {
+    /// Open (creating if needed) the library at `path`, applying pending migrations
+    pub fn open(path: &Path) -> Result<Self, LibraryError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        Self::init(conn)
+    }
+
+    /// Library held in memory (for tests)
+    pub fn open_in_memory() -> Result<Self, LibraryError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(mut conn: Connection) -> Result<Self, LibraryError> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        let supported = MIGRATIONS.len() as i64;
+        if version > supported {
+            return Err(LibraryError::NewerSchema {
+                found: version,
+                supported,
+            });
+        }
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let tx = conn.transaction()?;
+            tx.execute_batch(migration)?;
+            tx.pragma_update(None, "user_version", i as i64 + 1)?;
+            tx.commit()?;
+            debug!("Replay library migrated to schema version {}", i + 1);
+        }
+        Ok(Self { conn })
+    }
+
+    /// Schema version of the open library
+    pub fn schema_version(&self) -> Result<i64, LibraryError> {
+        Ok(self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))?)
+    }
+
+    /// Index one replay, replacing what was indexed from it before
+    ///
+    /// The match record stored in `matches_dir` is preferred over the copy in the
+    /// replay, since it carries later sideboard corrections and deck tags. Replays
+    /// finished without a record are indexed from their header.
+    /// Annotations come from the replay's annotations file, if it can be read.
+    ///
+    /// # Arguments
+    /// * `path` - Finished replay file
+    /// * `matches_dir` - Directory of stored match records, if any
+    pub fn index_replay(
+        &mut self,
+        path: &Path,
+        matches_dir: Option<&Path>,
+    ) -> Result<(), LibraryError> {
+        let index_error = |reason: String| LibraryError::Index {
+            path: path.display().to_string(),
+            reason,
+        };
+        let stamp = FileStamp::of(path, matches_dir)?;
+        let mut reader = ReplayReader::open(path).map_err(|e| index_error(e.to_string()))?;
+        // A shared copy of a match already in the library would count it twice
+        if reader.header().redactions.is_some() {
+            return Err(LibraryError::Anonymized(path.display().to_string()));
+        }
+
+        let stored = matches_dir.zip(record_id(path)).and_then(|(dir, id)| {
+            match match_store::load_match(dir, id) {
+                Ok(record) => Some(record),
+                Err(GameError::MatchNotFound(_)) => None,
+                Err(e) => {
+                    warn!("Ignoring match record of {}: {}", path.display(), e);
+                    None
+                }
+            }
+        });
+        let record = match stored {
+            Some(record) => record,
+            None => match reader
+                .match_record()
+                .map_err(|e| index_error(e.to_string()))?
+            {
+                Some(record) => record,
+                None => header_record(&reader)
+                    .ok_or_else(|| index_error("no match information".to_string()))?,
+            },
+        };
+        let summary = read_events(&mut reader, &record).map_err(|e| index_error(e.to_string()))?;
+        let (major, minor) = reader.format_version();
+        let annotations = load_annotations(path);
+
+        let tx = self.conn.transaction()?;
+        let path_text = path.to_string_lossy();
+        tx.execute("DELETE FROM replays WHERE path = ?1", [&path_text])?;
+        tx.execute(
+            "INSERT INTO replays (path, file_size, modified_ms, record_modified_ms, annotations_modified_ms, \
+             format_version, indexed_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                path_text,
+                stamp.size as i64,
+                stamp.modified_ms,
+                stamp.record_modified_ms,
+                stamp.annotations_modified_ms,
+                format!("{}.{}", major, minor),
+                timestamp(&Utc::now()),
+            ],
+        )?;
+        let replay_id = tx.last_insert_rowid();
+        insert_match(&tx, replay_id, path, &record)?;
+        insert_plays(&tx, replay_id, &summary.plays)?;
+        insert_hands(&tx, replay_id, &summary.hands)?;
+        insert_card_games(&tx, replay_id, &summary.cards)?;
+        insert_annotations(&tx, replay_id, &annotations)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Re-index only the annotations of an indexed replay, after they were edited
+    ///
+    /// # Returns
+    /// Whether the replay is indexed; annotations of other replays are picked up
+    /// when the replay is
+    pub fn index_annotations(&mut self, path: &Path) -> Result<bool, LibraryError> {
+        let modified_ms = annotations_modified_ms(path);
+        let annotations = load_annotations(path);
+        let tx = self.conn.transaction()?;
+        let replay_id: Option<i64> = tx
+            .query_row(
+                "SELECT id FROM replays WHERE path = ?1",
+                [path.to_string_lossy()],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let Some(replay_id) = replay_id else {
+            return Ok(false);
+        };
+        tx.execute(
+            "UPDATE replays SET annotations_modified_ms = ?2 WHERE id = ?1",
+            params![replay_id, modified_ms],
+        )?;
+        tx.execute(
+            "DELETE FROM annotation_tags WHERE replay_id = ?1",
+            [replay_id],
+        )?;
+        tx.execute("DELETE FROM annotations WHERE replay_id = ?1", [replay_id])?;
+        insert_annotations(&tx, replay_id, &annotations)?;
+        tx.commit()?;
+        Ok(true)
+    }
+
+    /// Drop a replay from the index
+    ///
+    /// # Returns
+    /// Whether the replay was indexed
+    pub fn remove(&mut self, path: &Path) -> Result<bool, LibraryError> {
+        let removed = self.conn.execute(
+            "DELETE FROM replays WHERE path = ?1",
+            [path.to_string_lossy()],
+        )?;
+        Ok(removed > 0)
+    }
+
+    /// Every indexed replay with the stamp it was indexed at
+    pub fn indexed_files(&self) -> Result<HashMap<PathBuf, FileStamp>, LibraryError> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT path, file_size, modified_ms, record_modified_ms, annotations_modified_ms FROM replays")?;
+        let files = stmt
+            .query_map([], |row| {
+                let path: String = row.get(0)?;
+                let size: i64 = row.get(1)?;
+                Ok((
+                    PathBuf::from(path),
+                    FileStamp {
+                        size: size as u64,
+                        modified_ms: row.get(2)?,
+                        record_modified_ms: row.get(3)?,
+                        annotations_modified_ms: row.get(4)?,
+                    },
+                ))
+            })?
+            .collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(files)
+    }
+
+    /// Search indexed matches
+    ///
+    /// # Returns
+    /// Matching matches, newest first, at most `query.limit` of them
+    pub fn query(&self, query: &LibraryQuery) -> Result<Vec<LibraryMatch>, LibraryError> {
+        let (where_clause, values) = filter_clause(query);
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+        let sql = format!(
+            "SELECT {} FROM matches m JOIN replays r ON r.id = m.replay_id {} \
+             ORDER BY m.started_at DESC, m.replay_id DESC LIMIT {} OFFSET {}",
+            MATCH_COLUMNS, where_clause, limit, query.offset
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let matches = stmt
+            .query_map(params_from_iter(values.iter()), read_match)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(matches)
+    }
+
+    /// Every game of the matches a query selects, oldest first (`limit` and
+    /// `offset` are ignored)
+    pub fn games(&self, query: &LibraryQuery) -> Result<Vec<LibraryGame>, LibraryError> {
+        let (where_clause, values) = filter_clause(query);
+        let sql = format!(
+            "SELECT m.record_id, m.format, m.opponent, m.deck_name, m.archetype, m.opponent_archetype, \
+             m.started_at, m.result, g.game_number, g.started_at, g.on_play, g.mulligans, g.result \
+             FROM games g JOIN matches m ON m.replay_id = g.replay_id {} \
+             ORDER BY m.started_at, m.replay_id, g.game_number",
+            where_clause
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let games = stmt
+            .query_map(params_from_iter(values.iter()), |row| {
+                let match_started_at: String = row.get(6)?;
+                let match_result: Option<String> = row.get(7)?;
+                let started_at: String = row.get(9)?;
+                let result: Option<String> = row.get(12)?;
+                Ok(LibraryGame {
+                    record_id: row.get(0)?,
+                    format: row.get(1)?,
+                    opponent: row.get(2)?,
+                    deck_name: row.get(3)?,
+                    archetype: row.get(4)?,
+                    opponent_archetype: row.get(5)?,
+                    match_started_at: parse_timestamp(&match_started_at).unwrap_or_default(),
+                    match_result: match_result.as_deref().and_then(parse_outcome),
+                    game_number: row.get(8)?,
+                    started_at: parse_timestamp(&started_at).unwrap_or_default(),
+                    on_play: row.get(10)?,
+                    mulligans: row.get(11)?,
+                    result: result.as_deref().and_then(parse_outcome),
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(games)
+    }
+
+    /// Every opening hand of the matches a query selects, oldest first (`limit`
+    /// and `offset` are ignored)
+    pub fn opening_hands(&self, query: &LibraryQuery) -> Result<Vec<LibraryHand>, LibraryError> {
+        let (where_clause, values) = filter_clause(query);
+        let sql = format!(
+            "SELECT h.replay_id, r.path, m.record_id, m.format, m.opponent, m.deck_name, m.archetype, \
+             m.opponent_archetype, g.game_number, g.game_id, g.on_play, h.mulligans, h.kept, g.result \
+             FROM opening_hands h \
+             JOIN games g ON g.replay_id = h.replay_id AND g.game_id = h.game_id \
+             JOIN matches m ON m.replay_id = h.replay_id \
+             JOIN replays r ON r.id = h.replay_id {} \
+             ORDER BY m.started_at, m.replay_id, g.game_number, h.mulligans",
+            where_clause
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut hands = stmt
+            .query_map(params_from_iter(values.iter()), |row| {
+                let replay_id: i64 = row.get(0)?;
+                let path: String = row.get(1)?;
+                let result: Option<String> = row.get(13)?;
+                Ok((
+                    replay_id,
+                    LibraryHand {
+                        path: PathBuf::from(path),
+                        record_id: row.get(2)?,
+                        format: row.get(3)?,
+                        opponent: row.get(4)?,
+                        deck_name: row.get(5)?,
+                        archetype: row.get(6)?,
+                        opponent_archetype: row.get(7)?,
+                        game_number: row.get(8)?,
+                        game_id: row.get(9)?,
+                        on_play: row.get(10)?,
+                        mulligans: row.get(11)?,
+                        kept: row.get(12)?,
+                        cards: Vec::new(),
+                        bottomed: Vec::new(),
+                        result: result.as_deref().and_then(parse_outcome),
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut card_stmt = self.conn.prepare(
+            "SELECT card_id, quantity, bottomed FROM opening_hand_cards \
+             WHERE replay_id = ?1 AND game_id = ?2 AND mulligans = ?3 ORDER BY card_id",
+        )?;
+        for (replay_id, hand) in &mut hands {
+            let rows = card_stmt
+                .query_map(params![*replay_id, hand.game_id, hand.mulligans], |row| {
+                    Ok((
+                        row.get::<_, u32>(0)?,
+                        row.get::<_, usize>(1)?,
+                        row.get::<_, usize>(2)?,
+                    ))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (card_id, quantity, bottomed) in rows {
+                hand.cards.resize(hand.cards.len() + quantity, card_id);
+                hand.bottomed
+                    .resize(hand.bottomed.len() + bottomed, card_id);
+            }
+        }
+        Ok(hands.into_iter().map(|(_, hand)| hand).collect())
+    }
+
+    /// The user's cards in every game of the matches a query selects: each card
+    /// of the registered maindeck and each card seen in hand or cast, oldest
+    /// game first (`limit` and `offset` are ignored)
+    pub fn card_games(&self, query: &LibraryQuery) -> Result<Vec<LibraryCardGame>, LibraryError> {
+        let (where_clause, values) = filter_clause(query);
+        let sql = format!(
+            "WITH keys AS ( \
+                 SELECT replay_id, game_id, card_id FROM card_games \
+                 UNION \
+                 SELECT g.replay_id, g.game_id, d.card_id FROM deck_cards d \
+                 JOIN games g ON g.replay_id = d.replay_id AND g.game_number = d.game_number \
+                 WHERE d.board = 'main' \
+             ) \
+             SELECT m.record_id, m.format, m.deck_name, g.game_number, g.on_play, g.result, k.card_id, \
+             COALESCE(d.quantity, 0), COALESCE(c.opening_hand, 0), COALESCE(c.drawn, 0), \
+             COALESCE(c.casts, 0), c.first_cast_turn \
+             FROM keys k \
+             JOIN games g ON g.replay_id = k.replay_id AND g.game_id = k.game_id \
+             JOIN matches m ON m.replay_id = k.replay_id \
+             LEFT JOIN card_games c \
+                 ON c.replay_id = k.replay_id AND c.game_id = k.game_id AND c.card_id = k.card_id \
+             LEFT JOIN deck_cards d ON d.replay_id = g.replay_id AND d.game_number = g.game_number \
+                 AND d.board = 'main' AND d.card_id = k.card_id {} \
+             ORDER BY m.started_at, m.replay_id, g.game_number, k.card_id",
+            where_clause
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let cards = stmt
+            .query_map(params_from_iter(values.iter()), |row| {
+                let result: Option<String> = row.get(5)?;
+                Ok(LibraryCardGame {
+                    record_id: row.get(0)?,
+                    format: row.get(1)?,
+                    deck_name: row.get(2)?,
+                    game_number: row.get(3)?,
+                    on_play: row.get(4)?,
+                    result: result.as_deref().and_then(parse_outcome),
+                    card_id: row.get(6)?,
+                    in_deck: row.get(7)?,
+                    opening_hand: row.get(8)?,
+                    drawn: row.get(9)?,
+                    casts: row.get(10)?,
+                    first_cast_turn: row.get(11)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(cards)
+    }
+
+    /// Decks of the matches not yet classified with a given version of the
+    /// archetype definitions
+    ///
+    /// # Arguments
+    /// * `version` - Classification version; None selects every match
+    pub fn decks_to_classify(
+        &self,
+        version: Option<&str>,
+    ) -> Result<Vec<MatchDecks>, LibraryError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT m.replay_id, r.path, m.format FROM matches m JOIN replays r ON r.id = m.replay_id \
+             WHERE ?1 IS NULL OR m.classified_with IS NULL OR m.classified_with != ?1 ORDER BY m.replay_id",
+        )?;
+        let matches = stmt
+            .query_map([version], |row| {
+                let path: String = row.get(1)?;
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    PathBuf::from(path),
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // Every card the user registered in any game, and every opponent card seen
+        let mut card_stmt = self.conn.prepare(
+            "SELECT DISTINCT board = 'opponent_seen', card_id FROM deck_cards \
+             WHERE replay_id = ?1 AND board IN ('main', 'opponent_seen') ORDER BY card_id",
+        )?;
+        let mut decks = Vec::with_capacity(matches.len());
+        for (replay_id, path, format) in matches {
+            let mut entry = MatchDecks {
+                path,
+                format,
+                deck: Vec::new(),
+                opponent_cards: Vec::new(),
+            };
+            let cards = card_stmt
+                .query_map([replay_id], |row| {
+                    Ok((row.get::<_, bool>(0)?, row.get::<_, u32>(1)?))
+                })?
+                .collect::<Result<Vec<_>, _>>()?;
+            for (opponent, card_id) in cards {
+                if opponent {
+                    entry.opponent_cards.push(card_id);
+                } else {
+                    entry.deck.push(card_id);
+                }
+            }
+            decks.push(entry);
+        }
+        Ok(decks)
+    }
+
+    /// Store the archetypes of classified matches
+    ///
+    /// # Arguments
+    /// * `version` - Classification version the archetypes were assigned with
+    /// * `assignments` - Archetypes by replay; None for unknown
+    pub fn set_archetypes(
+        &mut self,
+        version: &str,
+        assignments: &[ArchetypeAssignment],
+    ) -> Result<(), LibraryError> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "UPDATE matches SET archetype = ?1, archetype_confidence = ?2, opponent_archetype = ?3, \
+                 opponent_archetype_confidence = ?4, classified_with = ?5 \
+                 WHERE replay_id = (SELECT id FROM replays WHERE path = ?6)",
+            )?;
+            for assignment in assignments {
+                stmt.execute(params![
+                    assignment.archetype,
+                    assignment.archetype_confidence,
+                    assignment.opponent_archetype,
+                    assignment.opponent_archetype_confidence,
+                    version,
+                    assignment.path.to_string_lossy(),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// How often each card was played in one indexed match, by player
+    ///
+    /// # Returns
+    /// (side, catalog id, plays) over all games, most played first
+    pub fn card_plays(&self, path: &Path) -> Result<Vec<(PlayerSide, u32, u32)>, LibraryError> {
+        let mut stmt = self.conn.prepare(
+            "SELECT p.side, p.card_id, SUM(p.plays) AS total FROM card_plays p \
+             JOIN replays r ON r.id = p.replay_id WHERE r.path = ?1 \
+             GROUP BY p.side, p.card_id ORDER BY total DESC, p.side, p.card_id",
+        )?;
+        let plays = stmt
+            .query_map([path.to_string_lossy()], |row| {
+                let side: String = row.get(0)?;
+                let side = if side == PlayerSide::Local.as_str() {
+                    PlayerSide::Local
+                } else {
+                    PlayerSide::Opponent
+                };
+                Ok((side, row.get(1)?, row.get(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(plays)
+    }
+
+    /// Annotations written on the matches a query selects, newest match first and
+    /// in timeline order within a replay
+    ///
+    /// # Arguments
+    /// * `query` - Match filters, with `limit` and `offset` paging the annotations
+    /// * `filter` - Tag, author, text and bookmark filters
+    pub fn annotations(
+        &self,
+        query: &LibraryQuery,
+        filter: &AnnotationQuery,
+    ) -> Result<Vec<LibraryAnnotation>, LibraryError> {
+        let (where_clause, mut values) = filter_clause(query);
+        let mut conditions = Vec::new();
+        for tag in filter
+            .tags
+            .iter()
+            .map(|tag| normalize_tag(tag))
+            .filter(|tag| !tag.is_empty())
+        {
+            let p = bind(Value::Text(tag), &mut values);
+            conditions.push(format!(
+                "EXISTS (SELECT 1 FROM annotation_tags t WHERE t.replay_id = a.replay_id \
+                 AND t.annotation_id = a.annotation_id AND t.tag = {})",
+                p
+            ));
+        }
+        if let Some(author) = filter
+            .author
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+        {
+            let p = bind(Value::Text(author.to_string()), &mut values);
+            conditions.push(format!("a.author = {} COLLATE NOCASE", p));
+        }
+        if let Some(text) = filter.text.as_deref().filter(|v| !v.is_empty()) {
+            let p = bind(Value::Text(like_pattern(text)), &mut values);
+            conditions.push(format!("a.text LIKE {} ESCAPE '\\'", p));
+        }
+        if filter.bookmarks_only {
+            conditions.push("a.bookmark = 1".to_string());
+        }
+        let where_clause = match (where_clause.is_empty(), conditions.is_empty()) {
+            (_, true) => where_clause,
+            (true, false) => format!("WHERE {}", conditions.join(" AND ")),
+            (false, false) => format!("{} AND {}", where_clause, conditions.join(" AND ")),
+        };
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_QUERY_LIMIT)
+            .min(MAX_QUERY_LIMIT);
+        let sql = format!(
+            "SELECT a.replay_id, r.path, m.record_id, m.format, m.opponent, m.started_at, g.game_number, \
+             a.annotation_id, a.game_id, a.turn, a.seq, a.text, a.bookmark, a.author, a.created_at, a.updated_at \
+             FROM annotations a \
+             JOIN matches m ON m.replay_id = a.replay_id \
+             JOIN replays r ON r.id = a.replay_id \
+             LEFT JOIN games g ON g.replay_id = a.replay_id AND g.game_id = a.game_id {} \
+             ORDER BY m.started_at DESC, a.replay_id DESC, a.game_id IS NOT NULL, a.game_id, a.turn, \
+             a.seq IS NOT NULL, a.seq, a.created_at, a.annotation_id LIMIT {} OFFSET {}",
+            where_clause, limit, query.offset
+        );
+
+        let mut stmt = self.conn.prepare(&sql)?;
+        let mut annotations = stmt
+            .query_map(params_from_iter(values.iter()), |row| {
+                let replay_id: i64 = row.get(0)?;
+                let path: String = row.get(1)?;
+                let match_started_at: String = row.get(5)?;
+                let created_at: String = row.get(14)?;
+                let updated_at: String = row.get(15)?;
+                Ok((
+                    replay_id,
+                    LibraryAnnotation {
+                        path: PathBuf::from(path),
+                        record_id: row.get(2)?,
+                        format: row.get(3)?,
+                        opponent: row.get(4)?,
+                        match_started_at: parse_timestamp(&match_started_at).unwrap_or_default(),
+                        game_number: row.get(6)?,
+                        annotation: Annotation {
+                            id: row.get(7)?,
+                            anchor: AnnotationAnchor {
+                                game_id: row.get(8)?,
+                                turn: row.get(9)?,
+                                seq: row.get::<_, Option<i64>>(10)?.map(|seq| seq as u64),
+                            },
+                            text: row.get(11)?,
+                            tags: Vec::new(),
+                            bookmark: row.get(12)?,
+                            author: row.get(13)?,
+                            created_at: parse_timestamp(&created_at).unwrap_or_default(),
+                            updated_at: parse_timestamp(&updated_at).unwrap_or_default(),
+                            deleted: false,
+                        },
+                    },
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut tag_stmt = self.conn.prepare(
+            "SELECT tag FROM annotation_tags WHERE replay_id = ?1 AND annotation_id = ?2 ORDER BY rowid",
+        )?;
+        for (replay_id, found) in &mut annotations {
+            found.annotation.tags = tag_stmt
+                .query_map(params![*replay_id, found.annotation.id], |row| row.get(0))?
+                .collect::<Result<Vec<_>, _>>()?;
+        }
+        Ok(annotations.into_iter().map(|(_, found)| found).collect())
+    }
+
+    /// Record counts and the last time a replay was indexed
+    pub fn status(&self) -> Result<LibraryStatus, LibraryError> {
+        let count = |sql: &str| -> Result<usize, LibraryError> {
+            let n: i64 = self.conn.query_row(sql, [], |row| row.get(0))?;
+            Ok(n as usize)
+        };
+        let last: Option<String> = self
+            .conn
+            .query_row("SELECT MAX(indexed_at) FROM replays", [], |row| row.get(0))
+            .optional()?
+            .flatten();
+        Ok(LibraryStatus {
+            replays: count("SELECT COUNT(*) FROM replays")?,
+            matches: count("SELECT COUNT(*) FROM matches")?,
+            games: count("SELECT COUNT(*) FROM games")?,
+            last_indexed_at: last.as_deref().and_then(parse_timestamp),
+        })
+    }
+}
+
+/// WHERE clause (over `matches m`) and parameters for the filters of a query;
+/// paging is left to the caller
+fn filter_clause(query: &LibraryQuery) -> (String, Vec<Value>) {
+    let mut conditions = Vec::new();
+    let mut values: Vec<Value> = Vec::new();
+
+    if let Some(from) = &query.from {
+        let p = bind(Value::Text(timestamp(from)), &mut values);
+        conditions.push(format!("m.started_at >= {}", p));
+    }
+    if let Some(to) = &query.to {
+        let p = bind(Value::Text(timestamp(to)), &mut values);
+        conditions.push(format!("m.started_at < {}", p));
+    }
+    if let Some(format) = query.format.as_deref().filter(|v| !v.is_empty()) {
+        let p = bind(Value::Text(format.to_string()), &mut values);
+        conditions.push(format!("m.format = {} COLLATE NOCASE", p));
+    }
+    if let Some(opponent) = query.opponent.as_deref().filter(|v| !v.is_empty()) {
+        let p = bind(Value::Text(like_pattern(opponent)), &mut values);
+        conditions.push(format!("m.opponent LIKE {} ESCAPE '\\'", p));
+    }
+    if let Some(archetype) = query.archetype.as_deref().filter(|v| !v.is_empty()) {
+        let p = bind(Value::Text(archetype.to_string()), &mut values);
+        conditions.push(format!(
+            "(m.archetype = {p} COLLATE NOCASE OR m.deck_name = {p} COLLATE NOCASE)"
+        ));
+    }
+    if let Some(archetype) = query
+        .opponent_archetype
+        .as_deref()
+        .filter(|v| !v.is_empty())
+    {
+        let p = bind(Value::Text(archetype.to_string()), &mut values);
+        conditions.push(format!("m.opponent_archetype = {} COLLATE NOCASE", p));
+    }
+    if let Some(result) = query.result {
+        match result.outcome() {
+            Some(outcome) => {
+                let p = bind(Value::Text(outcome_str(outcome).to_string()), &mut values);
+                conditions.push(format!("m.result = {}", p));
+            }
+            None => conditions.push("m.result IS NULL".to_string()),
+        }
+    }
+    if !query.card_played.is_empty() {
+        let ids: Vec<String> = query
+            .card_played
+            .iter()
+            .map(|&id| bind(Value::Integer(i64::from(id)), &mut values))
+            .collect();
+        let mut condition = format!(
+            "EXISTS (SELECT 1 FROM card_plays p WHERE p.replay_id = m.replay_id AND p.card_id IN ({})",
+            ids.join(", ")
+        );
+        if let Some(side) = query.played_by {
+            let p = bind(Value::Text(side.as_str().to_string()), &mut values);
+            condition.push_str(&format!(" AND p.side = {}", p));
+        }
+        condition.push(')');
+        conditions.push(condition);
+    }
+
+    let where_clause = if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    };
+    (where_clause, values)
+}
+
+/// Add a query parameter, returning its placeholder
+fn bind(value: Value, values: &mut Vec<Value>) -> String {
+    values.push(value);
+    format!("?{}", values.len())
+}
+
+/// Escape LIKE wildcards so user input matches literally
+fn like_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{}%", escaped)
+}
+
+/// Minimal record for a replay finished without one, from its header
+fn header_record<R: std::io::Read + std::io::Seek>(reader: &ReplayReader<R>) -> Option<Match> {
+    let header = reader.header();
+    let info = header.match_info.as_ref()?;
+    let non_empty = |s: &str| (!s.is_empty()).then(|| s.to_string());
+    Some(Match {
+        id: info.match_id,
+        format: info.format.clone(),
+        event_type: EventType::parse(&info.event_type),
+        event_name: info.event_type.clone(),
+        best_of: info.best_of,
+        local_player: non_empty(&info.local_player),
+        opponent: non_empty(&info.opponent),
+        started_at: header.created_at,
+        ended_at: None,
+        games: Vec::new(),
+        result: None,
+        end_reason: None,
+        session_id: header.session_id.clone(),
+        intended_deck: None,
+    })
+}
+
+/// Play counts keyed by (game id, side, catalog id)
+type PlayCounts = BTreeMap<(u32, PlayerSide, u32), u32>;
+
+/// One of the local player's cards in one game
+#[derive(Debug, Clone, Copy, Default)]
+struct CardGame {
+    /// In the kept opening hand (not bottomed)
+    opening_hand: bool,
+    /// Drawn (put into hand) after the game began
+    drawn: bool,
+    /// Cast or put onto the battlefield
+    casts: u32,
+    first_cast_turn: Option<u32>,
+}
+
+/// Local cards keyed by (game id, catalog id)
+type CardGames = BTreeMap<(u32, u32), CardGame>;
+
+/// What indexing reads from the event stream of a replay
+struct EventSummary {
+    plays: PlayCounts,
+    hands: Vec<OpeningHand>,
+    cards: CardGames,
+}
+
+/// Count the cards each player cast or put onto the battlefield, game by game,
+/// and collect the local player's opening hands and how each of their cards
+/// was drawn and cast
+///
+/// The local player is the one the deck was submitted for, or failing that the
+/// player named like the record's local player.
+fn read_events<R: std::io::Read + std::io::Seek>(
+    reader: &mut ReplayReader<R>,
+    record: &Match,
+) -> Result<EventSummary, ReplayError> {
+    let mut plays = PlayCounts::new();
+    let mut hands = Vec::new();
+    let mut cards = CardGames::new();
+    let mut tracker = OpeningHandTracker::new(record.local_player.clone());
+    let mut local: Option<PlayerId> = None;
+    let mut names: HashMap<PlayerId, String> = HashMap::new();
+    let mut turn: Option<u32> = None;
+    let is_local =
+        |player: &PlayerId, local: Option<PlayerId>, names: &HashMap<PlayerId, String>| {
+            local == Some(*player)
+                || names
+                    .get(player)
+                    .zip(record.local_player.as_ref())
+                    .is_some_and(|(name, local)| name.eq_ignore_ascii_case(local))
+        };
+    let entries = reader.index().to_vec();
+    for entry in entries
+        .iter()
+        .filter(|e| e.chunk_kind() == Some(ChunkKind::Events))
+    {
+        let Chunk::Events(events) = reader.read_chunk(entry)? else {
+            continue;
+        };
+        for event in &events {
+            if let Some(hand) = tracker.apply(event) {
+                if hand.kept {
+                    let mut held = hand.cards.clone();
+                    for card_id in &hand.bottomed {
+                        if let Some(i) = held.iter().position(|held| held == card_id) {
+                            held.remove(i);
+                        }
+                    }
+                    for card_id in held {
+                        cards
+                            .entry((hand.game_id, card_id))
+                            .or_default()
+                            .opening_hand = true;
+                    }
+                }
+                hands.push(hand);
+            }
+            match &event.kind {
+                GameEventKind::GameStarted { .. } => {
+                    names.clear();
+                    turn = None;
+                }
+                GameEventKind::PlayerJoined { player, name, .. } => {
+                    names.insert(*player, name.clone());
+                }
+                GameEventKind::DeckSubmitted { player, .. } => local = Some(*player),
+                GameEventKind::TurnStarted { turn: number, .. } => turn = Some(*number),
+                GameEventKind::ZoneChanged {
+                    card_id: Some(card_id),
+                    controller,
+                    ..
+                } if event.kind.is_play() => {
+                    let Some(game_id) = event.game_id else {
+                        continue;
+                    };
+                    let side = if is_local(controller, local, &names) {
+                        let card = cards.entry((game_id, *card_id)).or_default();
+                        card.casts += 1;
+                        card.first_cast_turn = card.first_cast_turn.or(turn);
+                        PlayerSide::Local
+                    } else {
+                        PlayerSide::Opponent
+                    };
+                    *plays.entry((game_id, side, *card_id)).or_insert(0) += 1;
+                }
+                GameEventKind::ZoneChanged {
+                    card_id: Some(card_id),
+                    owner,
+                    from,
+                    to: Zone::Hand,
+                    ..
+                } if turn.is_some()
+                    && matches!(from, None | Some(Zone::Library))
+                    && is_local(owner, local, &names) =>
+                {
+                    if let Some(game_id) = event.game_id {
+                        cards.entry((game_id, *card_id)).or_default().drawn = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    Ok(EventSummary {
+        plays,
+        hands,
+        cards,
+    })
+}
+
+fn insert_match(
+    tx: &Transaction,
+    replay_id: i64,
+    path: &Path,
+    record: &Match,
+) -> Result<(), LibraryError> {
+    tx.execute(
+        "INSERT INTO matches (replay_id, record_id, match_id, format, event_type, event_name, best_of, \
+         local_player, opponent, started_at, ended_at, result, end_reason, games_won, games_lost, deck_name) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+        params![
+            replay_id,
+            record_id(path).map(str::to_string).unwrap_or_else(|| record.record_id()),
+            record.id,
+            record.format,
+            record.event_type.as_str(),
+            record.event_name,
+            record.best_of,
+            record.local_player,
+            record.opponent,
+            timestamp(&record.started_at),
+            record.ended_at.as_ref().map(timestamp),
+            record.result.map(outcome_str),
+            record.end_reason,
+            record.wins() as i64,
+            record.losses() as i64,
+            record.intended_deck.as_ref().map(|d| d.name.clone()),
+        ],
+    )?;
+
+    let mut game_stmt = tx.prepare(
+        "INSERT INTO games (replay_id, game_number, game_id, started_at, ended_at, on_play, mulligans, \
+         opponent_mulligans, turns, result, end_reason) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+    )?;
+    let mut card_stmt = tx.prepare(
+        "INSERT OR REPLACE INTO deck_cards (replay_id, game_number, board, card_id, quantity) \
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    let mut plan_stmt = tx.prepare(
+        "INSERT OR REPLACE INTO sideboard_plans (replay_id, game_number, direction, card_id, quantity) \
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for game in &record.games {
+        game_stmt.execute(params![
+            replay_id,
+            game.number,
+            game.game_id,
+            timestamp(&game.started_at),
+            game.ended_at.as_ref().map(timestamp),
+            game.on_play,
+            game.mulligans,
+            game.opponent_mulligans,
+            game.turns,
+            game.result.map(outcome_str),
+            game.end_reason,
+        ])?;
+
+        let boards = game
+            .deck
+            .iter()
+            .flat_map(|deck| [("main", &deck.maindeck), ("sideboard", &deck.sideboard)])
+            .chain([("opponent_seen", &game.opponent_cards)]);
+        for (board, cards) in boards {
+            for (card_id, quantity) in cards {
+                card_stmt.execute(params![replay_id, game.number, board, card_id, quantity])?;
+            }
+        }
+
+        if let Some(change) = &game.sideboard {
+            let plan = change.effective();
+            let moves: [(&str, &[CardCount]); 2] =
+                [("in", &plan.cards_in), ("out", &plan.cards_out)];
+            for (direction, cards) in moves {
+                for card in cards {
+                    plan_stmt.execute(params![
+                        replay_id,
+                        game.number,
+                        direction,
+                        card.card_id,
+                        card.count
+                    ])?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn insert_plays(tx: &Transaction, replay_id: i64, plays: &PlayCounts) -> Result<(), LibraryError> {
+    let mut stmt = tx.prepare(
+        "INSERT INTO card_plays (replay_id, game_id, side, card_id, plays) VALUES (?1, ?2, ?3, ?4, ?5)",
+    )?;
+    for ((game_id, side, card_id), count) in plays {
+        stmt.execute(params![replay_id, game_id, side.as_str(), card_id, count])?;
+    }
+    Ok(())
+}
+
+fn insert_hands(
+    tx: &Transaction,
+    replay_id: i64,
+    hands: &[OpeningHand],
+) -> Result<(), LibraryError> {
+    let mut hand_stmt = tx.prepare(
+        "INSERT OR REPLACE INTO opening_hands (replay_id, game_id, mulligans, kept) VALUES (?1, ?2, ?3, ?4)",
+    )?;
+    let mut card_stmt = tx.prepare(
+        "INSERT INTO opening_hand_cards (replay_id, game_id, mulligans, card_id, quantity, bottomed) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for hand in hands {
+        hand_stmt.execute(params![replay_id, hand.game_id, hand.mulligans, hand.kept])?;
+        let mut cards: BTreeMap<u32, (u32, u32)> = BTreeMap::new();
+        for card_id in &hand.cards {
+            cards.entry(*card_id).or_default().0 += 1;
+        }
+        for card_id in &hand.bottomed {
+            cards.entry(*card_id).or_default().1 += 1;
+        }
+        tx.execute(
+            "DELETE FROM opening_hand_cards WHERE replay_id = ?1 AND game_id = ?2 AND mulligans = ?3",
+            params![replay_id, hand.game_id, hand.mulligans],
+        )?;
+        for (card_id, (quantity, bottomed)) in cards {
+            card_stmt.execute(params![
+                replay_id,
+                hand.game_id,
+                hand.mulligans,
+                card_id,
+                quantity,
+                bottomed
+            ])?;
+        }
+    }
+    Ok(())
+}
+
+fn insert_card_games(
+    tx: &Transaction,
+    replay_id: i64,
+    cards: &CardGames,
+) -> Result<(), LibraryError> {
+    let mut stmt = tx.prepare(
+        "INSERT INTO card_games (replay_id, game_id, card_id, opening_hand, drawn, casts, first_cast_turn) \
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    for ((game_id, card_id), card) in cards {
+        stmt.execute(params![
+            replay_id,
+            game_id,
+            card_id,
+            card.opening_hand,
+            card.drawn,
+            card.casts,
+            card.first_cast_turn,
+        ])?;
+    }
+    Ok(())
+}
+
+fn insert_annotations(
+    tx: &Transaction,
+    replay_id: i64,
+    annotations: &ReplayAnnotations,
+) -> Result<(), LibraryError> {
+    let mut stmt = tx.prepare(
+        "INSERT OR REPLACE INTO annotations (replay_id, annotation_id, game_id, turn, seq, text, bookmark, author, \
+         created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+    )?;
+    let mut tag_stmt = tx.prepare(
+        "INSERT OR IGNORE INTO annotation_tags (replay_id, annotation_id, tag) VALUES (?1, ?2, ?3)",
+    )?;
+    for annotation in annotations.visible() {
+        stmt.execute(params![
+            replay_id,
+            annotation.id,
+            annotation.anchor.game_id,
+            annotation.anchor.turn,
+            annotation.anchor.seq.map(|seq| seq as i64),
+            annotation.text,
+            annotation.bookmark,
+            annotation.author,
+            timestamp(&annotation.created_at),
+            timestamp(&annotation.updated_at),
+        ])?;
+        for tag in &annotation.tags {
+            tag_stmt.execute(params![replay_id, annotation.id, normalize_tag(tag)])?;
+        }
+    }
+    Ok(())
+}
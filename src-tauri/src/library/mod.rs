@@ -0,0 +1,258 @@
+//! Searchable library of saved replays (STAT-004)
+//!
+//! An embedded SQLite index of the replay directory: match metadata, the decks
+//! registered for each game, sideboard plans, opening hands, when the user's
+//! cards were drawn and cast, how often each card was played, and the
+//! annotations written on each replay, with the path of the replay every row
+//! came from. The replay files and their annotations files stay the source of
+//! truth; the index can be deleted and rebuilt by rescanning.
+//!
+//! `watcher` keeps the index in step with the replay directory, indexing
+//! replays as they are finished and dropping rows of deleted ones.
+
+pub mod db;
+pub mod watcher;
+
+use crate::game::lifecycle::Outcome;
+use crate::replay::Annotation;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+pub use db::ReplayLibrary;
+
+/// Which player a play count belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerSide {
+    /// The capturing user
+    Local,
+    Opponent,
+}
+
+impl PlayerSide {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PlayerSide::Local => "local",
+            PlayerSide::Opponent => "opponent",
+        }
+    }
+}
+
+/// Library search filters; every present filter must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LibraryQuery {
+    /// Matches started at or after this time
+    pub from: Option<DateTime<Utc>>,
+    /// Matches started before this time
+    pub to: Option<DateTime<Utc>>,
+    /// Exact format, case-insensitive (e.g. "Modern")
+    pub format: Option<String>,
+    /// Case-insensitive substring of the opponent's name
+    pub opponent: Option<String>,
+    /// Archetype of the user's deck, or the name the deck was tagged with
+    pub archetype: Option<String>,
+    /// Archetype of the opponent's deck
+    pub opponent_archetype: Option<String>,
+    /// Match result; use `undecided` to find matches without one
+    pub result: Option<ResultFilter>,
+    /// Catalog ids of a card's printings: matches in which any of them was played
+    #[serde(default)]
+    pub card_played: Vec<u32>,
+    /// Restrict `card_played` to one player (default: either)
+    pub played_by: Option<PlayerSide>,
+    /// Maximum number of results (default 100, capped at 1000)
+    pub limit: Option<usize>,
+    /// Results to skip, for paging
+    #[serde(default)]
+    pub offset: usize,
+}
+
+/// Result filter of a library query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResultFilter {
+    Win,
+    Loss,
+    Draw,
+    /// Unfinished matches and matches whose winner could not be attributed
+    Undecided,
+}
+
+impl ResultFilter {
+    /// Outcome the match result must equal; None for undecided matches
+    pub fn outcome(self) -> Option<Outcome> {
+        match self {
+            ResultFilter::Win => Some(Outcome::Win),
+            ResultFilter::Loss => Some(Outcome::Loss),
+            ResultFilter::Draw => Some(Outcome::Draw),
+            ResultFilter::Undecided => None,
+        }
+    }
+}
+
+/// One indexed match, as returned by library queries
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LibraryMatch {
+    /// Replay file the match was indexed from
+    pub path: PathBuf,
+    pub record_id: String,
+    pub match_id: u32,
+    pub format: String,
+    pub event_name: String,
+    pub best_of: u8,
+    pub local_player: Option<String>,
+    pub opponent: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+    pub result: Option<Outcome>,
+    pub games_won: u32,
+    pub games_lost: u32,
+    pub games: u32,
+    /// Name of the deck list the match was tagged with
+    pub deck_name: Option<String>,
+    /// Set by archetype classification; None until classified or if unknown
+    pub archetype: Option<String>,
+    pub opponent_archetype: Option<String>,
+    /// Confidence of the classification, between 0 and 1
+    pub archetype_confidence: Option<f64>,
+    pub opponent_archetype_confidence: Option<f64>,
+}
+
+/// One game of an indexed match, with the match attributes statistics group by
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryGame {
+    pub record_id: String,
+    pub format: String,
+    pub opponent: Option<String>,
+    pub deck_name: Option<String>,
+    pub archetype: Option<String>,
+    pub opponent_archetype: Option<String>,
+    pub match_started_at: DateTime<Utc>,
+    pub match_result: Option<Outcome>,
+    /// Position in the match, starting at 1
+    pub game_number: u8,
+    pub started_at: DateTime<Utc>,
+    pub on_play: Option<bool>,
+    pub mulligans: u8,
+    pub result: Option<Outcome>,
+}
+
+/// One opening hand of an indexed game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryHand {
+    /// Replay file the hand was indexed from
+    pub path: PathBuf,
+    pub record_id: String,
+    pub format: String,
+    pub opponent: Option<String>,
+    pub deck_name: Option<String>,
+    pub archetype: Option<String>,
+    pub opponent_archetype: Option<String>,
+    pub game_number: u8,
+    pub game_id: u32,
+    pub on_play: Option<bool>,
+    /// Mulligans taken before this hand was drawn
+    pub mulligans: u8,
+    pub kept: bool,
+    /// Catalog ids of the cards drawn, sorted
+    pub cards: Vec<u32>,
+    /// Cards put on the bottom after keeping, sorted
+    pub bottomed: Vec<u32>,
+    /// Result of the game the hand was drawn in
+    pub result: Option<Outcome>,
+}
+
+/// One of the user's cards in one indexed game
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryCardGame {
+    pub record_id: String,
+    pub format: String,
+    pub deck_name: Option<String>,
+    pub game_number: u8,
+    pub on_play: Option<bool>,
+    pub result: Option<Outcome>,
+    pub card_id: u32,
+    /// Copies in the registered maindeck; 0 if not in it or the deck is unknown
+    pub in_deck: u32,
+    /// In the kept opening hand (not bottomed)
+    pub opening_hand: bool,
+    /// Drawn after the game began
+    pub drawn: bool,
+    /// Times cast or put onto the battlefield
+    pub casts: u32,
+    pub first_cast_turn: Option<u32>,
+}
+
+/// Annotation filters of a library search, on top of the match filters; every
+/// present filter must match
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnnotationQuery {
+    /// Tags the annotation must all carry, case-insensitive (e.g. "misplay")
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Exact author, case-insensitive
+    pub author: Option<String>,
+    /// Case-insensitive substring of the text
+    pub text: Option<String>,
+    /// Only bookmarks
+    #[serde(default)]
+    pub bookmarks_only: bool,
+}
+
+/// One annotation of an indexed replay, with the match it was written on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryAnnotation {
+    /// Replay file the annotation belongs to
+    pub path: PathBuf,
+    pub record_id: String,
+    pub format: String,
+    pub opponent: Option<String>,
+    pub match_started_at: DateTime<Utc>,
+    /// Position in the match of the annotated game, if the game is known
+    pub game_number: Option<u8>,
+    pub annotation: Annotation,
+}
+
+/// Cards of an indexed match to classify
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MatchDecks {
+    pub path: PathBuf,
+    pub format: String,
+    /// Catalog ids in the user's registered maindecks
+    pub deck: Vec<u32>,
+    /// Catalog ids of the opponent's cards seen
+    pub opponent_cards: Vec<u32>,
+}
+
+/// Archetypes assigned to an indexed match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArchetypeAssignment {
+    pub path: PathBuf,
+    pub archetype: Option<String>,
+    pub archetype_confidence: Option<f64>,
+    pub opponent_archetype: Option<String>,
+    pub opponent_archetype_confidence: Option<f64>,
+}
+
+/// Outcome of bringing the library up to date with the replay directory
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanReport {
+    /// Replays indexed for the first time or re-indexed after a change
+    pub indexed: usize,
+    /// Replays already up to date
+    pub unchanged: usize,
+    /// Replays no longer on disk, dropped from the index
+    pub removed: usize,
+    /// Replays that could not be read: path and reason
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+/// Counts of the library
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LibraryStatus {
+    pub replays: usize,
+    pub matches: usize,
+    pub games: usize,
+    pub last_indexed_at: Option<DateTime<Utc>>,
+}
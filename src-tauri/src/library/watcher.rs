@@ -0,0 +1,167 @@
+use crate::archetype::{self, ArchetypeDefinitions};
+use crate::cards::CardDatabase;
+use crate::common::error::LibraryError;
+use crate::library::db::FileStamp;
+use crate::library::{ReplayLibrary, ScanReport};
+use crate::replay::anonymize;
+use crate::replay::format::REPLAY_EXTENSION;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// How often the replay directory is checked for new, changed and deleted replays
+pub const SCAN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Bring the library up to date with the replay directory
+///
+/// Replays are indexed when first seen and re-indexed when their file, their
+/// stored match record or their annotations change; rows of replays no longer on
+/// disk are dropped.
+/// Replays still being written (`.partial`) are left alone until finished, and
+/// anonymized copies of replays are left out so their matches count once.
+///
+/// # Arguments
+/// * `library` - Library to update
+/// * `replays_dir` - Directory of finished replays
+/// * `matches_dir` - Directory of stored match records, if any
+///
+/// # Returns
+/// Counts of replays indexed, unchanged and removed, and the replays that could not be read
+pub fn scan(
+    library: &mut ReplayLibrary,
+    replays_dir: &Path,
+    matches_dir: Option<&Path>,
+) -> Result<ScanReport, LibraryError> {
+    let indexed = library.indexed_files()?;
+    let mut report = ScanReport::default();
+    let mut seen = HashSet::new();
+
+    for path in replay_files(replays_dir)? {
+        seen.insert(path.clone());
+        let stamp = match FileStamp::of(&path, matches_dir) {
+            Ok(stamp) => stamp,
+            // Deleted between listing and reading; dropped on the next scan
+            Err(e) => {
+                report.failed.push((path, e.to_string()));
+                continue;
+            }
+        };
+        if indexed.get(&path) == Some(&stamp) {
+            report.unchanged += 1;
+            continue;
+        }
+        match library.index_replay(&path, matches_dir) {
+            Ok(()) => {
+                debug!("Indexed replay {}", path.display());
+                report.indexed += 1;
+            }
+            // Renamed copies are only told apart by their header; drop any stale row
+            Err(LibraryError::Anonymized(_)) => {
+                debug!("Skipped anonymized replay {}", path.display());
+                seen.remove(&path);
+            }
+            Err(e @ (LibraryError::Index { .. } | LibraryError::Io(_))) => {
+                report.failed.push((path, e.to_string()))
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    for path in indexed.keys().filter(|path| !seen.contains(*path)) {
+        if library.remove(path)? {
+            report.removed += 1;
+        }
+    }
+    Ok(report)
+}
+
+/// Finished replays in a directory, without anonymized copies; none if it does not exist yet
+fn replay_files(dir: &Path) -> Result<Vec<PathBuf>, LibraryError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut files = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_file()
+            && path.extension().and_then(|e| e.to_str()) == Some(REPLAY_EXTENSION)
+            && !anonymize::is_default_output(&path)
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Files the library watcher reads and updates
+#[derive(Debug, Clone)]
+pub struct WatchPaths {
+    /// Library database
+    pub db_path: PathBuf,
+    /// Directory of finished replays
+    pub replays_dir: PathBuf,
+    /// Directory of stored match records
+    pub matches_dir: PathBuf,
+    /// Card database naming the cards of decks to classify
+    pub card_db_path: PathBuf,
+    /// Archetype definitions
+    pub archetypes_path: PathBuf,
+}
+
+/// Keep the library up to date with the replay directory, forever
+///
+/// The directory is polled every `interval` rather than watched through OS
+/// notifications: a replay is only finished once per match, so a few seconds of
+/// delay go unnoticed, and polling behaves the same on every file system.
+/// After each scan, matches indexed since, or classified with other archetype
+/// definitions, are classified. Failures are logged and retried on the next scan.
+///
+/// # Arguments
+/// * `paths` - Library, replay, match record, card database and definition paths
+/// * `interval` - Time between scans
+pub async fn watch(paths: WatchPaths, interval: Duration) {
+    let mut failed: HashSet<PathBuf> = HashSet::new();
+    loop {
+        let task_paths = paths.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            let paths = task_paths;
+            let mut library = ReplayLibrary::open(&paths.db_path)?;
+            let report = scan(&mut library, &paths.replays_dir, Some(&paths.matches_dir))?;
+            let classified =
+                ArchetypeDefinitions::load(&paths.archetypes_path).and_then(|definitions| {
+                    let cards = CardDatabase::open(&paths.card_db_path)?;
+                    archetype::reclassify(&mut library, &cards, &definitions, false)
+                });
+            if let Err(e) = classified {
+                warn!("Archetype classification failed: {}", e);
+            }
+            Ok::<_, LibraryError>(report)
+        })
+        .await;
+
+        match result {
+            Ok(Ok(report)) => {
+                if report.indexed > 0 || report.removed > 0 {
+                    info!(
+                        "Replay library updated: {} indexed, {} removed",
+                        report.indexed, report.removed
+                    );
+                }
+                // Warn about each unreadable replay once, not on every scan
+                let now_failed: HashSet<PathBuf> =
+                    report.failed.iter().map(|(path, _)| path.clone()).collect();
+                for (path, reason) in &report.failed {
+                    if !failed.contains(path) {
+                        warn!("Replay {} not indexed: {}", path.display(), reason);
+                    }
+                }
+                failed = now_failed;
+            }
+            Ok(Err(e)) => warn!("Replay library scan failed: {}", e),
+            Err(e) => warn!("Replay library scan task failed: {}", e),
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
@@ -0,0 +1,264 @@
+use crate::common::error::ProtocolError;
+use crate::protocol::frame::Frame;
+use crate::protocol::quarantine::QuarantinedFrame;
+use crate::protocol::version::ClientVersion;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Session metadata file within an archive directory
+pub const MANIFEST_FILE: &str = "session.json";
+
+/// Every frame of the session, one JSON object per line, in capture order
+pub const FRAMES_FILE: &str = "frames.jsonl";
+
+/// Frames that failed to decode, with context
+pub const QUARANTINE_FILE: &str = "quarantine.jsonl";
+
+/// Metadata describing one capture session archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionManifest {
+    pub id: String,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Client version reported by the handshake, if seen
+    pub client_version: Option<ClientVersion>,
+    /// Schema set most recently used to decode the session
+    pub schema_version: ClientVersion,
+    pub frame_count: u64,
+    pub quarantined_count: u64,
+    pub last_redecoded_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// On-disk archive of one capture session
+///
+/// Each session gets its own directory under the sessions root containing the
+/// manifest, the raw frame log and the quarantine log. Logs are append-only JSON
+/// Lines so a crash loses at most the last partially written line (PERF-001).
+pub struct SessionArchive {
+    dir: PathBuf,
+    manifest: SessionManifest,
+    frames: BufWriter<File>,
+    quarantine: BufWriter<File>,
+}
+
+impl SessionArchive {
+    /// Create a new archive directory for a session starting now
+    pub fn create(
+        sessions_dir: &Path,
+        started_at: chrono::DateTime<chrono::Utc>,
+        schema_version: ClientVersion,
+    ) -> Result<Self, ProtocolError> {
+        let id = started_at.format("%Y%m%dT%H%M%S%.3fZ").to_string();
+        let dir = sessions_dir.join(&id);
+        std::fs::create_dir_all(&dir)?;
+
+        let manifest = SessionManifest {
+            id,
+            started_at,
+            ended_at: None,
+            client_version: None,
+            schema_version,
+            frame_count: 0,
+            quarantined_count: 0,
+            last_redecoded_at: None,
+        };
+        write_manifest(&dir, &manifest)?;
+
+        Ok(Self {
+            frames: BufWriter::new(open_append(&dir.join(FRAMES_FILE))?),
+            quarantine: BufWriter::new(open_append(&dir.join(QUARANTINE_FILE))?),
+            dir,
+            manifest,
+        })
+    }
+
+    pub fn id(&self) -> &str {
+        &self.manifest.id
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn manifest(&self) -> &SessionManifest {
+        &self.manifest
+    }
+
+    /// Append a raw frame to the frame log
+    pub fn append_frame(&mut self, frame: &Frame) -> Result<(), ProtocolError> {
+        write_json_line(&mut self.frames, frame)?;
+        self.manifest.frame_count += 1;
+        Ok(())
+    }
+
+    /// Append an undecodable frame to the quarantine log
+    pub fn quarantine(&mut self, entry: &QuarantinedFrame) -> Result<(), ProtocolError> {
+        write_json_line(&mut self.quarantine, entry)?;
+        self.manifest.quarantined_count += 1;
+        Ok(())
+    }
+
+    /// Record the negotiated client and schema versions
+    pub fn set_versions(
+        &mut self,
+        client_version: Option<&ClientVersion>,
+        schema_version: &ClientVersion,
+    ) {
+        self.manifest.client_version = client_version.cloned();
+        self.manifest.schema_version = schema_version.clone();
+    }
+
+    /// Flush buffered log lines and persist the manifest
+    pub fn flush(&mut self) -> Result<(), ProtocolError> {
+        self.frames.flush()?;
+        self.quarantine.flush()?;
+        write_manifest(&self.dir, &self.manifest)
+    }
+
+    /// Close the session, stamping its end time
+    pub fn finish(
+        mut self,
+        ended_at: chrono::DateTime<chrono::Utc>,
+    ) -> Result<SessionManifest, ProtocolError> {
+        self.manifest.ended_at = Some(ended_at);
+        self.flush()?;
+        Ok(self.manifest)
+    }
+}
+
+/// Resolve a session id to its archive directory
+///
+/// Session ids arrive from the UI, so anything that is not a plain directory name
+/// is rejected rather than joined onto the sessions root.
+pub fn session_dir(sessions_dir: &Path, session_id: &str) -> Result<PathBuf, ProtocolError> {
+    let is_plain = !session_id.is_empty()
+        && session_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        && session_id != "."
+        && session_id != "..";
+
+    let dir = sessions_dir.join(session_id);
+    if !is_plain || !dir.join(MANIFEST_FILE).exists() {
+        return Err(ProtocolError::SessionNotFound(session_id.to_string()));
+    }
+    Ok(dir)
+}
+
+/// Read a session's manifest
+pub fn read_manifest(dir: &Path) -> Result<SessionManifest, ProtocolError> {
+    let json = std::fs::read_to_string(dir.join(MANIFEST_FILE))?;
+    serde_json::from_str(&json).map_err(|e| ProtocolError::CorruptArchive(e.to_string()))
+}
+
+/// Persist a session's manifest atomically (write to temp file, then rename)
+pub fn write_manifest(dir: &Path, manifest: &SessionManifest) -> Result<(), ProtocolError> {
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| ProtocolError::CorruptArchive(e.to_string()))?;
+    let tmp = dir.join(format!("{}.tmp", MANIFEST_FILE));
+    std::fs::write(&tmp, json)?;
+    std::fs::rename(&tmp, dir.join(MANIFEST_FILE))?;
+    Ok(())
+}
+
+/// Manifests of every session under the sessions root, newest first
+pub fn list_sessions(sessions_dir: &Path) -> Result<Vec<SessionManifest>, ProtocolError> {
+    if !sessions_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut sessions = Vec::new();
+    for entry in std::fs::read_dir(sessions_dir)? {
+        let path = entry?.path();
+        if !path.join(MANIFEST_FILE).exists() {
+            continue;
+        }
+        match read_manifest(&path) {
+            Ok(manifest) => sessions.push(manifest),
+            Err(e) => warn!("Skipping session archive {}: {}", path.display(), e),
+        }
+    }
+
+    sessions.sort_by_key(|s| std::cmp::Reverse(s.started_at));
+    Ok(sessions)
+}
+
+/// Read the raw frame log of a session
+pub fn read_frames(dir: &Path) -> Result<Vec<Frame>, ProtocolError> {
+    read_json_lines(&dir.join(FRAMES_FILE))
+}
+
+/// Read every frame of a session, including quarantined ones, in capture order
+pub fn read_all_frames(dir: &Path) -> Result<Vec<Frame>, ProtocolError> {
+    let mut frames = read_frames(dir)?;
+    let archived: HashSet<u64> = frames.iter().map(|f| f.index).collect();
+    frames.extend(
+        read_quarantine(dir)?
+            .into_iter()
+            .filter(|q| !archived.contains(&q.frame.index))
+            .map(|q| q.frame),
+    );
+    frames.sort_by_key(|f| f.index);
+    Ok(frames)
+}
+
+/// Read the quarantine log of a session
+pub fn read_quarantine(dir: &Path) -> Result<Vec<QuarantinedFrame>, ProtocolError> {
+    read_json_lines(&dir.join(QUARANTINE_FILE))
+}
+
+/// Replace the quarantine log atomically
+pub fn write_quarantine(dir: &Path, entries: &[QuarantinedFrame]) -> Result<(), ProtocolError> {
+    let tmp = dir.join(format!("{}.tmp", QUARANTINE_FILE));
+    {
+        let mut writer = BufWriter::new(File::create(&tmp)?);
+        for entry in entries {
+            write_json_line(&mut writer, entry)?;
+        }
+        writer.flush()?;
+    }
+    std::fs::rename(&tmp, dir.join(QUARANTINE_FILE))?;
+    Ok(())
+}
+
+fn open_append(path: &Path) -> Result<File, ProtocolError> {
+    Ok(OpenOptions::new().create(true).append(true).open(path)?)
+}
+
+fn write_json_line<W: Write, T: Serialize>(writer: &mut W, value: &T) -> Result<(), ProtocolError> {
+    serde_json::to_writer(&mut *writer, value)
+        .map_err(|e| ProtocolError::CorruptArchive(e.to_string()))?;
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Read a JSON Lines file, skipping a truncated final line left by a crash
+fn read_json_lines<T: DeserializeOwned>(path: &Path) -> Result<Vec<T>, ProtocolError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut values = Vec::new();
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(&line) {
+            Ok(value) => values.push(value),
+            Err(e) => warn!(
+                "Skipping unreadable line {} of {}: {}",
+                number + 1,
+                path.display(),
+                e
+            ),
+        }
+    }
+
+    Ok(values)
+}
@@ -0,0 +1,183 @@
+use crate::common::error::ProtocolError;
+use crate::protocol::frame::Frame;
+use crate::protocol::packet::FlowKey;
+use crate::protocol::schema::DecodedMessage;
+use crate::protocol::version::{ClientVersion, SchemaRegistry};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// A capture session whose client version had no exactly matching schema set
+///
+/// These are persisted so the session can be re-decoded once schemas for the
+/// version become available.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnknownVersionSession {
+    /// Connection on which the handshake was seen
+    pub connection: FlowKey,
+    pub detected_at: chrono::DateTime<chrono::Utc>,
+    /// Version reported by the client
+    pub client_version: ClientVersion,
+    /// Version of the schema set used instead
+    pub fallback_version: ClientVersion,
+}
+
+/// Version-aware MTGO message decoder
+///
+/// Until a handshake or login message reveals the client version, frames are decoded
+/// with the newest known schema set. Once the version is detected, the matching set is
+/// selected for the rest of the session.
+pub struct Decoder {
+    registry: Arc<SchemaRegistry>,
+    /// Version reported by the client, once detected
+    client_version: Option<ClientVersion>,
+    /// Version of the schema set currently used for decoding
+    active_version: ClientVersion,
+    exact_match: bool,
+    unknown_sessions: Vec<UnknownVersionSession>,
+    unknown_version_log: Option<PathBuf>,
+}
+
+impl Decoder {
+    /// Create a decoder over a schema registry
+    ///
+    /// # Returns
+    /// Err(ProtocolError::NoSchemas) if the registry is empty
+    pub fn new(registry: Arc<SchemaRegistry>) -> Result<Self, ProtocolError> {
+        let active_version = registry
+            .latest()
+            .ok_or(ProtocolError::NoSchemas)?
+            .client_version
+            .clone();
+
+        Ok(Self {
+            registry,
+            client_version: None,
+            active_version,
+            exact_match: false,
+            unknown_sessions: Vec::new(),
+            unknown_version_log: None,
+        })
+    }
+
+    /// Append unknown-version sessions to a JSON Lines file as they are detected
+    pub fn with_unknown_version_log(mut self, path: PathBuf) -> Self {
+        self.unknown_version_log = Some(path);
+        self
+    }
+
+    /// Client version reported by the handshake, if seen yet
+    pub fn client_version(&self) -> Option<&ClientVersion> {
+        self.client_version.as_ref()
+    }
+
+    /// Version of the schema set currently in use
+    pub fn active_version(&self) -> &ClientVersion {
+        &self.active_version
+    }
+
+    /// True if the active schema set matches the detected client version exactly
+    pub fn is_exact_match(&self) -> bool {
+        self.exact_match
+    }
+
+    /// Sessions seen during this decoder's lifetime that used a fallback schema set
+    pub fn unknown_sessions(&self) -> &[UnknownVersionSession] {
+        &self.unknown_sessions
+    }
+
+    /// Shared schema registry
+    pub fn registry(&self) -> &Arc<SchemaRegistry> {
+        &self.registry
+    }
+
+    /// Decode a frame, switching schema sets if it announces the client version
+    pub fn decode(&mut self, frame: &Frame) -> Result<DecodedMessage, ProtocolError> {
+        if let Some(version) = self.registry.detect_version(frame.type_id, &frame.payload) {
+            if self.client_version.as_ref() != Some(&version) {
+                self.negotiate(version, frame)?;
+            }
+        }
+
+        self.registry
+            .get(&self.active_version)
+            .ok_or(ProtocolError::NoSchemas)?
+            .decode(frame.type_id, &frame.payload)
+    }
+
+    /// Select the schema set for a newly detected client version
+    fn negotiate(&mut self, version: ClientVersion, frame: &Frame) -> Result<(), ProtocolError> {
+        let (selected, exact) = {
+            let selection = self.registry.select(&version)?;
+            (selection.schemas.client_version.clone(), selection.exact)
+        };
+
+        if exact {
+            info!(
+                "MTGO client version {} detected, using matching schemas",
+                version
+            );
+        } else {
+            warn!(
+                "No protocol schemas for MTGO client {}, falling back to nearest known version {}",
+                version, selected
+            );
+            self.record_unknown(UnknownVersionSession {
+                connection: frame.flow.connection(),
+                detected_at: frame.timestamp,
+                client_version: version.clone(),
+                fallback_version: selected.clone(),
+            });
+        }
+
+        self.exact_match = exact;
+        self.active_version = selected;
+        self.client_version = Some(version);
+        Ok(())
+    }
+
+    fn record_unknown(&mut self, session: UnknownVersionSession) {
+        if let Some(path) = &self.unknown_version_log {
+            if let Err(e) = append_json_line(path, &session) {
+                error!(
+                    "Failed to record unknown-version session in {}: {}",
+                    path.display(),
+                    e
+                );
+            }
+        }
+        self.unknown_sessions.push(session);
+    }
+}
+
+/// Append one JSON value as a line to a file, creating it if needed
+fn append_json_line<T: Serialize>(path: &Path, value: &T) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    let line = serde_json::to_string(value)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+    writeln!(file, "{}", line)
+}
+
+/// Read back every unknown-version session recorded in a log file
+///
+/// Malformed lines (e.g. from a crash mid-write) are skipped.
+pub fn load_unknown_sessions(path: &Path) -> Result<Vec<UnknownVersionSession>, ProtocolError> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let contents = std::fs::read_to_string(path)?;
+    Ok(contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
@@ -0,0 +1,144 @@
+use crate::common::error::ProtocolError;
+use crate::protocol::packet::FlowKey;
+use serde::{Deserialize, Serialize};
+
+/// Length of the MTGO frame header: u32 payload length + u16 message type (little-endian)
+///
+/// The framing is provisional and based on observed traffic: every message on an MTGO
+/// connection starts with the payload length followed by a message type identifier.
+/// Schema files describe payloads only; the header is handled here.
+pub const FRAME_HEADER_LEN: usize = 6;
+
+/// Maximum accepted frame payload length
+///
+/// Anything larger is treated as a desynchronized stream rather than a real message,
+/// so a corrupted length field cannot trigger a huge allocation.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// Ports at or above this value are treated as client-side ephemeral ports
+const EPHEMERAL_PORT_START: u16 = 49152;
+
+/// Direction of a message relative to the local MTGO client
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Direction {
+    ClientToServer,
+    ServerToClient,
+}
+
+impl Direction {
+    /// Infer direction from a flow's ports
+    ///
+    /// The client connects from an ephemeral port, so the side with the ephemeral
+    /// (or otherwise higher) port is taken to be the client.
+    pub fn from_flow(flow: &FlowKey) -> Self {
+        let src_ephemeral = flow.src_port >= EPHEMERAL_PORT_START;
+        let dst_ephemeral = flow.dst_port >= EPHEMERAL_PORT_START;
+
+        match (src_ephemeral, dst_ephemeral) {
+            (true, false) => Direction::ClientToServer,
+            (false, true) => Direction::ServerToClient,
+            _ if flow.src_port >= flow.dst_port => Direction::ClientToServer,
+            _ => Direction::ServerToClient,
+        }
+    }
+}
+
+/// One framed MTGO message extracted from a reassembled TCP stream
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub flow: FlowKey,
+    pub direction: Direction,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Position of this frame within its capture session (all flows)
+    pub index: u64,
+    pub type_id: u16,
+    #[serde(with = "crate::common::hex")]
+    pub payload: Vec<u8>,
+}
+
+/// Encode a message type and payload into wire format (header + payload)
+pub fn encode_frame(type_id: u16, payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(FRAME_HEADER_LEN + payload.len());
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(&type_id.to_le_bytes());
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Incremental splitter turning a reassembled byte stream into frames
+///
+/// Bytes are appended as they arrive from the reassembler; complete frames are
+/// returned by `next_frame()`, partial frames stay buffered until more data arrives.
+#[derive(Debug, Default)]
+pub struct FrameReader {
+    buffer: Vec<u8>,
+    /// Read position within `buffer`; consumed bytes are compacted lazily
+    position: usize,
+}
+
+impl FrameReader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append stream bytes
+    pub fn push(&mut self, data: &[u8]) {
+        if self.position > 0 && self.position == self.buffer.len() {
+            self.buffer.clear();
+            self.position = 0;
+        }
+        self.buffer.extend_from_slice(data);
+    }
+
+    /// Number of buffered bytes not yet returned as frames
+    pub fn buffered(&self) -> usize {
+        self.buffer.len() - self.position
+    }
+
+    /// Discard all buffered bytes (used to resynchronize after a framing error)
+    pub fn reset(&mut self) {
+        self.buffer.clear();
+        self.position = 0;
+    }
+
+    /// Extract the next complete frame, if any
+    ///
+    /// # Returns
+    /// Ok(Some((type_id, payload))) when a complete frame is buffered
+    /// Ok(None) when more data is needed
+    /// Err(ProtocolError::FrameTooLarge) if the length field is implausible; the caller
+    /// should `reset()` since the stream position can no longer be trusted
+    pub fn next_frame(&mut self) -> Result<Option<(u16, Vec<u8>)>, ProtocolError> {
+        let available = &self.buffer[self.position..];
+        if available.len() < FRAME_HEADER_LEN {
+            self.compact();
+            return Ok(None);
+        }
+
+        let length =
+            u32::from_le_bytes([available[0], available[1], available[2], available[3]]) as usize;
+        if length > MAX_FRAME_LEN {
+            return Err(ProtocolError::FrameTooLarge(length, MAX_FRAME_LEN));
+        }
+
+        if available.len() < FRAME_HEADER_LEN + length {
+            self.compact();
+            return Ok(None);
+        }
+
+        let type_id = u16::from_le_bytes([available[4], available[5]]);
+        let payload = available[FRAME_HEADER_LEN..FRAME_HEADER_LEN + length].to_vec();
+        self.position += FRAME_HEADER_LEN + length;
+
+        Ok(Some((type_id, payload)))
+    }
+
+    /// Drop consumed bytes once they dominate the buffer
+    fn compact(&mut self) {
+        if self.position > 0 && self.position * 2 >= self.buffer.len() {
+            self.buffer.drain(..self.position);
+            self.position = 0;
+        }
+    }
+}
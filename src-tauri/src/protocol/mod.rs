@@ -0,0 +1,10 @@
+pub mod archive;
+pub mod decoder;
+pub mod frame;
+pub mod packet;
+pub mod quarantine;
+pub mod reassembly;
+pub mod schema;
+pub mod session;
+pub mod stream;
+pub mod version;
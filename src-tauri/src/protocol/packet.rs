@@ -0,0 +1,218 @@
+use crate::common::error::ProtocolError;
+use serde::{Deserialize, Serialize};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// IP protocol number for TCP
+const IPPROTO_TCP: u8 = 6;
+
+/// Minimum TCP header length (no options)
+const TCP_MIN_HEADER_LEN: usize = 20;
+
+/// Fixed IPv6 header length (extension headers are not supported)
+const IPV6_HEADER_LEN: usize = 40;
+
+/// Identifies one direction of a TCP connection
+///
+/// Each captured segment belongs to exactly one flow. The reverse direction of the
+/// same connection is a different flow; use `reversed()` to pair them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct FlowKey {
+    pub src_addr: IpAddr,
+    pub src_port: u16,
+    pub dst_addr: IpAddr,
+    pub dst_port: u16,
+}
+
+impl FlowKey {
+    /// Flow key for the opposite direction of the same connection
+    pub fn reversed(&self) -> FlowKey {
+        FlowKey {
+            src_addr: self.dst_addr,
+            src_port: self.dst_port,
+            dst_addr: self.src_addr,
+            dst_port: self.src_port,
+        }
+    }
+
+    /// Direction-independent key identifying the connection itself
+    ///
+    /// Both directions of a connection map to the same connection key, which is
+    /// used to group client and server traffic into one session.
+    pub fn connection(&self) -> FlowKey {
+        let reversed = self.reversed();
+        if *self <= reversed {
+            *self
+        } else {
+            reversed
+        }
+    }
+}
+
+impl std::fmt::Display for FlowKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{} -> {}:{}",
+            self.src_addr, self.src_port, self.dst_addr, self.dst_port
+        )
+    }
+}
+
+/// A parsed TCP segment with the fields needed for stream reassembly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TcpSegment {
+    pub flow: FlowKey,
+    pub seq: u32,
+    pub syn: bool,
+    pub fin: bool,
+    pub rst: bool,
+    pub payload: Vec<u8>,
+}
+
+/// Parse a raw IP packet (as delivered by WinDivert at the network layer) into a TCP segment
+///
+/// Supports IPv4 (with options) and IPv6 without extension headers. Packets that are
+/// not TCP, are fragmented, or have inconsistent length fields are rejected rather than
+/// guessed at, since the input is untrusted network data.
+///
+/// # Arguments
+/// * `data` - Raw packet bytes starting at the IP header
+///
+/// # Returns
+/// Ok(TcpSegment) for a well-formed TCP packet
+/// Err(ProtocolError::MalformedPacket) otherwise
+pub fn parse_packet(data: &[u8]) -> Result<TcpSegment, ProtocolError> {
+    let version = data
+        .first()
+        .map(|b| b >> 4)
+        .ok_or_else(|| ProtocolError::MalformedPacket("empty packet".to_string()))?;
+
+    let (src_addr, dst_addr, transport) = match version {
+        4 => parse_ipv4(data)?,
+        6 => parse_ipv6(data)?,
+        other => {
+            return Err(ProtocolError::MalformedPacket(format!(
+                "unsupported IP version {}",
+                other
+            )))
+        }
+    };
+
+    parse_tcp(src_addr, dst_addr, transport)
+}
+
+/// Parse an IPv4 header and return the addresses and transport payload
+fn parse_ipv4(data: &[u8]) -> Result<(IpAddr, IpAddr, &[u8]), ProtocolError> {
+    if data.len() < 20 {
+        return Err(ProtocolError::MalformedPacket(format!(
+            "IPv4 header truncated ({} bytes)",
+            data.len()
+        )));
+    }
+
+    let header_len = ((data[0] & 0x0f) as usize) * 4;
+    let total_len = u16::from_be_bytes([data[2], data[3]]) as usize;
+    if header_len < 20 || total_len < header_len || total_len > data.len() {
+        return Err(ProtocolError::MalformedPacket(format!(
+            "inconsistent IPv4 lengths (header {}, total {}, captured {})",
+            header_len,
+            total_len,
+            data.len()
+        )));
+    }
+
+    // Fragment offset or More Fragments set: reassembling IP fragments is out of scope
+    let flags_fragment = u16::from_be_bytes([data[6], data[7]]);
+    if flags_fragment & 0x3fff != 0 {
+        return Err(ProtocolError::MalformedPacket(
+            "fragmented IPv4 packet".to_string(),
+        ));
+    }
+
+    if data[9] != IPPROTO_TCP {
+        return Err(ProtocolError::MalformedPacket(format!(
+            "not a TCP packet (protocol {})",
+            data[9]
+        )));
+    }
+
+    let src = Ipv4Addr::new(data[12], data[13], data[14], data[15]);
+    let dst = Ipv4Addr::new(data[16], data[17], data[18], data[19]);
+
+    Ok((
+        IpAddr::V4(src),
+        IpAddr::V4(dst),
+        &data[header_len..total_len],
+    ))
+}
+
+/// Parse a fixed IPv6 header and return the addresses and transport payload
+fn parse_ipv6(data: &[u8]) -> Result<(IpAddr, IpAddr, &[u8]), ProtocolError> {
+    if data.len() < IPV6_HEADER_LEN {
+        return Err(ProtocolError::MalformedPacket(format!(
+            "IPv6 header truncated ({} bytes)",
+            data.len()
+        )));
+    }
+
+    let payload_len = u16::from_be_bytes([data[4], data[5]]) as usize;
+    if IPV6_HEADER_LEN + payload_len > data.len() {
+        return Err(ProtocolError::MalformedPacket(format!(
+            "IPv6 payload length {} exceeds captured {} bytes",
+            payload_len,
+            data.len() - IPV6_HEADER_LEN
+        )));
+    }
+
+    if data[6] != IPPROTO_TCP {
+        return Err(ProtocolError::MalformedPacket(format!(
+            "not a TCP packet (next header {})",
+            data[6]
+        )));
+    }
+
+    let mut src = [0u8; 16];
+    let mut dst = [0u8; 16];
+    src.copy_from_slice(&data[8..24]);
+    dst.copy_from_slice(&data[24..40]);
+
+    Ok((
+        IpAddr::V6(Ipv6Addr::from(src)),
+        IpAddr::V6(Ipv6Addr::from(dst)),
+        &data[IPV6_HEADER_LEN..IPV6_HEADER_LEN + payload_len],
+    ))
+}
+
+/// Parse a TCP header following the IP header
+fn parse_tcp(src_addr: IpAddr, dst_addr: IpAddr, data: &[u8]) -> Result<TcpSegment, ProtocolError> {
+    if data.len() < TCP_MIN_HEADER_LEN {
+        return Err(ProtocolError::MalformedPacket(format!(
+            "TCP header truncated ({} bytes)",
+            data.len()
+        )));
+    }
+
+    let data_offset = ((data[12] >> 4) as usize) * 4;
+    if data_offset < TCP_MIN_HEADER_LEN || data_offset > data.len() {
+        return Err(ProtocolError::MalformedPacket(format!(
+            "invalid TCP data offset {}",
+            data_offset
+        )));
+    }
+
+    let flags = data[13];
+
+    Ok(TcpSegment {
+        flow: FlowKey {
+            src_addr,
+            src_port: u16::from_be_bytes([data[0], data[1]]),
+            dst_addr,
+            dst_port: u16::from_be_bytes([data[2], data[3]]),
+        },
+        seq: u32::from_be_bytes([data[4], data[5], data[6], data[7]]),
+        fin: flags & 0x01 != 0,
+        syn: flags & 0x02 != 0,
+        rst: flags & 0x04 != 0,
+        payload: data[data_offset..].to_vec(),
+    })
+}
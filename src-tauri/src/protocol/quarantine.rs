@@ -0,0 +1,83 @@
+use crate::protocol::frame::{Direction, Frame};
+use crate::protocol::packet::FlowKey;
+use crate::protocol::version::ClientVersion;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+/// Number of preceding messages kept as context for each quarantined frame
+pub const PRECEDING_CONTEXT: usize = 8;
+
+/// Summary of a message seen shortly before a quarantined frame
+///
+/// The full frame is available in the session's frame log by `index`; the summary
+/// keeps the quarantine file readable on its own.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrecedingMessage {
+    pub index: u64,
+    pub type_id: u16,
+    pub direction: Direction,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Decoded message name, or None if this frame was undecodable too
+    pub name: Option<String>,
+}
+
+/// A frame that failed to decode, preserved with the context needed to study it later
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuarantinedFrame {
+    pub frame: Frame,
+    /// Decoder error message
+    pub reason: String,
+    /// Schema set that failed to decode the frame
+    pub schema_version: ClientVersion,
+    /// Client version reported by the handshake, if it had been seen
+    pub client_version: Option<ClientVersion>,
+    pub quarantined_at: chrono::DateTime<chrono::Utc>,
+    /// Messages on the same connection immediately before this frame, oldest first
+    pub preceding: Vec<PrecedingMessage>,
+}
+
+/// Rolling window of recent messages used to give quarantined frames context
+#[derive(Debug, Default)]
+pub struct RecentMessages {
+    window: VecDeque<(FlowKey, PrecedingMessage)>,
+}
+
+impl RecentMessages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a frame and the name it decoded to (if any)
+    pub fn record(&mut self, frame: &Frame, name: Option<&str>) {
+        // Hold several connections' worth of history so a busy neighbouring
+        // connection does not crowd out the context of a quieter one
+        if self.window.len() == PRECEDING_CONTEXT * 4 {
+            self.window.pop_front();
+        }
+        self.window.push_back((
+            frame.flow.connection(),
+            PrecedingMessage {
+                index: frame.index,
+                type_id: frame.type_id,
+                direction: frame.direction,
+                timestamp: frame.timestamp,
+                name: name.map(str::to_string),
+            },
+        ));
+    }
+
+    /// Context for a frame: the most recent earlier messages on the same connection
+    pub fn context_for(&self, frame: &Frame) -> Vec<PrecedingMessage> {
+        let connection = frame.flow.connection();
+        let mut context: Vec<PrecedingMessage> = self
+            .window
+            .iter()
+            .rev()
+            .filter(|(conn, m)| *conn == connection && m.index < frame.index)
+            .take(PRECEDING_CONTEXT)
+            .map(|(_, m)| m.clone())
+            .collect();
+        context.reverse();
+        context
+    }
+}
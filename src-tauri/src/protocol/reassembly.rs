@@ -0,0 +1,192 @@
+use std::collections::BTreeMap;
+use tracing::warn;
+
+/// Maximum out-of-order bytes buffered per flow before giving up on a gap (PERF-004)
+///
+/// If a segment is lost and never retransmitted (e.g. WinDivert dropped it during a
+/// burst), buffering forever would grow without bound. Once this limit is reached the
+/// reassembler skips the gap and resumes from the earliest buffered segment.
+pub const MAX_BUFFERED_BYTES: usize = 4 * 1024 * 1024;
+
+/// Reassembles one direction of a TCP connection into a contiguous byte stream
+///
+/// Handles out-of-order delivery, retransmissions and overlapping segments (PROT-001:
+/// "Handle packet reordering and loss"). Sequence numbers are tracked as 64-bit offsets
+/// relative to the initial sequence number, so wraparound of the 32-bit TCP sequence
+/// space within a single connection is handled transparently.
+#[derive(Debug)]
+pub struct TcpReassembler {
+    /// Initial sequence number (the sequence number of the first payload byte)
+    base_seq: Option<u32>,
+    /// Offset of the next byte expected in order
+    next_offset: u64,
+    /// Out-of-order segments keyed by their relative offset
+    pending: BTreeMap<u64, Vec<u8>>,
+    buffered_bytes: usize,
+    max_buffered: usize,
+    /// Bytes skipped because of unrecoverable gaps
+    gap_bytes: u64,
+}
+
+impl Default for TcpReassembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TcpReassembler {
+    /// Create a reassembler that anchors on the first SYN or payload segment it sees
+    pub fn new() -> Self {
+        Self {
+            base_seq: None,
+            next_offset: 0,
+            pending: BTreeMap::new(),
+            buffered_bytes: 0,
+            max_buffered: MAX_BUFFERED_BYTES,
+            gap_bytes: 0,
+        }
+    }
+
+    /// Create a reassembler with a known initial sequence number
+    ///
+    /// Use this when the stream start is known out-of-band (e.g. from a SYN seen
+    /// earlier), so segments arriving out of order from the very first one are placed
+    /// correctly.
+    pub fn with_initial_seq(initial_seq: u32) -> Self {
+        Self {
+            base_seq: Some(initial_seq),
+            ..Self::new()
+        }
+    }
+
+    /// Override the out-of-order buffer limit
+    pub fn with_max_buffered(mut self, max_buffered: usize) -> Self {
+        self.max_buffered = max_buffered;
+        self
+    }
+
+    /// Total bytes skipped due to unrecoverable gaps
+    pub fn gap_bytes(&self) -> u64 {
+        self.gap_bytes
+    }
+
+    /// Feed one segment and return any newly contiguous bytes
+    ///
+    /// # Arguments
+    /// * `seq` - TCP sequence number of the segment
+    /// * `syn` - Whether the SYN flag is set (SYN consumes one sequence number)
+    /// * `payload` - Segment payload
+    ///
+    /// # Returns
+    /// Bytes that can now be delivered in order (possibly empty)
+    pub fn push(&mut self, seq: u32, syn: bool, payload: &[u8]) -> Vec<u8> {
+        let seq = if syn {
+            if self.base_seq.is_none() {
+                self.base_seq = Some(seq.wrapping_add(1));
+            }
+            seq.wrapping_add(1)
+        } else {
+            seq
+        };
+
+        if payload.is_empty() {
+            return Vec::new();
+        }
+
+        let base = *self.base_seq.get_or_insert(seq);
+
+        // Early in the stream, segments more than 2^31 "behind" the base are stale
+        // retransmissions from before the anchor point rather than data far ahead
+        let relative = seq.wrapping_sub(base);
+        if self.next_offset < (1u64 << 31) && relative > u32::MAX / 2 {
+            return Vec::new();
+        }
+        let offset = self.unwrap_offset(relative);
+
+        self.insert(offset, payload);
+        self.drain_contiguous()
+    }
+
+    /// Map a 32-bit relative sequence number onto the 64-bit offset closest to `next_offset`
+    fn unwrap_offset(&self, relative: u32) -> u64 {
+        let epoch = self.next_offset & !0xffff_ffff;
+        let candidate = epoch | relative as u64;
+        let window = 1u64 << 31;
+
+        if candidate + window < self.next_offset {
+            candidate + (1u64 << 32)
+        } else if candidate > self.next_offset + window && candidate >= (1u64 << 32) {
+            candidate - (1u64 << 32)
+        } else {
+            candidate
+        }
+    }
+
+    /// Insert a segment into the pending buffer, trimming data already delivered
+    fn insert(&mut self, offset: u64, payload: &[u8]) {
+        let end = offset + payload.len() as u64;
+        if end <= self.next_offset {
+            // Pure retransmission of delivered data
+            return;
+        }
+
+        let (offset, payload) = if offset < self.next_offset {
+            let skip = (self.next_offset - offset) as usize;
+            (self.next_offset, &payload[skip..])
+        } else {
+            (offset, payload)
+        };
+
+        // Keep the longer copy if the same offset was seen before
+        match self.pending.get(&offset) {
+            Some(existing) if existing.len() >= payload.len() => return,
+            Some(existing) => self.buffered_bytes -= existing.len(),
+            None => {}
+        }
+
+        self.buffered_bytes += payload.len();
+        self.pending.insert(offset, payload.to_vec());
+
+        if self.buffered_bytes > self.max_buffered {
+            self.skip_gap();
+        }
+    }
+
+    /// Give up on the missing range before the earliest buffered segment
+    fn skip_gap(&mut self) {
+        if let Some((&first, _)) = self.pending.iter().next() {
+            if first > self.next_offset {
+                let skipped = first - self.next_offset;
+                warn!(
+                    "TCP reassembly buffer exceeded {} bytes, skipping {} missing bytes",
+                    self.max_buffered, skipped
+                );
+                self.gap_bytes += skipped;
+                self.next_offset = first;
+            }
+        }
+    }
+
+    /// Deliver all bytes that are now contiguous with the stream position
+    fn drain_contiguous(&mut self) -> Vec<u8> {
+        let mut out = Vec::new();
+
+        while let Some((&offset, _)) = self.pending.iter().next() {
+            if offset > self.next_offset {
+                break;
+            }
+
+            let data = self.pending.remove(&offset).unwrap_or_default();
+            self.buffered_bytes -= data.len();
+
+            let end = offset + data.len() as u64;
+            if end > self.next_offset {
+                let skip = (self.next_offset - offset) as usize;
+                out.extend_from_slice(&data[skip..]);
+                self.next_offset = end;
+            }
+        }
+
+        out
+    }
+}
@@ -0,0 +1,384 @@
+use crate::common::error::ProtocolError;
+use crate::protocol::version::ClientVersion;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Wire type of a message field
+///
+/// All integers are little-endian. Variable-length types carry their own length
+/// prefix: `string` uses a u16 byte count, `bytes` a u32 byte count and `array` a u16
+/// element count.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldType {
+    U8,
+    U16,
+    U32,
+    U64,
+    I32,
+    I64,
+    Bool,
+    String,
+    Bytes,
+    Array(Box<FieldType>),
+}
+
+/// A named field within a message schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FieldDef {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: FieldType,
+}
+
+/// Layout of one MTGO message type
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MessageSchema {
+    pub type_id: u16,
+    pub name: String,
+    pub fields: Vec<FieldDef>,
+}
+
+/// All message schemas known for one MTGO client version
+///
+/// Schema sets are stored as JSON files so protocol knowledge can be updated
+/// without rebuilding the application:
+///
+/// ```json
+/// { "client_version": "3.4.0.0",
+///   "messages": [ { "type_id": 1, "name": "Handshake",
+///                   "fields": [ { "name": "client_version", "type": "string" } ] } ] }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SchemaSet {
+    pub client_version: ClientVersion,
+    pub messages: Vec<MessageSchema>,
+    #[serde(skip)]
+    by_type: BTreeMap<u16, usize>,
+}
+
+impl SchemaSet {
+    /// Build a schema set, validating that type ids are unique
+    pub fn new(
+        client_version: ClientVersion,
+        messages: Vec<MessageSchema>,
+    ) -> Result<Self, ProtocolError> {
+        let mut by_type = BTreeMap::new();
+        for (i, message) in messages.iter().enumerate() {
+            if by_type.insert(message.type_id, i).is_some() {
+                return Err(ProtocolError::InvalidSchema(format!(
+                    "duplicate message type {:#06x} in schema set {}",
+                    message.type_id, client_version
+                )));
+            }
+        }
+
+        Ok(Self {
+            client_version,
+            messages,
+            by_type,
+        })
+    }
+
+    /// Parse a schema set from its JSON representation
+    pub fn from_json(json: &str) -> Result<Self, ProtocolError> {
+        let raw: SchemaSet =
+            serde_json::from_str(json).map_err(|e| ProtocolError::InvalidSchema(e.to_string()))?;
+        Self::new(raw.client_version, raw.messages)
+    }
+
+    /// Look up the schema for a message type
+    pub fn message(&self, type_id: u16) -> Option<&MessageSchema> {
+        self.by_type.get(&type_id).map(|&i| &self.messages[i])
+    }
+
+    /// Look up a message schema by name
+    pub fn message_by_name(&self, name: &str) -> Option<&MessageSchema> {
+        self.messages.iter().find(|m| m.name == name)
+    }
+
+    /// Decode a frame payload using the schema for its type
+    pub fn decode(&self, type_id: u16, payload: &[u8]) -> Result<DecodedMessage, ProtocolError> {
+        let schema = self
+            .message(type_id)
+            .ok_or(ProtocolError::UnknownMessageType(type_id))?;
+        schema.decode(payload, &self.client_version)
+    }
+}
+
+/// A decoded field value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum FieldValue {
+    #[serde(rename = "uint")]
+    UInt(u64),
+    Int(i64),
+    Bool(bool),
+    String(String),
+    Bytes(Vec<u8>),
+    Array(Vec<FieldValue>),
+}
+
+impl FieldValue {
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            FieldValue::UInt(v) => Some(*v),
+            FieldValue::Int(v) if *v >= 0 => Some(*v as u64),
+            _ => None,
+        }
+    }
+
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            FieldValue::Int(v) => Some(*v),
+            FieldValue::UInt(v) => i64::try_from(*v).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            FieldValue::Bool(v) => Some(*v),
+            FieldValue::UInt(v) => Some(*v != 0),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            FieldValue::String(v) => Some(v),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[FieldValue]> {
+        match self {
+            FieldValue::Array(v) => Some(v),
+            _ => None,
+        }
+    }
+}
+
+/// A decoded field with the byte range it occupied in the payload
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedField {
+    pub name: String,
+    pub value: FieldValue,
+    pub offset: usize,
+    pub len: usize,
+}
+
+/// A frame payload decoded against a message schema
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedMessage {
+    pub type_id: u16,
+    pub name: String,
+    /// Version of the schema set used to decode this message
+    pub schema_version: ClientVersion,
+    pub fields: Vec<DecodedField>,
+    /// Bytes left over after the last known field (tolerated, not an error)
+    pub trailing: usize,
+}
+
+impl DecodedMessage {
+    /// Look up a field value by name
+    pub fn field(&self, name: &str) -> Option<&FieldValue> {
+        self.fields
+            .iter()
+            .find(|f| f.name == name)
+            .map(|f| &f.value)
+    }
+
+    pub fn u64(&self, name: &str) -> Option<u64> {
+        self.field(name).and_then(FieldValue::as_u64)
+    }
+
+    pub fn i64(&self, name: &str) -> Option<i64> {
+        self.field(name).and_then(FieldValue::as_i64)
+    }
+
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        self.field(name).and_then(FieldValue::as_bool)
+    }
+
+    pub fn str(&self, name: &str) -> Option<&str> {
+        self.field(name).and_then(FieldValue::as_str)
+    }
+}
+
+impl MessageSchema {
+    /// Decode a payload field by field
+    ///
+    /// Trailing bytes after the last field are tolerated so that fields appended by a
+    /// newer client do not break older schemas (Phase 2 Success Criterion #2).
+    pub fn decode(
+        &self,
+        payload: &[u8],
+        schema_version: &ClientVersion,
+    ) -> Result<DecodedMessage, ProtocolError> {
+        let mut cursor = Cursor {
+            data: payload,
+            offset: 0,
+        };
+        let mut fields = Vec::with_capacity(self.fields.len());
+
+        for def in &self.fields {
+            let start = cursor.offset;
+            let value = cursor.read_value(&def.ty, &def.name)?;
+            fields.push(DecodedField {
+                name: def.name.clone(),
+                value,
+                offset: start,
+                len: cursor.offset - start,
+            });
+        }
+
+        Ok(DecodedMessage {
+            type_id: self.type_id,
+            name: self.name.clone(),
+            schema_version: schema_version.clone(),
+            fields,
+            trailing: payload.len() - cursor.offset,
+        })
+    }
+}
+
+impl MessageSchema {
+    /// Encode field values into a payload, in schema order
+    ///
+    /// The inverse of `decode`: decoding the result yields the same values. Used to
+    /// build synthetic traffic for tests and for round-trip checks of schema files.
+    pub fn encode(&self, values: &[FieldValue]) -> Result<Vec<u8>, ProtocolError> {
+        if values.len() != self.fields.len() {
+            return Err(ProtocolError::InvalidSchema(format!(
+                "{} expects {} fields, got {}",
+                self.name,
+                self.fields.len(),
+                values.len()
+            )));
+        }
+
+        let mut out = Vec::new();
+        for (def, value) in self.fields.iter().zip(values) {
+            write_value(&mut out, &def.ty, value, &def.name)?;
+        }
+        Ok(out)
+    }
+}
+
+fn write_value(
+    out: &mut Vec<u8>,
+    ty: &FieldType,
+    value: &FieldValue,
+    field: &str,
+) -> Result<(), ProtocolError> {
+    let mismatch = || {
+        ProtocolError::InvalidSchema(format!(
+            "value {:?} does not fit field {} ({:?})",
+            value, field, ty
+        ))
+    };
+    let uint = |max: u64| value.as_u64().filter(|&v| v <= max).ok_or_else(mismatch);
+    let int = |min: i64, max: i64| {
+        value
+            .as_i64()
+            .filter(|v| (min..=max).contains(v))
+            .ok_or_else(mismatch)
+    };
+
+    match ty {
+        FieldType::U8 => out.push(uint(u8::MAX as u64)? as u8),
+        FieldType::U16 => out.extend_from_slice(&(uint(u16::MAX as u64)? as u16).to_le_bytes()),
+        FieldType::U32 => out.extend_from_slice(&(uint(u32::MAX as u64)? as u32).to_le_bytes()),
+        FieldType::U64 => out.extend_from_slice(&uint(u64::MAX)?.to_le_bytes()),
+        FieldType::I32 => {
+            out.extend_from_slice(&(int(i32::MIN as i64, i32::MAX as i64)? as i32).to_le_bytes())
+        }
+        FieldType::I64 => out.extend_from_slice(&int(i64::MIN, i64::MAX)?.to_le_bytes()),
+        FieldType::Bool => out.push(value.as_bool().ok_or_else(mismatch)? as u8),
+        FieldType::String => {
+            let s = value.as_str().ok_or_else(mismatch)?;
+            let len = u16::try_from(s.len()).map_err(|_| mismatch())?;
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(s.as_bytes());
+        }
+        FieldType::Bytes => {
+            let FieldValue::Bytes(bytes) = value else {
+                return Err(mismatch());
+            };
+            let len = u32::try_from(bytes.len()).map_err(|_| mismatch())?;
+            out.extend_from_slice(&len.to_le_bytes());
+            out.extend_from_slice(bytes);
+        }
+        FieldType::Array(inner) => {
+            let items = value.as_array().ok_or_else(mismatch)?;
+            let count = u16::try_from(items.len()).map_err(|_| mismatch())?;
+            out.extend_from_slice(&count.to_le_bytes());
+            for item in items {
+                write_value(out, inner, item, field)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Bounds-checked reader over an untrusted payload
+struct Cursor<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn take(&mut self, len: usize, field: &str) -> Result<&'a [u8], ProtocolError> {
+        let end = self
+            .offset
+            .checked_add(len)
+            .filter(|&end| end <= self.data.len())
+            .ok_or_else(|| ProtocolError::Truncated {
+                field: field.to_string(),
+                offset: self.offset,
+            })?;
+        let bytes = &self.data[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    fn read_array<const N: usize>(&mut self, field: &str) -> Result<[u8; N], ProtocolError> {
+        let mut out = [0u8; N];
+        out.copy_from_slice(self.take(N, field)?);
+        Ok(out)
+    }
+
+    fn read_value(&mut self, ty: &FieldType, field: &str) -> Result<FieldValue, ProtocolError> {
+        Ok(match ty {
+            FieldType::U8 => FieldValue::UInt(self.read_array::<1>(field)?[0] as u64),
+            FieldType::U16 => FieldValue::UInt(u16::from_le_bytes(self.read_array(field)?) as u64),
+            FieldType::U32 => FieldValue::UInt(u32::from_le_bytes(self.read_array(field)?) as u64),
+            FieldType::U64 => FieldValue::UInt(u64::from_le_bytes(self.read_array(field)?)),
+            FieldType::I32 => FieldValue::Int(i32::from_le_bytes(self.read_array(field)?) as i64),
+            FieldType::I64 => FieldValue::Int(i64::from_le_bytes(self.read_array(field)?)),
+            FieldType::Bool => FieldValue::Bool(self.read_array::<1>(field)?[0] != 0),
+            FieldType::String => {
+                let len = u16::from_le_bytes(self.read_array(field)?) as usize;
+                let bytes = self.take(len, field)?;
+                let s = std::str::from_utf8(bytes)
+                    .map_err(|_| ProtocolError::InvalidString(field.to_string()))?;
+                FieldValue::String(s.to_string())
+            }
+            FieldType::Bytes => {
+                let len = u32::from_le_bytes(self.read_array(field)?) as usize;
+                FieldValue::Bytes(self.take(len, field)?.to_vec())
+            }
+            FieldType::Array(inner) => {
+                let count = u16::from_le_bytes(self.read_array(field)?) as usize;
+                // Every element occupies at least one byte, so the remaining payload
+                // bounds the allocation regardless of the claimed count
+                let mut items = Vec::with_capacity(count.min(self.data.len() - self.offset));
+                for _ in 0..count {
+                    items.push(self.read_value(inner, field)?);
+                }
+                FieldValue::Array(items)
+            }
+        })
+    }
+}
@@ -0,0 +1,279 @@
+use crate::common::error::ProtocolError;
+use crate::protocol::archive::{self, SessionArchive};
+use crate::protocol::decoder::{load_unknown_sessions, Decoder, UnknownVersionSession};
+use crate::protocol::frame::Frame;
+use crate::protocol::quarantine::{QuarantinedFrame, RecentMessages};
+use crate::protocol::schema::DecodedMessage;
+use crate::protocol::version::{ClientVersion, SchemaRegistry};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, error, info};
+
+/// Decodes a live capture session while archiving every frame
+///
+/// Frames that fail to decode are not dropped: they go to the session's quarantine
+/// log together with the messages that preceded them, so they can be studied and
+/// re-decoded once the schemas catch up.
+pub struct SessionRecorder {
+    decoder: Decoder,
+    archive: SessionArchive,
+    recent: RecentMessages,
+}
+
+impl SessionRecorder {
+    pub fn new(decoder: Decoder, archive: SessionArchive) -> Self {
+        Self {
+            decoder,
+            archive,
+            recent: RecentMessages::new(),
+        }
+    }
+
+    pub fn session_id(&self) -> &str {
+        self.archive.id()
+    }
+
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    /// Archive and decode one frame
+    ///
+    /// # Returns
+    /// Some(message) if the frame decoded, None if it was quarantined
+    pub fn process(&mut self, frame: &Frame) -> Option<DecodedMessage> {
+        if let Err(e) = self.archive.append_frame(frame) {
+            error!("Failed to archive frame {}: {}", frame.index, e);
+        }
+
+        let previous_version = self.decoder.client_version().cloned();
+        let result = self.decoder.decode(frame);
+        if self.decoder.client_version() != previous_version.as_ref() {
+            self.archive
+                .set_versions(self.decoder.client_version(), self.decoder.active_version());
+        }
+
+        match result {
+            Ok(message) => {
+                self.recent.record(frame, Some(&message.name));
+                Some(message)
+            }
+            Err(e) => {
+                debug!(
+                    "Quarantining frame {} (type {:#06x}): {}",
+                    frame.index, frame.type_id, e
+                );
+                let entry = quarantine_entry(frame, e.to_string(), &self.decoder, &self.recent);
+                if let Err(e) = self.archive.quarantine(&entry) {
+                    error!("Failed to quarantine frame {}: {}", frame.index, e);
+                }
+                self.recent.record(frame, None);
+                None
+            }
+        }
+    }
+
+    /// Flush archive logs to disk
+    pub fn flush(&mut self) -> Result<(), ProtocolError> {
+        self.archive.flush()
+    }
+
+    /// Close the session archive
+    pub fn finish(self) -> Result<archive::SessionManifest, ProtocolError> {
+        self.archive.finish(chrono::Utc::now())
+    }
+}
+
+fn quarantine_entry(
+    frame: &Frame,
+    reason: String,
+    decoder: &Decoder,
+    recent: &RecentMessages,
+) -> QuarantinedFrame {
+    QuarantinedFrame {
+        frame: frame.clone(),
+        reason,
+        schema_version: decoder.active_version().clone(),
+        client_version: decoder.client_version().cloned(),
+        quarantined_at: chrono::Utc::now(),
+        preceding: recent.context_for(frame),
+    }
+}
+
+/// Outcome of re-decoding an archived session with the current schemas
+#[derive(Debug, Clone, Serialize)]
+pub struct RedecodeReport {
+    pub session_id: String,
+    pub client_version: Option<ClientVersion>,
+    pub schema_version: ClientVersion,
+    pub exact_schema_match: bool,
+    pub total_frames: u64,
+    pub decoded: u64,
+    /// Frames still undecodable after this pass
+    pub quarantined: u64,
+    /// Previously quarantined frames that decode now
+    pub recovered: u64,
+    /// Decoded message counts by message name
+    pub message_counts: BTreeMap<String, u64>,
+}
+
+/// Re-run the decoder over every frame of an archived session
+///
+/// Both the raw frame log and the quarantine log are read; quarantined frames missing
+/// from the frame log (e.g. after a partial write) are merged back in by index. The
+/// quarantine log and manifest are rewritten to reflect the new result, so old
+/// captures benefit from new protocol knowledge.
+///
+/// # Arguments
+/// * `dir` - Session archive directory
+/// * `registry` - Schema registry to decode with (normally built-in + user schemas)
+/// * `on_message` - Called for each successfully decoded message, in capture order
+pub fn redecode_session(
+    dir: &Path,
+    registry: Arc<SchemaRegistry>,
+    mut on_message: impl FnMut(&Frame, &DecodedMessage),
+) -> Result<RedecodeReport, ProtocolError> {
+    let mut manifest = archive::read_manifest(dir)?;
+    let previously_quarantined: HashSet<u64> = archive::read_quarantine(dir)?
+        .iter()
+        .map(|q| q.frame.index)
+        .collect();
+
+    let frames = archive::read_all_frames(dir)?;
+
+    let mut decoder = Decoder::new(registry)?;
+    let mut recent = RecentMessages::new();
+    let mut quarantine = Vec::new();
+    let mut report = RedecodeReport {
+        session_id: manifest.id.clone(),
+        client_version: None,
+        schema_version: decoder.active_version().clone(),
+        exact_schema_match: false,
+        total_frames: frames.len() as u64,
+        decoded: 0,
+        quarantined: 0,
+        recovered: 0,
+        message_counts: BTreeMap::new(),
+    };
+
+    for frame in &frames {
+        match decoder.decode(frame) {
+            Ok(message) => {
+                report.decoded += 1;
+                if previously_quarantined.contains(&frame.index) {
+                    report.recovered += 1;
+                }
+                *report
+                    .message_counts
+                    .entry(message.name.clone())
+                    .or_insert(0) += 1;
+                recent.record(frame, Some(&message.name));
+                on_message(frame, &message);
+            }
+            Err(e) => {
+                quarantine.push(quarantine_entry(frame, e.to_string(), &decoder, &recent));
+                recent.record(frame, None);
+            }
+        }
+    }
+
+    report.quarantined = quarantine.len() as u64;
+    report.client_version = decoder.client_version().cloned();
+    report.schema_version = decoder.active_version().clone();
+    report.exact_schema_match = decoder.is_exact_match();
+
+    archive::write_quarantine(dir, &quarantine)?;
+    manifest.client_version = report.client_version.clone();
+    manifest.schema_version = report.schema_version.clone();
+    manifest.quarantined_count = report.quarantined;
+    manifest.last_redecoded_at = Some(chrono::Utc::now());
+    archive::write_manifest(dir, &manifest)?;
+
+    info!(
+        "Re-decoded session {}: {}/{} frames decoded, {} recovered from quarantine",
+        report.session_id, report.decoded, report.total_frames, report.recovered
+    );
+
+    Ok(report)
+}
+
+/// A recorded unknown-version session and whether it can be decoded properly now
+#[derive(Debug, Clone, Serialize)]
+pub struct UnknownSessionStatus {
+    #[serde(flatten)]
+    pub session: UnknownVersionSession,
+    /// Archived capture session the handshake was seen in, if it is still on disk
+    pub session_id: Option<String>,
+    /// True once a schema set matches the client version exactly
+    pub schema_available: bool,
+    /// True if the archive has been re-decoded with those schemas
+    pub redecoded: bool,
+}
+
+/// Match every session in the unknown-version log to its archive
+///
+/// A session belongs to the archive that reported the same client version and whose
+/// capture window contains the handshake.
+///
+/// # Arguments
+/// * `sessions_dir` - Root directory of the session archives
+/// * `unknown_log` - JSON Lines file the decoder recorded the sessions in
+/// * `registry` - Schema registry that would be used to re-decode
+pub fn unknown_session_status(
+    sessions_dir: &Path,
+    unknown_log: &Path,
+    registry: &SchemaRegistry,
+) -> Result<Vec<UnknownSessionStatus>, ProtocolError> {
+    let archives = archive::list_sessions(sessions_dir)?;
+
+    Ok(load_unknown_sessions(unknown_log)?
+        .into_iter()
+        .map(|session| {
+            let archived = archives.iter().find(|manifest| {
+                manifest.client_version.as_ref() == Some(&session.client_version)
+                    && manifest.started_at <= session.detected_at
+                    && !matches!(manifest.ended_at, Some(end) if end < session.detected_at)
+            });
+            UnknownSessionStatus {
+                session_id: archived.map(|manifest| manifest.id.clone()),
+                schema_available: registry.get(&session.client_version).is_some(),
+                redecoded: archived
+                    .is_some_and(|manifest| manifest.schema_version == session.client_version),
+                session,
+            }
+        })
+        .collect())
+}
+
+/// Re-decode the archives of unknown-version sessions whose schemas have arrived
+///
+/// Sessions still without matching schemas, or already re-decoded with them, are
+/// left alone, so this can run after every schema update.
+///
+/// # Returns
+/// One report per re-decoded archive
+pub fn redecode_unknown_sessions(
+    sessions_dir: &Path,
+    unknown_log: &Path,
+    registry: Arc<SchemaRegistry>,
+) -> Result<Vec<RedecodeReport>, ProtocolError> {
+    let mut session_ids: Vec<String> =
+        unknown_session_status(sessions_dir, unknown_log, &registry)?
+            .into_iter()
+            .filter(|status| status.schema_available && !status.redecoded)
+            .filter_map(|status| status.session_id)
+            .collect();
+    // Reconnects log the same archive more than once
+    session_ids.sort();
+    session_ids.dedup();
+
+    session_ids
+        .iter()
+        .map(|id| {
+            let dir = archive::session_dir(sessions_dir, id)?;
+            redecode_session(&dir, Arc::clone(&registry), |_, _| {})
+        })
+        .collect()
+}
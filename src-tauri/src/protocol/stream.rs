@@ -0,0 +1,96 @@
+use crate::common::error::ProtocolError;
+use crate::protocol::frame::{Direction, Frame, FrameReader};
+use crate::protocol::packet::{parse_packet, FlowKey, TcpSegment};
+use crate::protocol::reassembly::TcpReassembler;
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+/// Per-flow reassembly and framing state
+#[derive(Debug)]
+struct FlowStream {
+    reassembler: TcpReassembler,
+    reader: FrameReader,
+    direction: Direction,
+}
+
+/// Turns captured IP packets into ordered MTGO frames, one stream per TCP flow
+///
+/// This is the glue between the capture layer and the protocol decoder:
+/// packet parsing -> per-flow TCP reassembly -> length-prefixed framing.
+#[derive(Debug, Default)]
+pub struct StreamAssembler {
+    flows: HashMap<FlowKey, FlowStream>,
+    next_index: u64,
+    framing_errors: u64,
+}
+
+impl StreamAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of times a flow had to be resynchronized after a framing error
+    pub fn framing_errors(&self) -> u64 {
+        self.framing_errors
+    }
+
+    /// Parse a raw captured packet and return any frames it completes
+    pub fn push_packet(
+        &mut self,
+        data: &[u8],
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<Frame>, ProtocolError> {
+        let segment = parse_packet(data)?;
+        Ok(self.push_segment(segment, timestamp))
+    }
+
+    /// Feed a parsed TCP segment and return any frames it completes
+    pub fn push_segment(
+        &mut self,
+        segment: TcpSegment,
+        timestamp: chrono::DateTime<chrono::Utc>,
+    ) -> Vec<Frame> {
+        let flow = segment.flow;
+        let stream = self.flows.entry(flow).or_insert_with(|| FlowStream {
+            reassembler: TcpReassembler::new(),
+            reader: FrameReader::new(),
+            direction: Direction::from_flow(&flow),
+        });
+
+        let bytes = stream
+            .reassembler
+            .push(segment.seq, segment.syn, &segment.payload);
+        stream.reader.push(&bytes);
+
+        let mut frames = Vec::new();
+        loop {
+            match stream.reader.next_frame() {
+                Ok(Some((type_id, payload))) => {
+                    frames.push(Frame {
+                        flow,
+                        direction: stream.direction,
+                        timestamp,
+                        index: self.next_index,
+                        type_id,
+                        payload,
+                    });
+                    self.next_index += 1;
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    warn!("Framing error on {}: {}; resynchronizing stream", flow, e);
+                    self.framing_errors += 1;
+                    stream.reader.reset();
+                    break;
+                }
+            }
+        }
+
+        if segment.fin || segment.rst {
+            debug!("Flow {} closed", flow);
+            self.flows.remove(&flow);
+        }
+
+        frames
+    }
+}
@@ -0,0 +1,212 @@
+use crate::common::error::ProtocolError;
+use crate::protocol::schema::SchemaSet;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::{info, warn};
+
+/// Message names that carry the client version during connection setup
+///
+/// Detection is name-based rather than type-id-based because the type ids of these
+/// messages may themselves change between client versions.
+pub const HANDSHAKE_MESSAGES: &[&str] = &["Handshake", "Login"];
+
+/// Field within a handshake message holding the dotted client version string
+pub const CLIENT_VERSION_FIELD: &str = "client_version";
+
+/// Baseline schema set compiled into the application
+const BUILTIN_SCHEMAS: &[&str] = &[include_str!("../../schemas/baseline.json")];
+
+/// MTGO client version, e.g. "3.4.123.4567"
+///
+/// Compared numerically component by component; missing trailing components are
+/// treated as zero so "3.4" == "3.4.0.0".
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ClientVersion {
+    parts: [u32; 4],
+}
+
+impl ClientVersion {
+    pub fn new(major: u32, minor: u32, build: u32, revision: u32) -> Self {
+        Self {
+            parts: [major, minor, build, revision],
+        }
+    }
+
+    /// Parse a dotted version string with one to four numeric components
+    pub fn parse(s: &str) -> Result<Self, ProtocolError> {
+        let trimmed = s.trim();
+        let components: Vec<&str> = trimmed.split('.').collect();
+        if trimmed.is_empty() || components.len() > 4 {
+            return Err(ProtocolError::InvalidVersion(s.to_string()));
+        }
+
+        let mut parts = [0u32; 4];
+        for (slot, component) in parts.iter_mut().zip(&components) {
+            *slot = component
+                .parse()
+                .map_err(|_| ProtocolError::InvalidVersion(s.to_string()))?;
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+impl std::fmt::Display for ClientVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c, d] = self.parts;
+        write!(f, "{}.{}.{}.{}", a, b, c, d)
+    }
+}
+
+impl Serialize for ClientVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ClientVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        ClientVersion::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Result of choosing a schema set for a detected client version
+#[derive(Debug, Clone)]
+pub struct SchemaSelection<'a> {
+    pub schemas: &'a SchemaSet,
+    /// True if the schema set was written for exactly the detected version
+    pub exact: bool,
+}
+
+/// All known schema sets, keyed by the client version they describe
+///
+/// PROT-001: "Support protocol version changes". When the client version is detected
+/// from the handshake, the matching schema set is selected; if there is none, the
+/// nearest older set is used (protocol changes are usually additive), falling back
+/// to the oldest newer set if the client predates everything we know.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaRegistry {
+    sets: BTreeMap<ClientVersion, SchemaSet>,
+}
+
+impl SchemaRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registry containing the schema sets compiled into the application
+    pub fn builtin() -> Result<Self, ProtocolError> {
+        let mut registry = Self::new();
+        for json in BUILTIN_SCHEMAS {
+            registry.insert(SchemaSet::from_json(json)?);
+        }
+        Ok(registry)
+    }
+
+    /// Built-in schema sets overlaid with user-supplied schema files from a directory
+    pub fn with_user_schemas(dir: &Path) -> Result<Self, ProtocolError> {
+        let mut registry = Self::builtin()?;
+        registry.load_dir(dir)?;
+        Ok(registry)
+    }
+
+    /// Add or replace the schema set for its client version
+    pub fn insert(&mut self, set: SchemaSet) {
+        self.sets.insert(set.client_version.clone(), set);
+    }
+
+    /// Load every `*.json` schema set from a directory, replacing built-in versions
+    ///
+    /// # Returns
+    /// Number of schema sets loaded
+    pub fn load_dir(&mut self, dir: &Path) -> Result<usize, ProtocolError> {
+        if !dir.exists() {
+            return Ok(0);
+        }
+
+        let mut loaded = 0;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+
+            let json = std::fs::read_to_string(&path)?;
+            match SchemaSet::from_json(&json) {
+                Ok(set) => {
+                    info!(
+                        "Loaded protocol schemas for client {} from {}",
+                        set.client_version,
+                        path.display()
+                    );
+                    self.insert(set);
+                    loaded += 1;
+                }
+                Err(e) => warn!("Skipping schema file {}: {}", path.display(), e),
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    /// Known client versions in ascending order
+    pub fn versions(&self) -> impl Iterator<Item = &ClientVersion> {
+        self.sets.keys()
+    }
+
+    /// Newest known schema set, used until the client version has been detected
+    pub fn latest(&self) -> Option<&SchemaSet> {
+        self.sets.values().next_back()
+    }
+
+    /// Schema set for exactly this version, if known
+    pub fn get(&self, version: &ClientVersion) -> Option<&SchemaSet> {
+        self.sets.get(version)
+    }
+
+    /// Choose the schema set for a client version
+    pub fn select(&self, version: &ClientVersion) -> Result<SchemaSelection<'_>, ProtocolError> {
+        if let Some(schemas) = self.sets.get(version) {
+            return Ok(SchemaSelection {
+                schemas,
+                exact: true,
+            });
+        }
+
+        let nearest = self
+            .sets
+            .range(..version.clone())
+            .next_back()
+            .or_else(|| self.sets.range(version.clone()..).next())
+            .map(|(_, set)| set)
+            .ok_or(ProtocolError::NoSchemas)?;
+
+        Ok(SchemaSelection {
+            schemas: nearest,
+            exact: false,
+        })
+    }
+
+    /// Try to extract the client version from a frame
+    ///
+    /// Every known schema set is consulted (newest first) for a handshake message with
+    /// this type id, since the handshake layout may differ between versions.
+    ///
+    /// # Returns
+    /// Some(version) if the frame is a handshake/login message carrying a parseable
+    /// version string, None otherwise
+    pub fn detect_version(&self, type_id: u16, payload: &[u8]) -> Option<ClientVersion> {
+        self.sets.values().rev().find_map(|set| {
+            let schema = set.message(type_id)?;
+            if !HANDSHAKE_MESSAGES.contains(&schema.name.as_str()) {
+                return None;
+            }
+
+            let decoded = schema.decode(payload, &set.client_version).ok()?;
+            let version = decoded.str(CLIENT_VERSION_FIELD)?;
+            ClientVersion::parse(version).ok()
+        })
+    }
+}
@@ -0,0 +1,311 @@
+use crate::common::error::AnnotationError;
+use crate::common::hex;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Version of the annotations file format written by this application
+pub const ANNOTATIONS_VERSION: u32 = 1;
+
+/// Extension of the annotations file kept next to a replay
+pub const ANNOTATIONS_EXTENSION: &str = "annotations.json";
+
+fn annotations_version() -> u32 {
+    ANNOTATIONS_VERSION
+}
+
+/// Annotations file of a replay: `<name>.annotations.json` next to `<name>.mtgoreplay`
+///
+/// Annotations live beside the replay rather than in it, so annotating neither
+/// rewrites the replay nor breaks its signature.
+pub fn sidecar_path(replay: &Path) -> PathBuf {
+    replay.with_extension(ANNOTATIONS_EXTENSION)
+}
+
+/// Point on a replay's timeline an annotation is attached to
+///
+/// Ordered as on the timeline, a turn's own notes before those on its events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct AnnotationAnchor {
+    /// Game the annotation is in; None before the first game starts
+    pub game_id: Option<u32>,
+    /// Turn the annotation is in; 0 before the first turn
+    pub turn: u32,
+    /// Event the annotation is on; None for a note on the whole turn
+    pub seq: Option<u64>,
+}
+
+/// A note or bookmark on a replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// Random id, unique across every teammate's annotations
+    pub id: String,
+    pub anchor: AnnotationAnchor,
+    #[serde(default)]
+    pub text: String,
+    /// Lowercase tags, e.g. "misplay" or "key decision"
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Listed among the replay's bookmarks
+    #[serde(default)]
+    pub bookmark: bool,
+    /// Who wrote the annotation
+    pub author: String,
+    pub created_at: DateTime<Utc>,
+    /// Last edit; the latest edit wins when annotations are merged
+    pub updated_at: DateTime<Utc>,
+    /// Deleted annotations are kept, emptied, so merging does not bring them back
+    #[serde(default)]
+    pub deleted: bool,
+}
+
+/// Content of an annotation being written or edited
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnnotationDraft {
+    pub anchor: AnnotationAnchor,
+    #[serde(default)]
+    pub text: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub bookmark: bool,
+}
+
+impl AnnotationDraft {
+    /// Trimmed text and normalized tags; a draft needs text, a tag or a bookmark
+    fn normalized(&self) -> Result<(String, Vec<String>), AnnotationError> {
+        let text = self.text.trim().to_string();
+        let mut tags: Vec<String> = Vec::new();
+        for tag in &self.tags {
+            let tag = normalize_tag(tag);
+            if !tag.is_empty() && !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        if text.is_empty() && tags.is_empty() && !self.bookmark {
+            return Err(AnnotationError::Invalid(
+                "an annotation needs text, a tag or a bookmark".to_string(),
+            ));
+        }
+        Ok((text, tags))
+    }
+}
+
+/// Tags compare case-insensitively and ignore spacing: "Key  Decision" is "key decision"
+pub fn normalize_tag(tag: &str) -> String {
+    tag.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// What merging another copy of a replay's annotations changed
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// Annotations only the other copy had
+    pub added: usize,
+    /// Annotations edited later in the other copy
+    pub updated: usize,
+    /// Annotations deleted in the other copy
+    pub deleted: usize,
+}
+
+/// Every annotation of one replay, as stored in its annotations file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayAnnotations {
+    #[serde(default = "annotations_version")]
+    pub version: u32,
+    /// Including deleted annotations
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+impl Default for ReplayAnnotations {
+    fn default() -> Self {
+        Self {
+            version: ANNOTATIONS_VERSION,
+            annotations: Vec::new(),
+        }
+    }
+}
+
+impl ReplayAnnotations {
+    /// Read an annotations file; a missing file holds no annotations
+    pub fn load(path: &Path) -> Result<Self, AnnotationError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let corrupt = |reason: String| AnnotationError::Corrupt {
+            path: path.display().to_string(),
+            reason,
+        };
+        let json = std::fs::read_to_string(path)?;
+        let annotations: Self = serde_json::from_str(&json).map_err(|e| corrupt(e.to_string()))?;
+        if annotations.version > ANNOTATIONS_VERSION {
+            return Err(corrupt(format!(
+                "version {} is newer than this application supports",
+                annotations.version
+            )));
+        }
+        Ok(annotations)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), AnnotationError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| AnnotationError::Invalid(e.to_string()))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Annotations not deleted, in timeline order
+    pub fn visible(&self) -> Vec<&Annotation> {
+        let mut visible: Vec<&Annotation> =
+            self.annotations.iter().filter(|a| !a.deleted).collect();
+        visible
+            .sort_by(|a, b| (a.anchor, a.created_at, &a.id).cmp(&(b.anchor, b.created_at, &b.id)));
+        visible
+    }
+
+    /// Write a new annotation
+    ///
+    /// # Arguments
+    /// * `author` - Who is writing it
+    /// * `draft` - Where it goes and what it says
+    /// * `now` - Time of writing
+    pub fn add(
+        &mut self,
+        author: &str,
+        draft: &AnnotationDraft,
+        now: DateTime<Utc>,
+    ) -> Result<Annotation, AnnotationError> {
+        let author = author.trim();
+        if author.is_empty() {
+            return Err(AnnotationError::Invalid(
+                "an annotation needs an author".to_string(),
+            ));
+        }
+        let (text, tags) = draft.normalized()?;
+        let annotation = Annotation {
+            id: new_id()?,
+            anchor: draft.anchor,
+            text,
+            tags,
+            bookmark: draft.bookmark,
+            author: author.to_string(),
+            created_at: now,
+            updated_at: now,
+            deleted: false,
+        };
+        self.annotations.push(annotation.clone());
+        Ok(annotation)
+    }
+
+    /// Edit an annotation, keeping its author
+    pub fn update(
+        &mut self,
+        id: &str,
+        draft: &AnnotationDraft,
+        now: DateTime<Utc>,
+    ) -> Result<Annotation, AnnotationError> {
+        let (text, tags) = draft.normalized()?;
+        let annotation = self.find_mut(id)?;
+        annotation.anchor = draft.anchor;
+        annotation.text = text;
+        annotation.tags = tags;
+        annotation.bookmark = draft.bookmark;
+        annotation.updated_at = edited_at(annotation, now);
+        Ok(annotation.clone())
+    }
+
+    /// Delete an annotation, leaving a tombstone for merging
+    pub fn delete(&mut self, id: &str, now: DateTime<Utc>) -> Result<(), AnnotationError> {
+        let annotation = self.find_mut(id)?;
+        annotation.text.clear();
+        annotation.tags.clear();
+        annotation.bookmark = false;
+        annotation.deleted = true;
+        annotation.updated_at = edited_at(annotation, now);
+        Ok(())
+    }
+
+    /// Bring in a teammate's annotations of the same replay
+    ///
+    /// Annotations are matched by id; of two versions of one annotation the later
+    /// edit wins, and a deletion wins over an edit made at the same moment.
+    /// Merging is order-independent, so teammates merging each other's files end
+    /// up with the same annotations.
+    pub fn merge(&mut self, other: &ReplayAnnotations) -> MergeReport {
+        let mut report = MergeReport::default();
+        let positions: HashMap<String, usize> = self
+            .annotations
+            .iter()
+            .enumerate()
+            .map(|(i, a)| (a.id.clone(), i))
+            .collect();
+        for theirs in &other.annotations {
+            match positions.get(&theirs.id) {
+                Some(&i) => {
+                    let ours = &mut self.annotations[i];
+                    if precedence(theirs) <= precedence(ours) {
+                        continue;
+                    }
+                    if theirs.deleted && !ours.deleted {
+                        report.deleted += 1;
+                    } else {
+                        report.updated += 1;
+                    }
+                    *ours = theirs.clone();
+                }
+                None => {
+                    if !theirs.deleted {
+                        report.added += 1;
+                    }
+                    self.annotations.push(theirs.clone());
+                }
+            }
+        }
+        report
+    }
+
+    fn find_mut(&mut self, id: &str) -> Result<&mut Annotation, AnnotationError> {
+        self.annotations
+            .iter_mut()
+            .find(|a| a.id == id && !a.deleted)
+            .ok_or_else(|| AnnotationError::NotFound(id.to_string()))
+    }
+}
+
+/// Which of two versions of an annotation a merge keeps: the later edit, then a
+/// deletion, then an arbitrary but fixed choice between simultaneous edits
+fn precedence(
+    annotation: &Annotation,
+) -> (DateTime<Utc>, bool, &str, &[String], bool, AnnotationAnchor) {
+    (
+        annotation.updated_at,
+        annotation.deleted,
+        &annotation.text,
+        &annotation.tags,
+        annotation.bookmark,
+        annotation.anchor,
+    )
+}
+
+/// Time of an edit, kept after the previous one even if the clock went back,
+/// so the edit wins over the version it replaced when merged
+fn edited_at(annotation: &Annotation, now: DateTime<Utc>) -> DateTime<Utc> {
+    now.max(annotation.updated_at + Duration::milliseconds(1))
+}
+
+/// Random 128-bit annotation id
+fn new_id() -> Result<String, AnnotationError> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes)
+        .map_err(|e| AnnotationError::Io(std::io::Error::other(e.to_string())))?;
+    Ok(hex::encode(&bytes))
+}
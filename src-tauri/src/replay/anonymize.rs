@@ -0,0 +1,390 @@
+use crate::common::error::ReplayError;
+use crate::common::hex;
+use crate::game::event::GameEventKind;
+use crate::protocol::decoder::Decoder;
+use crate::protocol::frame::Frame;
+use crate::protocol::packet::FlowKey;
+use crate::protocol::schema::FieldValue;
+use crate::protocol::version::SchemaRegistry;
+use crate::replay::format::{Redactions, REPLAY_EXTENSION};
+use crate::replay::migrate::{self, ReplayContents};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{IpAddr, Ipv4Addr};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::info;
+
+/// Messages that identify the account and are never kept in a shared replay
+pub const IDENTIFYING_MESSAGES: &[&str] = &["Login", "LoginResult"];
+
+/// Length of the secret key pseudonyms are derived from
+pub const KEY_LEN: usize = 32;
+
+/// Maps player names to pseudonyms
+///
+/// A pseudonym is derived from the name and a secret key kept on this machine, so
+/// the same player gets the same pseudonym in every replay shared from here (and
+/// different ones from another installation), while the name cannot be recovered
+/// by hashing a list of known screen names.
+pub struct Pseudonymizer {
+    key: [u8; KEY_LEN],
+}
+
+impl Pseudonymizer {
+    pub fn new(key: [u8; KEY_LEN]) -> Self {
+        Self { key }
+    }
+
+    /// Pseudonymizer with a fresh key, for a one-off anonymization
+    pub fn random() -> Result<Self, ReplayError> {
+        let mut key = [0u8; KEY_LEN];
+        getrandom::getrandom(&mut key).map_err(|e| ReplayError::InvalidKey(e.to_string()))?;
+        Ok(Self::new(key))
+    }
+
+    /// Load the key from a hex file, creating the file with a fresh key if missing
+    pub fn load_or_create(path: &Path) -> Result<Self, ReplayError> {
+        if !path.exists() {
+            let pseudonymizer = Self::random()?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, hex::encode(&pseudonymizer.key))?;
+            return Ok(pseudonymizer);
+        }
+
+        let text = std::fs::read_to_string(path)?;
+        let key = hex::decode(text.trim())
+            .ok()
+            .and_then(|bytes| <[u8; KEY_LEN]>::try_from(bytes).ok())
+            .ok_or_else(|| {
+                ReplayError::InvalidKey(format!(
+                    "{} does not hold a {}-byte hex key",
+                    path.display(),
+                    KEY_LEN
+                ))
+            })?;
+        Ok(Self::new(key))
+    }
+
+    /// Pseudonym for a player name, e.g. `Player-3FA2C19B`
+    ///
+    /// Names are compared case-insensitively, like MTGO screen names.
+    pub fn pseudonym(&self, name: &str) -> String {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts any key length");
+        mac.update(normalize(name).as_bytes());
+        let digest = mac.finalize().into_bytes();
+        format!("Player-{}", hex::encode(&digest[..4]).to_uppercase())
+    }
+}
+
+/// Outcome of anonymizing one replay file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AnonymizeReport {
+    pub output: PathBuf,
+    pub redactions: Redactions,
+}
+
+/// Default location of the anonymized copy: `<name>.anon.mtgoreplay` next to the original
+pub fn default_output(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("replay");
+    path.with_file_name(format!("{}.anon.{}", stem, REPLAY_EXTENSION))
+}
+
+/// Whether a file is named like an anonymized copy (see `default_output`)
+pub fn is_default_output(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .is_some_and(|stem| stem.ends_with(".anon"))
+}
+
+/// Write an anonymized copy of a replay file (XPORT-001)
+///
+/// # Arguments
+/// * `path` - Replay to anonymize; it is not modified
+/// * `output` - Where to write the copy (see `default_output`)
+/// * `pseudonyms` - Name to pseudonym mapping
+/// * `registry` - Schemas for inspecting raw frames; None removes every raw frame
+///
+/// # Returns
+/// Where the copy was written and what was redacted
+pub fn anonymize_file(
+    path: &Path,
+    output: &Path,
+    pseudonyms: &Pseudonymizer,
+    registry: Option<&Arc<SchemaRegistry>>,
+) -> Result<AnonymizeReport, ReplayError> {
+    let mut contents = migrate::load(path)?;
+    let redactions = anonymize(&mut contents, pseudonyms, registry, chrono::Utc::now());
+    migrate::save(&contents, output)?;
+    info!(
+        "Anonymized replay {} into {} ({} players renamed, {} frames removed)",
+        path.display(),
+        output.display(),
+        redactions.players_renamed,
+        redactions.frames_removed
+    );
+    Ok(AnonymizeReport {
+        output: output.to_path_buf(),
+        redactions,
+    })
+}
+
+/// Anonymize replay contents in place
+///
+/// Player names in the header, events, snapshots and match record become
+/// pseudonyms, also where free text such as an end reason or the intended deck's
+/// name mentions them, and the capture session reference is dropped. Raw frames are
+/// decoded: chat and account messages are removed, player names inside the
+/// remaining messages are rewritten, and any frame that cannot be decoded or still
+/// contains a name afterwards is removed. Connection addresses and ports are
+/// blanked. What was done is recorded in the header.
+pub fn anonymize(
+    contents: &mut ReplayContents,
+    pseudonyms: &Pseudonymizer,
+    registry: Option<&Arc<SchemaRegistry>>,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Redactions {
+    let names: BTreeMap<String, String> = player_names(contents)
+        .into_iter()
+        .map(|name| (normalize(&name), pseudonyms.pseudonym(&name)))
+        .collect();
+    let rename = |name: &mut String| {
+        if let Some(pseudonym) = names.get(&normalize(name)) {
+            *name = pseudonym.clone();
+        }
+    };
+    let rename_mentions = |text: &mut String| *text = replace_mentions(text, &names);
+
+    let header = &mut contents.header;
+    if let Some(info) = header.match_info.as_mut() {
+        rename(&mut info.local_player);
+        rename(&mut info.opponent);
+    }
+    let mut session_id_removed = header.session_id.take().is_some();
+
+    for event in &mut contents.events {
+        match &mut event.kind {
+            GameEventKind::PlayerJoined { name, .. } => rename(name),
+            GameEventKind::MatchStarted {
+                local_player,
+                opponent,
+                ..
+            } => {
+                rename(local_player);
+                rename(opponent);
+            }
+            GameEventKind::GameEnded { reason, .. } | GameEventKind::MatchEnded { reason, .. } => {
+                rename_mentions(reason)
+            }
+            _ => {}
+        }
+    }
+    for snapshot in &mut contents.snapshots {
+        for player in snapshot.state.players.values_mut() {
+            rename(&mut player.name);
+        }
+    }
+    if let Some(record) = contents.match_record.as_mut() {
+        record.local_player.iter_mut().for_each(rename);
+        record.opponent.iter_mut().for_each(rename);
+        record.end_reason.iter_mut().for_each(rename_mentions);
+        for game in &mut record.games {
+            game.end_reason.iter_mut().for_each(rename_mentions);
+        }
+        if let Some(deck) = record.intended_deck.as_mut() {
+            rename_mentions(&mut deck.name);
+        }
+        session_id_removed |= record.session_id.take().is_some();
+    }
+
+    let mut redactions = Redactions {
+        anonymized_at: now,
+        players_renamed: names.len() as u32,
+        frames_rewritten: 0,
+        frames_removed: 0,
+        chat_removed: 0,
+        network_addresses_removed: !contents.frames.is_empty(),
+        session_id_removed,
+    };
+    let frames = std::mem::take(&mut contents.frames);
+    contents.frames = match registry {
+        Some(registry) => anonymize_frames(frames, registry, &names, &mut redactions),
+        None => {
+            redactions.frames_removed = frames.len() as u64;
+            Vec::new()
+        }
+    };
+    contents.header.redactions = Some(redactions.clone());
+    redactions
+}
+
+/// Every player name the replay mentions
+fn player_names(contents: &ReplayContents) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    if let Some(info) = &contents.header.match_info {
+        names.insert(info.local_player.clone());
+        names.insert(info.opponent.clone());
+    }
+    for event in &contents.events {
+        match &event.kind {
+            GameEventKind::PlayerJoined { name, .. } => {
+                names.insert(name.clone());
+            }
+            GameEventKind::MatchStarted {
+                local_player,
+                opponent,
+                ..
+            } => {
+                names.insert(local_player.clone());
+                names.insert(opponent.clone());
+            }
+            _ => {}
+        }
+    }
+    for snapshot in &contents.snapshots {
+        names.extend(snapshot.state.players.values().map(|p| p.name.clone()));
+    }
+    if let Some(record) = &contents.match_record {
+        names.extend(record.local_player.iter().cloned());
+        names.extend(record.opponent.iter().cloned());
+    }
+    names.retain(|name| !name.trim().is_empty());
+    names
+}
+
+fn anonymize_frames(
+    frames: Vec<Frame>,
+    registry: &Arc<SchemaRegistry>,
+    names: &BTreeMap<String, String>,
+    redactions: &mut Redactions,
+) -> Vec<Frame> {
+    let Ok(mut decoder) = Decoder::new(registry.clone()) else {
+        redactions.frames_removed = frames.len() as u64;
+        return Vec::new();
+    };
+    let blank_flow = FlowKey {
+        src_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        src_port: 0,
+        dst_addr: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        dst_port: 0,
+    };
+
+    let mut kept = Vec::with_capacity(frames.len());
+    for mut frame in frames {
+        let Ok(message) = decoder.decode(&frame) else {
+            redactions.frames_removed += 1;
+            continue;
+        };
+        if message.name.to_ascii_lowercase().contains("chat") {
+            redactions.chat_removed += 1;
+            continue;
+        }
+        if IDENTIFYING_MESSAGES.contains(&message.name.as_str()) {
+            redactions.frames_removed += 1;
+            continue;
+        }
+
+        let mut values: Vec<FieldValue> = message.fields.iter().map(|f| f.value.clone()).collect();
+        let mut renamed = false;
+        for value in &mut values {
+            renamed |= rename_value(value, names);
+        }
+        if renamed {
+            let encoded = registry
+                .get(&message.schema_version)
+                .and_then(|set| set.message(frame.type_id))
+                .map(|schema| schema.encode(&values));
+            match encoded {
+                Some(Ok(payload)) => {
+                    frame.payload = payload;
+                    redactions.frames_rewritten += 1;
+                }
+                _ => {
+                    redactions.frames_removed += 1;
+                    continue;
+                }
+            }
+        }
+        if mentions_any(&frame.payload, names) {
+            redactions.frames_removed += 1;
+            continue;
+        }
+
+        frame.flow = blank_flow;
+        kept.push(frame);
+    }
+    kept
+}
+
+/// Replace string values naming a player with the pseudonym
+///
+/// # Returns
+/// True if anything was replaced
+fn rename_value(value: &mut FieldValue, names: &BTreeMap<String, String>) -> bool {
+    match value {
+        FieldValue::String(s) => match names.get(&normalize(s)) {
+            Some(pseudonym) => {
+                *s = pseudonym.clone();
+                true
+            }
+            None => false,
+        },
+        FieldValue::Array(items) => items
+            .iter_mut()
+            .fold(false, |renamed, item| rename_value(item, names) | renamed),
+        _ => false,
+    }
+}
+
+/// Replace every mention of a player name in free text with the pseudonym, ignoring
+/// ASCII case
+///
+/// Longer names are tried first, so a name containing another one is replaced whole.
+fn replace_mentions(text: &str, names: &BTreeMap<String, String>) -> String {
+    let mut by_length: Vec<_> = names.iter().collect();
+    by_length.sort_by_key(|(name, _)| std::cmp::Reverse(name.len()));
+    // ASCII lowercasing keeps byte offsets, so matches index the original text
+    let lower = text.to_ascii_lowercase();
+
+    let mut replaced = String::with_capacity(text.len());
+    let mut copied = 0;
+    let mut at = 0;
+    while at < lower.len() {
+        let rest = &lower[at..];
+        match by_length
+            .iter()
+            .find(|(name, _)| rest.starts_with(name.as_str()))
+        {
+            Some((name, pseudonym)) => {
+                replaced.push_str(&text[copied..at]);
+                replaced.push_str(pseudonym);
+                at += name.len();
+                copied = at;
+            }
+            None => at += rest.chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    replaced.push_str(&text[copied..]);
+    replaced
+}
+
+/// Whether a payload still contains any player name, ignoring ASCII case
+fn mentions_any(payload: &[u8], names: &BTreeMap<String, String>) -> bool {
+    let payload = payload.to_ascii_lowercase();
+    names.keys().any(|name| {
+        let needle = name.as_bytes();
+        payload.windows(needle.len()).any(|window| window == needle)
+    })
+}
+
+fn normalize(name: &str) -> String {
+    name.trim().to_ascii_lowercase()
+}
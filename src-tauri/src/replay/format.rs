@@ -0,0 +1,552 @@
+use crate::common::error::ReplayError;
+use crate::game::event::{GameEvent, GameEventKind};
+use crate::protocol::frame::{Direction, Frame};
+use crate::protocol::packet::FlowKey;
+use crate::protocol::version::ClientVersion;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// File extension of replay files
+pub const REPLAY_EXTENSION: &str = "mtgoreplay";
+
+/// First bytes of every replay file
+pub const MAGIC: [u8; 8] = *b"MTGOREPL";
+
+/// Container format version written by this build
+///
+/// Readers refuse files with a newer major version. Minor versions only add chunk
+/// types or header fields, which older readers skip or ignore.
+///
+/// 1.1 added the optional frames chunk, 1.2 the optional signature chunk.
+pub const FORMAT_MAJOR: u16 = 1;
+pub const FORMAT_MINOR: u16 = 2;
+
+/// Magic, major, minor, header length, header CRC
+pub const PREAMBLE_LEN: usize = 20;
+
+/// Kind, flags, stored length, raw length, first seq, last seq, CRC
+pub const CHUNK_HEADER_LEN: usize = 32;
+
+/// Index offset followed by `FOOTER_MAGIC`, the last bytes of a finished replay
+pub const FOOTER_LEN: usize = 16;
+pub const FOOTER_MAGIC: [u8; 8] = *b"REPLEND\0";
+
+/// Largest header or chunk payload accepted, so a corrupt length field cannot
+/// trigger a huge allocation
+pub const MAX_HEADER_LEN: u32 = 1024 * 1024;
+pub const MAX_CHUNK_LEN: u32 = 64 * 1024 * 1024;
+
+/// Chunk payload is zstd-compressed (PERF-003)
+pub const FLAG_COMPRESSED: u16 = 0x0001;
+/// Readers that do not understand the chunk kind must refuse the file rather than skip it
+pub const FLAG_REQUIRED: u16 = 0x0002;
+
+/// Chunk types understood by this build
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkKind {
+    /// Consecutive game events
+    Events,
+    /// Full game state at one point, for seeking
+    Snapshot,
+    /// Closed match record (result, games, decks)
+    Match,
+    /// Raw framed messages the events were decoded from, for re-decoding with
+    /// newer schemas; the sequence range holds frame indices, not event seqs
+    Frames,
+    /// Signature over the header and every other chunk, written last before the index
+    Signature,
+    /// Location of every chunk; written once when the replay is finished
+    Index,
+}
+
+impl ChunkKind {
+    /// Wire code of the chunk kind
+    pub fn code(self) -> u16 {
+        match self {
+            ChunkKind::Events => 0x0001,
+            ChunkKind::Snapshot => 0x0002,
+            ChunkKind::Match => 0x0003,
+            ChunkKind::Frames => 0x0004,
+            ChunkKind::Signature => 0x0005,
+            ChunkKind::Index => 0x00FF,
+        }
+    }
+
+    /// Map a wire code; None for kinds written by a newer build
+    pub fn from_code(code: u16) -> Option<ChunkKind> {
+        match code {
+            0x0001 => Some(ChunkKind::Events),
+            0x0002 => Some(ChunkKind::Snapshot),
+            0x0003 => Some(ChunkKind::Match),
+            0x0004 => Some(ChunkKind::Frames),
+            0x0005 => Some(ChunkKind::Signature),
+            0x00FF => Some(ChunkKind::Index),
+            _ => None,
+        }
+    }
+}
+
+/// Match metadata known when the replay starts
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayMatch {
+    pub match_id: u32,
+    pub format: String,
+    pub event_type: String,
+    pub best_of: u8,
+    pub local_player: String,
+    pub opponent: String,
+}
+
+/// Replay file header (REPL-001)
+///
+/// Stored as a MessagePack map, so fields added later are ignored by older readers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    /// MTGO client version reported by the handshake, if seen
+    pub client_version: Option<ClientVersion>,
+    /// Schema set the events were decoded with
+    pub schema_version: Option<ClientVersion>,
+    /// Version of the application that captured the replay
+    pub app_version: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    /// Capture session the replay was recorded in
+    pub session_id: Option<String>,
+    #[serde(rename = "match")]
+    pub match_info: Option<ReplayMatch>,
+    /// Present if the replay was anonymized for sharing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redactions: Option<Redactions>,
+}
+
+/// What anonymizing a replay removed or rewrote (XPORT-001)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Redactions {
+    pub anonymized_at: chrono::DateTime<chrono::Utc>,
+    /// Distinct player names replaced with pseudonyms
+    pub players_renamed: u32,
+    /// Raw frames whose player names were rewritten to pseudonyms
+    pub frames_rewritten: u64,
+    /// Raw frames removed because they carried identifiers or could not be inspected
+    pub frames_removed: u64,
+    /// Chat messages removed from the raw frames
+    pub chat_removed: u64,
+    /// Connection addresses and ports removed from the raw frames
+    pub network_addresses_removed: bool,
+    /// Reference to the local capture session removed
+    pub session_id_removed: bool,
+}
+
+impl ReplayHeader {
+    /// Header for a replay captured by this build
+    pub fn new(created_at: chrono::DateTime<chrono::Utc>) -> Self {
+        Self {
+            client_version: None,
+            schema_version: None,
+            app_version: env!("CARGO_PKG_VERSION").to_string(),
+            created_at,
+            session_id: None,
+            match_info: None,
+            redactions: None,
+        }
+    }
+}
+
+/// Fixed-size header in front of every chunk payload
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkHeader {
+    pub kind: u16,
+    pub flags: u16,
+    /// Payload length on disk
+    pub stored_len: u32,
+    /// Payload length after decompression
+    pub raw_len: u32,
+    /// Event sequence range covered (events: first and last event, snapshot: its seq,
+    /// frames: first and last frame index)
+    pub first_seq: u64,
+    pub last_seq: u64,
+    /// CRC-32 of the other header fields followed by the stored payload
+    pub crc: u32,
+}
+
+impl ChunkHeader {
+    pub fn encode(&self) -> [u8; CHUNK_HEADER_LEN] {
+        let mut bytes = [0u8; CHUNK_HEADER_LEN];
+        bytes[0..2].copy_from_slice(&self.kind.to_le_bytes());
+        bytes[2..4].copy_from_slice(&self.flags.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.stored_len.to_le_bytes());
+        bytes[8..12].copy_from_slice(&self.raw_len.to_le_bytes());
+        bytes[12..20].copy_from_slice(&self.first_seq.to_le_bytes());
+        bytes[20..28].copy_from_slice(&self.last_seq.to_le_bytes());
+        bytes[28..32].copy_from_slice(&self.crc.to_le_bytes());
+        bytes
+    }
+
+    pub fn decode(bytes: &[u8; CHUNK_HEADER_LEN]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([bytes[i], bytes[i + 1]]);
+        let u32_at =
+            |i: usize| u32::from_le_bytes([bytes[i], bytes[i + 1], bytes[i + 2], bytes[i + 3]]);
+        let u64_at = |i: usize| {
+            let mut b = [0u8; 8];
+            b.copy_from_slice(&bytes[i..i + 8]);
+            u64::from_le_bytes(b)
+        };
+        Self {
+            kind: u16_at(0),
+            flags: u16_at(2),
+            stored_len: u32_at(4),
+            raw_len: u32_at(8),
+            first_seq: u64_at(12),
+            last_seq: u64_at(20),
+            crc: u32_at(28),
+        }
+    }
+
+    /// CRC over the header fields (without the CRC itself) and the stored payload
+    pub fn compute_crc(&self, payload: &[u8]) -> u32 {
+        let mut hasher = crc32fast::Hasher::new();
+        hasher.update(&self.encode()[..CHUNK_HEADER_LEN - 4]);
+        hasher.update(payload);
+        hasher.finalize()
+    }
+
+    pub fn is_compressed(&self) -> bool {
+        self.flags & FLAG_COMPRESSED != 0
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.flags & FLAG_REQUIRED != 0
+    }
+}
+
+/// Location of one chunk within a replay file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IndexEntry {
+    pub kind: u16,
+    pub flags: u16,
+    /// Byte offset of the chunk header
+    pub offset: u64,
+    pub first_seq: u64,
+    pub last_seq: u64,
+}
+
+impl IndexEntry {
+    pub fn chunk_kind(&self) -> Option<ChunkKind> {
+        ChunkKind::from_code(self.kind)
+    }
+
+    pub fn is_required(&self) -> bool {
+        self.flags & FLAG_REQUIRED != 0
+    }
+}
+
+/// Payload of a signature chunk
+///
+/// The signature covers the MessagePack bytes of a `SignedManifest` exactly as
+/// stored, so verifying does not depend on re-encoding anything.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplaySignature {
+    pub manifest: ByteBuf,
+    /// Ed25519 signature over `manifest`
+    pub signature: ByteBuf,
+}
+
+/// What a replay signature vouches for
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedManifest {
+    /// Ed25519 public key of the signer
+    pub public_key: ByteBuf,
+    pub signed_at: chrono::DateTime<chrono::Utc>,
+    /// SHA-256 of the header bytes as stored
+    pub header_sha256: ByteBuf,
+    /// Every chunk of the replay except the signature and the index, in file order
+    pub chunks: Vec<ChunkDigest>,
+}
+
+/// Identity and content hash of one signed chunk
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkDigest {
+    pub kind: u16,
+    pub first_seq: u64,
+    pub last_seq: u64,
+    /// SHA-256 of the decompressed payload, so recompressing a chunk keeps it valid
+    pub sha256: ByteBuf,
+}
+
+/// Event as stored in an events chunk
+///
+/// `GameEvent` flattens its kind into the event for the frontend, which makes
+/// `game_id` ambiguous for the game start and end kinds; chunks keep the kind nested.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StoredEvent {
+    seq: u64,
+    game_id: Option<u32>,
+    frame_index: u64,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    kind: GameEventKind,
+}
+
+impl From<&GameEvent> for StoredEvent {
+    fn from(event: &GameEvent) -> Self {
+        Self {
+            seq: event.seq,
+            game_id: event.game_id,
+            frame_index: event.frame_index,
+            timestamp: event.timestamp,
+            kind: event.kind.clone(),
+        }
+    }
+}
+
+impl From<StoredEvent> for GameEvent {
+    fn from(event: StoredEvent) -> Self {
+        Self {
+            seq: event.seq,
+            game_id: event.game_id,
+            frame_index: event.frame_index,
+            timestamp: event.timestamp,
+            kind: event.kind,
+        }
+    }
+}
+
+/// Payload of a frames chunk
+///
+/// Flows and payloads are stored once per chunk and referenced by position, since
+/// the same connection carries every frame and many messages repeat verbatim.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub(crate) struct StoredFrames {
+    flows: Vec<FlowKey>,
+    payloads: Vec<ByteBuf>,
+    frames: Vec<StoredFrame>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredFrame {
+    index: u64,
+    timestamp: chrono::DateTime<chrono::Utc>,
+    direction: Direction,
+    type_id: u16,
+    /// Position in `StoredFrames::flows`
+    flow: u32,
+    /// Position in `StoredFrames::payloads`
+    payload: u32,
+}
+
+impl StoredFrames {
+    pub fn new(frames: &[Frame]) -> Self {
+        let mut stored = Self::default();
+        let mut flows: HashMap<&FlowKey, u32> = HashMap::new();
+        let mut payloads: HashMap<&[u8], u32> = HashMap::new();
+        for frame in frames {
+            let flow = *flows.entry(&frame.flow).or_insert_with(|| {
+                stored.flows.push(frame.flow);
+                (stored.flows.len() - 1) as u32
+            });
+            let payload = *payloads.entry(frame.payload.as_slice()).or_insert_with(|| {
+                stored.payloads.push(ByteBuf::from(frame.payload.clone()));
+                (stored.payloads.len() - 1) as u32
+            });
+            stored.frames.push(StoredFrame {
+                index: frame.index,
+                timestamp: frame.timestamp,
+                direction: frame.direction,
+                type_id: frame.type_id,
+                flow,
+                payload,
+            });
+        }
+        stored
+    }
+
+    /// Expand back into frames
+    ///
+    /// # Returns
+    /// Err(ReplayError::Corrupt) if a frame refers to a flow or payload that is not stored
+    pub fn into_frames(self) -> Result<Vec<Frame>, ReplayError> {
+        let lookup = |table: &str, len: usize, position: u32| {
+            if (position as usize) < len {
+                Ok(position as usize)
+            } else {
+                Err(ReplayError::Corrupt(format!(
+                    "frames chunk refers to {} {} of {}",
+                    table, position, len
+                )))
+            }
+        };
+        self.frames
+            .iter()
+            .map(|frame| {
+                Ok(Frame {
+                    flow: self.flows[lookup("flow", self.flows.len(), frame.flow)?],
+                    direction: frame.direction,
+                    timestamp: frame.timestamp,
+                    index: frame.index,
+                    type_id: frame.type_id,
+                    payload: self.payloads[lookup("payload", self.payloads.len(), frame.payload)?]
+                        .to_vec(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Path next to a replay with a suffix appended to its full file name, e.g.
+/// `match.mtgoreplay.bak`, for backups and copies written before a rename
+pub fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Encode a header, index or chunk payload as MessagePack
+pub fn encode<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, ReplayError> {
+    rmp_serde::to_vec_named(value).map_err(|e| ReplayError::Encode(e.to_string()))
+}
+
+/// Decode a header, index or chunk payload
+pub fn decode<T: DeserializeOwned>(bytes: &[u8], what: &str) -> Result<T, ReplayError> {
+    rmp_serde::from_slice(bytes).map_err(|e| ReplayError::Corrupt(format!("{}: {}", what, e)))
+}
+
+/// Read exactly `buf.len()` bytes
+///
+/// # Returns
+/// Ok(false) if the input ended first (a truncated file, not an I/O failure)
+pub fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<bool, ReplayError> {
+    match reader.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Read `len` bytes into `buf`, growing it as data arrives so a corrupt length
+/// cannot allocate more than the file holds
+///
+/// # Returns
+/// Ok(false) if the input ended first
+pub fn read_up_to<R: Read>(
+    reader: &mut R,
+    len: u32,
+    buf: &mut Vec<u8>,
+) -> Result<bool, ReplayError> {
+    let read = reader.take(len as u64).read_to_end(buf)?;
+    Ok(read == len as usize)
+}
+
+/// A chunk read from disk with its checksum verified
+pub struct RawChunk {
+    pub header: ChunkHeader,
+    /// Decompressed payload
+    pub payload: Vec<u8>,
+}
+
+/// Read and verify the chunk whose header starts at `offset`
+///
+/// # Returns
+/// Ok(None) if the file ends inside the chunk
+/// Err(ReplayError) if the chunk is damaged
+pub fn read_chunk_at<R: Read + Seek>(
+    reader: &mut R,
+    offset: u64,
+) -> Result<Option<RawChunk>, ReplayError> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut header_bytes = [0u8; CHUNK_HEADER_LEN];
+    if !read_full(reader, &mut header_bytes)? {
+        return Ok(None);
+    }
+    let header = ChunkHeader::decode(&header_bytes);
+    if header.stored_len > MAX_CHUNK_LEN || header.raw_len > MAX_CHUNK_LEN {
+        return Err(ReplayError::Corrupt(format!(
+            "chunk at offset {} claims {} bytes",
+            offset,
+            header.stored_len.max(header.raw_len)
+        )));
+    }
+
+    let mut stored = Vec::new();
+    if !read_up_to(reader, header.stored_len, &mut stored)? {
+        return Ok(None);
+    }
+    if header.compute_crc(&stored) != header.crc {
+        return Err(ReplayError::ChecksumMismatch(offset));
+    }
+
+    let payload = if header.is_compressed() {
+        // Let the output grow with what actually decompresses rather than trusting raw_len
+        let mut payload = Vec::new();
+        zstd::stream::read::Decoder::with_buffer(stored.as_slice())
+            .and_then(|decoder| {
+                decoder
+                    .take(header.raw_len as u64 + 1)
+                    .read_to_end(&mut payload)
+            })
+            .map_err(|e| ReplayError::Corrupt(format!("chunk at offset {}: {}", offset, e)))?;
+        payload
+    } else {
+        stored
+    };
+    if payload.len() != header.raw_len as usize {
+        return Err(ReplayError::Corrupt(format!(
+            "chunk at offset {} decompressed to {} bytes, expected {}",
+            offset,
+            payload.len(),
+            header.raw_len
+        )));
+    }
+    Ok(Some(RawChunk { header, payload }))
+}
+
+/// Result of walking the chunks of a replay from the start
+pub struct ScanResult {
+    /// Every intact chunk, in file order (index chunks excluded)
+    pub entries: Vec<IndexEntry>,
+    /// Offset just past the last intact chunk
+    pub valid_len: u64,
+    /// True if the scan stopped at a damaged or incomplete chunk rather than the end of the file
+    pub truncated: bool,
+}
+
+/// Walk the chunks from `start` until the end of the file, an index chunk, or the
+/// first damaged chunk
+///
+/// Used for replays without an index, which is what a crash mid-capture leaves.
+pub fn scan_chunks<R: Read + Seek>(reader: &mut R, start: u64) -> Result<ScanResult, ReplayError> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    let mut entries = Vec::new();
+    let mut offset = start;
+    let mut truncated = false;
+
+    while offset < file_len {
+        let chunk = match read_chunk_at(reader, offset) {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) | Err(ReplayError::ChecksumMismatch(_)) | Err(ReplayError::Corrupt(_)) => {
+                truncated = true;
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        if chunk.header.kind == ChunkKind::Index.code() {
+            break;
+        }
+        entries.push(IndexEntry {
+            kind: chunk.header.kind,
+            flags: chunk.header.flags,
+            offset,
+            first_seq: chunk.header.first_seq,
+            last_seq: chunk.header.last_seq,
+        });
+        offset += (CHUNK_HEADER_LEN as u64) + chunk.header.stored_len as u64;
+    }
+
+    Ok(ScanResult {
+        entries,
+        valid_len: offset,
+        truncated,
+    })
+}
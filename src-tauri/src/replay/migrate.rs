@@ -0,0 +1,357 @@
+use crate::common::error::ReplayError;
+use crate::game::engine::GameEngine;
+use crate::game::event::{GameEvent, GameSnapshot};
+use crate::game::lifecycle::Match;
+use crate::protocol::archive;
+use crate::protocol::decoder::Decoder;
+use crate::protocol::frame::Frame;
+use crate::protocol::version::SchemaRegistry;
+use crate::replay::format::{self, IndexEntry, ReplayHeader, FORMAT_MAJOR, FORMAT_MINOR, MAGIC};
+use crate::replay::reader::{Chunk, ReplayReader};
+use crate::replay::writer::ReplayWriter;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Suffix given to the original file when a replay is migrated in place
+pub const BACKUP_SUFFIX: &str = ".bak";
+
+/// Everything a replay holds, independent of the format version it was written with
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayContents {
+    /// Format (major, minor) version the replay was read from
+    pub version: (u16, u16),
+    pub header: ReplayHeader,
+    pub events: Vec<GameEvent>,
+    pub snapshots: Vec<GameSnapshot>,
+    pub match_record: Option<Match>,
+    /// Raw frames recorded with the replay, empty if none were
+    pub frames: Vec<Frame>,
+    /// Optional chunks of kinds this build does not know, kept byte for byte
+    pub unknown_chunks: Vec<(IndexEntry, Vec<u8>)>,
+    /// The file was signed; the signature is not kept, since rewriting the
+    /// replay would invalidate it
+    pub signed: bool,
+}
+
+/// Where re-decoding takes the original frames from
+#[derive(Clone)]
+pub struct Redecode {
+    /// Schemas to decode with, normally the current registry including user schemas
+    pub registry: Arc<SchemaRegistry>,
+    /// Sessions directory holding the archive named by the replay header, used
+    /// for replays recorded without their frames
+    pub sessions_dir: Option<PathBuf>,
+}
+
+/// How `migrate` rewrites a replay
+#[derive(Clone, Default)]
+pub struct MigrateOptions {
+    /// Rebuild events and snapshots from the original frames with current schemas
+    pub redecode: Option<Redecode>,
+    /// Rewrite replays already at the current format version
+    pub force: bool,
+    /// When migrating in place, keep the original next to it with `BACKUP_SUFFIX`
+    pub backup: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationStatus {
+    Migrated,
+    /// Already at the current format version; nothing was written
+    UpToDate,
+}
+
+/// Outcome of migrating one replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    /// The replay that was read
+    pub source: PathBuf,
+    /// Where the migrated replay was written (the source when migrating in place)
+    pub output: PathBuf,
+    pub status: MigrationStatus,
+    /// Format version of the source, e.g. "1.0"
+    pub from_version: String,
+    pub to_version: String,
+    pub redecoded: bool,
+    pub events_before: usize,
+    pub events_after: usize,
+    /// The source was signed and the migrated replay no longer is
+    pub signature_removed: bool,
+    /// Copy of the original kept when migrating in place
+    pub backup: Option<PathBuf>,
+}
+
+/// Read a replay written with any format version this build has a reader for
+///
+/// Only the preamble layout (magic bytes then major and minor version) is shared
+/// by every version; the rest of the file is read by the reader for its major
+/// version. Retiring a major version means adding its reader here, along with a
+/// sample file under `tests/compat`.
+///
+/// # Returns
+/// Err(ReplayError::UnsupportedVersion) if no reader exists for the file's version
+pub fn load(path: &Path) -> Result<ReplayContents, ReplayError> {
+    let mut file = BufReader::new(File::open(path)?);
+    let (major, minor) = read_version(&mut file)?;
+    match major {
+        1 => load_v1(file),
+        _ => Err(ReplayError::UnsupportedVersion { major, minor }),
+    }
+}
+
+/// Write replay contents with the current format version
+///
+/// The file is written next to `path` and renamed over it once complete.
+pub fn save(contents: &ReplayContents, path: &Path) -> Result<(), ReplayError> {
+    let tmp = format::sibling(path, ".tmp");
+    let result = write_contents(contents, &tmp);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Bring a replay up to the current format version, optionally re-decoding it
+///
+/// # Arguments
+/// * `path` - Replay to migrate
+/// * `output` - Where to write the result; None migrates in place
+/// * `options` - Re-decode, force and backup settings
+///
+/// # Returns
+/// What was done; replays already current are left alone unless forced or re-decoded
+pub fn migrate(
+    path: &Path,
+    output: Option<&Path>,
+    options: &MigrateOptions,
+) -> Result<MigrationReport, ReplayError> {
+    let mut contents = load(path)?;
+    let output = output.unwrap_or(path).to_path_buf();
+    let mut report = MigrationReport {
+        source: path.to_path_buf(),
+        output: output.clone(),
+        status: MigrationStatus::UpToDate,
+        from_version: version_string(contents.version),
+        to_version: version_string((FORMAT_MAJOR, FORMAT_MINOR)),
+        redecoded: false,
+        events_before: contents.events.len(),
+        events_after: contents.events.len(),
+        signature_removed: false,
+        backup: None,
+    };
+
+    let current = contents.version == (FORMAT_MAJOR, FORMAT_MINOR);
+    if current && !options.force && options.redecode.is_none() && output == path {
+        return Ok(report);
+    }
+
+    if let Some(redecode) = &options.redecode {
+        contents = redecoded(contents, redecode)?;
+        report.redecoded = true;
+        report.events_after = contents.events.len();
+    }
+
+    if output == path && options.backup {
+        let backup = format::sibling(path, BACKUP_SUFFIX);
+        std::fs::copy(path, &backup)?;
+        report.backup = Some(backup);
+    }
+    if let Some(parent) = output.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+    save(&contents, &output)?;
+
+    if contents.signed {
+        warn!(
+            "Removed the signature of replay {} while migrating it; sign {} again to share it as signed",
+            path.display(),
+            output.display()
+        );
+        report.signature_removed = true;
+    }
+    info!(
+        "Migrated replay {} from format {} to {}",
+        path.display(),
+        report.from_version,
+        report.to_version
+    );
+    report.status = MigrationStatus::Migrated;
+    Ok(report)
+}
+
+/// Rebuild events and snapshots from the original frames
+///
+/// Frames embedded in the replay are used when present: the match is decoded on
+/// its own and events keep numbering from the replay's first event. Otherwise the
+/// capture session named by the header is decoded up to the replay's last frame,
+/// so the engine has the state it had during capture, and only events and
+/// snapshots from frames within the replay's frame range are kept.
+fn redecoded(
+    mut contents: ReplayContents,
+    redecode: &Redecode,
+) -> Result<ReplayContents, ReplayError> {
+    let embedded = !contents.frames.is_empty();
+    let (frames, first_frame, last_frame) = if embedded {
+        (contents.frames.clone(), 0, u64::MAX)
+    } else {
+        session_frames(&contents, redecode)?
+    };
+
+    let mut decoder = Decoder::new(redecode.registry.clone())
+        .map_err(|e| ReplayError::Redecode(e.to_string()))?;
+    let mut engine = GameEngine::new();
+    let mut events = Vec::new();
+    let mut snapshots = Vec::new();
+
+    for frame in frames.iter().take_while(|f| f.index <= last_frame) {
+        let Ok(message) = decoder.decode(frame) else {
+            continue;
+        };
+        match engine.apply(frame, &message) {
+            Ok(update) if frame.index >= first_frame => {
+                events.extend(update.events);
+                snapshots.extend(update.snapshot);
+            }
+            Ok(_) => {}
+            Err(e) => warn!("Skipping frame {} while re-decoding: {}", frame.index, e),
+        }
+    }
+    if events.is_empty() {
+        return Err(ReplayError::Redecode(
+            "no game events were decoded from the original frames".to_string(),
+        ));
+    }
+
+    if embedded {
+        let first_seq = contents.events.first().map(|e| e.seq).unwrap_or(0);
+        events.iter_mut().for_each(|e| e.seq += first_seq);
+        snapshots.iter_mut().for_each(|s| s.seq += first_seq);
+    }
+    contents.header.client_version = decoder.client_version().cloned();
+    contents.header.schema_version = Some(decoder.active_version().clone());
+    contents.events = events;
+    contents.snapshots = snapshots;
+    Ok(contents)
+}
+
+/// Frames of the capture session a replay came from, with the replay's frame range
+fn session_frames(
+    contents: &ReplayContents,
+    redecode: &Redecode,
+) -> Result<(Vec<Frame>, u64, u64), ReplayError> {
+    let sessions_dir = redecode.sessions_dir.as_ref().ok_or_else(|| {
+        ReplayError::Redecode(
+            "the replay has no embedded frames and no sessions directory was given".to_string(),
+        )
+    })?;
+    let session_id = contents.header.session_id.as_deref().ok_or_else(|| {
+        ReplayError::Redecode("the replay does not name its capture session".to_string())
+    })?;
+    let frame_indices = || {
+        contents
+            .events
+            .iter()
+            .map(|e| e.frame_index)
+            .chain(contents.snapshots.iter().map(|s| s.frame_index))
+    };
+    let (Some(first_frame), Some(last_frame)) = (frame_indices().min(), frame_indices().max())
+    else {
+        return Err(ReplayError::Redecode(
+            "the replay has no events to locate in its session".to_string(),
+        ));
+    };
+
+    let frames = archive::session_dir(sessions_dir, session_id)
+        .and_then(|dir| archive::read_all_frames(&dir))
+        .map_err(|e| ReplayError::Redecode(format!("session {}: {}", session_id, e)))?;
+    Ok((frames, first_frame, last_frame))
+}
+
+/// Read the format version from the preamble
+fn read_version<R: Read>(reader: &mut R) -> Result<(u16, u16), ReplayError> {
+    let mut preamble = [0u8; 12];
+    if !format::read_full(reader, &mut preamble)? || preamble[0..8] != MAGIC {
+        return Err(ReplayError::BadMagic);
+    }
+    Ok((
+        u16::from_le_bytes([preamble[8], preamble[9]]),
+        u16::from_le_bytes([preamble[10], preamble[11]]),
+    ))
+}
+
+/// Reader for format 1.x
+fn load_v1(file: BufReader<File>) -> Result<ReplayContents, ReplayError> {
+    let mut reader = ReplayReader::new(file)?;
+    let mut contents = ReplayContents {
+        version: reader.format_version(),
+        header: reader.header().clone(),
+        events: Vec::new(),
+        snapshots: Vec::new(),
+        match_record: None,
+        frames: Vec::new(),
+        unknown_chunks: Vec::new(),
+        signed: false,
+    };
+    for entry in reader.index().to_vec() {
+        match reader.read_chunk(&entry)? {
+            Chunk::Events(events) => contents.events.extend(events),
+            Chunk::Snapshot(snapshot) => contents.snapshots.push(*snapshot),
+            Chunk::Match(record) => contents.match_record = Some(*record),
+            Chunk::Frames(frames) => contents.frames.extend(frames),
+            Chunk::Signature(_) => contents.signed = true,
+            Chunk::Unknown { payload, .. } => contents.unknown_chunks.push((entry, payload)),
+        }
+    }
+    Ok(contents)
+}
+
+fn write_contents(contents: &ReplayContents, path: &Path) -> Result<(), ReplayError> {
+    let mut writer = ReplayWriter::new(BufWriter::new(File::create(path)?), &contents.header)?;
+
+    // A snapshot goes after the events it covers
+    let mut snapshots = contents.snapshots.iter().peekable();
+    for event in &contents.events {
+        while let Some(snapshot) = snapshots.next_if(|s| s.seq < event.seq) {
+            writer.write_snapshot(snapshot)?;
+        }
+        writer.write_event(event)?;
+    }
+    for snapshot in snapshots {
+        writer.write_snapshot(snapshot)?;
+    }
+    writer.flush_events()?;
+    for frame in &contents.frames {
+        writer.write_frame(frame)?;
+    }
+    writer.flush_frames()?;
+    for (entry, payload) in &contents.unknown_chunks {
+        writer.write_chunk(
+            entry.kind,
+            entry.flags,
+            entry.first_seq,
+            entry.last_seq,
+            payload,
+        )?;
+    }
+    if let Some(record) = &contents.match_record {
+        writer.write_match(record)?;
+    }
+
+    let file = writer
+        .finish()?
+        .into_inner()
+        .map_err(|e| ReplayError::Io(e.into_error()))?;
+    file.sync_all()?;
+    Ok(())
+}
+
+fn version_string((major, minor): (u16, u16)) -> String {
+    format!("{}.{}", major, minor)
+}
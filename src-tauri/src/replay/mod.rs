@@ -0,0 +1,54 @@
+//! Versioned replay file format (REPL-001)
+//!
+//! A replay file is a preamble (magic bytes, format version, header length and
+//! CRC), a MessagePack header, then a sequence of chunks. Every chunk carries its
+//! kind, flags, the event sequence range it covers and a CRC-32, and its payload is
+//! zstd-compressed. A finished replay ends with an index chunk and a fixed footer
+//! pointing at it:
+//!
+//! ```text
+//! "MTGOREPL" major:u16 minor:u16 header_len:u32 header_crc:u32 header
+//! [kind:u16 flags:u16 stored_len:u32 raw_len:u32 first_seq:u64 last_seq:u64 crc:u32 payload]*
+//! index chunk, index_offset:u64 "REPLEND\0"
+//! ```
+//!
+//! All integers are little-endian. Readers skip chunk kinds they do not know
+//! unless the chunk is flagged as required.
+//!
+//! Besides events, snapshots and the match record, a replay can carry the raw
+//! framed messages it was decoded from (an optional frames chunk), so it can be
+//! decoded again when schemas improve. A replay can also be signed (`signing`): a
+//! signature chunk holds an Ed25519 signature over the header and the hash of
+//! every other chunk, so edits made after signing can be pinpointed.
+//!
+//! During capture each match is streamed to a `.partial` file that has no index
+//! yet; `recovery` finalizes any such file left behind by a crash. Replays
+//! written by older format versions are read and rewritten by `migrate`, and
+//! `anonymize` prepares a copy for sharing. `playback` steps through a replay for
+//! the viewer, and `annotations` keeps coaching notes and bookmarks on its
+//! timeline in a file beside it.
+
+pub mod annotations;
+pub mod anonymize;
+pub mod format;
+pub mod migrate;
+pub mod playback;
+pub mod reader;
+pub mod recovery;
+pub mod signing;
+pub mod sink;
+pub mod stream;
+pub mod writer;
+
+pub use annotations::{
+    Annotation, AnnotationAnchor, AnnotationDraft, MergeReport, ReplayAnnotations,
+};
+pub use anonymize::{AnonymizeReport, Pseudonymizer};
+pub use format::{ChunkKind, IndexEntry, Redactions, ReplayHeader, ReplayMatch};
+pub use migrate::{MigrateOptions, MigrationReport, ReplayContents};
+pub use playback::{PlaybackOutline, PlaybackView, ReplayPlayback, SeekTarget, StepUnit};
+pub use reader::{Chunk, ReplayReader, SeekPoint};
+pub use signing::{Keyring, SigningIdentity, VerifyReport};
+pub use sink::ReplaySink;
+pub use stream::{StreamConfig, StreamingReplayWriter};
+pub use writer::ReplayWriter;
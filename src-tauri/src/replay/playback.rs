@@ -0,0 +1,522 @@
+use crate::common::error::ReplayError;
+use crate::game::event::{GameEvent, GameEventKind, GameSnapshot};
+use crate::game::knowledge::{KnowledgeIndex, Provenance, ViewMode};
+use crate::game::model::{GameState, ObjectId, Phase, PlayerId, Step, TurnInfo};
+use crate::replay::format::ReplayHeader;
+use crate::replay::reader::ReplayReader;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+/// Slowest and fastest playback speeds, as multiples of real time
+pub const MIN_SPEED: f64 = 0.25;
+pub const MAX_SPEED: f64 = 16.0;
+
+/// Bounds of the pause between two frames at normal speed
+///
+/// Frames arriving together still get a short pause so each change can be seen,
+/// and long thinks are cut short.
+pub const MIN_FRAME_DELAY: Duration = Duration::from_millis(150);
+pub const MAX_FRAME_DELAY: Duration = Duration::from_secs(2);
+
+/// Where to move the playback cursor
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SeekTarget {
+    /// Before the first event
+    Start,
+    /// After the last event
+    End,
+    /// Just after an event, by sequence number
+    Event { seq: u64 },
+    /// Start of a game
+    Game { game_id: u32 },
+    /// Start of a turn; `game_id` defaults to the game shown
+    Turn { game_id: Option<u32>, turn: u32 },
+    /// First step of a phase of a turn
+    Phase {
+        game_id: Option<u32>,
+        turn: u32,
+        phase: Phase,
+    },
+    /// A step of a turn
+    Step {
+        game_id: Option<u32>,
+        turn: u32,
+        step: Step,
+    },
+}
+
+/// Granularity of stepping forward and back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepUnit {
+    Event,
+    /// Turn steps (upkeep, draw, declare attackers...)
+    Step,
+    Turn,
+    Game,
+}
+
+/// One turn on the playback timeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnMarker {
+    pub turn: u32,
+    pub active_player: PlayerId,
+    /// Cursor position at the start of the turn
+    pub position: usize,
+}
+
+/// One game on the playback timeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameMarker {
+    pub game_id: u32,
+    /// Cursor position at the start of the game
+    pub position: usize,
+    pub turns: Vec<TurnMarker>,
+}
+
+/// Games and turns of a replay, for drawing the playback timeline
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaybackOutline {
+    pub header: ReplayHeader,
+    /// Number of events; the cursor ranges over 0..=total
+    pub total: usize,
+    pub games: Vec<GameMarker>,
+}
+
+/// Board state at the cursor, as sent to the renderer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackView {
+    /// Events applied so far
+    pub position: usize,
+    pub total: usize,
+    /// Last applied event; None before the first
+    pub seq: Option<u64>,
+    pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    pub turn: TurnInfo,
+    pub playing: bool,
+    pub speed: f64,
+    pub mode: ViewMode,
+    /// Player whose knowledge the board shows, if known
+    pub viewer: Option<PlayerId>,
+    /// Events applied by the last move, when it moved forward; for animating them
+    pub events: Vec<GameEvent>,
+    /// Board with the cards the viewer did not know masked
+    pub state: GameState,
+    /// Why each object is shown face up or masked
+    pub knowledge: BTreeMap<ObjectId, Provenance>,
+}
+
+/// Cursor over the events of a replay (VIEW-001)
+///
+/// The board at any position is rebuilt from the closest snapshot at or before
+/// it plus the events after that snapshot, so seeking costs at most one
+/// snapshot interval of events. Moving forward from the current position only
+/// applies the events in between.
+///
+/// The board is shown as the capturing player saw it, with everything the replay
+/// reveals, or as the opponent saw it (`ViewMode`); cards the viewer could not
+/// have known are masked.
+pub struct ReplayPlayback {
+    header: ReplayHeader,
+    events: Vec<GameEvent>,
+    knowledge: KnowledgeIndex,
+    mode: ViewMode,
+    /// Snapshots with the cursor position they were taken at, in order
+    snapshots: Vec<(usize, GameSnapshot)>,
+    position: usize,
+    state: GameState,
+    /// Events the last move applied, as a range of positions
+    applied: std::ops::Range<usize>,
+    playing: bool,
+    speed: f64,
+}
+
+impl ReplayPlayback {
+    /// Load a replay's events and snapshots
+    pub fn open(path: &Path) -> Result<Self, ReplayError> {
+        let mut reader = ReplayReader::open(path)?;
+        let events = reader.events()?;
+        let snapshots = reader.snapshots()?;
+        Ok(Self::new(reader.header().clone(), events, snapshots))
+    }
+
+    /// # Arguments
+    /// * `header` - Header of the replay
+    /// * `events` - Events in sequence order
+    /// * `snapshots` - Snapshots in sequence order; any subset will do
+    pub fn new(header: ReplayHeader, events: Vec<GameEvent>, snapshots: Vec<GameSnapshot>) -> Self {
+        let snapshots = snapshots
+            .into_iter()
+            .map(|snapshot| (events.partition_point(|e| e.seq <= snapshot.seq), snapshot))
+            .collect();
+        Self {
+            knowledge: KnowledgeIndex::new(&events),
+            mode: ViewMode::default(),
+            header,
+            events,
+            snapshots,
+            position: 0,
+            state: GameState::default(),
+            applied: 0..0,
+            playing: false,
+            speed: 1.0,
+        }
+    }
+
+    /// Number of events applied to the board shown
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    /// Number of events in the replay
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    pub fn at_end(&self) -> bool {
+        self.position >= self.events.len()
+    }
+
+    /// Board after the applied events
+    pub fn state(&self) -> &GameState {
+        &self.state
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    pub fn mode(&self) -> ViewMode {
+        self.mode
+    }
+
+    /// Choose whose knowledge the board is shown with
+    pub fn set_mode(&mut self, mode: ViewMode) {
+        self.mode = mode;
+    }
+
+    /// Player whose knowledge the board shows at the cursor
+    pub fn viewer(&self) -> Option<PlayerId> {
+        let local_name = self
+            .header
+            .match_info
+            .as_ref()
+            .map(|info| info.local_player.as_str());
+        let local = self
+            .knowledge
+            .local_player(&self.state, self.position, local_name);
+        match self.mode {
+            ViewMode::AsPlayed | ViewMode::FullInformation => local,
+            ViewMode::OpponentPerspective => {
+                let local = local?;
+                self.state.players.keys().copied().find(|id| *id != local)
+            }
+        }
+    }
+
+    /// Set the speed, clamped to `MIN_SPEED..=MAX_SPEED`
+    pub fn set_speed(&mut self, speed: f64) {
+        if speed.is_finite() {
+            self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+        }
+    }
+
+    /// Start playing; playing from the end starts over
+    pub fn play(&mut self) {
+        if self.at_end() {
+            self.seek_position(0);
+        }
+        self.playing = !self.is_empty();
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Games and turns with their cursor positions
+    pub fn outline(&self) -> PlaybackOutline {
+        let mut games: Vec<GameMarker> = Vec::new();
+        for (i, event) in self.events.iter().enumerate() {
+            match &event.kind {
+                GameEventKind::GameStarted { game_id, .. } => games.push(GameMarker {
+                    game_id: *game_id,
+                    position: i + 1,
+                    turns: Vec::new(),
+                }),
+                GameEventKind::TurnStarted {
+                    turn,
+                    active_player,
+                } => {
+                    if let Some(game) = games.last_mut() {
+                        game.turns.push(TurnMarker {
+                            turn: *turn,
+                            active_player: *active_player,
+                            position: self.landing(i),
+                        });
+                    }
+                }
+                _ => {}
+            }
+        }
+        PlaybackOutline {
+            header: self.header.clone(),
+            total: self.events.len(),
+            games,
+        }
+    }
+
+    /// Board at the cursor as the viewer saw it, with the events the last move applied
+    pub fn view(&self) -> PlaybackView {
+        let last = self.position.checked_sub(1).map(|i| &self.events[i]);
+        let viewer = self.viewer();
+        let full = self.mode == ViewMode::FullInformation;
+        let captured = self.mode != ViewMode::OpponentPerspective;
+        let (state, knowledge) =
+            self.knowledge
+                .mask(&self.state, self.position, viewer, captured, full);
+        // Events carry identities too: moves show what the board shows, and
+        // only the viewer's own deck list is kept
+        let events = self.events[self.applied.clone()]
+            .iter()
+            .filter(|event| match &event.kind {
+                GameEventKind::DeckSubmitted { player, .. } => full || viewer == Some(*player),
+                _ => true,
+            })
+            .cloned()
+            .map(|mut event| {
+                if let GameEventKind::ZoneChanged {
+                    object, card_id, ..
+                } = &mut event.kind
+                {
+                    *card_id = state.objects.get(object).and_then(|o| o.card_id);
+                }
+                event
+            })
+            .collect();
+        PlaybackView {
+            position: self.position,
+            total: self.events.len(),
+            seq: last.map(|e| e.seq),
+            timestamp: last.map(|e| e.timestamp),
+            turn: self.state.turn.clone(),
+            playing: self.playing,
+            speed: self.speed,
+            mode: self.mode,
+            viewer,
+            events,
+            state,
+            knowledge,
+        }
+    }
+
+    /// Move the cursor to a turn, phase, step, game or event
+    ///
+    /// # Returns
+    /// Err(ReplayError::SeekTarget) if the replay has no such point
+    pub fn seek(&mut self, target: &SeekTarget) -> Result<(), ReplayError> {
+        let position = match target {
+            SeekTarget::Start => Some(0),
+            SeekTarget::End => Some(self.events.len()),
+            SeekTarget::Event { seq } => self.events.iter().position(|e| e.seq == *seq).map(|i| i + 1),
+            SeekTarget::Game { game_id } => self
+                .events
+                .iter()
+                .position(|e| matches!(e.kind, GameEventKind::GameStarted { game_id: id, .. } if id == *game_id))
+                .map(|i| i + 1),
+            SeekTarget::Turn { game_id, turn } => self
+                .find_in_game(*game_id, |kind| {
+                    matches!(kind, GameEventKind::TurnStarted { turn: t, .. } if t == turn)
+                })
+                .map(|i| self.landing(i)),
+            SeekTarget::Phase { game_id, turn, phase } => self
+                .find_in_game(*game_id, |kind| {
+                    matches!(kind, GameEventKind::StepChanged { turn: t, phase: p, .. } if t == turn && p == phase)
+                })
+                .map(|i| i + 1),
+            SeekTarget::Step { game_id, turn, step } => self
+                .find_in_game(*game_id, |kind| {
+                    matches!(kind, GameEventKind::StepChanged { turn: t, step: s, .. } if t == turn && s == step)
+                })
+                .map(|i| i + 1),
+        };
+        let position = position.ok_or_else(|| ReplayError::SeekTarget(describe(target)))?;
+        self.seek_position(position);
+        Ok(())
+    }
+
+    /// Step forward or back by events, steps, turns or games
+    ///
+    /// # Arguments
+    /// * `unit` - What to step by
+    /// * `forward` - Direction
+    ///
+    /// # Returns
+    /// false if there was nothing to step to (the cursor stays put)
+    pub fn step(&mut self, unit: StepUnit, forward: bool) -> bool {
+        let boundaries = (0..self.events.len())
+            .filter(|i| self.is_boundary(unit, *i))
+            .map(|i| {
+                if unit == StepUnit::Turn {
+                    self.landing(i)
+                } else {
+                    i + 1
+                }
+            });
+        let target = if forward {
+            boundaries.filter(|p| *p > self.position).min()
+        } else {
+            // Back past the start of the first boundary returns to the start
+            boundaries
+                .filter(|p| *p < self.position)
+                .max()
+                .or((self.position > 0).then_some(0))
+        };
+        match target {
+            Some(position) => {
+                self.seek_position(position);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Apply the next frame's events while playing
+    ///
+    /// Events decoded from the same frame happened at once and are applied
+    /// together. Reaching the end stops playback.
+    pub fn advance(&mut self) {
+        let Some(frame_index) = self.events.get(self.position).map(|e| e.frame_index) else {
+            self.playing = false;
+            return;
+        };
+        let end = self.events[self.position..]
+            .iter()
+            .position(|e| e.frame_index != frame_index)
+            .map_or(self.events.len(), |n| self.position + n);
+        self.seek_position(end);
+        if self.at_end() {
+            self.playing = false;
+        }
+    }
+
+    /// How long to wait before the next `advance` while playing
+    ///
+    /// # Returns
+    /// The recorded time until the next frame, bounded by `MIN_FRAME_DELAY` and
+    /// `MAX_FRAME_DELAY` and divided by the speed; None at the end
+    pub fn next_delay(&self) -> Option<Duration> {
+        let next = self.events.get(self.position)?;
+        let gap = match self.position.checked_sub(1).map(|i| &self.events[i]) {
+            Some(last) => (next.timestamp - last.timestamp)
+                .to_std()
+                .unwrap_or_default(),
+            None => Duration::ZERO,
+        };
+        Some(
+            gap.clamp(MIN_FRAME_DELAY, MAX_FRAME_DELAY)
+                .div_f64(self.speed),
+        )
+    }
+
+    /// Move the cursor to a position, from the closest snapshot or by applying the
+    /// events ahead of the cursor
+    pub fn seek_position(&mut self, position: usize) {
+        let position = position.min(self.events.len());
+        let snapshot = self.snapshots.iter().rev().find(|(at, _)| *at <= position);
+        let start = match snapshot {
+            // Moving forward within the snapshot's interval: keep the board
+            Some((at, _)) if position >= self.position && self.position >= *at => self.position,
+            None if position >= self.position => self.position,
+            Some((at, snapshot)) => {
+                self.state = snapshot.state.clone();
+                *at
+            }
+            None => {
+                self.state = GameState::default();
+                0
+            }
+        };
+        for event in &self.events[start..position] {
+            self.state.apply(&event.kind);
+        }
+        self.applied = if position > self.position {
+            self.position..position
+        } else {
+            position..position
+        };
+        self.position = position;
+    }
+
+    /// First event of a game matching a predicate
+    fn find_in_game(
+        &self,
+        game_id: Option<u32>,
+        matches: impl Fn(&GameEventKind) -> bool,
+    ) -> Option<usize> {
+        let game_id = game_id.or(self.state.game_id);
+        self.events
+            .iter()
+            .position(|e| (game_id.is_none() || e.game_id == game_id) && matches(&e.kind))
+    }
+
+    /// Whether the cursor can stop after event `i` when stepping by `unit`
+    fn is_boundary(&self, unit: StepUnit, i: usize) -> bool {
+        let kind = &self.events[i].kind;
+        match unit {
+            StepUnit::Event => true,
+            StepUnit::Step => matches!(kind, GameEventKind::StepChanged { .. }),
+            StepUnit::Turn => matches!(kind, GameEventKind::TurnStarted { .. }),
+            StepUnit::Game => matches!(kind, GameEventKind::GameStarted { .. }),
+        }
+    }
+
+    /// Cursor position showing event `i`: a turn start is shown with the step it
+    /// began in, which the engine reports right after it
+    fn landing(&self, i: usize) -> usize {
+        match (
+            &self.events[i].kind,
+            self.events.get(i + 1).map(|e| &e.kind),
+        ) {
+            (
+                GameEventKind::TurnStarted { turn, .. },
+                Some(GameEventKind::StepChanged { turn: next, .. }),
+            ) if turn == next => i + 2,
+            _ => i + 1,
+        }
+    }
+}
+
+fn describe(target: &SeekTarget) -> String {
+    let game = |game_id: &Option<u32>| {
+        game_id
+            .map(|id| format!(" of game {}", id))
+            .unwrap_or_default()
+    };
+    match target {
+        SeekTarget::Start | SeekTarget::End => "events".to_string(),
+        SeekTarget::Event { seq } => format!("event {}", seq),
+        SeekTarget::Game { game_id } => format!("game {}", game_id),
+        SeekTarget::Turn { game_id, turn } => format!("turn {}{}", turn, game(game_id)),
+        SeekTarget::Phase {
+            game_id,
+            turn,
+            phase,
+        } => format!("{:?} phase in turn {}{}", phase, turn, game(game_id)),
+        SeekTarget::Step {
+            game_id,
+            turn,
+            step,
+        } => format!("{:?} step in turn {}{}", step, turn, game(game_id)),
+    }
+}
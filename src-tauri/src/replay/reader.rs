@@ -0,0 +1,327 @@
+use crate::common::error::ReplayError;
+use crate::game::event::{GameEvent, GameSnapshot};
+use crate::game::lifecycle::Match;
+use crate::protocol::frame::Frame;
+use crate::replay::format::{
+    self, ChunkKind, IndexEntry, ReplayHeader, ReplaySignature, StoredEvent, StoredFrames,
+    FOOTER_LEN, FOOTER_MAGIC, FORMAT_MAJOR, MAGIC, MAX_HEADER_LEN, PREAMBLE_LEN,
+};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::warn;
+
+/// One decoded chunk
+#[derive(Debug, Clone, PartialEq)]
+pub enum Chunk {
+    Events(Vec<GameEvent>),
+    Snapshot(Box<GameSnapshot>),
+    Match(Box<Match>),
+    Frames(Vec<Frame>),
+    Signature(Box<ReplaySignature>),
+    /// A chunk kind written by a newer build, left undecoded
+    Unknown {
+        kind: u16,
+        payload: Vec<u8>,
+    },
+}
+
+/// State needed to show the replay at one event
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeekPoint {
+    /// Latest snapshot at or before the target, None if the target precedes every snapshot
+    pub snapshot: Option<GameSnapshot>,
+    /// Events after the snapshot up to and including the target, in order
+    pub events: Vec<GameEvent>,
+}
+
+/// Reads a replay file written by `ReplayWriter`
+///
+/// The index at the end of the file is used when present. A replay without one
+/// (capture interrupted before it was finished) is indexed by walking its chunks,
+/// stopping at the first damaged one.
+pub struct ReplayReader<R: Read + Seek> {
+    inner: R,
+    version: (u16, u16),
+    header: ReplayHeader,
+    index: Vec<IndexEntry>,
+    complete: bool,
+}
+
+impl ReplayReader<BufReader<File>> {
+    /// Open a replay file
+    pub fn open(path: &Path) -> Result<Self, ReplayError> {
+        Self::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read + Seek> ReplayReader<R> {
+    /// Read the header and index of a replay
+    ///
+    /// # Returns
+    /// Err(ReplayError) if the file is not a replay, was written by a newer
+    /// incompatible format version, or uses a required chunk kind this build
+    /// does not understand
+    pub fn new(mut inner: R) -> Result<Self, ReplayError> {
+        let (version, header, data_start) = read_preamble(&mut inner)?;
+
+        let (index, complete) = match read_index(&mut inner, data_start)? {
+            Some(index) => (index, true),
+            None => {
+                let scan = format::scan_chunks(&mut inner, data_start)?;
+                if scan.truncated {
+                    warn!(
+                        "Replay is damaged after byte {}; reading the intact part",
+                        scan.valid_len
+                    );
+                }
+                (scan.entries, false)
+            }
+        };
+
+        if let Some(entry) = index
+            .iter()
+            .find(|e| e.chunk_kind().is_none() && e.is_required())
+        {
+            return Err(ReplayError::UnknownRequiredChunk(entry.kind));
+        }
+
+        Ok(Self {
+            inner,
+            version,
+            header,
+            index,
+            complete,
+        })
+    }
+
+    pub fn header(&self) -> &ReplayHeader {
+        &self.header
+    }
+
+    /// Format (major, minor) version the file was written with
+    pub fn format_version(&self) -> (u16, u16) {
+        self.version
+    }
+
+    /// Every chunk of the replay, in file order
+    pub fn index(&self) -> &[IndexEntry] {
+        &self.index
+    }
+
+    /// False if the replay has no index (it was not finished) and was indexed by scanning
+    pub fn is_complete(&self) -> bool {
+        self.complete
+    }
+
+    /// Read and decode one chunk
+    pub fn read_chunk(&mut self, entry: &IndexEntry) -> Result<Chunk, ReplayError> {
+        let raw = format::read_chunk_at(&mut self.inner, entry.offset)?.ok_or_else(|| {
+            ReplayError::Corrupt(format!("file ends inside chunk at offset {}", entry.offset))
+        })?;
+        if raw.header.kind != entry.kind {
+            return Err(ReplayError::Corrupt(format!(
+                "index points at a {:#06x} chunk where a {:#06x} chunk was expected",
+                raw.header.kind, entry.kind
+            )));
+        }
+
+        Ok(match entry.chunk_kind() {
+            Some(ChunkKind::Events) => {
+                let stored: Vec<StoredEvent> = format::decode(&raw.payload, "events chunk")?;
+                Chunk::Events(stored.into_iter().map(GameEvent::from).collect())
+            }
+            Some(ChunkKind::Snapshot) => {
+                Chunk::Snapshot(Box::new(format::decode(&raw.payload, "snapshot chunk")?))
+            }
+            Some(ChunkKind::Match) => {
+                Chunk::Match(Box::new(format::decode(&raw.payload, "match chunk")?))
+            }
+            Some(ChunkKind::Frames) => {
+                let stored: StoredFrames = format::decode(&raw.payload, "frames chunk")?;
+                Chunk::Frames(stored.into_frames()?)
+            }
+            Some(ChunkKind::Signature) => {
+                Chunk::Signature(Box::new(format::decode(&raw.payload, "signature chunk")?))
+            }
+            Some(ChunkKind::Index) | None => Chunk::Unknown {
+                kind: entry.kind,
+                payload: raw.payload,
+            },
+        })
+    }
+
+    /// Every event of the replay, in order
+    pub fn events(&mut self) -> Result<Vec<GameEvent>, ReplayError> {
+        self.events_in(0, u64::MAX)
+    }
+
+    /// Every snapshot of the replay, in order
+    pub fn snapshots(&mut self) -> Result<Vec<GameSnapshot>, ReplayError> {
+        let entries = self.entries_of(ChunkKind::Snapshot);
+        let mut snapshots = Vec::with_capacity(entries.len());
+        for entry in entries {
+            if let Chunk::Snapshot(snapshot) = self.read_chunk(&entry)? {
+                snapshots.push(*snapshot);
+            }
+        }
+        Ok(snapshots)
+    }
+
+    /// The match record, if the replay was finished with one
+    pub fn match_record(&mut self) -> Result<Option<Match>, ReplayError> {
+        let Some(entry) = self.entries_of(ChunkKind::Match).pop() else {
+            return Ok(None);
+        };
+        match self.read_chunk(&entry)? {
+            Chunk::Match(record) => Ok(Some(*record)),
+            _ => Ok(None),
+        }
+    }
+
+    /// Raw frames recorded with the replay, in capture order; empty if none were recorded
+    pub fn frames(&mut self) -> Result<Vec<Frame>, ReplayError> {
+        let mut frames = Vec::new();
+        for entry in self.entries_of(ChunkKind::Frames) {
+            if let Chunk::Frames(chunk) = self.read_chunk(&entry)? {
+                frames.extend(chunk);
+            }
+        }
+        Ok(frames)
+    }
+
+    /// State needed to show the replay as of event `seq`
+    ///
+    /// Only the closest preceding snapshot and the events after it are read.
+    pub fn seek(&mut self, seq: u64) -> Result<SeekPoint, ReplayError> {
+        let snapshot_entry = self
+            .entries_of(ChunkKind::Snapshot)
+            .into_iter()
+            .rev()
+            .find(|e| e.first_seq <= seq);
+        let snapshot = match snapshot_entry {
+            Some(entry) => match self.read_chunk(&entry)? {
+                Chunk::Snapshot(snapshot) => Some(*snapshot),
+                _ => None,
+            },
+            None => None,
+        };
+
+        let from = snapshot.as_ref().map(|s| s.seq + 1).unwrap_or(0);
+        let events = if from <= seq {
+            self.events_in(from, seq)?
+        } else {
+            Vec::new()
+        };
+        Ok(SeekPoint { snapshot, events })
+    }
+
+    fn entries_of(&self, kind: ChunkKind) -> Vec<IndexEntry> {
+        self.index
+            .iter()
+            .filter(|e| e.kind == kind.code())
+            .copied()
+            .collect()
+    }
+
+    /// Events with `from <= seq <= to`
+    fn events_in(&mut self, from: u64, to: u64) -> Result<Vec<GameEvent>, ReplayError> {
+        let entries: Vec<IndexEntry> = self
+            .entries_of(ChunkKind::Events)
+            .into_iter()
+            .filter(|e| e.last_seq >= from && e.first_seq <= to)
+            .collect();
+        let mut events = Vec::new();
+        for entry in entries {
+            if let Chunk::Events(chunk) = self.read_chunk(&entry)? {
+                events.extend(chunk.into_iter().filter(|e| e.seq >= from && e.seq <= to));
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Read the preamble and header
+///
+/// # Returns
+/// Format (major, minor) version, the header, and the offset of the first chunk
+pub(crate) fn read_preamble<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<((u16, u16), ReplayHeader, u64), ReplayError> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut preamble = [0u8; PREAMBLE_LEN];
+    if !format::read_full(reader, &mut preamble)? || preamble[0..8] != MAGIC {
+        return Err(ReplayError::BadMagic);
+    }
+    let major = u16::from_le_bytes([preamble[8], preamble[9]]);
+    let minor = u16::from_le_bytes([preamble[10], preamble[11]]);
+    if major > FORMAT_MAJOR {
+        return Err(ReplayError::UnsupportedVersion { major, minor });
+    }
+    let header_len = u32::from_le_bytes([preamble[12], preamble[13], preamble[14], preamble[15]]);
+    let header_crc = u32::from_le_bytes([preamble[16], preamble[17], preamble[18], preamble[19]]);
+    if header_len > MAX_HEADER_LEN {
+        return Err(ReplayError::Corrupt(format!(
+            "header claims {} bytes",
+            header_len
+        )));
+    }
+
+    let mut header_bytes = Vec::new();
+    if !format::read_up_to(reader, header_len, &mut header_bytes)? {
+        return Err(ReplayError::Corrupt(
+            "file ends inside the header".to_string(),
+        ));
+    }
+    if crc32fast::hash(&header_bytes) != header_crc {
+        return Err(ReplayError::ChecksumMismatch(PREAMBLE_LEN as u64));
+    }
+    let header: ReplayHeader = format::decode(&header_bytes, "header")?;
+    Ok((
+        (major, minor),
+        header,
+        (PREAMBLE_LEN + header_bytes.len()) as u64,
+    ))
+}
+
+/// Read the index through the footer
+///
+/// # Returns
+/// Ok(None) if the file has no valid footer or index
+pub(crate) fn read_index<R: Read + Seek>(
+    reader: &mut R,
+    data_start: u64,
+) -> Result<Option<Vec<IndexEntry>>, ReplayError> {
+    let file_len = reader.seek(SeekFrom::End(0))?;
+    if file_len < data_start + FOOTER_LEN as u64 {
+        return Ok(None);
+    }
+    reader.seek(SeekFrom::Start(file_len - FOOTER_LEN as u64))?;
+    let mut footer = [0u8; FOOTER_LEN];
+    reader.read_exact(&mut footer)?;
+    if footer[8..] != FOOTER_MAGIC {
+        return Ok(None);
+    }
+    let mut offset = [0u8; 8];
+    offset.copy_from_slice(&footer[..8]);
+    let index_offset = u64::from_le_bytes(offset);
+    if index_offset < data_start || index_offset >= file_len {
+        return Ok(None);
+    }
+
+    let raw = match format::read_chunk_at(reader, index_offset) {
+        Ok(Some(raw)) if raw.header.kind == ChunkKind::Index.code() => raw,
+        Ok(_) | Err(ReplayError::ChecksumMismatch(_)) | Err(ReplayError::Corrupt(_)) => {
+            warn!("Replay index is damaged; rebuilding it from the chunks");
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    match format::decode(&raw.payload, "index") {
+        Ok(index) => Ok(Some(index)),
+        Err(e) => {
+            warn!("{}; rebuilding the replay index from the chunks", e);
+            Ok(None)
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::common::error::ReplayError;
+use crate::replay::format::{self, ChunkKind, IndexEntry, REPLAY_EXTENSION};
+use crate::replay::reader;
+use crate::replay::stream::PARTIAL_SUFFIX;
+use crate::replay::writer::ReplayWriter;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Outcome of finalizing one interrupted replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecoveryReport {
+    /// Path of the finalized replay
+    pub path: PathBuf,
+    /// Intact chunks kept
+    pub chunks: usize,
+    /// Sequence number of the last event kept, None if no events survived
+    pub last_seq: Option<u64>,
+    /// Bytes of damaged or incomplete data cut from the end
+    pub discarded_bytes: u64,
+}
+
+/// Finalize a replay left unfinished by a crash
+///
+/// The file is truncated after its last intact chunk, an index and footer are
+/// appended, and the `.partial` suffix is dropped so the replay can be opened
+/// like any other.
+///
+/// # Arguments
+/// * `path` - A `.partial` replay file
+///
+/// # Returns
+/// What was kept and discarded
+/// Err(ReplayError) if the header itself is damaged (nothing can be recovered)
+pub fn recover(path: &Path) -> Result<RecoveryReport, ReplayError> {
+    let final_path = finished_path(path)?;
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    let (_, _, data_start) = reader::read_preamble(&mut file)?;
+
+    // Crashed between writing the footer and renaming: nothing to repair
+    if let Some(index) = reader::read_index(&mut file, data_start)? {
+        drop(file);
+        std::fs::rename(path, &final_path)?;
+        return Ok(RecoveryReport {
+            path: final_path,
+            chunks: index.len(),
+            last_seq: last_event_seq(&index),
+            discarded_bytes: 0,
+        });
+    }
+
+    let scan = format::scan_chunks(&mut file, data_start)?;
+    let file_len = file.seek(SeekFrom::End(0))?;
+    file.set_len(scan.valid_len)?;
+    file.seek(SeekFrom::Start(scan.valid_len))?;
+
+    let report = RecoveryReport {
+        path: final_path.clone(),
+        chunks: scan.entries.len(),
+        last_seq: last_event_seq(&scan.entries),
+        discarded_bytes: file_len - scan.valid_len,
+    };
+
+    let file = ReplayWriter::resume(BufWriter::new(file), scan.valid_len, scan.entries)
+        .finish()?
+        .into_inner()
+        .map_err(|e| ReplayError::Io(e.into_error()))?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(path, &final_path)?;
+    Ok(report)
+}
+
+/// Finalize every interrupted replay in a directory
+///
+/// Run at startup, before any capture writes new replays. Files whose header is
+/// damaged are left in place and logged.
+pub fn recover_all(dir: &Path) -> Result<Vec<RecoveryReport>, ReplayError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut reports = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_partial = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .is_some_and(|n| n.ends_with(&format!(".{}{}", REPLAY_EXTENSION, PARTIAL_SUFFIX)));
+        if !is_partial {
+            continue;
+        }
+        match recover(&path) {
+            Ok(report) => {
+                info!(
+                    "Recovered interrupted replay {} ({} chunks, {} bytes discarded)",
+                    report.path.display(),
+                    report.chunks,
+                    report.discarded_bytes
+                );
+                reports.push(report);
+            }
+            Err(e) => warn!("Cannot recover replay {}: {}", path.display(), e),
+        }
+    }
+    Ok(reports)
+}
+
+/// Path of a `.partial` replay once it is finished
+fn finished_path(path: &Path) -> Result<PathBuf, ReplayError> {
+    path.to_str()
+        .and_then(|p| p.strip_suffix(PARTIAL_SUFFIX))
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            ReplayError::Corrupt(format!("{} is not an unfinished replay", path.display()))
+        })
+}
+
+fn last_event_seq(index: &[IndexEntry]) -> Option<u64> {
+    index
+        .iter()
+        .filter(|e| e.chunk_kind() == Some(ChunkKind::Events))
+        .map(|e| e.last_seq)
+        .max()
+}
@@ -0,0 +1,495 @@
+use crate::common::error::ReplayError;
+use crate::common::hex;
+use crate::replay::format::{
+    self, ChunkDigest, ChunkKind, IndexEntry, ReplaySignature, SignedManifest, FOOTER_LEN,
+    PREAMBLE_LEN,
+};
+use crate::replay::reader::{Chunk, ReplayReader};
+use crate::replay::writer::ReplayWriter;
+use ed25519_dalek::{Signature, Signer, SigningKey, Verifier, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use serde_bytes::ByteBuf;
+use sha2::{Digest, Sha256};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Read, Seek, SeekFrom};
+use std::path::Path;
+use tracing::info;
+
+/// Suffix of the copy a replay is signed in before it replaces the original
+const SIGNING_SUFFIX: &str = ".signing";
+
+/// Ed25519 key pair replays are signed with, generated locally on first use
+pub struct SigningIdentity {
+    key: SigningKey,
+}
+
+impl SigningIdentity {
+    /// Fresh key pair
+    pub fn generate() -> Result<Self, ReplayError> {
+        let mut secret = [0u8; 32];
+        getrandom::getrandom(&mut secret).map_err(|e| ReplayError::InvalidKey(e.to_string()))?;
+        Ok(Self {
+            key: SigningKey::from_bytes(&secret),
+        })
+    }
+
+    /// Load the key pair from a hex file holding the secret key
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        let text = std::fs::read_to_string(path)?;
+        let secret = hex::decode(text.trim())
+            .ok()
+            .and_then(|bytes| <[u8; 32]>::try_from(bytes).ok())
+            .ok_or_else(|| {
+                ReplayError::InvalidKey(format!("{} does not hold a signing key", path.display()))
+            })?;
+        Ok(Self {
+            key: SigningKey::from_bytes(&secret),
+        })
+    }
+
+    /// Load the key pair, generating and saving one if the file does not exist
+    pub fn load_or_create(path: &Path) -> Result<Self, ReplayError> {
+        if path.exists() {
+            return Self::load(path);
+        }
+        let identity = Self::generate()?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, hex::encode(identity.key.as_bytes()))?;
+        info!("Generated replay signing key {}", identity.fingerprint());
+        Ok(identity)
+    }
+
+    pub fn public_key(&self) -> [u8; 32] {
+        self.key.verifying_key().to_bytes()
+    }
+
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.public_key())
+    }
+
+    /// Public half of the key pair, for sharing with teammates
+    pub fn signer(&self) -> PublicKeyInfo {
+        PublicKeyInfo::new(&self.public_key())
+    }
+}
+
+/// A public key as shown to the user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PublicKeyInfo {
+    /// Hex-encoded Ed25519 public key
+    pub public_key: String,
+    pub fingerprint: String,
+}
+
+impl PublicKeyInfo {
+    fn new(public_key: &[u8]) -> Self {
+        Self {
+            public_key: hex::encode(public_key),
+            fingerprint: fingerprint(public_key),
+        }
+    }
+}
+
+/// Short form of a public key for comparing by eye, e.g. `3fa2:c19b:07d4:e810`
+///
+/// The first 8 bytes of the SHA-256 of the key, in groups of two bytes.
+pub fn fingerprint(public_key: &[u8]) -> String {
+    let digest = Sha256::digest(public_key);
+    digest[..8]
+        .chunks(2)
+        .map(hex::encode)
+        .collect::<Vec<_>>()
+        .join(":")
+}
+
+/// Public key of a teammate whose signatures are trusted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrustedKey {
+    pub name: String,
+    #[serde(with = "crate::common::hex")]
+    pub public_key: Vec<u8>,
+    pub fingerprint: String,
+    pub added_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Trusted public keys, stored as a JSON file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Keyring {
+    pub keys: Vec<TrustedKey>,
+}
+
+impl Keyring {
+    /// Read the keyring; a missing file is an empty keyring
+    pub fn load(path: &Path) -> Result<Self, ReplayError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let json = std::fs::read_to_string(path)?;
+        serde_json::from_str(&json)
+            .map_err(|e| ReplayError::InvalidKey(format!("{}: {}", path.display(), e)))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ReplayError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let json =
+            serde_json::to_string_pretty(self).map_err(|e| ReplayError::Encode(e.to_string()))?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Trust a public key under a name, renaming it if already trusted
+    ///
+    /// # Arguments
+    /// * `name` - Who the key belongs to
+    /// * `public_key` - Hex-encoded Ed25519 public key, as shown by `SigningIdentity::signer`
+    pub fn trust(&mut self, name: &str, public_key: &str) -> Result<TrustedKey, ReplayError> {
+        let bytes = hex::decode(public_key.trim()).map_err(ReplayError::InvalidKey)?;
+        let key = <[u8; 32]>::try_from(bytes.as_slice())
+            .ok()
+            .and_then(|b| VerifyingKey::from_bytes(&b).ok())
+            .ok_or_else(|| ReplayError::InvalidKey("not an Ed25519 public key".to_string()))?;
+
+        let trusted = TrustedKey {
+            name: name.trim().to_string(),
+            public_key: key.to_bytes().to_vec(),
+            fingerprint: fingerprint(key.as_bytes()),
+            added_at: chrono::Utc::now(),
+        };
+        self.keys.retain(|k| k.public_key != trusted.public_key);
+        self.keys.push(trusted.clone());
+        Ok(trusted)
+    }
+
+    /// Stop trusting the key with this fingerprint
+    ///
+    /// # Returns
+    /// False if no such key was trusted
+    pub fn remove(&mut self, fingerprint: &str) -> bool {
+        let before = self.keys.len();
+        self.keys.retain(|k| k.fingerprint != fingerprint);
+        self.keys.len() != before
+    }
+
+    pub fn find(&self, public_key: &[u8]) -> Option<&TrustedKey> {
+        self.keys.iter().find(|k| k.public_key == public_key)
+    }
+}
+
+/// Outcome of signing a replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignatureInfo {
+    pub signer: PublicKeyInfo,
+    pub signed_at: chrono::DateTime<chrono::Utc>,
+    /// Chunks covered by the signature
+    pub chunks: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SignatureStatus {
+    /// The replay carries no signature
+    Unsigned,
+    /// Signature is valid and nothing changed since signing
+    Valid,
+    /// Signature is valid but the header or some chunks changed since signing
+    Modified,
+    /// The signature itself is damaged or does not match its manifest
+    Invalid,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChunkChangeKind {
+    /// Contents differ from what was signed (or the chunk can no longer be read)
+    Modified,
+    /// Chunk was not present when the replay was signed
+    Added,
+    /// Signed chunk is missing
+    Removed,
+}
+
+/// A chunk that differs from the signed replay
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkChange {
+    pub kind: u16,
+    /// None for chunk kinds this build does not know
+    pub chunk_kind: Option<ChunkKind>,
+    pub first_seq: u64,
+    pub last_seq: u64,
+    pub change: ChunkChangeKind,
+}
+
+/// Result of checking a replay's signature
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyReport {
+    pub status: SignatureStatus,
+    /// Key the replay claims to be signed with
+    pub signer: Option<PublicKeyInfo>,
+    /// Keyring name of the signer, if trusted
+    pub trusted_as: Option<String>,
+    /// Signed with this installation's own key
+    pub own_key: bool,
+    pub signed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub header_modified: bool,
+    pub modified_chunks: Vec<ChunkChange>,
+}
+
+impl VerifyReport {
+    fn unsigned() -> Self {
+        Self {
+            status: SignatureStatus::Unsigned,
+            signer: None,
+            trusted_as: None,
+            own_key: false,
+            signed_at: None,
+            header_modified: false,
+            modified_chunks: Vec::new(),
+        }
+    }
+}
+
+/// Sign a finished replay in place
+///
+/// The signature covers the header and every chunk and is stored in a signature
+/// chunk just before the index, replacing any previous signature. Readers that
+/// predate signing skip it.
+///
+/// # Returns
+/// Who signed and how many chunks are covered
+pub fn sign_file(path: &Path, identity: &SigningIdentity) -> Result<SignatureInfo, ReplayError> {
+    let reader = ReplayReader::open(path)?;
+    if !reader.is_complete() {
+        return Err(ReplayError::Signing(
+            "the replay was not finished".to_string(),
+        ));
+    }
+    let mut entries = reader.index().to_vec();
+    drop(reader);
+
+    let mut file = File::open(path)?;
+    // Chunks written after the previous signature (only the index) are rewritten
+    let mut end = index_offset(&mut file)?;
+    if entries.last().and_then(IndexEntry::chunk_kind) == Some(ChunkKind::Signature) {
+        end = entries.pop().map(|e| e.offset).unwrap_or(end);
+    }
+    entries.retain(|e| e.chunk_kind() != Some(ChunkKind::Signature));
+
+    let mut chunks = Vec::with_capacity(entries.len());
+    for entry in &entries {
+        chunks.push(ChunkDigest {
+            kind: entry.kind,
+            first_seq: entry.first_seq,
+            last_seq: entry.last_seq,
+            sha256: ByteBuf::from(chunk_sha256(&mut file, entry)?),
+        });
+    }
+    let manifest = SignedManifest {
+        public_key: ByteBuf::from(identity.public_key().to_vec()),
+        signed_at: chrono::Utc::now(),
+        header_sha256: ByteBuf::from(header_sha256(&mut file)?),
+        chunks,
+    };
+    drop(file);
+    let manifest_bytes = format::encode(&manifest)?;
+    let signature = ReplaySignature {
+        signature: ByteBuf::from(identity.key.sign(&manifest_bytes).to_bytes().to_vec()),
+        manifest: ByteBuf::from(manifest_bytes),
+    };
+
+    let tmp = format::sibling(path, SIGNING_SUFFIX);
+    let result = write_signed(path, &tmp, end, entries, &signature);
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result?;
+    std::fs::rename(&tmp, path)?;
+
+    info!(
+        "Signed replay {} with key {}",
+        path.display(),
+        identity.fingerprint()
+    );
+    Ok(SignatureInfo {
+        signer: identity.signer(),
+        signed_at: manifest.signed_at,
+        chunks: manifest.chunks.len(),
+    })
+}
+
+/// Check a replay's signature against its current contents
+///
+/// # Arguments
+/// * `path` - Replay to check
+/// * `keyring` - Trusted keys, to name the signer
+/// * `own_key` - Public key of this installation, if it has one
+///
+/// # Returns
+/// The signature status, the signer, and every chunk that changed since signing
+pub fn verify_file(
+    path: &Path,
+    keyring: &Keyring,
+    own_key: Option<&[u8]>,
+) -> Result<VerifyReport, ReplayError> {
+    let mut reader = ReplayReader::open(path)?;
+    let entries = reader.index().to_vec();
+    let Some(signature_entry) = entries
+        .iter()
+        .rev()
+        .find(|e| e.chunk_kind() == Some(ChunkKind::Signature))
+        .copied()
+    else {
+        return Ok(VerifyReport::unsigned());
+    };
+
+    let mut report = VerifyReport {
+        status: SignatureStatus::Invalid,
+        ..VerifyReport::unsigned()
+    };
+    let signature = match reader.read_chunk(&signature_entry) {
+        Ok(Chunk::Signature(signature)) => signature,
+        _ => return Ok(report),
+    };
+    drop(reader);
+    let Ok(manifest) = format::decode::<SignedManifest>(&signature.manifest, "signature manifest")
+    else {
+        return Ok(report);
+    };
+    report.signer = Some(PublicKeyInfo::new(&manifest.public_key));
+    report.trusted_as = keyring.find(&manifest.public_key).map(|k| k.name.clone());
+    report.own_key = own_key == Some(manifest.public_key.as_slice());
+    report.signed_at = Some(manifest.signed_at);
+    if !signature_matches(
+        &manifest.public_key,
+        &signature.manifest,
+        &signature.signature,
+    ) {
+        return Ok(report);
+    }
+
+    let mut file = File::open(path)?;
+    report.header_modified = header_sha256(&mut file)? != manifest.header_sha256.as_slice();
+
+    // Pair current chunks with signed ones by kind and sequence range, in file order
+    let mut signed: Vec<Option<&ChunkDigest>> = manifest.chunks.iter().map(Some).collect();
+    for entry in entries
+        .iter()
+        .filter(|e| e.chunk_kind() != Some(ChunkKind::Signature))
+    {
+        let matching = signed.iter_mut().find(|d| {
+            d.is_some_and(|d| {
+                (d.kind, d.first_seq, d.last_seq) == (entry.kind, entry.first_seq, entry.last_seq)
+            })
+        });
+        let change = match matching.and_then(Option::take) {
+            None => Some(ChunkChangeKind::Added),
+            Some(digest) => match chunk_sha256(&mut file, entry) {
+                Ok(hash) if hash == digest.sha256.as_slice() => None,
+                _ => Some(ChunkChangeKind::Modified),
+            },
+        };
+        if let Some(change) = change {
+            report.modified_chunks.push(ChunkChange {
+                kind: entry.kind,
+                chunk_kind: entry.chunk_kind(),
+                first_seq: entry.first_seq,
+                last_seq: entry.last_seq,
+                change,
+            });
+        }
+    }
+    for digest in signed.into_iter().flatten() {
+        report.modified_chunks.push(ChunkChange {
+            kind: digest.kind,
+            chunk_kind: ChunkKind::from_code(digest.kind),
+            first_seq: digest.first_seq,
+            last_seq: digest.last_seq,
+            change: ChunkChangeKind::Removed,
+        });
+    }
+
+    report.status = if report.header_modified || !report.modified_chunks.is_empty() {
+        SignatureStatus::Modified
+    } else {
+        SignatureStatus::Valid
+    };
+    Ok(report)
+}
+
+fn signature_matches(public_key: &[u8], message: &[u8], signature: &[u8]) -> bool {
+    let Some(key) = <[u8; 32]>::try_from(public_key)
+        .ok()
+        .and_then(|b| VerifyingKey::from_bytes(&b).ok())
+    else {
+        return false;
+    };
+    let Ok(signature) = Signature::from_slice(signature) else {
+        return false;
+    };
+    key.verify(message, &signature).is_ok()
+}
+
+/// SHA-256 of the header bytes as stored after the preamble
+fn header_sha256(file: &mut File) -> Result<Vec<u8>, ReplayError> {
+    file.seek(SeekFrom::Start(0))?;
+    let mut preamble = [0u8; PREAMBLE_LEN];
+    file.read_exact(&mut preamble)?;
+    let header_len = u32::from_le_bytes([preamble[12], preamble[13], preamble[14], preamble[15]]);
+    let mut header = Vec::new();
+    if !format::read_up_to(file, header_len, &mut header)? {
+        return Err(ReplayError::Corrupt(
+            "file ends inside the header".to_string(),
+        ));
+    }
+    Ok(Sha256::digest(&header).to_vec())
+}
+
+/// SHA-256 of a chunk's decompressed payload
+fn chunk_sha256(file: &mut File, entry: &IndexEntry) -> Result<Vec<u8>, ReplayError> {
+    let raw = format::read_chunk_at(file, entry.offset)?.ok_or_else(|| {
+        ReplayError::Corrupt(format!("file ends inside chunk at offset {}", entry.offset))
+    })?;
+    Ok(Sha256::digest(&raw.payload).to_vec())
+}
+
+/// Offset of the index chunk, from the footer of a finished replay
+fn index_offset(file: &mut File) -> Result<u64, ReplayError> {
+    file.seek(SeekFrom::End(-(FOOTER_LEN as i64)))?;
+    let mut offset = [0u8; 8];
+    file.read_exact(&mut offset)?;
+    Ok(u64::from_le_bytes(offset))
+}
+
+/// Copy the replay up to `end` and append the signature, index and footer
+fn write_signed(
+    path: &Path,
+    tmp: &Path,
+    end: u64,
+    entries: Vec<IndexEntry>,
+    signature: &ReplaySignature,
+) -> Result<(), ReplayError> {
+    std::fs::copy(path, tmp)?;
+    let mut file = OpenOptions::new().write(true).open(tmp)?;
+    file.set_len(end)?;
+    file.seek(SeekFrom::Start(end))?;
+
+    let mut writer = ReplayWriter::resume(BufWriter::new(file), end, entries);
+    writer.write_chunk(
+        ChunkKind::Signature.code(),
+        0,
+        0,
+        0,
+        &format::encode(signature)?,
+    )?;
+    let file = writer
+        .finish()?
+        .into_inner()
+        .map_err(|e| ReplayError::Io(e.into_error()))?;
+    file.sync_all()?;
+    Ok(())
+}
@@ -0,0 +1,203 @@
+use crate::capture::pipeline::GameSink;
+use crate::common::error::ReplayError;
+use crate::game::event::{GameEvent, GameUpdate};
+use crate::game::lifecycle::{LifecycleEvent, Match, MatchTracker};
+use crate::game::model::GameState;
+use crate::protocol::frame::Frame;
+use crate::protocol::version::ClientVersion;
+use crate::replay::format::{ReplayHeader, ReplayMatch};
+use crate::replay::stream::{StreamConfig, StreamingReplayWriter};
+use std::path::PathBuf;
+use tracing::{error, info};
+
+/// Writes one replay file per match from the live capture (PERF-001)
+///
+/// A replay is opened when a match (or a game seen without its match start)
+/// begins and finished when the match ends or capture stops. If writing fails the
+/// rest of that match is not recorded; what reached the disk is recovered on the
+/// next launch.
+///
+/// With `StreamConfig::embed_frames` the replay also keeps every frame captured
+/// while it is open, plus the frame that opened it and the client handshake, so
+/// the match can be decoded again on its own.
+pub struct ReplaySink {
+    dir: PathBuf,
+    session_id: String,
+    config: StreamConfig,
+    tracker: MatchTracker,
+    client_version: Option<ClientVersion>,
+    schema_version: Option<ClientVersion>,
+    current: Option<StreamingReplayWriter>,
+    /// Most recent frame, written first when it turns out to open a replay
+    last_frame: Option<Frame>,
+    /// Frame that announced the client version, which decoding the replay needs
+    handshake: Option<Frame>,
+    /// Record id of a match whose replay failed, so it is not reopened on every event
+    failed: Option<String>,
+}
+
+impl ReplaySink {
+    pub fn new(dir: PathBuf, session_id: String, config: StreamConfig) -> Self {
+        Self {
+            dir,
+            tracker: MatchTracker::new(Some(session_id.clone())),
+            session_id,
+            config,
+            client_version: None,
+            schema_version: None,
+            current: None,
+            last_frame: None,
+            handshake: None,
+            failed: None,
+        }
+    }
+
+    fn open(&mut self, record: &Match) {
+        let record_id = record.record_id();
+        if self.failed.as_deref() == Some(record_id.as_str()) {
+            return;
+        }
+
+        let header = ReplayHeader {
+            client_version: self.client_version.clone(),
+            schema_version: self.schema_version.clone(),
+            session_id: Some(self.session_id.clone()),
+            match_info: Some(ReplayMatch {
+                match_id: record.id,
+                format: record.format.clone(),
+                event_type: record.event_name.clone(),
+                best_of: record.best_of,
+                local_player: record.local_player.clone().unwrap_or_default(),
+                opponent: record.opponent.clone().unwrap_or_default(),
+            }),
+            ..ReplayHeader::new(record.started_at)
+        };
+        match StreamingReplayWriter::create(&self.dir, &record_id, &header, self.config) {
+            Ok(writer) => {
+                info!("Recording replay {}", writer.partial_path().display());
+                self.current = Some(writer);
+            }
+            Err(e) => {
+                error!("Failed to create replay for match {}: {}", record_id, e);
+                self.failed = Some(record_id);
+            }
+        }
+    }
+
+    /// Write the frames captured before the replay opened that decoding it needs
+    fn write_opening_frames(&mut self) {
+        let mut opening: Vec<Frame> = self
+            .handshake
+            .iter()
+            .chain(&self.last_frame)
+            .cloned()
+            .collect();
+        opening.dedup_by_key(|f| f.index);
+        for frame in &opening {
+            self.write(|writer| writer.write_frame(frame));
+        }
+    }
+
+    fn close(&mut self, record: Option<&Match>) {
+        let Some(writer) = self.current.take() else {
+            return;
+        };
+        match writer.finish(record) {
+            Ok(path) => info!("Replay saved to {}", path.display()),
+            Err(e) => error!("Failed to finish replay: {}", e),
+        }
+    }
+
+    /// Run a write against the open replay, abandoning the replay if it fails
+    fn write(&mut self, f: impl FnOnce(&mut StreamingReplayWriter) -> Result<(), ReplayError>) {
+        let Some(writer) = self.current.as_mut() else {
+            return;
+        };
+        if let Err(e) = f(writer) {
+            error!(
+                "Replay {} stopped after a write error: {}",
+                writer.partial_path().display(),
+                e
+            );
+            self.failed = self.tracker.current().map(|m| m.record_id());
+            self.current = None;
+        }
+    }
+
+    fn on_event(&mut self, event: &GameEvent, state: &GameState) {
+        let mut ended = None;
+        let mut started = false;
+        for lifecycle in self.tracker.apply(event, state) {
+            match lifecycle {
+                LifecycleEvent::MatchEnded { record } => ended = Some(record),
+                LifecycleEvent::MatchStarted { .. } => started = true,
+                _ => {}
+            }
+        }
+
+        // A match start that supersedes an open match belongs to the new replay
+        if started {
+            if let Some(record) = ended.take() {
+                self.close(Some(&record));
+            }
+        }
+        if self.current.is_none() {
+            if let Some(record) = self.tracker.current().filter(|m| !m.is_closed()).cloned() {
+                self.open(&record);
+                if self.config.embed_frames {
+                    self.write_opening_frames();
+                }
+            }
+        }
+
+        self.write(|writer| writer.write_event(event));
+
+        if let Some(record) = ended {
+            self.close(Some(&record));
+        }
+    }
+}
+
+impl GameSink for ReplaySink {
+    fn on_update(&mut self, update: &GameUpdate, state: &GameState) {
+        for event in &update.events {
+            self.on_event(event, state);
+        }
+        if let Some(snapshot) = &update.snapshot {
+            self.write(|writer| writer.write_snapshot(snapshot));
+        }
+    }
+
+    fn on_frame(&mut self, frame: &Frame) {
+        if !self.config.embed_frames {
+            return;
+        }
+        self.write(|writer| writer.write_frame(frame));
+        self.last_frame = Some(frame.clone());
+    }
+
+    fn on_versions(
+        &mut self,
+        client_version: Option<&ClientVersion>,
+        schema_version: &ClientVersion,
+    ) {
+        // Versions are announced right after the frame that revealed them
+        if client_version.is_some() && client_version != self.client_version.as_ref() {
+            self.handshake = self.last_frame.clone();
+        }
+        self.client_version = client_version.cloned();
+        self.schema_version = Some(schema_version.clone());
+    }
+
+    /// Write out and sync buffered events once their interval has passed, so a
+    /// long think does not leave them only in memory
+    fn on_tick(&mut self) {
+        self.write(|writer| writer.flush_due());
+    }
+
+    /// Finish the replay of a match still open when capture stops
+    fn on_finish(&mut self) {
+        let record = self.tracker.current().cloned();
+        self.close(record.as_ref());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::common::error::ReplayError;
+use crate::game::event::{GameEvent, GameSnapshot};
+use crate::game::lifecycle::Match;
+use crate::protocol::frame::Frame;
+use crate::replay::format::{ReplayHeader, REPLAY_EXTENSION};
+use crate::replay::writer::ReplayWriter;
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Suffix of replay files still being written; anything left with it after a
+/// restart was interrupted and is finalized by `recovery::recover_all`
+pub const PARTIAL_SUFFIX: &str = ".partial";
+
+/// What a live replay records and how often it reaches the disk (PERF-001)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StreamConfig {
+    /// Longest time buffered events wait before being written to the file as a chunk
+    pub chunk_interval: Duration,
+    /// Longest time written chunks wait before being fsynced; zero syncs every chunk
+    pub sync_interval: Duration,
+    /// Also store the raw frames of the match so it can be re-decoded with newer schemas
+    pub embed_frames: bool,
+}
+
+impl Default for StreamConfig {
+    fn default() -> Self {
+        Self {
+            chunk_interval: Duration::from_secs(1),
+            sync_interval: Duration::from_secs(5),
+            embed_frames: true,
+        }
+    }
+}
+
+/// Path of the finished replay for a file name stem
+pub fn replay_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.{}", name, REPLAY_EXTENSION))
+}
+
+/// Path of the replay while it is being written
+pub fn partial_path(dir: &Path, name: &str) -> PathBuf {
+    dir.join(format!("{}.{}{}", name, REPLAY_EXTENSION, PARTIAL_SUFFIX))
+}
+
+/// Replay file written while the match is being played
+///
+/// Buffered events are handed to the OS as a chunk once `chunk_interval` has passed,
+/// which survives an application crash, and fsynced once `sync_interval` has passed,
+/// which survives a system crash. `flush_due` checks both; it runs on every write and
+/// should also be called periodically (the capture pipeline's tick) so that events
+/// reach the disk during idle stretches too.
+/// The file carries `PARTIAL_SUFFIX` until `finish` writes the index and renames it.
+pub struct StreamingReplayWriter {
+    writer: ReplayWriter<BufWriter<File>>,
+    partial_path: PathBuf,
+    final_path: PathBuf,
+    config: StreamConfig,
+    last_chunk: Instant,
+    last_sync: Instant,
+}
+
+impl StreamingReplayWriter {
+    /// Create a replay file and write its header
+    ///
+    /// # Arguments
+    /// * `dir` - Replays directory (created if missing)
+    /// * `name` - File name stem, e.g. the match record id
+    /// * `header` - Replay header
+    /// * `config` - Flush and sync intervals
+    pub fn create(
+        dir: &Path,
+        name: &str,
+        header: &ReplayHeader,
+        config: StreamConfig,
+    ) -> Result<Self, ReplayError> {
+        std::fs::create_dir_all(dir)?;
+        let partial_path = partial_path(dir, name);
+        let file = OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&partial_path)?;
+
+        let mut stream = Self {
+            writer: ReplayWriter::new(BufWriter::new(file), header)?,
+            final_path: replay_path(dir, name),
+            partial_path,
+            config,
+            last_chunk: Instant::now(),
+            last_sync: Instant::now(),
+        };
+        stream.sync()?;
+        Ok(stream)
+    }
+
+    /// Path the replay is written to until it is finished
+    pub fn partial_path(&self) -> &Path {
+        &self.partial_path
+    }
+
+    /// Append an event
+    pub fn write_event(&mut self, event: &GameEvent) -> Result<(), ReplayError> {
+        self.writer.write_event(event)?;
+        self.flush_due()
+    }
+
+    /// Append a raw frame
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), ReplayError> {
+        self.writer.write_frame(frame)?;
+        self.flush_due()
+    }
+
+    /// Append a snapshot, writing buffered events first
+    pub fn write_snapshot(&mut self, snapshot: &GameSnapshot) -> Result<(), ReplayError> {
+        self.writer.write_snapshot(snapshot)?;
+        self.last_chunk = Instant::now();
+        self.flush_due()
+    }
+
+    /// Write out and sync whatever the configured intervals say is due
+    pub fn flush_due(&mut self) -> Result<(), ReplayError> {
+        if self.last_chunk.elapsed() >= self.config.chunk_interval {
+            self.writer.flush_events()?;
+            self.writer.flush_frames()?;
+            self.writer.get_mut().flush()?;
+            self.last_chunk = Instant::now();
+        }
+        if self.last_sync.elapsed() >= self.config.sync_interval {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Make every chunk written so far durable
+    ///
+    /// Buffered events and frames that have not become a chunk yet are not included.
+    pub fn sync(&mut self) -> Result<(), ReplayError> {
+        let file = self.writer.get_mut();
+        file.flush()?;
+        file.get_ref().sync_data()?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Finish the replay: write remaining events, the match record and the index,
+    /// then give the file its final name
+    ///
+    /// # Arguments
+    /// * `record` - Match record to store with the replay, if known
+    ///
+    /// # Returns
+    /// Path of the finished replay
+    pub fn finish(mut self, record: Option<&Match>) -> Result<PathBuf, ReplayError> {
+        if let Some(record) = record {
+            self.writer.write_match(record)?;
+        }
+        let file = self
+            .writer
+            .finish()?
+            .into_inner()
+            .map_err(|e| ReplayError::Io(e.into_error()))?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&self.partial_path, &self.final_path)?;
+        Ok(self.final_path)
+    }
+}
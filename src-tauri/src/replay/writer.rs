@@ -0,0 +1,252 @@
+use crate::common::error::ReplayError;
+use crate::game::event::{GameEvent, GameSnapshot};
+use crate::game::lifecycle::Match;
+use crate::protocol::frame::Frame;
+use crate::replay::format::{
+    self, ChunkHeader, ChunkKind, IndexEntry, ReplayHeader, StoredEvent, StoredFrames,
+    CHUNK_HEADER_LEN, FLAG_COMPRESSED, FLAG_REQUIRED, FOOTER_MAGIC, FORMAT_MAJOR, FORMAT_MINOR,
+    MAGIC, MAX_CHUNK_LEN, MAX_HEADER_LEN,
+};
+use std::io::Write;
+
+/// Events buffered before they are written out as one chunk
+pub const EVENTS_PER_CHUNK: usize = 512;
+
+/// Raw frames buffered before they are written out as one chunk
+pub const FRAMES_PER_CHUNK: usize = 256;
+
+/// zstd level used for chunk payloads
+pub const COMPRESSION_LEVEL: i32 = 3;
+
+/// Writes a replay file: preamble and header, then chunks, then the index and footer
+///
+/// Events are buffered and written as one compressed chunk every
+/// `EVENTS_PER_CHUNK` events, and before every snapshot so that a snapshot always
+/// follows the events it covers. Raw frames, when recorded, are buffered the same
+/// way into their own chunks.
+pub struct ReplayWriter<W: Write> {
+    inner: W,
+    /// Bytes written so far (offset of the next chunk)
+    offset: u64,
+    index: Vec<IndexEntry>,
+    pending: Vec<GameEvent>,
+    pending_frames: Vec<Frame>,
+}
+
+impl<W: Write> ReplayWriter<W> {
+    /// Start a replay by writing the preamble and header
+    ///
+    /// # Arguments
+    /// * `inner` - Destination, positioned at the start of the file
+    /// * `header` - Replay header
+    pub fn new(mut inner: W, header: &ReplayHeader) -> Result<Self, ReplayError> {
+        let header_bytes = format::encode(header)?;
+        if header_bytes.len() > MAX_HEADER_LEN as usize {
+            return Err(ReplayError::Encode(format!(
+                "header of {} bytes exceeds the {} byte limit",
+                header_bytes.len(),
+                MAX_HEADER_LEN
+            )));
+        }
+
+        inner.write_all(&MAGIC)?;
+        inner.write_all(&FORMAT_MAJOR.to_le_bytes())?;
+        inner.write_all(&FORMAT_MINOR.to_le_bytes())?;
+        inner.write_all(&(header_bytes.len() as u32).to_le_bytes())?;
+        inner.write_all(&crc32fast::hash(&header_bytes).to_le_bytes())?;
+        inner.write_all(&header_bytes)?;
+
+        Ok(Self {
+            inner,
+            offset: (format::PREAMBLE_LEN + header_bytes.len()) as u64,
+            index: Vec::new(),
+            pending: Vec::new(),
+            pending_frames: Vec::new(),
+        })
+    }
+
+    /// Continue a replay whose preamble, header and chunks are already written
+    ///
+    /// # Arguments
+    /// * `inner` - Destination, positioned at `offset`
+    /// * `offset` - End of the last chunk on disk
+    /// * `index` - The chunks already on disk
+    pub fn resume(inner: W, offset: u64, index: Vec<IndexEntry>) -> Self {
+        Self {
+            inner,
+            offset,
+            index,
+            pending: Vec::new(),
+            pending_frames: Vec::new(),
+        }
+    }
+
+    /// Chunks written so far
+    pub fn index(&self) -> &[IndexEntry] {
+        &self.index
+    }
+
+    /// Bytes written so far, not counting buffered events
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    /// Buffer an event, writing a chunk once enough have accumulated
+    pub fn write_event(&mut self, event: &GameEvent) -> Result<(), ReplayError> {
+        self.pending.push(event.clone());
+        if self.pending.len() >= EVENTS_PER_CHUNK {
+            self.flush_events()?;
+        }
+        Ok(())
+    }
+
+    /// Write buffered events as a chunk
+    pub fn flush_events(&mut self) -> Result<(), ReplayError> {
+        let (Some(first), Some(last)) = (self.pending.first(), self.pending.last()) else {
+            return Ok(());
+        };
+        let (first_seq, last_seq) = (first.seq, last.seq);
+        let stored: Vec<StoredEvent> = self.pending.iter().map(StoredEvent::from).collect();
+        let payload = format::encode(&stored)?;
+        self.write_chunk(
+            ChunkKind::Events.code(),
+            FLAG_REQUIRED,
+            first_seq,
+            last_seq,
+            &payload,
+        )?;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Buffer a raw frame, writing a chunk once enough have accumulated
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), ReplayError> {
+        self.pending_frames.push(frame.clone());
+        if self.pending_frames.len() >= FRAMES_PER_CHUNK {
+            self.flush_frames()?;
+        }
+        Ok(())
+    }
+
+    /// Write buffered frames as a chunk
+    ///
+    /// Frames chunks are optional: a reader that does not know them still reads the events.
+    pub fn flush_frames(&mut self) -> Result<(), ReplayError> {
+        let (Some(first), Some(last)) = (self.pending_frames.first(), self.pending_frames.last())
+        else {
+            return Ok(());
+        };
+        let (first_index, last_index) = (first.index, last.index);
+        let payload = format::encode(&StoredFrames::new(&self.pending_frames))?;
+        self.write_chunk(
+            ChunkKind::Frames.code(),
+            0,
+            first_index,
+            last_index,
+            &payload,
+        )?;
+        self.pending_frames.clear();
+        Ok(())
+    }
+
+    /// Write a state snapshot (after any buffered events)
+    pub fn write_snapshot(&mut self, snapshot: &GameSnapshot) -> Result<(), ReplayError> {
+        self.flush_events()?;
+        let payload = format::encode(snapshot)?;
+        self.write_chunk(
+            ChunkKind::Snapshot.code(),
+            FLAG_REQUIRED,
+            snapshot.seq,
+            snapshot.seq,
+            &payload,
+        )
+    }
+
+    /// Write the match record, normally once the match has closed
+    pub fn write_match(&mut self, record: &Match) -> Result<(), ReplayError> {
+        self.flush_events()?;
+        let payload = format::encode(record)?;
+        self.write_chunk(ChunkKind::Match.code(), 0, 0, 0, &payload)
+    }
+
+    /// Write one chunk, compressing the payload when that makes it smaller
+    ///
+    /// # Arguments
+    /// * `kind` - Chunk kind code (kinds unknown to this build are allowed)
+    /// * `flags` - Chunk flags; `FLAG_COMPRESSED` is set here as appropriate
+    /// * `first_seq` / `last_seq` - Event sequence range the chunk covers
+    /// * `payload` - Uncompressed payload
+    pub fn write_chunk(
+        &mut self,
+        kind: u16,
+        flags: u16,
+        first_seq: u64,
+        last_seq: u64,
+        payload: &[u8],
+    ) -> Result<(), ReplayError> {
+        if payload.len() > MAX_CHUNK_LEN as usize {
+            return Err(ReplayError::Encode(format!(
+                "chunk of {} bytes exceeds the {} byte limit",
+                payload.len(),
+                MAX_CHUNK_LEN
+            )));
+        }
+
+        let compressed = zstd::bulk::compress(payload, COMPRESSION_LEVEL)?;
+        let (stored, flags) = if compressed.len() < payload.len() {
+            (compressed.as_slice(), flags | FLAG_COMPRESSED)
+        } else {
+            (payload, flags & !FLAG_COMPRESSED)
+        };
+
+        let mut header = ChunkHeader {
+            kind,
+            flags,
+            stored_len: stored.len() as u32,
+            raw_len: payload.len() as u32,
+            first_seq,
+            last_seq,
+            crc: 0,
+        };
+        header.crc = header.compute_crc(stored);
+        self.inner.write_all(&header.encode())?;
+        self.inner.write_all(stored)?;
+
+        if kind != ChunkKind::Index.code() {
+            self.index.push(IndexEntry {
+                kind,
+                flags,
+                offset: self.offset,
+                first_seq,
+                last_seq,
+            });
+        }
+        self.offset += (CHUNK_HEADER_LEN + stored.len()) as u64;
+        Ok(())
+    }
+
+    /// Finish the replay: write buffered events and frames, the index and the footer
+    ///
+    /// # Returns
+    /// The underlying writer, flushed
+    pub fn finish(mut self) -> Result<W, ReplayError> {
+        self.flush_events()?;
+        self.flush_frames()?;
+        let index_offset = self.offset;
+        let payload = format::encode(&self.index)?;
+        self.write_chunk(ChunkKind::Index.code(), 0, 0, 0, &payload)?;
+
+        self.inner.write_all(&index_offset.to_le_bytes())?;
+        self.inner.write_all(&FOOTER_MAGIC)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
@@ -0,0 +1,172 @@
+use crate::analytics::cards::{self, CardStats};
+use crate::analytics::mulligan::{self, AnalyzedHand, HandFilter, KeyCard, MulliganReport};
+use crate::analytics::winrate::{
+    self, Dimension, MatchupMatrix, StatUnit, TimeBucket, WinRateBreakdown, WinRateSeries,
+};
+use crate::library::LibraryQuery;
+use crate::ui::card_commands::with_card_db;
+use crate::ui::library_commands::with_library;
+use std::collections::{HashMap, HashSet};
+
+/// Win rates grouped by deck, archetype, opponent archetype, play/draw or game
+/// number (STAT-002)
+///
+/// # Arguments
+/// * `filter` - Library filters selecting the matches to count (paging is ignored)
+/// * `dimension` - What to group by
+/// * `unit` - Count games or matches; per-game dimensions always count games
+///
+/// # Returns
+/// Overall win rate and one row per group, with 95% Wilson intervals
+#[tauri::command]
+pub async fn get_win_rates(
+    app: tauri::AppHandle,
+    filter: LibraryQuery,
+    dimension: Dimension,
+    unit: StatUnit,
+) -> Result<WinRateBreakdown, String> {
+    let games = with_library(&app, move |library| library.games(&filter)).await?;
+    Ok(winrate::breakdown(&games, dimension, unit))
+}
+
+/// Win rate over time, by day, week or month (STAT-002)
+///
+/// # Arguments
+/// * `filter` - Library filters selecting the matches to count (paging is ignored)
+/// * `bucket` - Width of each point
+/// * `unit` - Count games or matches
+/// * `dimension` - One series per group, or a single overall series if absent
+///
+/// # Returns
+/// One dated series per group
+#[tauri::command]
+pub async fn get_win_rate_trend(
+    app: tauri::AppHandle,
+    filter: LibraryQuery,
+    bucket: TimeBucket,
+    unit: StatUnit,
+    dimension: Option<Dimension>,
+) -> Result<Vec<WinRateSeries>, String> {
+    let games = with_library(&app, move |library| library.games(&filter)).await?;
+    Ok(winrate::trend(&games, bucket, unit, dimension))
+}
+
+/// Matchup matrix of the user's decks against opponent archetypes (STAT-002)
+///
+/// # Arguments
+/// * `filter` - Library filters selecting the matches to count (paging is ignored)
+/// * `rows` - Group the user's side by `deck` or `archetype`
+/// * `unit` - Count games or matches
+#[tauri::command]
+pub async fn get_matchup_matrix(
+    app: tauri::AppHandle,
+    filter: LibraryQuery,
+    rows: Dimension,
+    unit: StatUnit,
+) -> Result<MatchupMatrix, String> {
+    if !matches!(rows, Dimension::Deck | Dimension::Archetype) {
+        return Err("Matchup matrix rows must be grouped by deck or archetype".to_string());
+    }
+    let games = with_library(&app, move |library| library.games(&filter)).await?;
+    Ok(winrate::matchup_matrix(&games, rows, unit))
+}
+
+/// Opening hands of the matches a filter selects, with lands counted from the
+/// card database, narrowed to those a hand filter matches
+async fn analyzed_hands(
+    app: &tauri::AppHandle,
+    filter: LibraryQuery,
+    hands: HandFilter,
+) -> Result<Vec<AnalyzedHand>, String> {
+    let library_hands = with_library(app, move |library| library.opening_hands(&filter)).await?;
+    let ids: Vec<u32> = library_hands
+        .iter()
+        .flat_map(|hand| hand.cards.iter().copied())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let lands: HashSet<u32> = with_card_db(app, move |db| {
+        Ok(db
+            .get_many(&ids)?
+            .into_values()
+            .filter(|card| card.is_land())
+            .map(|card| card.mtgo_id)
+            .collect())
+    })
+    .await?;
+    Ok(mulligan::analyze(library_hands, &lands)
+        .into_iter()
+        .filter(|hand| hands.matches(hand))
+        .collect())
+}
+
+/// Keep rates by land count and key cards, and win rates after keeping 7 or
+/// mulliganing to each smaller size (STAT-002)
+///
+/// Lands are recognised through the card database; import card data first.
+///
+/// # Arguments
+/// * `filter` - Library filters selecting the matches to count (paging is ignored)
+/// * `hands` - Narrow the hands counted (e.g. only on the draw)
+/// * `key_cards` - Cards to report keep rates for
+#[tauri::command]
+pub async fn get_mulligan_report(
+    app: tauri::AppHandle,
+    filter: LibraryQuery,
+    hands: HandFilter,
+    key_cards: Vec<KeyCard>,
+) -> Result<MulliganReport, String> {
+    let hands = analyzed_hands(&app, filter, hands).await?;
+    Ok(mulligan::report(&hands, &key_cards))
+}
+
+/// Search opening hands, e.g. hands with 1 land kept on the draw
+///
+/// # Arguments
+/// * `filter` - Library filters selecting the matches to search (paging is ignored)
+/// * `hands` - Kept, play/draw, land count, mulligans and card filters
+///
+/// # Returns
+/// Matching hands with the replay they were drawn in, oldest first
+#[tauri::command]
+pub async fn search_opening_hands(
+    app: tauri::AppHandle,
+    filter: LibraryQuery,
+    hands: HandFilter,
+) -> Result<Vec<AnalyzedHand>, String> {
+    analyzed_hands(&app, filter, hands).await
+}
+
+/// Per-card performance: win rates in hand, in the opening hand, when drawn and
+/// when not seen, improvement when drawn, casts and turn first cast (STAT-002)
+///
+/// Printings are grouped by name through the card database; filter by deck
+/// (`archetype`) and `format` like a library search.
+///
+/// # Arguments
+/// * `filter` - Library filters selecting the matches to count (paging is ignored)
+///
+/// # Returns
+/// One entry per card, the most often in hand first
+#[tauri::command]
+pub async fn get_card_stats(
+    app: tauri::AppHandle,
+    filter: LibraryQuery,
+) -> Result<Vec<CardStats>, String> {
+    let card_games = with_library(&app, move |library| library.card_games(&filter)).await?;
+    let ids: Vec<u32> = card_games
+        .iter()
+        .map(|card| card.card_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let names: HashMap<u32, String> = with_card_db(&app, move |db| {
+        Ok(db
+            .get_many(&ids)?
+            .into_iter()
+            .map(|(id, card)| (id, card.name))
+            .collect())
+    })
+    .await?;
+    Ok(cards::card_stats(&card_games, &names))
+}
@@ -0,0 +1,155 @@
+use crate::common::error::AnnotationError;
+use crate::library::{AnnotationQuery, LibraryAnnotation, LibraryQuery};
+use crate::replay::annotations::{
+    self, Annotation, AnnotationDraft, MergeReport, ReplayAnnotations,
+};
+use crate::replay::format::REPLAY_EXTENSION;
+use crate::ui::library_commands::with_library;
+use chrono::Utc;
+use std::path::PathBuf;
+use tracing::warn;
+
+/// Change the annotations of a replay and bring the library's copy up to date
+///
+/// # Arguments
+/// * `path` - Replay whose annotations file to change
+/// * `f` - Change to make; nothing is saved if it fails
+async fn edit_annotations<T, F>(app: &tauri::AppHandle, path: PathBuf, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut ReplayAnnotations) -> Result<T, AnnotationError> + Send + 'static,
+{
+    let sidecar = annotations::sidecar_path(&path);
+    let result = tokio::task::spawn_blocking(move || {
+        let mut annotations = ReplayAnnotations::load(&sidecar)?;
+        let result = f(&mut annotations)?;
+        annotations.save(&sidecar)?;
+        Ok::<_, AnnotationError>(result)
+    })
+    .await
+    .map_err(|e| format!("Annotations task failed: {}", e))??;
+
+    // The watcher would pick the change up too, but searches should see it now
+    if let Err(e) = with_library(app, move |library| library.index_annotations(&path)).await {
+        warn!("Failed to index annotations: {}", e);
+    }
+    Ok(result)
+}
+
+/// Annotations and bookmarks of a replay, in timeline order
+///
+/// # Arguments
+/// * `path` - Replay file
+#[tauri::command]
+pub async fn get_annotations(path: PathBuf) -> Result<Vec<Annotation>, String> {
+    let sidecar = annotations::sidecar_path(&path);
+    tokio::task::spawn_blocking(move || {
+        let annotations = ReplayAnnotations::load(&sidecar)?;
+        Ok::<_, AnnotationError>(annotations.visible().into_iter().cloned().collect())
+    })
+    .await
+    .map_err(|e| format!("Annotations task failed: {}", e))?
+    .map_err(String::from)
+}
+
+/// Annotate a turn or an event of a replay, or bookmark it
+///
+/// # Arguments
+/// * `path` - Replay file
+/// * `author` - Name the annotation is signed with
+/// * `draft` - Game, turn and event, text, tags and whether it is a bookmark
+///
+/// # Returns
+/// The new annotation with its id
+#[tauri::command]
+pub async fn add_annotation(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    author: String,
+    draft: AnnotationDraft,
+) -> Result<Annotation, String> {
+    edit_annotations(&app, path, move |annotations| {
+        annotations.add(&author, &draft, Utc::now())
+    })
+    .await
+}
+
+/// Edit an annotation of a replay
+///
+/// # Arguments
+/// * `path` - Replay file
+/// * `id` - Annotation to edit
+/// * `draft` - New position and content
+#[tauri::command]
+pub async fn update_annotation(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    id: String,
+    draft: AnnotationDraft,
+) -> Result<Annotation, String> {
+    edit_annotations(&app, path, move |annotations| {
+        annotations.update(&id, &draft, Utc::now())
+    })
+    .await
+}
+
+/// Delete an annotation of a replay
+#[tauri::command]
+pub async fn delete_annotation(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    id: String,
+) -> Result<(), String> {
+    edit_annotations(&app, path, move |annotations| {
+        annotations.delete(&id, Utc::now())
+    })
+    .await
+}
+
+/// Merge a teammate's annotations of the same replay into the user's
+///
+/// # Arguments
+/// * `path` - Replay file whose annotations to merge into
+/// * `from` - The teammate's annotations file, or their copy of the replay with
+///   its annotations file beside it
+///
+/// # Returns
+/// Annotations added, updated and deleted by the merge
+#[tauri::command]
+pub async fn merge_annotations(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    from: PathBuf,
+) -> Result<MergeReport, String> {
+    let from = if from.extension().and_then(|e| e.to_str()) == Some(REPLAY_EXTENSION) {
+        annotations::sidecar_path(&from)
+    } else {
+        from
+    };
+    if !from.exists() {
+        return Err(format!("No annotations found at {}", from.display()));
+    }
+    edit_annotations(&app, path, move |annotations| {
+        let theirs = ReplayAnnotations::load(&from)?;
+        Ok(annotations.merge(&theirs))
+    })
+    .await
+}
+
+/// Search the annotations of the replay library, e.g. the user's misplays in
+/// Modern this month (STAT-004)
+///
+/// # Arguments
+/// * `query` - Match filters: date range, format, opponent, archetype, result
+/// * `filter` - Tags, author, text and bookmark filters
+///
+/// # Returns
+/// Matching annotations with their replay and match, newest match first
+#[tauri::command]
+pub async fn search_annotations(
+    app: tauri::AppHandle,
+    query: LibraryQuery,
+    filter: AnnotationQuery,
+) -> Result<Vec<LibraryAnnotation>, String> {
+    with_library(&app, move |library| library.annotations(&query, &filter)).await
+}
@@ -0,0 +1,87 @@
+use crate::archetype::{self, ArchetypeDefinitions, Classification, ReclassifyReport};
+use crate::cards::CardDatabase;
+use crate::common::paths::{archetypes_path, card_db_path, library_db_path};
+use crate::library::ReplayLibrary;
+
+/// Reclassify the library against the definitions on disk
+async fn reclassify_library_with(
+    app: &tauri::AppHandle,
+    all: bool,
+) -> Result<ReclassifyReport, String> {
+    let definitions_path = archetypes_path(app)?;
+    let library_path = library_db_path(app)?;
+    let cards_path = card_db_path(app)?;
+    tokio::task::spawn_blocking(move || {
+        let definitions = ArchetypeDefinitions::load(&definitions_path)?;
+        let cards = CardDatabase::open(&cards_path)?;
+        let mut library = ReplayLibrary::open(&library_path)?;
+        archetype::reclassify(&mut library, &cards, &definitions, all)
+    })
+    .await
+    .map_err(|e| format!("Archetype classification task failed: {}", e))?
+    .map_err(String::from)
+}
+
+/// The user's archetype definitions; empty until some are saved
+#[tauri::command]
+pub async fn get_archetype_definitions(
+    app: tauri::AppHandle,
+) -> Result<ArchetypeDefinitions, String> {
+    let path = archetypes_path(&app)?;
+    tokio::task::spawn_blocking(move || ArchetypeDefinitions::load(&path))
+        .await
+        .map_err(|e| format!("Archetype definitions task failed: {}", e))?
+        .map_err(String::from)
+}
+
+/// Replace the archetype definitions and reclassify the library with them (STAT-004)
+///
+/// # Arguments
+/// * `definitions` - Archetypes with their signature cards and weights
+///
+/// # Returns
+/// Matches whose archetypes were recomputed
+#[tauri::command]
+pub async fn save_archetype_definitions(
+    app: tauri::AppHandle,
+    definitions: ArchetypeDefinitions,
+) -> Result<ReclassifyReport, String> {
+    let path = archetypes_path(&app)?;
+    tokio::task::spawn_blocking(move || definitions.save(&path))
+        .await
+        .map_err(|e| format!("Archetype definitions task failed: {}", e))?
+        .map_err(String::from)?;
+    reclassify_library_with(&app, false).await
+}
+
+/// Classify a deck list with the saved definitions, e.g. while editing them
+///
+/// # Arguments
+/// * `format` - Format the deck is played in
+/// * `card_ids` - Catalog ids of the deck's cards, or of the cards seen of it
+///
+/// # Returns
+/// The archetype (None for unknown), its confidence and every archetype's score
+#[tauri::command]
+pub async fn classify_deck(
+    app: tauri::AppHandle,
+    format: String,
+    card_ids: Vec<u32>,
+) -> Result<Classification, String> {
+    let definitions_path = archetypes_path(&app)?;
+    let cards_path = card_db_path(&app)?;
+    tokio::task::spawn_blocking(move || {
+        let definitions = ArchetypeDefinitions::load(&definitions_path)?;
+        let cards = CardDatabase::open(&cards_path)?;
+        definitions.classify_ids(&format, &card_ids, &cards)
+    })
+    .await
+    .map_err(|e| format!("Archetype classification task failed: {}", e))?
+    .map_err(String::from)
+}
+
+/// Classify every match in the library again, e.g. after importing card data
+#[tauri::command]
+pub async fn reclassify_library(app: tauri::AppHandle) -> Result<ReclassifyReport, String> {
+    reclassify_library_with(&app, true).await
+}
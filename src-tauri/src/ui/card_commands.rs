@@ -0,0 +1,73 @@
+use crate::cards::{Card, CardDatabase, CardDbStatus, CardQuery, CardSource, ImportReport};
+use crate::common::error::CardError;
+use crate::common::paths::card_db_path;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Run a card database operation on the blocking pool
+///
+/// SQLite calls block, so each command opens the database on a blocking thread;
+/// opening is cheap and avoids sharing a connection across async tasks.
+pub(crate) async fn with_card_db<T, F>(app: &tauri::AppHandle, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut CardDatabase) -> Result<T, CardError> + Send + 'static,
+{
+    let path = card_db_path(app)?;
+    tokio::task::spawn_blocking(move || {
+        let mut db = CardDatabase::open(&path)?;
+        f(&mut db)
+    })
+    .await
+    .map_err(|e| format!("Card database task failed: {}", e))?
+    .map_err(String::from)
+}
+
+/// Import card data from a local file into the card database
+///
+/// # Arguments
+/// * `source` - `scryfall` for a Scryfall bulk JSON file, `mtgo_catalog` for the
+///   MTGO client's `CardDataSource` directory or a catalog XML file
+/// * `path` - File or directory to import
+#[tauri::command]
+pub async fn import_card_data(
+    app: tauri::AppHandle,
+    source: CardSource,
+    path: PathBuf,
+) -> Result<ImportReport, String> {
+    with_card_db(&app, move |db| match source {
+        CardSource::Scryfall => db.import_scryfall(&path),
+        CardSource::MtgoCatalog => db.import_mtgo_catalog(&path),
+    })
+    .await
+}
+
+/// Look up a card by MTGO catalog id
+#[tauri::command]
+pub async fn get_card(app: tauri::AppHandle, mtgo_id: u32) -> Result<Option<Card>, String> {
+    with_card_db(&app, move |db| db.get(mtgo_id)).await
+}
+
+/// Resolve a batch of MTGO catalog ids (e.g. every card in a game state)
+///
+/// # Returns
+/// Map of id -> card; ids missing from the database are omitted
+#[tauri::command]
+pub async fn resolve_cards(
+    app: tauri::AppHandle,
+    mtgo_ids: Vec<u32>,
+) -> Result<HashMap<u32, Card>, String> {
+    with_card_db(&app, move |db| db.get_many(&mtgo_ids)).await
+}
+
+/// Search cards by name, type, text and set
+#[tauri::command]
+pub async fn search_cards(app: tauri::AppHandle, query: CardQuery) -> Result<Vec<Card>, String> {
+    with_card_db(&app, move |db| db.search(&query)).await
+}
+
+/// Card counts and last import times, for prompting the user to import card data
+#[tauri::command]
+pub async fn get_card_db_status(app: tauri::AppHandle) -> Result<CardDbStatus, String> {
+    with_card_db(&app, |db| db.status()).await
+}
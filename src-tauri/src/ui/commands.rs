@@ -1,14 +1,32 @@
-use crate::capture::admin::{is_running_as_admin, check_windivert_driver};
+use crate::capture::admin::is_running_as_admin;
 use crate::capture::handle::CaptureHandle;
 use crate::capture::loop_::capture_loop;
+use crate::capture::pipeline::{spawn_pipeline, CapturePipeline};
+use crate::capture::settings::CaptureSettings;
+use crate::common::paths::{
+    capture_settings_path, matches_dir, replays_dir, schemas_dir, sessions_dir,
+    unknown_versions_log,
+};
+use crate::protocol::archive::SessionArchive;
+use crate::protocol::decoder::Decoder;
+use crate::protocol::session::SessionRecorder;
+use crate::protocol::version::SchemaRegistry;
+use crate::replay::ReplaySink;
+use crate::ui::game_commands::{LiveGameState, TauriGameSink};
+use crate::ui::match_commands::TauriMatchSink;
 use serde::Serialize;
 use std::sync::Arc;
 use tokio::sync::{broadcast, Mutex};
 
+/// Time allowed for the pipeline to finalize the session archive after capture stops
+const PIPELINE_SHUTDOWN_TIMEOUT: tokio::time::Duration = tokio::time::Duration::from_secs(5);
+
 /// Capture task handle for shutdown control
 struct CaptureTask {
     abort_handle: tokio::task::AbortHandle,
     shutdown_tx: broadcast::Sender<()>,
+    /// Packet consumer; finishes once the capture loop drops its sender
+    pipeline: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl CaptureTask {
@@ -63,7 +81,8 @@ pub struct CaptureStatus {
 pub async fn check_admin_privileges() -> Result<AdminStatus, String> {
     use crate::capture::admin::check_windivert_driver;
 
-    let is_admin = is_running_as_admin().map_err(|e| format!("Failed to check admin privileges: {}", e))?;
+    let is_admin =
+        is_running_as_admin().map_err(|e| format!("Failed to check admin privileges: {}", e))?;
     let driver_found = check_windivert_driver().map_err(|e| e.to_string())?;
 
     Ok(AdminStatus {
@@ -88,10 +107,73 @@ pub async fn get_capture_status(
     })
 }
 
+/// Create the packet consumer for a new capture session
+///
+/// Loads the protocol schemas, opens a session archive and connects the game
+/// engine to the frontend, the match store and the replay writer, which writes
+/// as often as the capture settings say.
+fn build_pipeline(
+    app: &tauri::AppHandle,
+    live_game: Arc<Mutex<LiveGameState>>,
+) -> Result<CapturePipeline, String> {
+    let settings = CaptureSettings::load(&capture_settings_path(app)?)?;
+    let registry = Arc::new(SchemaRegistry::with_user_schemas(&schemas_dir(app)?)?);
+    let decoder =
+        Decoder::new(Arc::clone(&registry))?.with_unknown_version_log(unknown_versions_log(app)?);
+    let archive = SessionArchive::create(
+        &sessions_dir(app)?,
+        chrono::Utc::now(),
+        decoder.active_version().clone(),
+    )?;
+
+    let mut pipeline = CapturePipeline::new(SessionRecorder::new(decoder, archive));
+    let session_id = pipeline.session_id().to_string();
+    pipeline.add_sink(Box::new(TauriGameSink::new(app.clone(), live_game)));
+    pipeline.add_sink(Box::new(TauriMatchSink::new(
+        app.clone(),
+        matches_dir(app)?,
+        session_id.clone(),
+    )));
+    pipeline.add_sink(Box::new(ReplaySink::new(
+        replays_dir(app)?,
+        session_id,
+        settings.stream_config(),
+    )));
+    Ok(pipeline)
+}
+
+/// Get the capture settings
+#[tauri::command]
+pub async fn get_capture_settings(app: tauri::AppHandle) -> Result<CaptureSettings, String> {
+    let path = capture_settings_path(&app)?;
+    tokio::task::spawn_blocking(move || CaptureSettings::load(&path))
+        .await
+        .map_err(|e| format!("Settings task failed: {}", e))?
+        .map_err(String::from)
+}
+
+/// Save the capture settings; they apply from the next capture started
+///
+/// # Arguments
+/// * `settings` - Replay chunk and sync intervals and whether replays keep raw frames
+#[tauri::command]
+pub async fn set_capture_settings(
+    app: tauri::AppHandle,
+    settings: CaptureSettings,
+) -> Result<(), String> {
+    let path = capture_settings_path(&app)?;
+    tokio::task::spawn_blocking(move || settings.save(&path))
+        .await
+        .map_err(|e| format!("Settings task failed: {}", e))?
+        .map_err(String::from)
+}
+
 /// Start packet capture
 #[tauri::command]
 pub async fn start_capture(
+    app: tauri::AppHandle,
     state: tauri::State<'_, Arc<Mutex<CaptureState>>>,
+    live_game: tauri::State<'_, Arc<Mutex<LiveGameState>>>,
 ) -> Result<CaptureStatus, String> {
     // Check if already capturing
     {
@@ -107,19 +189,24 @@ pub async fn start_capture(
     }
 
     // Create WinDivert handle
-    let handle = CaptureHandle::new()
-        .map_err(|e| format!("Failed to initialize WinDivert: {}", e))?;
+    let handle =
+        CaptureHandle::new().map_err(|e| format!("Failed to initialize WinDivert: {}", e))?;
+
+    // Open the session archive only once the capture driver is ready
+    let pipeline = build_pipeline(&app, Arc::clone(&live_game))?;
+    *live_game.lock().await = LiveGameState {
+        session_id: Some(pipeline.session_id().to_string()),
+        ..LiveGameState::default()
+    };
 
     // Create shutdown channel
     let (shutdown_tx, _shutdown_rx) = broadcast::channel(1);
     let shutdown_tx_for_capture = shutdown_tx.clone();
     let shutdown_tx = shutdown_tx.clone();
 
-    // Start capture loop
-    let (_packet_rx, abort_handle) = capture_loop(
-        handle.clone_handle(),
-        shutdown_tx_for_capture,
-    );
+    // Start capture loop and feed its packets to the decoding pipeline
+    let (packet_rx, abort_handle) = capture_loop(handle.clone_handle(), shutdown_tx_for_capture);
+    let pipeline = spawn_pipeline(pipeline, packet_rx);
 
     // Update state
     let mut state_guard = state.lock().await;
@@ -130,6 +217,7 @@ pub async fn start_capture(
     state_guard.capture_task = Some(CaptureTask {
         abort_handle,
         shutdown_tx,
+        pipeline: Some(pipeline),
     });
 
     Ok(CaptureStatus {
@@ -155,7 +243,7 @@ pub async fn stop_capture(
         state_guard.capture_task.take()
     };
 
-    if let Some(capture_task) = capture_task_opt {
+    if let Some(mut capture_task) = capture_task_opt {
         // Send shutdown signal
         let _ = capture_task.shutdown_tx.send(());
 
@@ -164,6 +252,19 @@ pub async fn stop_capture(
 
         // Abort if still running
         capture_task.shutdown();
+
+        // Wait for the pipeline to drain buffered packets and close the session archive
+        if let Some(pipeline) = capture_task.pipeline.take() {
+            if tokio::time::timeout(PIPELINE_SHUTDOWN_TIMEOUT, pipeline)
+                .await
+                .is_err()
+            {
+                tracing::warn!(
+                    "Capture pipeline did not finish within {:?}",
+                    PIPELINE_SHUTDOWN_TIMEOUT
+                );
+            }
+        }
     }
 
     let state_guard = state.lock().await;
@@ -0,0 +1,74 @@
+use crate::cards::CardDatabase;
+use crate::common::paths::{card_db_path, matches_dir};
+use crate::decklist::dek::{self, DekFile};
+use crate::decklist::{self, DeckExport, DeckFormat, DeckSide};
+use crate::game::deck::IntendedDeck;
+use crate::game::lifecycle::Match;
+use crate::game::match_store;
+use std::path::PathBuf;
+
+/// Export a deck list from a match record (STAT-005)
+///
+/// # Arguments
+/// * `record_id` - Match record id
+/// * `side` - `own`, `opponent` (cards seen) or `intended` (tagged list)
+/// * `format` - `dek`, `text` or `arena`
+/// * `game_number` - For `own`, which game's registration to export (default: game 1)
+/// * `output_path` - Also write the list to this file when given
+///
+/// # Returns
+/// The rendered list and the catalog ids the card database could not name
+#[tauri::command]
+pub async fn export_match_deck(
+    app: tauri::AppHandle,
+    record_id: String,
+    side: DeckSide,
+    format: DeckFormat,
+    game_number: Option<u8>,
+    output_path: Option<PathBuf>,
+) -> Result<DeckExport, String> {
+    let matches_dir = matches_dir(&app)?;
+    let card_db = card_db_path(&app)?;
+
+    tokio::task::spawn_blocking(move || -> Result<DeckExport, String> {
+        let record = match_store::load_match(&matches_dir, &record_id)?;
+        let deck = decklist::match_deck(&record, side, game_number)?;
+        let cards = CardDatabase::open(&card_db)?;
+        let export = decklist::export(&deck, format, &cards);
+        if let Some(path) = output_path {
+            std::fs::write(&path, &export.content)
+                .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        }
+        Ok(export)
+    })
+    .await
+    .map_err(|e| format!("Deck export task failed: {}", e))?
+}
+
+/// Read an MTGO .dek file
+#[tauri::command]
+pub async fn import_dek_file(path: PathBuf) -> Result<DekFile, String> {
+    dek::read_dek(&path).map_err(String::from)
+}
+
+/// Tag a match with the deck list the user intended to play, from a .dek file
+///
+/// # Returns
+/// The updated match record
+#[tauri::command]
+pub async fn tag_match_deck(
+    app: tauri::AppHandle,
+    record_id: String,
+    path: PathBuf,
+) -> Result<Match, String> {
+    let dir = matches_dir(&app)?;
+    let dek = dek::read_dek(&path)?;
+    let mut record = match_store::load_match(&dir, &record_id)?;
+    record.intended_deck = Some(IntendedDeck {
+        name: dek.name,
+        deck: dek.deck,
+        imported_at: chrono::Utc::now(),
+    });
+    match_store::save_match(&dir, &record)?;
+    Ok(record)
+}
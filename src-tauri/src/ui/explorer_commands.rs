@@ -0,0 +1,134 @@
+use crate::common::paths::{schemas_dir, sessions_dir};
+use crate::explorer::captures::{
+    compare_captures, CaptureDiffReport, ScenarioLabel, DEFAULT_LABEL_WINDOW_SECONDS,
+};
+use crate::explorer::diff::MessageDiff;
+use crate::explorer::{LoadedSession, MessageDetail, MessageFilter, MessagePage};
+use crate::protocol::archive;
+use crate::protocol::version::SchemaRegistry;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Protocol explorer state: the most recently opened session, kept decoded in memory
+///
+/// Paging and dumps are requested many times per session while browsing, so the
+/// session is decoded once on first access rather than on every command.
+#[derive(Default)]
+pub struct ExplorerState {
+    loaded: Option<Arc<LoadedSession>>,
+}
+
+/// Return the decoded session, loading it if a different session is open
+async fn loaded_session(
+    app: &tauri::AppHandle,
+    state: &Arc<Mutex<ExplorerState>>,
+    session_id: &str,
+) -> Result<Arc<LoadedSession>, String> {
+    let mut state_guard = state.lock().await;
+    if let Some(loaded) = &state_guard.loaded {
+        if loaded.session_id == session_id {
+            return Ok(Arc::clone(loaded));
+        }
+    }
+
+    let dir = archive::session_dir(&sessions_dir(app)?, session_id)?;
+    let schemas_dir = schemas_dir(app)?;
+    let loaded = tokio::task::spawn_blocking(move || {
+        let registry = SchemaRegistry::with_user_schemas(&schemas_dir)?;
+        LoadedSession::load(&dir, Arc::new(registry))
+    })
+    .await
+    .map_err(|e| format!("Failed to load session: {}", e))??;
+
+    let loaded = Arc::new(loaded);
+    state_guard.loaded = Some(Arc::clone(&loaded));
+    Ok(loaded)
+}
+
+/// Page through the messages of a capture session
+#[tauri::command]
+pub async fn list_session_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Arc<Mutex<ExplorerState>>>,
+    session_id: String,
+    offset: usize,
+    limit: usize,
+    filter: Option<MessageFilter>,
+) -> Result<MessagePage, String> {
+    let session = loaded_session(&app, &state, &session_id).await?;
+    Ok(session.page(&filter.unwrap_or_default(), offset, limit))
+}
+
+/// Hex+ASCII dump of one message with decoded field byte ranges overlaid
+#[tauri::command]
+pub async fn get_message_detail(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Arc<Mutex<ExplorerState>>>,
+    session_id: String,
+    index: u64,
+) -> Result<MessageDetail, String> {
+    let session = loaded_session(&app, &state, &session_id).await?;
+    session.detail(index).map_err(String::from)
+}
+
+/// Byte-by-byte diff of two messages of the same type
+#[tauri::command]
+pub async fn diff_messages(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Arc<Mutex<ExplorerState>>>,
+    session_id: String,
+    left_index: u64,
+    right_index: u64,
+) -> Result<MessageDiff, String> {
+    let session = loaded_session(&app, &state, &session_id).await?;
+    session.diff(left_index, right_index).map_err(String::from)
+}
+
+/// Drop the cached session so the next request re-decodes with current schemas
+#[tauri::command]
+pub async fn close_explorer_session(
+    state: tauri::State<'_, Arc<Mutex<ExplorerState>>>,
+) -> Result<(), String> {
+    state.lock().await.loaded = None;
+    Ok(())
+}
+
+/// Compare controlled captures to locate fields carrying card ids, life totals and zones
+///
+/// Aligns the message sequences of two or more sessions, reports which message types
+/// and byte positions differ, correlates differences with the analyst's labels and
+/// ranks candidate fields.
+#[tauri::command]
+pub async fn compare_capture_sessions(
+    app: tauri::AppHandle,
+    session_ids: Vec<String>,
+    labels: Vec<ScenarioLabel>,
+    window_seconds: Option<f64>,
+) -> Result<CaptureDiffReport, String> {
+    if session_ids.len() < 2 {
+        return Err("At least two capture sessions are required for comparison".to_string());
+    }
+
+    let sessions_dir = sessions_dir(&app)?;
+    let schemas_dir = schemas_dir(&app)?;
+
+    tokio::task::spawn_blocking(move || {
+        let registry = Arc::new(SchemaRegistry::with_user_schemas(&schemas_dir)?);
+        let sessions = session_ids
+            .iter()
+            .map(|id| {
+                let dir = archive::session_dir(&sessions_dir, id)?;
+                LoadedSession::load(&dir, Arc::clone(&registry)).map(Arc::new)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(compare_captures(
+            &sessions,
+            &labels,
+            window_seconds.unwrap_or(DEFAULT_LABEL_WINDOW_SECONDS),
+        ))
+    })
+    .await
+    .map_err(|e| format!("Capture comparison task failed: {}", e))?
+    .map_err(|e: crate::common::error::ProtocolError| e.to_string())
+}
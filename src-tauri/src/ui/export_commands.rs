@@ -0,0 +1,31 @@
+use crate::export::{self, ExportFormat, ExportReport};
+use std::path::PathBuf;
+
+/// Export a replay as a JSON document or as CSV tables (XPORT-001)
+///
+/// # Arguments
+/// * `path` - Replay to export; it is not modified
+/// * `format` - `json` or `csv`
+/// * `output_path` - JSON file or CSV directory (default: `<name>.json` or `<name>_csv` next to the replay)
+///
+/// # Returns
+/// The files written and the number of events exported
+#[tauri::command]
+pub async fn export_replay(
+    path: PathBuf,
+    format: ExportFormat,
+    output_path: Option<PathBuf>,
+) -> Result<ExportReport, String> {
+    tokio::task::spawn_blocking(move || -> Result<ExportReport, String> {
+        let output = output_path.unwrap_or_else(|| export::default_output(&path, format));
+        Ok(export::export_file(&path, format, &output)?)
+    })
+    .await
+    .map_err(|e| format!("Replay export task failed: {}", e))?
+}
+
+/// JSON Schema of the JSON export, for tools consuming it
+#[tauri::command]
+pub fn get_export_schema() -> String {
+    export::JSON_SCHEMA.to_string()
+}
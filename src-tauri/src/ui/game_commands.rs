@@ -0,0 +1,55 @@
+use crate::capture::pipeline::GameSink;
+use crate::game::event::GameUpdate;
+use crate::game::model::GameState;
+use serde::Serialize;
+use std::sync::Arc;
+use tauri::Emitter;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Tauri event carrying each `GameUpdate` of the live capture
+pub const GAME_UPDATE_EVENT: &str = "game-update";
+
+/// Latest reconstructed state of the game being captured
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct LiveGameState {
+    pub session_id: Option<String>,
+    /// Sequence number of the last applied event
+    pub last_seq: Option<u64>,
+    pub state: Option<GameState>,
+}
+
+/// Forwards game updates to the frontend and keeps `LiveGameState` current
+pub struct TauriGameSink {
+    app: tauri::AppHandle,
+    live: Arc<Mutex<LiveGameState>>,
+}
+
+impl TauriGameSink {
+    pub fn new(app: tauri::AppHandle, live: Arc<Mutex<LiveGameState>>) -> Self {
+        Self { app, live }
+    }
+}
+
+impl GameSink for TauriGameSink {
+    fn on_update(&mut self, update: &GameUpdate, state: &GameState) {
+        // Sinks run on the pipeline's blocking thread, so a blocking lock is fine
+        {
+            let mut live = self.live.blocking_lock();
+            live.last_seq = update.events.last().map(|e| e.seq).or(live.last_seq);
+            live.state = Some(state.clone());
+        }
+
+        if let Err(e) = self.app.emit(GAME_UPDATE_EVENT, update) {
+            warn!("Failed to emit game update: {}", e);
+        }
+    }
+}
+
+/// Get the reconstructed state of the game currently being captured
+#[tauri::command]
+pub async fn get_live_game_state(
+    state: tauri::State<'_, Arc<Mutex<LiveGameState>>>,
+) -> Result<LiveGameState, String> {
+    Ok(state.lock().await.clone())
+}
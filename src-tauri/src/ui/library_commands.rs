@@ -0,0 +1,57 @@
+use crate::common::error::LibraryError;
+use crate::common::paths::{library_db_path, matches_dir, replays_dir};
+use crate::library::{
+    watcher, LibraryMatch, LibraryQuery, LibraryStatus, ReplayLibrary, ScanReport,
+};
+
+/// Run a replay library operation on the blocking pool
+///
+/// Like the card database, the library is opened per command on a blocking
+/// thread; the watcher task opens its own connection.
+pub(crate) async fn with_library<T, F>(app: &tauri::AppHandle, f: F) -> Result<T, String>
+where
+    T: Send + 'static,
+    F: FnOnce(&mut ReplayLibrary) -> Result<T, LibraryError> + Send + 'static,
+{
+    let path = library_db_path(app)?;
+    tokio::task::spawn_blocking(move || {
+        let mut library = ReplayLibrary::open(&path)?;
+        f(&mut library)
+    })
+    .await
+    .map_err(|e| format!("Replay library task failed: {}", e))?
+    .map_err(String::from)
+}
+
+/// Search the replay library (STAT-004)
+///
+/// # Arguments
+/// * `query` - Date range, format, opponent, archetype, result and card filters
+///
+/// # Returns
+/// Matching matches with the path of their replay, newest first
+#[tauri::command]
+pub async fn query_library(
+    app: tauri::AppHandle,
+    query: LibraryQuery,
+) -> Result<Vec<LibraryMatch>, String> {
+    with_library(&app, move |library| library.query(&query)).await
+}
+
+/// Bring the library up to date with the replay directory now, without waiting
+/// for the watcher
+#[tauri::command]
+pub async fn rescan_library(app: tauri::AppHandle) -> Result<ScanReport, String> {
+    let replays_dir = replays_dir(&app)?;
+    let matches_dir = matches_dir(&app)?;
+    with_library(&app, move |library| {
+        watcher::scan(library, &replays_dir, Some(&matches_dir))
+    })
+    .await
+}
+
+/// Replay, match and game counts of the library
+#[tauri::command]
+pub async fn get_library_status(app: tauri::AppHandle) -> Result<LibraryStatus, String> {
+    with_library(&app, |library| library.status()).await
+}
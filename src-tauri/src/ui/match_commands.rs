@@ -0,0 +1,117 @@
+use crate::capture::pipeline::GameSink;
+use crate::common::paths::matches_dir;
+use crate::game::event::GameUpdate;
+use crate::game::lifecycle::{LifecycleEvent, Match, MatchTracker};
+use crate::game::match_store;
+use crate::game::model::GameState;
+use crate::game::sideboard::{self, SideboardPlan};
+use std::path::PathBuf;
+use tauri::Emitter;
+use tracing::{error, warn};
+
+/// Tauri event carrying each `LifecycleEvent` of the live capture
+pub const MATCH_LIFECYCLE_EVENT: &str = "match-lifecycle";
+
+/// Tracks matches in the live capture, emitting boundaries and persisting closed records
+pub struct TauriMatchSink {
+    app: tauri::AppHandle,
+    tracker: MatchTracker,
+    matches_dir: PathBuf,
+}
+
+impl TauriMatchSink {
+    pub fn new(app: tauri::AppHandle, matches_dir: PathBuf, session_id: String) -> Self {
+        Self {
+            app,
+            tracker: MatchTracker::new(Some(session_id)),
+            matches_dir,
+        }
+    }
+
+    fn save(&self, record: &Match) {
+        let mut record = record.clone();
+        // Keep what the user added to the stored record since the last save
+        if let Ok(stored) = match_store::load_match(&self.matches_dir, &record.record_id()) {
+            sideboard::keep_corrections(&mut record, &stored);
+            if record.intended_deck.is_none() {
+                record.intended_deck = stored.intended_deck;
+            }
+        }
+        if let Err(e) = match_store::save_match(&self.matches_dir, &record) {
+            error!("Failed to save match {}: {}", record.record_id(), e);
+        }
+    }
+}
+
+impl GameSink for TauriMatchSink {
+    fn on_update(&mut self, update: &GameUpdate, state: &GameState) {
+        for event in &update.events {
+            for lifecycle in self.tracker.apply(event, state) {
+                if lifecycle.closes_record() {
+                    match &lifecycle {
+                        LifecycleEvent::MatchEnded { record } => self.save(record),
+                        _ => {
+                            if let Some(record) = self.tracker.current() {
+                                self.save(record);
+                            }
+                        }
+                    }
+                }
+                if let Err(e) = self.app.emit(MATCH_LIFECYCLE_EVENT, &lifecycle) {
+                    warn!("Failed to emit match lifecycle event: {}", e);
+                }
+            }
+        }
+    }
+
+    /// Keep the partial record of a match still open when capture stops
+    fn on_finish(&mut self) {
+        if let Some(record) = self.tracker.current().filter(|m| !m.is_closed()) {
+            self.save(record);
+        }
+    }
+}
+
+/// List recorded matches, newest first
+#[tauri::command]
+pub async fn list_matches(app: tauri::AppHandle) -> Result<Vec<Match>, String> {
+    let dir = matches_dir(&app)?;
+    tokio::task::spawn_blocking(move || match_store::list_matches(&dir))
+        .await
+        .map_err(|e| format!("Match listing task failed: {}", e))?
+        .map_err(String::from)
+}
+
+/// Get one match record by its record id
+#[tauri::command]
+pub async fn get_match(app: tauri::AppHandle, record_id: String) -> Result<Match, String> {
+    let dir = matches_dir(&app)?;
+    match_store::load_match(&dir, &record_id).map_err(String::from)
+}
+
+/// Correct the detected sideboarding before a game (SIDE-001)
+///
+/// Both the detected and the corrected plan are kept with the match record.
+///
+/// # Arguments
+/// * `record_id` - Match record id
+/// * `game_number` - Game the sideboarding led into (2 or later)
+/// * `plan` - Corrected cards in/out; None discards an earlier correction
+/// * `note` - Optional note about the correction
+///
+/// # Returns
+/// The updated match record
+#[tauri::command]
+pub async fn correct_sideboard(
+    app: tauri::AppHandle,
+    record_id: String,
+    game_number: u8,
+    plan: Option<SideboardPlan>,
+    note: Option<String>,
+) -> Result<Match, String> {
+    let dir = matches_dir(&app)?;
+    let mut record = match_store::load_match(&dir, &record_id)?;
+    sideboard::correct(&mut record, game_number, plan, note)?;
+    match_store::save_match(&dir, &record)?;
+    Ok(record)
+}
@@ -1 +1,14 @@
+pub mod analytics_commands;
+pub mod annotation_commands;
+pub mod archetype_commands;
+pub mod card_commands;
 pub mod commands;
+pub mod deck_commands;
+pub mod explorer_commands;
+pub mod export_commands;
+pub mod game_commands;
+pub mod library_commands;
+pub mod match_commands;
+pub mod playback_commands;
+pub mod replay_commands;
+pub mod session_commands;
@@ -0,0 +1,219 @@
+use crate::game::knowledge::ViewMode;
+use crate::replay::playback::{
+    PlaybackOutline, PlaybackView, ReplayPlayback, SeekTarget, StepUnit,
+};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tauri::Emitter;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+/// Tauri event carrying the board after each frame played while playing
+pub const PLAYBACK_UPDATE_EVENT: &str = "playback-update";
+
+/// Replay viewer state: the open replay and its play timer
+#[derive(Default)]
+pub struct PlaybackState {
+    playback: Option<ReplayPlayback>,
+    /// Bumped whenever playback starts or stops or the replay changes, which
+    /// retires the running timer
+    timer: u64,
+}
+
+impl PlaybackState {
+    fn playback(&mut self) -> Result<&mut ReplayPlayback, String> {
+        self.playback
+            .as_mut()
+            .ok_or_else(|| "No replay is open for playback".to_string())
+    }
+}
+
+/// An opened replay: its timeline and the board before the first event
+#[derive(Debug, Clone, Serialize)]
+pub struct PlaybackOpened {
+    pub outline: PlaybackOutline,
+    pub view: PlaybackView,
+}
+
+/// Play frames at the recorded pace until paused, the end, or another timer starts
+async fn run_timer(app: tauri::AppHandle, state: Arc<Mutex<PlaybackState>>, timer: u64) {
+    loop {
+        let delay = {
+            let mut guard = state.lock().await;
+            if guard.timer != timer {
+                return;
+            }
+            match guard
+                .playback()
+                .ok()
+                .filter(|p| p.is_playing())
+                .and_then(|p| p.next_delay())
+            {
+                Some(delay) => delay,
+                None => return,
+            }
+        };
+        tokio::time::sleep(delay).await;
+
+        let view = {
+            let mut guard = state.lock().await;
+            if guard.timer != timer {
+                return;
+            }
+            let Some(playback) = guard.playback().ok().filter(|p| p.is_playing()) else {
+                return;
+            };
+            playback.advance();
+            playback.view()
+        };
+        if let Err(e) = app.emit(PLAYBACK_UPDATE_EVENT, &view) {
+            warn!("Failed to emit playback update: {}", e);
+        }
+    }
+}
+
+/// Open a replay for turn-by-turn playback (VIEW-001)
+///
+/// Replaces the replay open before, stopping its playback.
+///
+/// # Returns
+/// The games and turns of the replay and the board before the first event
+#[tauri::command]
+pub async fn open_playback(
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+    path: PathBuf,
+) -> Result<PlaybackOpened, String> {
+    let playback = tokio::task::spawn_blocking(move || ReplayPlayback::open(&path))
+        .await
+        .map_err(|e| format!("Failed to load replay: {}", e))??;
+    let opened = PlaybackOpened {
+        outline: playback.outline(),
+        view: playback.view(),
+    };
+    let mut guard = state.lock().await;
+    guard.timer += 1;
+    guard.playback = Some(playback);
+    Ok(opened)
+}
+
+/// Close the replay open for playback
+#[tauri::command]
+pub async fn close_playback(
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+) -> Result<(), String> {
+    let mut guard = state.lock().await;
+    guard.timer += 1;
+    guard.playback = None;
+    Ok(())
+}
+
+/// Board at the playback cursor
+#[tauri::command]
+pub async fn get_playback_view(
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+) -> Result<PlaybackView, String> {
+    Ok(state.lock().await.playback()?.view())
+}
+
+/// Move the playback cursor to a turn, phase, step, game or event
+///
+/// Playback continues from the new point if it was playing.
+#[tauri::command]
+pub async fn seek_playback(
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+    target: SeekTarget,
+) -> Result<PlaybackView, String> {
+    let mut guard = state.lock().await;
+    let playback = guard.playback()?;
+    playback.seek(&target)?;
+    Ok(playback.view())
+}
+
+/// Step forward or back by one event, step, turn or game
+///
+/// # Arguments
+/// * `unit` - What to step by
+/// * `forward` - Direction; stepping past either end leaves the cursor where it is
+#[tauri::command]
+pub async fn step_playback(
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+    unit: StepUnit,
+    forward: bool,
+) -> Result<PlaybackView, String> {
+    let mut guard = state.lock().await;
+    let playback = guard.playback()?;
+    playback.step(unit, forward);
+    Ok(playback.view())
+}
+
+/// Start playing at the recorded pace, scaled by the speed
+///
+/// Each frame played is sent as a `playback-update` event; playback stops at the
+/// end of the replay.
+///
+/// # Arguments
+/// * `speed` - Multiple of real time, if changing it
+#[tauri::command]
+pub async fn play_playback(
+    app: tauri::AppHandle,
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+    speed: Option<f64>,
+) -> Result<PlaybackView, String> {
+    let mut guard = state.lock().await;
+    let playback = guard.playback()?;
+    if let Some(speed) = speed {
+        playback.set_speed(speed);
+    }
+    playback.play();
+    let view = playback.view();
+    guard.timer += 1;
+    tauri::async_runtime::spawn(run_timer(app, state.inner().clone(), guard.timer));
+    Ok(view)
+}
+
+/// Pause playback
+#[tauri::command]
+pub async fn pause_playback(
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+) -> Result<PlaybackView, String> {
+    let mut guard = state.lock().await;
+    guard.timer += 1;
+    let playback = guard.playback()?;
+    playback.pause();
+    Ok(playback.view())
+}
+
+/// Change the playback speed; a running playback picks it up from the next frame
+///
+/// # Arguments
+/// * `speed` - Multiple of real time, clamped to 0.25-16
+#[tauri::command]
+pub async fn set_playback_speed(
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+    speed: f64,
+) -> Result<PlaybackView, String> {
+    let mut guard = state.lock().await;
+    let playback = guard.playback()?;
+    playback.set_speed(speed);
+    Ok(playback.view())
+}
+
+/// Show the board as the user knew it, with every card the replay identifies,
+/// or as the opponent knew it
+///
+/// # Arguments
+/// * `mode` - `as_played`, `full_information` or `opponent_perspective`
+///
+/// # Returns
+/// The board at the cursor in the new mode, with the provenance of each card
+#[tauri::command]
+pub async fn set_playback_view_mode(
+    state: tauri::State<'_, Arc<Mutex<PlaybackState>>>,
+    mode: ViewMode,
+) -> Result<PlaybackView, String> {
+    let mut guard = state.lock().await;
+    let playback = guard.playback()?;
+    playback.set_mode(mode);
+    Ok(playback.view())
+}
@@ -0,0 +1,132 @@
+use crate::common::paths::{anonymize_key_path, keyring_path, schemas_dir, signing_key_path};
+use crate::protocol::version::SchemaRegistry;
+use crate::replay::anonymize::{self, AnonymizeReport, Pseudonymizer};
+use crate::replay::signing::{
+    self, Keyring, PublicKeyInfo, SignatureInfo, SigningIdentity, TrustedKey, VerifyReport,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// Write an anonymized copy of a replay for sharing (XPORT-001)
+///
+/// Player names become pseudonyms derived from this installation's key, so the
+/// same opponent keeps the same pseudonym across shared replays.
+///
+/// # Arguments
+/// * `path` - Replay to anonymize; it is not modified
+/// * `output_path` - Where to write the copy (default: `<name>.anon.mtgoreplay` next to it)
+///
+/// # Returns
+/// Where the copy was written and what was redacted
+#[tauri::command]
+pub async fn anonymize_replay(
+    app: tauri::AppHandle,
+    path: PathBuf,
+    output_path: Option<PathBuf>,
+) -> Result<AnonymizeReport, String> {
+    let key_path = anonymize_key_path(&app)?;
+    let schemas_dir = schemas_dir(&app)?;
+
+    tokio::task::spawn_blocking(move || -> Result<AnonymizeReport, String> {
+        let pseudonyms = Pseudonymizer::load_or_create(&key_path)?;
+        let registry = Arc::new(SchemaRegistry::with_user_schemas(&schemas_dir)?);
+        let output = output_path.unwrap_or_else(|| anonymize::default_output(&path));
+        Ok(anonymize::anonymize_file(
+            &path,
+            &output,
+            &pseudonyms,
+            Some(&registry),
+        )?)
+    })
+    .await
+    .map_err(|e| format!("Replay anonymization task failed: {}", e))?
+}
+
+/// Sign a replay with this installation's key, generating the key pair on first use
+///
+/// # Returns
+/// The signer's public key and fingerprint and how many chunks are covered
+#[tauri::command]
+pub async fn sign_replay(app: tauri::AppHandle, path: PathBuf) -> Result<SignatureInfo, String> {
+    let key_path = signing_key_path(&app)?;
+
+    tokio::task::spawn_blocking(move || -> Result<SignatureInfo, String> {
+        let identity = SigningIdentity::load_or_create(&key_path)?;
+        Ok(signing::sign_file(&path, &identity)?)
+    })
+    .await
+    .map_err(|e| format!("Replay signing task failed: {}", e))?
+}
+
+/// Check that a replay was not modified since it was signed
+///
+/// # Returns
+/// The signature status, the signer's fingerprint (and keyring name if trusted)
+/// and every chunk that changed since signing
+#[tauri::command]
+pub async fn verify_replay(app: tauri::AppHandle, path: PathBuf) -> Result<VerifyReport, String> {
+    let key_path = signing_key_path(&app)?;
+    let keyring_path = keyring_path(&app)?;
+
+    tokio::task::spawn_blocking(move || -> Result<VerifyReport, String> {
+        let keyring = Keyring::load(&keyring_path)?;
+        let own_key = if key_path.exists() {
+            Some(SigningIdentity::load(&key_path)?.public_key())
+        } else {
+            None
+        };
+        Ok(signing::verify_file(
+            &path,
+            &keyring,
+            own_key.as_ref().map(|k| k.as_slice()),
+        )?)
+    })
+    .await
+    .map_err(|e| format!("Replay verification task failed: {}", e))?
+}
+
+/// This installation's public signing key, to share with teammates
+#[tauri::command]
+pub async fn get_signing_key(app: tauri::AppHandle) -> Result<PublicKeyInfo, String> {
+    let identity = SigningIdentity::load_or_create(&signing_key_path(&app)?)?;
+    Ok(identity.signer())
+}
+
+/// Public keys whose replay signatures are trusted
+#[tauri::command]
+pub async fn list_trusted_keys(app: tauri::AppHandle) -> Result<Vec<TrustedKey>, String> {
+    Ok(Keyring::load(&keyring_path(&app)?)?.keys)
+}
+
+/// Trust a teammate's public key
+///
+/// # Arguments
+/// * `name` - Who the key belongs to
+/// * `public_key` - Hex-encoded public key as shown by `get_signing_key` on their machine
+#[tauri::command]
+pub async fn trust_key(
+    app: tauri::AppHandle,
+    name: String,
+    public_key: String,
+) -> Result<TrustedKey, String> {
+    let path = keyring_path(&app)?;
+    let mut keyring = Keyring::load(&path)?;
+    let trusted = keyring.trust(&name, &public_key)?;
+    keyring.save(&path)?;
+    Ok(trusted)
+}
+
+/// Stop trusting a public key
+///
+/// # Returns
+/// False if no key with that fingerprint was trusted
+#[tauri::command]
+pub async fn untrust_key(app: tauri::AppHandle, fingerprint: String) -> Result<bool, String> {
+    let path = keyring_path(&app)?;
+    let mut keyring = Keyring::load(&path)?;
+    let removed = keyring.remove(&fingerprint);
+    if removed {
+        keyring.save(&path)?;
+    }
+    Ok(removed)
+}
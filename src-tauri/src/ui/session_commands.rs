@@ -0,0 +1,73 @@
+use crate::common::paths::{schemas_dir, sessions_dir, unknown_versions_log};
+use crate::protocol::archive::{self, SessionManifest};
+use crate::protocol::session::{self, RedecodeReport, UnknownSessionStatus};
+use crate::protocol::version::SchemaRegistry;
+use std::sync::Arc;
+
+/// Re-decode an archived capture session with the latest protocol schemas
+///
+/// Reruns the decoder over the session's raw and quarantined frames so captures
+/// made before a message type was understood benefit from newer schema files.
+#[tauri::command]
+pub async fn redecode_session(
+    app: tauri::AppHandle,
+    session_id: String,
+) -> Result<RedecodeReport, String> {
+    let sessions_dir = sessions_dir(&app)?;
+    let schemas_dir = schemas_dir(&app)?;
+
+    tokio::task::spawn_blocking(move || {
+        let dir = archive::session_dir(&sessions_dir, &session_id)?;
+        let registry = SchemaRegistry::with_user_schemas(&schemas_dir)?;
+        session::redecode_session(&dir, Arc::new(registry), |_, _| {})
+    })
+    .await
+    .map_err(|e| format!("Re-decode task failed: {}", e))?
+    .map_err(String::from)
+}
+
+/// List archived capture sessions, newest first
+#[tauri::command]
+pub async fn list_capture_sessions(app: tauri::AppHandle) -> Result<Vec<SessionManifest>, String> {
+    let sessions_dir = sessions_dir(&app)?;
+    archive::list_sessions(&sessions_dir).map_err(String::from)
+}
+
+/// List sessions captured from a client version without matching schemas
+///
+/// Each entry says whether matching schemas exist by now and whether its archive
+/// has already been re-decoded with them.
+#[tauri::command]
+pub async fn list_unknown_version_sessions(
+    app: tauri::AppHandle,
+) -> Result<Vec<UnknownSessionStatus>, String> {
+    let sessions_dir = sessions_dir(&app)?;
+    let schemas_dir = schemas_dir(&app)?;
+    let unknown_log = unknown_versions_log(&app)?;
+
+    tokio::task::spawn_blocking(move || {
+        let registry = SchemaRegistry::with_user_schemas(&schemas_dir)?;
+        session::unknown_session_status(&sessions_dir, &unknown_log, &registry)
+    })
+    .await
+    .map_err(|e| format!("Session listing task failed: {}", e))?
+    .map_err(String::from)
+}
+
+/// Re-decode every unknown-version session whose client version now has schemas
+#[tauri::command]
+pub async fn redecode_unknown_version_sessions(
+    app: tauri::AppHandle,
+) -> Result<Vec<RedecodeReport>, String> {
+    let sessions_dir = sessions_dir(&app)?;
+    let schemas_dir = schemas_dir(&app)?;
+    let unknown_log = unknown_versions_log(&app)?;
+
+    tokio::task::spawn_blocking(move || {
+        let registry = SchemaRegistry::with_user_schemas(&schemas_dir)?;
+        session::redecode_unknown_sessions(&sessions_dir, &unknown_log, Arc::new(registry))
+    })
+    .await
+    .map_err(|e| format!("Re-decode task failed: {}", e))?
+    .map_err(String::from)
+}
@@ -0,0 +1,215 @@
+//! Win rate analytics: Wilson intervals, breakdowns, trends and the matchup matrix
+
+use chrono::{DateTime, Duration, NaiveDate, TimeZone, Utc};
+use mtgo_replay_lib::analytics::winrate::{self, Dimension, StatUnit, TimeBucket};
+use mtgo_replay_lib::analytics::{wilson_interval, WinRate, CONFIDENCE_Z};
+use mtgo_replay_lib::game::lifecycle::Outcome;
+use mtgo_replay_lib::library::LibraryGame;
+
+fn start() -> DateTime<Utc> {
+    // A Wednesday
+    Utc.with_ymd_and_hms(2025, 3, 5, 18, 0, 0).unwrap()
+}
+
+/// One game of match `record_id`; the match result is set by `finish`
+fn game(record_id: &str, number: u8, on_play: bool, result: Outcome) -> LibraryGame {
+    LibraryGame {
+        record_id: record_id.to_string(),
+        format: "Modern".to_string(),
+        opponent: Some("bob".to_string()),
+        deck_name: Some("Burn".to_string()),
+        archetype: Some("Burn".to_string()),
+        opponent_archetype: Some("Tron".to_string()),
+        match_started_at: start(),
+        match_result: None,
+        game_number: number,
+        started_at: start() + Duration::minutes(i64::from(number) * 20),
+        on_play: Some(on_play),
+        mulligans: 0,
+        result: Some(result),
+    }
+}
+
+/// Games of a match with its result and attributes set
+fn finish(
+    mut games: Vec<LibraryGame>,
+    result: Option<Outcome>,
+    edit: impl Fn(&mut LibraryGame),
+) -> Vec<LibraryGame> {
+    for game in &mut games {
+        game.match_result = result;
+        edit(game);
+    }
+    games
+}
+
+/// Burn beats Tron 2-1, loses 0-2 to Tron a week later, and an untagged deck
+/// beats an unclassified opponent 2-0 a month later
+fn library() -> Vec<LibraryGame> {
+    let mut games = finish(
+        vec![
+            game("a", 1, true, Outcome::Loss),
+            game("a", 2, false, Outcome::Win),
+            game("a", 3, true, Outcome::Win),
+        ],
+        Some(Outcome::Win),
+        |_| {},
+    );
+    games.extend(finish(
+        vec![
+            game("b", 1, false, Outcome::Loss),
+            game("b", 2, true, Outcome::Loss),
+        ],
+        Some(Outcome::Loss),
+        |g| {
+            g.match_started_at += Duration::days(7);
+            g.started_at += Duration::days(7);
+        },
+    ));
+    games.extend(finish(
+        vec![
+            game("c", 1, true, Outcome::Win),
+            game("c", 2, false, Outcome::Win),
+        ],
+        Some(Outcome::Win),
+        |g| {
+            g.deck_name = None;
+            g.archetype = None;
+            g.opponent_archetype = None;
+            g.match_started_at += Duration::days(30);
+            g.started_at += Duration::days(30);
+        },
+    ));
+    games
+}
+
+fn close(actual: Option<f64>, expected: f64) -> bool {
+    actual.is_some_and(|value| (value - expected).abs() < 1e-4)
+}
+
+#[test]
+fn wilson_interval_matches_known_values() {
+    let (low, high) = wilson_interval(7, 10, CONFIDENCE_Z).unwrap();
+    assert!(
+        close(Some(low), 0.3968) && close(Some(high), 0.8922),
+        "{low} {high}"
+    );
+
+    // Stays within [0, 1] at the extremes
+    let (low, high) = wilson_interval(0, 5, CONFIDENCE_Z).unwrap();
+    assert_eq!(low, 0.0);
+    assert!(close(Some(high), 0.4345), "{high}");
+    let (_, high) = wilson_interval(5, 5, CONFIDENCE_Z).unwrap();
+    assert_eq!(high, 1.0);
+    assert_eq!(wilson_interval(0, 0, CONFIDENCE_Z), None);
+
+    // Draws are counted but not part of the rate
+    let rate = WinRate::from_outcomes([
+        Some(Outcome::Win),
+        Some(Outcome::Draw),
+        Some(Outcome::Loss),
+        None,
+    ]);
+    assert_eq!(
+        (rate.wins, rate.losses, rate.draws, rate.total()),
+        (1, 1, 1, 3)
+    );
+    assert_eq!(rate.win_rate, Some(0.5));
+    assert_eq!(WinRate::from_counts(0, 0, 2).win_rate, None);
+}
+
+#[test]
+fn breakdowns_count_games_or_matches() {
+    let games = library();
+
+    let by_deck = winrate::breakdown(&games, Dimension::Deck, StatUnit::Matches);
+    assert_eq!(by_deck.unit, StatUnit::Matches);
+    assert_eq!((by_deck.overall.wins, by_deck.overall.losses), (2, 1));
+    let rows: Vec<(&str, u32, u32)> = by_deck
+        .rows
+        .iter()
+        .map(|row| (row.label.as_str(), row.rate.wins, row.rate.losses))
+        .collect();
+    assert_eq!(rows, [("Burn", 1, 1), ("Untagged", 1, 0)]);
+
+    let by_opponent = winrate::breakdown(&games, Dimension::OpponentArchetype, StatUnit::Games);
+    let rows: Vec<(&str, u32, u32)> = by_opponent
+        .rows
+        .iter()
+        .map(|row| (row.label.as_str(), row.rate.wins, row.rate.losses))
+        .collect();
+    assert_eq!(rows, [("Tron", 2, 3), ("Unknown", 2, 0)]);
+
+    // Per-game dimensions count games even when matches are asked for
+    let play_draw = winrate::breakdown(&games, Dimension::PlayDraw, StatUnit::Matches);
+    assert_eq!(play_draw.unit, StatUnit::Games);
+    let rows: Vec<(&str, u32, u32)> = play_draw
+        .rows
+        .iter()
+        .map(|row| (row.label.as_str(), row.rate.wins, row.rate.losses))
+        .collect();
+    assert_eq!(rows, [("Draw", 2, 1), ("Play", 2, 2)]);
+
+    let boarding = winrate::breakdown(&games, Dimension::Sideboarding, StatUnit::Games);
+    let rows: Vec<(&str, u32, u32)> = boarding
+        .rows
+        .iter()
+        .map(|row| (row.label.as_str(), row.rate.wins, row.rate.losses))
+        .collect();
+    assert_eq!(rows, [("Post-board", 3, 1), ("Pre-board", 1, 2)]);
+    let by_game = winrate::breakdown(&games, Dimension::GameNumber, StatUnit::Games);
+    let labels: Vec<&str> = by_game.rows.iter().map(|row| row.label.as_str()).collect();
+    assert_eq!(labels, ["Game 1", "Game 2", "Game 3"]);
+}
+
+#[test]
+fn trends_bucket_by_utc_week_and_month() {
+    let games = library();
+    let day = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+
+    let weekly = winrate::trend(&games, TimeBucket::Week, StatUnit::Matches, None);
+    assert_eq!(weekly.len(), 1);
+    assert_eq!(weekly[0].label, "All");
+    let points: Vec<(NaiveDate, u32, u32)> = weekly[0]
+        .points
+        .iter()
+        .map(|p| (p.bucket, p.rate.wins, p.rate.losses))
+        .collect();
+    assert_eq!(
+        points,
+        [(day(3, 3), 1, 0), (day(3, 10), 0, 1), (day(3, 31), 1, 0)]
+    );
+
+    let monthly = winrate::trend(
+        &games,
+        TimeBucket::Month,
+        StatUnit::Games,
+        Some(Dimension::Deck),
+    );
+    let series: Vec<(&str, Vec<NaiveDate>)> = monthly
+        .iter()
+        .map(|s| {
+            (
+                s.label.as_str(),
+                s.points.iter().map(|p| p.bucket).collect(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        series,
+        [("Burn", vec![day(3, 1)]), ("Untagged", vec![day(4, 1)])]
+    );
+    assert_eq!(monthly[0].points[0].rate.total(), 5);
+}
+
+#[test]
+fn matchup_matrix_crosses_decks_with_opponent_archetypes() {
+    let matrix = winrate::matchup_matrix(&library(), Dimension::Archetype, StatUnit::Matches);
+    assert_eq!(matrix.decks, ["Burn", "Unknown"]);
+    assert_eq!(matrix.opponents, ["Tron", "Unknown"]);
+    assert_eq!((matrix.cells[0][0].wins, matrix.cells[0][0].losses), (1, 1));
+    assert_eq!(matrix.cells[0][1], WinRate::default());
+    assert_eq!(matrix.cells[1][1].wins, 1);
+    assert!(close(matrix.cells[0][0].win_rate, 0.5));
+    assert!(matrix.cells[0][0].ci_low.unwrap() < 0.5 && matrix.cells[0][0].ci_high.unwrap() > 0.5);
+}
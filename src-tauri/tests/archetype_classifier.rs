@@ -0,0 +1,231 @@
+//! Archetype classification: scoring deck lists against signature cards and
+//! reclassifying the library when the definitions change
+
+use mtgo_replay_lib::archetype::{self, ArchetypeDefinition, ArchetypeDefinitions, SignatureCard};
+use mtgo_replay_lib::cards::CardDatabase;
+use mtgo_replay_lib::common::error::ArchetypeError;
+use mtgo_replay_lib::library::{LibraryQuery, ReplayLibrary};
+use std::path::PathBuf;
+
+fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/replay_v1.mtgoreplay")
+}
+
+fn archetype(name: &str, formats: &[&str], cards: &[(&str, f64)]) -> ArchetypeDefinition {
+    ArchetypeDefinition {
+        name: name.to_string(),
+        formats: formats.iter().map(|f| f.to_string()).collect(),
+        cards: cards
+            .iter()
+            .map(|(name, weight)| SignatureCard {
+                name: name.to_string(),
+                weight: *weight,
+            })
+            .collect(),
+        min_score: 1.0,
+    }
+}
+
+fn definitions() -> ArchetypeDefinitions {
+    ArchetypeDefinitions {
+        archetypes: vec![
+            archetype(
+                "Burn",
+                &[],
+                &[("Goblin Guide", 2.0), ("Lightning Bolt", 1.0)],
+            ),
+            archetype(
+                "Prowess",
+                &["Modern", "Pioneer"],
+                &[("Monastery Swiftspear", 1.0), ("Lightning Bolt", 0.5)],
+            ),
+            archetype(
+                "Living End",
+                &["Modern"],
+                &[
+                    ("Living End", 3.0),
+                    ("Violent Outburst", 1.0),
+                    ("Goblin Guide", -4.0),
+                ],
+            ),
+            archetype(
+                "Zoo",
+                &["Legacy"],
+                &[("Wild Nacatl", 1.0), ("Fire // Ice", 1.0)],
+            ),
+        ],
+        ..ArchetypeDefinitions::default()
+    }
+}
+
+#[test]
+fn decks_are_classified_with_confidence_or_left_unknown() {
+    let definitions = definitions();
+
+    let burn = definitions.classify(
+        "Modern",
+        ["Goblin Guide", "Lightning Bolt", "Monastery Swiftspear"],
+    );
+    assert_eq!(burn.archetype.as_deref(), Some("Burn"));
+    let scores: Vec<(&str, f64)> = burn
+        .scores
+        .iter()
+        .map(|s| (s.name.as_str(), s.score))
+        .collect();
+    assert_eq!(scores, [("Burn", 3.0), ("Prowess", 1.5)]);
+    assert!((burn.confidence - 2.0 / 3.0).abs() < 1e-9);
+
+    // Prowess leads 1.5 to 1: enough by default, too close to call with a
+    // stricter threshold, which leaves it unknown with the scores kept
+    let cards = ["Lightning Bolt", "Monastery Swiftspear"];
+    let prowess = definitions.classify("Modern", cards);
+    assert_eq!(
+        (prowess.archetype.as_deref(), prowess.confidence),
+        (Some("Prowess"), 0.6)
+    );
+    let strict = ArchetypeDefinitions {
+        min_confidence: 0.7,
+        ..definitions.clone()
+    };
+    let close = strict.classify("Modern", cards);
+    assert_eq!(close.archetype, None);
+    assert_eq!(close.scores.len(), 2);
+
+    // One signature card is enough when no other archetype scores
+    let partial = definitions.classify("Legacy", ["Lightning Bolt", "Brainstorm"]);
+    assert_eq!(
+        (partial.archetype.as_deref(), partial.confidence),
+        (Some("Burn"), 1.0)
+    );
+    // A list below every minimum score is unknown
+    let nothing = definitions.classify("Modern", ["Brainstorm"]);
+    assert_eq!((nothing.archetype, nothing.confidence), (None, 0.0));
+
+    // Prowess is not a Legacy archetype; format names are case-insensitive
+    let legacy = definitions.classify(
+        "legacy",
+        ["Monastery Swiftspear", "Violent Outburst", "Living End"],
+    );
+    assert_eq!(legacy.archetype, None);
+    let modern = definitions.classify("modern", ["Violent Outburst", "Living End"]);
+    assert_eq!(modern.archetype.as_deref(), Some("Living End"));
+
+    // Negative weights rule an archetype out
+    let mixed = definitions.classify("Modern", ["Living End", "Violent Outburst", "Goblin Guide"]);
+    assert_eq!(mixed.archetype.as_deref(), Some("Burn"));
+    assert!(!mixed.scores.iter().any(|s| s.name == "Living End"));
+
+    // Split cards match by their full name or front face
+    let zoo = definitions.classify("Legacy", ["wild nacatl", "Fire"]);
+    assert_eq!(zoo.archetype.as_deref(), Some("Zoo"));
+}
+
+#[test]
+fn definitions_are_validated_saved_and_versioned() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("archetypes.json");
+    assert_eq!(
+        ArchetypeDefinitions::load(&path).unwrap(),
+        ArchetypeDefinitions::default()
+    );
+
+    let definitions = definitions();
+    definitions.save(&path).unwrap();
+    let loaded = ArchetypeDefinitions::load(&path).unwrap();
+    assert_eq!(loaded, definitions);
+    assert_eq!(loaded.version(), definitions.version());
+
+    let mut changed = definitions.clone();
+    changed.archetypes[0].cards[1].weight = 1.5;
+    assert_ne!(changed.version(), definitions.version());
+
+    let mut duplicate = definitions.clone();
+    duplicate.archetypes[1].name = "burn".to_string();
+    assert!(matches!(
+        duplicate.validate(),
+        Err(ArchetypeError::InvalidDefinitions(_))
+    ));
+    assert!(duplicate.save(&path).is_err());
+    assert_eq!(ArchetypeDefinitions::load(&path).unwrap(), definitions);
+
+    let confident = ArchetypeDefinitions {
+        min_confidence: 1.5,
+        ..definitions.clone()
+    };
+    assert!(confident.validate().is_err());
+    std::fs::write(&path, "{\"archetypes\": [{\"name\": \"Burn\"}]}").unwrap();
+    assert!(matches!(
+        ArchetypeDefinitions::load(&path),
+        Err(ArchetypeError::InvalidDefinitions(_))
+    ));
+}
+
+#[test]
+fn library_is_reclassified_when_definitions_change() {
+    let dir = tempfile::tempdir().unwrap();
+    let scryfall = dir.path().join("cards.json");
+    std::fs::write(
+        &scryfall,
+        r#"[
+            {"name": "Goblin Guide", "mtgo_id": 101, "type_line": "Creature — Goblin Scout"},
+            {"name": "Lightning Bolt", "mtgo_id": 102, "type_line": "Instant"},
+            {"name": "Monastery Swiftspear", "mtgo_id": 103, "type_line": "Creature — Human Monk"}
+        ]"#,
+    )
+    .unwrap();
+    let mut cards = CardDatabase::open_in_memory().unwrap();
+    cards.import_scryfall(&scryfall).unwrap();
+
+    // The golden replay registers cards 101-103 and reveals none of the opponent's
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    library.index_replay(&golden_path(), None).unwrap();
+    let definitions = definitions();
+    let report = archetype::reclassify(&mut library, &cards, &definitions, false).unwrap();
+    assert_eq!(
+        (
+            report.classified,
+            report.decks_known,
+            report.opponents_known
+        ),
+        (1, 1, 0)
+    );
+    let matches = library.query(&LibraryQuery::default()).unwrap();
+    let record = &matches[0];
+    assert_eq!(record.archetype.as_deref(), Some("Burn"));
+    assert!((record.archetype_confidence.unwrap() - 2.0 / 3.0).abs() < 1e-9);
+    assert_eq!(
+        (
+            record.opponent_archetype.as_deref(),
+            record.opponent_archetype_confidence
+        ),
+        (None, None)
+    );
+
+    // Archetypes are searchable like the rest of the library
+    let burn = LibraryQuery {
+        archetype: Some("burn".to_string()),
+        ..LibraryQuery::default()
+    };
+    assert_eq!(library.query(&burn).unwrap().len(), 1);
+
+    // Matches classified with the same definitions are skipped unless asked for
+    let again = archetype::reclassify(&mut library, &cards, &definitions, false).unwrap();
+    assert_eq!(again.classified, 0);
+    assert_eq!(
+        archetype::reclassify(&mut library, &cards, &definitions, true)
+            .unwrap()
+            .classified,
+        1
+    );
+
+    // Editing the definitions reclassifies: Burn loses Goblin Guide and ties Prowess
+    let mut edited = definitions.clone();
+    edited.archetypes[0].cards.remove(0);
+    edited.archetypes[0].cards[0].weight = 1.5;
+    let report = archetype::reclassify(&mut library, &cards, &edited, false).unwrap();
+    assert_eq!((report.classified, report.decks_known), (1, 0));
+    let matches = library.query(&LibraryQuery::default()).unwrap();
+    let record = &matches[0];
+    assert_eq!(record.archetype, None);
+    assert_eq!(record.archetype_confidence, Some(0.5));
+}
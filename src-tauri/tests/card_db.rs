@@ -0,0 +1,257 @@
+//! Card database: Scryfall bulk and MTGO catalog imports, lookups and search
+
+use mtgo_replay_lib::cards::catalog::{read_catalog, CatalogEntry};
+use mtgo_replay_lib::cards::{CardDatabase, CardLookup, CardQuery, CardSource};
+use mtgo_replay_lib::common::error::CardError;
+use std::path::PathBuf;
+
+fn card_data(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/card_data")
+        .join(name)
+}
+
+fn ids(cards: &[mtgo_replay_lib::cards::Card]) -> Vec<u32> {
+    cards.iter().map(|card| card.mtgo_id).collect()
+}
+
+/// Both fixture imports, Scryfall first as the app does it
+fn imported() -> CardDatabase {
+    let mut db = CardDatabase::open_in_memory().unwrap();
+    db.import_scryfall(&card_data("scryfall.json")).unwrap();
+    db.import_mtgo_catalog(&card_data("catalog")).unwrap();
+    db
+}
+
+#[test]
+fn catalog_objects_are_resolved_through_string_tables() {
+    let catalog = read_catalog(&card_data("catalog")).unwrap();
+    // The booster, the object without properties and catalog id 0
+    assert_eq!(catalog.skipped, 3);
+    let entries: Vec<_> = catalog
+        .entries
+        .iter()
+        .map(|e| (e.mtgo_id, e.foil, e.name.as_str()))
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            (5001, false, "Lightning Bolt"),
+            (5002, true, "Lightning Bolt"),
+            (5003, false, "Fire/Ice"),
+            (1001, false, "Lightning Bolt"),
+            (5004, true, "Preview & Promo"),
+        ]
+    );
+    assert_eq!(
+        catalog.entries[0],
+        CatalogEntry {
+            mtgo_id: 5001,
+            foil: false,
+            name: "Lightning Bolt".to_string(),
+            set_code: Some("mh3".to_string()),
+            set_name: Some("Modern Horizons 3".to_string()),
+            collector_number: Some("123/303".to_string()),
+            mana_cost: Some("{R}".to_string()),
+            rarity: Some("C".to_string()),
+        }
+    );
+    assert_eq!(catalog.entries[1].set_name, None);
+
+    let broken = card_data("broken");
+    match read_catalog(&broken) {
+        Err(CardError::InvalidCatalog { path, .. }) => assert!(path.ends_with("client_BAD.xml")),
+        other => panic!("broken catalog was read: {:?}", other),
+    }
+    assert!(matches!(
+        read_catalog(&card_data("missing")),
+        Err(CardError::Io(_))
+    ));
+}
+
+#[test]
+fn scryfall_printings_are_imported_per_mtgo_id() {
+    let mut db = CardDatabase::open_in_memory().unwrap();
+    let report = db.import_scryfall(&card_data("scryfall.json")).unwrap();
+    assert_eq!(
+        (
+            report.records,
+            report.imported,
+            report.skipped,
+            report.linked
+        ),
+        (4, 4, 1, 0)
+    );
+
+    // The foil id is a second printing of the same card
+    let regular = db.get(1001).unwrap().unwrap();
+    let foil = db.get(1002).unwrap().unwrap();
+    assert!(!regular.foil && foil.foil);
+    assert_eq!(
+        (foil.name.as_str(), foil.oracle_id.clone()),
+        ("Lightning Bolt", regular.oracle_id.clone())
+    );
+    assert_eq!(regular.colors, ["R"]);
+    assert_eq!(regular.source, CardSource::Scryfall);
+
+    // Values missing on the card come from its faces, joined like Scryfall joins names
+    let delver = db.get(2001).unwrap().unwrap();
+    assert_eq!(delver.mana_cost.as_deref(), Some("{U}"));
+    assert_eq!(
+        delver.type_line.as_deref(),
+        Some("Creature — Human Wizard // Creature — Human Insect")
+    );
+    assert!(delver
+        .oracle_text
+        .unwrap()
+        .ends_with("top card of your library. // Flying"));
+    assert_eq!(delver.colors, ["U"]);
+    let fire = db.get(3001).unwrap().unwrap();
+    assert_eq!(fire.mana_cost.as_deref(), Some("{1}{R} // {1}{U}"));
+    assert!(fire
+        .oracle_text
+        .unwrap()
+        .ends_with(" // Tap target permanent.\nDraw a card."));
+    assert_eq!(db.get(0).unwrap(), None);
+
+    // A failed import leaves what was there
+    let dir = tempfile::tempdir().unwrap();
+    let truncated = dir.path().join("truncated.json");
+    std::fs::write(
+        &truncated,
+        r#"[{"name": "Shock", "mtgo_id": 1001}, {"name": "#,
+    )
+    .unwrap();
+    assert!(matches!(
+        db.import_scryfall(&truncated),
+        Err(CardError::InvalidScryfall(_))
+    ));
+    let not_cards = dir.path().join("error.json");
+    std::fs::write(&not_cards, r#"{"object": "error"}"#).unwrap();
+    assert!(matches!(
+        db.import_scryfall(&not_cards),
+        Err(CardError::InvalidScryfall(_))
+    ));
+    assert_eq!(db.get(1001).unwrap().unwrap(), regular);
+}
+
+#[test]
+fn catalog_printings_are_added_and_linked_to_oracle_data() {
+    let mut db = CardDatabase::open_in_memory().unwrap();
+    db.import_scryfall(&card_data("scryfall.json")).unwrap();
+    let report = db.import_mtgo_catalog(&card_data("catalog")).unwrap();
+    // Id 1001 is Scryfall's and is left alone
+    assert_eq!(
+        (
+            report.records,
+            report.imported,
+            report.skipped,
+            report.linked
+        ),
+        (8, 4, 3, 3)
+    );
+    assert_eq!(
+        db.get(1001).unwrap().unwrap().set_code.as_deref(),
+        Some("m11")
+    );
+
+    // MTGO's "Fire/Ice" is Scryfall's "Fire // Ice"
+    let fire = db.get(5003).unwrap().unwrap();
+    assert_eq!(
+        (fire.name.as_str(), fire.source),
+        ("Fire // Ice", CardSource::MtgoCatalog)
+    );
+    assert_eq!(fire.type_line.as_deref(), Some("Instant // Instant"));
+    assert_eq!(fire.set_code.as_deref(), Some("mh3"));
+    // The printing's own mana cost is kept
+    let bolt = db.get(5001).unwrap().unwrap();
+    assert_eq!(
+        (bolt.mana_cost.as_deref(), bolt.cmc),
+        (Some("{R}"), Some(1.0))
+    );
+    let promo = db.get(5004).unwrap().unwrap();
+    assert_eq!(
+        (promo.foil, promo.oracle_id, promo.type_line),
+        (true, None, None)
+    );
+
+    let status = db.status().unwrap();
+    assert_eq!(
+        (
+            status.cards,
+            status.scryfall_cards,
+            status.catalog_cards,
+            status.unlinked_cards
+        ),
+        (8, 4, 4, 1)
+    );
+    assert!(status.scryfall_imported_at.is_some() && status.catalog_imported_at.is_some());
+
+    assert!(matches!(
+        db.import_mtgo_catalog(&card_data("broken")),
+        Err(CardError::InvalidCatalog { .. })
+    ));
+    assert_eq!(db.status().unwrap().cards, 8);
+}
+
+#[test]
+fn cards_are_looked_up_and_searched() {
+    let db = imported();
+    assert_eq!(
+        ids(&db.find_by_name("LIGHTNING BOLT").unwrap()),
+        [1001, 5001, 1002, 5002]
+    );
+    assert_eq!(
+        db.card_name(2001).as_deref(),
+        Some("Delver of Secrets // Insectile Aberration")
+    );
+    assert_eq!(db.card(9999), None);
+    let many = db.get_many(&[1001, 9999, 1001, 3001]).unwrap();
+    assert_eq!(many.len(), 2);
+    assert!(many.contains_key(&1001) && many.contains_key(&3001));
+
+    let search = |query: CardQuery| ids(&db.search(&query).unwrap());
+    assert_eq!(
+        search(CardQuery {
+            name: Some("bolt".to_string()),
+            ..CardQuery::default()
+        }),
+        [1001, 5001]
+    );
+    assert_eq!(
+        search(CardQuery {
+            name: Some("bolt".to_string()),
+            include_foil: true,
+            ..CardQuery::default()
+        }),
+        [1001, 5001, 1002, 5002]
+    );
+    assert_eq!(
+        search(CardQuery {
+            type_line: Some("insect".to_string()),
+            ..CardQuery::default()
+        }),
+        [2001]
+    );
+    assert_eq!(
+        search(CardQuery {
+            text: Some("draw a card".to_string()),
+            ..CardQuery::default()
+        }),
+        [3001, 5003]
+    );
+    assert_eq!(
+        search(CardQuery {
+            set_code: Some("MH3".to_string()),
+            limit: Some(2),
+            ..CardQuery::default()
+        }),
+        [5003, 5001]
+    );
+    // Wildcards in the filter are matched literally
+    assert!(search(CardQuery {
+        name: Some("%".to_string()),
+        ..CardQuery::default()
+    })
+    .is_empty());
+}
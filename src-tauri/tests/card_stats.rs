@@ -0,0 +1,185 @@
+//! Per-card performance: indexing how cards were drawn and cast, and the statistics
+
+mod common;
+
+use chrono::Duration;
+use common::{golden_path, moved, write_replay};
+use mtgo_replay_lib::analytics::cards::card_stats;
+use mtgo_replay_lib::game::event::GameEventKind;
+use mtgo_replay_lib::game::lifecycle::Outcome;
+use mtgo_replay_lib::game::model::Zone;
+use mtgo_replay_lib::library::{LibraryCardGame, LibraryQuery, ReplayLibrary};
+use mtgo_replay_lib::replay::{ReplayReader, ReplayWriter};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::BufWriter;
+
+fn card_game(record_id: &str, card_id: u32, result: Outcome) -> LibraryCardGame {
+    LibraryCardGame {
+        record_id: record_id.to_string(),
+        format: "Modern".to_string(),
+        deck_name: Some("Burn".to_string()),
+        game_number: 1,
+        on_play: Some(true),
+        result: Some(result),
+        card_id,
+        in_deck: 4,
+        opening_hand: false,
+        drawn: false,
+        casts: 0,
+        first_cast_turn: None,
+    }
+}
+
+#[test]
+fn drawn_and_cast_cards_are_indexed_with_the_deck() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut golden = ReplayReader::open(&golden_path()).unwrap();
+    let won = golden.match_record().unwrap().unwrap();
+    let mut lost = won.clone();
+    lost.id = 9002;
+    lost.format = "Legacy".to_string();
+    lost.started_at += Duration::days(1);
+    lost.games[0].result = Some(Outcome::Loss);
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    library
+        .index_replay(&write_replay(dir.path(), &won), None)
+        .unwrap();
+    library
+        .index_replay(&write_replay(dir.path(), &lost), None)
+        .unwrap();
+
+    // Card 101 is drawn and cast on turn 1; the rest of the maindeck stays unseen
+    let modern = library
+        .card_games(&LibraryQuery {
+            format: Some("modern".to_string()),
+            ..LibraryQuery::default()
+        })
+        .unwrap();
+    let rows: Vec<(u32, u32, bool, bool, u32, Option<u32>)> = modern
+        .iter()
+        .map(|c| {
+            (
+                c.card_id,
+                c.in_deck,
+                c.opening_hand,
+                c.drawn,
+                c.casts,
+                c.first_cast_turn,
+            )
+        })
+        .collect();
+    assert_eq!(
+        rows,
+        [
+            (101, 2, false, true, 1, Some(1)),
+            (102, 1, false, false, 0, None),
+            (103, 1, false, false, 0, None),
+        ]
+    );
+
+    let all = library.card_games(&LibraryQuery::default()).unwrap();
+    let names = HashMap::from([(101, "Goblin Guide".to_string())]);
+    let stats = card_stats(&all, &names);
+    assert_eq!(stats[0].name.as_deref(), Some("Goblin Guide"));
+    assert_eq!((stats[0].in_hand.wins, stats[0].in_hand.losses), (1, 1));
+    assert_eq!((stats[0].drawn.wins, stats[0].opening_hand.total()), (1, 0));
+    assert_eq!((stats[0].casts, stats[0].games_cast), (2, 2));
+    assert_eq!(stats[0].avg_first_cast_turn, Some(1.0));
+    assert_eq!(stats[0].games_in_deck, 2);
+    let unseen = stats.iter().find(|s| s.card_ids == [102]).unwrap();
+    assert_eq!(unseen.name, None);
+    assert_eq!((unseen.not_seen.wins, unseen.not_seen.losses), (1, 1));
+    assert_eq!(unseen.in_hand.total(), 0);
+}
+
+#[test]
+fn cards_returned_to_hand_are_not_counted_as_drawn() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut golden = ReplayReader::open(&golden_path()).unwrap();
+    let record = golden.match_record().unwrap().unwrap();
+    let mut events = golden.events().unwrap();
+    // Before the game ends, 102 is bounced, 103 regrown and then exiled and returned
+    let mut end = events.pop().unwrap();
+    let returns = [
+        moved(8, Some(102), 1, Some(Zone::Battlefield), Zone::Hand),
+        moved(9, Some(103), 1, Some(Zone::Graveyard), Zone::Hand),
+        moved(9, Some(103), 1, Some(Zone::Hand), Zone::Exile),
+        moved(9, Some(103), 1, Some(Zone::Exile), Zone::Hand),
+    ];
+    for kind in returns {
+        let mut event = end.clone();
+        event.seq = events.len() as u64;
+        event.kind = kind;
+        events.push(event);
+    }
+    end.seq = events.len() as u64;
+    events.push(end);
+    assert!(matches!(
+        events.last().unwrap().kind,
+        GameEventKind::GameEnded { .. }
+    ));
+
+    let path = dir
+        .path()
+        .join(format!("{}.mtgoreplay", record.record_id()));
+    let file = BufWriter::new(File::create(&path).unwrap());
+    let mut writer = ReplayWriter::new(file, golden.header()).unwrap();
+    for event in &events {
+        writer.write_event(event).unwrap();
+    }
+    writer.write_match(&record).unwrap();
+    writer.finish().unwrap();
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    library.index_replay(&path, None).unwrap();
+
+    let drawn: Vec<(u32, bool)> = library
+        .card_games(&LibraryQuery::default())
+        .unwrap()
+        .iter()
+        .map(|c| (c.card_id, c.drawn))
+        .collect();
+    assert_eq!(drawn, [(101, true), (102, false), (103, false)]);
+}
+
+#[test]
+fn printings_are_merged_and_improvement_when_drawn_is_computed() {
+    let mut cards = Vec::new();
+    // Game a: both printings of the card in hand, won
+    let mut opening = card_game("a", 1, Outcome::Win);
+    opening.opening_hand = true;
+    let mut foil = card_game("a", 2, Outcome::Win);
+    foil.drawn = true;
+    foil.casts = 1;
+    foil.first_cast_turn = Some(3);
+    cards.extend([opening, foil]);
+    // Game b: drawn and cast on turn 2, won; game c and d: never seen, one win one loss
+    let mut drawn = card_game("b", 1, Outcome::Win);
+    drawn.drawn = true;
+    drawn.casts = 2;
+    drawn.first_cast_turn = Some(2);
+    cards.push(drawn);
+    cards.push(card_game("c", 1, Outcome::Win));
+    cards.push(card_game("d", 2, Outcome::Loss));
+
+    let names = HashMap::from([
+        (1, "Lightning Bolt".to_string()),
+        (2, "Lightning Bolt".to_string()),
+    ]);
+    let stats = card_stats(&cards, &names);
+    assert_eq!(stats.len(), 1);
+    let bolt = &stats[0];
+    assert_eq!(bolt.card_ids, [1, 2]);
+    assert_eq!(bolt.games_in_deck, 4);
+    // One game per game, however many printings took part
+    assert_eq!(bolt.in_hand.total(), 2);
+    assert_eq!(bolt.opening_hand.total(), 1);
+    assert_eq!(bolt.drawn.total(), 1);
+    assert_eq!((bolt.not_seen.wins, bolt.not_seen.losses), (1, 1));
+    assert_eq!(bolt.improvement_when_drawn, Some(0.5));
+    assert_eq!((bolt.casts, bolt.games_cast), (3, 2));
+    assert_eq!(bolt.avg_first_cast_turn, Some(2.5));
+
+    // Without names each printing stands alone
+    assert_eq!(card_stats(&cards, &HashMap::new()).len(), 2);
+}
@@ -0,0 +1,264 @@
+//! Shared support for the integration suites
+//!
+//! The parser property suites are seeded from the fuzz corpus (`fuzz/corpus/<target>/`),
+//! so frames captured from real MTGO sessions exercise the same code paths in
+//! `cargo test` as under `cargo fuzz`. The other suites share the frame and replay
+//! fixtures below. None of this needs the capture driver.
+#![allow(dead_code)]
+
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use mtgo_replay_lib::game::event::GameEventKind;
+use mtgo_replay_lib::game::lifecycle::Match;
+use mtgo_replay_lib::game::model::Zone;
+use mtgo_replay_lib::protocol::frame::{Direction, Frame};
+use mtgo_replay_lib::protocol::packet::FlowKey;
+use mtgo_replay_lib::protocol::schema::{FieldValue, SchemaSet};
+use mtgo_replay_lib::replay::{ReplayReader, ReplayWriter};
+use proptest::prelude::*;
+use proptest::sample::Index;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::fs::File;
+use std::io::BufWriter;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+/// TCP flag bits used by the packet builder
+pub const TCP_FIN: u8 = 0x01;
+pub const TCP_SYN: u8 = 0x02;
+pub const TCP_RST: u8 = 0x04;
+pub const TCP_PSH: u8 = 0x08;
+pub const TCP_ACK: u8 = 0x10;
+
+/// Allocation ceiling for parsing an input of `len` bytes
+///
+/// Parsers may copy and index their input, but an allocation that is not roughly
+/// proportional to the input means a length field was trusted.
+pub fn allocation_bound(len: usize) -> usize {
+    64 * len + 64 * 1024
+}
+
+thread_local! {
+    static LARGEST_ALLOCATION: Cell<usize> = const { Cell::new(0) };
+}
+
+/// Global allocator that records the largest single allocation made on each thread
+///
+/// Each test binary installs it with
+/// `#[global_allocator] static ALLOC: common::TrackingAllocator = common::TrackingAllocator;`
+pub struct TrackingAllocator;
+
+unsafe impl GlobalAlloc for TrackingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        record(layout.size());
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(new_size);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+fn record(size: usize) {
+    // try_with: the thread-local may already be gone while a thread shuts down
+    let _ = LARGEST_ALLOCATION.try_with(|largest| largest.set(largest.get().max(size)));
+}
+
+/// Run `f` and return its result with the largest allocation it made on this thread
+pub fn largest_allocation<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    LARGEST_ALLOCATION.with(|largest| largest.set(0));
+    let result = f();
+    (result, LARGEST_ALLOCATION.with(|largest| largest.get()))
+}
+
+/// Load every seed file of a fuzz target's corpus
+pub fn load_corpus(target: &str) -> Vec<Vec<u8>> {
+    let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("fuzz")
+        .join("corpus")
+        .join(target);
+
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.is_file() && !matches!(p.extension(), Some(ext) if ext == "md"))
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|p| std::fs::read(p).ok())
+        .collect()
+}
+
+/// Inputs for a target: corpus seeds with random byte edits and truncation, mixed
+/// with fully random data
+pub fn seeded_inputs(target: &str) -> BoxedStrategy<Vec<u8>> {
+    let random = prop::collection::vec(any::<u8>(), 0..512);
+    let corpus = load_corpus(target);
+    if corpus.is_empty() {
+        return random.boxed();
+    }
+
+    let mutated = (
+        prop::sample::select(corpus),
+        prop::collection::vec((any::<Index>(), any::<u8>()), 0..8),
+        prop::option::of(any::<Index>()),
+    )
+        .prop_map(|(mut seed, edits, cut)| {
+            if !seed.is_empty() {
+                for (at, mask) in edits {
+                    let i = at.index(seed.len());
+                    seed[i] ^= mask;
+                }
+                if let Some(cut) = cut {
+                    seed.truncate(cut.index(seed.len() + 1));
+                }
+            }
+            seed
+        });
+
+    prop_oneof![3 => mutated, 1 => random].boxed()
+}
+
+/// Build a raw IPv4 or IPv6 packet carrying a TCP segment
+///
+/// Both addresses must be of the same family.
+pub fn build_packet(
+    src: (IpAddr, u16),
+    dst: (IpAddr, u16),
+    seq: u32,
+    flags: u8,
+    payload: &[u8],
+) -> Vec<u8> {
+    let mut tcp = Vec::with_capacity(20 + payload.len());
+    tcp.extend_from_slice(&src.1.to_be_bytes());
+    tcp.extend_from_slice(&dst.1.to_be_bytes());
+    tcp.extend_from_slice(&seq.to_be_bytes());
+    tcp.extend_from_slice(&0u32.to_be_bytes()); // ack
+    tcp.push(5 << 4); // data offset: 5 words, no options
+    tcp.push(flags);
+    tcp.extend_from_slice(&65535u16.to_be_bytes()); // window
+    tcp.extend_from_slice(&[0, 0, 0, 0]); // checksum, urgent pointer
+    tcp.extend_from_slice(payload);
+
+    match (src.0, dst.0) {
+        (IpAddr::V4(s), IpAddr::V4(d)) => {
+            let total = (20 + tcp.len()) as u16;
+            let mut packet = vec![0x45, 0];
+            packet.extend_from_slice(&total.to_be_bytes());
+            packet.extend_from_slice(&[0, 0, 0x40, 0]); // id, don't fragment
+            packet.extend_from_slice(&[64, 6, 0, 0]); // ttl, tcp, checksum
+            packet.extend_from_slice(&s.octets());
+            packet.extend_from_slice(&d.octets());
+            packet.extend_from_slice(&tcp);
+            packet
+        }
+        (IpAddr::V6(s), IpAddr::V6(d)) => {
+            let mut packet = vec![0x60, 0, 0, 0];
+            packet.extend_from_slice(&(tcp.len() as u16).to_be_bytes());
+            packet.extend_from_slice(&[6, 64]); // next header tcp, hop limit
+            packet.extend_from_slice(&s.octets());
+            packet.extend_from_slice(&d.octets());
+            packet.extend_from_slice(&tcp);
+            packet
+        }
+        _ => panic!("mixed address families"),
+    }
+}
+
+/// Split `data` at the given cut points into (offset, bytes) segments
+pub fn split_at_cuts(data: &[u8], cuts: &[Index]) -> Vec<(usize, Vec<u8>)> {
+    let mut points: Vec<usize> = cuts.iter().map(|c| c.index(data.len() + 1)).collect();
+    points.push(0);
+    points.push(data.len());
+    points.sort_unstable();
+    points.dedup();
+
+    points
+        .windows(2)
+        .map(|w| (w[0], data[w[0]..w[1]].to_vec()))
+        .collect()
+}
+
+/// Strategy for a permutation of `0..len`
+pub fn shuffled(len: usize) -> impl Strategy<Value = Vec<usize>> {
+    Just((0..len).collect::<Vec<usize>>()).prop_shuffle()
+}
+
+/// A server-to-client frame of the named message, encoded with a schema set
+///
+/// Frames are a second apart, so `index` also orders their timestamps.
+pub fn frame(index: u64, set: &SchemaSet, name: &str, values: Vec<FieldValue>) -> Frame {
+    let message = set.message_by_name(name).unwrap();
+    Frame {
+        flow: FlowKey {
+            src_addr: [10, 0, 0, 1].into(),
+            src_port: 4724,
+            dst_addr: [192, 168, 1, 10].into(),
+            dst_port: 50123,
+        },
+        direction: Direction::ServerToClient,
+        timestamp: Utc.with_ymd_and_hms(2025, 3, 3, 20, 0, 0).unwrap()
+            + Duration::seconds(index as i64),
+        index,
+        type_id: message.type_id,
+        payload: message.encode(&values).unwrap(),
+    }
+}
+
+/// The golden replay: alice's Modern match 9001, eleven events and a snapshot
+pub fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/replay_v1.mtgoreplay")
+}
+
+/// Write a replay with the golden events and header and the given match record,
+/// named after the record as the capture names it
+pub fn write_replay(dir: &Path, record: &Match) -> PathBuf {
+    let mut golden = ReplayReader::open(&golden_path()).unwrap();
+    let path = dir.join(format!("{}.mtgoreplay", record.record_id()));
+    let file = BufWriter::new(File::create(&path).unwrap());
+    let mut writer = ReplayWriter::new(file, golden.header()).unwrap();
+    for event in golden.events().unwrap() {
+        writer.write_event(&event).unwrap();
+    }
+    writer.write_match(record).unwrap();
+    writer.finish().unwrap();
+    path
+}
+
+/// Event time `seconds` into the fixtures' match
+pub fn at(seconds: i64) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap() + Duration::seconds(seconds)
+}
+
+/// An object of a player's moving between zones, controlled by its owner
+pub fn moved(
+    object: u32,
+    card_id: Option<u32>,
+    owner: u32,
+    from: Option<Zone>,
+    to: Zone,
+) -> GameEventKind {
+    GameEventKind::ZoneChanged {
+        object,
+        card_id,
+        owner,
+        controller: owner,
+        from,
+        to,
+    }
+}
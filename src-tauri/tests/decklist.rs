@@ -0,0 +1,234 @@
+//! Deck list files: MTGO .dek reading and writing, and plain and Arena text lists
+
+use mtgo_replay_lib::cards::{Card, CardSource};
+use mtgo_replay_lib::common::error::DeckError;
+use mtgo_replay_lib::decklist::dek::read_dek;
+use mtgo_replay_lib::decklist::{export, DeckFormat};
+use mtgo_replay_lib::game::deck::Deck;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+const BOLT: u32 = 101;
+const BOLT_FOIL: u32 = 102;
+const FIRE_ICE: u32 = 103;
+const BONECRUSHER: u32 = 104;
+const DELVER: u32 = 105;
+const SECRET_LAIR: u32 = 201;
+const LURRUS: u32 = 202;
+const UNKNOWN: u32 = 999;
+
+fn card(mtgo_id: u32, name: &str, type_line: &str) -> Card {
+    Card {
+        mtgo_id,
+        foil: false,
+        name: name.to_string(),
+        mana_cost: None,
+        cmc: None,
+        type_line: Some(type_line.to_string()),
+        oracle_text: None,
+        colors: Vec::new(),
+        set_code: None,
+        set_name: None,
+        collector_number: None,
+        rarity: None,
+        oracle_id: None,
+        source: CardSource::Scryfall,
+    }
+}
+
+fn cards() -> HashMap<u32, Card> {
+    [
+        card(BOLT, "Lightning Bolt", "Instant"),
+        card(BOLT_FOIL, "Lightning Bolt", "Instant"),
+        card(FIRE_ICE, "Fire // Ice", "Instant // Instant"),
+        card(
+            BONECRUSHER,
+            "Bonecrusher Giant // Stomp",
+            "Creature — Giant // Instant — Adventure",
+        ),
+        card(
+            DELVER,
+            "Delver of Secrets // Insectile Aberration",
+            "Creature — Human // Creature — Insect",
+        ),
+        card(SECRET_LAIR, "R&D's \"Secret\" <Lair>", "Land"),
+        card(
+            LURRUS,
+            "Lurrus of the Dream-Den",
+            "Legendary Creature — Cat Nightmare",
+        ),
+    ]
+    .into_iter()
+    .map(|card| (card.mtgo_id, card))
+    .collect()
+}
+
+fn deck() -> Deck {
+    Deck::from_card_lists(
+        &[
+            BOLT,
+            BOLT,
+            BOLT,
+            BOLT_FOIL,
+            FIRE_ICE,
+            BONECRUSHER,
+            BONECRUSHER,
+            DELVER,
+        ],
+        &[SECRET_LAIR, SECRET_LAIR, LURRUS, UNKNOWN],
+        Some(LURRUS),
+    )
+}
+
+fn write(dir: &Path, name: &str, xml: &str) -> std::path::PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, xml).unwrap();
+    path
+}
+
+/// A .dek file with the given `Cards` lines
+fn dek(lines: &[&str]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<Deck>\n");
+    for line in lines {
+        xml.push_str(&format!("  <Cards {} />\n", line));
+    }
+    xml.push_str("</Deck>\n");
+    xml
+}
+
+#[test]
+fn dek_files_round_trip_with_escaped_names() {
+    let dir = tempfile::tempdir().unwrap();
+    let exported = export(&deck(), DeckFormat::Dek, &cards());
+    assert_eq!(exported.unresolved, [UNKNOWN]);
+    assert!(exported
+        .content
+        .contains("Name=\"R&amp;D&apos;s &quot;Secret&quot; &lt;Lair&gt;\""));
+    assert!(exported
+        .content
+        .contains("CatID=\"201\" Quantity=\"2\" Sideboard=\"true\""));
+    let path = write(dir.path(), "Burn.dek", &exported.content);
+
+    let read = read_dek(&path).unwrap();
+    assert_eq!(read.name, "Burn");
+    // The companion is not part of a .dek file; it stays in the sideboard
+    let mut expected = deck();
+    expected.companion = None;
+    assert_eq!(read.deck, expected);
+    let names = cards();
+    let expected_names: BTreeMap<u32, String> = [
+        BOLT,
+        BOLT_FOIL,
+        FIRE_ICE,
+        BONECRUSHER,
+        DELVER,
+        SECRET_LAIR,
+        LURRUS,
+    ]
+    .into_iter()
+    .map(|id| (id, names[&id].name.clone()))
+    .collect();
+    assert_eq!(read.card_names, expected_names);
+}
+
+#[test]
+fn dek_lines_are_sorted_into_maindeck_and_sideboard() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write(
+        dir.path(),
+        "lines.dek",
+        &dek(&[
+            r#"CatID="101" Quantity="3" Sideboard="false" Name="Lightning Bolt""#,
+            r#"CatID=" 101 " Quantity="1" Name="Lightning Bolt""#,
+            r#"CatID="201" Quantity="2" Sideboard="True""#,
+            r#"CatID="202" Sideboard=" TRUE ""#,
+            r#"CatID="103" Quantity="4294967295" Sideboard="yes""#,
+            r#"CatID="103" Quantity="4294967295""#,
+        ]),
+    );
+    let read = read_dek(&path).unwrap();
+    assert_eq!(read.deck.maindeck, [(101, 4), (103, u32::MAX)].into());
+    assert_eq!(read.deck.sideboard, [(201, 2), (202, 1)].into());
+    assert_eq!(
+        read.card_names,
+        [(101, "Lightning Bolt".to_string())].into()
+    );
+}
+
+#[test]
+fn dek_files_without_cards_or_with_bad_lines_are_rejected() {
+    let dir = tempfile::tempdir().unwrap();
+    let invalid = |name: &str, xml: &str| match read_dek(&write(dir.path(), name, xml)) {
+        Err(DeckError::InvalidDek { reason, .. }) => reason,
+        other => panic!("{} was read: {:?}", name, other),
+    };
+    assert_eq!(invalid("empty.dek", &dek(&[])), "no cards listed");
+    assert_eq!(
+        invalid(
+            "no_deck.dek",
+            "<?xml version=\"1.0\"?>\n<Deck><NetDeckID>0</NetDeckID></Deck>"
+        ),
+        "no cards listed"
+    );
+    assert_eq!(
+        invalid("no_id.dek", &dek(&[r#"Quantity="4""#])),
+        "Cards element without CatID"
+    );
+    assert_eq!(
+        invalid("bad_id.dek", &dek(&[r#"CatID="bolt""#])),
+        "invalid CatID 'bolt'"
+    );
+    assert_eq!(
+        invalid("bad_count.dek", &dek(&[r#"CatID="1" Quantity="-1""#])),
+        "invalid Quantity '-1'"
+    );
+    assert!(matches!(
+        read_dek(&dir.path().join("missing.dek")),
+        Err(DeckError::Io(_))
+    ));
+}
+
+#[test]
+fn text_lists_merge_printings_by_name() {
+    let exported = export(&deck(), DeckFormat::Text, &cards());
+    assert_eq!(
+        exported.content,
+        "2 Bonecrusher Giant // Stomp\n\
+         1 Delver of Secrets // Insectile Aberration\n\
+         1 Fire // Ice\n\
+         4 Lightning Bolt\n\
+         \n\
+         1 Lurrus of the Dream-Den\n\
+         2 R&D's \"Secret\" <Lair>\n\
+         1 Unknown card #999\n"
+    );
+    assert_eq!(exported.unresolved, [UNKNOWN]);
+
+    let maindeck_only = Deck::from_card_lists(&[BOLT], &[], None);
+    assert_eq!(
+        export(&maindeck_only, DeckFormat::Text, &cards()).content,
+        "1 Lightning Bolt\n"
+    );
+}
+
+#[test]
+fn arena_lists_use_arena_names_and_sections() {
+    let exported = export(&deck(), DeckFormat::Arena, &cards());
+    assert_eq!(
+        exported.content,
+        "Companion\n\
+         1 Lurrus of the Dream-Den\n\
+         \n\
+         Deck\n\
+         2 Bonecrusher Giant\n\
+         1 Delver of Secrets\n\
+         1 Fire // Ice\n\
+         4 Lightning Bolt\n\
+         \n\
+         Sideboard\n\
+         1 Lurrus of the Dream-Den\n\
+         2 R&D's \"Secret\" <Lair>\n\
+         1 Unknown card #999\n"
+    );
+    assert_eq!(exported.unresolved, [UNKNOWN]);
+}
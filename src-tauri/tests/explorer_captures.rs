@@ -0,0 +1,171 @@
+//! Protocol explorer comparison of controlled captures: alignment, byte variance,
+//! label correlation and candidate field ranking
+
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::frame;
+use mtgo_replay_lib::explorer::candidates::CandidateKind;
+use mtgo_replay_lib::explorer::captures::{compare_captures, ScenarioLabel};
+use mtgo_replay_lib::explorer::LoadedSession;
+use mtgo_replay_lib::protocol::archive::SessionArchive;
+use mtgo_replay_lib::protocol::schema::FieldValue;
+use mtgo_replay_lib::protocol::version::SchemaRegistry;
+use std::path::Path;
+use std::sync::Arc;
+
+const BOLT: u32 = 12345;
+const PATH: u32 = 67890;
+
+/// Archive and load a capture of alice casting `card_id` on turn 1; with `priority`
+/// an unrelated priority message arrives just before the cast
+fn capture(sessions: &Path, number: i64, card_id: u32, priority: bool) -> Arc<LoadedSession> {
+    let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+    let set = registry.latest().unwrap().clone();
+    let uint = |v: u32| FieldValue::UInt(v.into());
+    let mut messages = vec![
+        ("GameStarted", vec![uint(7), uint(1)]),
+        (
+            "PlayerJoined",
+            vec![
+                uint(1),
+                uint(0),
+                FieldValue::String("alice".to_string()),
+                FieldValue::Int(20),
+            ],
+        ),
+        ("TurnStep", vec![uint(1), uint(1), uint(3)]),
+        // Hand to stack
+        (
+            "ZoneChange",
+            vec![uint(30), uint(card_id), uint(1), uint(1), uint(1), uint(5)],
+        ),
+        ("LifeTotal", vec![uint(1), FieldValue::Int(20)]),
+    ];
+    if priority {
+        messages.insert(3, ("Priority", vec![uint(1)]));
+    }
+
+    let started_at = Utc.with_ymd_and_hms(2025, 3, 3, 20, 0, 0).unwrap() + Duration::hours(number);
+    let mut archive =
+        SessionArchive::create(sessions, started_at, set.client_version.clone()).unwrap();
+    for (index, (name, values)) in messages.into_iter().enumerate() {
+        archive
+            .append_frame(&frame(index as u64, &set, name, values))
+            .unwrap();
+    }
+    let dir = archive.dir().to_path_buf();
+    archive.finish(started_at).unwrap();
+    Arc::new(LoadedSession::load(&dir, registry).unwrap())
+}
+
+fn label(capture: usize, at_seconds: f64, card_id: u32) -> ScenarioLabel {
+    ScenarioLabel {
+        capture,
+        at_seconds,
+        text: "cast a one-drop".to_string(),
+        card_id: Some(card_id.into()),
+        life_total: None,
+        zone: None,
+    }
+}
+
+#[test]
+fn field_that_varies_with_the_scenario_ranks_first() {
+    let sessions = tempfile::tempdir().unwrap();
+    let captures = [
+        capture(sessions.path(), 0, BOLT, false),
+        capture(sessions.path(), 1, PATH, true),
+    ];
+    // The cast is at 3s in the first capture and 4s in the second
+    let labels = [label(0, 3.0, BOLT), label(1, 3.5, PATH)];
+    let report = compare_captures(&captures, &labels, 1.0);
+
+    let counts: Vec<_> = report
+        .captures
+        .iter()
+        .map(|c| (c.message_count, c.duration_seconds))
+        .collect();
+    assert_eq!(counts, [(5, 4.0), (6, 5.0)]);
+    let differing: Vec<_> = report.type_stats.iter().filter(|t| t.differs).collect();
+    assert_eq!(differing.len(), 1);
+    assert_eq!(
+        (differing[0].name.as_deref(), differing[0].counts.as_slice()),
+        (Some("Priority"), &[0, 1][..])
+    );
+
+    // Messages are aligned by occurrence despite the extra message, so only the
+    // card id bytes that differ between 12345 and 67890 vary
+    let variance: Vec<_> = report
+        .byte_variance
+        .iter()
+        .map(|v| {
+            (
+                v.offset,
+                v.field.as_deref(),
+                v.varying_groups,
+                v.aligned_groups,
+            )
+        })
+        .collect();
+    assert_eq!(
+        variance,
+        [
+            (4, Some("card_id"), 1, 1),
+            (5, Some("card_id"), 1, 1),
+            (6, Some("card_id"), 1, 1)
+        ]
+    );
+    assert!(report
+        .byte_variance
+        .iter()
+        .all(|v| v.type_id == captures[0].frames()[3].type_id));
+
+    // Near each label only the cast differs; the extra message has no counterpart
+    assert_eq!(report.label_correlations.len(), 2);
+    let correlated = |i: usize| -> Vec<_> {
+        report.label_correlations[i]
+            .messages
+            .iter()
+            .map(|m| {
+                (
+                    m.name.clone().unwrap(),
+                    m.seconds,
+                    m.unmatched,
+                    m.differing_offsets.clone(),
+                )
+            })
+            .collect()
+    };
+    assert_eq!(
+        correlated(0),
+        [("ZoneChange".to_string(), 3.0, false, vec![4, 5, 6])]
+    );
+    assert_eq!(
+        correlated(1),
+        [
+            ("Priority".to_string(), 3.0, true, vec![]),
+            ("ZoneChange".to_string(), 4.0, false, vec![4, 5, 6]),
+        ]
+    );
+
+    // The card id field holds both labelled values at the labelled times
+    let best = &report.candidates[0];
+    assert_eq!(best.kind, CandidateKind::CardId);
+    assert_eq!(
+        (best.message_name.as_deref(), best.field.as_deref()),
+        (Some("ZoneChange"), Some("card_id"))
+    );
+    assert_eq!((best.offset, best.width), (4, 4));
+    assert_eq!(
+        best.evidence[0],
+        "matched 2/2 labelled values near the labelled time"
+    );
+    let card_scores: Vec<_> = report
+        .candidates
+        .iter()
+        .filter(|c| c.kind == CandidateKind::CardId)
+        .map(|c| c.score)
+        .collect();
+    assert!(card_scores[1..].iter().all(|&score| score < card_scores[0]));
+}
@@ -0,0 +1,195 @@
+//! Protocol explorer views of single messages: hex dumps with field overlays and
+//! diffs between two messages of a type
+
+mod common;
+
+use common::frame;
+use mtgo_replay_lib::explorer::diff::{diff_payloads, RegionKind};
+use mtgo_replay_lib::explorer::hexdump::{display_value, hex_dump, BYTES_PER_ROW};
+use mtgo_replay_lib::protocol::decoder::Decoder;
+use mtgo_replay_lib::protocol::frame::Frame;
+use mtgo_replay_lib::protocol::schema::{DecodedMessage, FieldValue};
+use mtgo_replay_lib::protocol::version::SchemaRegistry;
+use std::sync::Arc;
+
+/// A PlayerJoined frame, decoded: player_id (4 bytes), seat (1), name (2 + length), life (4)
+fn player_joined(index: u64, name: &str, life: i64) -> (Frame, DecodedMessage) {
+    let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+    let set = registry.latest().unwrap().clone();
+    let values = vec![
+        FieldValue::UInt(1),
+        FieldValue::UInt(0),
+        FieldValue::String(name.to_string()),
+        FieldValue::Int(life),
+    ];
+    let frame = frame(index, &set, "PlayerJoined", values);
+    let message = Decoder::new(registry).unwrap().decode(&frame).unwrap();
+    (frame, message)
+}
+
+#[test]
+fn hex_dump_rows_are_offset_and_overlaid_with_fields() {
+    // 4 + 1 + 2 + 19 + 4 = 30 bytes: a full row and a short one
+    let (frame, message) = player_joined(0, "alice in wonderland", 20);
+    let dump = hex_dump(&frame.payload, &message.fields);
+    assert_eq!(dump.len, 30);
+    let offsets: Vec<_> = dump.rows.iter().map(|row| row.offset).collect();
+    assert_eq!(offsets, [0, BYTES_PER_ROW]);
+    assert_eq!(dump.rows[1].hex.len(), 30 - BYTES_PER_ROW);
+
+    let first = &dump.rows[0];
+    assert_eq!(first.hex[..5], ["01", "00", "00", "00", "00"]);
+    assert_eq!(first.ascii, ".......alice in ");
+    assert_eq!(dump.rows[1].ascii, "wonderland....");
+
+    let overlays: Vec<_> = dump
+        .fields
+        .iter()
+        .map(|f| (f.name.as_str(), f.offset, f.len))
+        .collect();
+    assert_eq!(
+        overlays,
+        [
+            ("player_id", 0, 4),
+            ("seat", 4, 1),
+            ("name", 5, 21),
+            ("life", 26, 4)
+        ]
+    );
+    assert_eq!(dump.fields[0].display, "1 (0x1)");
+    assert_eq!(dump.fields[2].display, "\"alice in wonderland\"");
+    assert_eq!(dump.fields[3].display, "20");
+
+    // Each byte points at the field covering it, across the row boundary
+    let owners: Vec<_> = dump
+        .rows
+        .iter()
+        .flat_map(|row| row.field_ids.iter().copied())
+        .collect();
+    let expected: Vec<_> = [(0, 4), (1, 1), (2, 21), (3, 4)]
+        .iter()
+        .flat_map(|&(field, len)| vec![Some(field); len])
+        .collect();
+    assert_eq!(owners, expected);
+
+    // Bytes no field accounts for are left bare, and fields past the end are clipped
+    let mut fields = message.fields.clone();
+    fields.retain(|f| f.name != "seat");
+    fields[2].len = 40;
+    let dump = hex_dump(&frame.payload, &fields);
+    assert_eq!(dump.rows[0].field_ids[4], None);
+    assert_eq!(dump.rows[1].field_ids.last(), Some(&Some(2)));
+    assert!(hex_dump(&[], &[]).rows.is_empty());
+}
+
+#[test]
+fn values_are_displayed_compactly() {
+    assert_eq!(display_value(&FieldValue::UInt(255)), "255 (0xff)");
+    assert_eq!(display_value(&FieldValue::Int(-3)), "-3");
+    assert_eq!(display_value(&FieldValue::Bool(true)), "true");
+    assert_eq!(
+        display_value(&FieldValue::Bytes(vec![0xde; 40])),
+        format!("[40 bytes] {}", "de".repeat(32))
+    );
+    let items = |n: i64| FieldValue::Array((0..n).map(FieldValue::Int).collect());
+    assert_eq!(display_value(&items(2)), "[0, 1]");
+    assert_eq!(
+        display_value(&items(10)),
+        "[0, 1, 2, 3, 4, 5, 6, 7, ... (10 items)]"
+    );
+}
+
+#[test]
+fn diff_of_messages_differing_in_one_field_points_at_it() {
+    let (left_frame, left) = player_joined(0, "alice", 20);
+    let (right_frame, right) = player_joined(1, "alice", 17);
+    let diff = diff_payloads(
+        left.type_id,
+        &left_frame.payload,
+        &right_frame.payload,
+        Some(&left),
+        Some(&right),
+    );
+    assert!(!diff.identical);
+    assert_eq!(
+        (diff.left_len, diff.right_len, diff.changed_bytes),
+        (16, 16, 1)
+    );
+    assert_eq!(diff.regions.len(), 1);
+    let region = &diff.regions[0];
+    assert_eq!(
+        (region.offset, region.len, region.kind),
+        (12, 1, RegionKind::Changed)
+    );
+    assert_eq!(
+        (region.left_hex.as_str(), region.right_hex.as_str()),
+        ("14", "11")
+    );
+    assert_eq!(region.fields, ["life"]);
+    assert_eq!(diff.field_changes.len(), 1);
+    let change = &diff.field_changes[0];
+    assert_eq!(change.name, "life");
+    assert_eq!(
+        (change.left.as_deref(), change.right.as_deref()),
+        (Some("20"), Some("17"))
+    );
+
+    let same = diff_payloads(
+        left.type_id,
+        &left_frame.payload,
+        &left_frame.payload,
+        Some(&left),
+        Some(&left),
+    );
+    assert!(same.identical && same.regions.is_empty() && same.field_changes.is_empty());
+}
+
+#[test]
+fn diff_of_messages_of_different_lengths_reports_the_tail() {
+    let (left_frame, left) = player_joined(0, "bob", 20);
+    let (right_frame, right) = player_joined(1, "bobby", 20);
+    let diff = diff_payloads(
+        left.type_id,
+        &left_frame.payload,
+        &right_frame.payload,
+        Some(&left),
+        Some(&right),
+    );
+    assert_eq!((diff.left_len, diff.right_len), (14, 16));
+    let regions: Vec<_> = diff
+        .regions
+        .iter()
+        .map(|r| (r.offset, r.len, r.kind))
+        .collect();
+    // The length prefix, then the life total shifted under the longer name, then the tail
+    assert_eq!(
+        regions,
+        [
+            (5, 1, RegionKind::Changed),
+            (10, 3, RegionKind::Changed),
+            (14, 2, RegionKind::RightOnly)
+        ]
+    );
+    assert_eq!(diff.regions[0].fields, ["name"]);
+    // Both sides' fields overlap the shifted bytes; each is named once
+    assert_eq!(diff.regions[1].fields, ["life", "name"]);
+    assert_eq!(diff.regions[2].left_hex, "");
+    let changed: Vec<_> = diff.field_changes.iter().map(|c| c.name.as_str()).collect();
+    assert_eq!(changed, ["name"]);
+
+    // Without decoded messages only the bytes are compared
+    let raw = diff_payloads(
+        left.type_id,
+        &right_frame.payload,
+        &left_frame.payload,
+        None,
+        None,
+    );
+    assert_eq!(raw.changed_bytes, diff.changed_bytes);
+    assert_eq!(
+        raw.regions.last().map(|r| r.kind),
+        Some(RegionKind::LeftOnly)
+    );
+    assert!(raw.regions.iter().all(|r| r.fields.is_empty()));
+    assert!(raw.field_changes.is_empty());
+}
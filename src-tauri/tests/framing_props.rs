@@ -0,0 +1,108 @@
+//! Property tests for length-prefixed MTGO framing
+
+mod common;
+
+use common::{
+    allocation_bound, largest_allocation, seeded_inputs, split_at_cuts, TrackingAllocator,
+};
+use mtgo_replay_lib::common::error::ProtocolError;
+use mtgo_replay_lib::protocol::frame::{
+    encode_frame, FrameReader, FRAME_HEADER_LEN, MAX_FRAME_LEN,
+};
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+type Frames = Vec<(u16, Vec<u8>)>;
+
+/// Drain every complete frame, stopping at the first framing error
+fn drain(reader: &mut FrameReader, out: &mut Frames) -> Result<(), ProtocolError> {
+    while let Some(frame) = reader.next_frame()? {
+        out.push(frame);
+    }
+    Ok(())
+}
+
+/// Feed `data` in chunks split at `cuts`, returning frames and the first error
+fn read_chunked(data: &[u8], cuts: &[Index]) -> (Frames, Option<String>) {
+    let mut reader = FrameReader::new();
+    let mut frames = Vec::new();
+    for (_, chunk) in split_at_cuts(data, cuts) {
+        reader.push(&chunk);
+        if let Err(e) = drain(&mut reader, &mut frames) {
+            return (frames, Some(e.to_string()));
+        }
+    }
+    (frames, None)
+}
+
+#[test]
+fn corpus_frames_without_panic() {
+    for seed in common::load_corpus("framing") {
+        let (_, largest) = largest_allocation(|| read_chunked(&seed, &[]));
+        assert!(largest <= allocation_bound(seed.len()));
+    }
+}
+
+#[test]
+fn oversized_length_is_rejected() {
+    let mut reader = FrameReader::new();
+    reader.push(&((MAX_FRAME_LEN + 1) as u32).to_le_bytes());
+    reader.push(&[1, 0]);
+    assert!(matches!(
+        reader.next_frame(),
+        Err(ProtocolError::FrameTooLarge(..))
+    ));
+}
+
+proptest! {
+    #[test]
+    fn encoded_frames_round_trip_across_chunk_boundaries(
+        frames in prop::collection::vec((any::<u16>(), prop::collection::vec(any::<u8>(), 0..2048)), 0..16),
+        cuts in prop::collection::vec(any::<Index>(), 0..32),
+    ) {
+        let stream: Vec<u8> = frames.iter().flat_map(|(t, p)| encode_frame(*t, p)).collect();
+        let (decoded, error) = read_chunked(&stream, &cuts);
+
+        prop_assert_eq!(error, None);
+        prop_assert_eq!(decoded, frames);
+    }
+
+    #[test]
+    fn chunking_does_not_change_the_result(
+        data in seeded_inputs("framing"),
+        cuts in prop::collection::vec(any::<Index>(), 0..32),
+    ) {
+        let (whole, largest) = largest_allocation(|| read_chunked(&data, &[]));
+        prop_assert!(largest <= allocation_bound(data.len()));
+        prop_assert_eq!(read_chunked(&data, &cuts), whole);
+    }
+
+    #[test]
+    fn partial_frame_stays_buffered(
+        type_id in any::<u16>(),
+        payload in prop::collection::vec(any::<u8>(), 1..512),
+        keep in any::<Index>(),
+    ) {
+        let encoded = encode_frame(type_id, &payload);
+        let keep = keep.index(encoded.len());
+
+        let mut reader = FrameReader::new();
+        reader.push(&encoded[..keep]);
+        prop_assert_eq!(reader.next_frame().unwrap(), None);
+        prop_assert_eq!(reader.buffered(), keep);
+
+        reader.push(&encoded[keep..]);
+        prop_assert_eq!(reader.next_frame().unwrap(), Some((type_id, payload)));
+        prop_assert_eq!(reader.buffered(), 0);
+    }
+
+    #[test]
+    fn header_length_matches_payload(type_id in any::<u16>(), payload in prop::collection::vec(any::<u8>(), 0..1024)) {
+        let encoded = encode_frame(type_id, &payload);
+        prop_assert_eq!(encoded.len(), FRAME_HEADER_LEN + payload.len());
+        prop_assert_eq!(&encoded[FRAME_HEADER_LEN..], payload.as_slice());
+    }
+}
@@ -0,0 +1,421 @@
+//! Game engine: decoded messages in, game events and snapshots out
+
+mod common;
+
+use common::{frame, moved};
+use mtgo_replay_lib::common::error::GameError;
+use mtgo_replay_lib::game::deck::Deck;
+use mtgo_replay_lib::game::engine::{GameEngine, SNAPSHOT_INTERVAL};
+use mtgo_replay_lib::game::event::{GameEventKind, GameUpdate};
+use mtgo_replay_lib::game::model::{Phase, Step, Zone};
+use mtgo_replay_lib::protocol::decoder::Decoder;
+use mtgo_replay_lib::protocol::frame::Frame;
+use mtgo_replay_lib::protocol::schema::{DecodedMessage, FieldValue, SchemaSet};
+use mtgo_replay_lib::protocol::version::SchemaRegistry;
+use std::sync::Arc;
+
+const ALICE: u32 = 1;
+const BOB: u32 = 2;
+
+/// Messages encoded with the built-in schemas, decoded and handed to an engine
+struct Session {
+    set: SchemaSet,
+    decoder: Decoder,
+    engine: GameEngine,
+    index: u64,
+}
+
+impl Session {
+    fn new() -> Self {
+        let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+        Self {
+            set: registry.latest().unwrap().clone(),
+            decoder: Decoder::new(registry).unwrap(),
+            engine: GameEngine::new(),
+            index: 0,
+        }
+    }
+
+    fn decode(&mut self, name: &str, values: Vec<FieldValue>) -> (Frame, DecodedMessage) {
+        let frame = frame(self.index, &self.set, name, values);
+        self.index += 1;
+        let message = self.decoder.decode(&frame).unwrap();
+        (frame, message)
+    }
+
+    fn send(&mut self, name: &str, values: Vec<FieldValue>) -> GameUpdate {
+        let (frame, message) = self.decode(name, values);
+        self.engine.apply(&frame, &message).unwrap()
+    }
+
+    /// Events of a message, without their sequence numbers and timestamps
+    fn kinds(&mut self, name: &str, values: Vec<FieldValue>) -> Vec<GameEventKind> {
+        self.send(name, values)
+            .events
+            .into_iter()
+            .map(|event| event.kind)
+            .collect()
+    }
+
+    fn start_game(&mut self) {
+        self.send("GameStarted", vec![uint(7), uint(ALICE)]);
+        self.send(
+            "PlayerJoined",
+            vec![uint(ALICE), uint(0), text("alice"), FieldValue::Int(20)],
+        );
+        self.send(
+            "PlayerJoined",
+            vec![uint(BOB), uint(1), text("bob"), FieldValue::Int(20)],
+        );
+    }
+
+    /// Move an object; card 0 is a hidden card and zone 255 no zone at all
+    fn move_object(
+        &mut self,
+        object: u32,
+        card_id: u32,
+        owner: u32,
+        from: u64,
+        to: u64,
+    ) -> Vec<GameEventKind> {
+        self.kinds(
+            "ZoneChange",
+            vec![
+                uint(object),
+                uint(card_id),
+                uint(owner),
+                uint(owner),
+                FieldValue::UInt(from),
+                FieldValue::UInt(to),
+            ],
+        )
+    }
+}
+
+fn uint(value: u32) -> FieldValue {
+    FieldValue::UInt(value.into())
+}
+
+fn text(value: &str) -> FieldValue {
+    FieldValue::String(value.to_string())
+}
+
+fn zone(zone: Zone) -> u64 {
+    Zone::ALL.iter().position(|z| *z == zone).unwrap() as u64
+}
+
+#[test]
+fn zone_changes_move_objects_and_keep_zone_sizes() {
+    let mut session = Session::new();
+    session.start_game();
+    assert_eq!(
+        session.kinds(
+            "ZoneSize",
+            vec![uint(ALICE), FieldValue::UInt(zone(Zone::Library)), uint(53)]
+        ),
+        [GameEventKind::ZoneSizeChanged {
+            player: ALICE,
+            zone: Zone::Library,
+            size: 53
+        }]
+    );
+    session.send(
+        "ZoneSize",
+        vec![uint(ALICE), FieldValue::UInt(zone(Zone::Hand)), uint(7)],
+    );
+    // Repeated sizes change nothing
+    assert!(session
+        .kinds(
+            "ZoneSize",
+            vec![uint(ALICE), FieldValue::UInt(zone(Zone::Hand)), uint(7)]
+        )
+        .is_empty());
+
+    let (library, hand, battlefield) = (
+        zone(Zone::Library),
+        zone(Zone::Hand),
+        zone(Zone::Battlefield),
+    );
+    assert_eq!(
+        session.move_object(30, 101, ALICE, library, hand),
+        [moved(30, Some(101), ALICE, Some(Zone::Library), Zone::Hand)]
+    );
+    // Bob's draw is hidden from alice
+    assert_eq!(
+        session.move_object(40, 0, BOB, library, hand),
+        [moved(40, None, BOB, Some(Zone::Library), Zone::Hand)]
+    );
+    assert_eq!(
+        session.move_object(30, 101, ALICE, hand, battlefield),
+        [moved(
+            30,
+            Some(101),
+            ALICE,
+            Some(Zone::Hand),
+            Zone::Battlefield
+        )]
+    );
+    // A token comes from no zone
+    assert_eq!(
+        session.move_object(31, 0, ALICE, 255, battlefield),
+        [moved(31, None, ALICE, None, Zone::Battlefield)]
+    );
+    let state = session.engine.state();
+    assert_eq!(state.players[&ALICE].zone_sizes[&Zone::Library], 52);
+    assert_eq!(state.players[&ALICE].zone_sizes[&Zone::Hand], 7);
+    assert_eq!(state.objects[&30].zone, Zone::Battlefield);
+    assert!(state.objects[&31].zone_order > state.objects[&30].zone_order);
+
+    assert_eq!(
+        session.kinds(
+            "ObjectState",
+            vec![
+                uint(30),
+                FieldValue::Bool(true),
+                FieldValue::Bool(false),
+                uint(BOB)
+            ]
+        ),
+        [
+            GameEventKind::Tapped {
+                object: 30,
+                tapped: true
+            },
+            GameEventKind::ControlChanged {
+                object: 30,
+                from: ALICE,
+                to: BOB
+            },
+        ]
+    );
+    assert!(session
+        .kinds(
+            "ObjectState",
+            vec![
+                uint(30),
+                FieldValue::Bool(true),
+                FieldValue::Bool(false),
+                uint(BOB)
+            ]
+        )
+        .is_empty());
+    assert_eq!(
+        session.kinds(
+            "Counters",
+            vec![uint(30), text("+1/+1"), FieldValue::Int(2)]
+        ),
+        [GameEventKind::CountersChanged {
+            object: 30,
+            counter: "+1/+1".to_string(),
+            from: 0,
+            to: 2
+        }]
+    );
+    assert_eq!(
+        session.kinds("Attachment", vec![uint(31), uint(30)]),
+        [GameEventKind::Attached {
+            object: 31,
+            to: Some(30)
+        }]
+    );
+    // Updates for objects never seen are dropped
+    assert!(session
+        .kinds(
+            "Counters",
+            vec![uint(99), text("+1/+1"), FieldValue::Int(1)]
+        )
+        .is_empty());
+
+    // Leaving the battlefield makes a new object: untapped, no counters, and what
+    // was attached to it falls off
+    assert_eq!(
+        session.move_object(30, 101, ALICE, battlefield, zone(Zone::Graveyard)),
+        [
+            moved(
+                30,
+                Some(101),
+                ALICE,
+                Some(Zone::Battlefield),
+                Zone::Graveyard
+            ),
+            GameEventKind::Attached {
+                object: 31,
+                to: None
+            },
+        ]
+    );
+    let state = session.engine.state();
+    let graveyard = &state.objects[&30];
+    assert!(!graveyard.tapped && graveyard.counters.is_empty());
+    assert_eq!(state.objects[&31].attached_to, None);
+}
+
+#[test]
+fn turns_steps_and_game_end_produce_events_and_snapshots() {
+    let mut session = Session::new();
+    let started = session.send("GameStarted", vec![uint(7), uint(ALICE)]);
+    assert_eq!(started.snapshot.as_ref().map(|s| s.seq), Some(0));
+    assert_eq!(session.engine.state().starting_player, Some(ALICE));
+    session.send(
+        "PlayerJoined",
+        vec![uint(ALICE), uint(0), text("alice"), FieldValue::Int(20)],
+    );
+    session.send(
+        "PlayerJoined",
+        vec![uint(BOB), uint(1), text("bob"), FieldValue::Int(20)],
+    );
+
+    let step =
+        |step: Step| FieldValue::UInt(Step::ALL.iter().position(|s| *s == step).unwrap() as u64);
+    let turn = session.send("TurnStep", vec![uint(1), uint(ALICE), step(Step::Untap)]);
+    let kinds: Vec<_> = turn.events.iter().map(|event| event.kind.clone()).collect();
+    assert_eq!(
+        kinds,
+        [
+            GameEventKind::TurnStarted {
+                turn: 1,
+                active_player: ALICE
+            },
+            GameEventKind::StepChanged {
+                turn: 1,
+                phase: Phase::Beginning,
+                step: Step::Untap
+            },
+        ]
+    );
+    // Every new turn is a snapshot point, taken after the turn's events
+    let snapshot = turn.snapshot.unwrap();
+    assert_eq!(snapshot.seq, turn.events[1].seq);
+    assert_eq!(snapshot.state.turn.step, Some(Step::Untap));
+
+    assert_eq!(
+        session.send("TurnStep", vec![uint(1), uint(ALICE), step(Step::Untap)]),
+        GameUpdate::default()
+    );
+    let main = session.send(
+        "TurnStep",
+        vec![uint(1), uint(ALICE), step(Step::PrecombatMain)],
+    );
+    assert_eq!(main.events.len(), 1);
+    assert!(main.snapshot.is_none());
+    assert_eq!(
+        session.engine.state().turn.phase,
+        Some(Phase::PrecombatMain)
+    );
+
+    assert_eq!(
+        session.kinds("Priority", vec![uint(ALICE)]),
+        [GameEventKind::PriorityChanged { player: ALICE }]
+    );
+    assert!(session.kinds("Priority", vec![uint(ALICE)]).is_empty());
+    assert_eq!(
+        session.kinds("LifeTotal", vec![uint(BOB), FieldValue::Int(17)]),
+        [GameEventKind::LifeChanged {
+            player: BOB,
+            from: 20,
+            to: 17
+        }]
+    );
+    assert_eq!(
+        session.kinds("Mulligan", vec![uint(BOB), uint(1)]),
+        [GameEventKind::Mulligan {
+            player: BOB,
+            count: 1
+        }]
+    );
+
+    let ended = session.send("GameEnded", vec![uint(7), uint(ALICE), text("concede")]);
+    assert_eq!(
+        ended.events[0].kind,
+        GameEventKind::GameEnded {
+            game_id: 7,
+            winner: Some(ALICE),
+            reason: "concede".to_string()
+        }
+    );
+    let state = &ended.snapshot.unwrap().state;
+    assert!(state.ended);
+    assert_eq!((state.winner, state.priority), (Some(ALICE), None));
+    // The server repeats the game end; it is reported once
+    assert_eq!(
+        session.send("GameEnded", vec![uint(7), uint(ALICE), text("concede")]),
+        GameUpdate::default()
+    );
+
+    // Match messages pass through without touching the game
+    assert_eq!(
+        session.kinds(
+            "DeckSubmitted",
+            vec![
+                uint(ALICE),
+                FieldValue::Array(vec![uint(101), uint(101), uint(102)]),
+                FieldValue::Array(vec![uint(201)]),
+                uint(0),
+            ]
+        ),
+        [GameEventKind::DeckSubmitted {
+            player: ALICE,
+            deck: Deck::from_card_lists(&[101, 101, 102], &[201], None)
+        }]
+    );
+    assert_eq!(
+        session.kinds("MatchEnded", vec![uint(9001), text("decided")]),
+        [GameEventKind::MatchEnded {
+            match_id: 9001,
+            reason: "decided".to_string()
+        }]
+    );
+    assert!(session.engine.state().ended);
+    // Events carry the game they belong to; messages outside the game protocol make none
+    assert_eq!(
+        session.send("Priority", vec![uint(BOB)]).events[0].game_id,
+        Some(7)
+    );
+    assert_eq!(
+        session.send("LoginResult", vec![FieldValue::Bool(true), uint(ALICE)]),
+        GameUpdate::default()
+    );
+
+    // Events are numbered across the whole session, and a new game starts afresh
+    let count = session.engine.event_count();
+    let next = session.send("GameStarted", vec![uint(8), uint(BOB)]);
+    assert_eq!(next.events[0].seq, count);
+    let state = session.engine.state();
+    assert_eq!(
+        (state.game_id, state.turn.number, state.players.len()),
+        (Some(8), 0, 0)
+    );
+}
+
+#[test]
+fn long_turns_are_snapshotted_and_malformed_messages_rejected() {
+    let mut session = Session::new();
+    session.start_game();
+    session.send("TurnStep", vec![uint(1), uint(ALICE), FieldValue::UInt(0)]);
+    for i in 1..SNAPSHOT_INTERVAL {
+        let update = session.send(
+            "LifeTotal",
+            vec![uint(BOB), FieldValue::Int(20 - (i % 2) as i64)],
+        );
+        assert_eq!(update.events.len(), 1);
+        assert!(update.snapshot.is_none());
+    }
+    let update = session.send("LifeTotal", vec![uint(BOB), FieldValue::Int(0)]);
+    assert_eq!(update.snapshot.map(|s| s.seq), Some(update.events[0].seq));
+
+    let (frame, message) = session.decode(
+        "ZoneChange",
+        vec![uint(1), uint(1), uint(1), uint(1), uint(0), uint(9)],
+    );
+    assert!(matches!(
+        session.engine.apply(&frame, &message),
+        Err(GameError::InvalidValue { .. })
+    ));
+    let (frame, mut message) = session.decode("LifeTotal", vec![uint(BOB), FieldValue::Int(5)]);
+    message.fields.retain(|field| field.name != "life");
+    assert!(matches!(
+        session.engine.apply(&frame, &message),
+        Err(GameError::MissingField { .. })
+    ));
+    assert_eq!(session.engine.state().players[&BOB].life, 0);
+}
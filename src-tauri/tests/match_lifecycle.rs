@@ -0,0 +1,347 @@
+//! Match tracking: match and game boundaries, results, best-of handling and stored records
+
+mod common;
+
+use common::{at, moved};
+use mtgo_replay_lib::common::error::GameError;
+use mtgo_replay_lib::game::deck::Deck;
+use mtgo_replay_lib::game::event::{GameEvent, GameEventKind};
+use mtgo_replay_lib::game::lifecycle::{EventType, LifecycleEvent, Match, MatchTracker, Outcome};
+use mtgo_replay_lib::game::match_store;
+use mtgo_replay_lib::game::model::{GameState, Zone};
+use mtgo_replay_lib::game::sideboard::{CardCount, SideboardPlan};
+
+const ALICE: u32 = 1;
+const BOB: u32 = 2;
+
+/// Tracker fed one event at a time, numbering events and spacing them a second apart
+struct Feed {
+    tracker: MatchTracker,
+    seq: u64,
+    game_id: Option<u32>,
+}
+
+impl Feed {
+    fn new() -> Self {
+        Self {
+            tracker: MatchTracker::new(Some("session-1".to_string())),
+            seq: 0,
+            game_id: None,
+        }
+    }
+
+    /// Apply an event and name the boundaries it crossed by their frontend tag
+    fn apply(&mut self, kind: GameEventKind) -> Vec<String> {
+        self.events(kind)
+            .iter()
+            .map(|event| {
+                serde_json::to_value(event).unwrap()["type"]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn events(&mut self, kind: GameEventKind) -> Vec<LifecycleEvent> {
+        if let GameEventKind::GameStarted { game_id, .. } = &kind {
+            self.game_id = Some(*game_id);
+        }
+        let event = GameEvent {
+            seq: self.seq,
+            game_id: self.game_id,
+            frame_index: self.seq,
+            timestamp: at(self.seq as i64),
+            kind,
+        };
+        self.seq += 1;
+        self.tracker
+            .apply(&event, &GameState::new(self.game_id.unwrap_or(0)))
+    }
+
+    fn current(&self) -> &Match {
+        self.tracker.current().unwrap()
+    }
+}
+
+fn match_started(match_id: u32, best_of: u8) -> GameEventKind {
+    GameEventKind::MatchStarted {
+        match_id,
+        format: "Modern".to_string(),
+        event_type: "Competitive League".to_string(),
+        best_of,
+        local_player: "alice".to_string(),
+        opponent: "bob".to_string(),
+    }
+}
+
+fn game_started(game_id: u32) -> GameEventKind {
+    GameEventKind::GameStarted {
+        game_id,
+        starting_player: None,
+    }
+}
+
+fn joined(player: u32, name: &str) -> GameEventKind {
+    GameEventKind::PlayerJoined {
+        player,
+        seat: player as u8 - 1,
+        name: name.to_string(),
+        life: 20,
+    }
+}
+
+fn turn(turn: u32, active_player: u32) -> GameEventKind {
+    GameEventKind::TurnStarted {
+        turn,
+        active_player,
+    }
+}
+
+fn game_ended(game_id: u32, winner: Option<u32>) -> GameEventKind {
+    GameEventKind::GameEnded {
+        game_id,
+        winner,
+        reason: "conceded".to_string(),
+    }
+}
+
+fn deck(maindeck: &[u32], sideboard: &[u32]) -> GameEventKind {
+    GameEventKind::DeckSubmitted {
+        player: ALICE,
+        deck: Deck::from_card_lists(maindeck, sideboard, None),
+    }
+}
+
+#[test]
+fn best_of_three_is_tracked_game_by_game() {
+    let mut feed = Feed::new();
+    assert_eq!(feed.apply(match_started(77, 3)), ["match_started"]);
+    // The server repeats the match start when the client reconnects
+    assert!(feed.apply(match_started(77, 3)).is_empty());
+    assert_eq!(feed.current().event_type, EventType::League);
+    assert_eq!(feed.current().session_id.as_deref(), Some("session-1"));
+
+    // Game 1: registered before the game starts, alice on the play, bob mulligans
+    assert!(feed.apply(deck(&[10, 10, 11], &[12])).is_empty());
+    assert_eq!(feed.apply(game_started(1)), ["game_started"]);
+    feed.apply(joined(ALICE, "Alice"));
+    feed.apply(joined(BOB, "bob"));
+    feed.apply(GameEventKind::Mulligan {
+        player: BOB,
+        count: 1,
+    });
+    feed.apply(turn(1, ALICE));
+    // An opponent card counts once per object, however often it moves
+    feed.apply(moved(50, Some(900), BOB, None, Zone::Battlefield));
+    feed.apply(moved(
+        50,
+        Some(900),
+        BOB,
+        Some(Zone::Battlefield),
+        Zone::Graveyard,
+    ));
+    feed.apply(moved(51, Some(900), BOB, None, Zone::Battlefield));
+    feed.apply(turn(5, BOB));
+    let ended = feed.events(game_ended(1, Some(ALICE)));
+    assert!(ended[0].closes_record());
+    assert_eq!(
+        ended[1],
+        LifecycleEvent::SideboardingStarted {
+            match_id: 77,
+            next_game: 2
+        }
+    );
+
+    // Game 2: sideboarded, on the draw after a mulligan, lost
+    assert!(feed.apply(deck(&[10, 11, 12], &[10])).is_empty());
+    assert_eq!(
+        feed.apply(game_started(2)),
+        ["game_started", "sideboard_detected"]
+    );
+    feed.apply(joined(ALICE, "alice"));
+    feed.apply(turn(1, BOB));
+    feed.apply(GameEventKind::Mulligan {
+        player: ALICE,
+        count: 2,
+    });
+    assert_eq!(
+        feed.apply(game_ended(2, Some(BOB))),
+        ["game_ended", "sideboarding_started"]
+    );
+
+    // Game 3 decides the match
+    feed.apply(game_started(3));
+    feed.apply(joined(ALICE, "alice"));
+    let closed = feed.events(game_ended(3, Some(ALICE)));
+    assert_eq!(closed.len(), 2);
+    assert!(closed.iter().all(LifecycleEvent::closes_record));
+    let LifecycleEvent::MatchEnded { record } = &closed[1] else {
+        panic!("match not ended: {:?}", closed[1]);
+    };
+    assert_eq!(record, feed.current());
+    assert_eq!(
+        (record.result, record.end_reason.as_deref()),
+        (Some(Outcome::Win), Some("decided"))
+    );
+    assert_eq!((record.wins(), record.losses()), (2, 1));
+    assert!(record.is_decided() && record.is_closed());
+
+    let games: Vec<_> = record
+        .games
+        .iter()
+        .map(|g| {
+            (
+                g.number,
+                g.on_play,
+                g.mulligans,
+                g.opponent_mulligans,
+                g.result,
+            )
+        })
+        .collect();
+    assert_eq!(
+        games,
+        [
+            (1, Some(true), 0, 1, Some(Outcome::Win)),
+            (2, Some(false), 2, 0, Some(Outcome::Loss)),
+            (3, None, 0, 0, Some(Outcome::Win)),
+        ]
+    );
+    assert_eq!(record.games[0].turns, 5);
+    assert_eq!(record.games[0].opponent_cards, [(900, 2)].into());
+    assert_eq!(
+        record.games[1].sideboard.as_ref().unwrap().detected,
+        SideboardPlan {
+            cards_in: vec![CardCount {
+                card_id: 12,
+                count: 1
+            }],
+            cards_out: vec![CardCount {
+                card_id: 10,
+                count: 1
+            }],
+        }
+    );
+    // No new registration, so no sideboarding was seen for game 3
+    assert_eq!(
+        (
+            record.games[2].deck.as_ref(),
+            record.games[2].sideboard.as_ref()
+        ),
+        (None, None)
+    );
+
+    // Late events for the closed match are ignored
+    assert!(feed
+        .apply(GameEventKind::MatchEnded {
+            match_id: 77,
+            reason: "closed".to_string()
+        })
+        .is_empty());
+}
+
+#[test]
+fn short_unknown_and_interrupted_matches_are_closed() {
+    // Best of one: a drawn game leaves the match open for the game replaying it
+    let mut feed = Feed::new();
+    feed.apply(match_started(1, 1));
+    feed.apply(game_started(1));
+    feed.apply(joined(ALICE, "alice"));
+    assert_eq!(feed.apply(game_ended(1, None)), ["game_ended"]);
+    assert!(!feed.current().is_closed());
+    feed.apply(game_started(2));
+    feed.apply(joined(ALICE, "alice"));
+    assert_eq!(
+        feed.apply(game_ended(2, Some(ALICE))),
+        ["game_ended", "match_ended"]
+    );
+    assert_eq!(feed.current().games[0].result, Some(Outcome::Draw));
+    assert_eq!(feed.current().result, Some(Outcome::Win));
+
+    // A new match before the last one ended supersedes it
+    let mut feed = Feed::new();
+    feed.apply(match_started(1, 3));
+    feed.apply(game_started(1));
+    let events = feed.events(match_started(2, 3));
+    assert!(matches!(&events[..], [
+        LifecycleEvent::MatchEnded { record },
+        LifecycleEvent::MatchStarted { .. },
+    ] if record.id == 1 && record.end_reason.as_deref() == Some("superseded") && record.result.is_none()));
+    assert_eq!(feed.current().id, 2);
+
+    // Capture started mid-match: the games go into an unnamed match of unknown length
+    let mut feed = Feed::new();
+    feed.apply(game_started(3));
+    feed.apply(joined(ALICE, "alice"));
+    feed.apply(joined(BOB, "bob"));
+    assert_eq!((feed.current().id, feed.current().best_of), (0, 0));
+    // Without the match start the user's name is unknown, so the winner is too
+    assert_eq!(
+        feed.apply(game_ended(3, Some(BOB))),
+        ["game_ended", "sideboarding_started"]
+    );
+    feed.apply(game_started(4));
+    let events = feed.events(GameEventKind::MatchEnded {
+        match_id: 55,
+        reason: "left".to_string(),
+    });
+    let [LifecycleEvent::MatchEnded { record }] = &events[..] else {
+        panic!("match not ended: {:?}", events);
+    };
+    assert_eq!((record.opponent.as_deref(), record.result), (None, None));
+    assert_eq!(record.games.len(), 2);
+    assert!(record
+        .games
+        .iter()
+        .all(|g| g.result.is_none() && g.ended_at.is_some()));
+    assert_eq!(record.games[1].end_reason.as_deref(), Some("left"));
+}
+
+#[test]
+fn match_records_are_stored_one_file_per_match() {
+    let dir = tempfile::tempdir().unwrap();
+    let matches = dir.path().join("matches");
+    assert!(match_store::list_matches(&matches).unwrap().is_empty());
+
+    let mut feed = Feed::new();
+    feed.apply(match_started(77, 3));
+    feed.apply(game_started(1));
+    feed.apply(joined(ALICE, "alice"));
+    feed.apply(game_ended(1, Some(ALICE)));
+    let first = feed.current().clone();
+    match_store::save_match(&matches, &first).unwrap();
+    // Saved again as the match goes on, under the same name
+    feed.apply(game_started(2));
+    feed.apply(joined(ALICE, "alice"));
+    feed.apply(game_ended(2, Some(ALICE)));
+    let older = feed.current().clone();
+    assert_eq!(older.record_id(), first.record_id());
+    match_store::save_match(&matches, &older).unwrap();
+    let mut newer = older.clone();
+    newer.id = 78;
+    newer.started_at = at(3600);
+    match_store::save_match(&matches, &newer).unwrap();
+    std::fs::write(matches.join("broken.json"), "{").unwrap();
+    std::fs::write(matches.join("notes.txt"), "").unwrap();
+
+    assert_eq!(
+        match_store::load_match(&matches, &older.record_id()).unwrap(),
+        older
+    );
+    assert_eq!(match_store::list_matches(&matches).unwrap(), [newer, older]);
+    assert!(matches!(
+        match_store::load_match(&matches, "20250301T120000000-1"),
+        Err(GameError::MatchNotFound(_))
+    ));
+    assert!(matches!(
+        match_store::load_match(&matches, "broken"),
+        Err(GameError::CorruptMatch(_))
+    ));
+    for id in ["../matches/broken", "", "a.b"] {
+        assert!(matches!(
+            match_store::record_path(&matches, id),
+            Err(GameError::MatchNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+//! Opening hands: reading them from the event stream, indexing and mulligan analysis
+
+mod common;
+
+use common::{at, golden_path, moved};
+use mtgo_replay_lib::analytics::mulligan::{self, HandFilter, KeyCard};
+use mtgo_replay_lib::game::deck::Deck;
+use mtgo_replay_lib::game::event::{GameEvent, GameEventKind};
+use mtgo_replay_lib::game::lifecycle::Outcome;
+use mtgo_replay_lib::game::model::Zone;
+use mtgo_replay_lib::game::opening_hand::{opening_hands, OpeningHand};
+use mtgo_replay_lib::library::{LibraryQuery, ReplayLibrary};
+use mtgo_replay_lib::replay::{ReplayReader, ReplayWriter};
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+const ALICE: u32 = 1;
+const BOB: u32 = 2;
+const FOREST: u32 = 1001;
+const MOUNTAIN: u32 = 1002;
+const BOLT: u32 = 2001;
+const GOYF: u32 = 2002;
+
+fn game_start(game_id: u32, starting_player: u32) -> Vec<GameEventKind> {
+    vec![
+        GameEventKind::DeckSubmitted {
+            player: ALICE,
+            deck: Deck::default(),
+        },
+        GameEventKind::GameStarted {
+            game_id,
+            starting_player: Some(starting_player),
+        },
+        GameEventKind::PlayerJoined {
+            player: ALICE,
+            seat: 0,
+            name: "alice".to_string(),
+            life: 20,
+        },
+        GameEventKind::PlayerJoined {
+            player: BOB,
+            seat: 1,
+            name: "bob".to_string(),
+            life: 20,
+        },
+    ]
+}
+
+/// Game 1: alice (on the draw) mulligans a no-lander, keeps the next seven and
+/// bottoms a Goyf, and wins. Game 2: she keeps a 1-lander on the play and loses.
+fn events() -> Vec<GameEvent> {
+    let mut kinds = game_start(1, BOB);
+    let first = [BOLT, BOLT, BOLT, GOYF, GOYF, GOYF, GOYF];
+    for (i, card) in first.iter().enumerate() {
+        kinds.push(moved(10 + i as u32, Some(*card), ALICE, None, Zone::Hand));
+    }
+    // Bob's hand is never revealed; his known cards are not alice's
+    kinds.push(moved(90, Some(BOLT), BOB, None, Zone::Hand));
+    for (i, card) in first.iter().enumerate() {
+        kinds.push(moved(
+            10 + i as u32,
+            Some(*card),
+            ALICE,
+            Some(Zone::Hand),
+            Zone::Library,
+        ));
+    }
+    kinds.push(GameEventKind::Mulligan {
+        player: ALICE,
+        count: 1,
+    });
+    let second = [FOREST, FOREST, MOUNTAIN, BOLT, BOLT, GOYF, GOYF];
+    for (i, card) in second.iter().enumerate() {
+        kinds.push(moved(20 + i as u32, Some(*card), ALICE, None, Zone::Hand));
+    }
+    kinds.push(moved(
+        26,
+        Some(GOYF),
+        ALICE,
+        Some(Zone::Hand),
+        Zone::Library,
+    ));
+    kinds.push(GameEventKind::TurnStarted {
+        turn: 1,
+        active_player: BOB,
+    });
+    // Drawn after the game began: not part of the opening hand
+    kinds.push(moved(30, Some(FOREST), ALICE, None, Zone::Hand));
+    kinds.push(GameEventKind::GameEnded {
+        game_id: 1,
+        winner: Some(ALICE),
+        reason: "life".to_string(),
+    });
+
+    kinds.extend(game_start(2, ALICE));
+    let third = [FOREST, BOLT, BOLT, BOLT, GOYF, GOYF, GOYF];
+    for (i, card) in third.iter().enumerate() {
+        kinds.push(moved(40 + i as u32, Some(*card), ALICE, None, Zone::Hand));
+    }
+    kinds.push(GameEventKind::TurnStarted {
+        turn: 1,
+        active_player: ALICE,
+    });
+    kinds.push(GameEventKind::GameEnded {
+        game_id: 2,
+        winner: Some(BOB),
+        reason: "life".to_string(),
+    });
+
+    let mut game_id = 1;
+    kinds
+        .into_iter()
+        .enumerate()
+        .map(|(i, kind)| {
+            if let GameEventKind::GameStarted { game_id: id, .. } = &kind {
+                game_id = *id;
+            }
+            GameEvent {
+                seq: i as u64,
+                game_id: Some(game_id),
+                frame_index: i as u64,
+                timestamp: at(i as i64),
+                kind,
+            }
+        })
+        .collect()
+}
+
+/// Replay of the two games, with the golden match record extended to match them
+fn write_replay(dir: &Path) -> PathBuf {
+    let mut golden = ReplayReader::open(&golden_path()).unwrap();
+    let mut record = golden.match_record().unwrap().unwrap();
+    record.games[0].game_id = 1;
+    record.games[0].on_play = Some(false);
+    record.games[0].result = Some(Outcome::Win);
+    let mut second = record.games[0].clone();
+    second.game_id = 2;
+    second.number = 2;
+    second.on_play = Some(true);
+    second.result = Some(Outcome::Loss);
+    record.games.push(second);
+    record.result = Some(Outcome::Draw);
+
+    let path = dir.join(format!("{}.mtgoreplay", record.record_id()));
+    let file = BufWriter::new(File::create(&path).unwrap());
+    let mut writer = ReplayWriter::new(file, golden.header()).unwrap();
+    for event in events() {
+        writer.write_event(&event).unwrap();
+    }
+    writer.write_match(&record).unwrap();
+    writer.finish().unwrap();
+    path
+}
+
+#[test]
+fn hands_are_read_from_the_event_stream() {
+    let events = events();
+    let hands = opening_hands(&events, Some("alice".to_string()));
+    assert_eq!(
+        hands,
+        [
+            OpeningHand {
+                game_id: 1,
+                mulligans: 0,
+                cards: vec![BOLT, BOLT, BOLT, GOYF, GOYF, GOYF, GOYF],
+                kept: false,
+                bottomed: vec![],
+            },
+            OpeningHand {
+                game_id: 1,
+                mulligans: 1,
+                cards: vec![FOREST, FOREST, MOUNTAIN, BOLT, BOLT, GOYF, GOYF],
+                kept: true,
+                bottomed: vec![GOYF],
+            },
+            OpeningHand {
+                game_id: 2,
+                mulligans: 0,
+                cards: vec![FOREST, BOLT, BOLT, BOLT, GOYF, GOYF, GOYF],
+                kept: true,
+                bottomed: vec![],
+            },
+        ]
+    );
+
+    // The registered deck identifies alice even without her name
+    assert_eq!(opening_hands(&events, None), hands);
+}
+
+#[test]
+fn indexed_hands_are_searched_and_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = write_replay(dir.path());
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    library.index_replay(&path, None).unwrap();
+
+    let hands = library.opening_hands(&LibraryQuery::default()).unwrap();
+    assert_eq!(hands.len(), 3);
+    assert_eq!(
+        hands[1].cards,
+        [FOREST, FOREST, MOUNTAIN, BOLT, BOLT, GOYF, GOYF]
+    );
+    assert_eq!(hands[1].bottomed, [GOYF]);
+    assert_eq!((hands[1].game_number, hands[1].on_play), (1, Some(false)));
+    assert_eq!(hands[2].result, Some(Outcome::Loss));
+
+    let lands = HashSet::from([FOREST, MOUNTAIN]);
+    let hands = mulligan::analyze(hands, &lands);
+    let lands_of: Vec<u8> = hands.iter().map(|hand| hand.lands).collect();
+    assert_eq!(lands_of, [0, 3, 1]);
+
+    let one_landers_on_the_play = HandFilter {
+        lands: Some(1),
+        kept: Some(true),
+        on_play: Some(true),
+        ..HandFilter::default()
+    };
+    let found: Vec<u32> = hands
+        .iter()
+        .filter(|hand| one_landers_on_the_play.matches(hand))
+        .map(|hand| hand.hand.game_id)
+        .collect();
+    assert_eq!(found, [2]);
+    let on_the_draw = HandFilter {
+        on_play: Some(false),
+        ..one_landers_on_the_play
+    };
+    assert!(!hands.iter().any(|hand| on_the_draw.matches(hand)));
+
+    let key_cards = [KeyCard {
+        name: "Mountain".to_string(),
+        ids: vec![MOUNTAIN],
+    }];
+    let report = mulligan::report(&hands, &key_cards);
+    assert_eq!((report.overall.hands, report.overall.kept), (3, 2));
+    let by_lands: Vec<(&str, u32, u32)> = report
+        .by_lands
+        .iter()
+        .map(|row| (row.label.as_str(), row.hands, row.kept))
+        .collect();
+    assert_eq!(
+        by_lands,
+        [("0 lands", 1, 0), ("1 land", 1, 1), ("3 lands", 1, 1)]
+    );
+    assert_eq!(report.by_lands[0].keep_rate, Some(0.0));
+    assert_eq!(report.by_key_card[0].kept_win_rate.wins, 1);
+    let by_mulligans: Vec<(&str, u32, u32)> = report
+        .by_mulligans
+        .iter()
+        .map(|row| (row.label.as_str(), row.rate.wins, row.rate.losses))
+        .collect();
+    assert_eq!(by_mulligans, [("Keep 7", 0, 1), ("Mull to 6", 1, 0)]);
+}
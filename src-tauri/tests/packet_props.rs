@@ -0,0 +1,102 @@
+//! Property tests for IP/TCP packet parsing (PROT-001)
+
+mod common;
+
+use common::{
+    allocation_bound, build_packet, largest_allocation, seeded_inputs, TrackingAllocator,
+};
+use common::{TCP_ACK, TCP_FIN, TCP_PSH, TCP_RST, TCP_SYN};
+use mtgo_replay_lib::protocol::packet::parse_packet;
+use proptest::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+fn endpoint_pair() -> impl Strategy<Value = ((IpAddr, u16), (IpAddr, u16))> {
+    let v4 = (
+        any::<[u8; 4]>(),
+        any::<u16>(),
+        any::<[u8; 4]>(),
+        any::<u16>(),
+    )
+        .prop_map(|(s, sp, d, dp)| {
+            (
+                (IpAddr::V4(Ipv4Addr::from(s)), sp),
+                (IpAddr::V4(Ipv4Addr::from(d)), dp),
+            )
+        });
+    let v6 = (
+        any::<[u8; 16]>(),
+        any::<u16>(),
+        any::<[u8; 16]>(),
+        any::<u16>(),
+    )
+        .prop_map(|(s, sp, d, dp)| {
+            (
+                (IpAddr::V6(Ipv6Addr::from(s)), sp),
+                (IpAddr::V6(Ipv6Addr::from(d)), dp),
+            )
+        });
+    prop_oneof![v4, v6]
+}
+
+#[test]
+fn corpus_parses_without_panic() {
+    for seed in common::load_corpus("packet") {
+        let (_, largest) = largest_allocation(|| parse_packet(&seed));
+        assert!(largest <= allocation_bound(seed.len()));
+    }
+}
+
+proptest! {
+    #[test]
+    fn arbitrary_bytes_never_panic(data in seeded_inputs("packet")) {
+        let (_, largest) = largest_allocation(|| parse_packet(&data));
+        prop_assert!(largest <= allocation_bound(data.len()));
+    }
+
+    #[test]
+    fn built_packets_round_trip(
+        (src, dst) in endpoint_pair(),
+        seq in any::<u32>(),
+        flags in prop::sample::select(vec![TCP_ACK, TCP_ACK | TCP_PSH, TCP_SYN, TCP_FIN | TCP_ACK, TCP_RST]),
+        payload in prop::collection::vec(any::<u8>(), 0..1400),
+    ) {
+        let packet = build_packet(src, dst, seq, flags, &payload);
+        let segment = parse_packet(&packet).unwrap();
+
+        prop_assert_eq!(segment.flow.src_addr, src.0);
+        prop_assert_eq!(segment.flow.src_port, src.1);
+        prop_assert_eq!(segment.flow.dst_addr, dst.0);
+        prop_assert_eq!(segment.flow.dst_port, dst.1);
+        prop_assert_eq!(segment.seq, seq);
+        prop_assert_eq!(segment.syn, flags & TCP_SYN != 0);
+        prop_assert_eq!(segment.fin, flags & TCP_FIN != 0);
+        prop_assert_eq!(segment.rst, flags & TCP_RST != 0);
+        prop_assert_eq!(segment.payload, payload);
+    }
+
+    #[test]
+    fn trailing_capture_bytes_are_ignored(
+        (src, dst) in endpoint_pair(),
+        payload in prop::collection::vec(any::<u8>(), 0..256),
+        padding in prop::collection::vec(any::<u8>(), 1..32),
+    ) {
+        // Link-layer padding after the IP datagram must not leak into the payload
+        let mut packet = build_packet(src, dst, 1, TCP_ACK, &payload);
+        packet.extend_from_slice(&padding);
+        prop_assert_eq!(parse_packet(&packet).unwrap().payload, payload);
+    }
+
+    #[test]
+    fn truncated_packets_are_rejected(
+        (src, dst) in endpoint_pair(),
+        payload in prop::collection::vec(any::<u8>(), 1..256),
+        cut in any::<prop::sample::Index>(),
+    ) {
+        let packet = build_packet(src, dst, 1, TCP_ACK, &payload);
+        let truncated = &packet[..cut.index(packet.len())];
+        prop_assert!(parse_packet(truncated).is_err());
+    }
+}
@@ -0,0 +1,198 @@
+//! Hidden information in playback: masking cards by what each player knew
+
+mod common;
+
+use common::{at, golden_path, moved};
+use mtgo_replay_lib::game::event::{GameEvent, GameEventKind, GameSnapshot};
+use mtgo_replay_lib::game::knowledge::{Provenance, ViewMode};
+use mtgo_replay_lib::game::model::Zone;
+use mtgo_replay_lib::replay::{PlaybackView, ReplayPlayback, ReplayReader, SeekTarget, StepUnit};
+
+const ALICE: u32 = 1;
+const BOB: u32 = 2;
+
+/// Alice (capturing, per the golden header) draws 101 and 102; bob draws three
+/// unknown cards. Alice plays 102 and bob bounces it, then casts 301, reveals
+/// 302 and shows 303 once the game is over.
+fn events() -> Vec<GameEvent> {
+    let kinds = vec![
+        GameEventKind::GameStarted {
+            game_id: 1,
+            starting_player: Some(ALICE),
+        },
+        GameEventKind::PlayerJoined {
+            player: ALICE,
+            seat: 0,
+            name: "alice".to_string(),
+            life: 20,
+        },
+        GameEventKind::PlayerJoined {
+            player: BOB,
+            seat: 1,
+            name: "bob".to_string(),
+            life: 20,
+        },
+        moved(10, Some(101), ALICE, None, Zone::Hand),
+        moved(20, None, BOB, None, Zone::Hand),
+        moved(21, None, BOB, None, Zone::Hand),
+        moved(22, None, BOB, None, Zone::Hand),
+        GameEventKind::TurnStarted {
+            turn: 1,
+            active_player: ALICE,
+        },
+        moved(11, Some(102), ALICE, None, Zone::Hand),
+        moved(11, Some(102), ALICE, Some(Zone::Hand), Zone::Battlefield),
+        moved(11, None, ALICE, Some(Zone::Battlefield), Zone::Hand),
+        moved(20, Some(301), BOB, Some(Zone::Hand), Zone::Stack),
+        moved(20, Some(301), BOB, Some(Zone::Stack), Zone::Graveyard),
+        moved(21, Some(302), BOB, Some(Zone::Hand), Zone::Hand),
+        GameEventKind::GameEnded {
+            game_id: 1,
+            winner: Some(ALICE),
+            reason: "concede".to_string(),
+        },
+        moved(22, Some(303), BOB, Some(Zone::Hand), Zone::Hand),
+    ];
+    kinds
+        .into_iter()
+        .enumerate()
+        .map(|(i, kind)| GameEvent {
+            seq: 200 + i as u64,
+            game_id: Some(1),
+            frame_index: i as u64,
+            timestamp: at(i as i64),
+            kind,
+        })
+        .collect()
+}
+
+fn playback() -> ReplayPlayback {
+    let header = ReplayReader::open(&golden_path()).unwrap().header().clone();
+    ReplayPlayback::new(header, events(), Vec::<GameSnapshot>::new())
+}
+
+/// (card id, provenance) of each listed object
+fn cards(view: &PlaybackView, objects: &[u32]) -> Vec<(Option<u32>, Provenance)> {
+    objects
+        .iter()
+        .map(|id| (view.state.objects[id].card_id, view.knowledge[id]))
+        .collect()
+}
+
+#[test]
+fn as_played_shows_what_the_user_knew_at_the_time() {
+    let mut playback = playback();
+    playback.seek_position(8);
+    let view = playback.view();
+    assert_eq!((view.mode, view.viewer), (ViewMode::AsPlayed, Some(ALICE)));
+    assert_eq!(
+        cards(&view, &[10, 20, 21, 22]),
+        [
+            (Some(101), Provenance::Owner),
+            (None, Provenance::Hidden),
+            (None, Provenance::Hidden),
+            (None, Provenance::Hidden),
+        ]
+    );
+
+    // The bounced card went back without its identity, but it is alice's own
+    playback.seek_position(11);
+    assert_eq!(
+        cards(&playback.view(), &[11]),
+        [(Some(102), Provenance::Owner)]
+    );
+
+    playback.seek_position(12);
+    assert_eq!(
+        cards(&playback.view(), &[20]),
+        [(Some(301), Provenance::Public)]
+    );
+
+    playback.seek_position(14);
+    assert_eq!(
+        cards(&playback.view(), &[21, 22]),
+        [
+            (Some(302), Provenance::Revealed { seq: 213 }),
+            (None, Provenance::Hidden)
+        ]
+    );
+    playback.seek(&SeekTarget::End).unwrap();
+    assert_eq!(
+        cards(&playback.view(), &[22]),
+        [(Some(303), Provenance::Revealed { seq: 215 })]
+    );
+}
+
+#[test]
+fn full_information_identifies_cards_from_later_events() {
+    let mut playback = playback();
+    playback.set_mode(ViewMode::FullInformation);
+    playback.seek_position(8);
+    let view = playback.view();
+    assert_eq!(view.viewer, Some(ALICE));
+    assert_eq!(
+        cards(&view, &[10, 20, 21, 22]),
+        [
+            (Some(101), Provenance::Owner),
+            (Some(301), Provenance::Identified { seq: 211 }),
+            (Some(302), Provenance::Identified { seq: 213 }),
+            (Some(303), Provenance::Identified { seq: 215 }),
+        ]
+    );
+    // What was known at the time keeps its provenance
+    playback.seek_position(14);
+    assert_eq!(
+        cards(&playback.view(), &[21, 22]),
+        [
+            (Some(302), Provenance::Revealed { seq: 213 }),
+            (Some(303), Provenance::Identified { seq: 215 }),
+        ]
+    );
+}
+
+#[test]
+fn opponent_perspective_masks_the_users_hand() {
+    let mut playback = playback();
+    playback.set_mode(ViewMode::OpponentPerspective);
+    playback.seek_position(8);
+    let view = playback.view();
+    assert_eq!(view.viewer, Some(BOB));
+    // Bob knew his own hand, as far as the replay ever shows it
+    assert_eq!(
+        cards(&view, &[10, 20, 22]),
+        [
+            (None, Provenance::Hidden),
+            (Some(301), Provenance::Owner),
+            (Some(303), Provenance::Owner),
+        ]
+    );
+
+    // Bob saw the card he bounced
+    playback.seek_position(11);
+    assert_eq!(
+        cards(&playback.view(), &[10, 11]),
+        [
+            (None, Provenance::Hidden),
+            (Some(102), Provenance::Revealed { seq: 210 })
+        ]
+    );
+
+    // Events do not leak what the board masks
+    playback.seek(&SeekTarget::Start).unwrap();
+    for _ in 0..4 {
+        playback.step(StepUnit::Event, true);
+    }
+    let view = playback.view();
+    assert_eq!(view.position, 4);
+    assert!(matches!(
+        view.events[..],
+        [GameEvent {
+            kind: GameEventKind::ZoneChanged {
+                object: 10,
+                card_id: None,
+                ..
+            },
+            ..
+        }]
+    ));
+}
@@ -0,0 +1,255 @@
+//! Client version negotiation: schema set selection, the unknown-version log and
+//! re-decoding logged sessions once their schemas arrive
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::frame;
+use mtgo_replay_lib::common::error::ProtocolError;
+use mtgo_replay_lib::protocol::archive::{self, SessionArchive};
+use mtgo_replay_lib::protocol::decoder::{load_unknown_sessions, Decoder};
+use mtgo_replay_lib::protocol::frame::Frame;
+use mtgo_replay_lib::protocol::schema::{FieldValue, SchemaSet};
+use mtgo_replay_lib::protocol::session::{
+    redecode_unknown_sessions, unknown_session_status, SessionRecorder,
+};
+use mtgo_replay_lib::protocol::version::{ClientVersion, SchemaRegistry};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const BASELINE: &str = include_str!("../schemas/baseline.json");
+
+/// The baseline schemas, relabelled for another client version
+fn schemas(version: &str) -> SchemaSet {
+    let mut json: serde_json::Value = serde_json::from_str(BASELINE).unwrap();
+    json["client_version"] = version.into();
+    SchemaSet::from_json(&json.to_string()).unwrap()
+}
+
+fn registry(versions: &[&str]) -> SchemaRegistry {
+    let mut registry = SchemaRegistry::new();
+    for version in versions {
+        registry.insert(schemas(version));
+    }
+    registry
+}
+
+fn version(s: &str) -> ClientVersion {
+    ClientVersion::parse(s).unwrap()
+}
+
+fn handshake(index: u64, client_version: &str) -> Frame {
+    let values = vec![
+        FieldValue::UInt(7),
+        FieldValue::String(client_version.to_string()),
+    ];
+    frame(index, &schemas("3.4.0.0"), "Handshake", values)
+}
+
+/// Log output written while `f` runs
+fn logs(f: impl FnOnce()) -> String {
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let buffer = Buffer::default();
+    let writer = buffer.clone();
+    let subscriber = tracing_subscriber::fmt()
+        .with_ansi(false)
+        .with_writer(move || writer.clone())
+        .finish();
+    tracing::subscriber::with_default(subscriber, f);
+    let output = buffer.0.lock().unwrap().clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn schema_set_is_selected_for_the_client_version() {
+    let known = registry(&["3.4.0.0", "3.4.2.0", "3.5.0.0"]);
+    // Detected version, selected schemas, exact
+    let cases = [
+        ("3.4.2.0", "3.4.2.0", true),
+        ("3.4", "3.4.0.0", true),
+        // Protocol changes are usually additive, so older schemas are preferred
+        ("3.4.5.0", "3.4.2.0", false),
+        ("3.4.2.1", "3.4.2.0", false),
+        ("4.0.0.0", "3.5.0.0", false),
+        // A client older than everything known gets the oldest newer schemas
+        ("3.3.9.9", "3.4.0.0", false),
+    ];
+    for (detected, selected, exact) in cases {
+        let selection = known.select(&version(detected)).unwrap();
+        assert_eq!(
+            (
+                selection.schemas.client_version.to_string(),
+                selection.exact
+            ),
+            (version(selected).to_string(), exact),
+            "client {}",
+            detected
+        );
+    }
+    assert!(matches!(
+        SchemaRegistry::new().select(&version("3.4")),
+        Err(ProtocolError::NoSchemas)
+    ));
+}
+
+#[test]
+fn fallback_sessions_are_warned_about_and_logged() {
+    let dir = tempfile::tempdir().unwrap();
+    let log = dir.path().join("logs").join("unknown_versions.jsonl");
+    let known = Arc::new(registry(&["3.4.0.0", "3.4.2.0", "3.5.0.0"]));
+
+    let mut decoder = Decoder::new(Arc::clone(&known))
+        .unwrap()
+        .with_unknown_version_log(log.clone());
+    let output = logs(|| {
+        decoder.decode(&handshake(0, "3.4.2.0")).unwrap();
+    });
+    assert!(decoder.is_exact_match());
+    assert!(output.contains("MTGO client version 3.4.2.0 detected, using matching schemas"));
+    assert!(decoder.unknown_sessions().is_empty() && !log.exists());
+
+    // Nearest older
+    let output = logs(|| {
+        decoder.decode(&handshake(1, "3.4.5.0")).unwrap();
+    });
+    assert!(output.contains("WARN"));
+    assert!(output.contains("No protocol schemas for MTGO client 3.4.5.0, falling back to nearest known version 3.4.2.0"));
+    assert_eq!(
+        (decoder.active_version(), decoder.is_exact_match()),
+        (&version("3.4.2.0"), false)
+    );
+
+    // Oldest newer, on a reconnect
+    let output = logs(|| {
+        decoder.decode(&handshake(2, "3.3.0.0")).unwrap();
+    });
+    assert!(output.contains("falling back to nearest known version 3.4.0.0"));
+
+    let recorded: Vec<_> = decoder
+        .unknown_sessions()
+        .iter()
+        .map(|s| {
+            (
+                s.client_version.to_string(),
+                s.fallback_version.to_string(),
+                s.detected_at,
+            )
+        })
+        .collect();
+    assert_eq!(
+        recorded,
+        [
+            (
+                "3.4.5.0".to_string(),
+                "3.4.2.0".to_string(),
+                handshake(1, "").timestamp
+            ),
+            (
+                "3.3.0.0".to_string(),
+                "3.4.0.0".to_string(),
+                handshake(2, "").timestamp
+            ),
+        ]
+    );
+    assert_eq!(
+        decoder.unknown_sessions()[0].connection,
+        handshake(1, "").flow.connection()
+    );
+
+    // Read back, skipping a line cut short by a crash
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&log)
+        .unwrap()
+        .write_all(b"{\"connection\":")
+        .unwrap();
+    assert_eq!(
+        load_unknown_sessions(&log).unwrap(),
+        decoder.unknown_sessions()
+    );
+    assert!(load_unknown_sessions(&dir.path().join("missing.jsonl"))
+        .unwrap()
+        .is_empty());
+}
+
+/// Record a session of client 3.4.5.0, which `known` has no schemas for
+fn record_unknown_session(sessions: &Path, log: &Path, known: Arc<SchemaRegistry>) -> String {
+    let set = schemas("3.4.0.0");
+    let started_at = Utc.with_ymd_and_hms(2025, 3, 3, 20, 0, 0).unwrap();
+    let archive = SessionArchive::create(sessions, started_at, set.client_version.clone()).unwrap();
+    let decoder = Decoder::new(known)
+        .unwrap()
+        .with_unknown_version_log(log.to_path_buf());
+    let mut recorder = SessionRecorder::new(decoder, archive);
+    recorder.process(&handshake(0, "3.4.5.0")).unwrap();
+    recorder
+        .process(&frame(
+            1,
+            &set,
+            "GameStarted",
+            vec![FieldValue::UInt(7), FieldValue::UInt(1)],
+        ))
+        .unwrap();
+    recorder.finish().unwrap().id
+}
+
+#[test]
+fn logged_sessions_are_redecoded_once_their_schemas_exist() {
+    let dir = tempfile::tempdir().unwrap();
+    let (sessions, log) = (
+        dir.path().join("sessions"),
+        dir.path().join("unknown_versions.jsonl"),
+    );
+    let known = Arc::new(registry(&["3.4.0.0", "3.5.0.0"]));
+    let id = record_unknown_session(&sessions, &log, Arc::clone(&known));
+
+    let status = unknown_session_status(&sessions, &log, &known).unwrap();
+    assert_eq!(status.len(), 1);
+    assert_eq!(status[0].session.client_version, version("3.4.5.0"));
+    assert_eq!(status[0].session_id.as_deref(), Some(id.as_str()));
+    assert!(!status[0].schema_available && !status[0].redecoded);
+    // Nothing to do until the schemas arrive
+    assert!(
+        redecode_unknown_sessions(&sessions, &log, Arc::clone(&known))
+            .unwrap()
+            .is_empty()
+    );
+
+    let mut updated = registry(&["3.4.0.0", "3.5.0.0"]);
+    updated.insert(schemas("3.4.5.0"));
+    let updated = Arc::new(updated);
+    let status = unknown_session_status(&sessions, &log, &updated).unwrap();
+    assert!(status[0].schema_available && !status[0].redecoded);
+
+    let reports = redecode_unknown_sessions(&sessions, &log, Arc::clone(&updated)).unwrap();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(
+        (
+            reports[0].session_id.as_str(),
+            reports[0].exact_schema_match
+        ),
+        (id.as_str(), true)
+    );
+    assert_eq!(reports[0].decoded, 2);
+    let manifest = archive::read_manifest(&sessions.join(&id)).unwrap();
+    assert_eq!(manifest.schema_version, version("3.4.5.0"));
+
+    let status = unknown_session_status(&sessions, &log, &updated).unwrap();
+    assert!(status[0].redecoded);
+    assert!(redecode_unknown_sessions(&sessions, &log, updated)
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,144 @@
+//! Property tests for TCP stream reassembly (PROT-001: packet reordering and loss)
+
+mod common;
+
+use common::{
+    allocation_bound, largest_allocation, seeded_inputs, shuffled, split_at_cuts, TrackingAllocator,
+};
+use mtgo_replay_lib::protocol::reassembly::TcpReassembler;
+use proptest::prelude::*;
+use proptest::sample::Index;
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+/// Stream bytes: real frame streams from the corpus (possibly mutated) or random data
+fn stream_data() -> impl Strategy<Value = Vec<u8>> {
+    seeded_inputs("framing").prop_filter("empty stream", |d| !d.is_empty())
+}
+
+/// Initial sequence numbers biased towards the 32-bit wraparound point
+fn initial_seq() -> impl Strategy<Value = u32> {
+    prop_oneof![any::<u32>(), (0u32..4096).prop_map(|d| u32::MAX - d)]
+}
+
+/// (offset, bytes) pieces of a stream
+type Segments = Vec<(usize, Vec<u8>)>;
+
+/// A stream cut into segments, delivered in a random order with some duplicated
+fn delivery() -> impl Strategy<Value = (Vec<u8>, Segments, Vec<usize>)> {
+    (stream_data(), prop::collection::vec(any::<Index>(), 0..16)).prop_flat_map(|(data, cuts)| {
+        let segments = split_at_cuts(&data, &cuts);
+        let len = segments.len();
+        (
+            Just(data),
+            Just(segments),
+            shuffled(len),
+            prop::collection::vec(any::<Index>(), 0..4),
+        )
+            .prop_map(move |(data, segments, mut order, duplicates)| {
+                order.extend(duplicates.iter().map(|d| d.index(len)));
+                (data, segments, order)
+            })
+    })
+}
+
+fn reassemble(
+    reassembler: &mut TcpReassembler,
+    isn: u32,
+    segments: &[(usize, Vec<u8>)],
+    order: &[usize],
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    for &i in order {
+        let (offset, bytes) = &segments[i];
+        out.extend(reassembler.push(isn.wrapping_add(*offset as u32), false, bytes));
+    }
+    out
+}
+
+proptest! {
+    #[test]
+    fn shuffled_segments_reassemble_to_original((data, segments, order) in delivery(), isn in initial_seq()) {
+        let mut reassembler = TcpReassembler::with_initial_seq(isn);
+        let out = reassemble(&mut reassembler, isn, &segments, &order);
+
+        prop_assert_eq!(out, data);
+        prop_assert_eq!(reassembler.gap_bytes(), 0);
+    }
+
+    #[test]
+    fn syn_anchors_out_of_order_stream((data, segments, order) in delivery(), isn in initial_seq()) {
+        // The SYN consumes one sequence number; the first payload byte follows it
+        let mut reassembler = TcpReassembler::new();
+        prop_assert!(reassembler.push(isn.wrapping_sub(1), true, &[]).is_empty());
+        let out = reassemble(&mut reassembler, isn, &segments, &order);
+
+        prop_assert_eq!(out, data);
+    }
+
+    #[test]
+    fn overlapping_retransmissions_are_trimmed(
+        data in stream_data(),
+        isn in initial_seq(),
+        ranges in prop::collection::vec((any::<Index>(), any::<Index>()), 1..24),
+    ) {
+        // Arbitrary overlapping ranges followed by full coverage must yield the stream once
+        let mut reassembler = TcpReassembler::with_initial_seq(isn);
+        let mut out = Vec::new();
+        for (a, b) in ranges {
+            let (a, b) = (a.index(data.len() + 1), b.index(data.len() + 1));
+            let (start, end) = (a.min(b), a.max(b));
+            out.extend(reassembler.push(isn.wrapping_add(start as u32), false, &data[start..end]));
+        }
+        out.extend(reassembler.push(isn, false, &data));
+
+        prop_assert_eq!(out, data);
+    }
+
+    #[test]
+    fn lost_segment_is_skipped_once_buffer_limit_is_hit(
+        data in prop::collection::vec(any::<u8>(), 64..2048),
+        lost in any::<Index>(),
+        limit in 1usize..256,
+    ) {
+        let lost_at = lost.index(data.len() - 1) + 1;
+        let mut reassembler = TcpReassembler::with_initial_seq(0).with_max_buffered(limit);
+
+        let mut out = reassembler.push(0, false, &data[..1]);
+        let mut offset = lost_at;
+        while offset < data.len() {
+            let end = (offset + 16).min(data.len());
+            out.extend(reassembler.push(offset as u32, false, &data[offset..end]));
+            offset = end;
+        }
+
+        // Everything delivered is a prefix plus a suffix of the original, and the
+        // skipped range is accounted for
+        let delivered = out.len() as u64 + reassembler.gap_bytes();
+        prop_assert!(delivered <= data.len() as u64);
+        prop_assert_eq!(&out[..1], &data[..1]);
+        prop_assert!(data.ends_with(&out[1..]) || out.len() == 1);
+    }
+
+    #[test]
+    fn arbitrary_segments_never_panic(
+        segments in prop::collection::vec(
+            (any::<u32>(), any::<bool>(), prop::collection::vec(any::<u8>(), 0..512)),
+            0..32,
+        ),
+    ) {
+        let input_len: usize = segments.iter().map(|(_, _, p)| p.len()).sum();
+        let mut reassembler = TcpReassembler::new().with_max_buffered(4096);
+
+        let (delivered, largest) = largest_allocation(|| {
+            segments
+                .iter()
+                .map(|(seq, syn, payload)| reassembler.push(*seq, *syn, payload).len())
+                .sum::<usize>()
+        });
+
+        prop_assert!(delivered <= input_len);
+        prop_assert!(largest <= allocation_bound(input_len));
+    }
+}
@@ -0,0 +1,289 @@
+//! Replay annotations: editing, merging teammates' copies and library search
+
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::{at, golden_path, write_replay};
+use mtgo_replay_lib::common::error::AnnotationError;
+use mtgo_replay_lib::library::{watcher, AnnotationQuery, LibraryQuery, ReplayLibrary};
+use mtgo_replay_lib::replay::annotations::sidecar_path;
+use mtgo_replay_lib::replay::{
+    AnnotationAnchor, AnnotationDraft, MergeReport, ReplayAnnotations, ReplayReader,
+};
+use std::fs::File;
+
+fn draft(turn: u32, seq: Option<u64>, text: &str, tags: &[&str]) -> AnnotationDraft {
+    AnnotationDraft {
+        anchor: AnnotationAnchor {
+            game_id: Some(1),
+            turn,
+            seq,
+        },
+        text: text.to_string(),
+        tags: tags.iter().map(|tag| tag.to_string()).collect(),
+        bookmark: false,
+    }
+}
+
+#[test]
+fn annotations_are_edited_and_saved_beside_the_replay() {
+    let dir = tempfile::tempdir().unwrap();
+    let sidecar = sidecar_path(&dir.path().join("abc.mtgoreplay"));
+    assert_eq!(sidecar, dir.path().join("abc.annotations.json"));
+    let mut annotations = ReplayAnnotations::load(&sidecar).unwrap();
+    assert!(annotations.annotations.is_empty());
+
+    let late = annotations
+        .add(
+            "alice",
+            &draft(3, Some(9), "Should have held up mana", &["Misplay"]),
+            at(0),
+        )
+        .unwrap();
+    let early = annotations
+        .add(
+            "alice",
+            &draft(1, None, "", &["  Key   Decision ", "key decision"]),
+            at(1),
+        )
+        .unwrap();
+    assert_eq!(early.tags, ["key decision"]);
+    assert_ne!(early.id, late.id);
+    assert!(matches!(
+        annotations.add("alice", &draft(1, None, " ", &[]), at(2)),
+        Err(AnnotationError::Invalid(_))
+    ));
+    assert!(matches!(
+        annotations.add(" ", &draft(1, None, "note", &[]), at(2)),
+        Err(AnnotationError::Invalid(_))
+    ));
+
+    // Edits keep the author and move the edit time on, even if the clock went back
+    let edited = annotations
+        .update(
+            &late.id,
+            &draft(3, Some(9), "Attack first", &["misplay"]),
+            at(-5),
+        )
+        .unwrap();
+    assert_eq!(
+        (edited.author.as_str(), edited.created_at),
+        ("alice", at(0))
+    );
+    assert!(edited.updated_at > late.updated_at);
+
+    annotations.save(&sidecar).unwrap();
+    let mut loaded = ReplayAnnotations::load(&sidecar).unwrap();
+    assert_eq!(loaded, annotations);
+    let ids: Vec<&str> = loaded.visible().iter().map(|a| a.id.as_str()).collect();
+    assert_eq!(ids, [early.id.as_str(), late.id.as_str()]);
+
+    loaded.delete(&early.id, at(3)).unwrap();
+    assert_eq!(loaded.visible().len(), 1);
+    assert!(matches!(
+        loaded.delete(&early.id, at(4)),
+        Err(AnnotationError::NotFound(_))
+    ));
+    let tombstone = loaded
+        .annotations
+        .iter()
+        .find(|a| a.id == early.id)
+        .unwrap();
+    assert!(tombstone.deleted && tombstone.tags.is_empty());
+
+    std::fs::write(&sidecar, "{\"version\": 2}").unwrap();
+    assert!(matches!(
+        ReplayAnnotations::load(&sidecar),
+        Err(AnnotationError::Corrupt { .. })
+    ));
+}
+
+#[test]
+fn teammates_annotations_merge_to_the_same_result_either_way() {
+    let mut base = ReplayAnnotations::default();
+    let shared = base
+        .add(
+            "alice",
+            &draft(2, Some(5), "Bolt the creature?", &[]),
+            at(0),
+        )
+        .unwrap();
+    let doomed = base
+        .add("alice", &draft(4, None, "Long turn", &[]), at(1))
+        .unwrap();
+
+    let mut alice = base.clone();
+    let mut bob = base.clone();
+    alice
+        .update(
+            &shared.id,
+            &draft(2, Some(5), "Bolt the creature", &["key decision"]),
+            at(10),
+        )
+        .unwrap();
+    bob.update(
+        &shared.id,
+        &draft(2, Some(5), "Bolt face instead", &["misplay"]),
+        at(20),
+    )
+    .unwrap();
+    bob.add("bob", &draft(5, Some(8), "", &["misplay"]), at(21))
+        .unwrap();
+    alice.delete(&doomed.id, at(5)).unwrap();
+    bob.update(&doomed.id, &draft(4, None, "Long turn, fine", &[]), at(5))
+        .unwrap();
+
+    let mut alice_merged = alice.clone();
+    let report = alice_merged.merge(&bob);
+    assert_eq!((report.added, report.updated, report.deleted), (1, 1, 0));
+    let mut bob_merged = bob.clone();
+    let report = bob_merged.merge(&alice);
+    assert_eq!((report.added, report.updated, report.deleted), (0, 0, 1));
+
+    let sorted = |annotations: &ReplayAnnotations| {
+        let mut all = annotations.annotations.clone();
+        all.sort_by(|a, b| a.id.cmp(&b.id));
+        all
+    };
+    assert_eq!(sorted(&alice_merged), sorted(&bob_merged));
+    let visible = alice_merged.visible();
+    assert_eq!(visible.len(), 2);
+    // The later edit wins, and a deletion wins a tie
+    assert_eq!(visible[0].text, "Bolt face instead");
+    assert_eq!(visible[1].author, "bob");
+
+    // Merging again changes nothing
+    assert_eq!(alice_merged.merge(&bob), MergeReport::default());
+    assert_eq!(alice_merged.merge(&alice), MergeReport::default());
+}
+
+#[test]
+fn library_finds_annotations_by_tag_author_format_and_date() {
+    let dir = tempfile::tempdir().unwrap();
+    let modern = ReplayReader::open(&golden_path())
+        .unwrap()
+        .match_record()
+        .unwrap()
+        .unwrap();
+    let mut legacy = modern.clone();
+    legacy.id = 9002;
+    legacy.format = "Legacy".to_string();
+    legacy.started_at += Duration::days(1);
+    let modern_path = write_replay(dir.path(), &modern);
+    let legacy_path = write_replay(dir.path(), &legacy);
+
+    let mut on_modern = ReplayAnnotations::default();
+    let misplay = on_modern
+        .add(
+            "alice",
+            &draft(1, Some(6), "Missed the land drop", &["misplay"]),
+            at(0),
+        )
+        .unwrap();
+    on_modern
+        .add(
+            "bob",
+            &draft(1, None, "Fine keep", &["misplay", "mulligan"]),
+            at(1),
+        )
+        .unwrap();
+    on_modern.save(&sidecar_path(&modern_path)).unwrap();
+    let mut on_legacy = ReplayAnnotations::default();
+    on_legacy
+        .add(
+            "alice",
+            &draft(1, None, "Wrong target", &["Misplay"]),
+            at(2),
+        )
+        .unwrap();
+    on_legacy.save(&sidecar_path(&legacy_path)).unwrap();
+
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    watcher::scan(&mut library, dir.path(), None).unwrap();
+    let search =
+        |library: &ReplayLibrary, query: LibraryQuery, filter: AnnotationQuery| -> Vec<String> {
+            library
+                .annotations(&query, &filter)
+                .unwrap()
+                .into_iter()
+                .map(|found| found.annotation.text)
+                .collect()
+        };
+    let my_misplays = AnnotationQuery {
+        tags: vec!["MISPLAY".to_string()],
+        author: Some("Alice".to_string()),
+        ..AnnotationQuery::default()
+    };
+    assert_eq!(
+        search(&library, LibraryQuery::default(), my_misplays.clone()),
+        ["Wrong target", "Missed the land drop"]
+    );
+    let modern_this_month = LibraryQuery {
+        format: Some("modern".to_string()),
+        from: Some(Utc.with_ymd_and_hms(2025, 3, 1, 0, 0, 0).unwrap()),
+        to: Some(Utc.with_ymd_and_hms(2025, 4, 1, 0, 0, 0).unwrap()),
+        ..LibraryQuery::default()
+    };
+    let found = library
+        .annotations(&modern_this_month, &my_misplays)
+        .unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(
+        (found[0].path.clone(), found[0].game_number),
+        (modern_path.clone(), Some(1))
+    );
+    assert_eq!(found[0].annotation, misplay);
+
+    // Turn notes come before the notes on the turn's events
+    let tagged = AnnotationQuery {
+        tags: vec!["misplay".to_string(), "mulligan".to_string()],
+        ..AnnotationQuery::default()
+    };
+    assert_eq!(
+        search(&library, modern_this_month.clone(), tagged),
+        ["Fine keep"]
+    );
+    assert_eq!(
+        search(
+            &library,
+            modern_this_month.clone(),
+            AnnotationQuery::default()
+        ),
+        ["Fine keep", "Missed the land drop"]
+    );
+
+    // Editing the annotations file re-indexes the replay on the next scan
+    on_modern.delete(&misplay.id, at(3)).unwrap();
+    on_modern.save(&sidecar_path(&modern_path)).unwrap();
+    let future = std::time::SystemTime::now() + std::time::Duration::from_secs(5);
+    File::options()
+        .write(true)
+        .open(sidecar_path(&modern_path))
+        .unwrap()
+        .set_modified(future)
+        .unwrap();
+    let report = watcher::scan(&mut library, dir.path(), None).unwrap();
+    assert_eq!((report.indexed, report.unchanged), (1, 1));
+    assert!(library
+        .annotations(&modern_this_month, &my_misplays)
+        .unwrap()
+        .is_empty());
+
+    // Or right away, for an edit made in the app
+    on_modern
+        .add(
+            "alice",
+            &draft(2, None, "Should have bottomed the land", &["misplay"]),
+            at(4),
+        )
+        .unwrap();
+    on_modern.save(&sidecar_path(&modern_path)).unwrap();
+    assert!(library.index_annotations(&modern_path).unwrap());
+    assert!(!library
+        .index_annotations(&dir.path().join("missing.mtgoreplay"))
+        .unwrap());
+    assert_eq!(
+        search(&library, modern_this_month, my_misplays),
+        ["Should have bottomed the land"]
+    );
+}
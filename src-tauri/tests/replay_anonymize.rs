@@ -0,0 +1,356 @@
+//! Anonymizing replays for sharing (XPORT-001)
+
+mod common;
+
+use chrono::Utc;
+use common::frame;
+use mtgo_replay_lib::game::deck::{Deck, IntendedDeck};
+use mtgo_replay_lib::game::engine::GameEngine;
+use mtgo_replay_lib::game::event::GameEventKind;
+use mtgo_replay_lib::game::lifecycle::Match;
+use mtgo_replay_lib::protocol::decoder::Decoder;
+use mtgo_replay_lib::protocol::frame::Frame;
+use mtgo_replay_lib::protocol::schema::{FieldValue, SchemaSet};
+use mtgo_replay_lib::protocol::version::SchemaRegistry;
+use mtgo_replay_lib::replay::anonymize::{self, Pseudonymizer};
+use mtgo_replay_lib::replay::migrate::{self, ReplayContents};
+use mtgo_replay_lib::replay::{ReplayHeader, ReplayMatch};
+use std::sync::Arc;
+
+const BASELINE: &str = include_str!("../schemas/baseline.json");
+
+/// Built-in schemas plus a chat message, which the baseline does not describe yet
+fn registry() -> Arc<SchemaRegistry> {
+    let mut json: serde_json::Value = serde_json::from_str(BASELINE).unwrap();
+    json["messages"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({
+            "type_id": 400,
+            "name": "ChatMessage",
+            "fields": [
+                { "name": "sender", "type": "string" },
+                { "name": "text", "type": "string" }
+            ]
+        }));
+    let mut registry = SchemaRegistry::new();
+    registry.insert(SchemaSet::from_json(&json.to_string()).unwrap());
+    Arc::new(registry)
+}
+
+fn text(s: &str) -> FieldValue {
+    FieldValue::String(s.to_string())
+}
+
+fn frames(set: &SchemaSet) -> Vec<Frame> {
+    vec![
+        frame(0, set, "Login", vec![text("Alice"), text("3.4.0.0")]),
+        frame(
+            1,
+            set,
+            "MatchStarted",
+            vec![
+                FieldValue::UInt(9),
+                text("Modern"),
+                text("league"),
+                FieldValue::UInt(3),
+                text("Alice"),
+                text("Bob"),
+            ],
+        ),
+        frame(
+            2,
+            set,
+            "GameStarted",
+            vec![FieldValue::UInt(7), FieldValue::UInt(1)],
+        ),
+        frame(
+            3,
+            set,
+            "PlayerJoined",
+            vec![
+                FieldValue::UInt(1),
+                FieldValue::UInt(0),
+                text("Alice"),
+                FieldValue::Int(20),
+            ],
+        ),
+        frame(
+            4,
+            set,
+            "PlayerJoined",
+            vec![
+                FieldValue::UInt(2),
+                FieldValue::UInt(1),
+                text("Bob"),
+                FieldValue::Int(20),
+            ],
+        ),
+        frame(5, set, "ChatMessage", vec![text("Bob"), text("gl hf")]),
+        frame(
+            6,
+            set,
+            "LifeTotal",
+            vec![FieldValue::UInt(2), FieldValue::Int(17)],
+        ),
+        // A game message that mentions a player in free text
+        frame(
+            7,
+            set,
+            "GameEnded",
+            vec![
+                FieldValue::UInt(7),
+                FieldValue::UInt(1),
+                text("bob conceded"),
+            ],
+        ),
+    ]
+}
+
+/// A replay as the capture pipeline would record it from `frames`
+fn contents(registry: &Arc<SchemaRegistry>) -> ReplayContents {
+    let frames = frames(registry.latest().unwrap());
+    let mut decoder = Decoder::new(registry.clone()).unwrap();
+    let mut engine = GameEngine::new();
+    let mut events = Vec::new();
+    let mut snapshots = Vec::new();
+    for frame in &frames {
+        let Ok(message) = decoder.decode(frame) else {
+            continue;
+        };
+        if let Ok(update) = engine.apply(frame, &message) {
+            events.extend(update.events);
+            snapshots.extend(update.snapshot);
+        }
+    }
+
+    let started_at = frames[0].timestamp;
+    let header = ReplayHeader {
+        session_id: Some("20250303-200000".to_string()),
+        match_info: Some(ReplayMatch {
+            match_id: 9,
+            format: "Modern".to_string(),
+            event_type: "league".to_string(),
+            best_of: 3,
+            local_player: "Alice".to_string(),
+            opponent: "Bob".to_string(),
+        }),
+        ..ReplayHeader::new(started_at)
+    };
+    let match_record: Match = serde_json::from_value(serde_json::json!({
+        "id": 9,
+        "format": "Modern",
+        "event_type": "league",
+        "event_name": "Modern League",
+        "best_of": 3,
+        "local_player": "Alice",
+        "opponent": "Bob",
+        "started_at": started_at,
+        "ended_at": null,
+        "games": [],
+        "result": null,
+        "end_reason": null,
+        "session_id": "20250303-200000"
+    }))
+    .unwrap();
+
+    ReplayContents {
+        version: (1, 1),
+        header,
+        events,
+        snapshots,
+        match_record: Some(match_record),
+        frames,
+        unknown_chunks: Vec::new(),
+        signed: false,
+    }
+}
+
+fn pseudonyms() -> Pseudonymizer {
+    Pseudonymizer::new([7; 32])
+}
+
+#[test]
+fn pseudonyms_are_stable_and_case_insensitive() {
+    let a = pseudonyms();
+    assert_eq!(a.pseudonym("Alice"), a.pseudonym(" alice "));
+    assert_ne!(a.pseudonym("Alice"), a.pseudonym("Bob"));
+    assert!(a.pseudonym("Alice").starts_with("Player-"));
+    assert_ne!(
+        a.pseudonym("Alice"),
+        Pseudonymizer::new([8; 32]).pseudonym("Alice")
+    );
+}
+
+#[test]
+fn key_file_is_created_once_and_reused() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("keys/anonymize.key");
+    let first = Pseudonymizer::load_or_create(&path)
+        .unwrap()
+        .pseudonym("Alice");
+    let second = Pseudonymizer::load_or_create(&path)
+        .unwrap()
+        .pseudonym("Alice");
+    assert_eq!(first, second);
+
+    std::fs::write(&path, "not a key").unwrap();
+    assert!(Pseudonymizer::load_or_create(&path).is_err());
+}
+
+#[test]
+fn names_are_replaced_everywhere() {
+    let registry = registry();
+    let mut contents = contents(&registry);
+    let alice = pseudonyms().pseudonym("Alice");
+    let bob = pseudonyms().pseudonym("Bob");
+    let redactions =
+        anonymize::anonymize(&mut contents, &pseudonyms(), Some(&registry), Utc::now());
+
+    let info = contents.header.match_info.as_ref().unwrap();
+    assert_eq!(
+        (info.local_player.as_str(), info.opponent.as_str()),
+        (alice.as_str(), bob.as_str())
+    );
+    assert_eq!(contents.header.session_id, None);
+
+    let joined: Vec<&str> = contents
+        .events
+        .iter()
+        .filter_map(|e| match &e.kind {
+            GameEventKind::PlayerJoined { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(joined, [alice.as_str(), bob.as_str()]);
+    for snapshot in &contents.snapshots {
+        for player in snapshot.state.players.values() {
+            assert!(
+                player.name == alice || player.name == bob,
+                "{}",
+                player.name
+            );
+        }
+    }
+    let record = contents.match_record.as_ref().unwrap();
+    assert_eq!(record.local_player.as_deref(), Some(alice.as_str()));
+    assert_eq!(record.opponent.as_deref(), Some(bob.as_str()));
+    assert_eq!(record.session_id, None);
+
+    assert_eq!(redactions.players_renamed, 2);
+    assert!(redactions.session_id_removed);
+    assert_eq!(contents.header.redactions.as_ref(), Some(&redactions));
+}
+
+#[test]
+fn names_in_free_text_are_replaced() {
+    let registry = registry();
+    let mut contents = contents(&registry);
+    let record = contents.match_record.as_mut().unwrap();
+    record.end_reason = Some("Bob left the match".to_string());
+    record.games.push(
+        serde_json::from_value(serde_json::json!({
+            "game_id": 7,
+            "number": 1,
+            "started_at": "2025-03-03T20:00:02Z",
+            "ended_at": "2025-03-03T20:00:07Z",
+            "on_play": true,
+            "mulligans": 0,
+            "opponent_mulligans": 0,
+            "turns": 1,
+            "result": "win",
+            "end_reason": "BOB conceded to alice"
+        }))
+        .unwrap(),
+    );
+    record.intended_deck = Some(IntendedDeck {
+        name: "Alice's Burn".to_string(),
+        deck: Deck::default(),
+        imported_at: Utc::now(),
+    });
+    anonymize::anonymize(&mut contents, &pseudonyms(), Some(&registry), Utc::now());
+    let alice = pseudonyms().pseudonym("Alice");
+    let bob = pseudonyms().pseudonym("Bob");
+
+    let reasons: Vec<&str> = contents
+        .events
+        .iter()
+        .filter_map(|e| match &e.kind {
+            GameEventKind::GameEnded { reason, .. } => Some(reason.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(reasons, [format!("{} conceded", bob)]);
+    let record = contents.match_record.as_ref().unwrap();
+    assert_eq!(record.end_reason, Some(format!("{} left the match", bob)));
+    assert_eq!(
+        record.games[0].end_reason,
+        Some(format!("{} conceded to {}", bob, alice))
+    );
+    assert_eq!(
+        record.intended_deck.as_ref().unwrap().name,
+        format!("{}'s Burn", alice)
+    );
+}
+
+#[test]
+fn frames_are_scrubbed() {
+    let registry = registry();
+    let mut contents = contents(&registry);
+    let redactions =
+        anonymize::anonymize(&mut contents, &pseudonyms(), Some(&registry), Utc::now());
+
+    // Login, chat and the free-text mention of a player are gone; names in the
+    // remaining messages are rewritten
+    let kept: Vec<u64> = contents.frames.iter().map(|f| f.index).collect();
+    assert_eq!(kept, [1, 2, 3, 4, 6]);
+    assert_eq!(redactions.chat_removed, 1);
+    assert_eq!(redactions.frames_removed, 2);
+    assert_eq!(redactions.frames_rewritten, 3);
+    assert!(redactions.network_addresses_removed);
+
+    for frame in &contents.frames {
+        assert_eq!(frame.flow.src_port, 0);
+        assert_eq!(frame.flow.dst_port, 0);
+        assert!(frame.flow.src_addr.is_unspecified() && frame.flow.dst_addr.is_unspecified());
+        let payload = String::from_utf8_lossy(&frame.payload).to_lowercase();
+        assert!(!payload.contains("alice") && !payload.contains("bob"));
+    }
+
+    // What is left still decodes to the same game
+    let mut decoder = Decoder::new(registry.clone()).unwrap();
+    let joined = decoder.decode(&contents.frames[3]).unwrap();
+    assert_eq!(
+        joined.field("name").and_then(FieldValue::as_str),
+        Some(pseudonyms().pseudonym("Bob").as_str())
+    );
+}
+
+#[test]
+fn frames_are_dropped_without_schemas() {
+    let registry = registry();
+    let mut contents = contents(&registry);
+    let count = contents.frames.len() as u64;
+    let redactions = anonymize::anonymize(&mut contents, &pseudonyms(), None, Utc::now());
+    assert!(contents.frames.is_empty());
+    assert_eq!(redactions.frames_removed, count);
+}
+
+#[test]
+fn anonymized_copy_is_written_next_to_the_original() {
+    let registry = registry();
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("match.mtgoreplay");
+    migrate::save(&contents(&registry), &path).unwrap();
+    let original = std::fs::read(&path).unwrap();
+
+    let output = anonymize::default_output(&path);
+    assert_eq!(output, dir.path().join("match.anon.mtgoreplay"));
+    let report = anonymize::anonymize_file(&path, &output, &pseudonyms(), Some(&registry)).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), original);
+
+    let shared = migrate::load(&report.output).unwrap();
+    assert_eq!(shared.header.redactions, Some(report.redactions));
+    let bytes = std::fs::read(&output).unwrap();
+    assert!(!bytes.windows(5).any(|w| w == b"Alice"));
+}
@@ -0,0 +1,443 @@
+//! Compatibility suite for replays written by every released format version
+//!
+//! `tests/compat/v<major>.<minor>/` holds replays written by that format version.
+//! Every sample must load, migrate to the current version and read back with the
+//! same contents. When the format version changes, add samples written by the
+//! outgoing version here and never edit existing ones.
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::frame;
+use mtgo_replay_lib::common::error::ReplayError;
+use mtgo_replay_lib::game::engine::GameEngine;
+use mtgo_replay_lib::game::event::GameEvent;
+use mtgo_replay_lib::protocol::archive::{self, SessionArchive};
+use mtgo_replay_lib::protocol::decoder::Decoder;
+use mtgo_replay_lib::protocol::frame::Frame;
+use mtgo_replay_lib::protocol::schema::{FieldValue, SchemaSet};
+use mtgo_replay_lib::protocol::version::SchemaRegistry;
+use mtgo_replay_lib::replay::format::{FORMAT_MAJOR, FORMAT_MINOR};
+use mtgo_replay_lib::replay::migrate::{self, MigrateOptions, MigrationStatus, Redecode};
+use mtgo_replay_lib::replay::signing::{self, Keyring, SignatureStatus, SigningIdentity};
+use mtgo_replay_lib::replay::{ReplayHeader, ReplayReader, ReplayWriter};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// What each sample is known to contain
+struct Expected {
+    file: &'static str,
+    events: usize,
+    snapshots: usize,
+    match_record: bool,
+    frames: usize,
+    unknown_chunks: usize,
+}
+
+const SAMPLES: &[Expected] = &[
+    Expected {
+        file: "v1.0/complete.mtgoreplay",
+        events: 11,
+        snapshots: 1,
+        match_record: true,
+        frames: 0,
+        unknown_chunks: 1,
+    },
+    Expected {
+        file: "v1.0/unfinished.mtgoreplay",
+        events: 11,
+        snapshots: 1,
+        match_record: true,
+        frames: 0,
+        unknown_chunks: 1,
+    },
+    Expected {
+        file: "v1.0/multi_chunk.mtgoreplay",
+        events: 1300,
+        snapshots: 3,
+        match_record: false,
+        frames: 0,
+        unknown_chunks: 1,
+    },
+    Expected {
+        file: "v1.1/complete.mtgoreplay",
+        events: 11,
+        snapshots: 1,
+        match_record: true,
+        frames: 3,
+        unknown_chunks: 1,
+    },
+    Expected {
+        file: "v1.2/signed.mtgoreplay",
+        events: 11,
+        snapshots: 1,
+        match_record: true,
+        frames: 3,
+        unknown_chunks: 1,
+    },
+];
+
+fn compat_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compat")
+}
+
+/// Every sample file, so a sample added without an expectation is still checked
+fn sample_files() -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for version in std::fs::read_dir(compat_dir()).unwrap() {
+        let version = version.unwrap().path();
+        if version.is_dir() {
+            files.extend(
+                std::fs::read_dir(&version)
+                    .unwrap()
+                    .map(|f| f.unwrap().path()),
+            );
+        }
+    }
+    files.sort();
+    files
+}
+
+/// Format version named by the sample's directory, e.g. `v1.0` -> (1, 0)
+fn directory_version(path: &Path) -> (u16, u16) {
+    let dir = path
+        .parent()
+        .unwrap()
+        .file_name()
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let (major, minor) = dir.strip_prefix('v').unwrap().split_once('.').unwrap();
+    (major.parse().unwrap(), minor.parse().unwrap())
+}
+
+#[test]
+fn every_sample_has_an_expectation() {
+    for path in sample_files() {
+        let relative = path.strip_prefix(compat_dir()).unwrap();
+        assert!(
+            SAMPLES.iter().any(|s| Path::new(s.file) == relative),
+            "{} has no entry in SAMPLES",
+            relative.display()
+        );
+    }
+}
+
+#[test]
+fn samples_load_with_their_version_reader() {
+    for sample in SAMPLES {
+        let path = compat_dir().join(sample.file);
+        let contents = migrate::load(&path).unwrap_or_else(|e| panic!("{}: {}", sample.file, e));
+        assert_eq!(
+            contents.version,
+            directory_version(&path),
+            "{}",
+            sample.file
+        );
+        assert_eq!(contents.events.len(), sample.events, "{}", sample.file);
+        assert_eq!(
+            contents.snapshots.len(),
+            sample.snapshots,
+            "{}",
+            sample.file
+        );
+        assert_eq!(
+            contents.match_record.is_some(),
+            sample.match_record,
+            "{}",
+            sample.file
+        );
+        assert_eq!(contents.frames.len(), sample.frames, "{}", sample.file);
+        assert_eq!(
+            contents.unknown_chunks.len(),
+            sample.unknown_chunks,
+            "{}",
+            sample.file
+        );
+        for (i, event) in contents.events.iter().enumerate().skip(1) {
+            assert!(event.seq > contents.events[i - 1].seq, "{}", sample.file);
+        }
+    }
+}
+
+#[test]
+fn samples_migrate_to_the_current_version_unchanged() {
+    let out = tempfile::tempdir().unwrap();
+    let options = MigrateOptions {
+        force: true,
+        ..MigrateOptions::default()
+    };
+    for path in sample_files() {
+        let output = out.path().join(path.file_name().unwrap());
+        let report = migrate::migrate(&path, Some(&output), &options).unwrap();
+        assert_eq!(report.status, MigrationStatus::Migrated);
+        assert_eq!(
+            report.to_version,
+            format!("{}.{}", FORMAT_MAJOR, FORMAT_MINOR)
+        );
+
+        let before = migrate::load(&path).unwrap();
+        let after = migrate::load(&output).unwrap();
+        assert_eq!(after.version, (FORMAT_MAJOR, FORMAT_MINOR));
+        assert_eq!(after.header, before.header);
+        assert_eq!(after.events, before.events);
+        assert_eq!(after.snapshots, before.snapshots);
+        assert_eq!(after.match_record, before.match_record);
+        assert_eq!(after.frames, before.frames);
+        assert_eq!(after.unknown_chunks.len(), before.unknown_chunks.len());
+        for ((_, a), (_, b)) in after.unknown_chunks.iter().zip(&before.unknown_chunks) {
+            assert_eq!(a, b);
+        }
+
+        // Migration also finishes replays that were never finished
+        assert!(ReplayReader::open(&output).unwrap().is_complete());
+    }
+}
+
+#[test]
+fn signed_sample_still_verifies() {
+    let path = compat_dir().join("v1.2/signed.mtgoreplay");
+    let report = signing::verify_file(&path, &Keyring::default(), None).unwrap();
+    assert_eq!(report.status, SignatureStatus::Valid);
+    assert!(!report.header_modified && report.modified_chunks.is_empty());
+    assert_eq!(report.trusted_as, None);
+}
+
+#[test]
+fn current_replays_are_left_alone_unless_forced() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("complete.mtgoreplay");
+    let sample = compat_dir().join("v1.0/complete.mtgoreplay");
+    migrate::migrate(&sample, Some(&path), &MigrateOptions::default()).unwrap();
+    let original = std::fs::read(&path).unwrap();
+
+    let options = MigrateOptions {
+        backup: true,
+        ..MigrateOptions::default()
+    };
+    let report = migrate::migrate(&path, None, &options).unwrap();
+    assert_eq!(report.status, MigrationStatus::UpToDate);
+    assert_eq!(report.backup, None);
+    assert_eq!(std::fs::read(&path).unwrap(), original);
+
+    let forced = MigrateOptions {
+        force: true,
+        backup: true,
+        ..MigrateOptions::default()
+    };
+    let report = migrate::migrate(&path, None, &forced).unwrap();
+    assert_eq!(report.status, MigrationStatus::Migrated);
+    let backup = report.backup.unwrap();
+    assert_eq!(std::fs::read(backup).unwrap(), original);
+    assert!(ReplayReader::open(&path).is_ok());
+}
+
+#[test]
+fn unknown_major_version_is_rejected() {
+    let mut bytes = std::fs::read(compat_dir().join("v1.0/complete.mtgoreplay")).unwrap();
+    bytes[8..10].copy_from_slice(&0u16.to_le_bytes());
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("v0.mtgoreplay");
+    std::fs::write(&path, bytes).unwrap();
+
+    assert!(matches!(
+        migrate::load(&path),
+        Err(ReplayError::UnsupportedVersion { major: 0, minor: 0 })
+    ));
+}
+
+/// A short game as the current schemas encode it
+fn game_frames(set: &SchemaSet) -> Vec<Frame> {
+    vec![
+        frame(
+            0,
+            set,
+            "GameStarted",
+            vec![FieldValue::UInt(7), FieldValue::UInt(1)],
+        ),
+        frame(
+            1,
+            set,
+            "PlayerJoined",
+            vec![
+                FieldValue::UInt(1),
+                FieldValue::UInt(0),
+                FieldValue::String("alice".to_string()),
+                FieldValue::Int(20),
+            ],
+        ),
+        frame(
+            2,
+            set,
+            "LifeTotal",
+            vec![FieldValue::UInt(1), FieldValue::Int(18)],
+        ),
+        frame(
+            3,
+            set,
+            "GameEnded",
+            vec![
+                FieldValue::UInt(7),
+                FieldValue::UInt(1),
+                FieldValue::String("concede".to_string()),
+            ],
+        ),
+    ]
+}
+
+/// Events the current schemas produce from `frames`
+fn decode_all(registry: &Arc<SchemaRegistry>, frames: &[Frame]) -> Vec<GameEvent> {
+    let mut decoder = Decoder::new(registry.clone()).unwrap();
+    let mut engine = GameEngine::new();
+    frames
+        .iter()
+        .flat_map(|f| engine.apply(f, &decoder.decode(f).unwrap()).unwrap().events)
+        .collect()
+}
+
+#[test]
+fn redecode_rebuilds_events_from_the_session_archive() {
+    let sessions = tempfile::tempdir().unwrap();
+    let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+    let set = registry.latest().unwrap().clone();
+    let started_at = Utc.with_ymd_and_hms(2025, 3, 3, 20, 0, 0).unwrap();
+    let mut session =
+        SessionArchive::create(sessions.path(), started_at, set.client_version.clone()).unwrap();
+    for frame in &game_frames(&set) {
+        session.append_frame(frame).unwrap();
+    }
+    let session_id = session.id().to_string();
+    session.finish(started_at).unwrap();
+    let expected = decode_all(
+        &registry,
+        &archive::read_all_frames(&sessions.path().join(&session_id)).unwrap(),
+    );
+
+    // A replay recorded by a build that only understood the first and last messages
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("old.mtgoreplay");
+    let header = ReplayHeader {
+        session_id: Some(session_id),
+        ..ReplayHeader::new(started_at)
+    };
+    let mut writer = ReplayWriter::new(Vec::new(), &header).unwrap();
+    writer.write_event(&expected[0]).unwrap();
+    writer.write_event(expected.last().unwrap()).unwrap();
+    std::fs::write(&path, writer.finish().unwrap()).unwrap();
+
+    let options = MigrateOptions {
+        redecode: Some(Redecode {
+            registry,
+            sessions_dir: Some(sessions.path().to_path_buf()),
+        }),
+        ..MigrateOptions::default()
+    };
+    let report = migrate::migrate(&path, None, &options).unwrap();
+    assert!(report.redecoded);
+    assert_eq!(report.events_before, 2);
+    assert_eq!(report.events_after, expected.len());
+
+    let contents = migrate::load(&path).unwrap();
+    assert_eq!(contents.events, expected);
+    assert_eq!(
+        contents.header.schema_version.as_ref(),
+        Some(&set.client_version)
+    );
+}
+
+#[test]
+fn migrating_a_signed_replay_reports_the_removed_signature() {
+    let dir = tempfile::tempdir().unwrap();
+    let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+    let frames = game_frames(registry.latest().unwrap());
+    let events = decode_all(&registry, &frames);
+
+    // A current replay with its frames, signed
+    let path = dir.path().join("signed.mtgoreplay");
+    let mut writer =
+        ReplayWriter::new(Vec::new(), &ReplayHeader::new(frames[0].timestamp)).unwrap();
+    writer.write_event(&events[0]).unwrap();
+    for frame in &frames {
+        writer.write_frame(frame).unwrap();
+    }
+    std::fs::write(&path, writer.finish().unwrap()).unwrap();
+    signing::sign_file(&path, &SigningIdentity::generate().unwrap()).unwrap();
+
+    // Left alone, it keeps its signature
+    let report = migrate::migrate(&path, None, &MigrateOptions::default()).unwrap();
+    assert_eq!(
+        (report.status, report.signature_removed),
+        (MigrationStatus::UpToDate, false)
+    );
+    assert!(migrate::load(&path).unwrap().signed);
+
+    let forced = MigrateOptions {
+        force: true,
+        ..MigrateOptions::default()
+    };
+    let redecoded = MigrateOptions {
+        redecode: Some(Redecode {
+            registry,
+            sessions_dir: None,
+        }),
+        ..MigrateOptions::default()
+    };
+    for (name, options) in [("forced", forced), ("redecoded", redecoded)] {
+        let output = dir.path().join(format!("{}.mtgoreplay", name));
+        let report = migrate::migrate(&path, Some(&output), &options).unwrap();
+        assert_eq!(report.status, MigrationStatus::Migrated, "{}", name);
+        assert!(report.signature_removed, "{}", name);
+        assert!(!migrate::load(&output).unwrap().signed, "{}", name);
+        let verified = signing::verify_file(&output, &Keyring::default(), None).unwrap();
+        assert_eq!(verified.status, SignatureStatus::Unsigned, "{}", name);
+    }
+
+    // Nothing is reported for a replay that was not signed
+    let forced = MigrateOptions {
+        force: true,
+        ..MigrateOptions::default()
+    };
+    let report = migrate::migrate(&dir.path().join("forced.mtgoreplay"), None, &forced).unwrap();
+    assert!(!report.signature_removed);
+}
+
+#[test]
+fn redecode_prefers_frames_embedded_in_the_replay() {
+    let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+    let set = registry.latest().unwrap().clone();
+    let frames = game_frames(&set);
+    let expected = decode_all(&registry, &frames);
+
+    // Recorded mid-session (events numbered from 40) by a build that decoded one message
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("embedded.mtgoreplay");
+    let mut writer =
+        ReplayWriter::new(Vec::new(), &ReplayHeader::new(frames[0].timestamp)).unwrap();
+    writer
+        .write_event(&GameEvent {
+            seq: 40,
+            ..expected[0].clone()
+        })
+        .unwrap();
+    for frame in &frames {
+        writer.write_frame(frame).unwrap();
+    }
+    std::fs::write(&path, writer.finish().unwrap()).unwrap();
+
+    let options = MigrateOptions {
+        redecode: Some(Redecode {
+            registry,
+            sessions_dir: None,
+        }),
+        ..MigrateOptions::default()
+    };
+    migrate::migrate(&path, None, &options).unwrap();
+
+    let contents = migrate::load(&path).unwrap();
+    assert_eq!(contents.frames, frames);
+    assert_eq!(contents.events.len(), expected.len());
+    for (event, expected) in contents.events.iter().zip(&expected) {
+        assert_eq!(event.seq, expected.seq + 40);
+        assert_eq!(event.kind, expected.kind);
+    }
+}
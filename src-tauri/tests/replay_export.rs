@@ -0,0 +1,285 @@
+//! JSON and CSV export of replays
+
+use chrono::{TimeZone, Utc};
+use mtgo_replay_lib::export::{self, csv, json, ExportFormat, JSON_SCHEMA};
+use mtgo_replay_lib::game::event::{GameEvent, GameEventKind};
+use mtgo_replay_lib::game::model::Zone;
+use mtgo_replay_lib::replay::{ReplayHeader, ReplayReader, ReplayWriter};
+use serde_json::Value;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/replay_v1.mtgoreplay")
+}
+
+/// Check `value` against the subset of JSON Schema the export schema uses
+fn validate(value: &Value, schema: &Value, root: &Value, at: &str) -> Result<(), String> {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.strip_prefix("#/$defs/").expect("local reference");
+        return validate(value, &root["$defs"][name], root, at);
+    }
+    if let Some(options) = schema.get("anyOf").and_then(Value::as_array) {
+        if !options
+            .iter()
+            .any(|option| validate(value, option, root, at).is_ok())
+        {
+            return Err(format!("{}: no anyOf alternative matches {}", at, value));
+        }
+    }
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            return Err(format!("{}: expected {}, got {}", at, expected, value));
+        }
+    }
+    if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+        if !allowed.contains(value) {
+            return Err(format!("{}: {} not in enum", at, value));
+        }
+    }
+    if let Some(types) = schema.get("type") {
+        let types: Vec<&str> = match types {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(Value::as_str).collect(),
+            _ => panic!("bad type keyword"),
+        };
+        let matches = |t: &&str| match *t {
+            "null" => value.is_null(),
+            "boolean" => value.is_boolean(),
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "string" => value.is_string(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            other => panic!("unknown type {}", other),
+        };
+        if !types.iter().any(matches) {
+            return Err(format!("{}: {} is not {:?}", at, value, types));
+        }
+    }
+    if let (Some(minimum), Some(n)) = (
+        schema.get("minimum").and_then(Value::as_f64),
+        value.as_f64(),
+    ) {
+        if n < minimum {
+            return Err(format!("{}: {} below minimum {}", at, n, minimum));
+        }
+    }
+    if let Some(object) = value.as_object() {
+        for key in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let key = key.as_str().unwrap();
+            if !object.contains_key(key) {
+                return Err(format!("{}: missing {}", at, key));
+            }
+        }
+        for (key, field) in object {
+            let at = format!("{}/{}", at, key);
+            if let Some(property) = schema.get("properties").and_then(|p| p.get(key)) {
+                validate(field, property, root, &at)?;
+            } else if let Some(additional) = schema.get("additionalProperties") {
+                validate(field, additional, root, &at)?;
+            }
+        }
+    }
+    if let (Some(items), Some(array)) = (schema.get("items"), value.as_array()) {
+        for (i, item) in array.iter().enumerate() {
+            validate(item, items, root, &format!("{}/{}", at, i))?;
+        }
+    }
+    Ok(())
+}
+
+fn read_csv(path: &Path) -> Vec<Vec<String>> {
+    std::fs::read_to_string(path)
+        .unwrap()
+        .lines()
+        .map(|line| line.split(',').map(str::to_string).collect())
+        .collect()
+}
+
+#[test]
+fn json_export_matches_the_published_schema() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("replay.json");
+    let report = export::export_file(&golden_path(), ExportFormat::Json, &output).unwrap();
+    assert_eq!(report.files, std::slice::from_ref(&output));
+    assert_eq!(report.events, 11);
+
+    let schema: Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+    let document: Value = serde_json::from_slice(&std::fs::read(&output).unwrap()).unwrap();
+    validate(&document, &schema, &schema, "").unwrap();
+
+    assert_eq!(document["replay_format"], "1.2");
+    assert_eq!(document["metadata"]["match"]["opponent"], "bob");
+    assert_eq!(document["match"]["id"], 9001);
+    let events = document["events"].as_array().unwrap();
+    assert_eq!(events.len(), 11);
+    assert!(events.iter().enumerate().all(|(i, e)| e["seq"] == i as u64));
+    assert_eq!(events[7]["type"], "zone_changed");
+    assert_eq!(events[7]["to"], "battlefield");
+    assert_eq!(document["decks"]["games"][0]["deck"]["maindeck"]["101"], 2);
+    assert_eq!(document["turn_snapshots"].as_array().unwrap().len(), 1);
+    assert_eq!(document["turn_snapshots"][0]["state"]["turn"]["number"], 1);
+}
+
+#[test]
+fn schema_rejects_malformed_documents() {
+    let schema: Value = serde_json::from_str(JSON_SCHEMA).unwrap();
+    let mut output = Vec::new();
+    let mut reader = ReplayReader::open(&golden_path()).unwrap();
+    json::write_json(&mut reader, &mut output).unwrap();
+    let document: Value = serde_json::from_slice(&output).unwrap();
+
+    let mut missing = document.clone();
+    missing.as_object_mut().unwrap().remove("events");
+    assert!(validate(&missing, &schema, &schema, "").is_err());
+    let mut bad_event = document.clone();
+    bad_event["events"][0]["type"] = "teleported".into();
+    assert!(validate(&bad_event, &schema, &schema, "").is_err());
+}
+
+#[test]
+fn csv_export_writes_each_table() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = export::default_output(&dir.path().join("golden.mtgoreplay"), ExportFormat::Csv);
+    assert_eq!(output, dir.path().join("golden_csv"));
+    let report = export::export_file(&golden_path(), ExportFormat::Csv, &output).unwrap();
+    assert_eq!(report.events, 11);
+    assert_eq!(report.files.len(), 4);
+
+    let games = read_csv(&output.join("games.csv"));
+    assert_eq!(games[0], csv::GAMES_COLUMNS);
+    assert_eq!(games.len(), 2);
+    assert_eq!(&games[1][..3], ["9001", "1", "1"]);
+    assert_eq!(games[1][9], "win");
+
+    let turns = read_csv(&output.join("turns.csv"));
+    assert_eq!(turns[0], csv::TURNS_COLUMNS);
+    assert_eq!(turns.len(), 2);
+    // game, turn, active player, name, first and last seq ... event count
+    assert_eq!(&turns[1][..6], ["1", "1", "1", "alice", "5", "10"]);
+    assert_eq!(turns[1][8], "6");
+
+    // Drawing the card is not a play; putting it onto the battlefield is
+    let plays = read_csv(&output.join("plays.csv"));
+    assert_eq!(plays[0], csv::PLAYS_COLUMNS);
+    assert_eq!(plays.len(), 2);
+    assert_eq!(&plays[1][..3], ["1", "1", "7"]);
+    assert_eq!(
+        &plays[1][4..],
+        ["1", "alice", "7", "101", "hand", "battlefield"]
+    );
+
+    let mulligans = read_csv(&output.join("mulligans.csv"));
+    assert_eq!(mulligans, [csv::MULLIGANS_COLUMNS]);
+}
+
+#[test]
+fn csv_fields_are_quoted_and_turns_split_per_game() {
+    let at = |s: u32| Utc.with_ymd_and_hms(2025, 3, 1, 20, 0, s).unwrap();
+    let kinds = [
+        GameEventKind::GameStarted {
+            game_id: 1,
+            starting_player: Some(1),
+        },
+        GameEventKind::PlayerJoined {
+            player: 1,
+            seat: 0,
+            name: "Smith, \"Jr\"".to_string(),
+            life: 20,
+        },
+        GameEventKind::Mulligan {
+            player: 1,
+            count: 1,
+        },
+        GameEventKind::PlayerJoined {
+            player: 2,
+            seat: 1,
+            name: "=HYPERLINK(\"http://x\")".to_string(),
+            life: 20,
+        },
+        GameEventKind::Mulligan {
+            player: 2,
+            count: 2,
+        },
+        GameEventKind::TurnStarted {
+            turn: 1,
+            active_player: 1,
+        },
+        GameEventKind::ZoneChanged {
+            object: 3,
+            card_id: Some(55),
+            owner: 1,
+            controller: 1,
+            from: Some(Zone::Hand),
+            to: Zone::Stack,
+        },
+        GameEventKind::ZoneChanged {
+            object: 3,
+            card_id: Some(55),
+            owner: 1,
+            controller: 1,
+            from: Some(Zone::Stack),
+            to: Zone::Battlefield,
+        },
+        // Conceded before the game ended: the turn ends with the stream
+        GameEventKind::GameStarted {
+            game_id: 2,
+            starting_player: Some(1),
+        },
+        GameEventKind::TurnStarted {
+            turn: 1,
+            active_player: 1,
+        },
+    ];
+    let mut writer = ReplayWriter::new(Vec::new(), &ReplayHeader::new(at(0))).unwrap();
+    for (i, kind) in kinds.into_iter().enumerate() {
+        let event = GameEvent {
+            seq: i as u64,
+            game_id: Some(if i < 8 { 1 } else { 2 }),
+            frame_index: i as u64,
+            timestamp: at(i as u32),
+            kind,
+        };
+        writer.write_event(&event).unwrap();
+    }
+    let mut reader = ReplayReader::new(Cursor::new(writer.finish().unwrap())).unwrap();
+
+    let mut outputs = csv::CsvOutputs {
+        games: Vec::new(),
+        turns: Vec::new(),
+        plays: Vec::new(),
+        mulligans: Vec::new(),
+    };
+    assert_eq!(csv::write_csv(&mut reader, &mut outputs).unwrap(), 10);
+
+    let mulligans = String::from_utf8(outputs.mulligans).unwrap();
+    let row = mulligans.lines().nth(1).unwrap();
+    assert!(row.ends_with(",1,\"Smith, \"\"Jr\"\"\",1"), "{}", row);
+
+    // Player names are not run as spreadsheet formulas
+    let row = mulligans.lines().nth(2).unwrap();
+    assert!(
+        row.ends_with(",2,\"'=HYPERLINK(\"\"http://x\"\")\",2"),
+        "{}",
+        row
+    );
+
+    // Resolving onto the battlefield is not a second play
+    let plays = String::from_utf8(outputs.plays).unwrap();
+    assert_eq!(plays.lines().count(), 2);
+    assert!(plays.lines().nth(1).unwrap().ends_with(",3,55,hand,stack"));
+
+    // Names are per game, and the unfinished turn of game 2 is still written
+    let turns = String::from_utf8(outputs.turns).unwrap();
+    let turns: Vec<&str> = turns.lines().collect();
+    assert_eq!(turns.len(), 3);
+    assert!(turns[1].starts_with("1,1,1,\"Smith, \"\"Jr\"\"\",5,7,"));
+    assert!(turns[2].starts_with("2,1,1,,9,9,"));
+    assert_eq!(String::from_utf8(outputs.games).unwrap().lines().count(), 1);
+}
@@ -0,0 +1,384 @@
+//! Golden-file and compatibility tests for the replay container
+//!
+//! `tests/golden/replay_v1.mtgoreplay` is a replay written by the current format
+//! version (1.2). Readers must keep decoding it unchanged; the writer must keep
+//! producing it byte for byte unless the format version is bumped, at which point
+//! the outgoing version's files are added to `tests/compat`. Regenerate it with
+//! `UPDATE_GOLDEN=1 cargo test --test replay_golden` only for an intentional format change.
+
+use chrono::{TimeZone, Utc};
+use mtgo_replay_lib::common::error::ReplayError;
+use mtgo_replay_lib::game::deck::Deck;
+use mtgo_replay_lib::game::event::{GameEvent, GameEventKind, GameSnapshot};
+use mtgo_replay_lib::game::lifecycle::{Match, MatchTracker};
+use mtgo_replay_lib::game::model::{GameObject, GameState, Player, Zone};
+use mtgo_replay_lib::protocol::frame::{Direction, Frame};
+use mtgo_replay_lib::protocol::packet::FlowKey;
+use mtgo_replay_lib::protocol::version::ClientVersion;
+use mtgo_replay_lib::replay::format::{
+    ChunkKind, FLAG_REQUIRED, FOOTER_LEN, FORMAT_MAJOR, FORMAT_MINOR,
+};
+use mtgo_replay_lib::replay::{Chunk, ReplayHeader, ReplayMatch, ReplayReader, ReplayWriter};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+/// Chunk kind no build understands, for forward-compatibility checks
+const FUTURE_CHUNK: u16 = 0x7F01;
+
+fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/replay_v1.mtgoreplay")
+}
+
+fn header() -> ReplayHeader {
+    ReplayHeader {
+        client_version: Some(ClientVersion::new(3, 4, 123, 4567)),
+        schema_version: Some(ClientVersion::new(3, 4, 0, 0)),
+        app_version: "0.1.0".to_string(),
+        created_at: Utc.with_ymd_and_hms(2025, 3, 1, 18, 30, 0).unwrap(),
+        session_id: Some("20250301T183000.000Z".to_string()),
+        match_info: Some(ReplayMatch {
+            match_id: 9001,
+            format: "Modern".to_string(),
+            event_type: "league".to_string(),
+            best_of: 3,
+            local_player: "alice".to_string(),
+            opponent: "bob".to_string(),
+        }),
+        redactions: None,
+    }
+}
+
+fn events() -> Vec<GameEvent> {
+    let kinds = vec![
+        GameEventKind::MatchStarted {
+            match_id: 9001,
+            format: "Modern".to_string(),
+            event_type: "league".to_string(),
+            best_of: 3,
+            local_player: "alice".to_string(),
+            opponent: "bob".to_string(),
+        },
+        GameEventKind::DeckSubmitted {
+            player: 1,
+            deck: Deck::from_card_lists(&[101, 101, 102, 103], &[201], None),
+        },
+        GameEventKind::GameStarted {
+            game_id: 1,
+            starting_player: Some(1),
+        },
+        GameEventKind::PlayerJoined {
+            player: 1,
+            seat: 0,
+            name: "alice".to_string(),
+            life: 20,
+        },
+        GameEventKind::PlayerJoined {
+            player: 2,
+            seat: 1,
+            name: "bob".to_string(),
+            life: 20,
+        },
+        GameEventKind::TurnStarted {
+            turn: 1,
+            active_player: 1,
+        },
+        GameEventKind::ZoneChanged {
+            object: 7,
+            card_id: Some(101),
+            owner: 1,
+            controller: 1,
+            from: None,
+            to: Zone::Hand,
+        },
+        GameEventKind::ZoneChanged {
+            object: 7,
+            card_id: Some(101),
+            owner: 1,
+            controller: 1,
+            from: Some(Zone::Hand),
+            to: Zone::Battlefield,
+        },
+        GameEventKind::CountersChanged {
+            object: 7,
+            counter: "+1/+1".to_string(),
+            from: 0,
+            to: 2,
+        },
+        GameEventKind::LifeChanged {
+            player: 2,
+            from: 20,
+            to: -3,
+        },
+        GameEventKind::GameEnded {
+            game_id: 1,
+            winner: Some(1),
+            reason: "life".to_string(),
+        },
+    ];
+    kinds
+        .into_iter()
+        .enumerate()
+        .map(|(i, kind)| GameEvent {
+            seq: i as u64,
+            game_id: if i >= 2 { Some(1) } else { None },
+            frame_index: 10 * i as u64,
+            timestamp: Utc.with_ymd_and_hms(2025, 3, 1, 18, 30, i as u32).unwrap(),
+            kind,
+        })
+        .collect()
+}
+
+/// Snapshot after the battlefield change (event 7)
+fn snapshot(events: &[GameEvent]) -> GameSnapshot {
+    let mut state = GameState::new(1);
+    for (id, name) in [(1, "alice"), (2, "bob")] {
+        state.players.insert(
+            id,
+            Player {
+                id,
+                seat: (id - 1) as u8,
+                name: name.to_string(),
+                life: 20,
+                mulligans: 0,
+                zone_sizes: BTreeMap::from([(Zone::Library, 52), (Zone::Hand, 7)]),
+            },
+        );
+    }
+    state.objects.insert(
+        7,
+        GameObject {
+            id: 7,
+            card_id: Some(101),
+            owner: 1,
+            controller: 1,
+            zone: Zone::Battlefield,
+            tapped: false,
+            face_down: false,
+            counters: BTreeMap::new(),
+            attached_to: None,
+            zone_order: 1,
+        },
+    );
+    state.turn.number = 1;
+    state.turn.active_player = Some(1);
+    state.starting_player = Some(1);
+    state.next_zone_order = 2;
+    GameSnapshot {
+        seq: 7,
+        frame_index: events[7].frame_index,
+        timestamp: events[7].timestamp,
+        state,
+    }
+}
+
+fn match_record(events: &[GameEvent]) -> Match {
+    let mut tracker = MatchTracker::new(Some("20250301T183000.000Z".to_string()));
+    let state = GameState::default();
+    for event in events {
+        tracker.apply(event, &state);
+    }
+    tracker.current().expect("match started").clone()
+}
+
+/// Raw frames, two of them with the same payload
+fn frames() -> Vec<Frame> {
+    let flow = FlowKey {
+        src_addr: [10, 0, 0, 1].into(),
+        src_port: 4724,
+        dst_addr: [192, 168, 1, 10].into(),
+        dst_port: 50123,
+    };
+    [
+        (40u64, 0x0101u16, vec![7u8, 0, 0, 0, 1]),
+        (50, 0x0102, vec![2, 3]),
+        (60, 0x0101, vec![7, 0, 0, 0, 1]),
+    ]
+    .into_iter()
+    .map(|(index, type_id, payload)| Frame {
+        flow,
+        direction: Direction::ServerToClient,
+        timestamp: Utc
+            .with_ymd_and_hms(2025, 3, 1, 18, 30, index as u32 / 10)
+            .unwrap(),
+        index,
+        type_id,
+        payload,
+    })
+    .collect()
+}
+
+/// Write the fixture replay: events 0-7, snapshot at 7, a future optional chunk,
+/// raw frames, events 8-10, the match record
+fn write_fixture() -> Vec<u8> {
+    let events = events();
+    let mut writer = ReplayWriter::new(Vec::new(), &header()).unwrap();
+    for event in &events[..8] {
+        writer.write_event(event).unwrap();
+    }
+    writer.write_snapshot(&snapshot(&events)).unwrap();
+    writer
+        .write_chunk(FUTURE_CHUNK, 0, 0, 0, b"written by a newer build")
+        .unwrap();
+    for frame in &frames() {
+        writer.write_frame(frame).unwrap();
+    }
+    writer.flush_frames().unwrap();
+    for event in &events[8..] {
+        writer.write_event(event).unwrap();
+    }
+    writer.write_match(&match_record(&events)).unwrap();
+    writer.finish().unwrap()
+}
+
+fn read(bytes: Vec<u8>) -> Result<ReplayReader<Cursor<Vec<u8>>>, ReplayError> {
+    ReplayReader::new(Cursor::new(bytes))
+}
+
+#[test]
+fn writer_matches_golden_file() {
+    let bytes = write_fixture();
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(golden_path().parent().unwrap()).unwrap();
+        std::fs::write(golden_path(), &bytes).unwrap();
+    }
+    let golden = std::fs::read(golden_path()).expect("golden replay file missing");
+    assert!(
+        bytes == golden,
+        "writer output differs from {}",
+        golden_path().display()
+    );
+}
+
+#[test]
+fn golden_file_decodes() {
+    let events = events();
+    let mut reader = ReplayReader::open(&golden_path()).unwrap();
+    assert_eq!(reader.format_version(), (FORMAT_MAJOR, FORMAT_MINOR));
+    assert!(reader.is_complete());
+    assert_eq!(reader.header(), &header());
+    assert_eq!(reader.events().unwrap(), events);
+    assert_eq!(reader.snapshots().unwrap(), vec![snapshot(&events)]);
+    assert_eq!(reader.match_record().unwrap(), Some(match_record(&events)));
+    assert_eq!(reader.frames().unwrap(), frames());
+
+    let kinds: Vec<Option<ChunkKind>> = reader.index().iter().map(|e| e.chunk_kind()).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            Some(ChunkKind::Events),
+            Some(ChunkKind::Snapshot),
+            None,
+            Some(ChunkKind::Frames),
+            Some(ChunkKind::Events),
+            Some(ChunkKind::Match),
+        ]
+    );
+}
+
+#[test]
+fn unknown_optional_chunks_are_skipped_but_readable() {
+    let mut reader = read(write_fixture()).unwrap();
+    let entry = *reader
+        .index()
+        .iter()
+        .find(|e| e.kind == FUTURE_CHUNK)
+        .unwrap();
+    assert_eq!(
+        reader.read_chunk(&entry).unwrap(),
+        Chunk::Unknown {
+            kind: FUTURE_CHUNK,
+            payload: b"written by a newer build".to_vec(),
+        }
+    );
+    assert_eq!(reader.events().unwrap().len(), events().len());
+}
+
+#[test]
+fn unknown_required_chunk_is_rejected() {
+    let mut writer = ReplayWriter::new(Vec::new(), &header()).unwrap();
+    writer
+        .write_chunk(FUTURE_CHUNK, FLAG_REQUIRED, 0, 0, b"must understand")
+        .unwrap();
+    let bytes = writer.finish().unwrap();
+    assert!(matches!(
+        read(bytes),
+        Err(ReplayError::UnknownRequiredChunk(FUTURE_CHUNK))
+    ));
+}
+
+#[test]
+fn newer_major_version_is_rejected() {
+    let mut bytes = write_fixture();
+    bytes[8..10].copy_from_slice(&2u16.to_le_bytes());
+    assert!(matches!(
+        read(bytes),
+        Err(ReplayError::UnsupportedVersion { major: 2, .. })
+    ));
+}
+
+#[test]
+fn newer_minor_version_is_accepted() {
+    let mut bytes = write_fixture();
+    bytes[10..12].copy_from_slice(&7u16.to_le_bytes());
+    let mut reader = read(bytes).unwrap();
+    assert_eq!(reader.format_version(), (1, 7));
+    assert_eq!(reader.events().unwrap(), events());
+}
+
+#[test]
+fn not_a_replay_is_rejected() {
+    assert!(matches!(
+        read(b"{\"frames\": []}".to_vec()),
+        Err(ReplayError::BadMagic)
+    ));
+    assert!(matches!(read(Vec::new()), Err(ReplayError::BadMagic)));
+}
+
+#[test]
+fn corrupted_chunk_fails_its_checksum() {
+    let bytes = write_fixture();
+    let offset = read(bytes.clone()).unwrap().index()[0].offset as usize;
+    let mut damaged = bytes;
+    damaged[offset + 40] ^= 0xFF;
+    let mut reader = read(damaged).unwrap();
+    assert!(matches!(reader.events(), Err(ReplayError::ChecksumMismatch(o)) if o == offset as u64));
+}
+
+#[test]
+fn unfinished_replay_is_indexed_by_scanning() {
+    let bytes = write_fixture();
+    let index = read(bytes.clone()).unwrap().index().to_vec();
+
+    // Cut inside the second events chunk: everything before it survives
+    let cut = index[4].offset as usize + 10;
+    let mut reader = read(bytes[..cut].to_vec()).unwrap();
+    assert!(!reader.is_complete());
+    assert_eq!(reader.index(), &index[..4]);
+    assert_eq!(reader.events().unwrap(), events()[..8].to_vec());
+
+    // Dropping only the footer keeps every chunk
+    let unfinished = bytes[..bytes.len() - FOOTER_LEN].to_vec();
+    let mut reader = read(unfinished).unwrap();
+    assert!(!reader.is_complete());
+    assert_eq!(reader.index(), &index[..]);
+    assert_eq!(reader.events().unwrap(), events());
+}
+
+#[test]
+fn seek_starts_from_the_closest_snapshot() {
+    let events = events();
+    let mut reader = read(write_fixture()).unwrap();
+
+    let before = reader.seek(3).unwrap();
+    assert_eq!(before.snapshot, None);
+    assert_eq!(before.events, events[..=3].to_vec());
+
+    let at = reader.seek(7).unwrap();
+    assert_eq!(at.snapshot, Some(snapshot(&events)));
+    assert!(at.events.is_empty());
+
+    let after = reader.seek(9).unwrap();
+    assert_eq!(after.snapshot.map(|s| s.seq), Some(7));
+    assert_eq!(after.events, events[8..=9].to_vec());
+}
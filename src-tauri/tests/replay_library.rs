@@ -0,0 +1,269 @@
+//! Replay library: indexing, the directory scan, queries and schema migrations
+
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::{golden_path, write_replay};
+use mtgo_replay_lib::common::error::LibraryError;
+use mtgo_replay_lib::game::deck::{Deck, IntendedDeck};
+use mtgo_replay_lib::game::lifecycle::{Match, Outcome};
+use mtgo_replay_lib::game::match_store;
+use mtgo_replay_lib::library::{watcher, LibraryQuery, PlayerSide, ReplayLibrary, ResultFilter};
+use mtgo_replay_lib::replay::anonymize::{self, Pseudonymizer};
+use mtgo_replay_lib::replay::ReplayReader;
+use std::path::{Path, PathBuf};
+
+fn golden_record() -> Match {
+    ReplayReader::open(&golden_path())
+        .unwrap()
+        .match_record()
+        .unwrap()
+        .unwrap()
+}
+
+/// The golden match (Modern against bob, undecided after game 1) and a Legacy loss
+/// against carol a day later
+fn library_dir(dir: &Path) -> (PathBuf, PathBuf) {
+    let modern = golden_record();
+    let mut legacy = modern.clone();
+    legacy.id = 9002;
+    legacy.format = "Legacy".to_string();
+    legacy.opponent = Some("carol".to_string());
+    legacy.started_at += Duration::days(1);
+    legacy.result = Some(Outcome::Loss);
+    legacy.games[0].result = Some(Outcome::Loss);
+    (write_replay(dir, &modern), write_replay(dir, &legacy))
+}
+
+fn query() -> LibraryQuery {
+    LibraryQuery::default()
+}
+
+#[test]
+fn scan_indexes_new_changed_and_deleted_replays() {
+    let dir = tempfile::tempdir().unwrap();
+    let replays = dir.path().join("replays");
+    let matches = dir.path().join("matches");
+    std::fs::create_dir_all(&replays).unwrap();
+    let (modern, legacy) = library_dir(&replays);
+    std::fs::write(replays.join("broken.mtgoreplay"), b"not a replay").unwrap();
+    std::fs::write(replays.join("recording.mtgoreplay.partial"), b"").unwrap();
+
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    let report = watcher::scan(&mut library, &replays, Some(&matches)).unwrap();
+    assert_eq!(
+        (report.indexed, report.unchanged, report.removed),
+        (2, 0, 0)
+    );
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].0, replays.join("broken.mtgoreplay"));
+
+    let report = watcher::scan(&mut library, &replays, Some(&matches)).unwrap();
+    assert_eq!((report.indexed, report.unchanged), (0, 2));
+
+    // Tagging the deck of a match re-indexes its replay from the stored record
+    let mut tagged = golden_record();
+    tagged.intended_deck = Some(IntendedDeck {
+        name: "Burn".to_string(),
+        deck: Deck::default(),
+        imported_at: Utc::now(),
+    });
+    match_store::save_match(&matches, &tagged).unwrap();
+    let report = watcher::scan(&mut library, &replays, Some(&matches)).unwrap();
+    assert_eq!((report.indexed, report.unchanged), (1, 1));
+    let burn = library
+        .query(&LibraryQuery {
+            archetype: Some("burn".to_string()),
+            ..query()
+        })
+        .unwrap();
+    assert_eq!(burn.len(), 1);
+    assert_eq!(burn[0].path, modern);
+    assert_eq!(burn[0].deck_name.as_deref(), Some("Burn"));
+
+    std::fs::remove_file(&legacy).unwrap();
+    let report = watcher::scan(&mut library, &replays, Some(&matches)).unwrap();
+    assert_eq!(report.removed, 1);
+    let status = library.status().unwrap();
+    assert_eq!((status.replays, status.matches, status.games), (1, 1, 1));
+    assert!(status.last_indexed_at.is_some());
+}
+
+#[test]
+fn anonymized_copies_are_not_indexed() {
+    let dir = tempfile::tempdir().unwrap();
+    let (modern, _) = library_dir(dir.path());
+    let pseudonyms = Pseudonymizer::new([7; 32]);
+    let copy = anonymize::default_output(&modern);
+    anonymize::anonymize_file(&modern, &copy, &pseudonyms, None).unwrap();
+    // A renamed copy is recognized by its header
+    let renamed = dir.path().join("shared.mtgoreplay");
+    anonymize::anonymize_file(&modern, &renamed, &pseudonyms, None).unwrap();
+
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    let report = watcher::scan(&mut library, dir.path(), None).unwrap();
+    assert_eq!((report.indexed, report.unchanged), (2, 0));
+    assert!(report.failed.is_empty());
+    let status = library.status().unwrap();
+    assert_eq!((status.replays, status.matches), (2, 2));
+    assert!(matches!(
+        library.index_replay(&renamed, None),
+        Err(LibraryError::Anonymized(_))
+    ));
+
+    let report = watcher::scan(&mut library, dir.path(), None).unwrap();
+    assert_eq!(
+        (report.indexed, report.unchanged, report.removed),
+        (0, 2, 0)
+    );
+}
+
+#[test]
+fn queries_filter_by_date_format_opponent_and_result() {
+    let dir = tempfile::tempdir().unwrap();
+    let (modern, legacy) = library_dir(dir.path());
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    watcher::scan(&mut library, dir.path(), None).unwrap();
+
+    let paths = |query: LibraryQuery| -> Vec<PathBuf> {
+        library
+            .query(&query)
+            .unwrap()
+            .into_iter()
+            .map(|m| m.path)
+            .collect()
+    };
+    assert_eq!(paths(query()), vec![legacy.clone(), modern.clone()]);
+
+    let day = Utc.with_ymd_and_hms(2025, 3, 2, 0, 0, 0).unwrap();
+    assert_eq!(
+        paths(LibraryQuery {
+            from: Some(day),
+            ..query()
+        }),
+        vec![legacy.clone()]
+    );
+    assert_eq!(
+        paths(LibraryQuery {
+            to: Some(day),
+            ..query()
+        }),
+        vec![modern.clone()]
+    );
+    assert_eq!(
+        paths(LibraryQuery {
+            format: Some("legacy".to_string()),
+            ..query()
+        }),
+        vec![legacy.clone()]
+    );
+    assert_eq!(
+        paths(LibraryQuery {
+            opponent: Some("CAR".to_string()),
+            ..query()
+        }),
+        vec![legacy.clone()]
+    );
+    assert_eq!(
+        paths(LibraryQuery {
+            opponent: Some("%".to_string()),
+            ..query()
+        }),
+        Vec::<PathBuf>::new()
+    );
+    assert_eq!(
+        paths(LibraryQuery {
+            result: Some(ResultFilter::Loss),
+            ..query()
+        }),
+        vec![legacy.clone()]
+    );
+    assert_eq!(
+        paths(LibraryQuery {
+            result: Some(ResultFilter::Undecided),
+            ..query()
+        }),
+        vec![modern.clone()]
+    );
+    assert_eq!(
+        paths(LibraryQuery {
+            limit: Some(1),
+            offset: 1,
+            ..query()
+        }),
+        vec![modern.clone()]
+    );
+
+    let summary = &library.query(&query()).unwrap()[1];
+    assert_eq!(summary.match_id, 9001);
+    assert_eq!(summary.opponent.as_deref(), Some("bob"));
+    assert_eq!(
+        (summary.games, summary.games_won, summary.games_lost),
+        (1, 1, 0)
+    );
+    assert_eq!(summary.archetype, None);
+}
+
+#[test]
+fn card_plays_are_counted_per_player() {
+    let dir = tempfile::tempdir().unwrap();
+    let (modern, _) = library_dir(dir.path());
+    let mut library = ReplayLibrary::open_in_memory().unwrap();
+    library.index_replay(&modern, None).unwrap();
+
+    // Card 101 is drawn, then put onto the battlefield: one play by alice
+    assert_eq!(
+        library.card_plays(&modern).unwrap(),
+        [(PlayerSide::Local, 101, 1)]
+    );
+
+    let played = |ids: Vec<u32>, by: Option<PlayerSide>| {
+        library
+            .query(&LibraryQuery {
+                card_played: ids,
+                played_by: by,
+                ..query()
+            })
+            .unwrap()
+            .len()
+    };
+    assert_eq!(played(vec![101], None), 1);
+    assert_eq!(played(vec![999, 101], Some(PlayerSide::Local)), 1);
+    assert_eq!(played(vec![101], Some(PlayerSide::Opponent)), 0);
+    assert_eq!(played(vec![102], None), 0);
+}
+
+#[test]
+fn database_is_migrated_and_newer_schemas_are_refused() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("library.sqlite");
+    let library = ReplayLibrary::open(&path).unwrap();
+    assert_eq!(library.schema_version().unwrap(), 5);
+    drop(library);
+
+    // Reopening an up-to-date library keeps its rows
+    let (modern, _) = library_dir(dir.path());
+    ReplayLibrary::open(&path)
+        .unwrap()
+        .index_replay(&modern, None)
+        .unwrap();
+    assert_eq!(
+        ReplayLibrary::open(&path)
+            .unwrap()
+            .status()
+            .unwrap()
+            .replays,
+        1
+    );
+
+    let conn = rusqlite::Connection::open(&path).unwrap();
+    conn.pragma_update(None, "user_version", 99).unwrap();
+    drop(conn);
+    assert!(matches!(
+        ReplayLibrary::open(&path),
+        Err(LibraryError::NewerSchema {
+            found: 99,
+            supported: 5
+        })
+    ));
+}
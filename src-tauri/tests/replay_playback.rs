@@ -0,0 +1,332 @@
+//! Replay playback: seeking from snapshots, stepping and timed play
+
+mod common;
+
+use common::{at, golden_path};
+use mtgo_replay_lib::common::error::ReplayError;
+use mtgo_replay_lib::game::event::{GameEvent, GameEventKind, GameSnapshot};
+use mtgo_replay_lib::game::model::{GameState, Phase, Step, Zone};
+use mtgo_replay_lib::replay::playback::{MAX_FRAME_DELAY, MIN_FRAME_DELAY};
+use mtgo_replay_lib::replay::{ReplayPlayback, ReplayReader, SeekTarget, StepUnit};
+
+fn step(turn: u32, step: Step) -> GameEventKind {
+    GameEventKind::StepChanged {
+        turn,
+        phase: step.phase(),
+        step,
+    }
+}
+
+/// Two turns of one game: (frame, seconds, event)
+fn events() -> Vec<GameEvent> {
+    let kinds = vec![
+        (
+            0,
+            0,
+            GameEventKind::GameStarted {
+                game_id: 1,
+                starting_player: Some(1),
+            },
+        ),
+        (
+            0,
+            0,
+            GameEventKind::PlayerJoined {
+                player: 1,
+                seat: 0,
+                name: "alice".to_string(),
+                life: 20,
+            },
+        ),
+        (
+            0,
+            0,
+            GameEventKind::PlayerJoined {
+                player: 2,
+                seat: 1,
+                name: "bob".to_string(),
+                life: 20,
+            },
+        ),
+        (
+            1,
+            1,
+            GameEventKind::TurnStarted {
+                turn: 1,
+                active_player: 1,
+            },
+        ),
+        (1, 1, step(1, Step::Untap)),
+        (2, 3, step(1, Step::PrecombatMain)),
+        (
+            3,
+            4,
+            GameEventKind::ZoneChanged {
+                object: 10,
+                card_id: Some(101),
+                owner: 1,
+                controller: 1,
+                from: None,
+                to: Zone::Battlefield,
+            },
+        ),
+        (
+            4,
+            10,
+            GameEventKind::Tapped {
+                object: 10,
+                tapped: true,
+            },
+        ),
+        (4, 10, step(1, Step::DeclareAttackers)),
+        (
+            5,
+            11,
+            GameEventKind::LifeChanged {
+                player: 2,
+                from: 20,
+                to: 18,
+            },
+        ),
+        (
+            6,
+            12,
+            GameEventKind::TurnStarted {
+                turn: 2,
+                active_player: 2,
+            },
+        ),
+        (6, 12, step(2, Step::Untap)),
+        (
+            7,
+            13,
+            GameEventKind::ZoneChanged {
+                object: 10,
+                card_id: Some(101),
+                owner: 1,
+                controller: 1,
+                from: Some(Zone::Battlefield),
+                to: Zone::Graveyard,
+            },
+        ),
+        (
+            8,
+            14,
+            GameEventKind::GameEnded {
+                game_id: 1,
+                winner: Some(1),
+                reason: "life".to_string(),
+            },
+        ),
+    ];
+    kinds
+        .into_iter()
+        .enumerate()
+        .map(|(i, (frame, seconds, kind))| GameEvent {
+            seq: 100 + i as u64,
+            game_id: Some(1),
+            frame_index: frame,
+            timestamp: at(seconds),
+            kind,
+        })
+        .collect()
+}
+
+/// State after the first `position` events, applied one by one
+fn state_at(events: &[GameEvent], position: usize) -> GameState {
+    let mut state = GameState::default();
+    for event in &events[..position] {
+        state.apply(&event.kind);
+    }
+    state
+}
+
+fn playback(snapshot_positions: &[usize]) -> ReplayPlayback {
+    let events = events();
+    let header = ReplayReader::open(&golden_path()).unwrap().header().clone();
+    let snapshots = snapshot_positions
+        .iter()
+        .map(|&position| GameSnapshot {
+            seq: events[position - 1].seq,
+            frame_index: events[position - 1].frame_index,
+            timestamp: events[position - 1].timestamp,
+            state: state_at(&events, position),
+        })
+        .collect();
+    ReplayPlayback::new(header, events, snapshots)
+}
+
+#[test]
+fn golden_replay_seeks_from_its_snapshot() {
+    let mut playback = ReplayPlayback::open(&golden_path()).unwrap();
+    let outline = playback.outline();
+    assert_eq!(outline.total, 11);
+    assert_eq!(outline.header.match_info.unwrap().match_id, 9001);
+    assert_eq!(
+        (
+            outline.games.len(),
+            outline.games[0].game_id,
+            outline.games[0].position
+        ),
+        (1, 1, 3)
+    );
+    assert_eq!(
+        (
+            outline.games[0].turns[0].turn,
+            outline.games[0].turns[0].position
+        ),
+        (1, 6)
+    );
+
+    // The snapshot after event 7 carries zone sizes no event reported
+    playback.seek(&SeekTarget::Event { seq: 8 }).unwrap();
+    let view = playback.view();
+    assert_eq!((view.position, view.seq), (9, Some(8)));
+    let state = &view.state;
+    assert_eq!(state.players[&1].zone_sizes[&Zone::Library], 52);
+    assert_eq!(state.objects[&7].counters["+1/+1"], 2);
+
+    playback.seek(&SeekTarget::Event { seq: 4 }).unwrap();
+    assert!(playback.state().players[&1].zone_sizes.is_empty());
+    assert!(playback.view().events.is_empty());
+
+    assert!(matches!(
+        playback.seek(&SeekTarget::Turn {
+            game_id: None,
+            turn: 2
+        }),
+        Err(ReplayError::SeekTarget(_))
+    ));
+    assert_eq!(playback.position(), 5);
+}
+
+#[test]
+fn seeking_from_anywhere_matches_applying_every_event() {
+    let events = events();
+    let mut playback = playback(&[4, 10]);
+    for from in 0..=events.len() {
+        for to in 0..=events.len() {
+            playback.seek_position(from);
+            playback.seek_position(to);
+            assert_eq!(
+                playback.state(),
+                &state_at(&events, to),
+                "from {} to {}",
+                from,
+                to
+            );
+        }
+    }
+
+    // Leaving the battlefield untaps
+    playback.seek(&SeekTarget::End).unwrap();
+    let object = &playback.state().objects[&10];
+    assert_eq!((object.zone, object.tapped), (Zone::Graveyard, false));
+    assert_eq!(playback.state().players[&2].life, 18);
+}
+
+#[test]
+fn seeks_and_steps_by_turn_phase_and_step() {
+    let mut playback = playback(&[]);
+
+    // Turns are shown with the step they begin in
+    playback
+        .seek(&SeekTarget::Turn {
+            game_id: None,
+            turn: 2,
+        })
+        .unwrap();
+    assert_eq!(playback.position(), 12);
+    assert_eq!(
+        (playback.view().turn.number, playback.view().turn.step),
+        (2, Some(Step::Untap))
+    );
+
+    playback
+        .seek(&SeekTarget::Phase {
+            game_id: Some(1),
+            turn: 1,
+            phase: Phase::Combat,
+        })
+        .unwrap();
+    assert_eq!(playback.position(), 9);
+    assert!(playback.state().objects[&10].tapped);
+    playback
+        .seek(&SeekTarget::Step {
+            game_id: None,
+            turn: 1,
+            step: Step::PrecombatMain,
+        })
+        .unwrap();
+    assert_eq!(playback.position(), 6);
+    assert!(playback
+        .seek(&SeekTarget::Turn {
+            game_id: Some(2),
+            turn: 1,
+        })
+        .is_err());
+
+    playback.seek_position(12);
+    assert!(playback.step(StepUnit::Step, false));
+    assert_eq!(playback.position(), 9);
+    assert!(playback.step(StepUnit::Turn, false));
+    assert_eq!(playback.position(), 5);
+    assert!(playback.step(StepUnit::Turn, false));
+    assert_eq!(playback.position(), 0);
+    assert!(!playback.step(StepUnit::Event, false));
+    assert!(playback.step(StepUnit::Game, true));
+    assert_eq!(playback.position(), 1);
+    assert!(playback.step(StepUnit::Event, true));
+    assert_eq!(playback.view().events.len(), 1);
+    playback.seek(&SeekTarget::End).unwrap();
+    assert!(!playback.step(StepUnit::Turn, true));
+}
+
+#[test]
+fn playing_applies_a_frame_at_a_time_at_the_recorded_pace() {
+    let mut playback = playback(&[4]);
+    assert_eq!(playback.next_delay(), Some(MIN_FRAME_DELAY));
+    playback.play();
+    assert!(playback.is_playing());
+
+    // The three events of frame 0 happen together
+    playback.advance();
+    assert_eq!(playback.position(), 3);
+    assert_eq!(playback.view().events.len(), 3);
+    assert_eq!(
+        playback.next_delay(),
+        Some(std::time::Duration::from_secs(1))
+    );
+    playback.set_speed(2.0);
+    assert_eq!(
+        playback.next_delay(),
+        Some(std::time::Duration::from_millis(500))
+    );
+    playback.set_speed(1000.0);
+    assert_eq!(playback.speed(), 16.0);
+    playback.set_speed(1.0);
+
+    // A long think is cut short
+    playback.seek_position(7);
+    assert_eq!(playback.next_delay(), Some(MAX_FRAME_DELAY));
+
+    while playback.is_playing() {
+        playback.advance();
+    }
+    let view = playback.view();
+    assert_eq!((view.position, view.playing), (14, false));
+    assert!(matches!(
+        view.events[..],
+        [GameEvent {
+            kind: GameEventKind::GameEnded { .. },
+            ..
+        }]
+    ));
+    assert_eq!(playback.next_delay(), None);
+
+    // Playing again from the end starts over
+    playback.play();
+    assert_eq!((playback.position(), playback.is_playing()), (0, true));
+    playback.pause();
+    assert!(!playback.is_playing());
+}
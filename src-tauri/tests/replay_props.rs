@@ -0,0 +1,39 @@
+//! Property tests for the replay container reader
+
+mod common;
+
+use common::{allocation_bound, largest_allocation, seeded_inputs, TrackingAllocator};
+use mtgo_replay_lib::replay::ReplayReader;
+use proptest::prelude::*;
+use std::io::Cursor;
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+/// Open a replay and decode everything it claims to hold, ignoring errors
+fn read_all(data: &[u8]) -> usize {
+    let Ok(mut reader) = ReplayReader::new(Cursor::new(data)) else {
+        return 0;
+    };
+    for entry in reader.index().to_vec() {
+        let _ = reader.read_chunk(&entry);
+    }
+    reader.events().map(|events| events.len()).unwrap_or(0)
+}
+
+#[test]
+fn corpus_replays_without_panic() {
+    for seed in common::load_corpus("replay") {
+        let (events, largest) = largest_allocation(|| read_all(&seed));
+        assert!(events > 0);
+        assert!(largest <= allocation_bound(seed.len()));
+    }
+}
+
+proptest! {
+    #[test]
+    fn damaged_replays_are_rejected_or_read_in_bounded_memory(data in seeded_inputs("replay")) {
+        let (_, largest) = largest_allocation(|| read_all(&data));
+        prop_assert!(largest <= allocation_bound(data.len()));
+    }
+}
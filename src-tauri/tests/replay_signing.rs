@@ -0,0 +1,281 @@
+//! Signed replays: signing, verification and the keyring
+
+use mtgo_replay_lib::common::error::ReplayError;
+use mtgo_replay_lib::game::lifecycle::Match;
+use mtgo_replay_lib::replay::format::{
+    self, ChunkKind, IndexEntry, ReplaySignature, FLAG_COMPRESSED,
+};
+use mtgo_replay_lib::replay::signing::{
+    self, ChunkChange, ChunkChangeKind, Keyring, SignatureStatus, SigningIdentity,
+};
+use mtgo_replay_lib::replay::{migrate, ReplayHeader, ReplayReader, ReplayWriter};
+use serde_bytes::ByteBuf;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+/// Chunk kind no build understands, as in the golden file
+const FUTURE_CHUNK: u16 = 0x7F01;
+
+fn golden_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden/replay_v1.mtgoreplay")
+}
+
+/// A signed copy of the golden replay
+fn signed_copy(dir: &Path, identity: &SigningIdentity) -> PathBuf {
+    let path = dir.join("signed.mtgoreplay");
+    std::fs::copy(golden_path(), &path).unwrap();
+    signing::sign_file(&path, identity).unwrap();
+    path
+}
+
+/// Rewrite a replay chunk by chunk, as someone editing it with the format
+/// documentation would: `edit` may replace a payload or return None to drop it
+fn rewrite(
+    from: &Path,
+    to: &Path,
+    header: &ReplayHeader,
+    mut edit: impl FnMut(&IndexEntry, Vec<u8>) -> Option<Vec<u8>>,
+    extra: &[(u16, Vec<u8>)],
+) {
+    let reader = ReplayReader::open(from).unwrap();
+    let entries = reader.index().to_vec();
+    let mut file = BufReader::new(File::open(from).unwrap());
+    let mut writer = ReplayWriter::new(Vec::new(), header).unwrap();
+    for (kind, payload) in extra {
+        writer.write_chunk(*kind, 0, 0, 0, payload).unwrap();
+    }
+    for entry in &entries {
+        let raw = format::read_chunk_at(&mut file, entry.offset)
+            .unwrap()
+            .unwrap();
+        if let Some(payload) = edit(entry, raw.payload) {
+            let flags = entry.flags & !FLAG_COMPRESSED;
+            writer
+                .write_chunk(entry.kind, flags, entry.first_seq, entry.last_seq, &payload)
+                .unwrap();
+        }
+    }
+    std::fs::write(to, writer.finish().unwrap()).unwrap();
+}
+
+fn header_of(path: &Path) -> ReplayHeader {
+    ReplayReader::open(path).unwrap().header().clone()
+}
+
+#[test]
+fn signed_replay_verifies_and_still_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    let identity = SigningIdentity::generate().unwrap();
+    let path = signed_copy(dir.path(), &identity);
+
+    let mut keyring = Keyring::default();
+    keyring
+        .trust("alice", &identity.signer().public_key)
+        .unwrap();
+    let report = signing::verify_file(&path, &keyring, Some(&identity.public_key())).unwrap();
+    assert_eq!(report.status, SignatureStatus::Valid);
+    assert_eq!(report.signer.unwrap().fingerprint, identity.fingerprint());
+    assert_eq!(report.trusted_as.as_deref(), Some("alice"));
+    assert!(report.own_key);
+    assert!(!report.header_modified);
+    assert!(report.modified_chunks.is_empty());
+
+    // Signing adds a chunk and leaves everything else readable as before
+    let before = migrate::load(&golden_path()).unwrap();
+    let after = migrate::load(&path).unwrap();
+    assert_eq!(after.events, before.events);
+    assert_eq!(after.frames, before.frames);
+    assert_eq!(after.match_record, before.match_record);
+    assert_eq!(after.unknown_chunks.len(), before.unknown_chunks.len());
+}
+
+#[test]
+fn unsigned_and_untrusted_signers_are_reported() {
+    let unsigned = signing::verify_file(&golden_path(), &Keyring::default(), None).unwrap();
+    assert_eq!(unsigned.status, SignatureStatus::Unsigned);
+    assert_eq!(unsigned.signer, None);
+
+    let dir = tempfile::tempdir().unwrap();
+    let path = signed_copy(dir.path(), &SigningIdentity::generate().unwrap());
+    let report = signing::verify_file(&path, &Keyring::default(), None).unwrap();
+    assert_eq!(report.status, SignatureStatus::Valid);
+    assert_eq!(report.trusted_as, None);
+    assert!(!report.own_key);
+}
+
+#[test]
+fn signing_again_replaces_the_signature() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = signed_copy(dir.path(), &SigningIdentity::generate().unwrap());
+    let second = SigningIdentity::generate().unwrap();
+    let info = signing::sign_file(&path, &second).unwrap();
+    assert_eq!(info.chunks, 6);
+
+    let reader = ReplayReader::open(&path).unwrap();
+    let signatures = reader
+        .index()
+        .iter()
+        .filter(|e| e.chunk_kind() == Some(ChunkKind::Signature))
+        .count();
+    assert_eq!(signatures, 1);
+    let report = signing::verify_file(&path, &Keyring::default(), None).unwrap();
+    assert_eq!(report.status, SignatureStatus::Valid);
+    assert_eq!(report.signer.unwrap().fingerprint, second.fingerprint());
+}
+
+#[test]
+fn edited_chunk_is_pinpointed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = signed_copy(dir.path(), &SigningIdentity::generate().unwrap());
+    let edited = dir.path().join("edited.mtgoreplay");
+    rewrite(
+        &path,
+        &edited,
+        &header_of(&path),
+        |entry, payload| {
+            if entry.chunk_kind() != Some(ChunkKind::Match) {
+                return Some(payload);
+            }
+            let mut record: Match = format::decode(&payload, "match").unwrap();
+            record.opponent = Some("mallory".to_string());
+            Some(format::encode(&record).unwrap())
+        },
+        &[],
+    );
+
+    let report = signing::verify_file(&edited, &Keyring::default(), None).unwrap();
+    assert_eq!(report.status, SignatureStatus::Modified);
+    assert!(!report.header_modified);
+    assert_eq!(
+        report.modified_chunks,
+        [ChunkChange {
+            kind: ChunkKind::Match.code(),
+            chunk_kind: Some(ChunkKind::Match),
+            first_seq: 0,
+            last_seq: 0,
+            change: ChunkChangeKind::Modified,
+        }]
+    );
+}
+
+#[test]
+fn added_and_removed_chunks_are_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = signed_copy(dir.path(), &SigningIdentity::generate().unwrap());
+    let edited = dir.path().join("edited.mtgoreplay");
+    rewrite(
+        &path,
+        &edited,
+        &header_of(&path),
+        |entry, payload| (entry.chunk_kind() != Some(ChunkKind::Snapshot)).then_some(payload),
+        &[(FUTURE_CHUNK + 1, b"injected".to_vec())],
+    );
+
+    let report = signing::verify_file(&edited, &Keyring::default(), None).unwrap();
+    assert_eq!(report.status, SignatureStatus::Modified);
+    let changes: Vec<(u16, ChunkChangeKind)> = report
+        .modified_chunks
+        .iter()
+        .map(|c| (c.kind, c.change))
+        .collect();
+    assert_eq!(
+        changes,
+        [
+            (FUTURE_CHUNK + 1, ChunkChangeKind::Added),
+            (ChunkKind::Snapshot.code(), ChunkChangeKind::Removed)
+        ]
+    );
+}
+
+#[test]
+fn edited_header_is_reported() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = signed_copy(dir.path(), &SigningIdentity::generate().unwrap());
+    let edited = dir.path().join("edited.mtgoreplay");
+    let mut header = header_of(&path);
+    header.match_info.as_mut().unwrap().opponent = "mallory".to_string();
+    rewrite(&path, &edited, &header, |_, payload| Some(payload), &[]);
+
+    let report = signing::verify_file(&edited, &Keyring::default(), None).unwrap();
+    assert_eq!(report.status, SignatureStatus::Modified);
+    assert!(report.header_modified);
+    assert!(report.modified_chunks.is_empty());
+}
+
+#[test]
+fn forged_signature_is_invalid() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = signed_copy(dir.path(), &SigningIdentity::generate().unwrap());
+    let forger = SigningIdentity::generate().unwrap();
+    let edited = dir.path().join("edited.mtgoreplay");
+
+    // Claim someone else's key for the original signature
+    rewrite(
+        &path,
+        &edited,
+        &header_of(&path),
+        |entry, payload| {
+            if entry.chunk_kind() != Some(ChunkKind::Signature) {
+                return Some(payload);
+            }
+            let signature: ReplaySignature = format::decode(&payload, "signature").unwrap();
+            let mut manifest: format::SignedManifest =
+                format::decode(&signature.manifest, "manifest").unwrap();
+            manifest.public_key = ByteBuf::from(forger.public_key().to_vec());
+            let forged = ReplaySignature {
+                manifest: ByteBuf::from(format::encode(&manifest).unwrap()),
+                signature: signature.signature,
+            };
+            Some(format::encode(&forged).unwrap())
+        },
+        &[],
+    );
+
+    let report = signing::verify_file(&edited, &Keyring::default(), None).unwrap();
+    assert_eq!(report.status, SignatureStatus::Invalid);
+    assert_eq!(report.signer.unwrap().fingerprint, forger.fingerprint());
+}
+
+#[test]
+fn unfinished_replays_are_not_signed() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("unfinished.mtgoreplay");
+    let sample =
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/compat/v1.0/unfinished.mtgoreplay");
+    std::fs::copy(sample, &path).unwrap();
+    let result = signing::sign_file(&path, &SigningIdentity::generate().unwrap());
+    assert!(matches!(result, Err(ReplayError::Signing(_))));
+}
+
+#[test]
+fn key_files_and_keyring_round_trip() {
+    let dir = tempfile::tempdir().unwrap();
+    let key_path = dir.path().join("signing.key");
+    let identity = SigningIdentity::load_or_create(&key_path).unwrap();
+    assert_eq!(
+        SigningIdentity::load_or_create(&key_path)
+            .unwrap()
+            .public_key(),
+        identity.public_key()
+    );
+
+    let keyring_path = dir.path().join("trusted_keys.json");
+    let mut keyring = Keyring::load(&keyring_path).unwrap();
+    assert!(keyring.keys.is_empty());
+    let trusted = keyring.trust("bob", &identity.signer().public_key).unwrap();
+    assert_eq!(trusted.fingerprint, identity.fingerprint());
+    keyring
+        .trust("Bob B.", &identity.signer().public_key)
+        .unwrap();
+    assert!(keyring.trust("eve", "not a key").is_err());
+    assert!(keyring.trust("eve", "00").is_err());
+    keyring.save(&keyring_path).unwrap();
+
+    let mut loaded = Keyring::load(&keyring_path).unwrap();
+    assert_eq!(loaded, keyring);
+    assert_eq!(loaded.keys.len(), 1);
+    assert_eq!(loaded.find(&identity.public_key()).unwrap().name, "Bob B.");
+    assert!(loaded.remove(&identity.fingerprint()));
+    assert!(!loaded.remove(&identity.fingerprint()));
+}
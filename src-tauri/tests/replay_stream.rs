@@ -0,0 +1,249 @@
+//! Live replay writing, recovery of replays interrupted by a crash, and the capture
+//! settings the writer is configured from
+
+mod common;
+
+use chrono::Utc;
+use common::golden_path;
+use mtgo_replay_lib::capture::loop_::CapturedPacket;
+use mtgo_replay_lib::capture::pipeline::{
+    spawn_pipeline, CapturePipeline, GameSink, TICK_INTERVAL,
+};
+use mtgo_replay_lib::capture::settings::CaptureSettings;
+use mtgo_replay_lib::common::error::{CaptureError, ReplayError};
+use mtgo_replay_lib::game::event::GameUpdate;
+use mtgo_replay_lib::game::model::GameState;
+use mtgo_replay_lib::protocol::archive::SessionArchive;
+use mtgo_replay_lib::protocol::decoder::Decoder;
+use mtgo_replay_lib::protocol::session::SessionRecorder;
+use mtgo_replay_lib::protocol::version::SchemaRegistry;
+use mtgo_replay_lib::replay::recovery::{recover, recover_all, RecoveryReport};
+use mtgo_replay_lib::replay::stream::{partial_path, replay_path, PARTIAL_SUFFIX};
+use mtgo_replay_lib::replay::{ReplayReader, ReplaySink, StreamConfig, StreamingReplayWriter};
+use std::fs::{self, OpenOptions};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Every event becomes its own chunk and is synced at once
+fn every_event() -> StreamConfig {
+    StreamConfig {
+        chunk_interval: Duration::ZERO,
+        sync_interval: Duration::ZERO,
+        embed_frames: false,
+    }
+}
+
+#[test]
+fn interrupted_replay_is_cut_after_its_last_intact_chunk() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut golden = ReplayReader::open(&golden_path()).unwrap();
+    let events = golden.events().unwrap();
+    let mut stream =
+        StreamingReplayWriter::create(dir.path(), "crashed", golden.header(), every_event())
+            .unwrap();
+    let partial = stream.partial_path().to_path_buf();
+    assert_eq!(partial, partial_path(dir.path(), "crashed"));
+
+    let mut sizes = Vec::new();
+    for event in &events {
+        stream.write_event(event).unwrap();
+        sizes.push(fs::metadata(&partial).unwrap().len());
+    }
+    assert!(sizes.windows(2).all(|pair| pair[0] < pair[1]));
+    // The application dies halfway through writing the last chunk
+    drop(stream);
+    let intact = sizes[sizes.len() - 2];
+    let cut = intact + (sizes[sizes.len() - 1] - intact) / 2;
+    OpenOptions::new()
+        .write(true)
+        .open(&partial)
+        .unwrap()
+        .set_len(cut)
+        .unwrap();
+    let damaged = partial_path(dir.path(), "damaged");
+    fs::write(&damaged, b"not a replay").unwrap();
+    fs::write(dir.path().join("notes.txt"), b"").unwrap();
+
+    let reports = recover_all(dir.path()).unwrap();
+    let kept = &events[..events.len() - 1];
+    assert_eq!(
+        reports,
+        [RecoveryReport {
+            path: replay_path(dir.path(), "crashed"),
+            chunks: kept.len(),
+            last_seq: kept.last().map(|event| event.seq),
+            discarded_bytes: cut - intact,
+        }]
+    );
+    assert!(!partial.exists());
+    // A replay whose header is damaged is left for the user to look at
+    assert!(damaged.exists());
+
+    let mut recovered = ReplayReader::open(&reports[0].path).unwrap();
+    assert!(recovered.is_complete());
+    assert_eq!(recovered.header(), golden.header());
+    assert_eq!(recovered.index().len(), kept.len());
+    assert!(recovered
+        .index()
+        .windows(2)
+        .all(|pair| pair[0].offset < pair[1].offset));
+    assert_eq!(recovered.events().unwrap(), kept);
+    assert_eq!(recover_all(dir.path()).unwrap(), []);
+}
+
+#[test]
+fn finished_replay_left_partial_is_only_renamed() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut golden = ReplayReader::open(&golden_path()).unwrap();
+    let events = golden.events().unwrap();
+    let record = golden.match_record().unwrap().unwrap();
+    let mut stream = StreamingReplayWriter::create(
+        dir.path(),
+        "finished",
+        golden.header(),
+        StreamConfig::default(),
+    )
+    .unwrap();
+    for event in &events {
+        stream.write_event(event).unwrap();
+    }
+    let path = stream.finish(Some(&record)).unwrap();
+    assert_eq!(path, replay_path(dir.path(), "finished"));
+    assert!(!partial_path(dir.path(), "finished").exists());
+    let index = ReplayReader::open(&path).unwrap().index().to_vec();
+
+    // Crash between writing the footer and renaming the file
+    let partial = partial_path(dir.path(), "finished");
+    fs::rename(&path, &partial).unwrap();
+    let report = recover(&partial).unwrap();
+    assert_eq!(
+        (report.path.clone(), report.chunks),
+        (path.clone(), index.len())
+    );
+    assert_eq!(
+        (report.last_seq, report.discarded_bytes),
+        (events.last().map(|event| event.seq), 0)
+    );
+
+    let mut recovered = ReplayReader::open(&path).unwrap();
+    assert_eq!(recovered.index(), index);
+    assert_eq!(recovered.events().unwrap(), events);
+    assert_eq!(recovered.match_record().unwrap(), Some(record));
+    assert!(matches!(recover(&path), Err(ReplayError::Corrupt(_))));
+}
+
+#[test]
+fn buffered_events_reach_the_disk_on_the_tick_without_further_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let events = ReplayReader::open(&golden_path())
+        .unwrap()
+        .events()
+        .unwrap();
+    let config = StreamConfig {
+        chunk_interval: Duration::from_millis(100),
+        sync_interval: Duration::from_millis(100),
+        embed_frames: false,
+    };
+    let mut sink = ReplaySink::new(dir.path().to_path_buf(), "session-1".to_string(), config);
+    let update = GameUpdate {
+        events: events[..2].to_vec(),
+        snapshot: None,
+    };
+    sink.on_update(&update, &GameState::new(0));
+
+    let partial = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .find(|path| path.to_string_lossy().ends_with(PARTIAL_SUFFIX))
+        .unwrap();
+    let header_only = fs::metadata(&partial).unwrap().len();
+    // Nothing else arrives while the player thinks
+    std::thread::sleep(Duration::from_millis(150));
+    assert_eq!(fs::metadata(&partial).unwrap().len(), header_only);
+    sink.on_tick();
+    assert!(fs::metadata(&partial).unwrap().len() > header_only);
+
+    // What the tick wrote is what a crash right now would leave behind
+    let crashed = tempfile::tempdir().unwrap();
+    let copy = crashed.path().join(partial.file_name().unwrap());
+    fs::copy(&partial, &copy).unwrap();
+    let report = recover(&copy).unwrap();
+    // Both events went out together as one chunk
+    assert_eq!((report.chunks, report.last_seq), (1, Some(events[1].seq)));
+    assert_eq!(
+        ReplayReader::open(&report.path).unwrap().events().unwrap(),
+        events[..2]
+    );
+}
+
+/// Sink counting its ticks
+struct Ticks(Arc<AtomicUsize>);
+
+impl GameSink for Ticks {
+    fn on_update(&mut self, _update: &GameUpdate, _state: &GameState) {}
+
+    fn on_tick(&mut self) {
+        self.0.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn pipeline_ticks_sinks_while_no_packets_arrive() {
+    let sessions = tempfile::tempdir().unwrap();
+    let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+    let version = registry.latest().unwrap().client_version.clone();
+    let archive = SessionArchive::create(sessions.path(), Utc::now(), version).unwrap();
+    let mut pipeline = CapturePipeline::new(SessionRecorder::new(
+        Decoder::new(registry).unwrap(),
+        archive,
+    ));
+    let ticks = Arc::new(AtomicUsize::new(0));
+    pipeline.add_sink(Box::new(Ticks(ticks.clone())));
+
+    let (packet_tx, packet_rx) = tokio::sync::mpsc::channel::<CapturedPacket>(1);
+    let handle = spawn_pipeline(pipeline, packet_rx);
+    tokio::time::sleep(TICK_INTERVAL * 4).await;
+    assert!(ticks.load(Ordering::SeqCst) >= 2);
+    drop(packet_tx);
+    handle.await.unwrap();
+}
+
+#[test]
+fn capture_settings_configure_replay_writing() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("capture_settings.json");
+    let defaults = CaptureSettings::load(&path).unwrap();
+    assert_eq!(defaults.stream_config(), StreamConfig::default());
+
+    let settings = CaptureSettings {
+        replay_chunk_interval_ms: 250,
+        replay_sync_interval_ms: 0,
+        embed_frames: false,
+    };
+    settings.save(&path).unwrap();
+    assert_eq!(CaptureSettings::load(&path).unwrap(), settings);
+    assert_eq!(
+        settings.stream_config(),
+        StreamConfig {
+            chunk_interval: Duration::from_millis(250),
+            sync_interval: Duration::ZERO,
+            embed_frames: false,
+        }
+    );
+
+    // Settings left out keep their defaults
+    fs::write(&path, r#"{"replay_sync_interval_ms": 60000}"#).unwrap();
+    let config = CaptureSettings::load(&path).unwrap().stream_config();
+    assert_eq!(config.sync_interval, Duration::from_secs(60));
+    assert_eq!(
+        config.chunk_interval,
+        StreamConfig::default().chunk_interval
+    );
+
+    fs::write(&path, "{").unwrap();
+    assert!(matches!(
+        CaptureSettings::load(&path),
+        Err(CaptureError::InvalidSettings(_))
+    ));
+}
@@ -0,0 +1,180 @@
+//! Property tests for schema-driven payload decoding and version negotiation
+
+mod common;
+
+use common::{allocation_bound, largest_allocation, seeded_inputs, TrackingAllocator};
+use mtgo_replay_lib::protocol::decoder::Decoder;
+use mtgo_replay_lib::protocol::frame::{Direction, Frame, FRAME_HEADER_LEN};
+use mtgo_replay_lib::protocol::packet::FlowKey;
+use mtgo_replay_lib::protocol::schema::{FieldDef, FieldType, FieldValue, MessageSchema};
+use mtgo_replay_lib::protocol::version::{ClientVersion, SchemaRegistry};
+use proptest::prelude::*;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::Arc;
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+fn field_type() -> impl Strategy<Value = FieldType> {
+    let leaf = prop_oneof![
+        Just(FieldType::U8),
+        Just(FieldType::U16),
+        Just(FieldType::U32),
+        Just(FieldType::U64),
+        Just(FieldType::I32),
+        Just(FieldType::I64),
+        Just(FieldType::Bool),
+        Just(FieldType::String),
+        Just(FieldType::Bytes),
+    ];
+    leaf.prop_recursive(2, 8, 1, |inner| {
+        inner.prop_map(|t| FieldType::Array(Box::new(t)))
+    })
+}
+
+fn value_for(ty: &FieldType) -> BoxedStrategy<FieldValue> {
+    match ty {
+        FieldType::U8 => any::<u8>().prop_map(|v| FieldValue::UInt(v as u64)).boxed(),
+        FieldType::U16 => any::<u16>()
+            .prop_map(|v| FieldValue::UInt(v as u64))
+            .boxed(),
+        FieldType::U32 => any::<u32>()
+            .prop_map(|v| FieldValue::UInt(v as u64))
+            .boxed(),
+        FieldType::U64 => any::<u64>().prop_map(FieldValue::UInt).boxed(),
+        FieldType::I32 => any::<i32>().prop_map(|v| FieldValue::Int(v as i64)).boxed(),
+        FieldType::I64 => any::<i64>().prop_map(FieldValue::Int).boxed(),
+        FieldType::Bool => any::<bool>().prop_map(FieldValue::Bool).boxed(),
+        FieldType::String => ".{0,24}".prop_map(FieldValue::String).boxed(),
+        FieldType::Bytes => prop::collection::vec(any::<u8>(), 0..64)
+            .prop_map(FieldValue::Bytes)
+            .boxed(),
+        FieldType::Array(inner) => prop::collection::vec(value_for(inner), 0..6)
+            .prop_map(FieldValue::Array)
+            .boxed(),
+    }
+}
+
+/// A random message schema together with values matching its fields
+fn schema_with_values() -> impl Strategy<Value = (MessageSchema, Vec<FieldValue>)> {
+    prop::collection::vec(field_type(), 0..8).prop_flat_map(|types| {
+        let values: Vec<BoxedStrategy<FieldValue>> = types.iter().map(value_for).collect();
+        let schema = MessageSchema {
+            type_id: 0x100,
+            name: "Generated".to_string(),
+            fields: types
+                .into_iter()
+                .enumerate()
+                .map(|(i, ty)| FieldDef {
+                    name: format!("field_{}", i),
+                    ty,
+                })
+                .collect(),
+        };
+        (Just(schema), values)
+    })
+}
+
+/// Split a wire frame (header + payload) as stored in the corpus
+fn split_frame(data: &[u8]) -> (u16, &[u8]) {
+    if data.len() < FRAME_HEADER_LEN {
+        return (0, data);
+    }
+    (
+        u16::from_le_bytes([data[4], data[5]]),
+        &data[FRAME_HEADER_LEN..],
+    )
+}
+
+fn frame(type_id: u16, payload: &[u8], index: u64) -> Frame {
+    let flow = FlowKey {
+        src_addr: IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)),
+        src_port: 50123,
+        dst_addr: IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)),
+        dst_port: 4724,
+    };
+    Frame {
+        flow,
+        direction: Direction::ClientToServer,
+        timestamp: chrono::Utc::now(),
+        index,
+        type_id,
+        payload: payload.to_vec(),
+    }
+}
+
+#[test]
+fn corpus_decodes_without_panic() {
+    let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+    let mut decoder = Decoder::new(registry).unwrap();
+    for (i, seed) in common::load_corpus("schema").into_iter().enumerate() {
+        let (type_id, payload) = split_frame(&seed);
+        let (_, largest) =
+            largest_allocation(|| decoder.decode(&frame(type_id, payload, i as u64)));
+        assert!(largest <= allocation_bound(seed.len()));
+    }
+}
+
+proptest! {
+    #[test]
+    fn encode_then_decode_round_trips((schema, values) in schema_with_values()) {
+        let version = ClientVersion::new(1, 0, 0, 0);
+        let payload = schema.encode(&values).unwrap();
+        let decoded = schema.decode(&payload, &version).unwrap();
+
+        prop_assert_eq!(decoded.trailing, 0);
+        let decoded_values: Vec<FieldValue> = decoded.fields.iter().map(|f| f.value.clone()).collect();
+        prop_assert_eq!(decoded_values, values);
+
+        // Field ranges tile the payload exactly
+        let mut expected_offset = 0;
+        for field in &decoded.fields {
+            prop_assert_eq!(field.offset, expected_offset);
+            expected_offset += field.len;
+        }
+        prop_assert_eq!(expected_offset, payload.len());
+    }
+
+    #[test]
+    fn trailing_bytes_are_tolerated(
+        (schema, values) in schema_with_values(),
+        extra in prop::collection::vec(any::<u8>(), 1..32),
+    ) {
+        let version = ClientVersion::new(1, 0, 0, 0);
+        let mut payload = schema.encode(&values).unwrap();
+        payload.extend_from_slice(&extra);
+
+        let decoded = schema.decode(&payload, &version).unwrap();
+        prop_assert_eq!(decoded.trailing, extra.len());
+    }
+
+    #[test]
+    fn arbitrary_payloads_decode_without_panic((schema, _) in schema_with_values(), data in seeded_inputs("schema")) {
+        let version = ClientVersion::new(1, 0, 0, 0);
+        let (result, largest) = largest_allocation(|| schema.decode(&data, &version));
+        prop_assert!(largest <= allocation_bound(data.len()));
+
+        // Whatever decodes must re-encode to the bytes it was decoded from
+        // (modulo bool normalization, hence the comparison of decoded values)
+        if let Ok(decoded) = result {
+            let values: Vec<FieldValue> = decoded.fields.iter().map(|f| f.value.clone()).collect();
+            let reencoded = schema.encode(&values).unwrap();
+            prop_assert_eq!(reencoded.len(), data.len() - decoded.trailing);
+            let again = schema.decode(&reencoded, &version).unwrap();
+            prop_assert_eq!(again.fields, decoded.fields);
+        }
+    }
+
+    #[test]
+    fn builtin_schemas_survive_arbitrary_frames(data in seeded_inputs("schema")) {
+        let registry = Arc::new(SchemaRegistry::builtin().unwrap());
+        let (type_id, payload) = split_frame(&data);
+
+        let (_, largest) = largest_allocation(|| {
+            let _ = registry.detect_version(type_id, payload);
+            let mut decoder = Decoder::new(Arc::clone(&registry)).unwrap();
+            let _ = decoder.decode(&frame(type_id, payload, 0));
+        });
+        prop_assert!(largest <= allocation_bound(data.len()));
+    }
+}
@@ -0,0 +1,170 @@
+//! Session archives: undecodable frames are quarantined with their context and
+//! recovered by re-decoding once a schema describes them
+
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::frame;
+use mtgo_replay_lib::protocol::archive::{self, SessionArchive};
+use mtgo_replay_lib::protocol::decoder::Decoder;
+use mtgo_replay_lib::protocol::frame::Frame;
+use mtgo_replay_lib::protocol::schema::{FieldValue, SchemaSet};
+use mtgo_replay_lib::protocol::session::{redecode_session, SessionRecorder};
+use mtgo_replay_lib::protocol::version::SchemaRegistry;
+use std::sync::Arc;
+
+const BASELINE: &str = include_str!("../schemas/baseline.json");
+
+/// Built-in schemas plus a chat message, as a user schema file would add it
+fn with_chat() -> Arc<SchemaRegistry> {
+    let mut json: serde_json::Value = serde_json::from_str(BASELINE).unwrap();
+    json["messages"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({
+            "type_id": 400,
+            "name": "ChatMessage",
+            "fields": [
+                { "name": "sender", "type": "string" },
+                { "name": "text", "type": "string" }
+            ]
+        }));
+    let mut registry = SchemaRegistry::new();
+    registry.insert(SchemaSet::from_json(&json.to_string()).unwrap());
+    Arc::new(registry)
+}
+
+fn text(s: &str) -> FieldValue {
+    FieldValue::String(s.to_string())
+}
+
+fn frames(set: &SchemaSet) -> Vec<Frame> {
+    let mut unknown = frame(4, set, "Priority", vec![FieldValue::UInt(1)]);
+    unknown.type_id = 401;
+    vec![
+        frame(
+            0,
+            set,
+            "GameStarted",
+            vec![FieldValue::UInt(7), FieldValue::UInt(1)],
+        ),
+        frame(
+            1,
+            set,
+            "PlayerJoined",
+            vec![
+                FieldValue::UInt(1),
+                FieldValue::UInt(0),
+                text("alice"),
+                FieldValue::Int(20),
+            ],
+        ),
+        frame(2, set, "ChatMessage", vec![text("bob"), text("gl hf")]),
+        frame(
+            3,
+            set,
+            "LifeTotal",
+            vec![FieldValue::UInt(1), FieldValue::Int(17)],
+        ),
+        // Nothing describes this one yet
+        unknown,
+    ]
+}
+
+#[test]
+fn quarantined_frame_decodes_once_its_schema_is_added() {
+    let sessions = tempfile::tempdir().unwrap();
+    let builtin = Arc::new(SchemaRegistry::builtin().unwrap());
+    let registry = with_chat();
+    let set = registry.latest().unwrap().clone();
+    let started_at = Utc.with_ymd_and_hms(2025, 3, 3, 20, 0, 0).unwrap();
+    let archive =
+        SessionArchive::create(sessions.path(), started_at, set.client_version.clone()).unwrap();
+    let dir = archive.dir().to_path_buf();
+
+    // Captured by a build whose schemas know nothing of chat
+    let mut recorder = SessionRecorder::new(Decoder::new(builtin).unwrap(), archive);
+    let decoded: Vec<_> = frames(&set)
+        .iter()
+        .map(|f| recorder.process(f).map(|m| m.name))
+        .collect();
+    assert_eq!(
+        decoded,
+        [
+            Some("GameStarted".to_string()),
+            Some("PlayerJoined".to_string()),
+            None,
+            Some("LifeTotal".to_string()),
+            None
+        ]
+    );
+    let manifest = recorder.finish().unwrap();
+    assert_eq!((manifest.frame_count, manifest.quarantined_count), (5, 2));
+
+    let quarantined = archive::read_quarantine(&dir).unwrap();
+    assert_eq!(quarantined.len(), 2);
+    let chat = &quarantined[0];
+    assert_eq!(chat.frame, frames(&set)[2]);
+    assert_eq!(chat.reason, "Unknown message type 0x0190");
+    let context: Vec<_> = chat
+        .preceding
+        .iter()
+        .map(|m| (m.index, m.name.as_deref()))
+        .collect();
+    assert_eq!(
+        context,
+        [(0, Some("GameStarted")), (1, Some("PlayerJoined"))]
+    );
+    // Undecodable neighbours are part of the context too, without a name
+    let context: Vec<_> = quarantined[1]
+        .preceding
+        .iter()
+        .map(|m| m.name.as_deref())
+        .collect();
+    assert_eq!(
+        context,
+        [
+            Some("GameStarted"),
+            Some("PlayerJoined"),
+            None,
+            Some("LifeTotal")
+        ]
+    );
+
+    // With the schema added, the chat frame decodes and leaves quarantine
+    let mut names = Vec::new();
+    let report = redecode_session(&dir, registry.clone(), |_, message| {
+        names.push(message.name.clone())
+    })
+    .unwrap();
+    assert_eq!((report.total_frames, report.decoded), (5, 4));
+    assert_eq!((report.quarantined, report.recovered), (1, 1));
+    assert_eq!(report.message_counts.get("ChatMessage"), Some(&1));
+    assert_eq!(
+        names,
+        ["GameStarted", "PlayerJoined", "ChatMessage", "LifeTotal"]
+    );
+
+    let remaining = archive::read_quarantine(&dir).unwrap();
+    assert_eq!(
+        remaining.iter().map(|q| q.frame.index).collect::<Vec<_>>(),
+        [4]
+    );
+    assert_eq!(
+        remaining[0]
+            .preceding
+            .last()
+            .and_then(|m| m.name.as_deref()),
+        Some("LifeTotal")
+    );
+    let manifest = archive::read_manifest(&dir).unwrap();
+    assert_eq!(manifest.quarantined_count, 1);
+    assert!(manifest.last_redecoded_at.is_some());
+
+    // Nothing left to recover on a second pass
+    let again = redecode_session(&dir, registry, |_, _| {}).unwrap();
+    assert_eq!(
+        (again.decoded, again.quarantined, again.recovered),
+        (4, 1, 0)
+    );
+}
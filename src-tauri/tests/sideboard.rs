@@ -0,0 +1,329 @@
+//! Sideboard plans: detection between games, user corrections and keeping them across saves
+
+mod common;
+
+use common::at;
+use mtgo_replay_lib::common::error::GameError;
+use mtgo_replay_lib::game::deck::Deck;
+use mtgo_replay_lib::game::lifecycle::Match;
+use mtgo_replay_lib::game::match_store;
+use mtgo_replay_lib::game::sideboard::{
+    self, CardCount, SideboardChange, SideboardFlag, SideboardPlan,
+};
+
+const BOLT: u32 = 1;
+const GOYF: u32 = 2;
+const LAND: u32 = 3;
+const BLAST: u32 = 10;
+const LURRUS: u32 = 11;
+const WISH: u32 = 12;
+
+fn cards(counts: &[(u32, u32)]) -> Vec<CardCount> {
+    counts
+        .iter()
+        .map(|&(card_id, count)| CardCount { card_id, count })
+        .collect()
+}
+
+fn plan(cards_in: &[(u32, u32)], cards_out: &[(u32, u32)]) -> SideboardPlan {
+    SideboardPlan {
+        cards_in: cards(cards_in),
+        cards_out: cards(cards_out),
+    }
+}
+
+fn game_one_deck() -> Deck {
+    Deck::from_card_lists(
+        &[BOLT, BOLT, BOLT, GOYF, GOYF, LAND],
+        &[BLAST, BLAST, LURRUS],
+        None,
+    )
+}
+
+/// A two-game match, whose sideboarding before game 2 was detected from the decks
+fn two_game_record() -> Match {
+    let next = Deck::from_card_lists(
+        &[BOLT, BOLT, GOYF, GOYF, LAND, BLAST],
+        &[BOLT, BLAST, LURRUS],
+        None,
+    );
+    let sideboard = sideboard::detect(Some(&game_one_deck()), &next, &[]);
+    let game = |number: u8, deck: &Deck, sideboard: Option<&SideboardChange>| {
+        let started = i64::from(number) * 1200;
+        serde_json::json!({
+            "game_id": 7000 + u32::from(number),
+            "number": number,
+            "started_at": at(started),
+            "ended_at": at(started + 900),
+            "on_play": number == 1,
+            "mulligans": 0,
+            "opponent_mulligans": 0,
+            "turns": 8,
+            "result": "win",
+            "end_reason": "concede",
+            "deck": deck,
+            "sideboard": sideboard
+        })
+    };
+    serde_json::from_value(serde_json::json!({
+        "id": 9001,
+        "format": "Modern",
+        "event_type": "league",
+        "event_name": "Modern League",
+        "best_of": 3,
+        "local_player": "alice",
+        "opponent": "bob",
+        "started_at": at(0),
+        "ended_at": null,
+        "games": [game(1, &game_one_deck(), None), game(2, &next, Some(&sideboard))],
+        "result": null,
+        "end_reason": null,
+        "session_id": null
+    }))
+    .unwrap()
+}
+
+/// Decks either side of a sideboarding and what detection should make of them
+struct DetectCase<'a> {
+    name: &'a str,
+    previous: Option<&'a Deck>,
+    next: Deck,
+    outside: Vec<u32>,
+    plan: SideboardPlan,
+    flags: Vec<SideboardFlag>,
+}
+
+/// A correction of game `game_number` and the corrected plan it should leave; None
+/// for a correction that is rejected
+struct CorrectCase<'a> {
+    name: &'a str,
+    game_number: u8,
+    plan: Option<SideboardPlan>,
+    note: Option<&'a str>,
+    corrected: Option<Option<SideboardPlan>>,
+}
+
+#[test]
+fn detection_flags_what_makes_a_plan_suspect() {
+    let previous = game_one_deck();
+    let swap = Deck::from_card_lists(
+        &[BOLT, BOLT, GOYF, GOYF, LAND, BLAST],
+        &[BOLT, BLAST, LURRUS],
+        None,
+    );
+    let mut with_companion = previous.clone();
+    with_companion.companion = Some(LURRUS);
+    let cases = vec![
+        DetectCase {
+            name: "clean swap",
+            previous: Some(&previous),
+            next: swap.clone(),
+            outside: vec![],
+            plan: plan(&[(BLAST, 1)], &[(BOLT, 1)]),
+            flags: vec![],
+        },
+        DetectCase {
+            name: "previous deck not captured",
+            previous: None,
+            next: swap.clone(),
+            outside: vec![],
+            plan: SideboardPlan::default(),
+            flags: vec![SideboardFlag::NoPreviousDeck],
+        },
+        DetectCase {
+            name: "pool changed",
+            previous: Some(&previous),
+            next: Deck::from_card_lists(
+                &[BOLT, BOLT, BOLT, GOYF, GOYF, LAND],
+                &[BLAST, BLAST, WISH],
+                None,
+            ),
+            outside: vec![],
+            plan: SideboardPlan::default(),
+            flags: vec![SideboardFlag::PoolChanged {
+                added: cards(&[(WISH, 1)]),
+                removed: cards(&[(LURRUS, 1)]),
+            }],
+        },
+        DetectCase {
+            name: "maindeck grew",
+            previous: Some(&previous),
+            next: Deck::from_card_lists(
+                &[BOLT, BOLT, BOLT, GOYF, GOYF, LAND, BLAST],
+                &[BLAST, LURRUS],
+                None,
+            ),
+            outside: vec![],
+            plan: plan(&[(BLAST, 1)], &[]),
+            flags: vec![SideboardFlag::MaindeckSizeChanged { from: 6, to: 7 }],
+        },
+        DetectCase {
+            name: "companion dropped",
+            previous: Some(&with_companion),
+            next: previous.clone(),
+            outside: vec![],
+            plan: SideboardPlan::default(),
+            flags: vec![SideboardFlag::CompanionChanged {
+                from: Some(LURRUS),
+                to: None,
+            }],
+        },
+        DetectCase {
+            name: "wished for a sideboard card",
+            previous: Some(&previous),
+            next: previous.clone(),
+            outside: vec![BLAST],
+            plan: SideboardPlan::default(),
+            flags: vec![SideboardFlag::OutsideCardsUsed { cards: vec![BLAST] }],
+        },
+    ];
+
+    for case in cases {
+        let name = case.name;
+        let change = sideboard::detect(case.previous, &case.next, &case.outside);
+        assert_eq!(change.detected, case.plan, "{}", name);
+        assert_eq!(change.flags, case.flags, "{}", name);
+        assert_eq!(change.is_ambiguous(), !case.flags.is_empty(), "{}", name);
+        assert_eq!(change.corrected, None, "{}", name);
+        assert_eq!(change.effective(), &change.detected, "{}", name);
+    }
+}
+
+#[test]
+fn corrections_replace_the_detected_plan() {
+    let cases = vec![
+        CorrectCase {
+            name: "game 1 has no sideboarding",
+            game_number: 1,
+            plan: Some(plan(&[(BLAST, 1)], &[])),
+            note: None,
+            corrected: None,
+        },
+        CorrectCase {
+            name: "game the match does not have",
+            game_number: 3,
+            plan: Some(plan(&[(BLAST, 1)], &[])),
+            note: None,
+            corrected: None,
+        },
+        CorrectCase {
+            name: "entries are merged and sorted",
+            game_number: 2,
+            plan: Some(plan(
+                &[(BLAST, 1), (WISH, 0), (BLAST, 1)],
+                &[(GOYF, 1), (BOLT, 1)],
+            )),
+            note: Some("took out a Goyf too"),
+            corrected: Some(Some(plan(&[(BLAST, 2)], &[(BOLT, 1), (GOYF, 1)]))),
+        },
+        CorrectCase {
+            name: "no plan reverts to the detected one",
+            game_number: 2,
+            plan: None,
+            note: None,
+            corrected: Some(None),
+        },
+    ];
+
+    for case in cases {
+        let name = case.name;
+        let mut record = two_game_record();
+        let result = sideboard::correct(
+            &mut record,
+            case.game_number,
+            case.plan,
+            case.note.map(str::to_string),
+        );
+        let Some(expected) = case.corrected else {
+            assert!(
+                matches!(result, Err(GameError::InvalidCorrection(_))),
+                "{}",
+                name
+            );
+            continue;
+        };
+        result.unwrap();
+        let change = record.games[1].sideboard.as_ref().unwrap();
+        let corrected = change.corrected.as_ref().map(|c| c.plan.clone());
+        assert_eq!(corrected, expected, "{}", name);
+        assert_eq!(
+            change.corrected.as_ref().and_then(|c| c.note.as_deref()),
+            case.note,
+            "{}",
+            name
+        );
+        assert_eq!(
+            change.effective(),
+            expected.as_ref().unwrap_or(&change.detected),
+            "{}",
+            name
+        );
+        assert_eq!(
+            change.detected,
+            plan(&[(BLAST, 1)], &[(BOLT, 1)]),
+            "{}",
+            name
+        );
+    }
+
+    // A game whose decks were not captured can be given a plan by hand; blank notes are dropped
+    let mut record = two_game_record();
+    record.games[1].sideboard = None;
+    sideboard::correct(
+        &mut record,
+        2,
+        Some(plan(&[(BLAST, 1)], &[(BOLT, 1)])),
+        Some("  ".to_string()),
+    )
+    .unwrap();
+    let change = record.games[1].sideboard.as_ref().unwrap();
+    assert_eq!(change.flags, [SideboardFlag::NoPreviousDeck]);
+    assert_eq!(change.effective(), &plan(&[(BLAST, 1)], &[(BOLT, 1)]));
+    assert_eq!(change.corrected.as_ref().unwrap().note, None);
+}
+
+#[test]
+fn corrections_survive_the_tracker_saving_the_match_again() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut corrected = two_game_record();
+    let fix = plan(&[(BLAST, 2)], &[(BOLT, 2)]);
+    sideboard::correct(
+        &mut corrected,
+        2,
+        Some(fix.clone()),
+        Some("sided in both".to_string()),
+    )
+    .unwrap();
+    match_store::save_match(dir.path(), &corrected).unwrap();
+
+    // The tracker's copy, written as the game closes, knows nothing of the correction
+    let mut closed = two_game_record();
+    closed.games[1].turns = 9;
+    let stored = match_store::load_match(dir.path(), &closed.record_id()).unwrap();
+    sideboard::keep_corrections(&mut closed, &stored);
+    match_store::save_match(dir.path(), &closed).unwrap();
+    let saved = match_store::load_match(dir.path(), &closed.record_id()).unwrap();
+    assert_eq!(saved.games[1].turns, 9);
+    let change = saved.games[1].sideboard.as_ref().unwrap();
+    assert_eq!(change.effective(), &fix);
+    assert_eq!(
+        change.corrected,
+        corrected.games[1].sideboard.as_ref().unwrap().corrected
+    );
+
+    // A game the tracker has no sideboarding for takes the stored change whole
+    let mut undetected = two_game_record();
+    undetected.games[1].sideboard = None;
+    sideboard::keep_corrections(&mut undetected, &saved);
+    assert_eq!(undetected.games[1].sideboard, saved.games[1].sideboard);
+
+    // A correction in the newer copy is the user's latest and stays
+    let mut newer = two_game_record();
+    let latest = plan(&[(BLAST, 1)], &[(GOYF, 1)]);
+    sideboard::correct(&mut newer, 2, Some(latest.clone()), None).unwrap();
+    sideboard::keep_corrections(&mut newer, &saved);
+    assert_eq!(
+        newer.games[1].sideboard.as_ref().unwrap().effective(),
+        &latest
+    );
+}
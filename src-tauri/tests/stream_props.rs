@@ -0,0 +1,137 @@
+//! Property tests for the packet -> reassembly -> framing pipeline
+
+mod common;
+
+use common::{
+    allocation_bound, build_packet, largest_allocation, seeded_inputs, shuffled, TrackingAllocator,
+};
+use common::{TCP_ACK, TCP_SYN};
+use mtgo_replay_lib::protocol::frame::{encode_frame, Direction};
+use mtgo_replay_lib::protocol::stream::StreamAssembler;
+use proptest::prelude::*;
+use std::net::{IpAddr, Ipv4Addr};
+
+#[global_allocator]
+static ALLOC: TrackingAllocator = TrackingAllocator;
+
+const CLIENT: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(192, 168, 1, 10)), 50123);
+const SERVER: (IpAddr, u16) = (IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1)), 4724);
+
+/// Split a stream fuzz input into packets (each prefixed by a u16 LE length)
+fn split_packets(data: &[u8]) -> Vec<&[u8]> {
+    let mut packets = Vec::new();
+    let mut rest = data;
+    while rest.len() >= 2 {
+        let len = (u16::from_le_bytes([rest[0], rest[1]]) as usize).min(rest.len() - 2);
+        packets.push(&rest[2..2 + len]);
+        rest = &rest[2 + len..];
+    }
+    packets
+}
+
+#[test]
+fn corpus_streams_without_panic() {
+    for seed in common::load_corpus("stream") {
+        let mut assembler = StreamAssembler::new();
+        let (_, largest) = largest_allocation(|| {
+            for packet in split_packets(&seed) {
+                let _ = assembler.push_packet(packet, chrono::Utc::now());
+            }
+        });
+        assert!(largest <= allocation_bound(seed.len()));
+    }
+}
+
+proptest! {
+    #[test]
+    fn reordered_packets_yield_frames_in_stream_order(
+        frames in prop::collection::vec((any::<u16>(), prop::collection::vec(any::<u8>(), 0..600)), 1..12),
+        mss in 1usize..1400,
+        isn in any::<u32>(),
+        seed in any::<u64>(),
+    ) {
+        let stream: Vec<u8> = frames.iter().flat_map(|(t, p)| encode_frame(*t, p)).collect();
+        let mut segments: Vec<(u32, &[u8])> = stream
+            .chunks(mss)
+            .enumerate()
+            .map(|(i, chunk)| (isn.wrapping_add(1).wrapping_add((i * mss) as u32), chunk))
+            .collect();
+
+        // Deterministic shuffle of everything after the SYN
+        let mut state = seed | 1;
+        for i in (1..segments.len()).rev() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            segments.swap(i, (state % (i as u64 + 1)) as usize);
+        }
+
+        let mut assembler = StreamAssembler::new();
+        let now = chrono::Utc::now();
+        let syn = build_packet(CLIENT, SERVER, isn, TCP_SYN, &[]);
+        prop_assert!(assembler.push_packet(&syn, now).unwrap().is_empty());
+
+        let mut received = Vec::new();
+        for (seq, chunk) in segments {
+            let packet = build_packet(CLIENT, SERVER, seq, TCP_ACK, chunk);
+            received.extend(assembler.push_packet(&packet, now).unwrap());
+        }
+
+        let decoded: Vec<(u16, Vec<u8>)> = received.iter().map(|f| (f.type_id, f.payload.clone())).collect();
+        prop_assert_eq!(decoded, frames);
+        prop_assert!(received.iter().all(|f| f.direction == Direction::ClientToServer));
+        prop_assert!(received.windows(2).all(|w| w[1].index == w[0].index + 1));
+        prop_assert_eq!(assembler.framing_errors(), 0);
+    }
+
+    #[test]
+    fn interleaved_flows_are_kept_apart(
+        client_frames in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..200), 1..6),
+        server_frames in prop::collection::vec(prop::collection::vec(any::<u8>(), 0..200), 1..6),
+        order in shuffled(64),
+    ) {
+        let client: Vec<u8> = client_frames.iter().flat_map(|p| encode_frame(1, p)).collect();
+        let server: Vec<u8> = server_frames.iter().flat_map(|p| encode_frame(2, p)).collect();
+
+        // Packets of both directions interleaved, each direction kept in order
+        let mut assembler = StreamAssembler::new();
+        let now = chrono::Utc::now();
+        let (mut c, mut s) = (client.chunks(32).enumerate(), server.chunks(32).enumerate());
+        let mut received = Vec::new();
+        for pick in order.iter().cycle().take(2 * (client.len() + server.len())) {
+            let next = if pick % 2 == 0 {
+                c.next().map(|(i, chunk)| build_packet(CLIENT, SERVER, (i * 32) as u32, TCP_ACK, chunk))
+            } else {
+                s.next().map(|(i, chunk)| build_packet(SERVER, CLIENT, (i * 32) as u32, TCP_ACK, chunk))
+            };
+            if let Some(packet) = next {
+                received.extend(assembler.push_packet(&packet, now).unwrap());
+            }
+        }
+
+        let from_client: Vec<Vec<u8>> = received
+            .iter()
+            .filter(|f| f.direction == Direction::ClientToServer)
+            .map(|f| f.payload.clone())
+            .collect();
+        let from_server: Vec<Vec<u8>> = received
+            .iter()
+            .filter(|f| f.direction == Direction::ServerToClient)
+            .map(|f| f.payload.clone())
+            .collect();
+        prop_assert_eq!(from_client, client_frames);
+        prop_assert_eq!(from_server, server_frames);
+    }
+
+    #[test]
+    fn arbitrary_packet_sequences_never_panic(data in seeded_inputs("stream")) {
+        let mut assembler = StreamAssembler::new();
+        let now = chrono::Utc::now();
+        let (_, largest) = largest_allocation(|| {
+            for packet in split_packets(&data) {
+                let _ = assembler.push_packet(packet, now);
+            }
+        });
+        prop_assert!(largest <= allocation_bound(data.len()));
+    }
+}